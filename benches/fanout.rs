@@ -0,0 +1,98 @@
+//! Measures how batching the writes of pub/sub messages speeds up fan-out.
+//!
+//! Many subscribers listen on a single channel, to which bursts of messages
+//! are published. Each message is then written to every subscriber. The
+//! server first writes every message to the socket as soon as it is encoded,
+//! then buffers the messages received together and writes them at once.
+//!
+//! Run it with:
+//!
+//!     cargo bench --bench fanout
+
+#![warn(rust_2018_idioms)]
+
+use mini_redis::clients::Client;
+use mini_redis::server;
+use std::time::Instant;
+use tokio::net::TcpListener;
+
+/// Number of connections subscribed to the channel.
+const SUBSCRIBERS: usize = 64;
+
+/// Number of bursts published.
+const ROUNDS: usize = 20;
+
+/// Number of messages in each burst.
+const BURST_LEN: usize = 100;
+
+#[tokio::main]
+async fn main() -> mini_redis::Result<()> {
+    let batched = server::Config::default().write_buffer_size;
+
+    for (name, write_buffer_size) in [("per-frame", 0), ("batched", batched)] {
+        let msgs_per_sec = run(write_buffer_size).await?;
+        println!("{:>9}: {:>10.0} messages/s", name, msgs_per_sec);
+    }
+
+    Ok(())
+}
+
+/// Start a server with a write buffer of `write_buffer_size` bytes, run the
+/// workload against it and return the number of messages received per second,
+/// over all subscribers.
+async fn run(write_buffer_size: usize) -> mini_redis::Result<f64> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    let config = server::Config {
+        write_buffer_size,
+        // Subscribers must not fall behind and miss messages.
+        channel_capacity: ROUNDS * BURST_LEN,
+        slowlog_log_slower_than: None,
+        ..Default::default()
+    };
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(server::run_with_config(listener, stopped, config));
+
+    let mut subscribers = Vec::with_capacity(SUBSCRIBERS);
+    for _ in 0..SUBSCRIBERS {
+        let client = Client::connect(addr).await?;
+        subscribers.push(client.subscribe(vec!["fanout".into()]).await?);
+    }
+
+    let mut publisher = Client::connect(addr).await?;
+
+    let started_at = Instant::now();
+
+    let tasks: Vec<_> = subscribers
+        .into_iter()
+        .map(|mut subscriber| {
+            tokio::spawn(async move {
+                for _ in 0..ROUNDS * BURST_LEN {
+                    subscriber.next_message().await?;
+                }
+
+                mini_redis::Result::Ok(())
+            })
+        })
+        .collect();
+
+    for _ in 0..ROUNDS {
+        let mut pipeline = publisher.pipeline();
+        for i in 0..BURST_LEN {
+            pipeline.publish("fanout", format!("message {}", i).into());
+        }
+        pipeline.execute().await?;
+    }
+
+    for task in tasks {
+        task.await??;
+    }
+
+    let elapsed = started_at.elapsed();
+
+    drop(stop);
+    server.await?;
+
+    Ok((SUBSCRIBERS * ROUNDS * BURST_LEN) as f64 / elapsed.as_secs_f64())
+}
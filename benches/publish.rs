@@ -0,0 +1,86 @@
+//! Measures how publishing without waiting for replies speeds up event
+//! emission.
+//!
+//! A single connection publishes messages to a channel with one subscriber.
+//! The connection first waits for the reply to each `PUBLISH`, then turns into
+//! a `Publisher`, which sends the messages without reading replies.
+//!
+//! Run it with:
+//!
+//!     cargo bench --bench publish
+
+#![warn(rust_2018_idioms)]
+
+use mini_redis::clients::Client;
+use mini_redis::server;
+use std::time::Instant;
+use tokio::net::TcpListener;
+
+/// Number of messages published.
+const MESSAGES: usize = 20_000;
+
+#[tokio::main]
+async fn main() -> mini_redis::Result<()> {
+    for (name, noreply) in [("awaiting", false), ("no-reply", true)] {
+        let msgs_per_sec = run(noreply).await?;
+        println!("{:>8}: {:>10.0} messages/s", name, msgs_per_sec);
+    }
+
+    Ok(())
+}
+
+/// Start a server, publish the messages to it, without reading the replies
+/// if `noreply` is set, and return the number of messages published per
+/// second, once the subscriber received them all.
+async fn run(noreply: bool) -> mini_redis::Result<f64> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    let config = server::Config {
+        // The subscriber must not fall behind and miss messages.
+        channel_capacity: MESSAGES,
+        slowlog_log_slower_than: None,
+        ..Default::default()
+    };
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(server::run_with_config(listener, stopped, config));
+
+    let client = Client::connect(addr).await?;
+    let mut subscriber = client.subscribe(vec!["events".into()]).await?;
+    let mut client = Client::connect(addr).await?;
+
+    let started_at = Instant::now();
+
+    let task = tokio::spawn(async move {
+        for _ in 0..MESSAGES {
+            subscriber.next_message().await?;
+        }
+
+        mini_redis::Result::Ok(())
+    });
+
+    if noreply {
+        let mut publisher = client.publisher().await?;
+        for i in 0..MESSAGES {
+            publisher
+                .publish("events", format!("event {}", i).into())
+                .await?;
+        }
+        publisher.finish().await?;
+    } else {
+        for i in 0..MESSAGES {
+            client
+                .publish("events", format!("event {}", i).into())
+                .await?;
+        }
+    }
+
+    task.await??;
+
+    let elapsed = started_at.elapsed();
+
+    drop(stop);
+    server.await?;
+
+    Ok(MESSAGES as f64 / elapsed.as_secs_f64())
+}
@@ -0,0 +1,95 @@
+//! Measures how sharding the keyspace reduces lock contention.
+//!
+//! Many connections increment counters spread over a thousand keys, first
+//! with the whole database behind a single lock, then with the default number
+//! of shards, one per CPU. Commands are pipelined so that the time is mostly
+//! spent applying them rather than waiting on the network. Sharding only pays
+//! off on a machine with several CPUs.
+//!
+//! Run it with:
+//!
+//!     cargo bench --bench shards
+
+#![warn(rust_2018_idioms)]
+
+use mini_redis::clients::Client;
+use mini_redis::server;
+use std::time::Instant;
+use tokio::net::TcpListener;
+
+/// Number of connections sending commands at once.
+const CONNECTIONS: usize = 64;
+
+/// Number of pipelines each connection sends.
+const ROUNDS: usize = 50;
+
+/// Number of commands in each pipeline.
+const PIPELINE_LEN: usize = 100;
+
+/// Number of distinct keys incremented.
+const KEYS: usize = 1000;
+
+#[tokio::main]
+async fn main() -> mini_redis::Result<()> {
+    let cpus = server::Config::default().shards;
+
+    for shards in [1, cpus] {
+        let ops_per_sec = run(shards).await?;
+        println!("{:>3} shard(s): {:>10.0} commands/s", shards, ops_per_sec);
+    }
+
+    Ok(())
+}
+
+/// Start a server with `shards` shards per database, run the workload against
+/// it and return the throughput.
+async fn run(shards: usize) -> mini_redis::Result<f64> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    let config = server::Config {
+        shards,
+        // The slow log would time every command.
+        slowlog_log_slower_than: None,
+        ..Default::default()
+    };
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(server::run_with_config(listener, stopped, config));
+
+    let mut clients = Vec::with_capacity(CONNECTIONS);
+    for _ in 0..CONNECTIONS {
+        clients.push(Client::connect(addr).await?);
+    }
+
+    let started_at = Instant::now();
+
+    let tasks: Vec<_> = clients
+        .into_iter()
+        .enumerate()
+        .map(|(id, mut client)| {
+            tokio::spawn(async move {
+                for round in 0..ROUNDS {
+                    let mut pipeline = client.pipeline();
+                    for i in 0..PIPELINE_LEN {
+                        let key = format!("counter:{}", (id + round * PIPELINE_LEN + i) % KEYS);
+                        pipeline.incr(&key);
+                    }
+                    pipeline.execute().await?;
+                }
+
+                mini_redis::Result::Ok(())
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await??;
+    }
+
+    let elapsed = started_at.elapsed();
+
+    drop(stop);
+    server.await?;
+
+    Ok((CONNECTIONS * ROUNDS * PIPELINE_LEN) as f64 / elapsed.as_secs_f64())
+}
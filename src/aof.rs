@@ -0,0 +1,139 @@
+//! Append-only file.
+//!
+//! Every write command is appended to the file as the frame it was received
+//! as, preceded by a `SELECT` frame whenever the database changes. Replaying
+//! the file through the regular command handling rebuilds the keyspace, so the
+//! file is read with a `Connection` like any peer.
+//!
+//! Expirations are logged as received. Relative expirations, such as
+//! `EXPIRE key 10`, are therefore counted again from the time the file is
+//! replayed.
+
+use crate::server::AppendFsync;
+use crate::{Connection, Frame};
+
+use bytes::Bytes;
+use std::path::Path;
+use std::sync::{Arc, Weak};
+use tokio::fs::{File, OpenOptions};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::time::{self, Duration};
+use tracing::error;
+
+/// Interval at which the file is flushed with `AppendFsync::EverySec`.
+const FSYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Handle to the append-only file, shared by all connections.
+#[derive(Debug)]
+pub(crate) struct Aof {
+    log: Mutex<Log>,
+}
+
+/// The open append-only file.
+///
+/// Obtained with `Aof::lock`. Connections hold the lock while applying a write
+/// command, so that commands are logged in the order they are applied.
+#[derive(Debug)]
+pub(crate) struct Log {
+    /// Encodes frames into the file. Writes are buffered until flushed.
+    connection: Connection,
+
+    /// Second handle to the file, used to sync it to disk.
+    file: File,
+
+    /// Database selected by the last `SELECT` frame written, if any.
+    db_index: Option<usize>,
+
+    /// When to flush the file.
+    fsync: AppendFsync,
+}
+
+impl Aof {
+    /// Open the append-only file at `path`, creating it if it does not exist.
+    ///
+    /// With `AppendFsync::EverySec`, a background task flushes the file every
+    /// second until the returned handle is dropped.
+    pub(crate) async fn open(path: &Path, fsync: AppendFsync) -> crate::Result<Arc<Aof>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let sync = file.try_clone().await?;
+
+        let aof = Arc::new(Aof {
+            log: Mutex::new(Log {
+                connection: Connection::from_stream(file),
+                file: sync,
+                db_index: None,
+                fsync,
+            }),
+        });
+
+        if fsync == AppendFsync::EverySec {
+            tokio::spawn(sync_periodically(Arc::downgrade(&aof)));
+        }
+
+        Ok(aof)
+    }
+
+    /// Lock the file in order to append to it.
+    pub(crate) async fn lock(&self) -> MutexGuard<'_, Log> {
+        self.log.lock().await
+    }
+
+    /// Write any buffered commands to the file and sync it to disk.
+    pub(crate) async fn sync(&self) -> crate::Result<()> {
+        self.lock().await.sync().await
+    }
+}
+
+impl Log {
+    /// Append `frame`, a write command applied to database `db_index`.
+    ///
+    /// With `AppendFsync::Always`, the file is synced to disk before returning.
+    pub(crate) async fn append(&mut self, db_index: usize, frame: &Frame) -> crate::Result<()> {
+        if self.db_index != Some(db_index) {
+            let mut select = Frame::array();
+            select.push_bulk(Bytes::from("select".as_bytes()));
+            select.push_bulk(Bytes::from(db_index.to_string()));
+
+            self.connection.write_frame_buffered(&select).await?;
+            self.db_index = Some(db_index);
+        }
+
+        self.connection.write_frame_buffered(frame).await?;
+
+        if self.fsync == AppendFsync::Always {
+            self.sync().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn sync(&mut self) -> crate::Result<()> {
+        self.connection.flush().await?;
+        self.file.sync_data().await?;
+        Ok(())
+    }
+}
+
+/// Routine executed by the background task flushing the file.
+///
+/// Runs until the `Aof` is dropped.
+async fn sync_periodically(aof: Weak<Aof>) {
+    let mut interval = time::interval(FSYNC_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let aof = match aof.upgrade() {
+            Some(aof) => aof,
+            None => return,
+        };
+
+        if let Err(err) = aof.sync().await {
+            error!(cause = %err, "failed to sync append-only file");
+        }
+    }
+}
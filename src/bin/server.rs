@@ -6,9 +6,18 @@
 //!
 //! The `clap` crate is used for parsing arguments.
 
-use mini_redis::{server, DEFAULT_PORT};
+use mini_redis::server::{self, AppendFsync, KeyspaceEvents, MaxMemoryPolicy};
+use mini_redis::DEFAULT_PORT;
 
 use clap::Parser;
+use mini_redis::rustls::pki_types::pem::PemObject;
+use mini_redis::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use mini_redis::rustls::ServerConfig;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::signal;
 
@@ -38,7 +47,48 @@ pub async fn main() -> mini_redis::Result<()> {
     // Bind a TCP listener
     let listener = TcpListener::bind(&format!("127.0.0.1:{}", port)).await?;
 
-    server::run(listener, signal::ctrl_c()).await;
+    let config = server::Config {
+        requirepass: cli.requirepass,
+        databases: cli.databases,
+        shards: cli
+            .shards
+            .unwrap_or_else(|| server::Config::default().shards),
+        maxmemory: cli.maxmemory,
+        maxmemory_policy: cli.maxmemory_policy,
+        dbfilename: cli.dbfilename,
+        appendfilename: cli.appendfilename,
+        appendfsync: cli.appendfsync,
+        channel_capacity: cli.channel_capacity,
+        max_frame_size: cli.max_frame_size,
+        max_array_len: cli.max_array_len,
+        write_buffer_size: cli.write_buffer_size,
+        enable_debug_command: cli.enable_debug_command,
+        enable_shutdown_command: cli.enable_shutdown_command,
+        allowed_commands: cli
+            .allowed_commands
+            .map(|names| names.into_iter().collect()),
+        timeout: cli.timeout.map(Duration::from_secs),
+        tcp_nodelay: cli.tcp_nodelay,
+        tcp_keepalive: Some(cli.tcp_keepalive)
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs),
+        shutdown_timeout: cli.shutdown_timeout.map(Duration::from_secs),
+        notify_keyspace_events: cli.notify_keyspace_events,
+        tls: match (cli.tls_cert_file, cli.tls_key_file) {
+            (Some(cert), Some(key)) => Some(tls_config(&cert, &key)?),
+            (None, None) => None,
+            _ => return Err("--tls-cert-file and --tls-key-file must be used together".into()),
+        },
+        slowlog_log_slower_than: u64::try_from(cli.slowlog_log_slower_than)
+            .ok()
+            .map(Duration::from_micros),
+        slowlog_max_len: cli.slowlog_max_len,
+        metrics_addr: cli
+            .metrics_port
+            .map(|port| SocketAddr::from(([127, 0, 0, 1], port))),
+    };
+
+    server::run_with_config(listener, signal::ctrl_c(), config).await;
 
     Ok(())
 }
@@ -48,6 +98,125 @@ pub async fn main() -> mini_redis::Result<()> {
 struct Cli {
     #[clap(long)]
     port: Option<u16>,
+
+    /// Require clients to authenticate with this password
+    #[clap(long)]
+    requirepass: Option<String>,
+
+    /// Number of databases clients can switch between with SELECT
+    #[clap(long, default_value_t = 16)]
+    databases: usize,
+
+    /// Number of shards the keys of each database are spread over, defaults
+    /// to the number of CPUs
+    #[clap(long)]
+    shards: Option<usize>,
+
+    /// Evict keys, as set by maxmemory-policy, once a database uses this many
+    /// bytes
+    #[clap(long)]
+    maxmemory: Option<usize>,
+
+    /// What to do once maxmemory is exceeded: `allkeys-lru`, `allkeys-lfu` or
+    /// `noeviction`
+    #[clap(long, default_value = "allkeys-lru")]
+    maxmemory_policy: MaxMemoryPolicy,
+
+    /// Write snapshots to this file with SAVE, and load it on startup
+    #[clap(long)]
+    dbfilename: Option<PathBuf>,
+
+    /// Log write commands to this file, and replay it on startup
+    #[clap(long)]
+    appendfilename: Option<PathBuf>,
+
+    /// How often to sync the append-only file: `always` or `everysec`
+    #[clap(long, default_value = "everysec")]
+    appendfsync: AppendFsync,
+
+    /// Number of messages pub/sub channels buffer for slow subscribers
+    #[clap(long, default_value_t = 1024)]
+    channel_capacity: usize,
+
+    /// Disconnect clients sending a request larger than this many bytes
+    #[clap(long, default_value_t = 512 * 1024 * 1024)]
+    max_frame_size: usize,
+
+    /// Disconnect clients sending a request with more arguments than this
+    #[clap(long, default_value_t = 1024 * 1024)]
+    max_array_len: usize,
+
+    /// Buffer up to this many bytes of replies before writing to the socket
+    #[clap(long, default_value_t = 8 * 1024)]
+    write_buffer_size: usize,
+
+    /// Accept the DEBUG command, used to test clients
+    #[clap(long)]
+    enable_debug_command: bool,
+
+    /// Accept the SHUTDOWN command, letting any client stop the server
+    #[clap(long)]
+    enable_shutdown_command: bool,
+
+    /// Only accept the commands in this comma-separated list, for example
+    /// `get,ping`, besides AUTH, HELLO and QUIT
+    #[clap(long, value_delimiter = ',')]
+    allowed_commands: Option<Vec<String>>,
+
+    /// Close connections idle for this many seconds
+    #[clap(long)]
+    timeout: Option<u64>,
+
+    /// Set TCP_NODELAY on accepted connections, disabling Nagle's algorithm
+    #[clap(long, default_value_t = true, action = clap::ArgAction::Set)]
+    tcp_nodelay: bool,
+
+    /// Send TCP keepalive probes after connections are idle for this many
+    /// seconds, 0 disables them
+    #[clap(long, default_value_t = 0)]
+    tcp_keepalive: u64,
+
+    /// On shutdown, wait at most this many seconds for connections to finish
+    #[clap(long)]
+    shutdown_timeout: Option<u64>,
+
+    /// Publish changes to the keyspace, as in the Redis notify-keyspace-events
+    /// setting, for example `KEA`
+    #[clap(long, default_value = "")]
+    notify_keyspace_events: KeyspaceEvents,
+
+    /// Accept TLS connections using this PEM encoded certificate chain
+    #[clap(long)]
+    tls_cert_file: Option<PathBuf>,
+
+    /// PEM encoded private key of the TLS certificate
+    #[clap(long)]
+    tls_key_file: Option<PathBuf>,
+
+    /// Record commands taking at least this many microseconds in the slow
+    /// log, a negative value disables it
+    #[clap(long, default_value_t = 10000, allow_hyphen_values = true)]
+    slowlog_log_slower_than: i64,
+
+    /// Number of commands kept in the slow log
+    #[clap(long, default_value_t = 128)]
+    slowlog_max_len: usize,
+
+    /// Serve Prometheus metrics over HTTP on this port
+    #[clap(long)]
+    metrics_port: Option<u16>,
+}
+
+/// Load the TLS certificate chain and private key from PEM files.
+fn tls_config(cert: &Path, key: &Path) -> mini_redis::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert)?.collect::<Result<Vec<_>, _>>()?;
+    let key = PrivateKeyDer::from_pem_file(key)?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(Arc::new(config))
 }
 
 #[cfg(not(feature = "otel"))]
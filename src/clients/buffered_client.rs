@@ -5,11 +5,15 @@ use bytes::Bytes;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::oneshot;
 
+/// Number of commands queued by `BufferedClient::buffer` before callers wait.
+const DEFAULT_CAPACITY: usize = 32;
+
 // Enum used to message pass the requested command from the `BufferedClient` handle
 #[derive(Debug)]
 enum Command {
     Get(String),
     Set(String, Bytes),
+    Close,
 }
 
 // Message type sent over the channel to the connection task.
@@ -24,14 +28,26 @@ type Message = (Command, oneshot::Sender<Result<Option<Bytes>>>);
 /// Receive commands sent through the channel and forward them to client. The
 /// response is returned back to the caller via a `oneshot`.
 async fn run(mut client: Client, mut rx: Receiver<Message>) {
+    // Callers of `BufferedClient::close`, notified once the connection is
+    // closed.
+    let mut closing = vec![];
+
     // Repeatedly pop messages from the channel. A return value of `None`
-    // indicates that all `BufferedClient` handles have dropped and there will never be
-    // another message sent on the channel.
+    // indicates that all `BufferedClient` handles have dropped, or that the
+    // buffer was closed, and there will never be another message sent on the
+    // channel. Messages already queued are received before `None`.
     while let Some((cmd, tx)) = rx.recv().await {
         // The command is forwarded to the connection
         let response = match cmd {
             Command::Get(key) => client.get(&key).await,
             Command::Set(key, value) => client.set(&key, value).await.map(|_| None),
+            Command::Close => {
+                // Stop accepting new commands. Those already queued are still
+                // applied before the loop ends.
+                rx.close();
+                closing.push(tx);
+                continue;
+            }
         };
 
         // Send the response back to the caller.
@@ -40,8 +56,25 @@ async fn run(mut client: Client, mut rx: Receiver<Message>) {
         // before receiving the message. This is a normal runtime event.
         let _ = tx.send(response);
     }
+
+    // Every queued command has been applied, close the connection before
+    // letting the callers of `close` return.
+    drop(client);
+
+    for tx in closing {
+        let _ = tx.send(Ok(None));
+    }
 }
 
+/// A handle to a connection shared by several tasks.
+///
+/// Commands are queued and applied by a dedicated task, one at a time, in the
+/// order they were queued. Commands submitted from one caller are therefore
+/// applied in submission order. Commands submitted concurrently from different
+/// handles are interleaved in an unspecified order.
+///
+/// Dropping every handle closes the connection once the queued commands have
+/// been applied, `close` also waits for that to happen.
 #[derive(Clone)]
 pub struct BufferedClient {
     tx: Sender<Message>,
@@ -65,9 +98,17 @@ impl BufferedClient {
     /// The returned `BufferedClient` handle may be cloned before passing the new handle to
     /// separate tasks.
     pub fn buffer(client: Client) -> BufferedClient {
-        // Setting the message limit to a hard coded value of 32. in a real-app, the
-        // buffer size should be configurable, but we don't need to do that here.
-        let (tx, rx) = channel(32);
+        BufferedClient::with_capacity(client, DEFAULT_CAPACITY)
+    }
+
+    /// Create a new client request buffer holding up to `capacity` queued
+    /// commands, at least one.
+    ///
+    /// Once the buffer is full, callers wait for a queued command to be sent
+    /// before theirs is queued. This applies backpressure to the callers when
+    /// they submit commands faster than the connection can apply them.
+    pub fn with_capacity(client: Client, capacity: usize) -> BufferedClient {
+        let (tx, rx) = channel(capacity.max(1));
 
         // Spawn a task to process requests for the connection.
         tokio::spawn(async move { run(client, rx).await });
@@ -117,4 +158,22 @@ impl BufferedClient {
             Err(err) => Err(err.into()),
         }
     }
+
+    /// Close the buffer, waiting for the commands already queued to be applied
+    /// and for the connection to be closed.
+    ///
+    /// Commands submitted through other handles after the buffer is closed
+    /// fail. Returns an error if the buffer was already closed.
+    pub async fn close(self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        // The request is queued after the pending commands, which are applied
+        // first.
+        self.tx.send((Command::Close, tx)).await?;
+
+        match rx.await {
+            Ok(res) => res.map(|_| ()),
+            Err(err) => Err(err.into()),
+        }
+    }
 }
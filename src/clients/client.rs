@@ -2,14 +2,37 @@
 //!
 //! Provides an async connect and methods for issuing the supported commands.
 
-use crate::cmd::{Get, MultiGet, Ping, Publish, Set, Subscribe, Unsubscribe};
+use crate::cmd::{
+    Append, Auth, BLPop, BRPop, BitCount, BitPos, BitUnit, ClientGetName, ClientKill, ClientList,
+    ClientPause, ClientReply, ClientSetName, ClientUnpause, CommandCount, CommandDocs,
+    CommandTable, ConfigGet, ConfigSet, Copy, DbSize, DebugExpireSweep, DebugObject,
+    DebugSetActiveExpire, DebugSleep, Decr, DecrBy, Del, Dump, Echo, Eval, Exists, Expire,
+    ExpireAt, ExpireCondition, FlushAll, FlushDb, Get, GetBit, GetDel, GetEx, GetExExpiration,
+    GetRange, GetSet, HDel, HExists, HGet, HGetAll, HIncrBy, HIncrByFloat, HKeys, HLen, HRandField,
+    HSet, HVals, Hello, Incr, IncrBy, IncrByFloat, Info, InsertPosition, Keys, LInsert, LLen,
+    LMPop, LPop, LPos, LPush, LPushX, LRange, LSet, ListEnd, MSet, MSetNx, Move, MultiGet,
+    ObjectEncoding, ObjectFreq, ObjectIdleTime, PExpireAt, PSetEx, PauseMode, Persist, Ping, Pttl,
+    PubSubCapacity, PubSubChannels, PubSubNumSub, Publish, Quit, RPop, RPush, RPushX, RandomKey,
+    Rename, RenameNx, ReplyMode, Reset, Restore, SAdd, SCard, SDiffStore, SInterCard, SInterStore,
+    SIsMember, SMembers, SMove, SRandMember, SRem, SUnionStore, Save, Scan, Select, Set, SetBit,
+    SetEx, SetNx, SetRange, Shutdown, SlowLogGet, SlowLogLen, SlowLogReset, StrLen, Subscribe,
+    SwapDb, Touch, Ttl, Type, Unlink, Unsubscribe, Wait, ZAdd, ZRange, ZRank, ZScore,
+};
 use crate::{Connection, Frame};
 
-use async_stream::try_stream;
+use async_stream::stream;
 use bytes::Bytes;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
 use std::io::{Error, ErrorKind};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
 use tokio_stream::Stream;
 use tracing::{debug, instrument};
 
@@ -17,7 +40,8 @@ use tracing::{debug, instrument};
 ///
 /// Backed by a single `TcpStream`, `Client` provides basic network client
 /// functionality (no pooling, retrying, ...). Connections are established using
-/// the [`connect`](fn@connect) function.
+/// the [`connect`](fn@connect) function, or [`connect_tls`](Client::connect_tls)
+/// for TLS connections.
 ///
 /// Requests are issued using the various methods of `Client`.
 pub struct Client {
@@ -29,6 +53,74 @@ pub struct Client {
     /// `Connection` allows the handler to operate at the "frame" level and keep
     /// the byte level protocol parsing details encapsulated in `Connection`.
     connection: Connection,
+
+    /// Maximum time to wait for a response to a command. `None` waits
+    /// forever.
+    timeout: Option<Duration>,
+
+    /// Called with every frame sent and received, if set
+    observer: Option<Observer>,
+
+    /// Number of commands written whose reply was not read yet. Only non-zero
+    /// while a request is in progress, unless the request was cancelled
+    /// before reading its reply, which would then be mistaken for the reply
+    /// of the next command.
+    pending: usize,
+
+    /// Set once a command changed the state of the connection on the server,
+    /// such as the selected database or the protocol, until `RESET`.
+    changed_state: bool,
+}
+
+/// Function called with the frames of a connection, see
+/// [`Client::with_observer`].
+type Observer = Box<dyn Fn(&Frame, Direction) + Send + Sync>;
+
+/// Direction of a frame passed to the observer set with
+/// [`Client::with_observer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// A command sent to the server.
+    Sent,
+
+    /// A reply, or a message, received from the server.
+    Received,
+}
+
+/// Error returned when the server does not respond within the timeout set with
+/// [`Client::set_timeout`].
+///
+/// A timed out command leaves the connection in an undefined state, as the
+/// response may still arrive later and be mistaken for the response of the
+/// next command. The client should be dropped.
+#[derive(Debug)]
+pub struct TimeoutError {
+    /// The timeout that elapsed
+    timeout: Duration,
+}
+
+/// Error replied by the server to a command.
+///
+/// Client methods return it when the server replies with an error frame, such
+/// as `WRONGTYPE` when operating on a key holding the wrong kind of value. It
+/// displays the message exactly as sent by the server.
+#[derive(Debug, Clone)]
+pub struct ServerError {
+    /// The message sent by the server, starting with the kind of the error
+    message: String,
+}
+
+/// Error returned by [`Subscriber::next_message`] when the server dropped
+/// messages published on a channel because the subscriber did not keep up.
+///
+/// The subscription remains active, following messages are still received.
+#[derive(Debug)]
+pub struct LaggedError {
+    /// The channel messages were dropped from
+    channel: String,
+
+    /// Number of messages dropped
+    skipped: u64,
 }
 
 /// A client that has entered pub/sub mode.
@@ -42,6 +134,51 @@ pub struct Subscriber {
 
     /// The set of channels to which the `Subscriber` is currently subscribed.
     subscribed_channels: Vec<String>,
+
+    /// Messages received while waiting for the confirmation of a
+    /// (un)subscription, not yet returned by `next_message`.
+    pending: VecDeque<Frame>,
+}
+
+/// A client whose commands are not replied, to publish messages as fast as
+/// they can be sent.
+///
+/// The server is asked not to reply with `CLIENT REPLY OFF`, so messages are
+/// written without waiting for a reply, and the connection does not fill up
+/// with replies nobody reads. The tradeoff is that nothing is known about the
+/// messages sent: neither how many subscribers received them, nor whether the
+/// server refused them. Messages may also sit in the write buffer until it
+/// fills up or [`flush`](Publisher::flush) is called. The `Client` type is
+/// transitioned to a `Publisher` type, as replies cannot be read until
+/// [`finish`](Publisher::finish) turns them back on.
+pub struct Publisher {
+    /// The client, whose commands are not replied.
+    client: Client,
+}
+
+/// A client that has entered monitor mode.
+///
+/// The connection receives every command the server processes, and only
+/// accepts `RESET` and `QUIT`. The `Client` type is transitioned to a
+/// `Monitor` type in order to prevent other methods from being called.
+pub struct Monitor {
+    /// The monitoring client.
+    client: Client,
+}
+
+/// A batch of commands sent to the server without waiting for each reply.
+///
+/// Commands are queued with the builder style methods, then `execute` writes
+/// them all to the socket at once and reads back the replies in order. This
+/// saves a round trip per command on high latency links.
+///
+/// Created with [`Client::pipeline`].
+pub struct Pipeline<'a> {
+    /// The client the commands are sent on.
+    client: &'a mut Client,
+
+    /// Encoded commands waiting to be sent.
+    frames: Vec<Frame>,
 }
 
 /// A message received on a subscribed channel.
@@ -51,6 +188,30 @@ pub struct Message {
     pub content: Bytes,
 }
 
+/// A command recorded in the slow log of the server, as returned by
+/// `Client::slowlog_get`.
+#[derive(Debug, Clone)]
+pub struct SlowLogEntry {
+    /// Unique identifier of the entry.
+    pub id: u64,
+
+    /// When the command was logged.
+    pub logged_at: SystemTime,
+
+    /// How long the command took to apply.
+    pub duration: Duration,
+
+    /// Name and arguments of the command. The server truncates long
+    /// commands.
+    pub args: Vec<Bytes>,
+
+    /// Address of the peer that sent the command, empty if unknown.
+    pub addr: String,
+
+    /// Name of the connection that sent the command, empty if it had none.
+    pub name: String,
+}
+
 impl Client {
     /// Establish a connection with the Redis server located at `addr`.
     ///
@@ -80,227 +241,2637 @@ impl Client {
         // bubbled up to the caller of `mini_redis` connect.
         let socket = TcpStream::connect(addr).await?;
 
+        // Commands are sent as soon as they are written, rather than delayed
+        // by Nagle's algorithm while waiting for the previous reply.
+        socket.set_nodelay(true)?;
+
         // Initialize the connection state. This allocates read/write buffers to
         // perform redis protocol frame parsing.
         let connection = Connection::new(socket);
 
-        Ok(Client { connection })
+        Ok(Client {
+            connection,
+            timeout: None,
+            observer: None,
+            pending: 0,
+            changed_state: false,
+        })
     }
 
-    /// Ping to the server.
+    /// Establish a TLS connection with the Redis server located at `addr`.
     ///
-    /// Returns PONG if no argument is provided, otherwise
-    /// return a copy of the argument as a bulk.
-    ///
-    /// This command is often used to test if a connection
-    /// is still alive, or to measure latency.
+    /// The server must present a certificate valid for `server_name`, issued
+    /// by one of the certificate authorities in `roots`.
     ///
     /// # Examples
     ///
-    /// Demonstrates basic usage.
     /// ```no_run
     /// use mini_redis::clients::Client;
+    /// use mini_redis::rustls::RootCertStore;
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///     let roots = RootCertStore::empty();
+    ///     // Add the certificate authorities to trust to `roots`
     ///
-    ///     let pong = client.ping(None).await.unwrap();
-    ///     assert_eq!(b"PONG", &pong[..]);
+    ///     let client = match Client::connect_tls("localhost:6379", "localhost", roots).await {
+    ///         Ok(client) => client,
+    ///         Err(_) => panic!("failed to establish connection"),
+    ///     };
+    /// # drop(client);
     /// }
     /// ```
-    #[instrument(skip(self))]
-    pub async fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
-        let frame = Ping::new(msg).into_frame();
-        debug!(request = ?frame);
-        self.connection.write_frame(&frame).await?;
+    pub async fn connect_tls<T: ToSocketAddrs>(
+        addr: T,
+        server_name: &str,
+        roots: RootCertStore,
+    ) -> crate::Result<Client> {
+        let server_name = ServerName::try_from(server_name.to_string())?;
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
 
-        match self.read_response().await? {
-            Frame::Simple(value) => Ok(value.into()),
-            Frame::Bulk(value) => Ok(value),
-            frame => Err(frame.to_error()),
-        }
+        let socket = TcpStream::connect(addr).await?;
+        socket.set_nodelay(true)?;
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(server_name, socket)
+            .await?;
+
+        Ok(Client {
+            connection: Connection::from_stream(stream),
+            timeout: None,
+            observer: None,
+            pending: 0,
+            changed_state: false,
+        })
     }
 
-    /// Get the value of key.
+    /// Wait at most `timeout` for the response to each command.
     ///
-    /// If the key does not exist the special value `None` is returned.
+    /// See [`set_timeout`](Client::set_timeout).
     ///
     /// # Examples
     ///
-    /// Demonstrates basic usage.
-    ///
     /// ```no_run
     /// use mini_redis::clients::Client;
+    /// use std::time::Duration;
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///     let mut client = Client::connect("localhost:6379")
+    ///         .await
+    ///         .unwrap()
+    ///         .with_timeout(Duration::from_secs(1));
     ///
     ///     let val = client.get("foo").await.unwrap();
     ///     println!("Got = {:?}", val);
     /// }
     /// ```
-    #[instrument(skip(self))]
-    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
-        // Create a `Get` command for the `key` and convert it to a frame.
-        let frame = Get::new(key).into_frame();
-
-        debug!(request = ?frame);
-
-        // Write the frame to the socket. This writes the full frame to the
-        // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
-
-        // Wait for the response from the server
-        //
-        // Both `Simple` and `Bulk` frames are accepted. `Null` represents the
-        // key not being present and `None` is returned.
-        match self.read_response().await? {
-            Frame::Simple(value) => Ok(Some(value.into())),
-            Frame::Bulk(value) => Ok(Some(value)),
-            Frame::Null => Ok(None),
-            frame => Err(frame.to_error()),
-        }
+    pub fn with_timeout(mut self, timeout: Duration) -> Client {
+        self.timeout = Some(timeout);
+        self
     }
 
-    #[instrument(skip(self))]
-    pub async fn multiget(&mut self, keys: Vec<String>) -> crate::Result<Vec<Option<Bytes>>> {
-        // Create a `Get` command for the `key` and convert it to a frame.
-        let frame = MultiGet::new(keys).into_frame();
-
-        debug!(request = ?frame);
-
-        // Write the frame to the socket. This writes the full frame to the
-        // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
+    /// Set the maximum time to wait for the response to each command. `None`
+    /// waits forever, which is the default.
+    ///
+    /// When the timeout elapses, the command fails with a [`TimeoutError`].
+    /// The connection is then in an undefined state, since the response may
+    /// still arrive later, and the client should be dropped.
+    ///
+    /// Messages received by a [`Subscriber`] are not subject to the timeout.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
 
-        // Wait for the response from the server
-        //
-        // Both `Simple` and `Bulk` frames are accepted. `Null` represents the
-        // key not being present and `None` is returned.
-        match self.read_response().await? {
-            Frame::Array(values) => {
-                let mapper = |f: Frame| match f {
-                    Frame::Bulk(b) => Some(b),
-                    Frame::Null => None,
-                    _ => {
-                        eprint!("unexpected frame: {}", f);
-                        None
-                    },
-                };
-                Ok(values.into_iter().map(mapper).collect::<Vec<_>>())
-            },
-            frame => Err(frame.to_error()),
-        }
+    /// Returns the maximum time to wait for the response to each command.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
     }
 
-    /// Set `key` to hold the given `value`.
-    ///
-    /// The `value` is associated with `key` until it is overwritten by the next
-    /// call to `set` or it is removed.
+    /// Call `observer` with every frame sent to and received from the server,
+    /// to log or record the traffic of the connection.
     ///
-    /// If key already holds a value, it is overwritten. Any previous time to
-    /// live associated with the key is discarded on successful SET operation.
+    /// Frames sent are observed before being written, and frames received
+    /// before being interpreted, including those received by a [`Subscriber`]
+    /// or a [`Monitor`]. The observer is called on the task issuing the
+    /// commands, so it should be cheap and must not block.
     ///
     /// # Examples
     ///
-    /// Demonstrates basic usage.
-    ///
     /// ```no_run
     /// use mini_redis::clients::Client;
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
-    ///
-    ///     client.set("foo", "bar".into()).await.unwrap();
+    ///     let mut client = Client::connect("localhost:6379")
+    ///         .await
+    ///         .unwrap()
+    ///         .with_observer(|frame, direction| println!("{:?} {}", direction, frame));
     ///
-    ///     // Getting the value immediately works
-    ///     let val = client.get("foo").await.unwrap().unwrap();
-    ///     assert_eq!(val, "bar");
+    ///     client.ping(None).await.unwrap();
     /// }
     /// ```
-    #[instrument(skip(self))]
-    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
-        // Create a `Set` command and pass it to `set_cmd`. A separate method is
-        // used to set a value with an expiration. The common parts of both
-        // functions are implemented by `set_cmd`.
-        self.set_cmd(Set::new(key, value, None)).await
+    pub fn with_observer(
+        mut self,
+        observer: impl Fn(&Frame, Direction) + Send + Sync + 'static,
+    ) -> Client {
+        self.observer = Some(Box::new(observer));
+        self
     }
 
-    /// Set `key` to hold the given `value`. The value expires after `expiration`
+    /// Returns `true` if a command failed in a way that leaves the connection
+    /// unusable, such as an I/O error or a timeout, or was cancelled before
+    /// its reply was read.
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.connection.is_poisoned() || self.pending > 0
+    }
+
+    /// Returns `true` if the state of the connection on the server differs
+    /// from the one of a new connection, see [`reset`](Client::reset).
+    pub(crate) fn changed_state(&self) -> bool {
+        self.changed_state
+    }
+
+    /// Authenticate the connection with `password`.
     ///
-    /// The `value` is associated with `key` until one of the following:
-    /// - it expires.
-    /// - it is overwritten by the next call to `set`.
-    /// - it is removed.
+    /// Required before issuing other commands when the server is started with
+    /// a password. Returns an error if the password does not match.
+    #[instrument(skip(self, password))]
+    pub async fn auth(&mut self, password: &str) -> crate::Result<()> {
+        let frame = Auth::new(password).into_frame();
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Switch the connection to the database numbered `index`.
     ///
-    /// If key already holds a value, it is overwritten. Any previous time to
-    /// live associated with the key is discarded on a successful SET operation.
+    /// Connections start on database `0`. Returns an error if the server does
+    /// not hold a database with that number.
+    #[instrument(skip(self))]
+    pub async fn select(&mut self, index: i64) -> crate::Result<()> {
+        let frame = Select::new(index).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => {
+                self.changed_state = true;
+                Ok(())
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Name the connection `name`, or remove its name if `name` is empty.
     ///
-    /// # Examples
+    /// The server attaches the name to the logs of the connection. Returns an
+    /// error if `name` contains spaces, newlines or special characters.
+    #[instrument(skip(self))]
+    pub async fn client_setname(&mut self, name: &str) -> crate::Result<()> {
+        let frame = ClientSetName::new(name).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => {
+                self.changed_state = true;
+                Ok(())
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the name of the connection, or `None` if it has no name.
+    #[instrument(skip(self))]
+    pub async fn client_getname(&mut self) -> crate::Result<Option<String>> {
+        match self.bulk_cmd(ClientGetName::new().into_frame()).await? {
+            Some(name) => Ok(Some(std::str::from_utf8(&name)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns a description of the connections open on the server, one line
+    /// per connection.
+    #[instrument(skip(self))]
+    pub async fn client_list(&mut self) -> crate::Result<String> {
+        match self.bulk_cmd(ClientList::new().into_frame()).await? {
+            Some(list) => Ok(std::str::from_utf8(&list)?.to_string()),
+            None => Err("protocol error; expected a bulk string".into()),
+        }
+    }
+
+    /// Close the connections with identifier `id` and peer address `addr`,
+    /// when given. Returns the number of connections closed.
     ///
-    /// Demonstrates basic usage. This example is not **guaranteed** to always
-    /// work as it relies on time based logic and assumes the client and server
-    /// stay relatively synchronized in time. The real world tends to not be so
-    /// favorable.
+    /// Identifiers and addresses are reported by `client_list`.
+    #[instrument(skip(self))]
+    pub async fn client_kill(&mut self, id: Option<u64>, addr: Option<&str>) -> crate::Result<u64> {
+        let frame = ClientKill::new(id, addr.map(str::to_string)).into_frame();
+        let killed = self.integer_cmd(frame).await?;
+        Ok(killed as u64)
+    }
+
+    /// Hold the commands of every connection for `timeout`, only those
+    /// writing to the keyspace with `PauseMode::Write`.
     ///
-    /// ```no_run
-    /// use mini_redis::clients::Client;
-    /// use tokio::time;
-    /// use std::time::Duration;
+    /// The commands held are applied once the pause ends, on its own or with
+    /// `client_unpause`.
+    #[instrument(skip(self))]
+    pub async fn client_pause(&mut self, timeout: Duration, mode: PauseMode) -> crate::Result<()> {
+        let frame = ClientPause::new(timeout.as_millis() as u64, mode).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// End the pause started with `client_pause`, if any.
+    #[instrument(skip(self))]
+    pub async fn client_unpause(&mut self) -> crate::Result<()> {
+        let frame = ClientUnpause::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the commands supported by the server, along with their arity.
     ///
-    /// #[tokio::main]
-    /// async fn main() {
-    ///     let ttl = Duration::from_millis(500);
-    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    /// As in Redis, the arity counts the command name itself and a negative
+    /// arity `-n` means the command takes at least `n` arguments.
+    #[instrument(skip(self))]
+    pub async fn command(&mut self) -> crate::Result<Vec<(String, i64)>> {
+        let frame = CommandTable::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let entries = match self.read_response().await? {
+            Frame::Array(entries) => entries,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut commands = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            match entry {
+                Frame::Array(entry) => match &entry[..] {
+                    [Frame::Bulk(name), Frame::Integer(arity), ..] => {
+                        commands.push((std::str::from_utf8(name)?.to_string(), *arity));
+                    }
+                    _ => return Err("protocol error; invalid command entry".into()),
+                },
+                frame => return Err(frame.to_error()),
+            }
+        }
+
+        Ok(commands)
+    }
+
+    /// Returns the number of commands supported by the server.
+    #[instrument(skip(self))]
+    pub async fn command_count(&mut self) -> crate::Result<u64> {
+        let count = self.integer_cmd(CommandCount::new().into_frame()).await?;
+        Ok(count as u64)
+    }
+
+    /// Returns the documentation of the commands in `names`, or of every
+    /// command if it is empty, keyed by command name.
+    #[instrument(skip(self))]
+    pub async fn command_docs(&mut self, names: &[&str]) -> crate::Result<Vec<(String, Frame)>> {
+        let names = names.iter().map(|name| name.to_string()).collect();
+        let frame = CommandDocs::new(names).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        // RESP2 servers reply with a flat array of alternating keys and values
+        let entries = match self.read_response().await? {
+            Frame::Map(entries) => entries,
+            Frame::Array(values) => {
+                let mut entries = Vec::with_capacity(values.len() / 2);
+                let mut values = values.into_iter();
+                while let (Some(key), Some(value)) = (values.next(), values.next()) {
+                    entries.push((key, value));
+                }
+                entries
+            }
+            frame => return Err(frame.to_error()),
+        };
+
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+    }
+
+    /// Returns the runtime parameters whose name matches `pattern`, along with
+    /// their value.
     ///
-    ///     client.set_expires("foo", "bar".into(), ttl).await.unwrap();
+    /// `pattern` is a glob-style pattern, such as `maxmemory*`.
+    #[instrument(skip(self))]
+    pub async fn config_get(&mut self, pattern: &str) -> crate::Result<Vec<(String, String)>> {
+        let frame = ConfigGet::new(pattern).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let values = match self.read_response().await? {
+            Frame::Array(values) => values,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut parameters = Vec::with_capacity(values.len() / 2);
+        let mut values = values.into_iter();
+
+        while let Some(name) = values.next() {
+            match (name, values.next()) {
+                (Frame::Bulk(name), Some(Frame::Bulk(value))) => {
+                    let name = std::str::from_utf8(&name)?.to_string();
+                    let value = std::str::from_utf8(&value)?.to_string();
+                    parameters.push((name, value));
+                }
+                (frame, _) => return Err(frame.to_error()),
+            }
+        }
+
+        Ok(parameters)
+    }
+
+    /// Set the runtime parameter `parameter` to `value`.
+    ///
+    /// The new value applies right away, to every database of the server.
+    #[instrument(skip(self))]
+    pub async fn config_set(&mut self, parameter: &str, value: &str) -> crate::Result<()> {
+        let frame = ConfigSet::new(parameter, value).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove all keys from the selected database.
+    #[instrument(skip(self))]
+    pub async fn flushdb(&mut self) -> crate::Result<()> {
+        let frame = FlushDb::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove all keys from every database of the server.
+    #[instrument(skip(self))]
+    pub async fn flushall(&mut self) -> crate::Result<()> {
+        let frame = FlushAll::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Write a snapshot of every database to disk.
+    ///
+    /// Returns once the snapshot has been written. Fails if the server was not
+    /// configured with a snapshot file.
+    #[instrument(skip(self))]
+    pub async fn save(&mut self) -> crate::Result<()> {
+        let frame = Save::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Negotiate the protocol version with the server.
+    ///
+    /// With `protover` set to `2` or `3` the connection switches to RESP2 or
+    /// RESP3. Without it, the current protocol is kept. Returns the server
+    /// information as a list of field names and values, such as `server`,
+    /// `version` and `proto`.
+    ///
+    /// Under RESP3, pub/sub messages are delivered as push frames. `Subscriber`
+    /// accepts both encodings.
+    #[instrument(skip(self))]
+    pub async fn hello(&mut self, protover: Option<u8>) -> crate::Result<Vec<(String, Frame)>> {
+        let frame = Hello::new(protover.map(u64::from)).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        // RESP2 servers reply with a flat array of alternating keys and values
+        let entries = match self.read_response().await? {
+            Frame::Map(entries) => entries,
+            Frame::Array(values) => {
+                let mut entries = Vec::with_capacity(values.len() / 2);
+                let mut values = values.into_iter();
+                while let (Some(key), Some(value)) = (values.next(), values.next()) {
+                    entries.push((key, value));
+                }
+                entries
+            }
+            frame => return Err(frame.to_error()),
+        };
+
+        if let Some(protover) = protover {
+            self.connection.set_protocol(protover);
+            self.changed_state = true;
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+    }
+
+    /// Start a pipeline of commands on this client.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let replies = client
+    ///         .pipeline()
+    ///         .set("foo", "bar".into())
+    ///         .get("foo")
+    ///         .execute()
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Got = {:?}", replies);
+    /// }
+    /// ```
+    pub fn pipeline(&mut self) -> Pipeline<'_> {
+        Pipeline {
+            client: self,
+            frames: vec![],
+        }
+    }
+
+    /// Ping to the server.
+    ///
+    /// Returns PONG if no argument is provided, otherwise
+    /// return a copy of the argument as a bulk.
+    ///
+    /// This command is often used to test if a connection
+    /// is still alive, or to measure latency.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let pong = client.ping(None).await.unwrap();
+    ///     assert_eq!(b"PONG", &pong[..]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
+        let frame = Ping::new(msg).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(value.into()),
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Return the connection to the state of a new connection.
+    ///
+    /// The server discards any open transaction, selects database `0`,
+    /// removes the name of the connection and switches back to RESP2. If the
+    /// server requires a password, the connection must authenticate again.
+    #[instrument(skip(self))]
+    pub async fn reset(&mut self) -> crate::Result<()> {
+        let frame = Reset::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "RESET" => {
+                self.connection.set_protocol(2);
+                self.changed_state = false;
+                Ok(())
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Ask the server to close the connection.
+    ///
+    /// The client is consumed, the connection is closed once the server has
+    /// confirmed.
+    #[instrument(skip(self))]
+    pub async fn quit(mut self) -> crate::Result<()> {
+        let frame = Quit::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Ask the server to shut down, writing a snapshot first if `save` is set.
+    ///
+    /// The client is consumed. The server closes the connection without
+    /// replying once it is shutting down, an error is returned if it refused.
+    #[instrument(skip(self))]
+    pub async fn shutdown(mut self, save: bool) -> crate::Result<()> {
+        let frame = Shutdown::new(save).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_frame().await? {
+            None => Ok(()),
+            Some(frame) => Err(frame.into_result()?.to_error()),
+        }
+    }
+
+    /// Send a command made of `args`, the name of the command followed by its
+    /// arguments, and return the reply as is.
+    ///
+    /// This is used to send commands that have no dedicated method. Each
+    /// argument is sent as a bulk string, so they need not be valid UTF-8.
+    /// Error replies are returned as `Err`, as by the other methods.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let args = [&b"SET"[..], b"hello", b"world"];
+    ///     let reply = client.raw_command(&args).await.unwrap();
+    ///     println!("{}", reply);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn raw_command(&mut self, args: &[&[u8]]) -> crate::Result<Frame> {
+        let mut frame = Frame::array();
+        for arg in args {
+            frame.push_bulk(Bytes::copy_from_slice(arg));
+        }
+
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Run `script` atomically on the server, with `keys` and `args`, and
+    /// return the value of its last expression.
+    ///
+    /// Scripts are written in the small expression language of the server
+    /// rather than in Lua. For instance, this sets a key only if it holds an
+    /// expected value:
+    ///
+    /// ```text
+    /// if(eq(get(KEYS[1]), ARGV[1]), set(KEYS[1], ARGV[2]))
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn eval(
+        &mut self,
+        script: &str,
+        keys: &[&str],
+        args: Vec<Bytes>,
+    ) -> crate::Result<Frame> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let frame = Eval::new(script, keys, args).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// Send `msg` to the server, which returns it unchanged.
+    #[instrument(skip(self))]
+    pub async fn echo(&mut self, msg: Bytes) -> crate::Result<Bytes> {
+        match self.bulk_cmd(Echo::new(msg).into_frame()).await? {
+            Some(msg) => Ok(msg),
+            None => Err(Frame::Null.to_error()),
+        }
+    }
+
+    /// Returns information and statistics about the server.
+    ///
+    /// The reply is made of `field:value` lines grouped in sections. Only
+    /// `section` is reported if given, otherwise every section is.
+    #[instrument(skip(self))]
+    pub async fn info(&mut self, section: Option<&str>) -> crate::Result<String> {
+        let cmd = Info::new(section.map(|section| section.to_string()));
+
+        match self.bulk_cmd(cmd.into_frame()).await? {
+            Some(info) => Ok(String::from_utf8(info.to_vec())?),
+            None => Err(Frame::Null.to_error()),
+        }
+    }
+
+    /// Returns up to `count` of the most recent entries of the slow log, most
+    /// recent first. The server returns ten entries if `count` is `None`, and
+    /// all of them if it is `-1`.
+    #[instrument(skip(self))]
+    pub async fn slowlog_get(&mut self, count: Option<i64>) -> crate::Result<Vec<SlowLogEntry>> {
+        let frame = SlowLogGet::new(count).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let entries = match self.read_response().await? {
+            Frame::Array(entries) => entries,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut slowlog = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let entry = match entry {
+                Frame::Array(entry) => entry,
+                frame => return Err(frame.to_error()),
+            };
+
+            match &entry[..] {
+                [Frame::Integer(id), Frame::Integer(logged_at), Frame::Integer(duration), Frame::Array(args), Frame::Bulk(addr), Frame::Bulk(name)] =>
+                {
+                    let args = args
+                        .iter()
+                        .map(|arg| match arg {
+                            Frame::Bulk(arg) => Ok(arg.clone()),
+                            frame => Err(frame.to_error()),
+                        })
+                        .collect::<crate::Result<_>>()?;
+
+                    slowlog.push(SlowLogEntry {
+                        id: u64::try_from(*id)?,
+                        logged_at: UNIX_EPOCH + Duration::from_secs(u64::try_from(*logged_at)?),
+                        duration: Duration::from_micros(u64::try_from(*duration)?),
+                        args,
+                        addr: std::str::from_utf8(addr)?.to_string(),
+                        name: std::str::from_utf8(name)?.to_string(),
+                    });
+                }
+                _ => return Err("protocol error; invalid slowlog entry".into()),
+            }
+        }
+
+        Ok(slowlog)
+    }
+
+    /// Returns the number of entries in the slow log.
+    #[instrument(skip(self))]
+    pub async fn slowlog_len(&mut self) -> crate::Result<u64> {
+        let len = self.integer_cmd(SlowLogLen::new().into_frame()).await?;
+        Ok(len as u64)
+    }
+
+    /// Remove all entries from the slow log.
+    #[instrument(skip(self))]
+    pub async fn slowlog_reset(&mut self) -> crate::Result<()> {
+        let frame = SlowLogReset::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Get the value of key.
+    ///
+    /// If the key does not exist the special value `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let val = client.get("foo").await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        // Create a `Get` command for the `key` and convert it to a frame.
+        let frame = Get::new(key).into_frame();
+
+        debug!(request = ?frame);
+
+        // Write the frame to the socket. This writes the full frame to the
+        // socket, waiting if necessary.
+        self.write_frame(&frame).await?;
+
+        // Wait for the response from the server
+        //
+        // Both `Simple` and `Bulk` frames are accepted. `Null` represents the
+        // key not being present and `None` is returned.
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold `value` and return the value it held before.
+    ///
+    /// Returns `None` if the key did not exist. Any expiration associated with
+    /// the key is discarded.
+    #[instrument(skip(self))]
+    pub async fn getset(&mut self, key: &str, value: Bytes) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(GetSet::new(key, value).into_frame()).await
+    }
+
+    /// Get the value of `key` and delete the key.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn getdel(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(GetDel::new(key).into_frame()).await
+    }
+
+    /// Get the value of `key`, changing its expiration as directed by
+    /// `expiration`. The expiration is left untouched if `None`.
+    ///
+    /// Returns `None` if the key does not exist, in which case nothing is
+    /// changed.
+    #[instrument(skip(self))]
+    pub async fn getex(
+        &mut self,
+        key: &str,
+        expiration: Option<GetExExpiration>,
+    ) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(GetEx::new(key, expiration).into_frame())
+            .await
+    }
+
+    /// Append `value` to the string stored at `key`, creating the key if it
+    /// does not exist.
+    ///
+    /// Returns the length of the string after the append.
+    #[instrument(skip(self))]
+    pub async fn append(&mut self, key: &str, value: Bytes) -> crate::Result<u64> {
+        let len = self
+            .integer_cmd(Append::new(key, value).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Returns the length of the string stored at `key`, or `0` if the key
+    /// does not exist.
+    #[instrument(skip(self))]
+    pub async fn strlen(&mut self, key: &str) -> crate::Result<u64> {
+        let len = self.integer_cmd(StrLen::new(key).into_frame()).await?;
+        Ok(len as u64)
+    }
+
+    /// Returns the bytes of the string stored at `key` between `start` and
+    /// `end`, inclusive.
+    ///
+    /// Negative offsets count from the end of the string, `-1` being the last
+    /// byte. An empty value is returned if the range does not overlap the
+    /// string or the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn getrange(&mut self, key: &str, start: i64, end: i64) -> crate::Result<Bytes> {
+        match self
+            .bulk_cmd(GetRange::new(key, start, end).into_frame())
+            .await?
+        {
+            Some(value) => Ok(value),
+            None => Err(Frame::Null.to_error()),
+        }
+    }
+
+    /// Overwrite the string stored at `key` with `value`, starting at byte
+    /// `offset`.
+    ///
+    /// The string is padded with zero bytes if `offset` is past its end.
+    /// Returns the length of the string after the write.
+    #[instrument(skip(self))]
+    pub async fn setrange(&mut self, key: &str, offset: u64, value: Bytes) -> crate::Result<u64> {
+        let offset = i64::try_from(offset)?;
+        let len = self
+            .integer_cmd(SetRange::new(key, offset, value).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Set the bit at `offset` in the string stored at `key` to `value`.
+    ///
+    /// Bit `0` is the most significant bit of the first byte. The string is
+    /// padded with zero bytes if `offset` is past its end. Returns the previous
+    /// value of the bit.
+    #[instrument(skip(self))]
+    pub async fn setbit(&mut self, key: &str, offset: u64, value: bool) -> crate::Result<bool> {
+        let offset = i64::try_from(offset)?;
+        let prev = self
+            .integer_cmd(SetBit::new(key, offset, value).into_frame())
+            .await?;
+        Ok(prev == 1)
+    }
+
+    /// Returns the bit at `offset` in the string stored at `key`.
+    ///
+    /// Bits past the end of the string, or of a missing key, are unset.
+    #[instrument(skip(self))]
+    pub async fn getbit(&mut self, key: &str, offset: u64) -> crate::Result<bool> {
+        let offset = i64::try_from(offset)?;
+        let bit = self
+            .integer_cmd(GetBit::new(key, offset).into_frame())
+            .await?;
+        Ok(bit == 1)
+    }
+
+    /// Count the bits set in the string stored at `key`.
+    ///
+    /// If `range` is given, only the bytes between its `start` and `end`
+    /// offsets, inclusive, are counted. Negative offsets count from the end of
+    /// the string.
+    #[instrument(skip(self))]
+    pub async fn bitcount(&mut self, key: &str, range: Option<(i64, i64)>) -> crate::Result<u64> {
+        let count = self
+            .integer_cmd(BitCount::new(key, range).into_frame())
+            .await?;
+        Ok(count as u64)
+    }
+
+    /// Count the bits set between the `start` and `end` offsets, inclusive, of
+    /// the string stored at `key`, the offsets being in `unit`.
+    #[instrument(skip(self))]
+    pub async fn bitcount_with_unit(
+        &mut self,
+        key: &str,
+        start: i64,
+        end: i64,
+        unit: BitUnit,
+    ) -> crate::Result<u64> {
+        let frame = BitCount::new(key, Some((start, end)))
+            .with_unit(unit)
+            .into_frame();
+        let count = self.integer_cmd(frame).await?;
+        Ok(count as u64)
+    }
+
+    /// Returns the offset, in bits, of the first bit set to `bit` in the string
+    /// stored at `key`, or `-1` if there is none.
+    ///
+    /// If `range` is given, only the offsets between its `start` and its `end`,
+    /// if any, are searched, the offsets being in `unit`. Looking for a clear
+    /// bit without an `end` returns the offset right after the string if all
+    /// its bits are set.
+    #[instrument(skip(self))]
+    pub async fn bitpos(
+        &mut self,
+        key: &str,
+        bit: bool,
+        range: Option<(i64, Option<i64>)>,
+        unit: BitUnit,
+    ) -> crate::Result<i64> {
+        let frame = BitPos::new(key, bit, range).with_unit(unit).into_frame();
+        self.integer_cmd(frame).await
+    }
+
+    /// Set each key of `pairs` to its value.
+    ///
+    /// Existing values are replaced and their expirations removed. All keys
+    /// are set at once.
+    #[instrument(skip(self))]
+    pub async fn mset(&mut self, pairs: Vec<(String, Bytes)>) -> crate::Result<()> {
+        let pairs = pairs
+            .into_iter()
+            .map(|(key, value)| (Bytes::from(key), value))
+            .collect();
+        let frame = MSet::new(pairs).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set each key of `pairs` to its value, only if none of the keys exists.
+    ///
+    /// Returns `true` if the keys were set. If any of them exists, nothing is
+    /// written and `false` is returned.
+    #[instrument(skip(self))]
+    pub async fn mset_nx(&mut self, pairs: Vec<(String, Bytes)>) -> crate::Result<bool> {
+        let pairs = pairs
+            .into_iter()
+            .map(|(key, value)| (Bytes::from(key), value))
+            .collect();
+        let set = self.integer_cmd(MSetNx::new(pairs).into_frame()).await?;
+        Ok(set == 1)
+    }
+
+    /// Get the values of `keys`, in the same order.
+    ///
+    /// `None` is returned for keys that do not exist, or do not hold a string.
+    #[instrument(skip(self))]
+    pub async fn mget(&mut self, keys: &[&str]) -> crate::Result<Vec<Option<Bytes>>> {
+        // `MGET` requires at least one key, there is nothing to ask for.
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let keys: Vec<_> = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let len = keys.len();
+
+        let frame = MultiGet::new(keys).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let values = match self.read_response().await? {
+            Frame::Array(values) if values.len() == len => values,
+            Frame::Array(values) => {
+                let msg = format!(
+                    "protocol error; expected {} values, got {}",
+                    len,
+                    values.len()
+                );
+                return Err(msg.into());
+            }
+            frame => return Err(frame.to_error()),
+        };
+
+        values
+            .into_iter()
+            .map(|value| match value {
+                Frame::Bulk(value) => Ok(Some(value)),
+                Frame::Null => Ok(None),
+                frame => Err(frame.to_error()),
+            })
+            .collect()
+    }
+
+    /// Get the values of `keys`, like `mget`.
+    #[instrument(skip(self))]
+    pub async fn multiget(&mut self, keys: Vec<String>) -> crate::Result<Vec<Option<Bytes>>> {
+        let keys: Vec<_> = keys.iter().map(String::as_str).collect();
+        self.mget(&keys).await
+    }
+
+    /// Set `key` to hold the given `value`.
+    ///
+    /// The `value` is associated with `key` until it is overwritten by the next
+    /// call to `set` or it is removed.
+    ///
+    /// If key already holds a value, it is overwritten. Any previous time to
+    /// live associated with the key is discarded on successful SET operation.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.set("foo", "bar".into()).await.unwrap();
     ///
     ///     // Getting the value immediately works
     ///     let val = client.get("foo").await.unwrap().unwrap();
     ///     assert_eq!(val, "bar");
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        // Create a `Set` command and pass it to `set_cmd`. A separate method is
+        // used to set a value with an expiration. The common parts of both
+        // functions are implemented by `set_cmd`.
+        self.set_cmd(Set::new(key, value, None)).await
+    }
+
+    /// Set `key` to hold the given `value`. The value expires after `expiration`
+    ///
+    /// The `value` is associated with `key` until one of the following:
+    /// - it expires.
+    /// - it is overwritten by the next call to `set`.
+    /// - it is removed.
+    ///
+    /// If key already holds a value, it is overwritten. Any previous time to
+    /// live associated with the key is discarded on a successful SET operation.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage. This example is not **guaranteed** to always
+    /// work as it relies on time based logic and assumes the client and server
+    /// stay relatively synchronized in time. The real world tends to not be so
+    /// favorable.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    /// use tokio::time;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let ttl = Duration::from_millis(500);
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.set_expires("foo", "bar".into(), ttl).await.unwrap();
+    ///
+    ///     // Getting the value immediately works
+    ///     let val = client.get("foo").await.unwrap().unwrap();
+    ///     assert_eq!(val, "bar");
+    ///
+    ///     // Wait for the TTL to expire
+    ///     time::sleep(ttl).await;
+    ///
+    ///     let val = client.get("foo").await.unwrap();
+    ///     assert!(val.is_some());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn set_expires(
+        &mut self,
+        key: &str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> crate::Result<()> {
+        // Create a `Set` command and pass it to `set_cmd`. A separate method is
+        // used to set a value with an expiration. The common parts of both
+        // functions are implemented by `set_cmd`.
+        self.set_cmd(Set::new(key, value, Some(expiration))).await
+    }
+
+    /// Set `key` to hold the given `value`, keeping the time to live of the
+    /// previous value, if any.
+    #[instrument(skip(self))]
+    pub async fn set_keep_ttl(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None).with_keep_ttl())
+            .await
+    }
+
+    /// Set `key` to hold the given `value` and return the value it previously
+    /// held.
+    ///
+    /// Any previous time to live is discarded. Returns `None` if the key did
+    /// not exist, and an error, without setting the value, if the key holds a
+    /// value that is not a string.
+    #[instrument(skip(self))]
+    pub async fn set_get(&mut self, key: &str, value: Bytes) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(Set::new(key, value, None).with_get().into_frame())
+            .await
+    }
+
+    /// Set `key` to hold the given `value` unless `key` already exists.
+    ///
+    /// Returns `true` if the value was set. An existing value is left
+    /// untouched.
+    #[instrument(skip(self))]
+    pub async fn setnx(&mut self, key: &str, value: Bytes) -> crate::Result<bool> {
+        let set = self
+            .integer_cmd(SetNx::new(key, value).into_frame())
+            .await?;
+        Ok(set == 1)
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `seconds`, with
+    /// `SETEX`.
+    ///
+    /// The server rejects a number of seconds that is not positive.
+    #[instrument(skip(self))]
+    pub async fn set_ex(&mut self, key: &str, seconds: i64, value: Bytes) -> crate::Result<()> {
+        let frame = SetEx::new(key, seconds, value).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `key` to hold the given `value`, expiring after `millis`
+    /// milliseconds, with `PSETEX`.
+    ///
+    /// The server rejects a number of milliseconds that is not positive.
+    #[instrument(skip(self))]
+    pub async fn pset_ex(&mut self, key: &str, millis: i64, value: Bytes) -> crate::Result<()> {
+        let frame = PSetEx::new(key, millis, value).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// The core `SET` logic, used by both `set` and `set_expires.
+    async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
+        // Convert the `Set` command into a frame
+        let frame = cmd.into_frame();
+
+        debug!(request = ?frame);
+
+        // Write the frame to the socket. This writes the full frame to the
+        // socket, waiting if necessary.
+        self.write_frame(&frame).await?;
+
+        // Wait for the response from the server. On success, the server
+        // responds simply with `OK`. Any other response indicates an error.
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove the specified `keys`.
+    ///
+    /// Keys that do not exist are ignored. Returns the number of keys that were
+    /// removed.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let removed = client.del(&["foo", "bar"]).await.unwrap();
+    ///     println!("Removed = {:?}", removed);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn del(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let count = self.integer_cmd(Del::new(keys).into_frame()).await?;
+        Ok(count as u64)
+    }
+
+    /// Removes the specified `keys`, like `del`, but lets the server free
+    /// their values in the background.
+    ///
+    /// Returns the number of keys that were removed.
+    #[instrument(skip(self))]
+    pub async fn unlink(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let count = self.integer_cmd(Unlink::new(keys).into_frame()).await?;
+        Ok(count as u64)
+    }
+
+    /// Returns how many of the specified `keys` exist.
+    ///
+    /// A key mentioned multiple times is counted multiple times.
+    #[instrument(skip(self))]
+    pub async fn exists(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let count = self.integer_cmd(Exists::new(keys).into_frame()).await?;
+        Ok(count as u64)
+    }
+
+    /// Mark the specified `keys` as recently used, so that they are the last
+    /// to be evicted, without reading their values.
+    ///
+    /// Returns how many of the `keys` exist.
+    #[instrument(skip(self))]
+    pub async fn touch(&mut self, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let count = self.integer_cmd(Touch::new(keys).into_frame()).await?;
+        Ok(count as u64)
+    }
+
+    /// Returns a random key of the selected database, or `None` if it is
+    /// empty.
+    #[instrument(skip(self))]
+    pub async fn randomkey(&mut self) -> crate::Result<Option<String>> {
+        match self.bulk_cmd(RandomKey::new().into_frame()).await? {
+            Some(key) => Ok(Some(std::str::from_utf8(&key)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the keys matching the glob-style `pattern`, in no particular
+    /// order.
+    ///
+    /// The server visits every key to answer, this is meant for debugging.
+    #[instrument(skip(self))]
+    pub async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        let frame = Keys::new(pattern).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(keys) => keys
+                .into_iter()
+                .map(|key| match key {
+                    Frame::Bulk(key) => Ok(std::str::from_utf8(&key)?.to_string()),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a batch of keys following `cursor`, along with the cursor of the
+    /// next batch.
+    ///
+    /// Start with cursor `0` and call again with the returned cursor until it
+    /// is `0`. Every key present for the whole iteration is returned at least
+    /// once. At most `count` keys are visited per call, and only those
+    /// matching the glob-style `pattern` are returned.
+    #[instrument(skip(self))]
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+    ) -> crate::Result<(u64, Vec<String>)> {
+        let (cursor, keys) = self.scan_cmd(cursor, pattern, count).await?;
+
+        let keys = keys
+            .into_iter()
+            .map(|key| Ok(std::str::from_utf8(&key)?.to_string()))
+            .collect::<crate::Result<_>>()?;
+
+        Ok((cursor, keys))
+    }
+
+    /// Returns a `Stream` yielding every key of the selected database matching
+    /// the glob-style `pattern`, if one is given.
+    ///
+    /// The stream issues `SCAN` commands visiting at most `count` keys each,
+    /// keeping track of the cursor, until the scan is complete. As with
+    /// [`Client::scan`], every key present for the whole iteration is yielded
+    /// at least once. An error ends the stream after being yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let keys = client.scan_iter(Some("user:*"), None);
+    ///     tokio::pin!(keys);
+    ///
+    ///     while let Some(key) = keys.next().await {
+    ///         println!("{:?}", key.unwrap());
+    ///     }
+    /// }
+    /// ```
+    pub fn scan_iter<'a>(
+        &'a mut self,
+        pattern: Option<&'a str>,
+        count: Option<u64>,
+    ) -> impl Stream<Item = crate::Result<Bytes>> + 'a {
+        stream! {
+            let mut cursor = 0;
+
+            loop {
+                match self.scan_cmd(cursor, pattern, count).await {
+                    Ok((next, keys)) => {
+                        for key in keys {
+                            yield Ok(key);
+                        }
+
+                        // The scan is complete once the server returns to the
+                        // first cursor.
+                        if next == 0 {
+                            break;
+                        }
+                        cursor = next;
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The core `SCAN` logic, used by both `scan` and `scan_iter`.
+    async fn scan_cmd(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+    ) -> crate::Result<(u64, Vec<Bytes>)> {
+        let pattern = pattern.map(|pattern| Bytes::copy_from_slice(pattern.as_bytes()));
+        let frame = Scan::new(cursor, pattern, count).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let (cursor, keys) = match self.read_response().await? {
+            Frame::Array(reply) => match <[Frame; 2]>::try_from(reply) {
+                Ok([Frame::Bulk(cursor), Frame::Array(keys)]) => (cursor, keys),
+                _ => return Err("protocol error; invalid SCAN reply".into()),
+            },
+            frame => return Err(frame.to_error()),
+        };
+
+        let cursor = std::str::from_utf8(&cursor)?.parse()?;
+        let keys = keys
+            .into_iter()
+            .map(|key| match key {
+                Frame::Bulk(key) => Ok(key),
+                frame => Err(frame.to_error()),
+            })
+            .collect::<crate::Result<_>>()?;
+
+        Ok((cursor, keys))
+    }
+
+    /// Returns the number of keys in the selected database.
+    #[instrument(skip(self))]
+    pub async fn dbsize(&mut self) -> crate::Result<u64> {
+        let count = self.integer_cmd(DbSize::new().into_frame()).await?;
+        Ok(count as u64)
+    }
+
+    /// Ask the server to wait for `duration` before replying.
+    ///
+    /// The server must be configured to accept the `DEBUG` command.
+    #[instrument(skip(self))]
+    pub async fn debug_sleep(&mut self, duration: Duration) -> crate::Result<()> {
+        let frame = DebugSleep::new(duration).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Pause the purge of expired keys by the server if `enabled` is unset,
+    /// and resume it otherwise.
+    ///
+    /// While paused, expired keys are hidden from reads but only removed when
+    /// written to or swept with [`Client::debug_expire_sweep`]. The server
+    /// must enable `DEBUG`.
+    #[instrument(skip(self))]
+    pub async fn debug_set_active_expire(&mut self, enabled: bool) -> crate::Result<()> {
+        let frame = DebugSetActiveExpire::new(enabled).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Purge every expired key of the server right away.
+    ///
+    /// The server must enable `DEBUG`.
+    #[instrument(skip(self))]
+    pub async fn debug_expire_sweep(&mut self) -> crate::Result<()> {
+        let frame = DebugExpireSweep::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Wait up to `timeout` for `numreplicas` replicas to acknowledge the
+    /// preceding writes. Returns the number of replicas that did.
+    ///
+    /// `mini-redis` has no replicas, so this always returns `0`, once
+    /// `timeout` has elapsed if replicas were requested.
+    #[instrument(skip(self))]
+    pub async fn wait(&mut self, numreplicas: u64, timeout: Duration) -> crate::Result<u64> {
+        let replicas = self
+            .integer_cmd(Wait::new(numreplicas, timeout).into_frame())
+            .await?;
+        Ok(replicas as u64)
+    }
+
+    /// Returns a description of the value stored at `key`, giving its type,
+    /// approximate size and whether the key has an expiration.
+    ///
+    /// The server must be configured to accept the `DEBUG` command.
+    #[instrument(skip(self))]
+    pub async fn debug_object(&mut self, key: &str) -> crate::Result<String> {
+        let frame = DebugObject::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the encoding of the value stored at `key`, such as `int` for a
+    /// string holding an integer.
+    ///
+    /// The key is not marked as used.
+    #[instrument(skip(self))]
+    pub async fn object_encoding(&mut self, key: &str) -> crate::Result<String> {
+        let frame = ObjectEncoding::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(response) => Ok(std::str::from_utf8(&response)?.to_string()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the time elapsed since `key` was last read or written, with a
+    /// resolution of seconds.
+    ///
+    /// The key is not marked as used.
+    #[instrument(skip(self))]
+    pub async fn object_idletime(&mut self, key: &str) -> crate::Result<Duration> {
+        let idle = self
+            .integer_cmd(ObjectIdleTime::new(key).into_frame())
+            .await?;
+        Ok(Duration::from_secs(idle as u64))
+    }
+
+    /// Returns the logarithmic access frequency counter of `key`.
+    ///
+    /// The server must use the `allkeys-lfu` policy. The key is not marked as
+    /// used.
+    #[instrument(skip(self))]
+    pub async fn object_freq(&mut self, key: &str) -> crate::Result<u8> {
+        let frequency = self.integer_cmd(ObjectFreq::new(key).into_frame()).await?;
+        Ok(frequency as u8)
+    }
+
+    /// Set a timeout on `key`. After `expiration` has elapsed, the key is
+    /// removed.
+    ///
+    /// The timeout replaces any expiration previously associated with the key.
+    /// Returns `true` if the timeout was set and `false` if the key does not
+    /// exist. The expiration is sent with a resolution of seconds.
+    #[instrument(skip(self))]
+    pub async fn expire(&mut self, key: &str, expiration: Duration) -> crate::Result<bool> {
+        let frame = Expire::new(key, expiration, None).into_frame();
+        Ok(self.integer_cmd(frame).await? == 1)
+    }
+
+    /// Remove `key` at the wall clock time `when`.
+    ///
+    /// The time replaces any expiration previously associated with the key,
+    /// and a time in the past removes the key right away. Returns `true` if
+    /// the key exists. The time is sent with a resolution of seconds.
+    #[instrument(skip(self))]
+    pub async fn expire_at(&mut self, key: &str, when: SystemTime) -> crate::Result<bool> {
+        let timestamp = when
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs() as i64)
+            .unwrap_or(0);
+        let frame = ExpireAt::new(key, timestamp).into_frame();
+        Ok(self.integer_cmd(frame).await? == 1)
+    }
+
+    /// Remove `key` at the wall clock time `when`, sent with a resolution of
+    /// milliseconds.
+    ///
+    /// See `expire_at`.
+    #[instrument(skip(self))]
+    pub async fn pexpire_at(&mut self, key: &str, when: SystemTime) -> crate::Result<bool> {
+        let timestamp = when
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_millis() as i64)
+            .unwrap_or(0);
+        let frame = PExpireAt::new(key, timestamp).into_frame();
+        Ok(self.integer_cmd(frame).await? == 1)
+    }
+
+    /// Set a timeout on `key` if `condition` is met, such as the key having
+    /// no expiration yet.
+    ///
+    /// Returns `true` if the timeout was set and `false` if the key does not
+    /// exist or the condition is not met. The condition is checked by the
+    /// server as the timeout is set, so concurrent updates cannot interleave.
+    #[instrument(skip(self))]
+    pub async fn expire_if(
+        &mut self,
+        key: &str,
+        expiration: Duration,
+        condition: ExpireCondition,
+    ) -> crate::Result<bool> {
+        let frame = Expire::new(key, expiration, Some(condition)).into_frame();
+        Ok(self.integer_cmd(frame).await? == 1)
+    }
+
+    /// Rename `src` to `dst`, overwriting any value stored at `dst`.
+    ///
+    /// The remaining time to live of `src`, if any, carries over. Returns an
+    /// error if `src` does not exist.
+    #[instrument(skip(self))]
+    pub async fn rename(&mut self, src: &str, dst: &str) -> crate::Result<()> {
+        let frame = Rename::new(src, dst).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Rename `src` to `dst` unless `dst` already exists.
+    ///
+    /// Returns `true` if the key was renamed. Returns an error if `src` does
+    /// not exist.
+    #[instrument(skip(self))]
+    pub async fn renamenx(&mut self, src: &str, dst: &str) -> crate::Result<bool> {
+        Ok(self
+            .integer_cmd(RenameNx::new(src, dst).into_frame())
+            .await?
+            == 1)
+    }
+
+    /// Move `key` from the selected database to database `db`, along with its
+    /// remaining time to live.
+    ///
+    /// Returns `true` if the key was moved and `false` if it does not exist or
+    /// `db` already holds it.
+    #[instrument(skip(self))]
+    pub async fn move_key(&mut self, key: &str, db: i64) -> crate::Result<bool> {
+        let moved = self.integer_cmd(Move::new(key, db).into_frame()).await?;
+        Ok(moved == 1)
+    }
+
+    /// Exchange the contents of databases `index1` and `index2`.
+    ///
+    /// Connections keep the database they selected, and see the keys of the
+    /// other one once swapped.
+    #[instrument(skip(self))]
+    pub async fn swapdb(&mut self, index1: i64, index2: i64) -> crate::Result<()> {
+        let frame = SwapDb::new(index1, index2).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Copy the value stored at `src` to `dst`, along with its remaining time
+    /// to live.
+    ///
+    /// Unless `replace` is set, nothing is copied if `dst` already exists.
+    /// Returns `true` if the key was copied.
+    #[instrument(skip(self))]
+    pub async fn copy(&mut self, src: &str, dst: &str, replace: bool) -> crate::Result<bool> {
+        Ok(self
+            .integer_cmd(Copy::new(src, dst, replace).into_frame())
+            .await?
+            == 1)
+    }
+
+    /// Serialize the value stored at `key`, along with its remaining time to
+    /// live.
+    ///
+    /// The returned blob can be passed to `restore`, on this server or
+    /// another. Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn dump(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(Dump::new(key).into_frame()).await
+    }
+
+    /// Create `key` from `serialized`, a blob returned by `dump`.
+    ///
+    /// The key expires after `ttl`, or keeps the time to live recorded in the
+    /// blob if `ttl` is zero. Unless `replace` is set, an error is returned if
+    /// `key` already exists.
+    #[instrument(skip(self, serialized))]
+    pub async fn restore(
+        &mut self,
+        key: &str,
+        ttl: Duration,
+        serialized: Bytes,
+        replace: bool,
+    ) -> crate::Result<()> {
+        let frame = Restore::new(key, ttl, serialized, replace).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Remove the timeout on `key`.
+    ///
+    /// Returns `true` if a timeout was removed and `false` if the key does not
+    /// exist or has no associated timeout.
+    #[instrument(skip(self))]
+    pub async fn persist(&mut self, key: &str) -> crate::Result<bool> {
+        Ok(self.integer_cmd(Persist::new(key).into_frame()).await? == 1)
+    }
+
+    /// Returns the remaining time to live of `key`, in seconds.
+    ///
+    /// Returns `-2` if the key does not exist and `-1` if the key exists but has
+    /// no associated expiration.
+    #[instrument(skip(self))]
+    pub async fn ttl(&mut self, key: &str) -> crate::Result<i64> {
+        self.integer_cmd(Ttl::new(key).into_frame()).await
+    }
+
+    /// Returns the remaining time to live of `key`, in milliseconds.
+    ///
+    /// Returns `-2` if the key does not exist and `-1` if the key exists but has
+    /// no associated expiration.
+    #[instrument(skip(self))]
+    pub async fn pttl(&mut self, key: &str) -> crate::Result<i64> {
+        self.integer_cmd(Pttl::new(key).into_frame()).await
+    }
+
+    /// Increment the integer stored at `key` by one.
+    ///
+    /// If the key does not exist, it is set to `0` before performing the
+    /// operation. Returns the value of `key` after the increment. An error is
+    /// returned if the key holds a value that is not an integer.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let val = client.incr("counter").await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn incr(&mut self, key: &str) -> crate::Result<i64> {
+        self.integer_cmd(Incr::new(key).into_frame()).await
+    }
+
+    /// Decrement the integer stored at `key` by one.
+    ///
+    /// If the key does not exist, it is set to `0` before performing the
+    /// operation. Returns the value of `key` after the decrement. An error is
+    /// returned if the key holds a value that is not an integer.
+    #[instrument(skip(self))]
+    pub async fn decr(&mut self, key: &str) -> crate::Result<i64> {
+        self.integer_cmd(Decr::new(key).into_frame()).await
+    }
+
+    /// Increment the integer stored at `key` by `increment`.
+    ///
+    /// If the key does not exist, it is set to `0` before performing the
+    /// operation. Returns the value of `key` after the increment. An error is
+    /// returned if the key holds a value that is not an integer, or if the
+    /// result overflows.
+    #[instrument(skip(self))]
+    pub async fn incr_by(&mut self, key: &str, increment: i64) -> crate::Result<i64> {
+        self.integer_cmd(IncrBy::new(key, increment).into_frame())
+            .await
+    }
+
+    /// Decrement the integer stored at `key` by `decrement`.
+    ///
+    /// If the key does not exist, it is set to `0` before performing the
+    /// operation. Returns the value of `key` after the decrement. An error is
+    /// returned if the key holds a value that is not an integer, or if the
+    /// result overflows.
+    #[instrument(skip(self))]
+    pub async fn decr_by(&mut self, key: &str, decrement: i64) -> crate::Result<i64> {
+        self.integer_cmd(DecrBy::new(key, decrement).into_frame())
+            .await
+    }
+
+    /// Increment the floating point number stored at `key` by `increment`.
+    ///
+    /// If the key does not exist, it is set to `0` before performing the
+    /// operation. Returns the value of `key` after the increment. An error is
+    /// returned if the key holds a value that is not a number, or if the
+    /// result is not finite.
+    #[instrument(skip(self))]
+    pub async fn incr_by_float(&mut self, key: &str, increment: f64) -> crate::Result<f64> {
+        match self
+            .bulk_cmd(IncrByFloat::new(key, increment).into_frame())
+            .await?
+        {
+            Some(value) => Ok(std::str::from_utf8(&value)?.parse()?),
+            None => Err(Frame::Null.to_error()),
+        }
+    }
+
+    /// Returns the name of the type of the value stored at `key`.
+    ///
+    /// Returns `"none"` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn value_type(&mut self, key: &str) -> crate::Result<String> {
+        let frame = Type::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Insert `values` at the head of the list stored at `key`.
+    ///
+    /// The list is created if the key does not exist. Returns the length of the
+    /// list after the push.
+    #[instrument(skip(self))]
+    pub async fn lpush(&mut self, key: &str, values: Vec<Bytes>) -> crate::Result<u64> {
+        let len = self
+            .integer_cmd(LPush::new(key, values).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Insert `values` at the tail of the list stored at `key`.
+    ///
+    /// The list is created if the key does not exist. Returns the length of the
+    /// list after the push.
+    #[instrument(skip(self))]
+    pub async fn rpush(&mut self, key: &str, values: Vec<Bytes>) -> crate::Result<u64> {
+        let len = self
+            .integer_cmd(RPush::new(key, values).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Insert `values` at the head of the list stored at `key`, only if the
+    /// key holds a list.
+    ///
+    /// Returns the length of the list after the push, or `0` if the key does
+    /// not exist, in which case it is not created.
+    #[instrument(skip(self))]
+    pub async fn lpushx(&mut self, key: &str, values: Vec<Bytes>) -> crate::Result<u64> {
+        let len = self
+            .integer_cmd(LPushX::new(key, values).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Insert `values` at the tail of the list stored at `key`, only if the
+    /// key holds a list.
+    ///
+    /// Returns the length of the list after the push, or `0` if the key does
+    /// not exist, in which case it is not created.
+    #[instrument(skip(self))]
+    pub async fn rpushx(&mut self, key: &str, values: Vec<Bytes>) -> crate::Result<u64> {
+        let len = self
+            .integer_cmd(RPushX::new(key, values).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Remove and return the first element of the list stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn lpop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(LPop::new(key).into_frame()).await
+    }
+
+    /// Remove and return the last element of the list stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn rpop(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(RPop::new(key).into_frame()).await
+    }
+
+    /// Returns the elements of the list stored at `key` between `start` and
+    /// `stop`, inclusive.
+    ///
+    /// Negative indices count from the end of the list, `-1` being the last
+    /// element.
+    /// Pop the head of the first non-empty list among `keys`, waiting up to
+    /// `timeout` for a value to be pushed if they are all empty.
+    ///
+    /// A zero `timeout` waits indefinitely. Returns the key the value was
+    /// popped from along with the value, or `None` if `timeout` elapsed.
+    #[instrument(skip(self))]
+    pub async fn blpop(
+        &mut self,
+        keys: &[&str],
+        timeout: Duration,
+    ) -> crate::Result<Option<(String, Bytes)>> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        self.blocking_pop_cmd(BLPop::new(keys, timeout).into_frame())
+            .await
+    }
+
+    /// Pop the tail of the first non-empty list among `keys`, waiting up to
+    /// `timeout` for a value to be pushed if they are all empty.
+    ///
+    /// A zero `timeout` waits indefinitely. Returns the key the value was
+    /// popped from along with the value, or `None` if `timeout` elapsed.
+    #[instrument(skip(self))]
+    pub async fn brpop(
+        &mut self,
+        keys: &[&str],
+        timeout: Duration,
+    ) -> crate::Result<Option<(String, Bytes)>> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        self.blocking_pop_cmd(BRPop::new(keys, timeout).into_frame())
+            .await
+    }
+
+    /// Pop up to `count` values from `end` of the first non-empty list among
+    /// `keys`, one if `count` is `None`.
+    ///
+    /// Returns the key the values were popped from along with the values, in
+    /// the order they were popped, or `None` if every list is empty.
+    #[instrument(skip(self))]
+    pub async fn lmpop(
+        &mut self,
+        keys: &[&str],
+        end: ListEnd,
+        count: Option<u64>,
+    ) -> crate::Result<Option<(String, Vec<Bytes>)>> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let frame = LMPop::new(keys, end, count).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let mut reply = match self.read_response().await? {
+            Frame::Array(reply) => reply.into_iter(),
+            Frame::Null => return Ok(None),
+            frame => return Err(frame.to_error()),
+        };
+
+        match (reply.next(), reply.next()) {
+            (Some(Frame::Bulk(key)), Some(Frame::Array(values))) => {
+                let key = std::str::from_utf8(&key)?.to_string();
+                let values = values
+                    .into_iter()
+                    .map(|value| match value {
+                        Frame::Bulk(value) => Ok(value),
+                        frame => Err(frame.to_error()),
+                    })
+                    .collect::<crate::Result<_>>()?;
+                Ok(Some((key, values)))
+            }
+            (frame, _) => Err(frame.unwrap_or(Frame::Null).to_error()),
+        }
+    }
+
+    /// Returns the index of the first element of the list at `key` equal to
+    /// `element`, or `None` if there is none.
+    ///
+    /// A `rank` of `n` returns the `n`th match instead, negative ranks
+    /// searching from the tail of the list.
+    #[instrument(skip(self))]
+    pub async fn lpos(
+        &mut self,
+        key: &str,
+        element: Bytes,
+        rank: Option<i64>,
+    ) -> crate::Result<Option<u64>> {
+        let frame = LPos::new(key, element, rank, None).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(index) => Ok(Some(index as u64)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the indices of up to `count` elements of the list at `key`
+    /// equal to `element`, all of them if `count` is `0`.
+    ///
+    /// Matches before the `rank`th are skipped, negative ranks searching from
+    /// the tail of the list.
+    #[instrument(skip(self))]
+    pub async fn lpos_count(
+        &mut self,
+        key: &str,
+        element: Bytes,
+        rank: Option<i64>,
+        count: u64,
+    ) -> crate::Result<Vec<u64>> {
+        let frame = LPos::new(key, element, rank, Some(count)).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(indices) => indices
+                .into_iter()
+                .map(|index| match index {
+                    Frame::Integer(index) => Ok(index as u64),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn lrange(&mut self, key: &str, start: i64, stop: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = LRange::new(key, start, stop).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(values) => values
+                .into_iter()
+                .map(|value| match value {
+                    Frame::Bulk(value) => Ok(value),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the length of the list at `key`, `0` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn llen(&mut self, key: &str) -> crate::Result<u64> {
+        let frame = LLen::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(len) => Ok(len as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set the element at `index` of the list at `key` to `value`.
+    ///
+    /// Negative indices count from the end of the list. An error is returned
+    /// if the key does not exist or the index is out of range.
+    #[instrument(skip(self))]
+    pub async fn lset(&mut self, key: &str, index: i64, value: Bytes) -> crate::Result<()> {
+        let frame = LSet::new(key, index, value).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Insert `value` at `position` relative to the first element of the list
+    /// at `key` equal to `pivot`.
+    ///
+    /// Returns the length of the list after the insertion, `0` if the key does
+    /// not exist, or `None` if `pivot` is not found.
+    #[instrument(skip(self))]
+    pub async fn linsert(
+        &mut self,
+        key: &str,
+        position: InsertPosition,
+        pivot: Bytes,
+        value: Bytes,
+    ) -> crate::Result<Option<u64>> {
+        let frame = LInsert::new(key, position, pivot, value).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(-1) => Ok(None),
+            Frame::Integer(len) => Ok(Some(len as u64)),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Set `fields` in the hash stored at `key`.
+    ///
+    /// The hash is created if the key does not exist. Returns the number of
+    /// fields that were added, fields that were overwritten are not counted.
+    #[instrument(skip(self))]
+    pub async fn hset(&mut self, key: &str, fields: Vec<(String, Bytes)>) -> crate::Result<u64> {
+        let fields = fields
+            .into_iter()
+            .map(|(field, value)| (Bytes::from(field), value))
+            .collect();
+        let added = self
+            .integer_cmd(HSet::new(key, fields).into_frame())
+            .await?;
+        Ok(added as u64)
+    }
+
+    /// Returns the value of `field` in the hash stored at `key`.
+    ///
+    /// Returns `None` if either the key or the field does not exist.
+    #[instrument(skip(self))]
+    pub async fn hget(&mut self, key: &str, field: &str) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(HGet::new(key, field).into_frame()).await
+    }
+
+    /// Remove `fields` from the hash stored at `key`.
+    ///
+    /// Returns the number of fields that were removed.
+    #[instrument(skip(self))]
+    pub async fn hdel(&mut self, key: &str, fields: &[&str]) -> crate::Result<u64> {
+        let fields = fields
+            .iter()
+            .map(|field| Bytes::copy_from_slice(field.as_bytes()))
+            .collect();
+        let removed = self
+            .integer_cmd(HDel::new(key, fields).into_frame())
+            .await?;
+        Ok(removed as u64)
+    }
+
+    /// Increment the integer stored in `field` of the hash stored at `key` by
+    /// `increment`.
+    ///
+    /// The hash and the field are created if they do not exist, the field
+    /// starting at `0`. Returns the value of the field after the increment. An
+    /// error is returned if the field holds a value that is not an integer.
+    #[instrument(skip(self))]
+    pub async fn hincr_by(&mut self, key: &str, field: &str, increment: i64) -> crate::Result<i64> {
+        self.integer_cmd(HIncrBy::new(key, field, increment).into_frame())
+            .await
+    }
+
+    /// Increment the floating point number stored in `field` of the hash
+    /// stored at `key` by `increment`.
+    ///
+    /// Behaves like [`Client::hincr_by`] otherwise. An error is returned if
+    /// the result is not finite.
+    #[instrument(skip(self))]
+    pub async fn hincr_by_float(
+        &mut self,
+        key: &str,
+        field: &str,
+        increment: f64,
+    ) -> crate::Result<f64> {
+        match self
+            .bulk_cmd(HIncrByFloat::new(key, field, increment).into_frame())
+            .await?
+        {
+            Some(value) => Ok(std::str::from_utf8(&value)?.parse()?),
+            None => Err(Frame::Null.to_error()),
+        }
+    }
+
+    /// Add `members` to the set stored at `key`.
+    ///
+    /// The set is created if the key does not exist. Returns the number of
+    /// members that were added, members already in the set are not counted.
+    #[instrument(skip(self))]
+    pub async fn sadd(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let added = self
+            .integer_cmd(SAdd::new(key, members).into_frame())
+            .await?;
+        Ok(added as u64)
+    }
+
+    /// Remove `members` from the set stored at `key`.
+    ///
+    /// Returns the number of members that were removed.
+    #[instrument(skip(self))]
+    pub async fn srem(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let removed = self
+            .integer_cmd(SRem::new(key, members).into_frame())
+            .await?;
+        Ok(removed as u64)
+    }
+
+    /// Returns all members of the set stored at `key`.
+    ///
+    /// The order of the members is unspecified.
+    #[instrument(skip(self))]
+    pub async fn smembers(&mut self, key: &str) -> crate::Result<Vec<Bytes>> {
+        let frame = SMembers::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(members) => members
+                .into_iter()
+                .map(|member| match member {
+                    Frame::Bulk(member) => Ok(member),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a member of the set stored at `key` picked at random, `None` if
+    /// the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn srandmember(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        self.bulk_cmd(SRandMember::new(key, None).into_frame())
+            .await
+    }
+
+    /// Returns `count` members of the set stored at `key` picked at random.
+    ///
+    /// A positive `count` returns distinct members, all of them if the set has
+    /// fewer. A negative `count` returns exactly `-count` members, the same
+    /// member possibly several times.
+    #[instrument(skip(self))]
+    pub async fn srandmember_count(&mut self, key: &str, count: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = SRandMember::new(key, Some(count)).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(members) => members
+                .into_iter()
+                .map(|member| match member {
+                    Frame::Bulk(member) => Ok(member),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns `true` if `member` is a member of the set stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn sismember(&mut self, key: &str, member: Bytes) -> crate::Result<bool> {
+        let found = self
+            .integer_cmd(SIsMember::new(key, member).into_frame())
+            .await?;
+        Ok(found == 1)
+    }
+
+    /// Move `member` from the set stored at `source` to the set stored at
+    /// `destination`.
+    ///
+    /// Returns `true` if the member was moved, `false` if it was not in the
+    /// source set.
+    #[instrument(skip(self))]
+    pub async fn smove(
+        &mut self,
+        source: &str,
+        destination: &str,
+        member: Bytes,
+    ) -> crate::Result<bool> {
+        let moved = self
+            .integer_cmd(SMove::new(source, destination, member).into_frame())
+            .await?;
+        Ok(moved == 1)
+    }
+
+    /// Returns the number of members of the set stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn scard(&mut self, key: &str) -> crate::Result<u64> {
+        let len = self.integer_cmd(SCard::new(key).into_frame()).await?;
+        Ok(len as u64)
+    }
+
+    /// Returns the number of members of the intersection of the sets stored
+    /// at `keys`, without transferring the members.
+    ///
+    /// At least one key must be given. Counting stops at `limit` members, if
+    /// set to a value other than `0`. A missing key makes the intersection
+    /// empty.
+    #[instrument(skip(self))]
+    pub async fn sintercard(&mut self, keys: &[&str], limit: Option<u64>) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let len = self
+            .integer_cmd(SInterCard::new(keys, limit).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Store the intersection of the sets stored at `keys` in `destination`.
+    ///
+    /// At least one key must be given. Missing keys are treated as empty
+    /// sets, and `destination` is removed if the result is empty. Returns the
+    /// number of members of the result.
+    #[instrument(skip(self))]
+    pub async fn sinterstore(&mut self, destination: &str, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let len = self
+            .integer_cmd(SInterStore::new(destination, keys).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Store the union of the sets stored at `keys` in `destination`.
+    ///
+    /// Behaves like [`Client::sinterstore`] otherwise.
+    #[instrument(skip(self))]
+    pub async fn sunionstore(&mut self, destination: &str, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let len = self
+            .integer_cmd(SUnionStore::new(destination, keys).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Store the members of the first set of `keys` that are in none of the
+    /// other sets in `destination`.
+    ///
+    /// Behaves like [`Client::sinterstore`] otherwise.
+    #[instrument(skip(self))]
+    pub async fn sdiffstore(&mut self, destination: &str, keys: &[&str]) -> crate::Result<u64> {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        let len = self
+            .integer_cmd(SDiffStore::new(destination, keys).into_frame())
+            .await?;
+        Ok(len as u64)
+    }
+
+    /// Returns all fields and values of the hash stored at `key`.
+    ///
+    /// The order of the pairs is unspecified.
+    #[instrument(skip(self))]
+    pub async fn hgetall(&mut self, key: &str) -> crate::Result<Vec<(String, Bytes)>> {
+        let frame = HGetAll::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let values = match self.read_response().await? {
+            Frame::Array(values) => values,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut fields = Vec::with_capacity(values.len() / 2);
+        let mut values = values.into_iter();
+
+        while let Some(field) = values.next() {
+            match (field, values.next()) {
+                (Frame::Bulk(field), Some(Frame::Bulk(value))) => {
+                    let field = std::str::from_utf8(&field)?.to_string();
+                    fields.push((field, value));
+                }
+                (frame, _) => return Err(frame.to_error()),
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Returns `true` if `field` is in the hash stored at `key`.
+    #[instrument(skip(self))]
+    pub async fn hexists(&mut self, key: &str, field: &str) -> crate::Result<bool> {
+        let found = self
+            .integer_cmd(HExists::new(key, field).into_frame())
+            .await?;
+        Ok(found == 1)
+    }
+
+    /// Returns the fields of the hash stored at `key`.
+    ///
+    /// The order of the fields is unspecified.
+    #[instrument(skip(self))]
+    pub async fn hkeys(&mut self, key: &str) -> crate::Result<Vec<String>> {
+        let frame = HKeys::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(fields) => fields
+                .into_iter()
+                .map(|field| match field {
+                    Frame::Bulk(field) => Ok(std::str::from_utf8(&field)?.to_string()),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns a field of the hash stored at `key` picked at random, `None`
+    /// if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn hrandfield(&mut self, key: &str) -> crate::Result<Option<String>> {
+        match self
+            .bulk_cmd(HRandField::new(key, None, false).into_frame())
+            .await?
+        {
+            Some(field) => Ok(Some(std::str::from_utf8(&field)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `count` fields of the hash stored at `key` picked at random.
+    ///
+    /// `count` is interpreted as by [`Client::srandmember_count`].
+    #[instrument(skip(self))]
+    pub async fn hrandfield_count(&mut self, key: &str, count: i64) -> crate::Result<Vec<String>> {
+        Ok(self
+            .hrandfield_with_values(key, count)
+            .await?
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect())
+    }
+
+    /// Returns `count` fields of the hash stored at `key` picked at random,
+    /// along with their values.
+    ///
+    /// Behaves like [`Client::hrandfield_count`] otherwise.
+    #[instrument(skip(self))]
+    pub async fn hrandfield_with_values(
+        &mut self,
+        key: &str,
+        count: i64,
+    ) -> crate::Result<Vec<(String, Bytes)>> {
+        let frame = HRandField::new(key, Some(count), true).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let values = match self.read_response().await? {
+            Frame::Array(values) => values,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut fields = Vec::with_capacity(values.len() / 2);
+        let mut values = values.into_iter();
+
+        while let Some(field) = values.next() {
+            match (field, values.next()) {
+                (Frame::Bulk(field), Some(Frame::Bulk(value))) => {
+                    let field = std::str::from_utf8(&field)?.to_string();
+                    fields.push((field, value));
+                }
+                (frame, _) => return Err(frame.to_error()),
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Returns the values of the hash stored at `key`.
+    ///
+    /// The order of the values is unspecified.
+    #[instrument(skip(self))]
+    pub async fn hvals(&mut self, key: &str) -> crate::Result<Vec<Bytes>> {
+        let frame = HVals::new(key).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(values) => values
+                .into_iter()
+                .map(|value| match value {
+                    Frame::Bulk(value) => Ok(value),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of fields of the hash stored at `key`, `0` if the
+    /// key does not exist.
+    #[instrument(skip(self))]
+    pub async fn hlen(&mut self, key: &str) -> crate::Result<u64> {
+        let len = self.integer_cmd(HLen::new(key).into_frame()).await?;
+        Ok(len as u64)
+    }
+
+    /// Add `members` with their scores to the sorted set stored at `key`.
+    ///
+    /// The sorted set is created if the key does not exist, and the score of
+    /// members already in the set is updated. Returns the number of members
+    /// that were added.
+    #[instrument(skip(self))]
+    pub async fn zadd(&mut self, key: &str, members: Vec<(f64, Bytes)>) -> crate::Result<u64> {
+        let added = self
+            .integer_cmd(ZAdd::new(key, members).into_frame())
+            .await?;
+        Ok(added as u64)
+    }
+
+    /// Returns the score of `member` in the sorted set stored at `key`.
+    ///
+    /// Returns `None` if either the key or the member does not exist.
+    #[instrument(skip(self))]
+    pub async fn zscore(&mut self, key: &str, member: &str) -> crate::Result<Option<f64>> {
+        let frame = ZScore::new(key, member).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        // RESP3 peers receive a double, RESP2 peers a bulk string.
+        match self.read_response().await? {
+            Frame::Double(score) => Ok(Some(score)),
+            Frame::Bulk(score) => Ok(Some(std::str::from_utf8(&score)?.parse()?)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the rank of `member` in the sorted set stored at `key`, `0`
+    /// being the member with the lowest score.
+    ///
+    /// Returns `None` if either the key or the member does not exist.
+    #[instrument(skip(self))]
+    pub async fn zrank(&mut self, key: &str, member: &str) -> crate::Result<Option<u64>> {
+        let frame = ZRank::new(key, member).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(rank) => Ok(Some(rank as u64)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the members of the sorted set stored at `key` ranked between
+    /// `start` and `stop`, inclusive, from the lowest score to the highest.
     ///
-    ///     // Wait for the TTL to expire
-    ///     time::sleep(ttl).await;
+    /// Negative indices count from the member with the highest score.
+    #[instrument(skip(self))]
+    pub async fn zrange(&mut self, key: &str, start: i64, stop: i64) -> crate::Result<Vec<Bytes>> {
+        let frame = ZRange::new(key, start, stop, false).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(members) => members
+                .into_iter()
+                .map(|member| match member {
+                    Frame::Bulk(member) => Ok(member),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the members of the sorted set stored at `key` ranked between
+    /// `start` and `stop` along with their scores.
     ///
-    ///     let val = client.get("foo").await.unwrap();
-    ///     assert!(val.is_some());
-    /// }
-    /// ```
+    /// Behaves like [`Client::zrange`] otherwise.
     #[instrument(skip(self))]
-    pub async fn set_expires(
+    pub async fn zrange_with_scores(
         &mut self,
         key: &str,
-        value: Bytes,
-        expiration: Duration,
-    ) -> crate::Result<()> {
-        // Create a `Set` command and pass it to `set_cmd`. A separate method is
-        // used to set a value with an expiration. The common parts of both
-        // functions are implemented by `set_cmd`.
-        self.set_cmd(Set::new(key, value, Some(expiration))).await
+        start: i64,
+        stop: i64,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let frame = ZRange::new(key, start, stop, true).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        let values = match self.read_response().await? {
+            Frame::Array(values) => values,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut members = Vec::with_capacity(values.len() / 2);
+        let mut values = values.into_iter();
+
+        while let Some(member) = values.next() {
+            match (member, values.next()) {
+                (Frame::Bulk(member), Some(Frame::Bulk(score))) => {
+                    let score = std::str::from_utf8(&score)?.parse()?;
+                    members.push((member, score));
+                }
+                (frame, _) => return Err(frame.to_error()),
+            }
+        }
+
+        Ok(members)
     }
 
-    /// The core `SET` logic, used by both `set` and `set_expires.
-    async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
-        // Convert the `Set` command into a frame
-        let frame = cmd.into_frame();
+    /// Send a command frame for which the server replies with a bulk string or
+    /// `nil`.
+    async fn bulk_cmd(&mut self, frame: Frame) -> crate::Result<Option<Bytes>> {
+        debug!(request = ?frame);
+
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
 
+    /// Send a command frame for which the server replies with an integer.
+    async fn integer_cmd(&mut self, frame: Frame) -> crate::Result<i64> {
         debug!(request = ?frame);
 
-        // Write the frame to the socket. This writes the full frame to the
-        // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
+        // Write the frame to the socket
+        self.write_frame(&frame).await?;
 
-        // Wait for the response from the server. On success, the server
-        // responds simply with `OK`. Any other response indicates an error.
+        // Read the response
         match self.read_response().await? {
-            Frame::Simple(response) if response == "OK" => Ok(()),
+            Frame::Integer(response) => Ok(response),
             frame => Err(frame.to_error()),
         }
     }
 
+    /// Send a `BLPOP` or `BRPOP` frame, whose reply is either the key and the
+    /// popped value, or `nil` on timeout.
+    async fn blocking_pop_cmd(&mut self, frame: Frame) -> crate::Result<Option<(String, Bytes)>> {
+        debug!(request = ?frame);
+
+        self.write_frame(&frame).await?;
+
+        let mut values = match self.read_response().await? {
+            Frame::Array(values) => values.into_iter(),
+            Frame::Null => return Ok(None),
+            frame => return Err(frame.to_error()),
+        };
+
+        match (values.next(), values.next()) {
+            (Some(Frame::Bulk(key)), Some(Frame::Bulk(value))) => {
+                let key = std::str::from_utf8(&key)?.to_string();
+                Ok(Some((key, value)))
+            }
+            (frame, _) => Err(frame.unwrap_or(Frame::Null).to_error()),
+        }
+    }
+
     /// Posts `message` to the given `channel`.
     ///
     /// Returns the number of subscribers currently listening on the channel.
@@ -330,11 +2901,123 @@ impl Client {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         // Read the response
         match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
+            Frame::Integer(response) => Ok(response as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of messages `channel` buffers for its slowest
+    /// subscriber, after setting it to `capacity` if one is given.
+    ///
+    /// A new capacity applies once the channel is created again, after its
+    /// current subscribers all left.
+    #[instrument(skip(self))]
+    pub async fn pubsub_capacity(
+        &mut self,
+        channel: &str,
+        capacity: Option<usize>,
+    ) -> crate::Result<u64> {
+        let frame = PubSubCapacity::new(channel, capacity).into_frame();
+        Ok(self.integer_cmd(frame).await? as u64)
+    }
+
+    /// Returns the channels with at least one subscriber, matching the
+    /// glob-style `pattern` if one is given.
+    #[instrument(skip(self))]
+    pub async fn pubsub_channels(&mut self, pattern: Option<&str>) -> crate::Result<Vec<String>> {
+        let frame = PubSubChannels::new(pattern).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(channels) => channels
+                .into_iter()
+                .map(|channel| match channel {
+                    Frame::Bulk(channel) => Ok(std::str::from_utf8(&channel)?.to_string()),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the number of subscribers to each of `channels`, in order.
+    #[instrument(skip(self))]
+    pub async fn pubsub_numsub(&mut self, channels: &[&str]) -> crate::Result<Vec<(String, u64)>> {
+        let frame = PubSubNumSub::new(channels).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(counts) => counts
+                .chunks(2)
+                .map(|pair| match pair {
+                    [Frame::Bulk(channel), Frame::Integer(count)] => {
+                        Ok((std::str::from_utf8(channel)?.to_string(), *count as u64))
+                    }
+                    _ => Err("protocol error; invalid pubsub numsub reply".into()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Stop waiting for replies, to publish messages without waiting for the
+    /// server to handle each of them.
+    ///
+    /// The function consumes `self` and returns a [`Publisher`], which sends
+    /// messages without reading replies. Use it for high-throughput event
+    /// emission, when the number of subscribers receiving each message does
+    /// not matter. On a connection in this mode, errors such as a full server
+    /// go unnoticed, see [`Publisher`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let client = Client::connect("localhost:6379").await.unwrap();
+    ///     let mut publisher = client.publisher().await.unwrap();
+    ///
+    ///     for i in 0..1000 {
+    ///         publisher.publish("events", format!("event {}", i).into()).await.unwrap();
+    ///     }
+    ///
+    ///     // Wait for the server to handle every message.
+    ///     let client = publisher.finish().await.unwrap();
+    /// # drop(client);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn publisher(mut self) -> crate::Result<Publisher> {
+        // No reply is sent to this command either.
+        let frame = ClientReply::new(ReplyMode::Off).into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+        self.pending -= 1;
+
+        Ok(Publisher { client: self })
+    }
+
+    /// Stream every command processed by the server to the client, as
+    /// requested with `MONITOR`.
+    ///
+    /// The function consumes `self` and returns a `Monitor`, which receives
+    /// the commands until it is reset.
+    #[instrument(skip(self))]
+    pub async fn monitor(mut self) -> crate::Result<Monitor> {
+        let frame = crate::cmd::Monitor::new().into_frame();
+        debug!(request = ?frame);
+        self.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(Monitor { client: self }),
             frame => Err(frame.to_error()),
         }
     }
@@ -351,34 +3034,43 @@ impl Client {
         // Issue the subscribe command to the server and wait for confirmation.
         // The client will then have been transitioned into the "subscriber"
         // state and may only issue pub/sub commands from that point on.
-        self.subscribe_cmd(&channels).await?;
+        let mut pending = VecDeque::new();
+        self.subscribe_cmd(&channels, &mut pending).await?;
 
         // Return the `Subscriber` type
         Ok(Subscriber {
             client: self,
             subscribed_channels: channels,
+            pending,
         })
     }
 
     /// The core `SUBSCRIBE` logic, used by misc subscribe fns
-    async fn subscribe_cmd(&mut self, channels: &[String]) -> crate::Result<()> {
+    ///
+    /// Messages published on channels already subscribed to may arrive before
+    /// all the confirmations. They are pushed to `pending`.
+    async fn subscribe_cmd(
+        &mut self,
+        channels: &[String],
+        pending: &mut VecDeque<Frame>,
+    ) -> crate::Result<()> {
         // Convert the `Subscribe` command into a frame
         let frame = Subscribe::new(channels.to_vec()).into_frame();
 
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         // For each channel being subscribed to, the server responds with a
         // message confirming subscription to that channel.
         for channel in channels {
             // Read the response
-            let response = self.read_response().await?;
+            let response = self.read_confirmation(pending).await?;
 
             // Verify it is confirmation of subscription.
             match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
+                Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
                     // The server responds with an array frame in the form of:
                     //
                     // ```
@@ -399,18 +3091,33 @@ impl Client {
         Ok(())
     }
 
+    /// Reads the confirmation of a (un)subscription from the socket.
+    ///
+    /// Messages received first are pushed to `pending`, to be returned later
+    /// by `Subscriber::next_message`.
+    async fn read_confirmation(&mut self, pending: &mut VecDeque<Frame>) -> crate::Result<Frame> {
+        loop {
+            let response = self.read_response().await?;
+
+            if is_message(&response) {
+                pending.push_back(response);
+            } else {
+                return Ok(response);
+            }
+        }
+    }
+
     /// Reads a response frame from the socket.
     ///
     /// If an `Error` frame is received, it is converted to `Err`.
     async fn read_response(&mut self) -> crate::Result<Frame> {
-        let response = self.connection.read_frame().await?;
+        let response = self.read_frame().await?;
 
         debug!(?response);
 
         match response {
             // Error frames are converted to `Err`
-            Some(Frame::Error(msg)) => Err(msg.into()),
-            Some(frame) => Ok(frame),
+            Some(frame) => frame.into_result(),
             None => {
                 // Receiving `None` here indicates the server has closed the
                 // connection without sending a frame. This is unexpected and is
@@ -421,6 +3128,298 @@ impl Client {
             }
         }
     }
+
+    /// Reads a frame from the socket, waiting at most for the configured
+    /// timeout.
+    async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
+        match self.timeout {
+            Some(timeout) => match time::timeout(timeout, self.read_frame_untimed()).await {
+                Ok(res) => res,
+                Err(_) => {
+                    // The reply may still arrive and be mistaken for the reply
+                    // to the next command.
+                    self.connection.poison();
+                    Err(TimeoutError { timeout }.into())
+                }
+            },
+            None => self.read_frame_untimed().await,
+        }
+    }
+
+    /// Reads a frame from the socket, waiting as long as necessary.
+    async fn read_frame_untimed(&mut self) -> crate::Result<Option<Frame>> {
+        let frame = self.connection.read_frame().await?;
+        if let Some(frame) = &frame {
+            self.observe(frame, Direction::Received);
+            // Messages received by subscribers and monitors reply to nothing.
+            self.pending = self.pending.saturating_sub(1);
+        }
+        Ok(frame)
+    }
+
+    /// Writes a frame to the socket, which the server replies to.
+    async fn write_frame(&mut self, frame: &Frame) -> std::io::Result<()> {
+        self.observe(frame, Direction::Sent);
+        self.pending += 1;
+        self.connection.write_frame(frame).await
+    }
+
+    /// Passes a frame to the observer, if any.
+    fn observe(&self, frame: &Frame, direction: Direction) {
+        if let Some(observer) = &self.observer {
+            observer(frame, direction);
+        }
+    }
+}
+
+impl TimeoutError {
+    /// Returns the timeout that elapsed.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "timed out waiting for a response after {:?}",
+            self.timeout
+        )
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+impl ServerError {
+    /// Create a new `ServerError` carrying `message`.
+    pub(crate) fn new(message: String) -> ServerError {
+        ServerError { message }
+    }
+
+    /// Returns the message sent by the server.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the kind of the error, the first word of the message, such as
+    /// `ERR` or `WRONGTYPE`.
+    pub fn kind(&self) -> &str {
+        self.message.split(' ').next().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(fmt)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl LaggedError {
+    /// Returns the channel messages were dropped from.
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Returns the number of messages dropped.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl fmt::Display for LaggedError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "subscriber lagged behind, {} messages dropped from channel '{}'",
+            self.skipped, self.channel
+        )
+    }
+}
+
+impl std::error::Error for LaggedError {}
+
+impl Pipeline<'_> {
+    /// Queue a `GET` of `key`.
+    pub fn get(&mut self, key: &str) -> &mut Self {
+        self.push(Get::new(key).into_frame())
+    }
+
+    /// Queue a `SET` of `key` to `value`.
+    pub fn set(&mut self, key: &str, value: Bytes) -> &mut Self {
+        self.push(Set::new(key, value, None).into_frame())
+    }
+
+    /// Queue a `SET` of `key` to `value`, expiring after `expiration`.
+    pub fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> &mut Self {
+        self.push(Set::new(key, value, Some(expiration)).into_frame())
+    }
+
+    /// Queue a `DEL` of `keys`.
+    pub fn del(&mut self, keys: &[&str]) -> &mut Self {
+        let keys = keys
+            .iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect();
+        self.push(Del::new(keys).into_frame())
+    }
+
+    /// Queue an `INCR` of `key`.
+    pub fn incr(&mut self, key: &str) -> &mut Self {
+        self.push(Incr::new(key).into_frame())
+    }
+
+    /// Queue a `DECR` of `key`.
+    pub fn decr(&mut self, key: &str) -> &mut Self {
+        self.push(Decr::new(key).into_frame())
+    }
+
+    /// Queue a `PING` with an optional `msg`.
+    pub fn ping(&mut self, msg: Option<Bytes>) -> &mut Self {
+        self.push(Ping::new(msg).into_frame())
+    }
+
+    /// Queue a `PUBLISH` of `message` to `channel`.
+    pub fn publish(&mut self, channel: &str, message: Bytes) -> &mut Self {
+        self.push(Publish::new(channel, message).into_frame())
+    }
+
+    /// Returns the number of queued commands.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if no commands are queued.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn push(&mut self, frame: Frame) -> &mut Self {
+        self.frames.push(frame);
+        self
+    }
+
+    /// Send all queued commands and read their replies.
+    ///
+    /// The replies are returned in the order the commands were queued. Error
+    /// replies from the server are returned as `Err` entries without affecting
+    /// the other replies. The outer `Err` is only returned if the connection
+    /// fails, in which case the state of the connection is undefined.
+    #[instrument(skip(self))]
+    pub async fn execute(&mut self) -> crate::Result<Vec<crate::Result<Frame>>> {
+        let frames = std::mem::take(&mut self.frames);
+
+        // Encode every command before flushing, so the batch is sent with as
+        // few writes as possible.
+        for frame in &frames {
+            debug!(request = ?frame);
+            self.client.observe(frame, Direction::Sent);
+            self.client.pending += 1;
+            self.client.connection.write_frame_buffered(frame).await?;
+        }
+        self.client.connection.flush().await?;
+
+        let mut replies = Vec::with_capacity(frames.len());
+
+        for _ in 0..frames.len() {
+            let reply = match self.client.read_frame().await? {
+                Some(frame) => frame.into_result(),
+                None => {
+                    let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+                    return Err(err.into());
+                }
+            };
+
+            debug!(?reply);
+            replies.push(reply);
+        }
+
+        Ok(replies)
+    }
+}
+
+impl Publisher {
+    /// Post `message` to the given `channel`, without waiting for the server.
+    ///
+    /// The message is written to the write buffer, which is sent to the
+    /// server once full. Call [`flush`](Publisher::flush) to send it right
+    /// away.
+    #[instrument(skip(self))]
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<()> {
+        let frame = Publish::new(channel, message).into_frame();
+        debug!(request = ?frame);
+        self.client.observe(&frame, Direction::Sent);
+        self.client.connection.write_frame_buffered(&frame).await?;
+        Ok(())
+    }
+
+    /// Send the messages in the write buffer to the server.
+    ///
+    /// This does not wait for the server to handle them, see
+    /// [`finish`](Publisher::finish).
+    pub async fn flush(&mut self) -> crate::Result<()> {
+        self.client.connection.flush().await?;
+        Ok(())
+    }
+
+    /// Turn replies back on with `CLIENT REPLY ON`, returning a client that
+    /// can issue commands again.
+    ///
+    /// Once this returns, the server handled every message published before.
+    #[instrument(skip(self))]
+    pub async fn finish(mut self) -> crate::Result<Client> {
+        let frame = ClientReply::new(ReplyMode::On).into_frame();
+        debug!(request = ?frame);
+        self.client.write_frame(&frame).await?;
+
+        match self.client.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(self.client),
+            frame => Err(frame.to_error()),
+        }
+    }
+}
+
+impl Monitor {
+    /// Receive the next command processed by the server, waiting if
+    /// necessary.
+    ///
+    /// Commands are formatted as by Redis: the time they were received, the
+    /// database and address of the connection that sent them, then the quoted
+    /// name and arguments. `None` indicates the connection was closed.
+    pub async fn next_command(&mut self) -> crate::Result<Option<String>> {
+        match self.client.read_frame_untimed().await? {
+            Some(Frame::Simple(line)) => Ok(Some(line)),
+            Some(frame) => Err(frame.into_result()?.to_error()),
+            None => Ok(None),
+        }
+    }
+
+    /// Leave monitor mode with `RESET`, returning a client that can issue
+    /// commands again.
+    ///
+    /// Commands received before the server handled the reset are dropped.
+    #[instrument(skip(self))]
+    pub async fn reset(mut self) -> crate::Result<Client> {
+        let frame = Reset::new().into_frame();
+        debug!(request = ?frame);
+        self.client.write_frame(&frame).await?;
+
+        loop {
+            match self.next_command().await? {
+                Some(response) if response == "RESET" => {
+                    self.client.connection.set_protocol(2);
+                    return Ok(self.client);
+                }
+                Some(_) => {}
+                None => {
+                    let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+                    return Err(err.into());
+                }
+            }
+        }
+    }
 }
 
 impl Subscriber {
@@ -432,18 +3431,32 @@ impl Subscriber {
     /// Receive the next message published on a subscribed channel, waiting if
     /// necessary.
     ///
-    /// `None` indicates the subscription has been terminated.
+    /// `None` indicates the subscription has been terminated. If the server
+    /// dropped messages because the subscriber fell behind, a [`LaggedError`]
+    /// is returned, after which the subscriber can keep receiving messages.
     pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
-        match self.client.connection.read_frame().await? {
+        let mframe = match self.pending.pop_front() {
+            Some(mframe) => Some(mframe),
+            None => self.client.read_frame_untimed().await?,
+        };
+
+        match mframe {
             Some(mframe) => {
                 debug!(?mframe);
 
                 match mframe {
-                    Frame::Array(ref frame) => match frame.as_slice() {
+                    Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
                         [message, channel, content] if *message == "message" => Ok(Some(Message {
                             channel: channel.to_string(),
                             content: Bytes::from(content.to_string()),
                         })),
+                        [lagged, channel, Frame::Integer(skipped)] if *lagged == "lagged" => {
+                            Err(LaggedError {
+                                channel: channel.to_string(),
+                                skipped: *skipped as u64,
+                            }
+                            .into())
+                        }
                         _ => Err(mframe.to_error()),
                     },
                     frame => Err(frame.to_error()),
@@ -461,14 +3474,26 @@ impl Subscriber {
     /// implementation to use `unsafe` code. Instead, a conversion function is
     /// provided and the returned stream is implemented with the help of the
     /// `async-stream` crate.
+    ///
+    /// When the server drops messages because the subscriber fell behind, a
+    /// [`LaggedError`] is yielded and the stream continues. Any other error
+    /// ends the stream after being yielded.
     pub fn into_stream(mut self) -> impl Stream<Item = crate::Result<Message>> {
-        // Uses the `try_stream` macro from the `async-stream` crate. Generators
-        // are not stable in Rust. The crate uses a macro to simulate generators
-        // on top of async/await. There are limitations, so read the
-        // documentation there.
-        try_stream! {
-            while let Some(message) = self.next_message().await? {
-                yield message;
+        // Uses the `stream` macro from the `async-stream` crate. Generators are
+        // not stable in Rust. The crate uses a macro to simulate generators on
+        // top of async/await. There are limitations, so read the documentation
+        // there.
+        stream! {
+            loop {
+                match self.next_message().await {
+                    Ok(Some(message)) => yield Ok(message),
+                    Ok(None) => break,
+                    Err(err) if err.is::<LaggedError>() => yield Err(err),
+                    Err(err) => {
+                        yield Err(err);
+                        break;
+                    }
+                }
             }
         }
     }
@@ -477,7 +3502,9 @@ impl Subscriber {
     #[instrument(skip(self))]
     pub async fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         // Issue the subscribe command
-        self.client.subscribe_cmd(channels).await?;
+        self.client
+            .subscribe_cmd(channels, &mut self.pending)
+            .await?;
 
         // Update the set of subscribed channels.
         self.subscribed_channels
@@ -494,7 +3521,7 @@ impl Subscriber {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.client.connection.write_frame(&frame).await?;
+        self.client.write_frame(&frame).await?;
 
         // if the input channel list is empty, server acknowledges as unsubscribing
         // from all subscribed channels, so we assert that the unsubscribe list received
@@ -505,12 +3532,23 @@ impl Subscriber {
             channels.len()
         };
 
+        // Unsubscribing from nothing is confirmed by a single reply, without
+        // a channel.
+        if num == 0 {
+            return match self.client.read_confirmation(&mut self.pending).await? {
+                Frame::Array(ref frame) | Frame::Push(ref frame) if matches!(frame.as_slice(), [unsubscribe, ..] if *unsubscribe == "unsubscribe") => {
+                    Ok(())
+                }
+                frame => Err(frame.to_error()),
+            };
+        }
+
         // Read the response
         for _ in 0..num {
-            let response = self.client.read_response().await?;
+            let response = self.client.read_confirmation(&mut self.pending).await?;
 
             match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
+                Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
                     [unsubscribe, channel, ..] if *unsubscribe == "unsubscribe" => {
                         let len = self.subscribed_channels.len();
 
@@ -537,3 +3575,14 @@ impl Subscriber {
         Ok(())
     }
 }
+
+/// Returns `true` if `frame` is a message delivered to a subscriber, as opposed
+/// to a reply to one of its commands.
+fn is_message(frame: &Frame) -> bool {
+    match frame {
+        Frame::Array(frame) | Frame::Push(frame) => {
+            matches!(frame.first(), Some(kind) if *kind == "message" || *kind == "lagged")
+        }
+        _ => false,
+    }
+}
@@ -1,8 +1,17 @@
 mod client;
-pub use client::{Client, Message, Subscriber};
+pub use client::{
+    Client, Direction, LaggedError, Message, Monitor, Pipeline, Publisher, ServerError,
+    SlowLogEntry, Subscriber, TimeoutError,
+};
 
 mod blocking_client;
 pub use blocking_client::BlockingClient;
 
 mod buffered_client;
 pub use buffered_client::BufferedClient;
+
+mod reconnecting_client;
+pub use reconnecting_client::{ReconnectingClient, RetryPolicy};
+
+mod pool;
+pub use pool::{Pool, PooledClient};
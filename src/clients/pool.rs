@@ -0,0 +1,133 @@
+use crate::clients::Client;
+use crate::Result;
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A bounded pool of connections to a Redis server.
+///
+/// A `Client` only has a single request in-flight at a time. A `Pool` shares a
+/// set of clients between tasks instead: [`get`](Pool::get) hands out a client
+/// for exclusive use, which returns to the pool when dropped.
+///
+/// Connections are opened lazily, when no idle client is available, up to the
+/// maximum size of the pool. Once all clients are handed out, `get` waits for
+/// one to be returned. A client that failed in a way that leaves its
+/// connection unusable, such as an I/O error or a request cancelled before its
+/// reply was read, is discarded instead of being returned, and a new
+/// connection is opened in its place. So is a client whose connection state
+/// was changed, for instance with `SELECT` or `HELLO`, unless it was
+/// [`reset`](Client::reset) since.
+///
+/// The `Pool` handle may be cloned before passing the new handle to separate
+/// tasks, all clones share the same connections.
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+/// A client handed out by a [`Pool`].
+///
+/// Derefs to [`Client`], and returns the client to the pool when dropped.
+pub struct PooledClient {
+    /// Always `Some`, until taken when dropped.
+    client: Option<Client>,
+
+    /// Pool the client returns to.
+    shared: Arc<Shared>,
+
+    /// Counts the client against the maximum size of the pool until it is
+    /// returned.
+    _permit: OwnedSemaphorePermit,
+}
+
+struct Shared {
+    /// Address of the server.
+    addr: String,
+
+    /// Idle clients, ready to be handed out.
+    idle: Mutex<Vec<Client>>,
+
+    /// Holds one permit per client that may be handed out.
+    permits: Arc<Semaphore>,
+}
+
+impl Pool {
+    /// Create a pool of at most `max_size` connections to the Redis server at
+    /// `addr`.
+    ///
+    /// No connection is opened until a client is requested.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let pool = Pool::new("localhost:6379", 8);
+    ///
+    ///     let mut client = pool.get().await.unwrap();
+    ///     let val = client.get("foo").await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    pub fn new(addr: impl ToString, max_size: usize) -> Pool {
+        Pool {
+            shared: Arc::new(Shared {
+                addr: addr.to_string(),
+                idle: Mutex::new(vec![]),
+                permits: Arc::new(Semaphore::new(max_size)),
+            }),
+        }
+    }
+
+    /// Get a client from the pool.
+    ///
+    /// Waits for a client to be returned if the pool is exhausted. Fails if a
+    /// new connection must be opened and connecting fails.
+    pub async fn get(&self) -> Result<PooledClient> {
+        // The semaphore is never closed, so `unwrap()` is safe.
+        let permit = self.shared.permits.clone().acquire_owned().await.unwrap();
+
+        let idle = self.shared.idle.lock().unwrap().pop();
+
+        let client = match idle {
+            Some(client) => client,
+            None => Client::connect(&self.shared.addr[..]).await?,
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            shared: self.shared.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        // The permit is released after this runs, once the client is back in
+        // the idle list.
+        if let Some(client) = self.client.take() {
+            if !client.is_poisoned() && !client.changed_state() {
+                self.shared.idle.lock().unwrap().push(client);
+            }
+        }
+    }
+}
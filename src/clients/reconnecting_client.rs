@@ -0,0 +1,181 @@
+use crate::clients::{Client, TimeoutError};
+use crate::Result;
+
+use bytes::Bytes;
+use std::io;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, warn};
+
+/// Controls how [`ReconnectingClient`] reconnects to the server.
+///
+/// Reconnecting is attempted `max_retries` times after the first attempt
+/// fails. The delay between attempts starts at `base_delay` and doubles after
+/// each attempt, up to `max_delay`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Number of attempts made after the first one fails.
+    pub max_retries: u32,
+
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+
+    /// Upper bound of the delay between two attempts.
+    pub max_delay: Duration,
+}
+
+/// A client that reconnects to the server when the connection drops.
+///
+/// `Client` holds a single connection, and is unusable once it fails. A
+/// `ReconnectingClient` instead opens a new connection, following its
+/// [`RetryPolicy`], when a command fails because of the connection. The error
+/// is only returned once the server cannot be reached after all retries.
+///
+/// Reads are idempotent, so a `get` that fails because of the connection is
+/// sent again once on the new connection. Other commands may have been applied
+/// by the server before the connection dropped, so their error is returned and
+/// the next command reconnects.
+pub struct ReconnectingClient {
+    /// Address of the server, resolved again on each connection attempt.
+    addr: String,
+
+    /// How to reconnect.
+    policy: RetryPolicy,
+
+    /// Current connection to the server. `None` once it failed, until the next
+    /// command reconnects.
+    client: Option<Client>,
+}
+
+impl ReconnectingClient {
+    /// Connect to the Redis server at `addr`, retrying with the default
+    /// [`RetryPolicy`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::ReconnectingClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = ReconnectingClient::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let val = client.get("foo").await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    pub async fn connect(addr: impl ToString) -> Result<ReconnectingClient> {
+        ReconnectingClient::connect_with_policy(addr, RetryPolicy::default()).await
+    }
+
+    /// Connect to the Redis server at `addr`, retrying as described by
+    /// `policy`.
+    pub async fn connect_with_policy(
+        addr: impl ToString,
+        policy: RetryPolicy,
+    ) -> Result<ReconnectingClient> {
+        let mut client = ReconnectingClient {
+            addr: addr.to_string(),
+            policy,
+            client: None,
+        };
+
+        client.client().await?;
+
+        Ok(client)
+    }
+
+    /// Get the value of a key.
+    ///
+    /// If the connection fails, the command is sent again once on a new
+    /// connection.
+    pub async fn get(&mut self, key: &str) -> Result<Option<Bytes>> {
+        let res = self.client().await?.get(key).await;
+
+        match self.check(res) {
+            Err(err) if is_connection_error(&err) => {
+                debug!(cause = %err, "retrying get on a new connection");
+                let res = self.client().await?.get(key).await;
+                self.check(res)
+            }
+            res => res,
+        }
+    }
+
+    /// Set `key` to hold the given `value`.
+    pub async fn set(&mut self, key: &str, value: Bytes) -> Result<()> {
+        let res = self.client().await?.set(key, value).await;
+        self.check(res)
+    }
+
+    /// Post `message` to the given `channel`.
+    ///
+    /// Returns the number of subscribers currently listening on the channel.
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> Result<u64> {
+        let res = self.client().await?.publish(channel, message).await;
+        self.check(res)
+    }
+
+    /// Returns the current connection, connecting first if there is none, or
+    /// if it is poisoned, for instance because a command was cancelled
+    /// before its reply was read.
+    async fn client(&mut self) -> Result<&mut Client> {
+        let client = match self.client.take() {
+            Some(client) if !client.is_poisoned() => client,
+            _ => self.reconnect().await?,
+        };
+
+        Ok(self.client.insert(client))
+    }
+
+    /// Open a new connection, retrying with an exponential backoff.
+    async fn reconnect(&self) -> Result<Client> {
+        let mut delay = self.policy.base_delay;
+        let mut retries = 0;
+
+        loop {
+            match Client::connect(&self.addr[..]).await {
+                Ok(client) => return Ok(client),
+                Err(err) if retries < self.policy.max_retries => {
+                    warn!(cause = %err, ?delay, "failed to connect, retrying");
+                }
+                Err(err) => return Err(err),
+            }
+
+            time::sleep(delay).await;
+
+            delay = delay.saturating_mul(2).min(self.policy.max_delay);
+            retries += 1;
+        }
+    }
+
+    /// Drop the connection if `res` shows that it failed, or if it is
+    /// poisoned, so the next command reconnects.
+    fn check<T>(&mut self, res: Result<T>) -> Result<T> {
+        let poisoned = self.client.as_ref().is_some_and(Client::is_poisoned);
+
+        if let Err(err) = &res {
+            if poisoned || is_connection_error(err) {
+                self.client = None;
+            }
+        }
+
+        res
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Returns `true` if `err` means the connection can no longer be used, as
+/// opposed to an error reported by the server.
+fn is_connection_error(err: &crate::Error) -> bool {
+    err.is::<io::Error>() || err.is::<TimeoutError>()
+}
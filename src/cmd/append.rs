@@ -0,0 +1,87 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Append `value` to the string stored at `key`.
+///
+/// If the key does not exist, it is created holding `value`. The length of the
+/// string after the append is returned. An error is returned if the key holds a
+/// value that is not a string.
+#[derive(Debug)]
+pub struct Append {
+    /// Name of the key to append to
+    key: Bytes,
+
+    /// Bytes to append
+    value: Bytes,
+}
+
+impl Append {
+    /// Create a new `Append` command which appends `value` to the string at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>, value: Bytes) -> Append {
+        Append {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse an `Append` instance from a received frame.
+    ///
+    /// The `APPEND` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// APPEND key value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Append> {
+        let key = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+
+        Ok(Append { key, value })
+    }
+
+    /// Apply the `Append` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.append(&self.key, &self.value) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Append` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("append".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
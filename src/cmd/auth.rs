@@ -0,0 +1,90 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Authenticate the connection.
+///
+/// When the server is started with a password, connections must authenticate
+/// before issuing any command other than `AUTH` and `PING`. The password is
+/// compared with the one configured on the server.
+#[derive(Debug)]
+pub struct Auth {
+    /// Password supplied by the client
+    password: String,
+}
+
+impl Auth {
+    /// Create a new `Auth` command authenticating with `password`.
+    pub fn new(password: impl ToString) -> Auth {
+        Auth {
+            password: password.to_string(),
+        }
+    }
+
+    /// Get the password
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// Parse an `Auth` instance from a received frame.
+    ///
+    /// The `AUTH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// AUTH password
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Auth> {
+        let password = parse.next_string()?;
+
+        Ok(Auth { password })
+    }
+
+    /// Check the password against `requirepass`, the password the server was
+    /// started with.
+    ///
+    /// The response is written to `dst`. Returns `true` if the connection is
+    /// now authenticated. The authenticated flag itself is per-connection state
+    /// owned by the connection handler.
+    #[instrument(skip(self, requirepass, dst))]
+    pub(crate) async fn apply(
+        self,
+        requirepass: Option<&str>,
+        dst: &mut Connection,
+    ) -> crate::Result<bool> {
+        let (authenticated, response) = match requirepass {
+            Some(requirepass) if requirepass == self.password => {
+                (true, Frame::Simple("OK".to_string()))
+            }
+            Some(_) => (false, Frame::Error("ERR invalid password".to_string())),
+            None => (
+                false,
+                Frame::Error(
+                    "ERR AUTH called without any password configured for the default user"
+                        .to_string(),
+                ),
+            ),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(authenticated)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Auth` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("auth".as_bytes()));
+        frame.push_bulk(Bytes::from(self.password.into_bytes()));
+        frame
+    }
+}
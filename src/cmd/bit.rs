@@ -0,0 +1,477 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Number of bits in the largest string `SetBit` may produce, matching the
+/// limit Redis places on bulk strings.
+const MAX_BIT_OFFSET: u64 = 512 * 1024 * 1024 * 8;
+
+/// Set or clear the bit at `offset` in the string stored at `key`.
+///
+/// Bits are numbered from the most significant bit of the first byte. If
+/// `offset` is past the end of the string, the string is padded with zero
+/// bytes. A missing key is treated as an empty string. The previous value of
+/// the bit is returned.
+#[derive(Debug)]
+pub struct SetBit {
+    /// Name of the key to write
+    key: Bytes,
+
+    /// Offset of the bit to write
+    offset: i64,
+
+    /// New value of the bit, `0` or `1`
+    value: i64,
+}
+
+/// Returns the bit at `offset` in the string stored at `key`.
+///
+/// Bits past the end of the string, or of a missing key, are `0`.
+#[derive(Debug)]
+pub struct GetBit {
+    /// Name of the key to read
+    key: Bytes,
+
+    /// Offset of the bit to read
+    offset: i64,
+}
+
+/// Count the bits set in the string stored at `key`.
+///
+/// If a range is given, only the bytes between `start` and `end`, inclusive,
+/// are counted, or the bits if the range is in `BIT` units. Negative offsets
+/// count from the end of the string, `-1` being the last byte or bit. A missing
+/// key counts as `0`.
+#[derive(Debug)]
+pub struct BitCount {
+    /// Name of the key to read
+    key: Bytes,
+
+    /// Offsets of the first and last bytes or bits to count
+    range: Option<(i64, i64)>,
+
+    /// Unit of the offsets of the range
+    unit: BitUnit,
+}
+
+/// Returns the offset of the first bit set to `bit` in the string stored at
+/// `key`.
+///
+/// If a range is given, only the bytes between `start` and `end`, inclusive,
+/// are searched, or the bits if the range is in `BIT` units. The offset
+/// returned is always in bits from the start of the string, or `-1` if no bit
+/// matches.
+///
+/// The string is considered padded with clear bits, so looking for a clear bit
+/// in a string of set bits returns the offset right after the string, unless
+/// an `end` is given. Looking for a clear bit in a missing key returns `0`.
+#[derive(Debug)]
+pub struct BitPos {
+    /// Name of the key to read
+    key: Bytes,
+
+    /// Value of the bit to look for
+    bit: bool,
+
+    /// Offsets of the first and, if given, last bytes or bits to search
+    range: Option<(i64, Option<i64>)>,
+
+    /// Unit of the offsets of the range
+    unit: BitUnit,
+}
+
+/// Unit of the offsets of the range given to `BITCOUNT` and `BITPOS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit {
+    /// Offsets are in bytes, `BYTE`, the default.
+    Byte,
+
+    /// Offsets are in bits, `BIT`.
+    Bit,
+}
+
+impl SetBit {
+    /// Create a new `SetBit` command which sets the bit at `offset` in the
+    /// string at `key` to `value`.
+    pub fn new(key: impl AsRef<[u8]>, offset: i64, value: bool) -> SetBit {
+        SetBit {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            offset,
+            value: value as i64,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Parse a `SetBit` instance from a received frame.
+    ///
+    /// The `SETBIT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETBIT key offset value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetBit> {
+        let key = parse.next_bytes()?;
+        let offset = parse.next_signed_int()?;
+        let value = parse.next_signed_int()?;
+
+        Ok(SetBit { key, offset, value })
+    }
+
+    /// Apply the `SetBit` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match (bit_offset(self.offset), self.value) {
+            (None, _) => offset_out_of_range(),
+            (Some(offset), value @ 0..=1) => match db.setbit(&self.key, offset, value == 1) {
+                Ok(prev) => Frame::Integer(prev as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            (Some(_), _) => Frame::Error("ERR bit is not an integer or out of range".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetBit` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setbit".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.offset);
+        frame.push_int(self.value);
+        frame
+    }
+}
+
+impl GetBit {
+    /// Create a new `GetBit` command which returns the bit at `offset` in the
+    /// string at `key`.
+    pub fn new(key: impl AsRef<[u8]>, offset: i64) -> GetBit {
+        GetBit {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            offset,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Parse a `GetBit` instance from a received frame.
+    ///
+    /// The `GETBIT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// GETBIT key offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetBit> {
+        let key = parse.next_bytes()?;
+        let offset = parse.next_signed_int()?;
+
+        Ok(GetBit { key, offset })
+    }
+
+    /// Apply the `GetBit` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match bit_offset(self.offset) {
+            Some(offset) => match db.getbit(&self.key, offset) {
+                Ok(bit) => Frame::Integer(bit as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => offset_out_of_range(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetBit` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getbit".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.offset);
+        frame
+    }
+}
+
+impl BitCount {
+    /// Create a new `BitCount` command which counts the bits set in the string
+    /// at `key`, restricted to the bytes in `range` if given.
+    pub fn new(key: impl AsRef<[u8]>, range: Option<(i64, i64)>) -> BitCount {
+        BitCount {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            range,
+            unit: BitUnit::Byte,
+        }
+    }
+
+    /// Give the offsets of the range in `unit` rather than in bytes.
+    pub fn with_unit(mut self, unit: BitUnit) -> BitCount {
+        self.unit = unit;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the range
+    pub fn range(&self) -> Option<(i64, i64)> {
+        self.range
+    }
+
+    /// Get the unit of the range
+    pub fn unit(&self) -> BitUnit {
+        self.unit
+    }
+
+    /// Parse a `BitCount` instance from a received frame.
+    ///
+    /// The `BITCOUNT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two, four or five entries.
+    ///
+    /// ```text
+    /// BITCOUNT key [start end [BYTE|BIT]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitCount> {
+        let key = parse.next_bytes()?;
+
+        let (range, unit) = match parse.next_signed_int() {
+            Ok(start) => {
+                let end = parse.next_signed_int()?;
+                (Some((start, end)), next_unit(parse, "BITCOUNT")?)
+            }
+            Err(ParseError::EndOfStream) => (None, BitUnit::Byte),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(BitCount { key, range, unit })
+    }
+
+    /// Apply the `BitCount` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let (start, end) = self.range.unwrap_or((0, -1));
+
+        let response = match db.bitcount(&self.key, start, end, self.unit) {
+            Ok(count) => Frame::Integer(count as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BitCount` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bitcount".as_bytes()));
+        frame.push_bulk(self.key);
+        if let Some((start, end)) = self.range {
+            frame.push_int(start);
+            frame.push_int(end);
+            if self.unit == BitUnit::Bit {
+                frame.push_bulk(Bytes::from("bit".as_bytes()));
+            }
+        }
+        frame
+    }
+}
+
+impl BitPos {
+    /// Create a new `BitPos` command which looks for the first bit set to
+    /// `bit` in the string at `key`, restricted to the bytes in `range` if
+    /// given. The range runs to the end of the string if it has no end.
+    pub fn new(key: impl AsRef<[u8]>, bit: bool, range: Option<(i64, Option<i64>)>) -> BitPos {
+        BitPos {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            bit,
+            range,
+            unit: BitUnit::Byte,
+        }
+    }
+
+    /// Give the offsets of the range in `unit` rather than in bytes. As in
+    /// Redis, the unit is only sent along with an end.
+    pub fn with_unit(mut self, unit: BitUnit) -> BitPos {
+        self.unit = unit;
+        self
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the value of the bit to look for
+    pub fn bit(&self) -> bool {
+        self.bit
+    }
+
+    /// Get the range
+    pub fn range(&self) -> Option<(i64, Option<i64>)> {
+        self.range
+    }
+
+    /// Get the unit of the range
+    pub fn unit(&self) -> BitUnit {
+        self.unit
+    }
+
+    /// Parse a `BitPos` instance from a received frame.
+    ///
+    /// The `BITPOS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three to six entries.
+    ///
+    /// ```text
+    /// BITPOS key bit [start [end [BYTE|BIT]]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitPos> {
+        let key = parse.next_bytes()?;
+
+        let bit = match parse.next_signed_int()? {
+            0 => false,
+            1 => true,
+            _ => return Err("The bit argument must be 1 or 0.".into()),
+        };
+
+        let start = match parse.next_signed_int() {
+            Ok(start) => start,
+            Err(ParseError::EndOfStream) => return Ok(BitPos::new(key, bit, None)),
+            Err(err) => return Err(err.into()),
+        };
+
+        let (end, unit) = match parse.next_signed_int() {
+            Ok(end) => (Some(end), next_unit(parse, "BITPOS")?),
+            Err(ParseError::EndOfStream) => (None, BitUnit::Byte),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(BitPos::new(key, bit, Some((start, end))).with_unit(unit))
+    }
+
+    /// Apply the `BitPos` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let (start, end) = self.range.unwrap_or((0, None));
+
+        let response = match db.bitpos(&self.key, self.bit, start, end, self.unit) {
+            Ok(offset) => Frame::Integer(offset),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BitPos` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("bitpos".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.bit as i64);
+        if let Some((start, end)) = self.range {
+            frame.push_int(start);
+            if let Some(end) = end {
+                frame.push_int(end);
+                if self.unit == BitUnit::Bit {
+                    frame.push_bulk(Bytes::from("bit".as_bytes()));
+                }
+            }
+        }
+        frame
+    }
+}
+
+/// Parse the optional unit following the range of the command `name`.
+fn next_unit(parse: &mut Parse, name: &str) -> crate::Result<BitUnit> {
+    match parse.next_string() {
+        Ok(s) if s.to_uppercase() == "BYTE" => Ok(BitUnit::Byte),
+        Ok(s) if s.to_uppercase() == "BIT" => Ok(BitUnit::Bit),
+        Ok(_) => Err(format!("`{}` only supports the BYTE and BIT units", name).into()),
+        Err(ParseError::EndOfStream) => Ok(BitUnit::Byte),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns `offset` if it is a valid bit offset.
+fn bit_offset(offset: i64) -> Option<u64> {
+    u64::try_from(offset)
+        .ok()
+        .filter(|&offset| offset < MAX_BIT_OFFSET)
+}
+
+fn offset_out_of_range() -> Frame {
+    Frame::Error("ERR bit offset is not an integer or out of range".to_string())
+}
@@ -0,0 +1,563 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Name the connection.
+///
+/// The name is reported by `CLIENT GETNAME` and attached to the logs of the
+/// commands the connection issues. It may not contain spaces, newlines or
+/// other special characters. An empty name removes the current name.
+#[derive(Debug)]
+pub struct ClientSetName {
+    /// New name of the connection
+    name: String,
+}
+
+/// Returns the name of the connection, as set with `CLIENT SETNAME`.
+///
+/// `nil` is returned if the connection has no name.
+#[derive(Debug, Default)]
+pub struct ClientGetName {}
+
+/// Describe the connections open on the server.
+///
+/// The reply is a bulk string with one line per connection, made of
+/// `field=value` pairs giving its identifier, the address of the peer, its
+/// name, its age in seconds and the database it selected.
+#[derive(Debug, Default)]
+pub struct ClientList {}
+
+/// Close the connections matching every given filter.
+///
+/// `ID` matches the connection with the identifier reported by `CLIENT LIST`,
+/// `ADDR` the connection from the given `ip:port` address. At least one filter
+/// must be given. The number of connections closed is returned. A connection
+/// killing itself receives the reply before it is closed.
+#[derive(Debug)]
+pub struct ClientKill {
+    /// Identifier of the connection to close
+    id: Option<u64>,
+
+    /// Address of the peer of the connection to close
+    addr: Option<String>,
+}
+
+/// Turn the replies of the server to the connection on or off.
+///
+/// With `OFF`, the server stops replying to the commands the connection sends,
+/// until `ON` is sent, which is replied `OK`. With `SKIP`, only the reply to
+/// the next command is dropped. This is used to send many commands without
+/// waiting for their replies, without the connection filling up with replies
+/// nobody reads. Errors are dropped along with the other replies.
+#[derive(Debug)]
+pub struct ClientReply {
+    /// Whether the following commands are replied
+    mode: ReplyMode,
+}
+
+/// Replies sent by the server to the connection, see `ClientReply`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyMode {
+    /// Every command is replied, `ON`, the default.
+    On,
+
+    /// No command is replied, `OFF`.
+    Off,
+
+    /// The next command is not replied, `SKIP`.
+    Skip,
+}
+
+/// Hold the commands of every connection for `timeout` milliseconds.
+///
+/// Commands received in the meantime wait for the pause to end before they
+/// are applied, they are not rejected. With `WRITE`, only the commands which
+/// write to the keyspace wait and reads go on, this is used to quiesce writes
+/// before a snapshot or a failover. With `ALL`, the default, every command
+/// waits. `CLIENT` commands are never held, so `CLIENT UNPAUSE` can end the
+/// pause early. A new pause replaces the current one.
+#[derive(Debug)]
+pub struct ClientPause {
+    /// Duration of the pause, in milliseconds
+    timeout: u64,
+
+    /// Which commands are held
+    mode: PauseMode,
+}
+
+/// Commands held by `CLIENT PAUSE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMode {
+    /// Every command is held, `ALL`, the default.
+    All,
+
+    /// Commands writing to the keyspace are held, `WRITE`.
+    Write,
+}
+
+/// End the pause started with `CLIENT PAUSE`, if any.
+///
+/// The commands held are applied right away.
+#[derive(Debug, Default)]
+pub struct ClientUnpause {}
+
+impl ClientSetName {
+    /// Create a new `ClientSetName` command which names the connection `name`.
+    pub fn new(name: impl ToString) -> ClientSetName {
+        ClientSetName {
+            name: name.to_string(),
+        }
+    }
+
+    /// Get the name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parse a `ClientSetName` instance from a received frame.
+    ///
+    /// The `CLIENT SETNAME` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// CLIENT SETNAME name
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ClientSetName> {
+        let name = parse.next_string()?;
+
+        Ok(ClientSetName { name })
+    }
+
+    /// Check the name, as Redis does.
+    ///
+    /// The response is written to `dst`. Returns the name to switch to if it
+    /// is valid, an empty name standing for no name. The name itself is
+    /// per-connection state owned by the connection handler.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<Option<String>> {
+        let valid = self.name.bytes().all(|b| (b'!'..=b'~').contains(&b));
+
+        let response = if valid {
+            Frame::Simple("OK".to_string())
+        } else {
+            Frame::Error(
+                "ERR Client names cannot contain spaces, newlines or special characters."
+                    .to_string(),
+            )
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(Some(self.name).filter(|_| valid))
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientSetName` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("setname".as_bytes()));
+        frame.push_bulk(Bytes::from(self.name.into_bytes()));
+        frame
+    }
+}
+
+impl ClientReply {
+    /// Create a new `ClientReply` command which switches the replies of the
+    /// server to `mode`.
+    pub fn new(mode: ReplyMode) -> ClientReply {
+        ClientReply { mode }
+    }
+
+    /// Get the mode
+    pub fn mode(&self) -> ReplyMode {
+        self.mode
+    }
+
+    /// Parse a `ClientReply` instance from a received frame.
+    ///
+    /// The `CLIENT REPLY` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// CLIENT REPLY ON|OFF|SKIP
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ClientReply> {
+        let mode = match &parse.next_string()?.to_uppercase()[..] {
+            "ON" => ReplyMode::On,
+            "OFF" => ReplyMode::Off,
+            "SKIP" => ReplyMode::Skip,
+            _ => return Err("`CLIENT REPLY` only supports the ON, OFF and SKIP modes".into()),
+        };
+
+        Ok(ClientReply { mode })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientReply` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mode = match self.mode {
+            ReplyMode::On => "on",
+            ReplyMode::Off => "off",
+            ReplyMode::Skip => "skip",
+        };
+
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("reply".as_bytes()));
+        frame.push_bulk(Bytes::from(mode.as_bytes()));
+        frame
+    }
+}
+
+impl ClientGetName {
+    /// Create a new `ClientGetName` command.
+    pub fn new() -> ClientGetName {
+        ClientGetName {}
+    }
+
+    /// Parse a `ClientGetName` instance from a received frame.
+    ///
+    /// The `CLIENT GETNAME` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// CLIENT GETNAME
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<ClientGetName> {
+        Ok(ClientGetName {})
+    }
+
+    /// Reply with `name`, the name of the connection held by the connection
+    /// handler.
+    ///
+    /// The response is written to `dst`.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, name: Option<&str>, dst: &mut Connection) -> crate::Result<()> {
+        let response = match name {
+            Some(name) => Frame::Bulk(Bytes::from(name.to_string())),
+            None => Frame::Null,
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientGetName` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("getname".as_bytes()));
+        frame
+    }
+}
+
+impl ClientList {
+    /// Create a new `ClientList` command.
+    pub fn new() -> ClientList {
+        ClientList {}
+    }
+
+    /// Parse a `ClientList` instance from a received frame.
+    ///
+    /// The `CLIENT LIST` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// CLIENT LIST
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<ClientList> {
+        Ok(ClientList {})
+    }
+
+    /// Apply the `ClientList` command to the specified `Db` instance.
+    ///
+    /// The connections are registered with the whole server, any database
+    /// lists all of them. The response is written to `dst`.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut list = String::new();
+        for client in db.clients() {
+            list.push_str(&format!(
+                "id={} addr={} name={} age={} db={}\n",
+                client.id(),
+                client.addr(),
+                client.name().unwrap_or_default(),
+                client.age().as_secs(),
+                client.db(),
+            ));
+        }
+
+        let response = Frame::Bulk(Bytes::from(list));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientList` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("list".as_bytes()));
+        frame
+    }
+}
+
+impl ClientKill {
+    /// Create a new `ClientKill` command which closes the connections with
+    /// identifier `id` and peer address `addr`, when given.
+    pub fn new(id: Option<u64>, addr: Option<String>) -> ClientKill {
+        ClientKill { id, addr }
+    }
+
+    /// Get the identifier
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// Get the address
+    pub fn addr(&self) -> Option<&str> {
+        self.addr.as_deref()
+    }
+
+    /// Parse a `ClientKill` instance from a received frame.
+    ///
+    /// The `CLIENT KILL` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// CLIENT KILL [ID id] [ADDR ip:port]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ClientKill> {
+        use ParseError::EndOfStream;
+
+        let mut id = None;
+        let mut addr = None;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "ID" => id = Some(parse.next_int()?),
+                Ok(s) if s.to_uppercase() == "ADDR" => addr = Some(parse.next_string()?),
+                Ok(_) => return Err("`CLIENT KILL` only supports the ID and ADDR filters".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if id.is_none() && addr.is_none() {
+            return Err("`CLIENT KILL` requires the ID or ADDR filter".into());
+        }
+
+        Ok(ClientKill { id, addr })
+    }
+
+    /// Apply the `ClientKill` command to the specified `Db` instance.
+    ///
+    /// The handlers of the matching connections are signalled to terminate,
+    /// they close their connection once done with the command they are
+    /// applying. The response is written to `dst`.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let killed = db.kill_clients(|client| {
+            self.id.is_none_or(|id| id == client.id())
+                && self
+                    .addr
+                    .as_ref()
+                    .is_none_or(|addr| *addr == client.addr().to_string())
+        });
+
+        let response = Frame::Integer(killed as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientKill` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("kill".as_bytes()));
+        if let Some(id) = self.id {
+            frame.push_bulk(Bytes::from("id".as_bytes()));
+            frame.push_int(id as i64);
+        }
+        if let Some(addr) = self.addr {
+            frame.push_bulk(Bytes::from("addr".as_bytes()));
+            frame.push_bulk(Bytes::from(addr.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl ClientPause {
+    /// Create a new `ClientPause` command which holds the commands selected by
+    /// `mode` for `timeout` milliseconds.
+    pub fn new(timeout: u64, mode: PauseMode) -> ClientPause {
+        ClientPause { timeout, mode }
+    }
+
+    /// Get the duration of the pause, in milliseconds
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    /// Get the mode
+    pub fn mode(&self) -> PauseMode {
+        self.mode
+    }
+
+    /// Parse a `ClientPause` instance from a received frame.
+    ///
+    /// The `CLIENT PAUSE` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// CLIENT PAUSE timeout [WRITE|ALL]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ClientPause> {
+        let timeout = parse.next_int()?;
+
+        let mode = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "ALL" => PauseMode::All,
+            Ok(s) if s.to_uppercase() == "WRITE" => PauseMode::Write,
+            Ok(_) => return Err("`CLIENT PAUSE` only supports the WRITE and ALL modes".into()),
+            Err(ParseError::EndOfStream) => PauseMode::All,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ClientPause { timeout, mode })
+    }
+
+    /// Apply the `ClientPause` command to the specified `Db` instance.
+    ///
+    /// The pause applies to the connections of every database, the handlers
+    /// check it before applying each command. The response is written to
+    /// `dst`.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match Instant::now().checked_add(Duration::from_millis(self.timeout)) {
+            Some(until) => {
+                db.pause_clients(until, self.mode);
+                Frame::Simple("OK".to_string())
+            }
+            None => Frame::Error("ERR timeout is out of range".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientPause` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mode = match self.mode {
+            PauseMode::All => "all",
+            PauseMode::Write => "write",
+        };
+
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("pause".as_bytes()));
+        frame.push_int(self.timeout as i64);
+        frame.push_bulk(Bytes::from(mode.as_bytes()));
+        frame
+    }
+}
+
+impl ClientUnpause {
+    /// Create a new `ClientUnpause` command.
+    pub fn new() -> ClientUnpause {
+        ClientUnpause {}
+    }
+
+    /// Parse a `ClientUnpause` instance from a received frame.
+    ///
+    /// The `CLIENT UNPAUSE` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// CLIENT UNPAUSE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<ClientUnpause> {
+        Ok(ClientUnpause {})
+    }
+
+    /// Apply the `ClientUnpause` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.unpause_clients();
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ClientUnpause` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        frame.push_bulk(Bytes::from("unpause".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Name and arity of every command supported by the server.
+///
+/// As in Redis, the arity counts the command name itself and a negative arity
+/// `-n` means the command takes at least `n` entries. Commands with
+/// subcommands, such as `CONFIG`, are listed once.
+const COMMANDS: &[(&str, i64)] = &[
+    ("append", 3),
+    ("auth", -2),
+    ("bitcount", -2),
+    ("bitpos", -3),
+    ("blpop", -3),
+    ("brpop", -3),
+    ("client", -2),
+    ("command", -1),
+    ("config", -2),
+    ("copy", -3),
+    ("dbsize", 1),
+    ("debug", -2),
+    ("decr", 2),
+    ("decrby", 3),
+    ("del", -2),
+    ("dump", 2),
+    ("echo", 2),
+    ("eval", -3),
+    ("discard", 1),
+    ("exec", 1),
+    ("exists", -2),
+    ("expire", -3),
+    ("expireat", 3),
+    ("flushall", 1),
+    ("flushdb", 1),
+    ("get", 2),
+    ("getbit", 3),
+    ("getdel", 2),
+    ("getex", -2),
+    ("getrange", 4),
+    ("getset", 3),
+    ("hdel", -3),
+    ("hello", -1),
+    ("hexists", 3),
+    ("hget", 3),
+    ("hgetall", 2),
+    ("hincrby", 4),
+    ("hincrbyfloat", 4),
+    ("hkeys", 2),
+    ("hlen", 2),
+    ("hrandfield", -2),
+    ("hset", -4),
+    ("hvals", 2),
+    ("incr", 2),
+    ("incrby", 3),
+    ("incrbyfloat", 3),
+    ("info", -1),
+    ("keys", 2),
+    ("linsert", 5),
+    ("llen", 2),
+    ("lmpop", -4),
+    ("lpop", 2),
+    ("lpos", -3),
+    ("lpush", -3),
+    ("lpushx", -3),
+    ("lrange", 4),
+    ("lset", 4),
+    ("mget", -2),
+    ("monitor", 1),
+    ("move", 3),
+    ("mset", -4),
+    ("msetnx", -4),
+    ("multi", 1),
+    ("object", -2),
+    ("persist", 2),
+    ("pexpireat", 3),
+    ("ping", -1),
+    ("psetex", 4),
+    ("psubscribe", -2),
+    ("pttl", 2),
+    ("publish", 3),
+    ("pubsub", -2),
+    ("punsubscribe", -1),
+    ("quit", 1),
+    ("randomkey", 1),
+    ("rename", 3),
+    ("renamenx", 3),
+    ("reset", 1),
+    ("restore", -4),
+    ("rpop", 2),
+    ("rpush", -3),
+    ("rpushx", -3),
+    ("sadd", -3),
+    ("save", 1),
+    ("scan", -2),
+    ("scard", 2),
+    ("sdiffstore", -3),
+    ("select", 2),
+    ("set", -3),
+    ("setbit", 4),
+    ("setex", 4),
+    ("setnx", 3),
+    ("setrange", 4),
+    ("shutdown", -1),
+    ("sintercard", -3),
+    ("sinterstore", -3),
+    ("sismember", 3),
+    ("slowlog", -2),
+    ("smembers", 2),
+    ("smove", 4),
+    ("srandmember", -2),
+    ("srem", -3),
+    ("strlen", 2),
+    ("subscribe", -2),
+    ("sunionstore", -3),
+    ("swapdb", 3),
+    ("touch", -2),
+    ("ttl", 2),
+    ("type", 2),
+    ("unlink", -2),
+    ("unsubscribe", -1),
+    ("wait", 3),
+    ("zadd", -4),
+    ("zrange", -4),
+    ("zrank", 3),
+    ("zscore", 3),
+];
+
+/// Returns `true` if `name` is the name of a supported command.
+pub(crate) fn is_supported(name: &str) -> bool {
+    COMMANDS.iter().any(|&(command, _)| command == name)
+}
+
+/// Check that a command named `name` may be sent with `argc` entries, the name
+/// included.
+///
+/// Commands missing from `COMMANDS` are not checked, they are reported as
+/// unknown.
+pub(crate) fn check_arity(name: &str, argc: usize) -> crate::Result<()> {
+    let arity = match COMMANDS.iter().find(|&&(command, _)| command == name) {
+        Some(&(_, arity)) => arity,
+        None => return Ok(()),
+    };
+
+    let argc = argc as i64;
+    let valid = if arity < 0 {
+        argc >= -arity
+    } else {
+        argc == arity
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("wrong number of arguments for '{}' command", name).into())
+    }
+}
+
+/// Describe the commands supported by the server.
+///
+/// The reply is an array with one entry per command, itself an array holding
+/// the name of the command and its arity. Client libraries call this when
+/// connecting to learn what the server supports.
+#[derive(Debug, Default)]
+pub struct CommandTable {}
+
+/// Returns the number of commands supported by the server.
+#[derive(Debug, Default)]
+pub struct CommandCount {}
+
+/// Returns the documentation of the given commands, or of every command.
+///
+/// Only the names of the commands are known to `mini-redis`, so the reply is
+/// always empty. It is supported because some clients, such as `redis-cli`,
+/// call it when connecting.
+#[derive(Debug, Default)]
+pub struct CommandDocs {
+    /// Names of the commands to document, all of them if empty
+    names: Vec<String>,
+}
+
+impl CommandTable {
+    /// Create a new `CommandTable` command.
+    pub fn new() -> CommandTable {
+        CommandTable {}
+    }
+
+    /// Parse a `CommandTable` instance from a received frame.
+    ///
+    /// The `COMMAND` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// COMMAND
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<CommandTable> {
+        Ok(CommandTable {})
+    }
+
+    /// Apply the `CommandTable` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let commands = COMMANDS
+            .iter()
+            .map(|&(name, arity)| {
+                Frame::Array(vec![
+                    Frame::Bulk(Bytes::from(name.as_bytes())),
+                    Frame::Integer(arity),
+                ])
+            })
+            .collect();
+
+        let response = Frame::Array(commands);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `CommandTable` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("command".as_bytes()));
+        frame
+    }
+}
+
+impl CommandCount {
+    /// Create a new `CommandCount` command.
+    pub fn new() -> CommandCount {
+        CommandCount {}
+    }
+
+    /// Parse a `CommandCount` instance from a received frame.
+    ///
+    /// The `COMMAND COUNT` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// COMMAND COUNT
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<CommandCount> {
+        Ok(CommandCount {})
+    }
+
+    /// Apply the `CommandCount` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(COMMANDS.len() as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `CommandCount` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("command".as_bytes()));
+        frame.push_bulk(Bytes::from("count".as_bytes()));
+        frame
+    }
+}
+
+impl CommandDocs {
+    /// Create a new `CommandDocs` command which documents the commands in
+    /// `names`, or every command if it is empty.
+    pub fn new(names: Vec<String>) -> CommandDocs {
+        CommandDocs { names }
+    }
+
+    /// Get the names
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Parse a `CommandDocs` instance from a received frame.
+    ///
+    /// The `COMMAND DOCS` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// COMMAND DOCS [command-name [command-name ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<CommandDocs> {
+        let mut names = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(name) => names.push(name),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(CommandDocs { names })
+    }
+
+    /// Apply the `CommandDocs` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Map(vec![]);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `CommandDocs` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("command".as_bytes()));
+        frame.push_bulk(Bytes::from("docs".as_bytes()));
+        for name in self.names {
+            frame.push_bulk(Bytes::from(name.into_bytes()));
+        }
+        frame
+    }
+}
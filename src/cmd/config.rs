@@ -0,0 +1,314 @@
+use crate::cmd::command;
+use crate::db::MAX_CHANNEL_CAPACITY;
+use crate::server::{KeyspaceEvents, MaxMemoryPolicy};
+use crate::{glob, Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::collections::HashSet;
+use tracing::{debug, instrument};
+
+/// Smallest value `proto-max-bulk-len` can be set to, 1mb.
+const MIN_PROTO_MAX_BULK_LEN: usize = 1024 * 1024;
+
+/// Parameters that can be read and changed at runtime.
+const PARAMETERS: &[&str] = &[
+    "allowed-commands",
+    "channel-capacity",
+    "maxmemory",
+    "maxmemory-policy",
+    "notify-keyspace-events",
+    "proto-max-bulk-len",
+];
+
+/// Returns the value of the runtime parameters matching `pattern`.
+///
+/// The pattern is matched against the parameter names as a glob-style
+/// pattern. The reply is an array of alternating names and values, empty if
+/// no parameter matches.
+#[derive(Debug)]
+pub struct ConfigGet {
+    /// Pattern matching the names of the parameters to return
+    pattern: String,
+}
+
+/// Change the value of a runtime parameter.
+///
+/// The new value applies to every database right away. For instance, lowering
+/// `maxmemory` evicts keys immediately. A new `channel-capacity` only applies
+/// to pub/sub channels created from then on, and a new `proto-max-bulk-len`
+/// to the next request of each connection.
+#[derive(Debug)]
+pub struct ConfigSet {
+    /// Name of the parameter
+    parameter: String,
+
+    /// New value of the parameter
+    value: String,
+}
+
+impl ConfigGet {
+    /// Create a new `ConfigGet` command which returns the parameters matching
+    /// `pattern`.
+    pub fn new(pattern: impl ToString) -> ConfigGet {
+        ConfigGet {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Parse a `ConfigGet` instance from a received frame.
+    ///
+    /// The `CONFIG GET` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// CONFIG GET pattern
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ConfigGet> {
+        let pattern = parse.next_string()?;
+
+        Ok(ConfigGet { pattern })
+    }
+
+    /// Apply the `ConfigGet` command to the databases of the server, indexed
+    /// by database number.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        let pattern = self.pattern.to_lowercase();
+
+        // Every database is configured the same, and there is always at least
+        // one.
+        let db = &dbs[0];
+
+        let mut response = Frame::array();
+
+        for &parameter in PARAMETERS {
+            if !glob::matches(pattern.as_bytes(), parameter.as_bytes()) {
+                continue;
+            }
+
+            let value = match parameter {
+                "allowed-commands" => match db.allowed_commands() {
+                    Some(names) => names.join(","),
+                    None => "*".to_string(),
+                },
+                "channel-capacity" => db.channel_capacity().to_string(),
+                // Redis reports the absence of a limit as `0`.
+                "maxmemory" => db.maxmemory().unwrap_or(0).to_string(),
+                "maxmemory-policy" => db.maxmemory_policy().to_string(),
+                "notify-keyspace-events" => db.keyspace_events().to_string(),
+                "proto-max-bulk-len" => db.max_frame_size().to_string(),
+                _ => continue,
+            };
+
+            response.push_bulk(Bytes::from(parameter.as_bytes()));
+            response.push_bulk(Bytes::from(value));
+        }
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ConfigGet` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("config".as_bytes()));
+        frame.push_bulk(Bytes::from("get".as_bytes()));
+        frame.push_bulk(Bytes::from(self.pattern.into_bytes()));
+        frame
+    }
+}
+
+impl ConfigSet {
+    /// Create a new `ConfigSet` command which sets `parameter` to `value`.
+    pub fn new(parameter: impl ToString, value: impl ToString) -> ConfigSet {
+        ConfigSet {
+            parameter: parameter.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// Get the parameter
+    pub fn parameter(&self) -> &str {
+        &self.parameter
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Parse a `ConfigSet` instance from a received frame.
+    ///
+    /// The `CONFIG SET` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// CONFIG SET parameter value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ConfigSet> {
+        let parameter = parse.next_string()?;
+        let value = parse.next_string()?;
+
+        Ok(ConfigSet { parameter, value })
+    }
+
+    /// Apply the `ConfigSet` command to the databases of the server, indexed
+    /// by database number.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        let parameter = self.parameter.to_lowercase();
+
+        let response = match &parameter[..] {
+            // Like pub/sub channels, the commands accepted are the same for
+            // all databases.
+            "allowed-commands" => match parse_commands(&self.value) {
+                Some(names) => {
+                    dbs[0].set_allowed_commands(names);
+                    Frame::Simple("OK".to_string())
+                }
+                None => invalid_argument(&parameter, &self.value),
+            },
+            // Pub/sub channels are shared by all databases.
+            "channel-capacity" => match self.value.parse::<usize>() {
+                Ok(capacity) if (1..=MAX_CHANNEL_CAPACITY).contains(&capacity) => {
+                    dbs[0].set_channel_capacity(capacity);
+                    Frame::Simple("OK".to_string())
+                }
+                _ => invalid_argument(&parameter, &self.value),
+            },
+            "maxmemory" => match parse_memory(&self.value) {
+                Some(limit) => {
+                    // A limit of `0` removes the limit, as in Redis.
+                    let limit = Some(limit).filter(|&limit| limit > 0);
+                    for db in dbs {
+                        db.set_maxmemory(limit);
+                    }
+                    Frame::Simple("OK".to_string())
+                }
+                None => invalid_argument(&parameter, &self.value),
+            },
+            "maxmemory-policy" => match self.value.parse::<MaxMemoryPolicy>() {
+                Ok(policy) => {
+                    for db in dbs {
+                        db.set_maxmemory_policy(policy);
+                    }
+                    Frame::Simple("OK".to_string())
+                }
+                Err(_) => invalid_argument(&parameter, &self.value),
+            },
+            "notify-keyspace-events" => match self.value.parse::<KeyspaceEvents>() {
+                Ok(events) => {
+                    for db in dbs {
+                        db.set_keyspace_events(events);
+                    }
+                    Frame::Simple("OK".to_string())
+                }
+                Err(_) => invalid_argument(&parameter, &self.value),
+            },
+            // The limit is shared by all connections, whatever database they
+            // selected.
+            // As in Redis, the limit cannot be lowered under 1mb, which would
+            // refuse most requests.
+            "proto-max-bulk-len" => match parse_memory(&self.value) {
+                Some(limit) if limit >= MIN_PROTO_MAX_BULK_LEN => {
+                    dbs[0].set_max_frame_size(limit);
+                    Frame::Simple("OK".to_string())
+                }
+                _ => invalid_argument(&parameter, &self.value),
+            },
+            _ => Frame::Error(format!(
+                "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                self.parameter
+            )),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ConfigSet` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("config".as_bytes()));
+        frame.push_bulk(Bytes::from("set".as_bytes()));
+        frame.push_bulk(Bytes::from(self.parameter.into_bytes()));
+        frame.push_bulk(Bytes::from(self.value.into_bytes()));
+        frame
+    }
+}
+
+fn invalid_argument(parameter: &str, value: &str) -> Frame {
+    Frame::Error(format!(
+        "ERR Invalid argument '{}' for CONFIG SET '{}'",
+        value, parameter
+    ))
+}
+
+/// Parse a comma-separated list of command names, or `*` for every command,
+/// returned as `None`. Every name must be a supported command.
+fn parse_commands(value: &str) -> Option<Option<HashSet<String>>> {
+    if value.trim() == "*" {
+        return Some(None);
+    }
+
+    let names = value
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .map(|name| Some(name).filter(|name| command::is_supported(name)))
+        .collect::<Option<_>>()?;
+
+    Some(Some(names))
+}
+
+/// Parse a number of bytes, optionally followed by a unit as in the Redis
+/// configuration file: `k`, `m` and `g` are powers of 1000, `kb`, `mb` and
+/// `gb` powers of 1024. Units are case insensitive.
+fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_lowercase();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+
+    let multiplier: usize = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
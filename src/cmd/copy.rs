@@ -0,0 +1,114 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Copy the value stored at `src` to `dst`.
+///
+/// The value keeps its type, and `dst` gets the same remaining time to live as
+/// `src`. Replies `1` if the key was copied and `0` if `src` does not exist or
+/// `dst` already exists.
+///
+/// # Options
+///
+/// * REPLACE -- Overwrite `dst` if it already exists.
+#[derive(Debug)]
+pub struct Copy {
+    /// Name of the key to copy
+    src: Bytes,
+
+    /// Name of the key to copy to
+    dst: Bytes,
+
+    /// Whether an existing `dst` is overwritten
+    replace: bool,
+}
+
+impl Copy {
+    /// Create a new `Copy` command which copies `src` to `dst`, overwriting
+    /// `dst` only if `replace` is set.
+    pub fn new(src: impl AsRef<[u8]>, dst: impl AsRef<[u8]>, replace: bool) -> Copy {
+        Copy {
+            src: Bytes::copy_from_slice(src.as_ref()),
+            dst: Bytes::copy_from_slice(dst.as_ref()),
+            replace,
+        }
+    }
+
+    /// Get the name of the key to copy
+    pub fn src(&self) -> &Bytes {
+        &self.src
+    }
+
+    /// Get the name of the key to copy to
+    pub fn dst(&self) -> &Bytes {
+        &self.dst
+    }
+
+    /// Get whether an existing `dst` is overwritten
+    pub fn replace(&self) -> bool {
+        self.replace
+    }
+
+    /// Parse a `Copy` instance from a received frame.
+    ///
+    /// The `COPY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// COPY src dst [REPLACE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Copy> {
+        let src = parse.next_bytes()?;
+        let dst = parse.next_bytes()?;
+
+        let replace = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "REPLACE" => true,
+            Ok(_) => return Err("`COPY` only supports the REPLACE option".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Copy { src, dst, replace })
+    }
+
+    /// Apply the `Copy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.src == self.dst {
+            Frame::Error("ERR source and destination objects are the same".to_string())
+        } else {
+            match db.copy(&self.src, &self.dst, self.replace) {
+                Ok(copied) => Frame::Integer(copied as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Copy` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("copy".as_bytes()));
+        frame.push_bulk(self.src);
+        frame.push_bulk(self.dst);
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        frame
+    }
+}
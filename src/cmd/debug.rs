@@ -0,0 +1,303 @@
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, instrument};
+
+/// Wait for `duration` before replying `OK`.
+///
+/// Only the connection issuing the command waits, other connections are served
+/// in the meantime. This is used to test how clients deal with slow commands.
+/// Like all `DEBUG` subcommands, it must be enabled in the server
+/// configuration.
+#[derive(Debug)]
+pub struct DebugSleep {
+    /// Time to wait before replying
+    duration: Duration,
+}
+
+/// Describe the value stored at `key`.
+///
+/// The reply is a simple string made of `field:value` pairs giving the type of
+/// the value, its approximate size in bytes and whether the key has an
+/// expiration. An error is returned if the key does not exist.
+#[derive(Debug)]
+pub struct DebugObject {
+    /// Name of the key to describe
+    key: Bytes,
+}
+
+/// Pause or resume the purge of expired keys by the background task of every
+/// database.
+///
+/// While paused, expired keys are hidden from reads as usual, but are only
+/// removed when written to, or swept with `DEBUG EXPIRE-SWEEP`. This lets tests
+/// observe expiration without depending on when the background task runs.
+#[derive(Debug)]
+pub struct DebugSetActiveExpire {
+    /// Whether the background task purges expired keys
+    enabled: bool,
+}
+
+/// Purge every expired key of every database right away, whether or not the
+/// background task is paused.
+///
+/// Replies `OK` once the keys have been purged.
+#[derive(Debug, Default)]
+pub struct DebugExpireSweep {}
+
+impl DebugSleep {
+    /// Create a new `DebugSleep` command which waits for `duration`.
+    pub fn new(duration: Duration) -> DebugSleep {
+        DebugSleep { duration }
+    }
+
+    /// Get the duration
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Parse a `DebugSleep` instance from a received frame.
+    ///
+    /// The `DEBUG SLEEP` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries. The number of
+    /// seconds may have a fractional part.
+    ///
+    /// ```text
+    /// DEBUG SLEEP seconds
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DebugSleep> {
+        let duration = parse
+            .next_string()?
+            .parse::<f64>()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or("protocol error; invalid number of seconds")?;
+
+        Ok(DebugSleep { duration })
+    }
+
+    /// Apply the `DebugSleep` command.
+    ///
+    /// The response is written to `dst` once `duration` has elapsed. If the
+    /// server shuts down in the meantime, no response is written.
+    #[instrument(skip(self, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        tokio::select! {
+            _ = time::sleep(self.duration) => {}
+            _ = shutdown.recv() => return Ok(()),
+        }
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DebugSleep` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("debug".as_bytes()));
+        frame.push_bulk(Bytes::from("sleep".as_bytes()));
+        frame.push_bulk(Bytes::from(self.duration.as_secs_f64().to_string()));
+        frame
+    }
+}
+
+impl DebugSetActiveExpire {
+    /// Create a new `DebugSetActiveExpire` command which pauses the purge of
+    /// expired keys if `enabled` is unset, and resumes it otherwise.
+    pub fn new(enabled: bool) -> DebugSetActiveExpire {
+        DebugSetActiveExpire { enabled }
+    }
+
+    /// Returns `true` if the purge is resumed
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Parse a `DebugSetActiveExpire` instance from a received frame.
+    ///
+    /// The `DEBUG SET-ACTIVE-EXPIRE` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// DEBUG SET-ACTIVE-EXPIRE 0|1
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DebugSetActiveExpire> {
+        let enabled = match parse.next_int()? {
+            0 => false,
+            1 => true,
+            _ => return Err("`DEBUG SET-ACTIVE-EXPIRE` expects 0 or 1".into()),
+        };
+
+        Ok(DebugSetActiveExpire { enabled })
+    }
+
+    /// Apply the `DebugSetActiveExpire` command to every database in `dbs`.
+    ///
+    /// The response is written to `dst`. Like `FlushAll`, this acts on every
+    /// database, so it is called by the connection handler with the full list
+    /// of databases.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        for db in dbs {
+            db.set_active_expire(self.enabled);
+        }
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DebugSetActiveExpire`
+    /// command to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("debug".as_bytes()));
+        frame.push_bulk(Bytes::from("set-active-expire".as_bytes()));
+        frame.push_int(self.enabled as i64);
+        frame
+    }
+}
+
+impl DebugExpireSweep {
+    /// Create a new `DebugExpireSweep` command.
+    pub fn new() -> DebugExpireSweep {
+        DebugExpireSweep {}
+    }
+
+    /// Parse a `DebugExpireSweep` instance from a received frame.
+    ///
+    /// The `DEBUG EXPIRE-SWEEP` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DEBUG EXPIRE-SWEEP
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<DebugExpireSweep> {
+        Ok(DebugExpireSweep {})
+    }
+
+    /// Apply the `DebugExpireSweep` command to every database in `dbs`.
+    ///
+    /// The response is written to `dst`. Like `FlushAll`, this acts on every
+    /// database, so it is called by the connection handler with the full list
+    /// of databases.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        for db in dbs {
+            db.purge_expired();
+        }
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DebugExpireSweep` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("debug".as_bytes()));
+        frame.push_bulk(Bytes::from("expire-sweep".as_bytes()));
+        frame
+    }
+}
+
+impl DebugObject {
+    /// Create a new `DebugObject` command which describes the value stored at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> DebugObject {
+        DebugObject {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `DebugObject` instance from a received frame.
+    ///
+    /// The `DEBUG OBJECT` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// DEBUG OBJECT key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DebugObject> {
+        let key = parse.next_bytes()?;
+
+        Ok(DebugObject { key })
+    }
+
+    /// Apply the `DebugObject` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.describe(&self.key) {
+            Some((value_type, size, expires)) => Frame::Simple(format!(
+                "type:{} length:{} expires:{}",
+                value_type, size, expires
+            )),
+            None => Frame::Error("ERR no such key".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DebugObject` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("debug".as_bytes()));
+        frame.push_bulk(Bytes::from("object".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
@@ -0,0 +1,96 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes the specified keys.
+///
+/// A key is ignored if it does not exist. The number of keys that were removed
+/// is returned to the client.
+#[derive(Debug)]
+pub struct Del {
+    /// Name of the keys to remove
+    keys: Vec<Bytes>,
+}
+
+impl Del {
+    /// Create a new `Del` command which removes `keys`.
+    pub fn new(keys: Vec<Bytes>) -> Del {
+        Del { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse a `Del` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `DEL` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Del` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// DEL key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Del> {
+        use ParseError::EndOfStream;
+
+        // At least one key must be provided.
+        let mut keys = vec![parse.next_bytes()?];
+
+        // The remaining entries are all keys. Read until the frame is fully
+        // consumed.
+        loop {
+            match parse.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Del { keys })
+    }
+
+    /// Apply the `Del` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Count the keys that actually existed
+        let count = self.keys.iter().filter(|key| db.remove(key)).count();
+
+        let response = Frame::Integer(count as i64);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Del` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("del".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        frame
+    }
+}
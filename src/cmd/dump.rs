@@ -0,0 +1,216 @@
+use crate::{serialize, Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Serialize the value stored at `key`.
+///
+/// The reply is an opaque blob holding the value and its remaining time to
+/// live, which `RESTORE` turns back into a key. `nil` is returned if the key
+/// does not exist.
+#[derive(Debug)]
+pub struct Dump {
+    /// Name of the key to serialize
+    key: Bytes,
+}
+
+/// Create `key` from a blob produced by `DUMP`.
+///
+/// If `ttl` is not zero, the key expires after that many milliseconds,
+/// otherwise it keeps the time to live recorded in the blob, if any. A
+/// `BUSYKEY` error is returned if the key already exists, unless `REPLACE` is
+/// given.
+#[derive(Debug)]
+pub struct Restore {
+    /// Name of the key to create
+    key: Bytes,
+
+    /// Time to live of the key, zero to keep the one recorded in the blob
+    ttl: Duration,
+
+    /// Blob produced by `DUMP`
+    serialized: Bytes,
+
+    /// Whether an existing key is overwritten
+    replace: bool,
+}
+
+impl Dump {
+    /// Create a new `Dump` command which serializes the value stored at `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> Dump {
+        Dump {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `Dump` instance from a received frame.
+    ///
+    /// The `DUMP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DUMP key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Dump> {
+        let key = parse.next_bytes()?;
+
+        Ok(Dump { key })
+    }
+
+    /// Apply the `Dump` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.dump_key(&self.key) {
+            Some((value, ttl)) => match serialize::dump(&value, ttl) {
+                Ok(serialized) => Frame::Bulk(serialized),
+                Err(err) => Frame::Error(format!("ERR {}", err)),
+            },
+            None => Frame::Null,
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Dump` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("dump".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl Restore {
+    /// Create a new `Restore` command which creates `key` from `serialized`,
+    /// a blob produced by `DUMP`.
+    ///
+    /// The key expires after `ttl`, or keeps the time to live recorded in the
+    /// blob if `ttl` is zero. An existing key is overwritten only if `replace`
+    /// is set.
+    pub fn new(key: impl AsRef<[u8]>, ttl: Duration, serialized: Bytes, replace: bool) -> Restore {
+        Restore {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            ttl,
+            serialized,
+            replace,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the time to live
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Get the serialized value
+    pub fn serialized(&self) -> &Bytes {
+        &self.serialized
+    }
+
+    /// Get whether an existing key is overwritten
+    pub fn replace(&self) -> bool {
+        self.replace
+    }
+
+    /// Parse a `Restore` instance from a received frame.
+    ///
+    /// The `RESTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or five entries.
+    ///
+    /// ```text
+    /// RESTORE key ttl serialized [REPLACE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Restore> {
+        let key = parse.next_bytes()?;
+        let ttl = Duration::from_millis(parse.next_int()?);
+        let serialized = parse.next_bytes()?;
+
+        let replace = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "REPLACE" => true,
+            Ok(_) => return Err("`RESTORE` only supports the REPLACE option".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Restore {
+            key,
+            ttl,
+            serialized,
+            replace,
+        })
+    }
+
+    /// Apply the `Restore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match serialize::restore(self.serialized) {
+            Ok((value, ttl)) => {
+                let ttl = if self.ttl > Duration::ZERO {
+                    Some(self.ttl)
+                } else {
+                    ttl
+                };
+
+                match db.restore_key(&self.key, value, ttl, self.replace) {
+                    Ok(true) => Frame::Simple("OK".to_string()),
+                    Ok(false) => {
+                        Frame::Error("BUSYKEY Target key name already exists.".to_string())
+                    }
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            }
+            Err(_) => Frame::Error("ERR DUMP payload version or checksum are wrong".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Restore` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("restore".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.ttl.as_millis() as i64);
+        frame.push_bulk(self.serialized);
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        frame
+    }
+}
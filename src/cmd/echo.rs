@@ -0,0 +1,68 @@
+use crate::{Connection, Frame, Parse};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns `message` unchanged.
+///
+/// Like `PING` with an argument, this is mostly useful to check that a
+/// connection works end to end, including binary data.
+#[derive(Debug)]
+pub struct Echo {
+    /// Message to send back
+    msg: Bytes,
+}
+
+impl Echo {
+    /// Create a new `Echo` command which sends back `msg`.
+    pub fn new(msg: Bytes) -> Echo {
+        Echo { msg }
+    }
+
+    /// Get the message
+    pub fn msg(&self) -> &Bytes {
+        &self.msg
+    }
+
+    /// Parse an `Echo` instance from a received frame.
+    ///
+    /// The `ECHO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// ECHO message
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Echo> {
+        let msg = parse.next_bytes()?;
+
+        Ok(Echo { msg })
+    }
+
+    /// Apply the `Echo` command and return the message.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Bulk(self.msg);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Echo` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("echo".as_bytes()));
+        frame.push_bulk(self.msg);
+        frame
+    }
+}
@@ -0,0 +1,135 @@
+use crate::script::Script;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Run a script atomically.
+///
+/// Scripts are not written in Lua, but in the small expression language
+/// described in the `script` module. They can read and write keys, and write
+/// depending on what they read, with no other command applied in between.
+/// The reply is the value of the last expression of the script.
+///
+/// The script is handled by the connection handler, which holds every
+/// database exclusively while it runs, as for `EXEC`.
+#[derive(Debug)]
+pub struct Eval {
+    /// Source of the script
+    script: String,
+
+    /// Names of the keys the script accesses, `KEYS` in the script
+    keys: Vec<Bytes>,
+
+    /// Other arguments, `ARGV` in the script
+    args: Vec<Bytes>,
+}
+
+impl Eval {
+    /// Create a new `Eval` command which runs `script` with `keys` and `args`.
+    pub fn new(script: impl ToString, keys: Vec<Bytes>, args: Vec<Bytes>) -> Eval {
+        Eval {
+            script: script.to_string(),
+            keys,
+            args,
+        }
+    }
+
+    /// Get the source of the script
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Get the arguments
+    pub fn args(&self) -> &[Bytes] {
+        &self.args
+    }
+
+    /// Parse an `Eval` instance from a received frame.
+    ///
+    /// The `EVAL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries. The first
+    /// `numkeys` entries following it are keys, the others arguments.
+    ///
+    /// ```text
+    /// EVAL script numkeys [key [key ...]] [arg [arg ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Eval> {
+        let script = parse.next_string()?;
+        let numkeys = parse.next_signed_int()?;
+
+        let mut args = vec![];
+        loop {
+            match parse.next_bytes() {
+                Ok(arg) => args.push(arg),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let numkeys = match usize::try_from(numkeys) {
+            Ok(numkeys) if numkeys <= args.len() => numkeys,
+            Ok(_) => return Err("Number of keys can't be greater than number of args".into()),
+            Err(_) => return Err("Number of keys can't be negative".into()),
+        };
+
+        let keys = args.drain(..numkeys).collect();
+
+        Ok(Eval { script, keys, args })
+    }
+
+    /// Apply the `Eval` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. The connection handler makes sure no
+    /// other command is applied while the script runs.
+    ///
+    /// Returns whether the script wrote to `db`. Writes are kept when the
+    /// script fails afterwards, so the command must then be logged all the
+    /// same.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<bool> {
+        let (response, wrote) = match Script::parse(&self.script) {
+            Ok(script) => match script.run(db, &self.keys, &self.args) {
+                (Ok(response), wrote) => (response, wrote),
+                (Err(err), wrote) => (Frame::Error(err.to_string()), wrote),
+            },
+            Err(err) => (
+                Frame::Error(format!("ERR Error compiling script: {}", err)),
+                false,
+            ),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(wrote)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Eval` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("eval".as_bytes()));
+        frame.push_bulk(Bytes::from(self.script.into_bytes()));
+        frame.push_int(self.keys.len() as i64);
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        for arg in self.args {
+            frame.push_bulk(arg);
+        }
+        frame
+    }
+}
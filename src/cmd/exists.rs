@@ -0,0 +1,95 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the number of the specified keys that exist.
+///
+/// A key mentioned multiple times is counted multiple times.
+#[derive(Debug)]
+pub struct Exists {
+    /// Name of the keys to check
+    keys: Vec<Bytes>,
+}
+
+impl Exists {
+    /// Create a new `Exists` command which checks `keys`.
+    pub fn new(keys: Vec<Bytes>) -> Exists {
+        Exists { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse an `Exists` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXISTS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Exists` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// EXISTS key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Exists> {
+        use ParseError::EndOfStream;
+
+        // At least one key must be provided.
+        let mut keys = vec![parse.next_bytes()?];
+
+        // The remaining entries are all keys. Read until the frame is fully
+        // consumed.
+        loop {
+            match parse.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Exists { keys })
+    }
+
+    /// Apply the `Exists` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Duplicates are intentionally counted once per occurrence
+        let count = self.keys.iter().filter(|key| db.exists(key)).count();
+
+        let response = Frame::Integer(count as i64);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Exists` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("exists".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        frame
+    }
+}
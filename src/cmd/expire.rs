@@ -0,0 +1,247 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+/// Set a timeout on key. After the timeout has expired, the key will
+/// automatically be deleted.
+///
+/// The timeout replaces any expiration previously associated with the key.
+/// Returns `1` if the timeout was set and `0` if the key does not exist.
+///
+/// # Options
+///
+/// At most one of the following options may be given:
+///
+/// * NX -- Only set the timeout if the key has no expiration.
+/// * XX -- Only set the timeout if the key already has an expiration.
+/// * GT -- Only set the timeout if it is later than the current one. A key
+///   without expiration never expires, so it is never updated.
+/// * LT -- Only set the timeout if it is earlier than the current one. A key
+///   without expiration is always updated.
+///
+/// `0` is returned if the condition is not met.
+#[derive(Debug)]
+pub struct Expire {
+    /// Name of the key to expire
+    key: Bytes,
+
+    /// Time after which the key is removed
+    expire: Duration,
+
+    /// Conditions given with the command. Repeating a condition is allowed,
+    /// giving different ones is reported as an error when the command is
+    /// applied.
+    conditions: Vec<ExpireCondition>,
+}
+
+/// Condition under which `Expire` replaces the expiration of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// Only if the key has no expiration, `NX`.
+    IfNone,
+
+    /// Only if the key already has an expiration, `XX`.
+    IfSome,
+
+    /// Only if the new expiration is later than the current one, `GT`.
+    IfGreater,
+
+    /// Only if the new expiration is earlier than the current one, `LT`.
+    IfLess,
+}
+
+/// Remove the existing timeout on key, turning the key from volatile to
+/// persistent.
+///
+/// Returns `1` if the timeout was removed and `0` if the key does not exist or
+/// does not have an associated timeout.
+#[derive(Debug)]
+pub struct Persist {
+    /// Name of the key to persist
+    key: Bytes,
+}
+
+impl Expire {
+    /// Create a new `Expire` command which removes `key` after `expire`, if
+    /// `condition` is met.
+    pub fn new(
+        key: impl AsRef<[u8]>,
+        expire: Duration,
+        condition: Option<ExpireCondition>,
+    ) -> Expire {
+        Expire {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            expire,
+            conditions: condition.into_iter().collect(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the expire
+    pub fn expire(&self) -> Duration {
+        self.expire
+    }
+
+    /// Get the condition, `None` if there is none or conflicting ones were
+    /// given
+    pub fn condition(&self) -> Option<ExpireCondition> {
+        match self.conditions[..] {
+            [first, ref rest @ ..] if rest.iter().all(|&c| c == first) => Some(first),
+            _ => None,
+        }
+    }
+
+    /// Parse an `Expire` instance from a received frame.
+    ///
+    /// The `EXPIRE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// EXPIRE key seconds [NX|XX|GT|LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Expire> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_bytes()?;
+        let secs = parse.next_int()?;
+
+        let mut conditions = vec![];
+
+        loop {
+            let condition = match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "NX" => ExpireCondition::IfNone,
+                Ok(s) if s.to_uppercase() == "XX" => ExpireCondition::IfSome,
+                Ok(s) if s.to_uppercase() == "GT" => ExpireCondition::IfGreater,
+                Ok(s) if s.to_uppercase() == "LT" => ExpireCondition::IfLess,
+                Ok(_) => return Err("`EXPIRE` only supports the NX, XX, GT and LT options".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            conditions.push(condition);
+        }
+
+        Ok(Expire {
+            key,
+            expire: Duration::from_secs(secs),
+            conditions,
+        })
+    }
+
+    /// Apply the `Expire` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    ///
+    /// A timeout too far in the future to be represented is rejected before
+    /// the key is looked up.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let condition = self.condition();
+
+        let response = if condition.is_none() && !self.conditions.is_empty() {
+            Frame::Error(
+                "ERR NX, XX, GT and LT options at the same time are not compatible".to_string(),
+            )
+        } else {
+            match Instant::now().checked_add(self.expire) {
+                Some(when) => Frame::Integer(db.expire(&self.key, when, condition) as i64),
+                None => Frame::Error("ERR invalid expire time in 'expire' command".to_string()),
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Expire` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("expire".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.expire.as_secs() as i64);
+        for condition in self.conditions {
+            let flag = match condition {
+                ExpireCondition::IfNone => "nx",
+                ExpireCondition::IfSome => "xx",
+                ExpireCondition::IfGreater => "gt",
+                ExpireCondition::IfLess => "lt",
+            };
+            frame.push_bulk(Bytes::from(flag.as_bytes()));
+        }
+        frame
+    }
+}
+
+impl Persist {
+    /// Create a new `Persist` command which removes the timeout on `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> Persist {
+        Persist {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `Persist` instance from a received frame.
+    ///
+    /// The `PERSIST` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// PERSIST key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Persist> {
+        let key = parse.next_bytes()?;
+
+        Ok(Persist { key })
+    }
+
+    /// Apply the `Persist` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.persist(&self.key) as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Persist` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("persist".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
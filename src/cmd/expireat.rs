@@ -0,0 +1,204 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+/// Set the time at which key is automatically deleted, as a Unix time in
+/// seconds.
+///
+/// The time replaces any expiration previously associated with the key. A
+/// time in the past deletes the key right away. Returns `1` if the expiration
+/// was set, or the key deleted, and `0` if the key does not exist.
+#[derive(Debug)]
+pub struct ExpireAt {
+    /// Name of the key to expire
+    key: Bytes,
+
+    /// Unix time at which the key is removed, in seconds
+    timestamp: i64,
+}
+
+/// Set the time at which key is automatically deleted, as a Unix time in
+/// milliseconds.
+///
+/// Behaves as `ExpireAt` otherwise.
+#[derive(Debug)]
+pub struct PExpireAt {
+    /// Name of the key to expire
+    key: Bytes,
+
+    /// Unix time at which the key is removed, in milliseconds
+    timestamp: i64,
+}
+
+impl ExpireAt {
+    /// Create a new `ExpireAt` command which removes `key` at the Unix time
+    /// `timestamp`, in seconds.
+    pub fn new(key: impl AsRef<[u8]>, timestamp: i64) -> ExpireAt {
+        ExpireAt {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            timestamp,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the timestamp, in seconds
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Parse an `ExpireAt` instance from a received frame.
+    ///
+    /// The `EXPIREAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// EXPIREAT key unix-time-seconds
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ExpireAt> {
+        let key = parse.next_bytes()?;
+        let timestamp = parse.next_signed_int()?;
+
+        Ok(ExpireAt { key, timestamp })
+    }
+
+    /// Apply the `ExpireAt` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // As in Redis, the timestamp must be representable in milliseconds.
+        let response = match self.timestamp.checked_mul(1000) {
+            Some(millis) => expire_at(db, &self.key, millis, "expireat"),
+            None => invalid_expire_time("expireat"),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `ExpireAt` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("expireat".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.timestamp);
+        frame
+    }
+}
+
+impl PExpireAt {
+    /// Create a new `PExpireAt` command which removes `key` at the Unix time
+    /// `timestamp`, in milliseconds.
+    pub fn new(key: impl AsRef<[u8]>, timestamp: i64) -> PExpireAt {
+        PExpireAt {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            timestamp,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the timestamp, in milliseconds
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Parse a `PExpireAt` instance from a received frame.
+    ///
+    /// The `PEXPIREAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// PEXPIREAT key unix-time-milliseconds
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PExpireAt> {
+        let key = parse.next_bytes()?;
+        let timestamp = parse.next_signed_int()?;
+
+        Ok(PExpireAt { key, timestamp })
+    }
+
+    /// Apply the `PExpireAt` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = expire_at(db, &self.key, self.timestamp, "pexpireat");
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PExpireAt` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pexpireat".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.timestamp);
+        frame
+    }
+}
+
+/// Expire `key` at the Unix time `millis`, in milliseconds, producing the
+/// reply frame of the command `name`.
+///
+/// Expirations are tracked as `Instant`s, which cannot be built from a wall
+/// clock time. The time left until the timestamp is measured against the wall
+/// clock instead, and expiring after that long is the same up to the time it
+/// takes to apply the command. A timestamp that is already reached, including
+/// a negative one, removes the key right away.
+fn expire_at(db: &Db, key: &[u8], millis: i64, name: &str) -> Frame {
+    let since_epoch = Duration::from_millis(millis.max(0) as u64);
+    let when = match UNIX_EPOCH.checked_add(since_epoch) {
+        Some(when) => when,
+        None => return invalid_expire_time(name),
+    };
+
+    // `duration_since` fails if `when` is earlier than now.
+    match when.duration_since(SystemTime::now()) {
+        Ok(expire) if !expire.is_zero() => {
+            // `Instant` has a narrower range than `SystemTime` on some
+            // platforms.
+            match Instant::now().checked_add(expire) {
+                Some(when) => Frame::Integer(db.expire(key, when, None) as i64),
+                None => invalid_expire_time(name),
+            }
+        }
+        _ => Frame::Integer(db.remove(key) as i64),
+    }
+}
+
+/// The error replied to a timestamp out of range, for the command `name`.
+fn invalid_expire_time(name: &str) -> Frame {
+    Frame::Error(format!("ERR invalid expire time in '{}' command", name))
+}
@@ -0,0 +1,113 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Remove all keys from the selected database.
+///
+/// Pending expirations are cancelled along with the keys. Always replies `OK`.
+#[derive(Debug, Default)]
+pub struct FlushDb {}
+
+/// Remove all keys from every database of the server.
+///
+/// Pending expirations are cancelled along with the keys. Always replies `OK`.
+#[derive(Debug, Default)]
+pub struct FlushAll {}
+
+impl FlushDb {
+    /// Create a new `FlushDb` command.
+    pub fn new() -> FlushDb {
+        FlushDb {}
+    }
+
+    /// Parse a `FlushDb` instance from a received frame.
+    ///
+    /// The `FLUSHDB` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// FLUSHDB
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<FlushDb> {
+        Ok(FlushDb {})
+    }
+
+    /// Apply the `FlushDb` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.flush();
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FlushDb` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("flushdb".as_bytes()));
+        frame
+    }
+}
+
+impl FlushAll {
+    /// Create a new `FlushAll` command.
+    pub fn new() -> FlushAll {
+        FlushAll {}
+    }
+
+    /// Parse a `FlushAll` instance from a received frame.
+    ///
+    /// The `FLUSHALL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// FLUSHALL
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<FlushAll> {
+        Ok(FlushAll {})
+    }
+
+    /// Apply the `FlushAll` command to all of the server's databases.
+    ///
+    /// The response is written to `dst`. Unlike most commands, this acts on
+    /// every database rather than the selected one, so it is called by the
+    /// connection handler with the full list of databases.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        for db in dbs {
+            db.flush();
+        }
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `FlushAll` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("flushall".as_bytes()));
+        frame
+    }
+}
@@ -11,19 +11,19 @@ use tracing::{debug, instrument};
 #[derive(Debug)]
 pub struct Get {
     /// Name of the key to get
-    key: String,
+    key: Bytes,
 }
 
 impl Get {
     /// Create a new `Get` command which fetches `key`.
-    pub fn new(key: impl ToString) -> Get {
+    pub fn new(key: impl AsRef<[u8]>) -> Get {
         Get {
-            key: key.to_string(),
+            key: Bytes::copy_from_slice(key.as_ref()),
         }
     }
 
     /// Get the key
-    pub fn key(&self) -> &str {
+    pub fn key(&self) -> &Bytes {
         &self.key
     }
 
@@ -51,7 +51,7 @@ impl Get {
         // The `GET` string has already been consumed. The next value is the
         // name of the key to get. If the next value is not a string or the
         // input is fully consumed, then an error is returned.
-        let key = parse.next_string()?;
+        let key = parse.next_bytes()?;
 
         Ok(Get { key })
     }
@@ -63,13 +63,14 @@ impl Get {
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
         // Get the value from the shared database state
-        let response = if let Some(value) = db.get(&self.key) {
+        let response = match db.get(&self.key) {
             // If a value is present, it is written to the client in "bulk"
             // format.
-            Frame::Bulk(value)
-        } else {
+            Ok(Some(value)) => Frame::Bulk(value),
             // If there is no value, `Null` is written.
-            Frame::Null
+            Ok(None) => Frame::Null,
+            // The key holds a value that is not a string.
+            Err(err) => Frame::Error(err.to_string()),
         };
 
         debug!(?response);
@@ -87,7 +88,7 @@ impl Get {
     pub(crate) fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
         frame.push_bulk(Bytes::from("get".as_bytes()));
-        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.key);
         frame
     }
 }
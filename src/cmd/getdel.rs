@@ -0,0 +1,76 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Get the value of `key` and delete the key.
+///
+/// If the key does not exist, `nil` is returned. The read and the removal
+/// happen atomically. An error is returned if the key holds a value that is
+/// not a string.
+#[derive(Debug)]
+pub struct GetDel {
+    /// Name of the key to get and delete
+    key: Bytes,
+}
+
+impl GetDel {
+    /// Create a new `GetDel` command which fetches and deletes `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> GetDel {
+        GetDel {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `GetDel` instance from a received frame.
+    ///
+    /// The `GETDEL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// GETDEL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetDel> {
+        let key = parse.next_bytes()?;
+
+        Ok(GetDel { key })
+    }
+
+    /// Apply the `GetDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.getdel(&self.key) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetDel` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getdel".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+/// Get the value of `key`, optionally changing its expiration.
+///
+/// Without options, this behaves exactly like `GET`. If the key does not
+/// exist, `nil` is returned and nothing is changed. The read and the change of
+/// expiration happen atomically. An error is returned if the key holds a value
+/// that is not a string.
+///
+/// # Options
+///
+/// * EX `seconds` -- Expire the key after the specified number of seconds.
+/// * PX `milliseconds` -- Expire the key after the specified number of
+///   milliseconds.
+/// * EXAT `timestamp` -- Expire the key at the specified Unix time, in seconds.
+/// * PXAT `timestamp` -- Expire the key at the specified Unix time, in
+///   milliseconds.
+/// * PERSIST -- Remove any expiration associated with the key.
+#[derive(Debug)]
+pub struct GetEx {
+    /// Name of the key to get
+    key: Bytes,
+
+    /// How to change the expiration of the key, `None` to leave it untouched
+    expiration: Option<GetExExpiration>,
+}
+
+/// How `GetEx` changes the expiration of the key it reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GetExExpiration {
+    /// Expire the key once the duration has elapsed.
+    In(Duration),
+
+    /// Expire the key at the given time.
+    At(SystemTime),
+
+    /// Remove the expiration of the key.
+    Persist,
+}
+
+impl GetEx {
+    /// Create a new `GetEx` command which fetches `key` and changes its
+    /// expiration as directed by `expiration`.
+    pub fn new(key: impl AsRef<[u8]>, expiration: Option<GetExExpiration>) -> GetEx {
+        GetEx {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            expiration,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the expiration change
+    pub fn expiration(&self) -> Option<GetExExpiration> {
+        self.expiration
+    }
+
+    /// Parse a `GetEx` instance from a received frame.
+    ///
+    /// The `GETEX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// GETEX key [EX seconds|PX milliseconds|EXAT timestamp|PXAT timestamp|PERSIST]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetEx> {
+        let key = parse.next_bytes()?;
+
+        let expiration = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "EX" => {
+                Some(GetExExpiration::In(Duration::from_secs(parse.next_int()?)))
+            }
+            Ok(s) if s.to_uppercase() == "PX" => Some(GetExExpiration::In(Duration::from_millis(
+                parse.next_int()?,
+            ))),
+            Ok(s) if s.to_uppercase() == "EXAT" => Some(GetExExpiration::At(timestamp(
+                Duration::from_secs(parse.next_int()?),
+            )?)),
+            Ok(s) if s.to_uppercase() == "PXAT" => Some(GetExExpiration::At(timestamp(
+                Duration::from_millis(parse.next_int()?),
+            )?)),
+            Ok(s) if s.to_uppercase() == "PERSIST" => Some(GetExExpiration::Persist),
+            Ok(_) => {
+                return Err(
+                    "`GETEX` only supports the EX, PX, EXAT, PXAT and PERSIST options".into(),
+                )
+            }
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(GetEx { key, expiration })
+    }
+
+    /// Apply the `GetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    ///
+    /// An expiration too far in the future to be represented is rejected
+    /// before the key is looked up.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // The new expiration, as a time to live, or `None` to remove it.
+        let expire = match self.expiration {
+            None => None,
+            Some(GetExExpiration::In(expire)) if expire > Duration::ZERO => Some(Some(expire)),
+            // A time in the past expires the key right away, as in Redis.
+            Some(GetExExpiration::At(when)) if when > UNIX_EPOCH => Some(Some(
+                when.duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO),
+            )),
+            Some(GetExExpiration::Persist) => Some(None),
+            Some(_) => return invalid_expire_time(dst).await,
+        };
+
+        // The time to live is turned into a deadline, which cannot be
+        // represented for times too far in the future.
+        let expire = match expire {
+            Some(Some(expire)) => match Instant::now().checked_add(expire) {
+                Some(when) => Some(Some(when)),
+                None => return invalid_expire_time(dst).await,
+            },
+            Some(None) => Some(None),
+            None => None,
+        };
+
+        let response = match db.getex(&self.key, expire) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetEx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getex".as_bytes()));
+        frame.push_bulk(self.key);
+        match self.expiration {
+            None => {}
+            // Durations and times are sent in milliseconds, for precision.
+            Some(GetExExpiration::In(expire)) => {
+                frame.push_bulk(Bytes::from("px".as_bytes()));
+                frame.push_int(expire.as_millis() as i64);
+            }
+            Some(GetExExpiration::At(when)) => {
+                let timestamp = when.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+                frame.push_bulk(Bytes::from("pxat".as_bytes()));
+                frame.push_int(timestamp.as_millis() as i64);
+            }
+            Some(GetExExpiration::Persist) => {
+                frame.push_bulk(Bytes::from("persist".as_bytes()));
+            }
+        }
+        frame
+    }
+}
+
+/// Returns the time `since_epoch` after the Unix epoch, rejecting times that
+/// cannot be represented.
+fn timestamp(since_epoch: Duration) -> crate::Result<SystemTime> {
+    UNIX_EPOCH
+        .checked_add(since_epoch)
+        .ok_or_else(|| "invalid expire time in 'getex' command".into())
+}
+
+/// Reply to an expiration that is not positive or too far in the future.
+async fn invalid_expire_time(dst: &mut Connection) -> crate::Result<()> {
+    let response = Frame::Error("ERR invalid expire time in 'getex' command".to_string());
+    debug!(?response);
+    dst.write_frame(&response).await?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Atomically set `key` to `value` and return the value previously stored at
+/// `key`.
+///
+/// If the key did not exist, `nil` is returned and the key is created. Any
+/// time to live associated with the key is discarded. An error is returned if
+/// the key holds a value that is not a string.
+#[derive(Debug)]
+pub struct GetSet {
+    /// the lookup key
+    key: Bytes,
+
+    /// the value to be stored
+    value: Bytes,
+}
+
+impl GetSet {
+    /// Create a new `GetSet` command which sets `key` to `value`.
+    pub fn new(key: impl AsRef<[u8]>, value: Bytes) -> GetSet {
+        GetSet {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `GetSet` instance from a received frame.
+    ///
+    /// The `GETSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// GETSET key value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetSet> {
+        let key = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+
+        Ok(GetSet { key, value })
+    }
+
+    /// Apply the `GetSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.getset(self.key, self.value) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetSet` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getset".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
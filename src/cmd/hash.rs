@@ -0,0 +1,981 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Sets the specified fields to their respective values in the hash stored at
+/// key.
+///
+/// If key does not exist, a new hash is created. Existing fields are
+/// overwritten. The number of fields that were added is returned.
+#[derive(Debug)]
+pub struct HSet {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Field and value pairs to set
+    fields: Vec<(Bytes, Bytes)>,
+}
+
+/// Returns the value associated with field in the hash stored at key.
+///
+/// `nil` is returned if the field or the key does not exist.
+#[derive(Debug)]
+pub struct HGet {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Name of the field to get
+    field: Bytes,
+}
+
+/// Removes the specified fields from the hash stored at key.
+///
+/// Fields that do not exist are ignored. The number of fields that were removed
+/// is returned.
+#[derive(Debug)]
+pub struct HDel {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Name of the fields to remove
+    fields: Vec<Bytes>,
+}
+
+/// Returns all fields and values of the hash stored at key.
+///
+/// The reply is a flat array where every field name is followed by its value.
+#[derive(Debug)]
+pub struct HGetAll {
+    /// Name of the hash
+    key: Bytes,
+}
+
+/// Returns whether field is in the hash stored at key.
+///
+/// `0` is returned if the field or the key does not exist, `1` otherwise.
+#[derive(Debug)]
+pub struct HExists {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Name of the field to look up
+    field: Bytes,
+}
+
+/// Returns all field names of the hash stored at key.
+///
+/// An empty array is returned if the key does not exist.
+#[derive(Debug)]
+pub struct HKeys {
+    /// Name of the hash
+    key: Bytes,
+}
+
+/// Returns all values of the hash stored at key.
+///
+/// An empty array is returned if the key does not exist.
+#[derive(Debug)]
+pub struct HVals {
+    /// Name of the hash
+    key: Bytes,
+}
+
+/// Returns the number of fields of the hash stored at key.
+///
+/// `0` is returned if the key does not exist.
+#[derive(Debug)]
+pub struct HLen {
+    /// Name of the hash
+    key: Bytes,
+}
+
+/// Increments the integer stored in field of the hash stored at key by
+/// increment.
+///
+/// The hash and the field are created if they do not exist, the field
+/// starting at `0`. The new value is returned.
+#[derive(Debug)]
+pub struct HIncrBy {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Name of the field to increment
+    field: Bytes,
+
+    /// Amount to add
+    increment: i64,
+}
+
+/// Increments the floating point number stored in field of the hash stored at
+/// key by increment.
+///
+/// The hash and the field are created if they do not exist, the field
+/// starting at `0`. The new value is returned as a bulk string.
+#[derive(Debug)]
+pub struct HIncrByFloat {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Name of the field to increment
+    field: Bytes,
+
+    /// Amount to add
+    increment: f64,
+}
+
+/// Returns random fields of the hash stored at key.
+///
+/// The count is interpreted as by `SRANDMEMBER`: without it, a single field
+/// is returned, `nil` if the key does not exist. With `WITHVALUES`, each field
+/// is followed by its value.
+#[derive(Debug)]
+pub struct HRandField {
+    /// Name of the hash
+    key: Bytes,
+
+    /// Number of fields to return
+    count: Option<i64>,
+
+    /// Whether the values are returned along with the fields
+    with_values: bool,
+}
+
+impl HSet {
+    /// Create a new `HSet` command which sets `fields` in the hash at `key`.
+    pub fn new(key: impl AsRef<[u8]>, fields: Vec<(Bytes, Bytes)>) -> HSet {
+        HSet {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the field and value pairs
+    pub fn fields(&self) -> &[(Bytes, Bytes)] {
+        &self.fields
+    }
+
+    /// Parse an `HSet` instance from a received frame.
+    ///
+    /// The `HSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the key followed by one or more field
+    /// and value pairs.
+    ///
+    /// ```text
+    /// HSET key field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HSet> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_bytes()?;
+
+        // At least one pair must be provided.
+        let mut fields = vec![(parse.next_bytes()?, parse.next_bytes()?)];
+
+        loop {
+            let field = match parse.next_bytes() {
+                Ok(field) => field,
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            // A field must always be followed by its value.
+            fields.push((field, parse.next_bytes()?));
+        }
+
+        Ok(HSet { key, fields })
+    }
+
+    /// Apply the `HSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hset(&self.key, self.fields) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hset".as_bytes()));
+        frame.push_bulk(self.key);
+        for (field, value) in self.fields {
+            frame.push_bulk(field);
+            frame.push_bulk(value);
+        }
+        frame
+    }
+}
+
+impl HGet {
+    /// Create a new `HGet` command which fetches `field` from the hash at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>, field: impl AsRef<[u8]>) -> HGet {
+        HGet {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            field: Bytes::copy_from_slice(field.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &Bytes {
+        &self.field
+    }
+
+    /// Parse an `HGet` instance from a received frame.
+    ///
+    /// The `HGET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// HGET key field
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGet> {
+        let key = parse.next_bytes()?;
+        let field = parse.next_bytes()?;
+
+        Ok(HGet { key, field })
+    }
+
+    /// Apply the `HGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hget(&self.key, &self.field) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HGet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hget".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.field);
+        frame
+    }
+}
+
+impl HDel {
+    /// Create a new `HDel` command which removes `fields` from the hash at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>, fields: Vec<Bytes>) -> HDel {
+        HDel {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            fields,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the fields
+    pub fn fields(&self) -> &[Bytes] {
+        &self.fields
+    }
+
+    /// Parse an `HDel` instance from a received frame.
+    ///
+    /// The `HDEL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// HDEL key field [field ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HDel> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_bytes()?;
+
+        // At least one field must be provided.
+        let mut fields = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(field) => fields.push(field),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HDel { key, fields })
+    }
+
+    /// Apply the `HDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hdel(&self.key, &self.fields) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HDel` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hdel".as_bytes()));
+        frame.push_bulk(self.key);
+        for field in self.fields {
+            frame.push_bulk(field);
+        }
+        frame
+    }
+}
+
+impl HGetAll {
+    /// Create a new `HGetAll` command which fetches the hash at `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> HGetAll {
+        HGetAll {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `HGetAll` instance from a received frame.
+    ///
+    /// The `HGETALL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HGETALL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGetAll> {
+        let key = parse.next_bytes()?;
+
+        Ok(HGetAll { key })
+    }
+
+    /// Apply the `HGetAll` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hgetall(&self.key) {
+            Ok(fields) => {
+                let mut response = Frame::array();
+                for (field, value) in fields {
+                    response.push_bulk(field);
+                    response.push_bulk(value);
+                }
+                response
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HGetAll` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hgetall".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl HExists {
+    /// Create a new `HExists` command which looks up `field` in the hash at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>, field: impl AsRef<[u8]>) -> HExists {
+        HExists {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            field: Bytes::copy_from_slice(field.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &Bytes {
+        &self.field
+    }
+
+    /// Parse an `HExists` instance from a received frame.
+    ///
+    /// The `HEXISTS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// HEXISTS key field
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HExists> {
+        let key = parse.next_bytes()?;
+        let field = parse.next_bytes()?;
+
+        Ok(HExists { key, field })
+    }
+
+    /// Apply the `HExists` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hexists(&self.key, &self.field) {
+            Ok(found) => Frame::Integer(found as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HExists` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hexists".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.field);
+        frame
+    }
+}
+
+impl HKeys {
+    /// Create a new `HKeys` command which fetches the fields of the hash at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> HKeys {
+        HKeys {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `HKeys` instance from a received frame.
+    ///
+    /// The `HKEYS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HKEYS key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HKeys> {
+        let key = parse.next_bytes()?;
+
+        Ok(HKeys { key })
+    }
+
+    /// Apply the `HKeys` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hkeys(&self.key) {
+            Ok(fields) => Frame::Array(fields.into_iter().map(Frame::Bulk).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HKeys` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hkeys".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl HVals {
+    /// Create a new `HVals` command which fetches the values of the hash at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> HVals {
+        HVals {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `HVals` instance from a received frame.
+    ///
+    /// The `HVALS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HVALS key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HVals> {
+        let key = parse.next_bytes()?;
+
+        Ok(HVals { key })
+    }
+
+    /// Apply the `HVals` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hvals(&self.key) {
+            Ok(values) => Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HVals` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hvals".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl HLen {
+    /// Create a new `HLen` command which counts the fields of the hash at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> HLen {
+        HLen {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `HLen` instance from a received frame.
+    ///
+    /// The `HLEN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HLen> {
+        let key = parse.next_bytes()?;
+
+        Ok(HLen { key })
+    }
+
+    /// Apply the `HLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hlen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HLen` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hlen".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl HIncrBy {
+    /// Create a new `HIncrBy` command which increments `field` of the hash at
+    /// `key` by `increment`.
+    pub fn new(key: impl AsRef<[u8]>, field: impl AsRef<[u8]>, increment: i64) -> HIncrBy {
+        HIncrBy {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            field: Bytes::copy_from_slice(field.as_ref()),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &Bytes {
+        &self.field
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> i64 {
+        self.increment
+    }
+
+    /// Parse an `HIncrBy` instance from a received frame.
+    ///
+    /// The `HINCRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// HINCRBY key field increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HIncrBy> {
+        let key = parse.next_bytes()?;
+        let field = parse.next_bytes()?;
+        let increment = parse.next_signed_int()?;
+
+        Ok(HIncrBy {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    /// Apply the `HIncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hincr_by(&self.key, &self.field, self.increment) {
+            Ok(value) => Frame::Integer(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HIncrBy` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hincrby".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.field);
+        frame.push_int(self.increment);
+        frame
+    }
+}
+
+impl HIncrByFloat {
+    /// Create a new `HIncrByFloat` command which increments `field` of the
+    /// hash at `key` by `increment`.
+    pub fn new(key: impl AsRef<[u8]>, field: impl AsRef<[u8]>, increment: f64) -> HIncrByFloat {
+        HIncrByFloat {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            field: Bytes::copy_from_slice(field.as_ref()),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the field
+    pub fn field(&self) -> &Bytes {
+        &self.field
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    /// Parse an `HIncrByFloat` instance from a received frame.
+    ///
+    /// The `HINCRBYFLOAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// HINCRBYFLOAT key field increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HIncrByFloat> {
+        let key = parse.next_bytes()?;
+        let field = parse.next_bytes()?;
+        let increment = parse
+            .next_string()?
+            .parse::<f64>()
+            .map_err(|_| "protocol error; invalid float")?;
+
+        Ok(HIncrByFloat {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    /// Apply the `HIncrByFloat` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hincr_by_float(&self.key, &self.field, self.increment) {
+            Ok(value) => Frame::Bulk(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HIncrByFloat` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hincrbyfloat".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.field);
+        frame.push_bulk(Bytes::from(self.increment.to_string()));
+        frame
+    }
+}
+
+impl HRandField {
+    /// Create a new `HRandField` command which picks `count` fields of the
+    /// hash at `key`, a single one if `count` is `None`. Values are only
+    /// returned along with the fields if `with_values` is set and a `count`
+    /// is given.
+    pub fn new(key: impl AsRef<[u8]>, count: Option<i64>, with_values: bool) -> HRandField {
+        HRandField {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            count,
+            with_values: with_values && count.is_some(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the number of fields to return
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Returns `true` if the values are returned along with the fields
+    pub fn with_values(&self) -> bool {
+        self.with_values
+    }
+
+    /// Parse an `HRandField` instance from a received frame.
+    ///
+    /// The `HRANDFIELD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// HRANDFIELD key [count [WITHVALUES]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HRandField> {
+        let key = parse.next_bytes()?;
+
+        let count = match parse.next_signed_int() {
+            Ok(count) => Some(count),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let with_values = match count.map(|_| parse.next_string()) {
+            Some(Ok(s)) if s.to_uppercase() == "WITHVALUES" => true,
+            Some(Ok(_)) => return Err("`HRANDFIELD` only supports the WITHVALUES option".into()),
+            Some(Err(ParseError::EndOfStream)) | None => false,
+            Some(Err(err)) => return Err(err.into()),
+        };
+
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
+    }
+
+    /// Apply the `HRandField` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match (
+            db.hrandfield(&self.key, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(mut fields), None) => match fields.pop() {
+                Some((field, _)) => Frame::Bulk(field),
+                None => Frame::Null,
+            },
+            (Ok(fields), Some(_)) => {
+                let mut response = Frame::array();
+                for (field, value) in fields {
+                    response.push_bulk(field);
+                    if self.with_values {
+                        response.push_bulk(value);
+                    }
+                }
+                response
+            }
+            (Err(err), _) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `HRandField` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hrandfield".as_bytes()));
+        frame.push_bulk(self.key);
+        if let Some(count) = self.count {
+            frame.push_int(count);
+        }
+        if self.with_values {
+            frame.push_bulk(Bytes::from("withvalues".as_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Switch to a different protocol version and return information about the
+/// server.
+///
+/// Without an argument, the reply describes the server using the protocol
+/// currently in use. With `protover`, the connection switches to RESP2 or RESP3
+/// before replying. The reply is a map frame, which RESP2 peers receive as a
+/// flat array of alternating keys and values.
+#[derive(Debug, Default)]
+pub struct Hello {
+    /// Protocol version requested by the client
+    protover: Option<u64>,
+}
+
+impl Hello {
+    /// Create a new `Hello` command requesting protocol version `protover`.
+    pub fn new(protover: Option<u64>) -> Hello {
+        Hello { protover }
+    }
+
+    /// Get the requested protocol version
+    pub fn protover(&self) -> Option<u64> {
+        self.protover
+    }
+
+    /// Parse a `Hello` instance from a received frame.
+    ///
+    /// The `HELLO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `HELLO` and an optional protocol
+    /// version.
+    ///
+    /// ```text
+    /// HELLO [protover]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hello> {
+        match parse.next_int() {
+            Ok(protover) => Ok(Hello::new(Some(protover))),
+            Err(ParseError::EndOfStream) => Ok(Hello::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply the `Hello` command to the connection.
+    ///
+    /// The protocol is switched before the reply is written, so the reply is
+    /// already encoded using the negotiated protocol.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.protover {
+            Some(protover @ 2..=3) => {
+                dst.set_protocol(protover as u8);
+                server_info(dst.protocol())
+            }
+            Some(_) => Frame::Error("NOPROTO unsupported protocol version".to_string()),
+            None => server_info(dst.protocol()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Hello` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hello".as_bytes()));
+        if let Some(protover) = self.protover {
+            frame.push_int(protover as i64);
+        }
+        frame
+    }
+}
+
+/// Build the map describing the server, as sent in reply to `HELLO`.
+fn server_info(protocol: u8) -> Frame {
+    let field = |name: &'static str| Frame::Bulk(Bytes::from_static(name.as_bytes()));
+
+    Frame::Map(vec![
+        (field("server"), field("mini-redis")),
+        (field("version"), field(env!("CARGO_PKG_VERSION"))),
+        (field("proto"), Frame::Integer(protocol as i64)),
+        (field("mode"), field("standalone")),
+        (field("role"), field("master")),
+    ])
+}
@@ -0,0 +1,416 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increments the number stored at key by one.
+///
+/// If the key does not exist, it is set to 0 before performing the operation.
+/// An error is returned if the key contains a value that can not be
+/// represented as a 64 bit signed integer.
+#[derive(Debug)]
+pub struct Incr {
+    /// Name of the key to increment
+    key: Bytes,
+}
+
+/// Decrements the number stored at key by one.
+///
+/// If the key does not exist, it is set to 0 before performing the operation.
+/// An error is returned if the key contains a value that can not be
+/// represented as a 64 bit signed integer.
+#[derive(Debug)]
+pub struct Decr {
+    /// Name of the key to decrement
+    key: Bytes,
+}
+
+/// Increments the number stored at key by `increment`.
+///
+/// If the key does not exist, it is set to 0 before performing the operation.
+/// An error is returned if the key contains a value that can not be
+/// represented as a 64 bit signed integer, or if the result overflows.
+#[derive(Debug)]
+pub struct IncrBy {
+    /// Name of the key to increment
+    key: Bytes,
+
+    /// Amount to add to the stored value
+    increment: i64,
+}
+
+/// Decrements the number stored at key by `decrement`.
+///
+/// If the key does not exist, it is set to 0 before performing the operation.
+/// An error is returned if the key contains a value that can not be
+/// represented as a 64 bit signed integer, or if the result overflows.
+#[derive(Debug)]
+pub struct DecrBy {
+    /// Name of the key to decrement
+    key: Bytes,
+
+    /// Amount to subtract from the stored value
+    decrement: i64,
+}
+
+/// Increments the floating point number stored at key by `increment`.
+///
+/// If the key does not exist, it is set to 0 before performing the operation.
+/// The new value is returned, and stored, as a string without an exponent or
+/// trailing zeros. An error is returned if the key contains a value that can
+/// not be parsed as a floating point number, or if the result is not finite.
+#[derive(Debug)]
+pub struct IncrByFloat {
+    /// Name of the key to increment
+    key: Bytes,
+
+    /// Amount to add to the stored value
+    increment: f64,
+}
+
+impl Incr {
+    /// Create a new `Incr` command which increments `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> Incr {
+        Incr {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `Incr` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INCR` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Incr` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// INCR key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Incr> {
+        let key = parse.next_bytes()?;
+
+        Ok(Incr { key })
+    }
+
+    /// Apply the `Incr` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = incr_by_response(db, &self.key, 1);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Incr` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("incr".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl Decr {
+    /// Create a new `Decr` command which decrements `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> Decr {
+        Decr {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `Decr` instance from a received frame.
+    ///
+    /// The `DECR` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DECR key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Decr> {
+        let key = parse.next_bytes()?;
+
+        Ok(Decr { key })
+    }
+
+    /// Apply the `Decr` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = incr_by_response(db, &self.key, -1);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Decr` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("decr".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl IncrBy {
+    /// Create a new `IncrBy` command which increments `key` by `increment`.
+    pub fn new(key: impl AsRef<[u8]>, increment: i64) -> IncrBy {
+        IncrBy {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> i64 {
+        self.increment
+    }
+
+    /// Parse an `IncrBy` instance from a received frame.
+    ///
+    /// The `INCRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBY key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrBy> {
+        let (key, increment) = parse_key_and_amount(parse)?;
+
+        Ok(IncrBy { key, increment })
+    }
+
+    /// Apply the `IncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = incr_by_response(db, &self.key, self.increment);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `IncrBy` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("incrby".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.increment);
+        frame
+    }
+}
+
+impl DecrBy {
+    /// Create a new `DecrBy` command which decrements `key` by `decrement`.
+    pub fn new(key: impl AsRef<[u8]>, decrement: i64) -> DecrBy {
+        DecrBy {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            decrement,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the decrement
+    pub fn decrement(&self) -> i64 {
+        self.decrement
+    }
+
+    /// Parse a `DecrBy` instance from a received frame.
+    ///
+    /// The `DECRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// DECRBY key decrement
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DecrBy> {
+        let (key, decrement) = parse_key_and_amount(parse)?;
+
+        Ok(DecrBy { key, decrement })
+    }
+
+    /// Apply the `DecrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // `i64::MIN` has no positive counterpart.
+        let response = match self.decrement.checked_neg() {
+            Some(delta) => incr_by_response(db, &self.key, delta),
+            None => Frame::Error("ERR decrement would overflow".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `DecrBy` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("decrby".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.decrement);
+        frame
+    }
+}
+
+impl IncrByFloat {
+    /// Create a new `IncrByFloat` command which increments `key` by
+    /// `increment`.
+    pub fn new(key: impl AsRef<[u8]>, increment: f64) -> IncrByFloat {
+        IncrByFloat {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    /// Parse an `IncrByFloat` instance from a received frame.
+    ///
+    /// The `INCRBYFLOAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBYFLOAT key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrByFloat> {
+        let key = parse.next_bytes()?;
+        let increment = parse
+            .next_string()?
+            .parse::<f64>()
+            .map_err(|_| "protocol error; invalid float")?;
+
+        Ok(IncrByFloat { key, increment })
+    }
+
+    /// Apply the `IncrByFloat` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.incr_by_float(&self.key, self.increment) {
+            Ok(value) => Frame::Bulk(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `IncrByFloat` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("incrbyfloat".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(Bytes::from(self.increment.to_string()));
+        frame
+    }
+}
+
+/// Parse the key and integer amount of `INCRBY` and `DECRBY`.
+fn parse_key_and_amount(parse: &mut Parse) -> crate::Result<(Bytes, i64)> {
+    let key = parse.next_bytes()?;
+    let amount = parse.next_signed_int()?;
+
+    Ok((key, amount))
+}
+
+/// Apply `delta` to the integer stored at `key`, producing the reply frame.
+///
+/// The new value is returned as an integer frame. If the stored value is not
+/// an integer, the error is reported to the client instead of terminating the
+/// connection.
+fn incr_by_response(db: &Db, key: &[u8], delta: i64) -> Frame {
+    match db.incr_by(key, delta) {
+        Ok(value) => Frame::Integer(value),
+        Err(err) => Frame::Error(err.to_string()),
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::fmt::{self, Write};
+use std::process;
+use tracing::{debug, instrument};
+
+/// Sections reported by `Info`, in order.
+const SECTIONS: &[&str] = &["server", "clients", "stats", "commandstats", "keyspace"];
+
+/// Returns information and statistics about the server.
+///
+/// The reply is a bulk string made of `field:value` lines, grouped in sections
+/// each starting with a `# Section` header line. The `Server`, `Clients`,
+/// `Stats` and `Keyspace` sections are reported by default, as is every section
+/// but `Commandstats` with `default`, and every section with `all`. If another
+/// section is given, only that section is reported.
+#[derive(Debug, Default)]
+pub struct Info {
+    /// Name of the section to report, all sections if `None`
+    section: Option<String>,
+}
+
+impl Info {
+    /// Create a new `Info` command which reports `section`, or every section
+    /// if `None`.
+    pub fn new(section: Option<String>) -> Info {
+        Info { section }
+    }
+
+    /// Get the section
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    /// Parse an `Info` instance from a received frame.
+    ///
+    /// The `INFO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// INFO [section]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        match parse.next_string() {
+            Ok(section) => Ok(Info::new(Some(section))),
+            Err(ParseError::EndOfStream) => Ok(Info::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply the `Info` command to the databases of the server, indexed by
+    /// database number.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command. An unknown section results in an empty
+    /// reply.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        let section = self.section.map(|section| section.to_lowercase());
+
+        let mut info = String::new();
+
+        for &name in SECTIONS {
+            let selected = match section.as_deref() {
+                None | Some("default") => name != "commandstats",
+                Some("all") | Some("everything") => true,
+                Some(section) => section == name,
+            };
+
+            if !selected {
+                continue;
+            }
+
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+
+            write_section(&mut info, name, dbs)?;
+        }
+
+        let response = Frame::Bulk(Bytes::from(info));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Info` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("info".as_bytes()));
+        if let Some(section) = self.section {
+            frame.push_bulk(Bytes::from(section.into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Write the header and fields of section `name` to `dst`.
+fn write_section(dst: &mut String, name: &str, dbs: &[Db]) -> fmt::Result {
+    // Statistics are shared by all databases, and there is always at least
+    // one.
+    let stats = dbs[0].stats();
+
+    match name {
+        "server" => {
+            dst.push_str("# Server\r\n");
+            write_field(dst, "mini_redis_version", env!("CARGO_PKG_VERSION"))?;
+            write_field(dst, "process_id", process::id())?;
+            write_field(dst, "uptime_in_seconds", stats.uptime().as_secs())?;
+        }
+        "clients" => {
+            dst.push_str("# Clients\r\n");
+            write_field(dst, "connected_clients", stats.connected_clients())?;
+        }
+        "stats" => {
+            dst.push_str("# Stats\r\n");
+            write_field(
+                dst,
+                "total_connections_received",
+                stats.connections_received(),
+            )?;
+            write_field(dst, "total_commands_processed", stats.commands_processed())?;
+            write_field(dst, "expired_keys", stats.expired_keys())?;
+            write_field(dst, "reaped_keys", stats.reaped_keys())?;
+        }
+        "commandstats" => {
+            dst.push_str("# Commandstats\r\n");
+
+            for (command, calls) in stats.commands() {
+                write_field(
+                    dst,
+                    &format!("cmdstat_{}", command),
+                    format!("calls={}", calls),
+                )?;
+            }
+        }
+        "keyspace" => {
+            dst.push_str("# Keyspace\r\n");
+
+            // Empty databases are omitted, as in Redis. The number of keys of
+            // each type follows the fields Redis reports.
+            for (index, db) in dbs.iter().enumerate() {
+                let (types, expires) = db.keyspace_counts();
+                let keys: u64 = types.iter().map(|(_, count)| count).sum();
+
+                if keys > 0 {
+                    let mut value = format!("keys={},expires={}", keys, expires);
+                    for (type_name, count) in types {
+                        write!(value, ",{}={}", type_name, count)?;
+                    }
+                    write_field(dst, &format!("db{}", index), value)?;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn write_field(dst: &mut String, name: &str, value: impl fmt::Display) -> fmt::Result {
+    write!(dst, "{}:{}\r\n", name, value)
+}
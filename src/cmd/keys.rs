@@ -0,0 +1,78 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns all keys matching `pattern`.
+///
+/// The pattern is a glob-style pattern, as accepted by `PSUBSCRIBE`. Keys are
+/// returned in no particular order.
+///
+/// Every key of the database is visited, so this takes time proportional to
+/// the number of keys, during which no other command can access the database.
+/// It is intended for debugging, not for regular use on large databases.
+#[derive(Debug)]
+pub struct Keys {
+    /// Pattern the returned keys match
+    pattern: Bytes,
+}
+
+impl Keys {
+    /// Create a new `Keys` command which returns the keys matching `pattern`.
+    pub fn new(pattern: impl AsRef<[u8]>) -> Keys {
+        Keys {
+            pattern: Bytes::copy_from_slice(pattern.as_ref()),
+        }
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> &Bytes {
+        &self.pattern
+    }
+
+    /// Parse a `Keys` instance from a received frame.
+    ///
+    /// The `KEYS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// KEYS pattern
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Keys> {
+        let pattern = parse.next_bytes()?;
+
+        Ok(Keys { pattern })
+    }
+
+    /// Apply the `Keys` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        for key in db.keys_matching(&self.pattern) {
+            response.push_bulk(key);
+        }
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Keys` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("keys".as_bytes()));
+        frame.push_bulk(self.pattern);
+        frame
+    }
+}
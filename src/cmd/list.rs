@@ -0,0 +1,1342 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Insert all the specified values at the head of the list stored at key.
+///
+/// If key does not exist, it is created as an empty list before performing the
+/// push. Elements are inserted one after the other, so `LPUSH list a b c`
+/// results in a list containing `c`, `b`, `a`. The length of the list after the
+/// push is returned.
+#[derive(Debug)]
+pub struct LPush {
+    /// Name of the list
+    key: Bytes,
+
+    /// Values to push
+    values: Vec<Bytes>,
+}
+
+/// Insert all the specified values at the tail of the list stored at key.
+///
+/// If key does not exist, it is created as an empty list before performing the
+/// push. The length of the list after the push is returned.
+#[derive(Debug)]
+pub struct RPush {
+    /// Name of the list
+    key: Bytes,
+
+    /// Values to push
+    values: Vec<Bytes>,
+}
+
+/// Insert all the specified values at the head of the list stored at key, only
+/// if the key already holds a list.
+///
+/// Unlike `LPUSH`, a missing key is not created, `0` is returned instead. The
+/// length of the list after the push is returned.
+#[derive(Debug)]
+pub struct LPushX {
+    /// Name of the list
+    key: Bytes,
+
+    /// Values to push
+    values: Vec<Bytes>,
+}
+
+/// Insert all the specified values at the tail of the list stored at key, only
+/// if the key already holds a list.
+///
+/// Unlike `RPUSH`, a missing key is not created, `0` is returned instead. The
+/// length of the list after the push is returned.
+#[derive(Debug)]
+pub struct RPushX {
+    /// Name of the list
+    key: Bytes,
+
+    /// Values to push
+    values: Vec<Bytes>,
+}
+
+/// Removes and returns the first element of the list stored at key.
+///
+/// `nil` is returned if the key does not exist.
+#[derive(Debug)]
+pub struct LPop {
+    /// Name of the list
+    key: Bytes,
+}
+
+/// Removes and returns the last element of the list stored at key.
+///
+/// `nil` is returned if the key does not exist.
+#[derive(Debug)]
+pub struct RPop {
+    /// Name of the list
+    key: Bytes,
+}
+
+/// Removes and returns the first element of the first non-empty list among
+/// the specified keys, blocking until one is available.
+///
+/// The keys are checked in order. If all of them are empty, the connection
+/// waits until an element is pushed to one of them or `timeout` elapses, in
+/// which case `nil` is returned. A zero `timeout` waits indefinitely. On
+/// success, the key the element was popped from is returned along with the
+/// element. When several connections wait on the same key, each pushed element
+/// is handed to only one of them.
+#[derive(Debug)]
+pub struct BLPop {
+    /// Name of the lists
+    keys: Vec<Bytes>,
+
+    /// How long to wait for an element, zero to wait indefinitely
+    timeout: Duration,
+}
+
+/// Removes and returns the last element of the first non-empty list among the
+/// specified keys, blocking until one is available.
+///
+/// This is the counterpart of `BLPop` popping from the tail of the lists.
+#[derive(Debug)]
+pub struct BRPop {
+    /// Name of the lists
+    keys: Vec<Bytes>,
+
+    /// How long to wait for an element, zero to wait indefinitely
+    timeout: Duration,
+}
+
+/// Returns the index of the elements of the list stored at `key` equal to
+/// `element`.
+///
+/// `RANK` skips matches: `2` returns the second match, `-1` the last one,
+/// negative ranks searching from the tail. Without `COUNT`, the index of the
+/// first match is returned, or `nil` if there is none. With `COUNT`, an array
+/// of up to `count` indices is returned, `0` returning all matches.
+#[derive(Debug)]
+pub struct LPos {
+    /// Name of the list
+    key: Bytes,
+
+    /// Value of the elements to find
+    element: Bytes,
+
+    /// Rank of the first match to return
+    rank: Option<i64>,
+
+    /// Number of matches to return
+    count: Option<u64>,
+}
+
+/// Returns the specified elements of the list stored at key.
+///
+/// `start` and `stop` are zero-based inclusive indices. They can also be
+/// negative numbers indicating offsets from the end of the list, `-1` being the
+/// last element.
+#[derive(Debug)]
+pub struct LRange {
+    /// Name of the list
+    key: Bytes,
+
+    /// Index of the first element to return
+    start: i64,
+
+    /// Index of the last element to return
+    stop: i64,
+}
+
+/// Removes and returns up to count elements from the first non-empty list
+/// among the specified keys.
+///
+/// The keys are checked in order and elements are popped from the end given
+/// by `LEFT` or `RIGHT`, one by default. The key the elements were popped from
+/// is returned along with the elements, or `nil` if every list is empty.
+#[derive(Debug)]
+pub struct LMPop {
+    /// Name of the lists
+    keys: Vec<Bytes>,
+
+    /// End of the list to pop from
+    end: ListEnd,
+
+    /// Maximum number of elements to pop
+    count: Option<u64>,
+}
+
+/// Returns the length of the list stored at key.
+///
+/// `0` is returned if the key does not exist.
+#[derive(Debug)]
+pub struct LLen {
+    /// Name of the list
+    key: Bytes,
+}
+
+/// Sets the element at index of the list stored at key.
+///
+/// Negative indices count from the end of the list, `-1` being the last
+/// element. An error is returned if the key does not exist or the index is out
+/// of range.
+#[derive(Debug)]
+pub struct LSet {
+    /// Name of the list
+    key: Bytes,
+
+    /// Index of the element to set
+    index: i64,
+
+    /// Value to set the element to
+    value: Bytes,
+}
+
+/// Inserts value in the list stored at key either before or after the first
+/// element equal to pivot.
+///
+/// The length of the list after the insertion is returned, `0` if the key does
+/// not exist and `-1` if pivot is not found.
+#[derive(Debug)]
+pub struct LInsert {
+    /// Name of the list
+    key: Bytes,
+
+    /// Whether the value is inserted before or after the pivot
+    position: InsertPosition,
+
+    /// Value of the element to insert next to
+    pivot: Bytes,
+
+    /// Value to insert
+    value: Bytes,
+}
+
+/// End of a list that push and pop operations act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    /// The head of the list, `LEFT`.
+    Left,
+
+    /// The tail of the list, `RIGHT`.
+    Right,
+}
+
+/// Side of the pivot `LINSERT` inserts the value on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    /// Right before the pivot, `BEFORE`.
+    Before,
+
+    /// Right after the pivot, `AFTER`.
+    After,
+}
+
+impl LPush {
+    /// Create a new `LPush` command which pushes `values` to the head of the
+    /// list at `key`.
+    pub fn new(key: impl AsRef<[u8]>, values: Vec<Bytes>) -> LPush {
+        LPush {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the values
+    pub fn values(&self) -> &[Bytes] {
+        &self.values
+    }
+
+    /// Parse an `LPush` instance from a received frame.
+    ///
+    /// The `LPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LPUSH key element [element ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPush> {
+        let (key, values) = parse_push(parse)?;
+
+        Ok(LPush { key, values })
+    }
+
+    /// Apply the `LPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = push_response(db, &self.key, self.values, ListEnd::Left, true);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LPush` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("lpush", self.key, self.values)
+    }
+}
+
+impl RPush {
+    /// Create a new `RPush` command which pushes `values` to the tail of the
+    /// list at `key`.
+    pub fn new(key: impl AsRef<[u8]>, values: Vec<Bytes>) -> RPush {
+        RPush {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the values
+    pub fn values(&self) -> &[Bytes] {
+        &self.values
+    }
+
+    /// Parse an `RPush` instance from a received frame.
+    ///
+    /// The `RPUSH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// RPUSH key element [element ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPush> {
+        let (key, values) = parse_push(parse)?;
+
+        Ok(RPush { key, values })
+    }
+
+    /// Apply the `RPush` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = push_response(db, &self.key, self.values, ListEnd::Right, true);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `RPush` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("rpush", self.key, self.values)
+    }
+}
+
+impl LPushX {
+    /// Create a new `LPushX` command which pushes `values` to the head of the
+    /// list at `key`, if it exists.
+    pub fn new(key: impl AsRef<[u8]>, values: Vec<Bytes>) -> LPushX {
+        LPushX {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the values
+    pub fn values(&self) -> &[Bytes] {
+        &self.values
+    }
+
+    /// Parse an `LPushX` instance from a received frame.
+    ///
+    /// The `LPUSHX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LPUSHX key element [element ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPushX> {
+        let (key, values) = parse_push(parse)?;
+
+        Ok(LPushX { key, values })
+    }
+
+    /// Apply the `LPushX` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = push_response(db, &self.key, self.values, ListEnd::Left, false);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LPushX` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("lpushx", self.key, self.values)
+    }
+}
+
+impl RPushX {
+    /// Create a new `RPushX` command which pushes `values` to the tail of the
+    /// list at `key`, if it exists.
+    pub fn new(key: impl AsRef<[u8]>, values: Vec<Bytes>) -> RPushX {
+        RPushX {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            values,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the values
+    pub fn values(&self) -> &[Bytes] {
+        &self.values
+    }
+
+    /// Parse an `RPushX` instance from a received frame.
+    ///
+    /// The `RPUSHX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// RPUSHX key element [element ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPushX> {
+        let (key, values) = parse_push(parse)?;
+
+        Ok(RPushX { key, values })
+    }
+
+    /// Apply the `RPushX` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = push_response(db, &self.key, self.values, ListEnd::Right, false);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `RPushX` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        push_frame("rpushx", self.key, self.values)
+    }
+}
+
+impl LPop {
+    /// Create a new `LPop` command which pops the head of the list at `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> LPop {
+        LPop {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `LPop` instance from a received frame.
+    ///
+    /// The `LPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// LPOP key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPop> {
+        let key = parse.next_bytes()?;
+
+        Ok(LPop { key })
+    }
+
+    /// Apply the `LPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = pop_response(db, &self.key, ListEnd::Left);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lpop".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl RPop {
+    /// Create a new `RPop` command which pops the tail of the list at `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> RPop {
+        RPop {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `RPop` instance from a received frame.
+    ///
+    /// The `RPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// RPOP key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RPop> {
+        let key = parse.next_bytes()?;
+
+        Ok(RPop { key })
+    }
+
+    /// Apply the `RPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = pop_response(db, &self.key, ListEnd::Right);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `RPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("rpop".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl BLPop {
+    /// Create a new `BLPop` command which pops the head of the first
+    /// non-empty list among `keys`, waiting up to `timeout`.
+    pub fn new(keys: Vec<Bytes>, timeout: Duration) -> BLPop {
+        BLPop { keys, timeout }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BLPop` instance from a received frame.
+    ///
+    /// The `BLPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries. The timeout,
+    /// in seconds, may have a fractional part.
+    ///
+    /// ```text
+    /// BLPOP key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BLPop> {
+        let (keys, timeout) = parse_blocking_pop(parse)?;
+
+        Ok(BLPop { keys, timeout })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BLPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        blocking_pop_frame("blpop", self.keys, self.timeout)
+    }
+}
+
+impl BRPop {
+    /// Create a new `BRPop` command which pops the tail of the first
+    /// non-empty list among `keys`, waiting up to `timeout`.
+    pub fn new(keys: Vec<Bytes>, timeout: Duration) -> BRPop {
+        BRPop { keys, timeout }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `BRPop` instance from a received frame.
+    ///
+    /// The `BRPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries. The timeout,
+    /// in seconds, may have a fractional part.
+    ///
+    /// ```text
+    /// BRPOP key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BRPop> {
+        let (keys, timeout) = parse_blocking_pop(parse)?;
+
+        Ok(BRPop { keys, timeout })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `BRPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        blocking_pop_frame("brpop", self.keys, self.timeout)
+    }
+}
+
+impl LRange {
+    /// Create a new `LRange` command which returns the elements of the list at
+    /// `key` between `start` and `stop`, inclusive.
+    pub fn new(key: impl AsRef<[u8]>, start: i64, stop: i64) -> LRange {
+        LRange {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            start,
+            stop,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the start index
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the stop index
+    pub fn stop(&self) -> i64 {
+        self.stop
+    }
+
+    /// Parse an `LRange` instance from a received frame.
+    ///
+    /// The `LRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LRANGE key start stop
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LRange> {
+        let key = parse.next_bytes()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        Ok(LRange { key, start, stop })
+    }
+
+    /// Apply the `LRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lrange(&self.key, self.start, self.stop) {
+            Ok(values) => {
+                let mut response = Frame::array();
+                for value in values {
+                    response.push_bulk(value);
+                }
+                response
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lrange".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.start);
+        frame.push_int(self.stop);
+        frame
+    }
+}
+
+impl LPos {
+    /// Create a new `LPos` command which finds the elements of the list at
+    /// `key` equal to `element`.
+    pub fn new(
+        key: impl AsRef<[u8]>,
+        element: Bytes,
+        rank: Option<i64>,
+        count: Option<u64>,
+    ) -> LPos {
+        LPos {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            element,
+            rank,
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the element
+    pub fn element(&self) -> &Bytes {
+        &self.element
+    }
+
+    /// Get the rank
+    pub fn rank(&self) -> Option<i64> {
+        self.rank
+    }
+
+    /// Get the count
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse an `LPos` instance from a received frame.
+    ///
+    /// The `LPOS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LPOS key element [RANK rank] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LPos> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_bytes()?;
+        let element = parse.next_bytes()?;
+
+        let mut rank = None;
+        let mut count = None;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "RANK" => rank = Some(parse.next_signed_int()?),
+                Ok(s) if s.to_uppercase() == "COUNT" => count = Some(parse.next_int()?),
+                Ok(_) => return Err("`LPOS` only supports the RANK and COUNT options".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(LPos {
+            key,
+            element,
+            rank,
+            count,
+        })
+    }
+
+    /// Apply the `LPos` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let rank = self.rank.unwrap_or(1);
+
+        // Without `COUNT`, only the first match is needed.
+        let count = self.count.unwrap_or(1) as usize;
+
+        let response = if rank == 0 {
+            Frame::Error(
+                "ERR RANK can't be zero: use 1 to start from the first match, 2 from the \
+                 second ... or use negative to start from the end of the list"
+                    .to_string(),
+            )
+        } else {
+            match (db.lpos(&self.key, &self.element, rank, count), self.count) {
+                (Ok(indices), Some(_)) => Frame::Array(
+                    indices
+                        .into_iter()
+                        .map(|index| Frame::Integer(index as i64))
+                        .collect(),
+                ),
+                (Ok(indices), None) => match indices.first() {
+                    Some(&index) => Frame::Integer(index as i64),
+                    None => Frame::Null,
+                },
+                (Err(err), _) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LPos` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lpos".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.element);
+        if let Some(rank) = self.rank {
+            frame.push_bulk(Bytes::from("rank".as_bytes()));
+            frame.push_int(rank);
+        }
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_int(count as i64);
+        }
+        frame
+    }
+}
+
+impl LMPop {
+    /// Create a new `LMPop` command which pops up to `count` elements from
+    /// `end` of the first non-empty list among `keys`, one if `count` is
+    /// `None`.
+    pub fn new(keys: Vec<Bytes>, end: ListEnd, count: Option<u64>) -> LMPop {
+        LMPop { keys, end, count }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Get the end of the lists to pop from
+    pub fn end(&self) -> ListEnd {
+        self.end
+    }
+
+    /// Get the count
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse an `LMPop` instance from a received frame.
+    ///
+    /// The `LMPOP` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the number of keys, the keys and the
+    /// end to pop from.
+    ///
+    /// ```text
+    /// LMPOP numkeys key [key ...] LEFT|RIGHT [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LMPop> {
+        let numkeys = parse.next_int()?;
+        if numkeys == 0 {
+            return Err("numkeys should be greater than 0".into());
+        }
+
+        let mut keys = vec![];
+        for _ in 0..numkeys {
+            keys.push(parse.next_bytes()?);
+        }
+
+        let end = match &parse.next_string()?.to_uppercase()[..] {
+            "LEFT" => ListEnd::Left,
+            "RIGHT" => ListEnd::Right,
+            _ => return Err("`LMPOP` expects LEFT or RIGHT".into()),
+        };
+
+        let count = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "COUNT" => Some(parse.next_int()?),
+            Ok(_) => return Err("`LMPOP` only supports the COUNT option".into()),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(LMPop { keys, end, count })
+    }
+
+    /// Apply the `LMPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let count = self.count.unwrap_or(1) as usize;
+
+        let response = if count == 0 {
+            Frame::Error("ERR count should be greater than 0".to_string())
+        } else {
+            match db.mpop(&self.keys, self.end, count) {
+                Ok(Some((key, values))) => Frame::Array(vec![
+                    Frame::Bulk(key),
+                    Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+                ]),
+                Ok(None) => Frame::Null,
+                Err(err) => Frame::Error(err.to_string()),
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LMPop` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lmpop".as_bytes()));
+        frame.push_int(self.keys.len() as i64);
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        let end = match self.end {
+            ListEnd::Left => "left",
+            ListEnd::Right => "right",
+        };
+        frame.push_bulk(Bytes::from(end.as_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_int(count as i64);
+        }
+        frame
+    }
+}
+
+impl LLen {
+    /// Create a new `LLen` command which returns the length of the list at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> LLen {
+        LLen {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `LLen` instance from a received frame.
+    ///
+    /// The `LLEN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// LLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LLen> {
+        let key = parse.next_bytes()?;
+
+        Ok(LLen { key })
+    }
+
+    /// Apply the `LLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.llen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LLen` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("llen".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl LSet {
+    /// Create a new `LSet` command which sets the element at `index` of the
+    /// list at `key` to `value`.
+    pub fn new(key: impl AsRef<[u8]>, index: i64, value: Bytes) -> LSet {
+        LSet {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            index,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the index
+    pub fn index(&self) -> i64 {
+        self.index
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse an `LSet` instance from a received frame.
+    ///
+    /// The `LSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LSET key index value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LSet> {
+        let key = parse.next_bytes()?;
+        let index = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(LSet { key, index, value })
+    }
+
+    /// Apply the `LSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lset(&self.key, self.index, self.value) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lset".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.index);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
+
+impl LInsert {
+    /// Create a new `LInsert` command which inserts `value` at `position`
+    /// relative to the first element equal to `pivot` of the list at `key`.
+    pub fn new(
+        key: impl AsRef<[u8]>,
+        position: InsertPosition,
+        pivot: Bytes,
+        value: Bytes,
+    ) -> LInsert {
+        LInsert {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            position,
+            pivot,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the side of the pivot the value is inserted on
+    pub fn position(&self) -> InsertPosition {
+        self.position
+    }
+
+    /// Get the pivot
+    pub fn pivot(&self) -> &Bytes {
+        &self.pivot
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse an `LInsert` instance from a received frame.
+    ///
+    /// The `LINSERT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing five entries.
+    ///
+    /// ```text
+    /// LINSERT key BEFORE|AFTER pivot element
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LInsert> {
+        let key = parse.next_bytes()?;
+
+        let position = match &parse.next_string()?.to_uppercase()[..] {
+            "BEFORE" => InsertPosition::Before,
+            "AFTER" => InsertPosition::After,
+            _ => return Err("`LINSERT` expects BEFORE or AFTER".into()),
+        };
+
+        let pivot = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+
+        Ok(LInsert {
+            key,
+            position,
+            pivot,
+            value,
+        })
+    }
+
+    /// Apply the `LInsert` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.linsert(&self.key, self.position, &self.pivot, self.value) {
+            Ok(Some(len)) => Frame::Integer(len as i64),
+            Ok(None) => Frame::Integer(-1),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `LInsert` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("linsert".as_bytes()));
+        frame.push_bulk(self.key);
+        let position = match self.position {
+            InsertPosition::Before => "before",
+            InsertPosition::After => "after",
+        };
+        frame.push_bulk(Bytes::from(position.as_bytes()));
+        frame.push_bulk(self.pivot);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
+
+/// Parse the `key element [element ...]` arguments shared by `LPUSH` and
+/// `RPUSH`.
+fn parse_push(parse: &mut Parse) -> crate::Result<(Bytes, Vec<Bytes>)> {
+    use ParseError::EndOfStream;
+
+    let key = parse.next_bytes()?;
+
+    // At least one element must be provided.
+    let mut values = vec![parse.next_bytes()?];
+
+    loop {
+        match parse.next_bytes() {
+            Ok(value) => values.push(value),
+            Err(EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok((key, values))
+}
+
+/// Parse the `key [key ...] timeout` arguments shared by `BLPOP` and `BRPOP`.
+fn parse_blocking_pop(parse: &mut Parse) -> crate::Result<(Vec<Bytes>, Duration)> {
+    use ParseError::EndOfStream;
+
+    // At least one key and the timeout must be provided.
+    let mut args = vec![parse.next_bytes()?, parse.next_bytes()?];
+
+    loop {
+        match parse.next_bytes() {
+            Ok(arg) => args.push(arg),
+            Err(EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    // The timeout is the last argument, the keys precede it.
+    let timeout = args
+        .pop()
+        .and_then(|timeout| std::str::from_utf8(&timeout).ok()?.parse::<f64>().ok())
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or("protocol error; timeout is not a float or out of range")?;
+
+    Ok((args, timeout))
+}
+
+/// Push `values` onto `end` of the list at `key`, producing the reply frame.
+fn push_response(db: &Db, key: &[u8], values: Vec<Bytes>, end: ListEnd, create: bool) -> Frame {
+    match db.push(key, values, end, create) {
+        Ok(len) => Frame::Integer(len as i64),
+        Err(err) => Frame::Error(err.to_string()),
+    }
+}
+
+/// Pop from `end` of the list at `key`, producing the reply frame.
+fn pop_response(db: &Db, key: &[u8], end: ListEnd) -> Frame {
+    match db.pop(key, end) {
+        Ok(Some(value)) => Frame::Bulk(value),
+        Ok(None) => Frame::Null,
+        Err(err) => Frame::Error(err.to_string()),
+    }
+}
+
+/// Encode a blocking pop command named `name`.
+fn blocking_pop_frame(name: &str, keys: Vec<Bytes>, timeout: Duration) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string().into_bytes()));
+    for key in keys {
+        frame.push_bulk(key);
+    }
+    frame.push_bulk(Bytes::from(timeout.as_secs_f64().to_string()));
+    frame
+}
+
+/// Encode a push command named `name`.
+fn push_frame(name: &str, key: Bytes, values: Vec<Bytes>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string().into_bytes()));
+    frame.push_bulk(key);
+    for value in values {
+        frame.push_bulk(value);
+    }
+    frame
+}
@@ -1,40 +1,335 @@
-mod get;
+use std::borrow::Cow;
+
+mod append;
+pub use append::Append;
+
+mod auth;
+pub use auth::Auth;
+
+mod bit;
+pub use bit::{BitCount, BitPos, BitUnit, GetBit, SetBit};
+
+mod client;
+pub use client::{
+    ClientGetName, ClientKill, ClientList, ClientPause, ClientReply, ClientSetName, ClientUnpause,
+    PauseMode, ReplyMode,
+};
+
+mod command;
+pub use command::{CommandCount, CommandDocs, CommandTable};
+
+mod config;
+pub use config::{ConfigGet, ConfigSet};
+
+mod copy;
+pub use copy::Copy;
+
+mod dbsize;
+pub use dbsize::DbSize;
 
+mod debug;
+pub use debug::{DebugExpireSweep, DebugObject, DebugSetActiveExpire, DebugSleep};
 
+mod del;
+pub use del::Del;
+
+mod dump;
+pub use dump::{Dump, Restore};
+
+mod echo;
+pub use echo::Echo;
+
+mod eval;
+pub use eval::Eval;
+
+mod exists;
+pub use exists::Exists;
+
+mod expire;
+pub use expire::{Expire, ExpireCondition, Persist};
+
+mod expireat;
+pub use expireat::{ExpireAt, PExpireAt};
+
+mod flush;
+pub use flush::{FlushAll, FlushDb};
+
+mod get;
 pub use get::Get;
 
+mod getdel;
+pub use getdel::GetDel;
+
+mod getex;
+pub use getex::{GetEx, GetExExpiration};
+
+mod getset;
+pub use getset::GetSet;
+
+mod hash;
+pub use hash::{
+    HDel, HExists, HGet, HGetAll, HIncrBy, HIncrByFloat, HKeys, HLen, HRandField, HSet, HVals,
+};
+
+mod hello;
+pub use hello::Hello;
+
+mod incr;
+pub use incr::{Decr, DecrBy, Incr, IncrBy, IncrByFloat};
+
+mod info;
+pub use info::Info;
+
+mod keys;
+pub use keys::Keys;
+
+mod list;
+pub use list::{
+    BLPop, BRPop, InsertPosition, LInsert, LLen, LMPop, LPop, LPos, LPush, LPushX, LRange, LSet,
+    ListEnd, RPop, RPush, RPushX,
+};
+
+mod monitor;
+pub use monitor::Monitor;
+
+mod move_cmd;
+pub use move_cmd::Move;
+
 mod multiget;
 pub use multiget::MultiGet;
 
+mod multiset;
+pub use multiset::{MSet, MSetNx};
+
+mod object;
+pub use object::{ObjectEncoding, ObjectFreq, ObjectIdleTime};
+
 mod publish;
 pub use publish::Publish;
 
+mod pubsub;
+pub use pubsub::{PubSubCapacity, PubSubChannels, PubSubNumSub};
+
+mod randomkey;
+pub use randomkey::RandomKey;
+
+mod quit;
+pub use quit::Quit;
+
+mod range;
+pub use range::{GetRange, SetRange};
+
+mod rename;
+pub use rename::{Rename, RenameNx};
+
+mod reset;
+pub use reset::Reset;
+
+mod save;
+pub use save::Save;
+
+mod scan;
+pub use scan::Scan;
+
+mod select;
+pub use select::Select;
+
 mod set;
-pub use set::Set;
+pub use set::{Set, SetNx};
+
+mod setex;
+pub use setex::{PSetEx, SetEx};
+
+mod set_cmd;
+pub use set_cmd::{
+    SAdd, SCard, SDiffStore, SInterCard, SInterStore, SIsMember, SMembers, SMove, SRandMember,
+    SRem, SUnionStore,
+};
+
+mod shutdown_cmd;
+pub use shutdown_cmd::Shutdown;
+
+mod slowlog;
+pub use slowlog::{SlowLogGet, SlowLogLen, SlowLogReset};
+
+mod strlen;
+pub use strlen::StrLen;
 
 mod subscribe;
-pub use subscribe::{Subscribe, Unsubscribe};
+pub(crate) use subscribe::{resume_subscribed, SubscribeExit, Subscriptions};
+pub use subscribe::{PSubscribe, PUnsubscribe, Subscribe, Unsubscribe};
 
 mod ping;
 pub use ping::Ping;
 
+mod swapdb;
+pub use swapdb::SwapDb;
+
+mod touch;
+pub use touch::Touch;
+
+mod ttl;
+pub use ttl::{Pttl, Ttl};
+
+mod transaction;
+pub use transaction::{Discard, Exec, Multi};
+
+mod type_cmd;
+pub use type_cmd::Type;
+
+mod unlink;
+pub use unlink::Unlink;
+
 mod unknown;
 pub use unknown::Unknown;
 
-use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+mod wait;
+pub use wait::Wait;
+
+mod zset;
+pub use zset::{ZAdd, ZRange, ZRank, ZScore};
+
+use crate::{Connection, Db, Frame, Parse, ParseError};
 
 /// Enumeration of supported Redis commands.
 ///
 /// Methods called on `Command` are delegated to the command implementation.
 #[derive(Debug)]
 pub enum Command {
+    Append(Append),
+    Auth(Auth),
+    BitCount(BitCount),
+    BitPos(BitPos),
+    BLPop(BLPop),
+    BRPop(BRPop),
+    ClientGetName(ClientGetName),
+    ClientKill(ClientKill),
+    ClientList(ClientList),
+    ClientSetName(ClientSetName),
+    ClientReply(ClientReply),
+    ClientPause(ClientPause),
+    ClientUnpause(ClientUnpause),
+    CommandTable(CommandTable),
+    CommandCount(CommandCount),
+    CommandDocs(CommandDocs),
+    ConfigGet(ConfigGet),
+    ConfigSet(ConfigSet),
+    Copy(Copy),
+    DbSize(DbSize),
+    DebugObject(DebugObject),
+    DebugSleep(DebugSleep),
+    DebugSetActiveExpire(DebugSetActiveExpire),
+    DebugExpireSweep(DebugExpireSweep),
+    Del(Del),
+    Dump(Dump),
+    Echo(Echo),
+    Eval(Eval),
+    Exists(Exists),
+    Expire(Expire),
+    ExpireAt(ExpireAt),
+    PExpireAt(PExpireAt),
+    Persist(Persist),
+    FlushDb(FlushDb),
+    FlushAll(FlushAll),
     Get(Get),
+    GetBit(GetBit),
+    GetDel(GetDel),
+    GetEx(GetEx),
+    GetRange(GetRange),
+    GetSet(GetSet),
+    HSet(HSet),
+    HGet(HGet),
+    HDel(HDel),
+    HExists(HExists),
+    HKeys(HKeys),
+    HVals(HVals),
+    HLen(HLen),
+    HGetAll(HGetAll),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HRandField(HRandField),
+    SAdd(SAdd),
+    SRem(SRem),
+    SMembers(SMembers),
+    SIsMember(SIsMember),
+    SCard(SCard),
+    SMove(SMove),
+    SInterCard(SInterCard),
+    SRandMember(SRandMember),
+    SInterStore(SInterStore),
+    SUnionStore(SUnionStore),
+    SDiffStore(SDiffStore),
+    ZAdd(ZAdd),
+    ZScore(ZScore),
+    ZRank(ZRank),
+    ZRange(ZRange),
+    Hello(Hello),
+    Info(Info),
+    Incr(Incr),
+    IncrBy(IncrBy),
+    IncrByFloat(IncrByFloat),
+    Decr(Decr),
+    DecrBy(DecrBy),
+    Keys(Keys),
+    LPush(LPush),
+    RPush(RPush),
+    LPushX(LPushX),
+    RPushX(RPushX),
+    LPop(LPop),
+    RPop(RPop),
+    LInsert(LInsert),
+    LLen(LLen),
+    LPos(LPos),
+    LRange(LRange),
+    LSet(LSet),
+    LMPop(LMPop),
+    Monitor(Monitor),
+    Move(Move),
     MultiGet(MultiGet),
+    MSet(MSet),
+    MSetNx(MSetNx),
+    ObjectEncoding(ObjectEncoding),
+    ObjectFreq(ObjectFreq),
+    ObjectIdleTime(ObjectIdleTime),
     Publish(Publish),
+    PubSubCapacity(PubSubCapacity),
+    PubSubChannels(PubSubChannels),
+    PubSubNumSub(PubSubNumSub),
+    Quit(Quit),
+    RandomKey(RandomKey),
+    Rename(Rename),
+    RenameNx(RenameNx),
+    Reset(Reset),
+    Restore(Restore),
+    Save(Save),
+    Scan(Scan),
+    Select(Select),
     Set(Set),
+    SetNx(SetNx),
+    SetEx(SetEx),
+    PSetEx(PSetEx),
+    SetBit(SetBit),
+    SetRange(SetRange),
+    Shutdown(Shutdown),
+    SlowLogGet(SlowLogGet),
+    SlowLogLen(SlowLogLen),
+    SlowLogReset(SlowLogReset),
+    StrLen(StrLen),
     Subscribe(Subscribe),
     Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
     Ping(Ping),
+    SwapDb(SwapDb),
+    Touch(Touch),
+    Ttl(Ttl),
+    Pttl(Pttl),
+    Type(Type),
+    Unlink(Unlink),
+    Wait(Wait),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
     Unknown(Unknown),
 }
 
@@ -60,16 +355,201 @@ impl Command {
         // matching.
         let command_name = parse.next_string()?.to_lowercase();
 
+        // Reject a wrong number of arguments before parsing them, with the
+        // same message as Redis.
+        command::check_arity(&command_name, parse.remaining() + 1)?;
+
         // Match the command name, delegating the rest of the parsing to the
         // specific command.
         let command = match &command_name[..] {
+            "append" => Command::Append(Append::parse_frames(&mut parse)?),
+            "auth" => Command::Auth(Auth::parse_frames(&mut parse)?),
+            "client" => match &parse.next_string()?.to_lowercase()[..] {
+                "getname" => Command::ClientGetName(ClientGetName::parse_frames(&mut parse)?),
+                "kill" => Command::ClientKill(ClientKill::parse_frames(&mut parse)?),
+                "list" => Command::ClientList(ClientList::parse_frames(&mut parse)?),
+                "pause" => Command::ClientPause(ClientPause::parse_frames(&mut parse)?),
+                "reply" => Command::ClientReply(ClientReply::parse_frames(&mut parse)?),
+                "setname" => Command::ClientSetName(ClientSetName::parse_frames(&mut parse)?),
+                "unpause" => Command::ClientUnpause(ClientUnpause::parse_frames(&mut parse)?),
+                subcommand => {
+                    let name = format!("client {}", subcommand);
+                    return Ok(Command::Unknown(Unknown::new(name)));
+                }
+            },
+            "command" => match parse.next_string() {
+                Err(ParseError::EndOfStream) => {
+                    Command::CommandTable(CommandTable::parse_frames(&mut parse)?)
+                }
+                Ok(subcommand) => match &subcommand.to_lowercase()[..] {
+                    "count" => Command::CommandCount(CommandCount::parse_frames(&mut parse)?),
+                    "docs" => Command::CommandDocs(CommandDocs::parse_frames(&mut parse)?),
+                    subcommand => {
+                        let name = format!("command {}", subcommand);
+                        return Ok(Command::Unknown(Unknown::new(name)));
+                    }
+                },
+                Err(err) => return Err(err.into()),
+            },
+            "config" => match &parse.next_string()?.to_lowercase()[..] {
+                "get" => Command::ConfigGet(ConfigGet::parse_frames(&mut parse)?),
+                "set" => Command::ConfigSet(ConfigSet::parse_frames(&mut parse)?),
+                subcommand => {
+                    // As for unknown commands, the remaining fields are left
+                    // unconsumed.
+                    let name = format!("config {}", subcommand);
+                    return Ok(Command::Unknown(Unknown::new(name)));
+                }
+            },
+            "blpop" => Command::BLPop(BLPop::parse_frames(&mut parse)?),
+            "brpop" => Command::BRPop(BRPop::parse_frames(&mut parse)?),
+            "bitcount" => Command::BitCount(BitCount::parse_frames(&mut parse)?),
+            "bitpos" => Command::BitPos(BitPos::parse_frames(&mut parse)?),
+            "copy" => Command::Copy(Copy::parse_frames(&mut parse)?),
+            "dbsize" => Command::DbSize(DbSize::parse_frames(&mut parse)?),
+            "debug" => match &parse.next_string()?.to_lowercase()[..] {
+                "object" => Command::DebugObject(DebugObject::parse_frames(&mut parse)?),
+                "sleep" => Command::DebugSleep(DebugSleep::parse_frames(&mut parse)?),
+                "set-active-expire" => {
+                    Command::DebugSetActiveExpire(DebugSetActiveExpire::parse_frames(&mut parse)?)
+                }
+                "expire-sweep" => {
+                    Command::DebugExpireSweep(DebugExpireSweep::parse_frames(&mut parse)?)
+                }
+                subcommand => {
+                    let name = format!("debug {}", subcommand);
+                    return Ok(Command::Unknown(Unknown::new(name)));
+                }
+            },
+            "del" => Command::Del(Del::parse_frames(&mut parse)?),
+            "dump" => Command::Dump(Dump::parse_frames(&mut parse)?),
+            "echo" => Command::Echo(Echo::parse_frames(&mut parse)?),
+            "eval" => Command::Eval(Eval::parse_frames(&mut parse)?),
+            "exists" => Command::Exists(Exists::parse_frames(&mut parse)?),
+            "expire" => Command::Expire(Expire::parse_frames(&mut parse)?),
+            "expireat" => Command::ExpireAt(ExpireAt::parse_frames(&mut parse)?),
+            "pexpireat" => Command::PExpireAt(PExpireAt::parse_frames(&mut parse)?),
+            "persist" => Command::Persist(Persist::parse_frames(&mut parse)?),
+            "flushdb" => Command::FlushDb(FlushDb::parse_frames(&mut parse)?),
+            "flushall" => Command::FlushAll(FlushAll::parse_frames(&mut parse)?),
             "get" => Command::Get(Get::parse_frames(&mut parse)?),
-            "multiget" => Command::MultiGet(MultiGet::parse_frames(&mut parse)?),
+            "getdel" => Command::GetDel(GetDel::parse_frames(&mut parse)?),
+            "getex" => Command::GetEx(GetEx::parse_frames(&mut parse)?),
+            "getbit" => Command::GetBit(GetBit::parse_frames(&mut parse)?),
+            "getrange" => Command::GetRange(GetRange::parse_frames(&mut parse)?),
+            "getset" => Command::GetSet(GetSet::parse_frames(&mut parse)?),
+            "hset" => Command::HSet(HSet::parse_frames(&mut parse)?),
+            "hget" => Command::HGet(HGet::parse_frames(&mut parse)?),
+            "hdel" => Command::HDel(HDel::parse_frames(&mut parse)?),
+            "hexists" => Command::HExists(HExists::parse_frames(&mut parse)?),
+            "hkeys" => Command::HKeys(HKeys::parse_frames(&mut parse)?),
+            "hvals" => Command::HVals(HVals::parse_frames(&mut parse)?),
+            "hlen" => Command::HLen(HLen::parse_frames(&mut parse)?),
+            "hgetall" => Command::HGetAll(HGetAll::parse_frames(&mut parse)?),
+            "hincrby" => Command::HIncrBy(HIncrBy::parse_frames(&mut parse)?),
+            "hincrbyfloat" => Command::HIncrByFloat(HIncrByFloat::parse_frames(&mut parse)?),
+            "hrandfield" => Command::HRandField(HRandField::parse_frames(&mut parse)?),
+            "sadd" => Command::SAdd(SAdd::parse_frames(&mut parse)?),
+            "srem" => Command::SRem(SRem::parse_frames(&mut parse)?),
+            "smembers" => Command::SMembers(SMembers::parse_frames(&mut parse)?),
+            "sismember" => Command::SIsMember(SIsMember::parse_frames(&mut parse)?),
+            "scard" => Command::SCard(SCard::parse_frames(&mut parse)?),
+            "smove" => Command::SMove(SMove::parse_frames(&mut parse)?),
+            "sintercard" => Command::SInterCard(SInterCard::parse_frames(&mut parse)?),
+            "srandmember" => Command::SRandMember(SRandMember::parse_frames(&mut parse)?),
+            "sinterstore" => Command::SInterStore(SInterStore::parse_frames(&mut parse)?),
+            "sunionstore" => Command::SUnionStore(SUnionStore::parse_frames(&mut parse)?),
+            "sdiffstore" => Command::SDiffStore(SDiffStore::parse_frames(&mut parse)?),
+            "zadd" => Command::ZAdd(ZAdd::parse_frames(&mut parse)?),
+            "zscore" => Command::ZScore(ZScore::parse_frames(&mut parse)?),
+            "zrank" => Command::ZRank(ZRank::parse_frames(&mut parse)?),
+            "zrange" => Command::ZRange(ZRange::parse_frames(&mut parse)?),
+            "hello" => Command::Hello(Hello::parse_frames(&mut parse)?),
+            "info" => Command::Info(Info::parse_frames(&mut parse)?),
+            "incr" => Command::Incr(Incr::parse_frames(&mut parse)?),
+            "incrby" => Command::IncrBy(IncrBy::parse_frames(&mut parse)?),
+            "incrbyfloat" => Command::IncrByFloat(IncrByFloat::parse_frames(&mut parse)?),
+            "decr" => Command::Decr(Decr::parse_frames(&mut parse)?),
+            "decrby" => Command::DecrBy(DecrBy::parse_frames(&mut parse)?),
+            "keys" => Command::Keys(Keys::parse_frames(&mut parse)?),
+            "lpush" => Command::LPush(LPush::parse_frames(&mut parse)?),
+            "rpush" => Command::RPush(RPush::parse_frames(&mut parse)?),
+            "lpushx" => Command::LPushX(LPushX::parse_frames(&mut parse)?),
+            "rpushx" => Command::RPushX(RPushX::parse_frames(&mut parse)?),
+            "lpop" => Command::LPop(LPop::parse_frames(&mut parse)?),
+            "rpop" => Command::RPop(RPop::parse_frames(&mut parse)?),
+            "linsert" => Command::LInsert(LInsert::parse_frames(&mut parse)?),
+            "llen" => Command::LLen(LLen::parse_frames(&mut parse)?),
+            "lpos" => Command::LPos(LPos::parse_frames(&mut parse)?),
+            "lrange" => Command::LRange(LRange::parse_frames(&mut parse)?),
+            "lset" => Command::LSet(LSet::parse_frames(&mut parse)?),
+            "lmpop" => Command::LMPop(LMPop::parse_frames(&mut parse)?),
+            // `multiget` is kept for clients predating the Redis name.
+            "monitor" => Command::Monitor(Monitor::parse_frames(&mut parse)?),
+            "move" => Command::Move(Move::parse_frames(&mut parse)?),
+            "mget" | "multiget" => Command::MultiGet(MultiGet::parse_frames(&mut parse)?),
+            "mset" => Command::MSet(MSet::parse_frames(&mut parse)?),
+            "msetnx" => Command::MSetNx(MSetNx::parse_frames(&mut parse)?),
+            "object" => match &parse.next_string()?.to_lowercase()[..] {
+                "encoding" => Command::ObjectEncoding(ObjectEncoding::parse_frames(&mut parse)?),
+                "freq" => Command::ObjectFreq(ObjectFreq::parse_frames(&mut parse)?),
+                "idletime" => Command::ObjectIdleTime(ObjectIdleTime::parse_frames(&mut parse)?),
+                subcommand => {
+                    let name = format!("object {}", subcommand);
+                    return Ok(Command::Unknown(Unknown::new(name)));
+                }
+            },
             "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
+            "pubsub" => match &parse.next_string()?.to_lowercase()[..] {
+                "capacity" => Command::PubSubCapacity(PubSubCapacity::parse_frames(&mut parse)?),
+                "channels" => Command::PubSubChannels(PubSubChannels::parse_frames(&mut parse)?),
+                "numsub" => Command::PubSubNumSub(PubSubNumSub::parse_frames(&mut parse)?),
+                subcommand => {
+                    let name = format!("pubsub {}", subcommand);
+                    return Ok(Command::Unknown(Unknown::new(name)));
+                }
+            },
+            "quit" => Command::Quit(Quit::parse_frames(&mut parse)?),
+            "randomkey" => Command::RandomKey(RandomKey::parse_frames(&mut parse)?),
+            "rename" => Command::Rename(Rename::parse_frames(&mut parse)?),
+            "renamenx" => Command::RenameNx(RenameNx::parse_frames(&mut parse)?),
+            "reset" => Command::Reset(Reset::parse_frames(&mut parse)?),
+            "restore" => Command::Restore(Restore::parse_frames(&mut parse)?),
+            "save" => Command::Save(Save::parse_frames(&mut parse)?),
+            "scan" => Command::Scan(Scan::parse_frames(&mut parse)?),
+            "select" => Command::Select(Select::parse_frames(&mut parse)?),
             "set" => Command::Set(Set::parse_frames(&mut parse)?),
+            "setnx" => Command::SetNx(SetNx::parse_frames(&mut parse)?),
+            "setex" => Command::SetEx(SetEx::parse_frames(&mut parse)?),
+            "psetex" => Command::PSetEx(PSetEx::parse_frames(&mut parse)?),
+            "setbit" => Command::SetBit(SetBit::parse_frames(&mut parse)?),
+            "setrange" => Command::SetRange(SetRange::parse_frames(&mut parse)?),
+            "shutdown" => Command::Shutdown(Shutdown::parse_frames(&mut parse)?),
+            "slowlog" => match &parse.next_string()?.to_lowercase()[..] {
+                "get" => Command::SlowLogGet(SlowLogGet::parse_frames(&mut parse)?),
+                "len" => Command::SlowLogLen(SlowLogLen::parse_frames(&mut parse)?),
+                "reset" => Command::SlowLogReset(SlowLogReset::parse_frames(&mut parse)?),
+                subcommand => {
+                    let name = format!("slowlog {}", subcommand);
+                    return Ok(Command::Unknown(Unknown::new(name)));
+                }
+            },
+            "strlen" => Command::StrLen(StrLen::parse_frames(&mut parse)?),
             "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
             "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
+            "psubscribe" => Command::PSubscribe(PSubscribe::parse_frames(&mut parse)?),
+            "punsubscribe" => Command::PUnsubscribe(PUnsubscribe::parse_frames(&mut parse)?),
             "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
+            "swapdb" => Command::SwapDb(SwapDb::parse_frames(&mut parse)?),
+            "touch" => Command::Touch(Touch::parse_frames(&mut parse)?),
+            "ttl" => Command::Ttl(Ttl::parse_frames(&mut parse)?),
+            "pttl" => Command::Pttl(Pttl::parse_frames(&mut parse)?),
+            "type" => Command::Type(Type::parse_frames(&mut parse)?),
+            "unlink" => Command::Unlink(Unlink::parse_frames(&mut parse)?),
+            "wait" => Command::Wait(Wait::parse_frames(&mut parse)?),
+            "multi" => Command::Multi(Multi::parse_frames(&mut parse)?),
+            "exec" => Command::Exec(Exec::parse_frames(&mut parse)?),
+            "discard" => Command::Discard(Discard::parse_frames(&mut parse)?),
             _ => {
                 // The command is not recognized and an Unknown command is
                 // returned.
@@ -98,35 +578,392 @@ impl Command {
         self,
         db: &Db,
         dst: &mut Connection,
-        shutdown: &mut Shutdown,
+        shutdown: &mut crate::Shutdown,
     ) -> crate::Result<()> {
         use Command::*;
 
         match self {
+            Append(cmd) => cmd.apply(db, dst).await,
+            BitCount(cmd) => cmd.apply(db, dst).await,
+            BitPos(cmd) => cmd.apply(db, dst).await,
+            CommandTable(cmd) => cmd.apply(dst).await,
+            CommandCount(cmd) => cmd.apply(dst).await,
+            CommandDocs(cmd) => cmd.apply(dst).await,
+            Copy(cmd) => cmd.apply(db, dst).await,
+            DbSize(cmd) => cmd.apply(db, dst).await,
+            DebugObject(cmd) => cmd.apply(db, dst).await,
+            DebugSleep(cmd) => cmd.apply(dst, shutdown).await,
+            Del(cmd) => cmd.apply(db, dst).await,
+            Dump(cmd) => cmd.apply(db, dst).await,
+            Echo(cmd) => cmd.apply(dst).await,
+            Eval(cmd) => cmd.apply(db, dst).await.map(drop),
+            Exists(cmd) => cmd.apply(db, dst).await,
+            Expire(cmd) => cmd.apply(db, dst).await,
+            ExpireAt(cmd) => cmd.apply(db, dst).await,
+            PExpireAt(cmd) => cmd.apply(db, dst).await,
+            Persist(cmd) => cmd.apply(db, dst).await,
+            FlushDb(cmd) => cmd.apply(db, dst).await,
             Get(cmd) => cmd.apply(db, dst).await,
+            GetDel(cmd) => cmd.apply(db, dst).await,
+            GetEx(cmd) => cmd.apply(db, dst).await,
+            GetBit(cmd) => cmd.apply(db, dst).await,
+            GetRange(cmd) => cmd.apply(db, dst).await,
+            GetSet(cmd) => cmd.apply(db, dst).await,
+            HSet(cmd) => cmd.apply(db, dst).await,
+            HGet(cmd) => cmd.apply(db, dst).await,
+            HDel(cmd) => cmd.apply(db, dst).await,
+            HExists(cmd) => cmd.apply(db, dst).await,
+            HKeys(cmd) => cmd.apply(db, dst).await,
+            HVals(cmd) => cmd.apply(db, dst).await,
+            HLen(cmd) => cmd.apply(db, dst).await,
+            HGetAll(cmd) => cmd.apply(db, dst).await,
+            HIncrBy(cmd) => cmd.apply(db, dst).await,
+            HIncrByFloat(cmd) => cmd.apply(db, dst).await,
+            HRandField(cmd) => cmd.apply(db, dst).await,
+            SAdd(cmd) => cmd.apply(db, dst).await,
+            SRem(cmd) => cmd.apply(db, dst).await,
+            SMembers(cmd) => cmd.apply(db, dst).await,
+            SIsMember(cmd) => cmd.apply(db, dst).await,
+            SCard(cmd) => cmd.apply(db, dst).await,
+            SMove(cmd) => cmd.apply(db, dst).await,
+            SInterCard(cmd) => cmd.apply(db, dst).await,
+            SRandMember(cmd) => cmd.apply(db, dst).await,
+            SInterStore(cmd) => cmd.apply(db, dst).await,
+            SUnionStore(cmd) => cmd.apply(db, dst).await,
+            SDiffStore(cmd) => cmd.apply(db, dst).await,
+            ZAdd(cmd) => cmd.apply(db, dst).await,
+            ZScore(cmd) => cmd.apply(db, dst).await,
+            ZRank(cmd) => cmd.apply(db, dst).await,
+            ZRange(cmd) => cmd.apply(db, dst).await,
+            Hello(cmd) => cmd.apply(dst).await,
+            Incr(cmd) => cmd.apply(db, dst).await,
+            IncrBy(cmd) => cmd.apply(db, dst).await,
+            IncrByFloat(cmd) => cmd.apply(db, dst).await,
+            Decr(cmd) => cmd.apply(db, dst).await,
+            DecrBy(cmd) => cmd.apply(db, dst).await,
+            Keys(cmd) => cmd.apply(db, dst).await,
+            LPush(cmd) => cmd.apply(db, dst).await,
+            RPush(cmd) => cmd.apply(db, dst).await,
+            LPushX(cmd) => cmd.apply(db, dst).await,
+            RPushX(cmd) => cmd.apply(db, dst).await,
+            LPop(cmd) => cmd.apply(db, dst).await,
+            RPop(cmd) => cmd.apply(db, dst).await,
+            LInsert(cmd) => cmd.apply(db, dst).await,
+            LLen(cmd) => cmd.apply(db, dst).await,
+            LPos(cmd) => cmd.apply(db, dst).await,
+            LRange(cmd) => cmd.apply(db, dst).await,
+            LSet(cmd) => cmd.apply(db, dst).await,
+            LMPop(cmd) => cmd.apply(db, dst).await,
             MultiGet(cmd) => cmd.apply(db, dst).await,
+            MSet(cmd) => cmd.apply(db, dst).await,
+            MSetNx(cmd) => cmd.apply(db, dst).await,
+            ObjectEncoding(cmd) => cmd.apply(db, dst).await,
+            ObjectFreq(cmd) => cmd.apply(db, dst).await,
+            ObjectIdleTime(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
+            PubSubCapacity(cmd) => cmd.apply(db, dst).await,
+            PubSubChannels(cmd) => cmd.apply(db, dst).await,
+            PubSubNumSub(cmd) => cmd.apply(db, dst).await,
+            RandomKey(cmd) => cmd.apply(db, dst).await,
+            ClientList(cmd) => cmd.apply(db, dst).await,
+            ClientKill(cmd) => cmd.apply(db, dst).await,
+            ClientPause(cmd) => cmd.apply(db, dst).await,
+            ClientUnpause(cmd) => cmd.apply(db, dst).await,
+            Rename(cmd) => cmd.apply(db, dst).await,
+            RenameNx(cmd) => cmd.apply(db, dst).await,
+            Restore(cmd) => cmd.apply(db, dst).await,
+            Scan(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
-            Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            SetNx(cmd) => cmd.apply(db, dst).await,
+            SetEx(cmd) => cmd.apply(db, dst).await,
+            PSetEx(cmd) => cmd.apply(db, dst).await,
+            SetBit(cmd) => cmd.apply(db, dst).await,
+            SetRange(cmd) => cmd.apply(db, dst).await,
+            SlowLogGet(cmd) => cmd.apply(db, dst).await,
+            SlowLogLen(cmd) => cmd.apply(db, dst).await,
+            SlowLogReset(cmd) => cmd.apply(db, dst).await,
+            StrLen(cmd) => cmd.apply(db, dst).await,
             Ping(cmd) => cmd.apply(dst).await,
+            Touch(cmd) => cmd.apply(db, dst).await,
+            Ttl(cmd) => cmd.apply(db, dst).await,
+            Pttl(cmd) => cmd.apply(db, dst).await,
+            Type(cmd) => cmd.apply(db, dst).await,
+            Unlink(cmd) => cmd.apply(db, dst).await,
+            Wait(cmd) => cmd.apply(dst, shutdown).await,
             Unknown(cmd) => cmd.apply(dst).await,
-            // `Unsubscribe` cannot be applied. It may only be received from the
-            // context of a `Subscribe` command.
+            // Subscribing runs until the peer disconnects or resets the
+            // connection, which must then be reset by the connection handler
+            // in `server.rs`.
+            Subscribe(_) | PSubscribe(_) => {
+                Err("subscribe commands are unsupported in this context".into())
+            }
+            // Resetting the connection resets per-connection state and is
+            // handled by the connection handler in `server.rs`.
+            Reset(_) => Err("`Reset` is unsupported in this context".into()),
+            // Closing the connection is handled by the connection handler too.
+            Quit(_) => Err("`Quit` is unsupported in this context".into()),
+            // So is streaming the commands of other connections.
+            Monitor(_) => Err("`Monitor` is unsupported in this context".into()),
+            // `Unsubscribe` and `PUnsubscribe` are handled by the subscribe
+            // loop while subscribed, and by the connection handler otherwise.
             Unsubscribe(_) => Err("`Unsubscribe` is unsupported in this context".into()),
+            PUnsubscribe(_) => Err("`PUnsubscribe` is unsupported in this context".into()),
+            // Transactions are per-connection state and are handled by the
+            // connection handler in `server.rs`.
+            Multi(_) | Exec(_) | Discard(_) => {
+                Err("transaction commands are unsupported in this context".into())
+            }
+            // Authentication is per-connection state and is handled by the
+            // connection handler in `server.rs`.
+            Auth(_) => Err("`Auth` is unsupported in this context".into()),
+            // The selected database is per-connection state and is handled by
+            // the connection handler in `server.rs`.
+            Select(_) => Err("`Select` is unsupported in this context".into()),
+            // `FlushAll` acts on every database and is applied by the
+            // connection handler in `server.rs`, which holds all of them.
+            FlushAll(_) => Err("`FlushAll` is unsupported in this context".into()),
+            // `Save` writes every database and is applied by the connection
+            // handler in `server.rs`, which also knows where to write them.
+            Save(_) => Err("`Save` is unsupported in this context".into()),
+            // These `DEBUG` subcommands act on every database too.
+            DebugSetActiveExpire(_) | DebugExpireSweep(_) => {
+                Err("these `DEBUG` subcommands are unsupported in this context".into())
+            }
+            // Shutting down the server is handled by the connection handler
+            // too, which holds the shutdown signal of the server.
+            Shutdown(_) => Err("`Shutdown` is unsupported in this context".into()),
+            // Blocking pops wait without holding the command lock, and log the
+            // values they pop, they are applied by the connection handler in
+            // `server.rs`.
+            BLPop(_) | BRPop(_) => Err("blocking pops are unsupported in this context".into()),
+            // The name of the connection and whether it is replied are
+            // per-connection state and are handled by the connection handler
+            // in `server.rs`.
+            ClientGetName(_) | ClientSetName(_) | ClientReply(_) => {
+                Err("`Client` is unsupported in this context".into())
+            }
+            // `Info` reports on every database and is applied by the
+            // connection handler in `server.rs`, which holds all of them.
+            Info(_) => Err("`Info` is unsupported in this context".into()),
+            // Runtime parameters are set on every database, `CONFIG` is applied
+            // by the connection handler in `server.rs`, which holds all of
+            // them.
+            ConfigGet(_) | ConfigSet(_) => Err("`Config` is unsupported in this context".into()),
+            // `Move` acts on two databases and is applied by the connection
+            // handler in `server.rs`, which holds all of them.
+            Move(_) => Err("`Move` is unsupported in this context".into()),
+            // `SwapDb` acts on two databases too.
+            SwapDb(_) => Err("`SwapDb` is unsupported in this context".into()),
         }
     }
 
     /// Returns the command name
     pub(crate) fn get_name(&self) -> &str {
         match self {
+            Command::Append(_) => "append",
+            Command::Auth(_) => "auth",
+            Command::BitCount(_) => "bitcount",
+            Command::BitPos(_) => "bitpos",
+            Command::BLPop(_) => "blpop",
+            Command::BRPop(_) => "brpop",
+            Command::ClientGetName(_)
+            | Command::ClientKill(_)
+            | Command::ClientList(_)
+            | Command::ClientSetName(_)
+            | Command::ClientReply(_)
+            | Command::ClientPause(_)
+            | Command::ClientUnpause(_) => "client",
+            Command::CommandTable(_) | Command::CommandCount(_) | Command::CommandDocs(_) => {
+                "command"
+            }
+            Command::ConfigGet(_) | Command::ConfigSet(_) => "config",
+            Command::Copy(_) => "copy",
+            Command::DbSize(_) => "dbsize",
+            Command::DebugObject(_)
+            | Command::DebugSleep(_)
+            | Command::DebugSetActiveExpire(_)
+            | Command::DebugExpireSweep(_) => "debug",
+            Command::Del(_) => "del",
+            Command::Dump(_) => "dump",
+            Command::Echo(_) => "echo",
+            Command::Eval(_) => "eval",
+            Command::Exists(_) => "exists",
+            Command::Expire(_) => "expire",
+            Command::ExpireAt(_) => "expireat",
+            Command::PExpireAt(_) => "pexpireat",
+            Command::Persist(_) => "persist",
+            Command::FlushDb(_) => "flushdb",
+            Command::FlushAll(_) => "flushall",
             Command::Get(_) => "get",
-            Command::MultiGet(_) => "multiget",
-            Command::Publish(_) => "pub",
+            Command::GetDel(_) => "getdel",
+            Command::GetEx(_) => "getex",
+            Command::GetBit(_) => "getbit",
+            Command::GetRange(_) => "getrange",
+            Command::GetSet(_) => "getset",
+            Command::HSet(_) => "hset",
+            Command::HGet(_) => "hget",
+            Command::HDel(_) => "hdel",
+            Command::HExists(_) => "hexists",
+            Command::HKeys(_) => "hkeys",
+            Command::HVals(_) => "hvals",
+            Command::HLen(_) => "hlen",
+            Command::HGetAll(_) => "hgetall",
+            Command::HIncrBy(_) => "hincrby",
+            Command::HIncrByFloat(_) => "hincrbyfloat",
+            Command::HRandField(_) => "hrandfield",
+            Command::SAdd(_) => "sadd",
+            Command::SRem(_) => "srem",
+            Command::SMembers(_) => "smembers",
+            Command::SIsMember(_) => "sismember",
+            Command::SCard(_) => "scard",
+            Command::SMove(_) => "smove",
+            Command::SInterCard(_) => "sintercard",
+            Command::SRandMember(_) => "srandmember",
+            Command::SInterStore(_) => "sinterstore",
+            Command::SUnionStore(_) => "sunionstore",
+            Command::SDiffStore(_) => "sdiffstore",
+            Command::ZAdd(_) => "zadd",
+            Command::ZScore(_) => "zscore",
+            Command::ZRank(_) => "zrank",
+            Command::ZRange(_) => "zrange",
+            Command::Hello(_) => "hello",
+            Command::Info(_) => "info",
+            Command::Incr(_) => "incr",
+            Command::IncrBy(_) => "incrby",
+            Command::IncrByFloat(_) => "incrbyfloat",
+            Command::Decr(_) => "decr",
+            Command::DecrBy(_) => "decrby",
+            Command::Keys(_) => "keys",
+            Command::LPush(_) => "lpush",
+            Command::RPush(_) => "rpush",
+            Command::LPushX(_) => "lpushx",
+            Command::RPushX(_) => "rpushx",
+            Command::LPop(_) => "lpop",
+            Command::RPop(_) => "rpop",
+            Command::LInsert(_) => "linsert",
+            Command::LLen(_) => "llen",
+            Command::LPos(_) => "lpos",
+            Command::LRange(_) => "lrange",
+            Command::LSet(_) => "lset",
+            Command::LMPop(_) => "lmpop",
+            Command::Monitor(_) => "monitor",
+            Command::Move(_) => "move",
+            Command::MultiGet(_) => "mget",
+            Command::MSet(_) => "mset",
+            Command::MSetNx(_) => "msetnx",
+            Command::ObjectEncoding(_) | Command::ObjectFreq(_) | Command::ObjectIdleTime(_) => {
+                "object"
+            }
+            Command::Publish(_) => "publish",
+            Command::PubSubCapacity(_) | Command::PubSubChannels(_) | Command::PubSubNumSub(_) => {
+                "pubsub"
+            }
+            Command::Quit(_) => "quit",
+            Command::RandomKey(_) => "randomkey",
+            Command::Rename(_) => "rename",
+            Command::RenameNx(_) => "renamenx",
+            Command::Reset(_) => "reset",
+            Command::Restore(_) => "restore",
+            Command::Save(_) => "save",
+            Command::Scan(_) => "scan",
+            Command::Select(_) => "select",
             Command::Set(_) => "set",
+            Command::SetNx(_) => "setnx",
+            Command::SetEx(_) => "setex",
+            Command::PSetEx(_) => "psetex",
+            Command::SetBit(_) => "setbit",
+            Command::SetRange(_) => "setrange",
+            Command::Shutdown(_) => "shutdown",
+            Command::SlowLogGet(_) | Command::SlowLogLen(_) | Command::SlowLogReset(_) => "slowlog",
+            Command::StrLen(_) => "strlen",
             Command::Subscribe(_) => "subscribe",
             Command::Unsubscribe(_) => "unsubscribe",
+            Command::PSubscribe(_) => "psubscribe",
+            Command::PUnsubscribe(_) => "punsubscribe",
             Command::Ping(_) => "ping",
+            Command::SwapDb(_) => "swapdb",
+            Command::Touch(_) => "touch",
+            Command::Ttl(_) => "ttl",
+            Command::Pttl(_) => "pttl",
+            Command::Type(_) => "type",
+            Command::Unlink(_) => "unlink",
+            Command::Wait(_) => "wait",
+            Command::Multi(_) => "multi",
+            Command::Exec(_) => "exec",
+            Command::Discard(_) => "discard",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
+
+    /// Returns `true` if the command may modify the keyspace.
+    ///
+    /// Write commands are appended to the append-only file, so that replaying
+    /// the file rebuilds the keyspace.
+    pub(crate) fn is_write(&self) -> bool {
+        use Command::*;
+
+        matches!(
+            self,
+            Append(_)
+                | Copy(_)
+                | Del(_)
+                | Expire(_)
+                | ExpireAt(_)
+                | PExpireAt(_)
+                | Persist(_)
+                | FlushDb(_)
+                | FlushAll(_)
+                | Eval(_)
+                | GetDel(_)
+                | GetEx(_)
+                | GetSet(_)
+                | HSet(_)
+                | HDel(_)
+                | HIncrBy(_)
+                | HIncrByFloat(_)
+                | SAdd(_)
+                | SRem(_)
+                | SMove(_)
+                | SInterStore(_)
+                | SUnionStore(_)
+                | SDiffStore(_)
+                | ZAdd(_)
+                | Incr(_)
+                | IncrBy(_)
+                | IncrByFloat(_)
+                | Decr(_)
+                | DecrBy(_)
+                | LPush(_)
+                | RPush(_)
+                | LPushX(_)
+                | RPushX(_)
+                | LPop(_)
+                | LMPop(_)
+                | RPop(_)
+                | LSet(_)
+                | LInsert(_)
+                | MSet(_)
+                | MSetNx(_)
+                | Move(_)
+                | SwapDb(_)
+                | Rename(_)
+                | RenameNx(_)
+                | Restore(_)
+                | Set(_)
+                | SetNx(_)
+                | SetEx(_)
+                | PSetEx(_)
+                | SetBit(_)
+                | SetRange(_)
+                | Unlink(_)
+        )
+    }
+}
+
+/// Render `key` as a string, for logging.
+///
+/// Keys are binary safe and need not be valid UTF-8. Invalid sequences are
+/// replaced with `U+FFFD`, so the result may not name the key exactly.
+pub fn key_lossy(key: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(key)
 }
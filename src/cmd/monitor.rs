@@ -0,0 +1,69 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Stream every command the server processes to the connection.
+///
+/// Each command is sent as a simple string holding the time it was received,
+/// the database and address of the connection that sent it, then the quoted
+/// command name and arguments, as Redis does:
+///
+/// ```text
+/// 1700000000.123456 [0 127.0.0.1:50000] "set" "foo" "bar"
+/// ```
+///
+/// The connection then only accepts `RESET`, which leaves monitor mode, and
+/// `QUIT`. Commands sent faster than the connection reads them are dropped.
+///
+/// The command is handled by the connection handler, which feeds the other
+/// connections' commands to the monitors.
+#[derive(Debug, Default)]
+pub struct Monitor {}
+
+impl Monitor {
+    /// Create a new `Monitor` command.
+    pub fn new() -> Monitor {
+        Monitor {}
+    }
+
+    /// Parse a `Monitor` instance from a received frame.
+    ///
+    /// The `MONITOR` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// MONITOR
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Monitor> {
+        Ok(Monitor {})
+    }
+
+    /// Confirm that the connection entered monitor mode.
+    ///
+    /// The response is written to `dst`. The connection handler streams the
+    /// commands once this is called.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Monitor` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("monitor".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Move `key` from the selected database to database `db`, along with its
+/// expiration.
+///
+/// Returns `1` if the key was moved, `0` if it does not exist in the selected
+/// database or already exists in `db`. The check and the move happen
+/// atomically.
+#[derive(Debug)]
+pub struct Move {
+    /// Name of the key to move
+    key: Bytes,
+
+    /// Index of the database to move the key to
+    db: i64,
+}
+
+impl Move {
+    /// Create a new `Move` command which moves `key` to database `db`.
+    pub fn new(key: impl AsRef<[u8]>, db: i64) -> Move {
+        Move {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            db,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the index of the destination database
+    pub fn db(&self) -> i64 {
+        self.db
+    }
+
+    /// Parse a `Move` instance from a received frame.
+    ///
+    /// The `MOVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// MOVE key db
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Move> {
+        let key = parse.next_bytes()?;
+        let db = parse.next_signed_int()?;
+
+        Ok(Move { key, db })
+    }
+
+    /// Apply the `Move` command, moving the key from `dbs[selected]` to the
+    /// requested database.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(
+        self,
+        dbs: &[Db],
+        selected: usize,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let response = match usize::try_from(self.db) {
+            Ok(index) if index == selected => {
+                Frame::Error("ERR source and destination objects are the same".to_string())
+            }
+            Ok(index) if index < dbs.len() => match dbs[selected].move_to(&self.key, &dbs[index]) {
+                Ok(moved) => Frame::Integer(moved as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            _ => Frame::Error("ERR DB index is out of range".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Move` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("move".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.db);
+        frame
+    }
+}
@@ -1,67 +1,69 @@
-use crate::{Connection, Db, Frame, Parse};
+use crate::{Connection, Db, Frame, Parse, ParseError};
 
 use bytes::Bytes;
 use tracing::{debug, instrument};
 
-/// Get the value of key.
+/// Get the values of all the given keys.
 ///
-/// If the key does not exist the special value nil is returned. An error is
-/// returned if the value stored at key is not a string, because GET only
-/// handles string values.
+/// For every key that does not exist or does not hold a string, the special
+/// value nil is returned.
 #[derive(Debug)]
 pub struct MultiGet {
     /// Name of the keys to get
-    keys: Vec<String>,
+    keys: Vec<Bytes>,
 }
 
 impl MultiGet {
-    /// Create a new `Get` command which fetches `key`.
-    pub fn new(keys: Vec<String>) -> MultiGet {
-        MultiGet {
-            keys,
-        }
+    /// Create a new `MultiGet` command which fetches `keys`.
+    pub fn new(keys: Vec<Bytes>) -> MultiGet {
+        MultiGet { keys }
     }
 
     /// Get the keys
-    pub fn keys(&self) -> &Vec<String> {
+    pub fn keys(&self) -> &Vec<Bytes> {
         &self.keys
     }
 
-    /// Parse a `Get` instance from a received frame.
+    /// Parse a `MultiGet` instance from a received frame.
     ///
     /// The `Parse` argument provides a cursor-like API to read fields from the
     /// `Frame`. At this point, the entire frame has already been received from
     /// the socket.
     ///
-    /// The `GET` string has already been consumed.
+    /// The `MGET` string has already been consumed.
     ///
     /// # Returns
     ///
-    /// Returns the `Get` value on success. If the frame is malformed, `Err` is
-    /// returned.
+    /// Returns the `MultiGet` value on success. If the frame is malformed,
+    /// `Err` is returned.
     ///
     /// # Format
     ///
-    /// Expects an array frame containing two entries.
+    /// Expects an array frame containing at least two entries. As in Redis,
+    /// the keys are not preceded by their number, they run until the end of
+    /// the frame.
     ///
     /// ```text
-    /// GET key
+    /// MGET key [key ...]
     /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MultiGet> {
-        // The `MULTIGET` string has already been consumed. The next value is the
-        // name of the key to get. If the next value is not a string or the
-        // input is fully consumed, then an error is returned.
-        let num_keys = parse.next_int()?;
-        let mut keys = Vec::new();
-        for _ in 0..num_keys {
-            let key = parse.next_string()?;
-            keys.push(key);
+        use ParseError::EndOfStream;
+
+        // At least one key must be provided.
+        let mut keys = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
         }
 
         Ok(MultiGet::new(keys))
     }
 
-    /// Apply the `Get` command to the specified `Db` instance.
+    /// Apply the `MultiGet` command to the specified `Db` instance.
     ///
     /// The response is written to `dst`. This is called by the server in order
     /// to execute a received command.
@@ -70,7 +72,9 @@ impl MultiGet {
         // Get the value from the shared database state
         let mut response = Frame::array();
         for key in self.keys {
-            if let Some(value) = db.get(&key) {
+            // Like Redis, keys that do not hold a string are reported as
+            // missing rather than failing the whole command.
+            if let Ok(Some(value)) = db.get(&key) {
                 response.push_bulk(value);
             } else {
                 response.push_null();
@@ -86,14 +90,13 @@ impl MultiGet {
 
     /// Converts the command into an equivalent `Frame`.
     ///
-    /// This is called by the client when encoding a `Get` command to send to
-    /// the server.
+    /// This is called by the client when encoding a `MultiGet` command to send
+    /// to the server.
     pub(crate) fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
-        frame.push_bulk(Bytes::from("multiget".as_bytes()));
-        frame.push_int(self.keys.len() as u64);
+        frame.push_bulk(Bytes::from("mget".as_bytes()));
         for key in self.keys {
-            frame.push_bulk(Bytes::from(key.into_bytes()));
+            frame.push_bulk(key);
         }
         frame
     }
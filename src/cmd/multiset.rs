@@ -0,0 +1,173 @@
+use crate::db::SetCondition;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Set the given keys to their respective values.
+///
+/// Existing values are replaced, whatever their type, and their expirations
+/// are removed. All keys are set at once, no client ever sees some of them
+/// updated while others are not. `OK` is always returned.
+#[derive(Debug)]
+pub struct MSet {
+    /// Key and value pairs to set
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+/// Set the given keys to their respective values, only if none of them
+/// exists.
+///
+/// Either all keys are set or none is. `1` is returned if the keys were set,
+/// `0` if at least one of them already exists.
+#[derive(Debug)]
+pub struct MSetNx {
+    /// Key and value pairs to set
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+impl MSet {
+    /// Create a new `MSet` command which sets each key of `pairs` to its value.
+    pub fn new(pairs: Vec<(Bytes, Bytes)>) -> MSet {
+        MSet { pairs }
+    }
+
+    /// Get the key and value pairs
+    pub fn pairs(&self) -> &[(Bytes, Bytes)] {
+        &self.pairs
+    }
+
+    /// Parse an `MSet` instance from a received frame.
+    ///
+    /// The `MSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the number of pairs followed by the
+    /// pairs themselves.
+    ///
+    /// ```text
+    /// MSET num_pairs key value [key value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MSet> {
+        let pairs = parse_pairs(parse)?;
+
+        Ok(MSet { pairs })
+    }
+
+    /// Apply the `MSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.mset(self.pairs, SetCondition::Always) {
+            Ok(_) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `MSet` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pairs_frame("mset", self.pairs)
+    }
+}
+
+impl MSetNx {
+    /// Create a new `MSetNx` command which sets each key of `pairs` to its
+    /// value, if none of the keys exists.
+    pub fn new(pairs: Vec<(Bytes, Bytes)>) -> MSetNx {
+        MSetNx { pairs }
+    }
+
+    /// Get the key and value pairs
+    pub fn pairs(&self) -> &[(Bytes, Bytes)] {
+        &self.pairs
+    }
+
+    /// Parse an `MSetNx` instance from a received frame.
+    ///
+    /// The `MSETNX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the number of pairs followed by the
+    /// pairs themselves.
+    ///
+    /// ```text
+    /// MSETNX num_pairs key value [key value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MSetNx> {
+        let pairs = parse_pairs(parse)?;
+
+        Ok(MSetNx { pairs })
+    }
+
+    /// Apply the `MSetNx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.mset(self.pairs, SetCondition::IfAbsent) {
+            Ok(set) => Frame::Integer(set as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `MSetNx` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        pairs_frame("msetnx", self.pairs)
+    }
+}
+
+/// Parse the `num_pairs key value [key value ...]` arguments shared by `MSET`
+/// and `MSETNX`.
+fn parse_pairs(parse: &mut Parse) -> crate::Result<Vec<(Bytes, Bytes)>> {
+    let num_pairs = parse.next_int()?;
+
+    // At least one pair must be provided.
+    if num_pairs == 0 {
+        return Err("protocol error; expected at least one key and value".into());
+    }
+
+    let mut pairs = Vec::new();
+    for _ in 0..num_pairs {
+        let key = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+        pairs.push((key, value));
+    }
+
+    Ok(pairs)
+}
+
+/// Encode a command named `name` taking key and value pairs, preceded by
+/// their number.
+fn pairs_frame(name: &str, pairs: Vec<(Bytes, Bytes)>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string().into_bytes()));
+    frame.push_int(pairs.len() as i64);
+    for (key, value) in pairs {
+        frame.push_bulk(key);
+        frame.push_bulk(value);
+    }
+    frame
+}
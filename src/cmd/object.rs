@@ -0,0 +1,233 @@
+use crate::server::MaxMemoryPolicy;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the encoding of the value stored at `key`.
+///
+/// Strings holding an integer are reported as `int`, other strings as `raw`.
+/// An error is returned if the key does not exist. The key is not marked as
+/// used.
+#[derive(Debug)]
+pub struct ObjectEncoding {
+    /// Name of the key to describe
+    key: Bytes,
+}
+
+/// Returns the number of seconds since the value stored at `key` was last read
+/// or written.
+///
+/// An error is returned if the key does not exist. The key is not marked as
+/// used, so calling this repeatedly does not reset the idle time.
+#[derive(Debug)]
+pub struct ObjectIdleTime {
+    /// Name of the key to describe
+    key: Bytes,
+}
+
+/// Returns the logarithmic access frequency counter of `key`.
+///
+/// The counter is only maintained with the `allkeys-lfu` policy, an error is
+/// returned otherwise, or if the key does not exist. The key is not marked as
+/// used.
+#[derive(Debug)]
+pub struct ObjectFreq {
+    /// Name of the key to describe
+    key: Bytes,
+}
+
+impl ObjectEncoding {
+    /// Create a new `ObjectEncoding` command which returns the encoding of the
+    /// value stored at `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> ObjectEncoding {
+        ObjectEncoding {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `ObjectEncoding` instance from a received frame.
+    ///
+    /// The `OBJECT ENCODING` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// OBJECT ENCODING key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ObjectEncoding> {
+        let key = parse.next_bytes()?;
+
+        Ok(ObjectEncoding { key })
+    }
+
+    /// Apply the `ObjectEncoding` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.encoding(&self.key) {
+            Some(encoding) => Frame::Bulk(Bytes::from(encoding)),
+            None => Frame::Error("ERR no such key".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `ObjectEncoding` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("object".as_bytes()));
+        frame.push_bulk(Bytes::from("encoding".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl ObjectIdleTime {
+    /// Create a new `ObjectIdleTime` command which returns the idle time of
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> ObjectIdleTime {
+        ObjectIdleTime {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `ObjectIdleTime` instance from a received frame.
+    ///
+    /// The `OBJECT IDLETIME` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// OBJECT IDLETIME key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ObjectIdleTime> {
+        let key = parse.next_bytes()?;
+
+        Ok(ObjectIdleTime { key })
+    }
+
+    /// Apply the `ObjectIdleTime` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.idle_time(&self.key) {
+            Some(idle) => Frame::Integer(idle.as_secs() as i64),
+            None => Frame::Error("ERR no such key".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `ObjectIdleTime` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("object".as_bytes()));
+        frame.push_bulk(Bytes::from("idletime".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl ObjectFreq {
+    /// Create a new `ObjectFreq` command which returns the access frequency of
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> ObjectFreq {
+        ObjectFreq {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `ObjectFreq` instance from a received frame.
+    ///
+    /// The `OBJECT FREQ` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// OBJECT FREQ key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ObjectFreq> {
+        let key = parse.next_bytes()?;
+
+        Ok(ObjectFreq { key })
+    }
+
+    /// Apply the `ObjectFreq` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if db.maxmemory_policy() != MaxMemoryPolicy::AllKeysLfu {
+            Frame::Error(
+                "ERR An LFU maxmemory policy is not selected, access frequency not tracked. \
+                 Please note that when switching between policies at runtime LRU and LFU \
+                 data will take some time to adjust."
+                    .to_string(),
+            )
+        } else {
+            match db.frequency(&self.key) {
+                Some(frequency) => Frame::Integer(frequency as i64),
+                None => Frame::Error("ERR no such key".to_string()),
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `ObjectFreq` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("object".as_bytes()));
+        frame.push_bulk(Bytes::from("freq".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
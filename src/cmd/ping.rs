@@ -66,6 +66,24 @@ impl Ping {
         Ok(())
     }
 
+    /// Apply the `Ping` command received in the subscribed state of a RESP2
+    /// connection.
+    ///
+    /// The reply is an array holding `pong` and the message, empty if there
+    /// is none, so that the peer can tell it apart from an error or message.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply_subscribed(self, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        response.push_bulk(Bytes::from_static(b"pong"));
+        response.push_bulk(self.msg.unwrap_or_default());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding a `Ping` command to send
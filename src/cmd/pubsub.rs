@@ -0,0 +1,268 @@
+use crate::db::MAX_CHANNEL_CAPACITY;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the channels with at least one subscriber.
+///
+/// If a glob-style pattern is given, only the channels matching it are
+/// returned. Subscriptions to patterns are not counted.
+#[derive(Debug, Default)]
+pub struct PubSubChannels {
+    /// Pattern channels must match
+    pattern: Option<String>,
+}
+
+/// Returns the number of subscribers to each of the specified channels.
+///
+/// The reply alternates channel names and counts. Subscriptions to patterns
+/// are not counted.
+#[derive(Debug, Default)]
+pub struct PubSubNumSub {
+    /// Channels to count the subscribers of
+    channels: Vec<String>,
+}
+
+/// Returns or changes the number of messages a channel buffers for its
+/// slowest subscriber.
+///
+/// A larger capacity lets subscribers fall further behind a bursty publisher
+/// before missing messages, at the cost of memory: the buffer is allocated
+/// when the channel is created, and messages are held until every subscriber
+/// received them. The new capacity applies once the channel is created again,
+/// after its current subscribers all left, so none of them loses messages to
+/// the change. The reply is the capacity the channel is created with.
+#[derive(Debug)]
+pub struct PubSubCapacity {
+    /// Channel to configure
+    channel: String,
+
+    /// New capacity of the channel, `None` to only return the current one
+    capacity: Option<usize>,
+}
+
+impl PubSubChannels {
+    /// Create a new `PubSubChannels` command which returns the active channels
+    /// matching `pattern`, or all of them if `pattern` is `None`.
+    pub fn new(pattern: Option<&str>) -> PubSubChannels {
+        PubSubChannels {
+            pattern: pattern.map(str::to_string),
+        }
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
+    /// Parse a `PubSubChannels` instance from a received frame.
+    ///
+    /// The `PUBSUB CHANNELS` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// PUBSUB CHANNELS [pattern]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSubChannels> {
+        let pattern = match parse.next_string() {
+            Ok(pattern) => Some(pattern),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(PubSubChannels { pattern })
+    }
+
+    /// Apply the `PubSubChannels` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        for channel in db.channels(self.pattern.as_deref()) {
+            response.push_bulk(Bytes::from(channel));
+        }
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PubSubChannels` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pubsub".as_bytes()));
+        frame.push_bulk(Bytes::from("channels".as_bytes()));
+        if let Some(pattern) = self.pattern {
+            frame.push_bulk(Bytes::from(pattern));
+        }
+        frame
+    }
+}
+
+impl PubSubNumSub {
+    /// Create a new `PubSubNumSub` command which counts the subscribers to
+    /// `channels`.
+    pub fn new(channels: &[&str]) -> PubSubNumSub {
+        PubSubNumSub {
+            channels: channels.iter().map(|channel| channel.to_string()).collect(),
+        }
+    }
+
+    /// Get the channels
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Parse a `PubSubNumSub` instance from a received frame.
+    ///
+    /// The `PUBSUB NUMSUB` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries. Without
+    /// channels, the reply is empty.
+    ///
+    /// ```text
+    /// PUBSUB NUMSUB [channel [channel ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSubNumSub> {
+        let mut channels = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(channel) => channels.push(channel),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PubSubNumSub { channels })
+    }
+
+    /// Apply the `PubSubNumSub` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        for channel in self.channels {
+            let count = db.subscribe_count(&channel);
+            response.push_bulk(Bytes::from(channel));
+            response.push_int(count as i64);
+        }
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PubSubNumSub` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pubsub".as_bytes()));
+        frame.push_bulk(Bytes::from("numsub".as_bytes()));
+        for channel in self.channels {
+            frame.push_bulk(Bytes::from(channel));
+        }
+        frame
+    }
+}
+
+impl PubSubCapacity {
+    /// Create a new `PubSubCapacity` command which returns the capacity of
+    /// `channel`, after setting it to `capacity` if one is given.
+    pub fn new(channel: impl ToString, capacity: Option<usize>) -> PubSubCapacity {
+        PubSubCapacity {
+            channel: channel.to_string(),
+            capacity,
+        }
+    }
+
+    /// Get the channel
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Get the new capacity
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Parse a `PubSubCapacity` instance from a received frame.
+    ///
+    /// The `PUBSUB CAPACITY` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries. The capacity
+    /// ranges from `1` to `MAX_CHANNEL_CAPACITY`.
+    ///
+    /// ```text
+    /// PUBSUB CAPACITY channel [capacity]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSubCapacity> {
+        let channel = parse.next_string()?;
+
+        let capacity = match parse.next_int() {
+            Ok(capacity) if (1..=MAX_CHANNEL_CAPACITY as u64).contains(&capacity) => {
+                Some(capacity as usize)
+            }
+            Ok(_) => return Err("capacity is out of range".into()),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(PubSubCapacity { channel, capacity })
+    }
+
+    /// Apply the `PubSubCapacity` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        if let Some(capacity) = self.capacity {
+            db.set_capacity_of(self.channel.clone(), capacity);
+        }
+
+        let response = Frame::Integer(db.capacity_of(&self.channel) as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PubSubCapacity` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pubsub".as_bytes()));
+        frame.push_bulk(Bytes::from("capacity".as_bytes()));
+        frame.push_bulk(Bytes::from(self.channel));
+        if let Some(capacity) = self.capacity {
+            frame.push_int(capacity as i64);
+        }
+        frame
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Ask the server to close the connection.
+///
+/// The server replies `OK` and closes the connection once the reply is sent.
+/// Any open transaction is discarded.
+///
+/// Closing the connection is done by the connection handler, the command only
+/// confirms it.
+#[derive(Debug, Default)]
+pub struct Quit {}
+
+impl Quit {
+    /// Create a new `Quit` command.
+    pub fn new() -> Quit {
+        Quit {}
+    }
+
+    /// Parse a `Quit` instance from a received frame.
+    ///
+    /// The `QUIT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// QUIT
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Quit> {
+        Ok(Quit {})
+    }
+
+    /// Confirm the connection is about to be closed.
+    ///
+    /// The response is written and flushed to `dst`, so the peer receives it
+    /// before the connection handler closes the connection.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Quit` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("quit".as_bytes()));
+        frame
+    }
+}
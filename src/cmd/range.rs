@@ -0,0 +1,204 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Largest string `SetRange` may produce, matching the limit Redis places on
+/// bulk strings.
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// Returns the substring of the string stored at `key`.
+///
+/// `start` and `end` are zero-based inclusive byte offsets. They can also be
+/// negative numbers indicating offsets from the end of the string, `-1` being
+/// the last byte. An empty string is returned if the range does not overlap the
+/// string or the key does not exist.
+#[derive(Debug)]
+pub struct GetRange {
+    /// Name of the key to read
+    key: Bytes,
+
+    /// Offset of the first byte to return
+    start: i64,
+
+    /// Offset of the last byte to return
+    end: i64,
+}
+
+/// Overwrite part of the string stored at `key`, starting at `offset`.
+///
+/// If `offset` is past the end of the string, the string is padded with zero
+/// bytes. A missing key is treated as an empty string. The length of the
+/// string after the write is returned.
+#[derive(Debug)]
+pub struct SetRange {
+    /// Name of the key to write
+    key: Bytes,
+
+    /// Offset at which `value` is written
+    offset: i64,
+
+    /// Bytes to write
+    value: Bytes,
+}
+
+impl GetRange {
+    /// Create a new `GetRange` command which returns the bytes of the string at
+    /// `key` between `start` and `end`, inclusive.
+    pub fn new(key: impl AsRef<[u8]>, start: i64, end: i64) -> GetRange {
+        GetRange {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            start,
+            end,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the start offset
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the end offset
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Parse a `GetRange` instance from a received frame.
+    ///
+    /// The `GETRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// GETRANGE key start end
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetRange> {
+        let key = parse.next_bytes()?;
+        let start = parse.next_signed_int()?;
+        let end = parse.next_signed_int()?;
+
+        Ok(GetRange { key, start, end })
+    }
+
+    /// Apply the `GetRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.getrange(&self.key, self.start, self.end) {
+            Ok(value) => Frame::Bulk(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getrange".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.start);
+        frame.push_int(self.end);
+        frame
+    }
+}
+
+impl SetRange {
+    /// Create a new `SetRange` command which writes `value` to the string at
+    /// `key`, starting at `offset`.
+    pub fn new(key: impl AsRef<[u8]>, offset: i64, value: Bytes) -> SetRange {
+        SetRange {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            offset,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetRange` instance from a received frame.
+    ///
+    /// The `SETRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETRANGE key offset value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetRange> {
+        let key = parse.next_bytes()?;
+        let offset = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetRange { key, offset, value })
+    }
+
+    /// Apply the `SetRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match usize::try_from(self.offset) {
+            Err(_) => Frame::Error("ERR offset is out of range".to_string()),
+            Ok(offset) if offset.saturating_add(self.value.len()) > MAX_STRING_LEN => {
+                Frame::Error("ERR string exceeds maximum allowed size (512MB)".to_string())
+            }
+            Ok(offset) => match db.setrange(&self.key, offset, &self.value) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setrange".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.offset);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
@@ -0,0 +1,174 @@
+use crate::db::RenameResult;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Rename `src` to `dst`.
+///
+/// The value and any remaining time to live move to the new name. If `dst`
+/// already exists, it is overwritten. An error is returned if `src` does not
+/// exist.
+#[derive(Debug)]
+pub struct Rename {
+    /// Name of the key to rename
+    src: Bytes,
+
+    /// New name of the key
+    dst: Bytes,
+}
+
+/// Rename `src` to `dst` if `dst` does not exist yet.
+///
+/// Replies `1` if the key was renamed and `0` if `dst` already exists. An error
+/// is returned if `src` does not exist.
+#[derive(Debug)]
+pub struct RenameNx {
+    /// Name of the key to rename
+    src: Bytes,
+
+    /// New name of the key
+    dst: Bytes,
+}
+
+impl Rename {
+    /// Create a new `Rename` command which renames `src` to `dst`.
+    pub fn new(src: impl AsRef<[u8]>, dst: impl AsRef<[u8]>) -> Rename {
+        Rename {
+            src: Bytes::copy_from_slice(src.as_ref()),
+            dst: Bytes::copy_from_slice(dst.as_ref()),
+        }
+    }
+
+    /// Get the name of the key to rename
+    pub fn src(&self) -> &Bytes {
+        &self.src
+    }
+
+    /// Get the new name of the key
+    pub fn dst(&self) -> &Bytes {
+        &self.dst
+    }
+
+    /// Parse a `Rename` instance from a received frame.
+    ///
+    /// The `RENAME` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RENAME src dst
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Rename> {
+        let src = parse.next_bytes()?;
+        let dst = parse.next_bytes()?;
+
+        Ok(Rename { src, dst })
+    }
+
+    /// Apply the `Rename` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.rename(&self.src, &self.dst, false) {
+            RenameResult::NoSuchKey => no_such_key(),
+            _ => Frame::Simple("OK".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Rename` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        rename_frame("rename", self.src, self.dst)
+    }
+}
+
+impl RenameNx {
+    /// Create a new `RenameNx` command which renames `src` to `dst` unless
+    /// `dst` already exists.
+    pub fn new(src: impl AsRef<[u8]>, dst: impl AsRef<[u8]>) -> RenameNx {
+        RenameNx {
+            src: Bytes::copy_from_slice(src.as_ref()),
+            dst: Bytes::copy_from_slice(dst.as_ref()),
+        }
+    }
+
+    /// Get the name of the key to rename
+    pub fn src(&self) -> &Bytes {
+        &self.src
+    }
+
+    /// Get the new name of the key
+    pub fn dst(&self) -> &Bytes {
+        &self.dst
+    }
+
+    /// Parse a `RenameNx` instance from a received frame.
+    ///
+    /// The `RENAMENX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RENAMENX src dst
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RenameNx> {
+        let src = parse.next_bytes()?;
+        let dst = parse.next_bytes()?;
+
+        Ok(RenameNx { src, dst })
+    }
+
+    /// Apply the `RenameNx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.rename(&self.src, &self.dst, true) {
+            RenameResult::Renamed => Frame::Integer(1),
+            RenameResult::Exists => Frame::Integer(0),
+            RenameResult::NoSuchKey => no_such_key(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `RenameNx` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        rename_frame("renamenx", self.src, self.dst)
+    }
+}
+
+fn no_such_key() -> Frame {
+    Frame::Error("ERR no such key".to_string())
+}
+
+fn rename_frame(name: &str, src: Bytes, dst: Bytes) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string().into_bytes()));
+    frame.push_bulk(src);
+    frame.push_bulk(dst);
+    frame
+}
@@ -0,0 +1,63 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Return the connection to the state of a new connection.
+///
+/// The connection leaves subscribed mode, discards any open transaction,
+/// selects database `0`, forgets its name and switches back to RESP2. If the
+/// server requires a password, the connection must authenticate again. This
+/// lets connection pools hand a used connection to a new user safely.
+///
+/// The state is per-connection and is reset by the connection handler, the
+/// command only confirms it.
+#[derive(Debug, Default)]
+pub struct Reset {}
+
+impl Reset {
+    /// Create a new `Reset` command.
+    pub fn new() -> Reset {
+        Reset {}
+    }
+
+    /// Parse a `Reset` instance from a received frame.
+    ///
+    /// The `RESET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// RESET
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Reset> {
+        Ok(Reset {})
+    }
+
+    /// Confirm the reset of the connection.
+    ///
+    /// The response is written to `dst`. The connection handler resets its
+    /// state before calling this.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Simple("RESET".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Reset` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("reset".as_bytes()));
+        frame
+    }
+}
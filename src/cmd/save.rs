@@ -0,0 +1,75 @@
+use crate::{snapshot, Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::path::Path;
+use tracing::{debug, error, instrument};
+
+/// Write a snapshot of every database to disk.
+///
+/// The snapshot is written to the path the server was configured with and is
+/// loaded again when the server starts. Replies `OK` once the snapshot has been
+/// written.
+#[derive(Debug, Default)]
+pub struct Save {}
+
+impl Save {
+    /// Create a new `Save` command.
+    pub fn new() -> Save {
+        Save {}
+    }
+
+    /// Parse a `Save` instance from a received frame.
+    ///
+    /// The `SAVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// SAVE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Save> {
+        Ok(Save {})
+    }
+
+    /// Apply the `Save` command, writing all of the server's databases to
+    /// `path`.
+    ///
+    /// The response is written to `dst`. Like `FlushAll`, this acts on every
+    /// database, so it is called by the connection handler with the full list
+    /// of databases. `path` is `None` when persistence is not configured.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(
+        self,
+        dbs: &[Db],
+        path: Option<&Path>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let response = match path {
+            Some(path) => match snapshot::save(dbs, path).await {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => {
+                    error!(cause = %err, "failed to save snapshot");
+                    Frame::Error(format!("ERR failed to save snapshot: {}", err))
+                }
+            },
+            None => Frame::Error("ERR no snapshot file configured".to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Save` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("save".as_bytes()));
+        frame
+    }
+}
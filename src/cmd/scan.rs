@@ -0,0 +1,137 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Number of keys returned per batch when `COUNT` is not given, as in Redis.
+const DEFAULT_COUNT: u64 = 10;
+
+/// Incrementally iterate over the keys of the selected database.
+///
+/// Each call returns a batch of keys along with the cursor to pass to the next
+/// call. Iteration starts with cursor `0` and is complete when the returned
+/// cursor is `0`. Every key present for the entire iteration is returned at
+/// least once, though keys may be returned more than once.
+///
+/// `COUNT` bounds the number of keys visited per call. `MATCH` only returns
+/// keys matching a glob-style pattern. As it is applied to the visited keys,
+/// calls may return fewer keys than `COUNT`, or none, before the iteration is
+/// complete.
+#[derive(Debug)]
+pub struct Scan {
+    /// Cursor returned by the previous call, or `0` to start iterating
+    cursor: u64,
+
+    /// Pattern the returned keys match
+    pattern: Option<Bytes>,
+
+    /// Number of keys to visit
+    count: Option<u64>,
+}
+
+impl Scan {
+    /// Create a new `Scan` command which returns the keys following `cursor`.
+    ///
+    /// At most `count` keys are visited, only those matching `pattern` are
+    /// returned.
+    pub fn new(cursor: u64, pattern: Option<Bytes>, count: Option<u64>) -> Scan {
+        Scan {
+            cursor,
+            pattern,
+            count,
+        }
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Get the pattern
+    pub fn pattern(&self) -> Option<&Bytes> {
+        self.pattern.as_ref()
+    }
+
+    /// Get the count
+    pub fn count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Parse a `Scan` instance from a received frame.
+    ///
+    /// The `SCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SCAN cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        use ParseError::EndOfStream;
+
+        let cursor = parse.next_int()?;
+
+        let mut pattern = None;
+        let mut count = None;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "MATCH" => pattern = Some(parse.next_bytes()?),
+                Ok(s) if s.to_uppercase() == "COUNT" => count = Some(parse.next_int()?),
+                Ok(_) => return Err("`SCAN` only supports the MATCH and COUNT options".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Scan {
+            cursor,
+            pattern,
+            count,
+        })
+    }
+
+    /// Apply the `Scan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let count = self.count.unwrap_or(DEFAULT_COUNT) as usize;
+        let (cursor, keys) = db.scan(self.cursor, count, self.pattern.as_deref());
+
+        let keys = keys.into_iter().map(Frame::Bulk).collect();
+
+        let response = Frame::Array(vec![
+            Frame::Bulk(Bytes::from(cursor.to_string())),
+            Frame::Array(keys),
+        ]);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Scan` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("scan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string()));
+        if let Some(pattern) = self.pattern {
+            frame.push_bulk(Bytes::from("match".as_bytes()));
+            frame.push_bulk(pattern);
+        }
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_int(count as i64);
+        }
+        frame
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Select the logical database the connection operates on.
+///
+/// A server holds a fixed number of databases, numbered from zero. New
+/// connections start on database `0`. Keys live in a single database, but
+/// pub/sub channels are shared by all of them.
+#[derive(Debug)]
+pub struct Select {
+    /// Index of the database to select
+    index: i64,
+}
+
+impl Select {
+    /// Create a new `Select` command which selects database `index`.
+    pub fn new(index: i64) -> Select {
+        Select { index }
+    }
+
+    /// Get the database index
+    pub fn index(&self) -> i64 {
+        self.index
+    }
+
+    /// Parse a `Select` instance from a received frame.
+    ///
+    /// The `SELECT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SELECT index
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Select> {
+        let index = parse.next_signed_int()?;
+
+        Ok(Select { index })
+    }
+
+    /// Check the index against the number of databases the server holds.
+    ///
+    /// The response is written to `dst`. Returns the index to switch to if it
+    /// is in range. The selected database itself is per-connection state owned
+    /// by the connection handler.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(
+        self,
+        databases: usize,
+        dst: &mut Connection,
+    ) -> crate::Result<Option<usize>> {
+        let index = match usize::try_from(self.index) {
+            Ok(index) if index < databases => Some(index),
+            _ => None,
+        };
+
+        let response = match index {
+            Some(_) => Frame::Simple("OK".to_string()),
+            None => Frame::Error("ERR DB index is out of range".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(index)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Select` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("select".as_bytes()));
+        frame.push_bulk(Bytes::from(self.index.to_string().into_bytes()));
+        frame
+    }
+}
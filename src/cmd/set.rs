@@ -1,15 +1,18 @@
 use crate::cmd::{Parse, ParseError};
+use crate::db::{SetCondition, SetExpiration};
 use crate::{Connection, Db, Frame};
 
 use bytes::Bytes;
+use std::convert::TryFrom;
 use std::time::Duration;
+use tokio::time::Instant;
 use tracing::{debug, instrument};
 
 /// Set `key` to hold the string `value`.
 ///
 /// If `key` already holds a value, it is overwritten, regardless of its type.
 /// Any previous time to live associated with the key is discarded on successful
-/// SET operation.
+/// SET operation, unless `KEEPTTL` is given.
 ///
 /// # Options
 ///
@@ -17,16 +20,48 @@ use tracing::{debug, instrument};
 ///
 /// * EX `seconds` -- Set the specified expire time, in seconds.
 /// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * KEEPTTL -- Keep the time to live of the previous value.
+/// * NX -- Only set the key if it does not already exist.
+/// * XX -- Only set the key if it already exists.
+/// * GET -- Reply with the previous value, or null if the key did not exist.
+///
+/// The expire time must be positive.
+///
+/// When the NX or XX condition does not hold, nothing is written and the
+/// reply is null, or the previous value with `GET`. With `GET`, an error is
+/// returned and nothing is written if the key holds a value that is not a
+/// string.
 #[derive(Debug)]
 pub struct Set {
     /// the lookup key
-    key: String,
+    key: Bytes,
 
     /// the value to be stored
     value: Bytes,
 
     /// When to expire the key
     expire: Option<Duration>,
+
+    /// Condition under which the value is written
+    condition: SetCondition,
+
+    /// Whether the time to live of the previous value is kept
+    keep_ttl: bool,
+
+    /// Whether the previous value is returned
+    get: bool,
+}
+
+/// Set `key` to hold the string `value` if `key` does not exist.
+///
+/// Replies `1` if the key was set and `0` otherwise.
+#[derive(Debug)]
+pub struct SetNx {
+    /// the lookup key
+    key: Bytes,
+
+    /// the value to be stored
+    value: Bytes,
 }
 
 impl Set {
@@ -34,16 +69,33 @@ impl Set {
     ///
     /// If `expire` is `Some`, the value should expire after the specified
     /// duration.
-    pub fn new(key: impl ToString, value: Bytes, expire: Option<Duration>) -> Set {
+    pub fn new(key: impl AsRef<[u8]>, value: Bytes, expire: Option<Duration>) -> Set {
         Set {
-            key: key.to_string(),
+            key: Bytes::copy_from_slice(key.as_ref()),
             value,
             expire,
+            condition: SetCondition::Always,
+            keep_ttl: false,
+            get: false,
         }
     }
 
+    /// Keep the time to live of the previous value instead of discarding it.
+    ///
+    /// The expiration given to `new` must be `None`.
+    pub fn with_keep_ttl(mut self) -> Set {
+        self.keep_ttl = true;
+        self
+    }
+
+    /// Reply with the previous value instead of `OK`.
+    pub fn with_get(mut self) -> Set {
+        self.get = true;
+        self
+    }
+
     /// Get the key
-    pub fn key(&self) -> &str {
+    pub fn key(&self) -> &Bytes {
         &self.key
     }
 
@@ -57,6 +109,16 @@ impl Set {
         self.expire
     }
 
+    /// Get whether the time to live of the previous value is kept
+    pub fn keep_ttl(&self) -> bool {
+        self.keep_ttl
+    }
+
+    /// Get whether the previous value is returned
+    pub fn get(&self) -> bool {
+        self.get
+    }
+
     /// Parse a `Set` instance from a received frame.
     ///
     /// The `Parse` argument provides a cursor-like API to read fields from the
@@ -75,49 +137,75 @@ impl Set {
     /// Expects an array frame containing at least 3 entries.
     ///
     /// ```text
-    /// SET key value [EX seconds|PX milliseconds]
+    /// SET key value [EX seconds|PX milliseconds|KEEPTTL] [NX|XX] [GET]
     /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Set> {
         use ParseError::EndOfStream;
 
         // Read the key to set. This is a required field
-        let key = parse.next_string()?;
+        let key = parse.next_bytes()?;
 
         // Read the value to set. This is a required field.
         let value = parse.next_bytes()?;
 
-        // The expiration is optional. If nothing else follows, then it is
-        // `None`.
+        // The options are optional. If nothing else follows, the value is
+        // written unconditionally and does not expire.
         let mut expire = None;
+        let mut condition = SetCondition::Always;
+        let mut keep_ttl = false;
+        let mut get = false;
 
-        // Attempt to parse another string.
-        match parse.next_string() {
-            Ok(s) if s.to_uppercase() == "EX" => {
-                // An expiration is specified in seconds. The next value is an
-                // integer.
-                let secs = parse.next_int()?;
-                expire = Some(Duration::from_secs(secs));
-            }
-            Ok(s) if s.to_uppercase() == "PX" => {
-                // An expiration is specified in milliseconds. The next value is
-                // an integer.
-                let ms = parse.next_int()?;
-                expire = Some(Duration::from_millis(ms));
+        loop {
+            // Attempt to parse another string.
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "EX" => {
+                    // An expiration is specified in seconds. The next value is
+                    // an integer.
+                    let secs = parse.next_signed_int()?;
+                    expire = Some(parse_expire(secs, Duration::from_secs)?);
+                }
+                Ok(s) if s.to_uppercase() == "PX" => {
+                    // An expiration is specified in milliseconds. The next
+                    // value is an integer.
+                    let ms = parse.next_signed_int()?;
+                    expire = Some(parse_expire(ms, Duration::from_millis)?);
+                }
+                Ok(s) if s.to_uppercase() == "NX" => condition = SetCondition::IfAbsent,
+                Ok(s) if s.to_uppercase() == "XX" => condition = SetCondition::IfPresent,
+                Ok(s) if s.to_uppercase() == "KEEPTTL" => keep_ttl = true,
+                Ok(s) if s.to_uppercase() == "GET" => get = true,
+                // Currently, mini-redis does not support any of the other SET
+                // options. An error here results in the connection being
+                // terminated. Other connections will continue to operate
+                // normally.
+                Ok(_) => {
+                    return Err(
+                        "currently `SET` only supports the expiration, NX/XX and GET options"
+                            .into(),
+                    )
+                }
+                // The `EndOfStream` error indicates there is no further data to
+                // parse. In this case, it is a normal run time situation and
+                // indicates there are no more `SET` options.
+                Err(EndOfStream) => break,
+                // All other errors are bubbled up, resulting in the connection
+                // being terminated.
+                Err(err) => return Err(err.into()),
             }
-            // Currently, mini-redis does not support any of the other SET
-            // options. An error here results in the connection being
-            // terminated. Other connections will continue to operate normally.
-            Ok(_) => return Err("currently `SET` only supports the expiration option".into()),
-            // The `EndOfStream` error indicates there is no further data to
-            // parse. In this case, it is a normal run time situation and
-            // indicates there are no specified `SET` options.
-            Err(EndOfStream) => {}
-            // All other errors are bubbled up, resulting in the connection
-            // being terminated.
-            Err(err) => return Err(err.into()),
         }
 
-        Ok(Set { key, value, expire })
+        if keep_ttl && expire.is_some() {
+            return Err("`SET` does not support KEEPTTL along with an expiration".into());
+        }
+
+        Ok(Set {
+            key,
+            value,
+            expire,
+            condition,
+            keep_ttl,
+            get,
+        })
     }
 
     /// Apply the `Set` command to the specified `Db` instance.
@@ -126,11 +214,25 @@ impl Set {
     /// to execute a received command.
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expire = match self.expire {
+            Some(duration) => SetExpiration::In(duration),
+            None if self.keep_ttl => SetExpiration::Keep,
+            None => SetExpiration::Clear,
+        };
+
         // Set the value in the shared database state.
-        db.set(self.key, self.value, self.expire);
+        let response = match db.set_and_get(self.key, self.value, expire, self.condition, self.get)
+        {
+            Ok((_, prev)) if self.get => match prev {
+                Some(prev) => Frame::Bulk(prev),
+                None => Frame::Null,
+            },
+            Ok((true, _)) => Frame::Simple("OK".to_string()),
+            Ok((false, _)) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
 
-        // Create a success response and write it to `dst`.
-        let response = Frame::Simple("OK".to_string());
+        // Write the response to `dst`.
         debug!(?response);
         dst.write_frame(&response).await?;
 
@@ -144,7 +246,7 @@ impl Set {
     pub(crate) fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
         frame.push_bulk(Bytes::from("set".as_bytes()));
-        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.key);
         frame.push_bulk(self.value);
         if let Some(ms) = self.expire {
             // Expirations in Redis procotol can be specified in two ways
@@ -154,8 +256,99 @@ impl Set {
             // src/bin/cli.rs parses the expiration argument as milliseconds
             // in duration_from_ms_str()
             frame.push_bulk(Bytes::from("px".as_bytes()));
-            frame.push_int(ms.as_millis() as u64);
+            frame.push_int(ms.as_millis() as i64);
+        }
+        match self.condition {
+            SetCondition::Always => {}
+            SetCondition::IfAbsent => frame.push_bulk(Bytes::from("nx".as_bytes())),
+            SetCondition::IfPresent => frame.push_bulk(Bytes::from("xx".as_bytes())),
+        }
+        if self.keep_ttl {
+            frame.push_bulk(Bytes::from("keepttl".as_bytes()));
+        }
+        if self.get {
+            frame.push_bulk(Bytes::from("get".as_bytes()));
         }
         frame
     }
 }
+
+impl SetNx {
+    /// Create a new `SetNx` command which sets `key` to `value` unless `key`
+    /// already exists.
+    pub fn new(key: impl AsRef<[u8]>, value: Bytes) -> SetNx {
+        SetNx {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetNx` instance from a received frame.
+    ///
+    /// The `SETNX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SETNX key value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetNx> {
+        let key = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetNx { key, value })
+    }
+
+    /// Apply the `SetNx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.set_if(self.key, self.value, None, SetCondition::IfAbsent) {
+            Ok(set) => Frame::Integer(set as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetNx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setnx".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
+
+/// Convert the time to live given to `SET` with `unit`.
+///
+/// As in Redis, a time to live that is not positive is rejected, and so is one
+/// too far in the future to be represented, before the key is locked.
+fn parse_expire(value: i64, unit: fn(u64) -> Duration) -> crate::Result<Duration> {
+    match u64::try_from(value) {
+        Ok(value) if value > 0 && Instant::now().checked_add(unit(value)).is_some() => {
+            Ok(unit(value))
+        }
+        _ => Err("invalid expire time in 'set' command".into()),
+    }
+}
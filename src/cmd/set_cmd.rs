@@ -0,0 +1,968 @@
+use crate::cmd::{Parse, ParseError};
+use crate::db::SetOp;
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Add the specified members to the set stored at key.
+///
+/// If key does not exist, a new set is created. Members already in the set are
+/// ignored. The number of members that were added is returned.
+#[derive(Debug)]
+pub struct SAdd {
+    /// Name of the set
+    key: Bytes,
+
+    /// Members to add
+    members: Vec<Bytes>,
+}
+
+/// Remove the specified members from the set stored at key.
+///
+/// Members that are not in the set are ignored. The number of members that
+/// were removed is returned.
+#[derive(Debug)]
+pub struct SRem {
+    /// Name of the set
+    key: Bytes,
+
+    /// Members to remove
+    members: Vec<Bytes>,
+}
+
+/// Returns all members of the set stored at key.
+///
+/// The members are returned in no particular order.
+#[derive(Debug)]
+pub struct SMembers {
+    /// Name of the set
+    key: Bytes,
+}
+
+/// Returns whether member is a member of the set stored at key.
+///
+/// `1` is returned if it is, `0` if it is not or the key does not exist.
+#[derive(Debug)]
+pub struct SIsMember {
+    /// Name of the set
+    key: Bytes,
+
+    /// Member to look for
+    member: Bytes,
+}
+
+/// Returns the number of members of the set stored at key.
+///
+/// `0` is returned if the key does not exist.
+#[derive(Debug)]
+pub struct SCard {
+    /// Name of the set
+    key: Bytes,
+}
+
+/// Move member from the set stored at source to the set stored at
+/// destination.
+///
+/// The move is atomic: other clients see the member in exactly one of the
+/// sets. `1` is returned if the member was moved, `0` if it was not in the
+/// source set. The source key is removed once its set is empty.
+#[derive(Debug)]
+pub struct SMove {
+    /// Name of the set to move the member from
+    source: Bytes,
+
+    /// Name of the set to move the member to
+    destination: Bytes,
+
+    /// Member to move
+    member: Bytes,
+}
+
+/// Store the intersection of the sets stored at the given keys in
+/// destination.
+///
+/// Missing keys are treated as empty sets, and destination is removed if the
+/// intersection is empty. The number of members of the result is returned.
+#[derive(Debug)]
+pub struct SInterStore {
+    /// Name of the set to store the result in
+    destination: Bytes,
+
+    /// Names of the sets to intersect
+    keys: Vec<Bytes>,
+}
+
+/// Store the union of the sets stored at the given keys in destination.
+///
+/// Missing keys are treated as empty sets, and destination is removed if the
+/// union is empty. The number of members of the result is returned.
+#[derive(Debug)]
+pub struct SUnionStore {
+    /// Name of the set to store the result in
+    destination: Bytes,
+
+    /// Names of the sets to unite
+    keys: Vec<Bytes>,
+}
+
+/// Store the members of the first set that are in none of the other sets in
+/// destination.
+///
+/// Missing keys are treated as empty sets, and destination is removed if the
+/// difference is empty. The number of members of the result is returned.
+#[derive(Debug)]
+pub struct SDiffStore {
+    /// Name of the set to store the result in
+    destination: Bytes,
+
+    /// Names of the sets to subtract from the first one
+    keys: Vec<Bytes>,
+}
+
+/// Returns the number of members of the intersection of the sets stored at
+/// the given keys, without returning the members.
+///
+/// Counting stops once `limit` members are found, `0` meaning no limit. Any
+/// missing key makes the intersection empty.
+#[derive(Debug)]
+pub struct SInterCard {
+    /// Names of the sets to intersect
+    keys: Vec<Bytes>,
+
+    /// Maximum number of members to count
+    limit: Option<u64>,
+}
+
+/// Returns random members of the set stored at key.
+///
+/// Without a count, a single member is returned, `nil` if the key does not
+/// exist. With a positive count, that many distinct members are returned, all
+/// of them if the set has fewer. With a negative count, exactly that many
+/// members are returned, the same member possibly several times. An empty
+/// array is returned if the key does not exist.
+#[derive(Debug)]
+pub struct SRandMember {
+    /// Name of the set
+    key: Bytes,
+
+    /// Number of members to return
+    count: Option<i64>,
+}
+
+impl SAdd {
+    /// Create a new `SAdd` command which adds `members` to the set at `key`.
+    pub fn new(key: impl AsRef<[u8]>, members: Vec<Bytes>) -> SAdd {
+        SAdd {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the members
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse an `SAdd` instance from a received frame.
+    ///
+    /// The `SADD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SADD key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SAdd> {
+        let (key, members) = parse_members(parse)?;
+
+        Ok(SAdd { key, members })
+    }
+
+    /// Apply the `SAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.sadd(&self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        members_frame("sadd", self.key, self.members)
+    }
+}
+
+impl SRem {
+    /// Create a new `SRem` command which removes `members` from the set at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>, members: Vec<Bytes>) -> SRem {
+        SRem {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the members
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse an `SRem` instance from a received frame.
+    ///
+    /// The `SREM` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SREM key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SRem> {
+        let (key, members) = parse_members(parse)?;
+
+        Ok(SRem { key, members })
+    }
+
+    /// Apply the `SRem` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.srem(&self.key, &self.members) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SRem` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        members_frame("srem", self.key, self.members)
+    }
+}
+
+impl SMembers {
+    /// Create a new `SMembers` command which fetches the set at `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> SMembers {
+        SMembers {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `SMembers` instance from a received frame.
+    ///
+    /// The `SMEMBERS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SMEMBERS key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SMembers> {
+        let key = parse.next_bytes()?;
+
+        Ok(SMembers { key })
+    }
+
+    /// Apply the `SMembers` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.smembers(&self.key) {
+            Ok(members) => {
+                let mut response = Frame::array();
+                for member in members {
+                    response.push_bulk(member);
+                }
+                response
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SMembers` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("smembers".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl SIsMember {
+    /// Create a new `SIsMember` command which checks whether `member` is in
+    /// the set at `key`.
+    pub fn new(key: impl AsRef<[u8]>, member: Bytes) -> SIsMember {
+        SIsMember {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            member,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse an `SIsMember` instance from a received frame.
+    ///
+    /// The `SISMEMBER` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SISMEMBER key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SIsMember> {
+        let key = parse.next_bytes()?;
+        let member = parse.next_bytes()?;
+
+        Ok(SIsMember { key, member })
+    }
+
+    /// Apply the `SIsMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.sismember(&self.key, &self.member) {
+            Ok(found) => Frame::Integer(found as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SIsMember` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sismember".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.member);
+        frame
+    }
+}
+
+impl SCard {
+    /// Create a new `SCard` command which counts the members of the set at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> SCard {
+        SCard {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse an `SCard` instance from a received frame.
+    ///
+    /// The `SCARD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SCARD key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SCard> {
+        let key = parse.next_bytes()?;
+
+        Ok(SCard { key })
+    }
+
+    /// Apply the `SCard` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.scard(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SCard` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("scard".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl SMove {
+    /// Create a new `SMove` command which moves `member` from the set at
+    /// `source` to the set at `destination`.
+    pub fn new(source: impl AsRef<[u8]>, destination: impl AsRef<[u8]>, member: Bytes) -> SMove {
+        SMove {
+            source: Bytes::copy_from_slice(source.as_ref()),
+            destination: Bytes::copy_from_slice(destination.as_ref()),
+            member,
+        }
+    }
+
+    /// Get the source
+    pub fn source(&self) -> &Bytes {
+        &self.source
+    }
+
+    /// Get the destination
+    pub fn destination(&self) -> &Bytes {
+        &self.destination
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse an `SMove` instance from a received frame.
+    ///
+    /// The `SMOVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SMOVE source destination member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SMove> {
+        let source = parse.next_bytes()?;
+        let destination = parse.next_bytes()?;
+        let member = parse.next_bytes()?;
+
+        Ok(SMove {
+            source,
+            destination,
+            member,
+        })
+    }
+
+    /// Apply the `SMove` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.smove(&self.source, &self.destination, self.member) {
+            Ok(moved) => Frame::Integer(moved as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SMove` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("smove".as_bytes()));
+        frame.push_bulk(self.source);
+        frame.push_bulk(self.destination);
+        frame.push_bulk(self.member);
+        frame
+    }
+}
+
+impl SInterStore {
+    /// Create a new `SInterStore` command which stores the intersection of the
+    /// sets at `keys` in `destination`.
+    pub fn new(destination: impl AsRef<[u8]>, keys: Vec<Bytes>) -> SInterStore {
+        SInterStore {
+            destination: Bytes::copy_from_slice(destination.as_ref()),
+            keys,
+        }
+    }
+
+    /// Get the destination
+    pub fn destination(&self) -> &Bytes {
+        &self.destination
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse an `SInterStore` instance from a received frame.
+    ///
+    /// The `SINTERSTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SINTERSTORE destination key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SInterStore> {
+        let (destination, keys) = parse_members(parse)?;
+
+        Ok(SInterStore { destination, keys })
+    }
+
+    /// Apply the `SInterStore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = store_response(db, SetOp::Inter, &self.destination, &self.keys);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SInterStore` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        members_frame("sinterstore", self.destination, self.keys)
+    }
+}
+
+impl SUnionStore {
+    /// Create a new `SUnionStore` command which stores the union of the sets
+    /// at `keys` in `destination`.
+    pub fn new(destination: impl AsRef<[u8]>, keys: Vec<Bytes>) -> SUnionStore {
+        SUnionStore {
+            destination: Bytes::copy_from_slice(destination.as_ref()),
+            keys,
+        }
+    }
+
+    /// Get the destination
+    pub fn destination(&self) -> &Bytes {
+        &self.destination
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse an `SUnionStore` instance from a received frame.
+    ///
+    /// The `SUNIONSTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SUNIONSTORE destination key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SUnionStore> {
+        let (destination, keys) = parse_members(parse)?;
+
+        Ok(SUnionStore { destination, keys })
+    }
+
+    /// Apply the `SUnionStore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = store_response(db, SetOp::Union, &self.destination, &self.keys);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SUnionStore` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        members_frame("sunionstore", self.destination, self.keys)
+    }
+}
+
+impl SDiffStore {
+    /// Create a new `SDiffStore` command which stores the members of the first
+    /// set of `keys` that are in none of the others in `destination`.
+    pub fn new(destination: impl AsRef<[u8]>, keys: Vec<Bytes>) -> SDiffStore {
+        SDiffStore {
+            destination: Bytes::copy_from_slice(destination.as_ref()),
+            keys,
+        }
+    }
+
+    /// Get the destination
+    pub fn destination(&self) -> &Bytes {
+        &self.destination
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse an `SDiffStore` instance from a received frame.
+    ///
+    /// The `SDIFFSTORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// SDIFFSTORE destination key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SDiffStore> {
+        let (destination, keys) = parse_members(parse)?;
+
+        Ok(SDiffStore { destination, keys })
+    }
+
+    /// Apply the `SDiffStore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = store_response(db, SetOp::Diff, &self.destination, &self.keys);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SDiffStore` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        members_frame("sdiffstore", self.destination, self.keys)
+    }
+}
+
+impl SInterCard {
+    /// Create a new `SInterCard` command which counts the members of the
+    /// intersection of the sets at `keys`, up to `limit`.
+    pub fn new(keys: Vec<Bytes>, limit: Option<u64>) -> SInterCard {
+        SInterCard { keys, limit }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Get the maximum number of members to count
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// Parse an `SInterCard` instance from a received frame.
+    ///
+    /// The `SINTERCARD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the number of keys, the keys and
+    /// optionally the limit.
+    ///
+    /// ```text
+    /// SINTERCARD numkeys key [key ...] [LIMIT limit]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SInterCard> {
+        let numkeys = parse.next_int()?;
+        if numkeys == 0 {
+            return Err("numkeys should be greater than 0".into());
+        }
+
+        let mut keys = vec![];
+        for _ in 0..numkeys {
+            match parse.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => {
+                    return Err("Number of keys can't be greater than number of args".into())
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let limit = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "LIMIT" => Some(parse.next_int()?),
+            Ok(_) => return Err("`SINTERCARD` only supports the LIMIT option".into()),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(SInterCard { keys, limit })
+    }
+
+    /// Apply the `SInterCard` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let limit = self
+            .limit
+            .filter(|&limit| limit > 0)
+            .map(|limit| limit as usize);
+
+        let response = match db.sintercard(&self.keys, limit) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SInterCard` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sintercard".as_bytes()));
+        frame.push_int(self.keys.len() as i64);
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        if let Some(limit) = self.limit {
+            frame.push_bulk(Bytes::from("limit".as_bytes()));
+            frame.push_int(limit as i64);
+        }
+        frame
+    }
+}
+
+/// Combine the sets at `keys` with `op` and store the result at
+/// `destination`, producing the reply frame.
+fn store_response(db: &Db, op: SetOp, destination: &[u8], keys: &[Bytes]) -> Frame {
+    match db.set_store(op, destination, keys) {
+        Ok(len) => Frame::Integer(len as i64),
+        Err(err) => Frame::Error(err.to_string()),
+    }
+}
+
+impl SRandMember {
+    /// Create a new `SRandMember` command which picks `count` members of the
+    /// set at `key`, a single one if `count` is `None`.
+    pub fn new(key: impl AsRef<[u8]>, count: Option<i64>) -> SRandMember {
+        SRandMember {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            count,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the number of members to return
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Parse an `SRandMember` instance from a received frame.
+    ///
+    /// The `SRANDMEMBER` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// SRANDMEMBER key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SRandMember> {
+        let key = parse.next_bytes()?;
+
+        let count = match parse.next_signed_int() {
+            Ok(count) => Some(count),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(SRandMember { key, count })
+    }
+
+    /// Apply the `SRandMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match (
+            db.srandmember(&self.key, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(mut members), None) => match members.pop() {
+                Some(member) => Frame::Bulk(member),
+                None => Frame::Null,
+            },
+            (Ok(members), Some(_)) => {
+                let mut response = Frame::array();
+                for member in members {
+                    response.push_bulk(member);
+                }
+                response
+            }
+            (Err(err), _) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `SRandMember` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("srandmember".as_bytes()));
+        frame.push_bulk(self.key);
+        if let Some(count) = self.count {
+            frame.push_int(count);
+        }
+        frame
+    }
+}
+
+/// Parse the `key member [member ...]` arguments shared by `SADD` and `SREM`.
+///
+/// The `destination key [key ...]` arguments of the store commands have the
+/// same shape and are parsed with it as well.
+fn parse_members(parse: &mut Parse) -> crate::Result<(Bytes, Vec<Bytes>)> {
+    use ParseError::EndOfStream;
+
+    let key = parse.next_bytes()?;
+
+    // At least one member must be provided.
+    let mut members = vec![parse.next_bytes()?];
+
+    loop {
+        match parse.next_bytes() {
+            Ok(member) => members.push(member),
+            Err(EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok((key, members))
+}
+
+/// Encode a command named `name` taking a key and members.
+fn members_frame(name: &str, key: Bytes, members: Vec<Bytes>) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from(name.to_string().into_bytes()));
+    frame.push_bulk(key);
+    for member in members {
+        frame.push_bulk(member);
+    }
+    frame
+}
@@ -0,0 +1,212 @@
+use crate::cmd::Parse;
+use crate::db::SetCondition;
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+/// Set `key` to hold the string `value` and expire after a number of seconds.
+///
+/// This is equivalent to `SET key value EX seconds`. The number of seconds
+/// must be positive.
+#[derive(Debug)]
+pub struct SetEx {
+    /// the lookup key
+    key: Bytes,
+
+    /// Number of seconds before the key expires
+    seconds: i64,
+
+    /// the value to be stored
+    value: Bytes,
+}
+
+/// Set `key` to hold the string `value` and expire after a number of
+/// milliseconds.
+///
+/// This is equivalent to `SET key value PX milliseconds`. The number of
+/// milliseconds must be positive.
+#[derive(Debug)]
+pub struct PSetEx {
+    /// the lookup key
+    key: Bytes,
+
+    /// Number of milliseconds before the key expires
+    millis: i64,
+
+    /// the value to be stored
+    value: Bytes,
+}
+
+impl SetEx {
+    /// Create a new `SetEx` command which sets `key` to `value`, expiring
+    /// after `seconds`.
+    pub fn new(key: impl AsRef<[u8]>, seconds: i64, value: Bytes) -> SetEx {
+        SetEx {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            seconds,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the number of seconds before the key expires
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetEx` instance from a received frame.
+    ///
+    /// The `SETEX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETEX key seconds value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetEx> {
+        let key = parse.next_bytes()?;
+        let seconds = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(SetEx {
+            key,
+            seconds,
+            value,
+        })
+    }
+
+    /// Apply the `SetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expire = u64::try_from(self.seconds).ok().map(Duration::from_secs);
+        let response = set_expiring(db, self.key, self.value, expire, "setex");
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SetEx` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("setex".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.seconds);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
+
+impl PSetEx {
+    /// Create a new `PSetEx` command which sets `key` to `value`, expiring
+    /// after `millis` milliseconds.
+    pub fn new(key: impl AsRef<[u8]>, millis: i64, value: Bytes) -> PSetEx {
+        PSetEx {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            millis,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the number of milliseconds before the key expires
+    pub fn millis(&self) -> i64 {
+        self.millis
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `PSetEx` instance from a received frame.
+    ///
+    /// The `PSETEX` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// PSETEX key milliseconds value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSetEx> {
+        let key = parse.next_bytes()?;
+        let millis = parse.next_signed_int()?;
+        let value = parse.next_bytes()?;
+
+        Ok(PSetEx { key, millis, value })
+    }
+
+    /// Apply the `PSetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expire = u64::try_from(self.millis).ok().map(Duration::from_millis);
+        let response = set_expiring(db, self.key, self.value, expire, "psetex");
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PSetEx` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("psetex".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.millis);
+        frame.push_bulk(self.value);
+        frame
+    }
+}
+
+/// Set `key` to `value`, expiring after `expire`, for the command `name`.
+///
+/// `expire` is `None` for a negative time to live. Like Redis, a time to live
+/// that is not positive, or too far in the future to be represented, is
+/// rejected and nothing is written.
+fn set_expiring(db: &Db, key: Bytes, value: Bytes, expire: Option<Duration>, name: &str) -> Frame {
+    let expire = expire
+        .filter(|&expire| expire > Duration::ZERO && Instant::now().checked_add(expire).is_some());
+
+    match expire {
+        Some(expire) => match db.set_if(key, value, Some(expire), SetCondition::Always) {
+            Ok(_) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        },
+        None => Frame::Error(format!("ERR invalid expire time in '{}' command", name)),
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{Frame, Parse, ParseError};
+
+use bytes::Bytes;
+
+/// Stop the server.
+///
+/// The server shuts down as it does when the `shutdown` future given to
+/// `server::run` completes: it stops accepting connections, and lets the
+/// active ones finish the commands they are applying. With `SAVE`, a snapshot
+/// of the databases is written first, if persistence is configured, and the
+/// server keeps running if it cannot be written. `NOSAVE`, the default, skips
+/// the snapshot.
+///
+/// On success there is no reply, the connection is closed. The command is
+/// refused unless the server was configured to accept it, and is handled by
+/// the connection handler, which holds the databases and the shutdown signal.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    /// Whether a snapshot is written before shutting down
+    save: bool,
+}
+
+impl Shutdown {
+    /// Create a new `Shutdown` command, writing a snapshot first if `save` is
+    /// set.
+    pub fn new(save: bool) -> Shutdown {
+        Shutdown { save }
+    }
+
+    /// Returns `true` if a snapshot is written before shutting down
+    pub fn save(&self) -> bool {
+        self.save
+    }
+
+    /// Parse a `Shutdown` instance from a received frame.
+    ///
+    /// The `SHUTDOWN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// SHUTDOWN [NOSAVE|SAVE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Shutdown> {
+        let save = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "SAVE" => true,
+            Ok(s) if s.to_uppercase() == "NOSAVE" => false,
+            Ok(_) => return Err("`SHUTDOWN` only supports the NOSAVE and SAVE options".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Shutdown { save })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Shutdown` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("shutdown".as_bytes()));
+        let option = if self.save { "save" } else { "nosave" };
+        frame.push_bulk(Bytes::from(option.as_bytes()));
+        frame
+    }
+}
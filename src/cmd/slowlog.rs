@@ -0,0 +1,232 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, instrument};
+
+/// Number of entries returned by `SLOWLOG GET` by default, as in Redis.
+const DEFAULT_COUNT: usize = 10;
+
+/// Returns the most recent entries of the slow log.
+///
+/// Each entry is an array holding a unique identifier, the Unix time at which
+/// the command was logged, how long it took to apply in microseconds, the
+/// name and arguments of the command, and the address and name of the
+/// connection that sent it. Up to ten entries are returned by default, all of
+/// them if `count` is `-1`.
+#[derive(Debug, Default)]
+pub struct SlowLogGet {
+    /// Maximum number of entries to return
+    count: Option<i64>,
+}
+
+/// Returns the number of entries in the slow log.
+#[derive(Debug, Default)]
+pub struct SlowLogLen {}
+
+/// Remove all entries from the slow log.
+#[derive(Debug, Default)]
+pub struct SlowLogReset {}
+
+impl SlowLogGet {
+    /// Create a new `SlowLogGet` command which returns up to `count` entries,
+    /// or the default number of entries if `count` is `None`.
+    pub fn new(count: Option<i64>) -> SlowLogGet {
+        SlowLogGet { count }
+    }
+
+    /// Get the count
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Parse a `SlowLogGet` instance from a received frame.
+    ///
+    /// The `SLOWLOG GET` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// SLOWLOG GET [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SlowLogGet> {
+        let count = match parse.next_signed_int() {
+            Ok(count) => Some(count),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(SlowLogGet { count })
+    }
+
+    /// Apply the `SlowLogGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.count {
+            None => entries(db, Some(DEFAULT_COUNT)),
+            Some(-1) => entries(db, None),
+            Some(count) => match usize::try_from(count) {
+                Ok(count) => entries(db, Some(count)),
+                Err(_) => {
+                    Frame::Error("ERR count should be greater than or equal to -1".to_string())
+                }
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SlowLogGet` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("slowlog".as_bytes()));
+        frame.push_bulk(Bytes::from("get".as_bytes()));
+        if let Some(count) = self.count {
+            frame.push_int(count);
+        }
+        frame
+    }
+}
+
+impl SlowLogLen {
+    /// Create a new `SlowLogLen` command.
+    pub fn new() -> SlowLogLen {
+        SlowLogLen {}
+    }
+
+    /// Parse a `SlowLogLen` instance from a received frame.
+    ///
+    /// The `SLOWLOG LEN` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SLOWLOG LEN
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<SlowLogLen> {
+        Ok(SlowLogLen {})
+    }
+
+    /// Apply the `SlowLogLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.slowlog_len() as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SlowLogLen` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("slowlog".as_bytes()));
+        frame.push_bulk(Bytes::from("len".as_bytes()));
+        frame
+    }
+}
+
+impl SlowLogReset {
+    /// Create a new `SlowLogReset` command.
+    pub fn new() -> SlowLogReset {
+        SlowLogReset {}
+    }
+
+    /// Parse a `SlowLogReset` instance from a received frame.
+    ///
+    /// The `SLOWLOG RESET` strings have already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SLOWLOG RESET
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<SlowLogReset> {
+        Ok(SlowLogReset {})
+    }
+
+    /// Apply the `SlowLogReset` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.slowlog_reset();
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SlowLogReset` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("slowlog".as_bytes()));
+        frame.push_bulk(Bytes::from("reset".as_bytes()));
+        frame
+    }
+}
+
+/// Returns up to `count` entries of the slow log of `db`, all of them if
+/// `count` is `None`.
+fn entries(db: &Db, count: Option<usize>) -> Frame {
+    let entries = db
+        .slowlog_get(count)
+        .into_iter()
+        .map(|entry| {
+            let logged_at = entry
+                .logged_at()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let args = entry.args().iter().cloned().map(Frame::Bulk).collect();
+            let addr = entry
+                .addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_default();
+            let name = entry.name().unwrap_or_default().to_string();
+
+            Frame::Array(vec![
+                Frame::Integer(entry.id() as i64),
+                Frame::Integer(logged_at.as_secs() as i64),
+                Frame::Integer(entry.duration().as_micros() as i64),
+                Frame::Array(args),
+                Frame::Bulk(Bytes::from(addr)),
+                Frame::Bulk(Bytes::from(name)),
+            ])
+        })
+        .collect();
+
+    Frame::Array(entries)
+}
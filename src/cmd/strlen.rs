@@ -0,0 +1,75 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the length in bytes of the string stored at `key`.
+///
+/// `0` is returned if the key does not exist. An error is returned if the key
+/// holds a value that is not a string.
+#[derive(Debug)]
+pub struct StrLen {
+    /// Name of the key to measure
+    key: Bytes,
+}
+
+impl StrLen {
+    /// Create a new `StrLen` command which returns the length of the string at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> StrLen {
+        StrLen {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `StrLen` instance from a received frame.
+    ///
+    /// The `STRLEN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// STRLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<StrLen> {
+        let key = parse.next_bytes()?;
+
+        Ok(StrLen { key })
+    }
+
+    /// Apply the `StrLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.strlen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `StrLen` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("strlen".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
@@ -1,17 +1,22 @@
-use crate::cmd::{Parse, ParseError, Unknown};
+use crate::cmd::{Parse, ParseError, Reset};
 use crate::{Command, Connection, Db, Frame, Shutdown};
 
 use bytes::Bytes;
+use std::fmt;
+use std::future::poll_fn;
 use std::pin::Pin;
+use std::task::Poll;
 use tokio::select;
 use tokio::sync::broadcast;
 use tokio_stream::{Stream, StreamExt, StreamMap};
+use tracing::warn;
 
 /// Subscribes the client to one or more channels.
 ///
 /// Once the client enters the subscribed state, it is not supposed to issue any
 /// other commands, except for additional SUBSCRIBE, PSUBSCRIBE, UNSUBSCRIBE,
-/// PUNSUBSCRIBE, PING and QUIT commands.
+/// PUNSUBSCRIBE, PING, QUIT and RESET commands. Other commands are refused,
+/// unless the client speaks RESP3.
 #[derive(Debug)]
 pub struct Subscribe {
     channels: Vec<String>,
@@ -26,11 +31,78 @@ pub struct Unsubscribe {
     channels: Vec<String>,
 }
 
+/// Subscribes the client to one or more channel patterns.
+///
+/// Patterns use glob-style syntax: `?` matches a single character, `*` any
+/// sequence of characters and `[...]` a set of characters. Messages published
+/// to a matching channel are delivered as `pmessage` replies, which carry the
+/// pattern as well as the channel name.
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+/// Unsubscribes the client from one or more channel patterns.
+///
+/// When no patterns are specified, the client is unsubscribed from all the
+/// previously subscribed patterns.
+#[derive(Clone, Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<String>,
+}
+
 /// Stream of messages. The stream receives messages from the
 /// `broadcast::Receiver`. We use `stream!` to create a `Stream` that consumes
 /// messages. Because `stream!` values cannot be named, we box the stream using
 /// a trait object.
-type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+///
+/// If the subscriber falls behind and the channel drops messages before they
+/// are received, the number of dropped messages is yielded as an error. The
+/// stream then resumes with the oldest message still held by the channel.
+type Messages = Pin<Box<dyn Stream<Item = Result<Bytes, u64>> + Send>>;
+
+/// Stream of messages received through a pattern subscription, along with the
+/// name of the channel each message was published to. Dropped messages are
+/// reported as for `Messages`.
+type PatternMessages = Pin<Box<dyn Stream<Item = Result<(String, Bytes), u64>> + Send>>;
+
+/// Most messages written to a subscriber before they are flushed.
+const MAX_BATCH_LEN: usize = 256;
+
+/// The channels and patterns a client is subscribed to.
+pub(crate) struct Subscriptions {
+    channels: StreamMap<String, Messages>,
+    patterns: StreamMap<String, PatternMessages>,
+}
+
+/// How a connection left the subscribed state.
+#[derive(Debug)]
+pub(crate) enum SubscribeExit {
+    /// The peer disconnected or quit, or the server is shutting down. The
+    /// caller must close the connection.
+    Closed,
+
+    /// The peer reset the connection. The caller must reset the rest of the
+    /// connection state.
+    Reset(Reset),
+
+    /// A RESP3 peer sent a command other than the pub/sub commands. The caller
+    /// applies it and then goes back to the subscribed state with
+    /// `resume_subscribed`, the messages received in the meantime being kept.
+    Command(Frame, Subscriptions),
+}
+
+/// A command received in the subscribed state that ends it.
+enum Interrupt {
+    /// `RESET` was received
+    Reset(Reset),
+
+    /// `QUIT` was received and replied to
+    Quit,
+
+    /// A RESP3 peer sent a command to apply outside of the subscribed state
+    Command(Frame),
+}
 
 impl Subscribe {
     /// Creates a new `Subscribe` command to listen on the specified channels.
@@ -96,61 +168,27 @@ impl Subscribe {
     /// commands may be received from the client and the list of subscriptions
     /// are updated accordingly.
     ///
+    /// RESP2 peers may only send pub/sub commands, `PING` and `RESET` in the
+    /// subscribed state. RESP3 peers can tell replies and messages apart, so
+    /// they may send any command: the subscribed state is left to apply it
+    /// and must then be resumed.
+    ///
     /// [here]: https://redis.io/topics/pubsub
     pub(crate) async fn apply(
-        mut self,
+        self,
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
-    ) -> crate::Result<()> {
-        // Each individual channel subscription is handled using a
-        // `sync::broadcast` channel. Messages are then fanned out to all
-        // clients currently subscribed to the channels.
-        //
-        // An individual client may subscribe to multiple channels and may
-        // dynamically add and remove channels from its subscription set. To
-        // handle this, a `StreamMap` is used to track active subscriptions. The
-        // `StreamMap` merges messages from individual broadcast channels as
-        // they are received.
-        let mut subscriptions = StreamMap::new();
-
-        loop {
-            // `self.channels` is used to track additional channels to subscribe
-            // to. When new `SUBSCRIBE` commands are received during the
-            // execution of `apply`, the new channels are pushed onto this vec.
-            for channel_name in self.channels.drain(..) {
-                subscribe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
-            }
-
-            // Wait for one of the following to happen:
-            //
-            // - Receive a message from one of the subscribed channels.
-            // - Receive a subscribe or unsubscribe command from the client.
-            // - A server shutdown signal.
-            select! {
-                // Receive messages from subscribed channels
-                Some((channel_name, msg)) = subscriptions.next() => {
-                    dst.write_frame(&make_message_frame(channel_name, msg)).await?;
-                }
-                res = dst.read_frame() => {
-                    let frame = match res? {
-                        Some(frame) => frame,
-                        // This happens if the remote client has disconnected.
-                        None => return Ok(())
-                    };
-
-                    handle_command(
-                        frame,
-                        &mut self.channels,
-                        &mut subscriptions,
-                        dst,
-                    ).await?;
-                }
-                _ = shutdown.recv() => {
-                    return Ok(());
-                }
-            };
-        }
+    ) -> crate::Result<SubscribeExit> {
+        subscribed(
+            Subscriptions::new(),
+            self.channels,
+            vec![],
+            db,
+            dst,
+            shutdown,
+        )
+        .await
     }
 
     /// Converts the command into an equivalent `Frame`.
@@ -167,28 +205,131 @@ impl Subscribe {
     }
 }
 
+/// Go back to the subscribed state after applying the command of an
+/// `SubscribeExit::Command`.
+pub(crate) async fn resume_subscribed(
+    subscriptions: Subscriptions,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> crate::Result<SubscribeExit> {
+    subscribed(subscriptions, vec![], vec![], db, dst, shutdown).await
+}
+
+/// Run the subscribed state of a connection, adding subscriptions to
+/// `channels` and `patterns` to `subscriptions`.
+///
+/// Every subscription is dropped on return, unless they are handed back with
+/// `SubscribeExit::Command`.
+async fn subscribed(
+    mut subscriptions: Subscriptions,
+    mut channels: Vec<String>,
+    mut patterns: Vec<String>,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> crate::Result<SubscribeExit> {
+    // Each individual channel subscription is handled using a
+    // `sync::broadcast` channel. Messages are then fanned out to all clients
+    // currently subscribed to the channels.
+    //
+    // An individual client may subscribe to multiple channels and may
+    // dynamically add and remove channels from its subscription set. To handle
+    // this, a `StreamMap` is used to track active subscriptions. The
+    // `StreamMap` merges messages from individual broadcast channels as they
+    // are received. Pattern subscriptions are tracked the same way in a second
+    // `StreamMap`.
+    loop {
+        // `channels` and `patterns` are used to track additional
+        // subscriptions. When new `SUBSCRIBE` or `PSUBSCRIBE` commands are
+        // received, the new channels and patterns are pushed onto these vecs.
+        for channel_name in channels.drain(..) {
+            subscribe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
+        }
+
+        for pattern in patterns.drain(..) {
+            subscribe_to_pattern(pattern, &mut subscriptions, db, dst).await?;
+        }
+
+        // As outside of the subscribed state, the next command is checked
+        // against the current `proto-max-bulk-len`.
+        dst.set_max_frame_size(db.max_frame_size());
+
+        // Wait for one of the following to happen:
+        //
+        // - Receive a message from one of the subscribed channels.
+        // - Receive a message from a channel matching a subscribed pattern.
+        // - Receive a subscribe or unsubscribe command from the client.
+        // - A server shutdown signal.
+        select! {
+            // Receive messages from subscribed channels
+            Some((channel_name, msg)) = subscriptions.channels.next() => {
+                dst.write_frame_buffered(&make_channel_frame(channel_name, msg)).await?;
+                write_ready_messages(&mut subscriptions, dst).await?;
+            }
+            // Receive messages from channels matching subscribed patterns
+            Some((pattern, msg)) = subscriptions.patterns.next() => {
+                dst.write_frame_buffered(&make_pattern_frame(pattern, msg)).await?;
+                write_ready_messages(&mut subscriptions, dst).await?;
+            }
+            res = dst.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    // This happens if the remote client has disconnected.
+                    None => return Ok(SubscribeExit::Closed)
+                };
+
+                let interrupt = handle_command(
+                    frame,
+                    &mut channels,
+                    &mut patterns,
+                    &mut subscriptions,
+                    dst,
+                ).await?;
+
+                match interrupt {
+                    Some(Interrupt::Reset(reset)) => return Ok(SubscribeExit::Reset(reset)),
+                    Some(Interrupt::Quit) => return Ok(SubscribeExit::Closed),
+                    Some(Interrupt::Command(frame)) => {
+                        return Ok(SubscribeExit::Command(frame, subscriptions));
+                    }
+                    None => {}
+                }
+            }
+            _ = shutdown.recv() => {
+                return Ok(SubscribeExit::Closed);
+            }
+        };
+    }
+}
+
 async fn subscribe_to_channel(
     channel_name: String,
-    subscriptions: &mut StreamMap<String, Messages>,
+    subscriptions: &mut Subscriptions,
     db: &Db,
     dst: &mut Connection,
 ) -> crate::Result<()> {
     let mut rx = db.subscribe(channel_name.clone());
+    let channel = channel_name.clone();
 
     // Subscribe to the channel.
     let rx = Box::pin(async_stream::stream! {
         loop {
             match rx.recv().await {
-                Ok(msg) => yield msg,
-                // If we lagged in consuming messages, just resume.
-                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Ok(msg) => yield Ok(msg),
+                // If we lagged in consuming messages, let the client know how
+                // many were dropped, then resume.
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(channel = %channel, skipped, "subscriber lagged behind");
+                    yield Err(skipped);
+                }
                 Err(_) => break,
             }
         }
     });
 
     // Track subscription in this client's subscription set.
-    subscriptions.insert(channel_name.clone(), rx);
+    subscriptions.channels.insert(channel_name.clone(), rx);
 
     // Respond with the successful subscription
     let response = make_subscribe_frame(channel_name, subscriptions.len());
@@ -197,26 +338,85 @@ async fn subscribe_to_channel(
     Ok(())
 }
 
-/// Handle a command received while inside `Subscribe::apply`. Only subscribe
-/// and unsubscribe commands are permitted in this context.
+async fn subscribe_to_pattern(
+    pattern: String,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    let mut rx = db.psubscribe(pattern.clone());
+    let name = pattern.clone();
+
+    // Subscribe to the pattern, see `subscribe_to_channel`.
+    let rx = Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(msg) => yield Ok(msg),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(pattern = %name, skipped, "subscriber lagged behind");
+                    yield Err(skipped);
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    subscriptions.patterns.insert(pattern.clone(), rx);
+
+    let response = make_psubscribe_frame(pattern, subscriptions.len());
+    dst.write_frame(&response).await?;
+
+    Ok(())
+}
+
+/// Handle a command received while in the subscribed state. Only subscribe,
+/// unsubscribe and reset commands are permitted in this context.
 ///
-/// Any new subscriptions are appended to `subscribe_to` instead of modifying
-/// `subscriptions`.
+/// Any new subscriptions are appended to `subscribe_to` and `psubscribe_to`
+/// instead of modifying `subscriptions`. A `Reset` command is returned to the
+/// caller, which leaves the subscribed state.
 async fn handle_command(
     frame: Frame,
     subscribe_to: &mut Vec<String>,
-    subscriptions: &mut StreamMap<String, Messages>,
+    psubscribe_to: &mut Vec<String>,
+    subscriptions: &mut Subscriptions,
     dst: &mut Connection,
-) -> crate::Result<()> {
+) -> crate::Result<Option<Interrupt>> {
     // A command has been received from the client.
     //
-    // Only `SUBSCRIBE` and `UNSUBSCRIBE` commands are permitted
-    // in this context.
-    match Command::from_frame(frame)? {
+    // Other commands are handed back to the connection handler for RESP3
+    // peers, which needs the frame as it was received.
+    let received = if dst.protocol() == 3 {
+        Some(frame.clone())
+    } else {
+        None
+    };
+
+    // Only `SUBSCRIBE`, `UNSUBSCRIBE`, `PSUBSCRIBE`, `PUNSUBSCRIBE`, `PING`,
+    // `QUIT` and `RESET` commands are permitted in this context for RESP2
+    // peers.
+    let command = match Command::from_frame(frame) {
+        Ok(command) => command,
+        Err(err) => {
+            dst.write_frame(&Frame::Error(format!("ERR {}", err)))
+                .await?;
+            return Ok(None);
+        }
+    };
+
+    match command {
+        Command::Reset(reset) => return Ok(Some(Interrupt::Reset(reset))),
+        Command::Quit(quit) => {
+            quit.apply(dst).await?;
+            return Ok(Some(Interrupt::Quit));
+        }
         Command::Subscribe(subscribe) => {
-            // The `apply` method will subscribe to the channels we add to this
-            // vector.
-            subscribe_to.extend(subscribe.channels.into_iter());
+            // The `subscribed` loop will subscribe to the channels we add to
+            // this vector.
+            subscribe_to.extend(subscribe.channels);
+        }
+        Command::PSubscribe(psubscribe) => {
+            psubscribe_to.extend(psubscribe.patterns);
         }
         Command::Unsubscribe(mut unsubscribe) => {
             // If no channels are specified, this requests unsubscribing from
@@ -225,24 +425,64 @@ async fn handle_command(
             // to.
             if unsubscribe.channels.is_empty() {
                 unsubscribe.channels = subscriptions
+                    .channels
                     .keys()
                     .map(|channel_name| channel_name.to_string())
                     .collect();
             }
 
+            // Unsubscribing from nothing is still confirmed, without a
+            // channel, so the client is not left waiting for a reply.
+            if unsubscribe.channels.is_empty() {
+                let response = make_unsubscribe_frame(None, subscriptions.len());
+                dst.write_frame(&response).await?;
+            }
+
             for channel_name in unsubscribe.channels {
-                subscriptions.remove(&channel_name);
+                subscriptions.channels.remove(&channel_name);
 
-                let response = make_unsubscribe_frame(channel_name, subscriptions.len());
+                let response = make_unsubscribe_frame(Some(channel_name), subscriptions.len());
                 dst.write_frame(&response).await?;
             }
         }
-        command => {
-            let cmd = Unknown::new(command.get_name());
-            cmd.apply(dst).await?;
+        Command::PUnsubscribe(mut punsubscribe) => {
+            // As with `UNSUBSCRIBE`, no patterns means all patterns.
+            if punsubscribe.patterns.is_empty() {
+                punsubscribe.patterns = subscriptions
+                    .patterns
+                    .keys()
+                    .map(|pattern| pattern.to_string())
+                    .collect();
+            }
+
+            if punsubscribe.patterns.is_empty() {
+                let response = make_punsubscribe_frame(None, subscriptions.len());
+                dst.write_frame(&response).await?;
+            }
+
+            for pattern in punsubscribe.patterns {
+                subscriptions.patterns.remove(&pattern);
+
+                let response = make_punsubscribe_frame(Some(pattern), subscriptions.len());
+                dst.write_frame(&response).await?;
+            }
         }
+        command => match received {
+            Some(frame) => return Ok(Some(Interrupt::Command(frame))),
+            None => match command {
+                Command::Ping(ping) => ping.apply_subscribed(dst).await?,
+                Command::Unknown(cmd) => cmd.apply(dst).await?,
+                command => {
+                    let response = Frame::Error(format!(
+                        "ERR Can't execute '{}' in subscribe context",
+                        command.get_name()
+                    ));
+                    dst.write_frame(&response).await?;
+                }
+            },
+        },
     }
-    Ok(())
+    Ok(None)
 }
 
 /// Creates the response to a subcribe request.
@@ -251,21 +491,81 @@ async fn handle_command(
 /// a `&str` since `Bytes::from` can reuse the allocation in the `String`, and
 /// taking a `&str` would require copying the data. This allows the caller to
 /// decide whether to clone the channel name or not.
+///
+/// Pub/sub replies are built as `Push` frames. RESP2 peers receive them as
+/// plain arrays, RESP3 peers as out-of-band pushes.
 fn make_subscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"subscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
-    response
+    response.push_int(num_subs as i64);
+    into_push(response)
 }
 
-/// Creates the response to an unsubcribe request.
-fn make_unsubscribe_frame(channel_name: String, num_subs: usize) -> Frame {
+/// Creates the response to an unsubcribe request. `channel_name` is `None`
+/// when there was no channel to unsubscribe from.
+fn make_unsubscribe_frame(channel_name: Option<String>, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"unsubscribe"));
-    response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
-    response
+    match channel_name {
+        Some(channel_name) => response.push_bulk(Bytes::from(channel_name)),
+        None => response.push_null(),
+    }
+    response.push_int(num_subs as i64);
+    into_push(response)
+}
+
+/// Write the messages already received from the subscriptions, without waiting
+/// for more, then flush them along with those written before.
+///
+/// Messages published in a burst, to a channel with many subscribers for
+/// example, are then sent with few writes to the socket. At most
+/// `MAX_BATCH_LEN` messages are written, so that commands sent by the client
+/// are still read while messages keep arriving.
+async fn write_ready_messages(
+    subscriptions: &mut Subscriptions,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    for _ in 0..MAX_BATCH_LEN {
+        let frame = if let Some((channel_name, msg)) = ready(&mut subscriptions.channels).await {
+            make_channel_frame(channel_name, msg)
+        } else if let Some((pattern, msg)) = ready(&mut subscriptions.patterns).await {
+            make_pattern_frame(pattern, msg)
+        } else {
+            break;
+        };
+
+        dst.write_frame_buffered(&frame).await?;
+    }
+
+    dst.flush().await?;
+
+    Ok(())
+}
+
+/// Returns the next item of `stream` if it is ready, without waiting for it.
+async fn ready<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    poll_fn(|cx| match Pin::new(&mut *stream).poll_next(cx) {
+        Poll::Ready(item) => Poll::Ready(item),
+        Poll::Pending => Poll::Ready(None),
+    })
+    .await
+}
+
+/// Creates the frame sent for an item received from a channel subscription.
+fn make_channel_frame(channel_name: String, msg: Result<Bytes, u64>) -> Frame {
+    match msg {
+        Ok(msg) => make_message_frame(channel_name, msg),
+        Err(skipped) => make_lagged_frame(channel_name, skipped),
+    }
+}
+
+/// Creates the frame sent for an item received from a pattern subscription.
+fn make_pattern_frame(pattern: String, msg: Result<(String, Bytes), u64>) -> Frame {
+    match msg {
+        Ok((channel_name, msg)) => make_pmessage_frame(pattern, channel_name, msg),
+        Err(skipped) => make_lagged_frame(pattern, skipped),
+    }
 }
 
 /// Creates a message informing the client about a new message on a channel that
@@ -275,7 +575,62 @@ fn make_message_frame(channel_name: String, msg: Bytes) -> Frame {
     response.push_bulk(Bytes::from_static(b"message"));
     response.push_bulk(Bytes::from(channel_name));
     response.push_bulk(msg);
-    response
+    into_push(response)
+}
+
+/// Creates a notice telling the client that `skipped` messages published to
+/// `channel_name`, or to channels matching a pattern, were dropped because the
+/// client did not keep up.
+///
+/// Redis has no such notice; it is specific to mini-redis and formatted like
+/// a message.
+fn make_lagged_frame(channel_name: String, skipped: u64) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"lagged"));
+    response.push_bulk(Bytes::from(channel_name));
+    response.push_int(skipped as i64);
+    into_push(response)
+}
+
+/// Creates the response to a psubscribe request.
+fn make_psubscribe_frame(pattern: String, num_subs: usize) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"psubscribe"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_int(num_subs as i64);
+    into_push(response)
+}
+
+/// Creates the response to a punsubscribe request, see
+/// `make_unsubscribe_frame`.
+fn make_punsubscribe_frame(pattern: Option<String>, num_subs: usize) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"punsubscribe"));
+    match pattern {
+        Some(pattern) => response.push_bulk(Bytes::from(pattern)),
+        None => response.push_null(),
+    }
+    response.push_int(num_subs as i64);
+    into_push(response)
+}
+
+/// Creates a message informing the client about a new message on a channel
+/// matching a pattern the client subscribes to.
+fn make_pmessage_frame(pattern: String, channel_name: String, msg: Bytes) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"pmessage"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_bulk(Bytes::from(channel_name));
+    response.push_bulk(msg);
+    into_push(response)
+}
+
+/// Converts an array frame built with the `push_*` helpers into a `Push` frame.
+fn into_push(frame: Frame) -> Frame {
+    match frame {
+        Frame::Array(parts) => Frame::Push(parts),
+        frame => frame,
+    }
 }
 
 impl Unsubscribe {
@@ -332,6 +687,25 @@ impl Unsubscribe {
         Ok(Unsubscribe { channels })
     }
 
+    /// Apply the `Unsubscribe` command on a connection that is not subscribed
+    /// to any channel.
+    ///
+    /// As in Redis, each channel is confirmed with a count of `0`, and a
+    /// single confirmation without a channel is written if none is given. The
+    /// connection is left open.
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        if self.channels.is_empty() {
+            dst.write_frame(&make_unsubscribe_frame(None, 0)).await?;
+        }
+
+        for channel_name in self.channels {
+            let response = make_unsubscribe_frame(Some(channel_name), 0);
+            dst.write_frame(&response).await?;
+        }
+
+        Ok(())
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding an `Unsubscribe` command to
@@ -347,3 +721,124 @@ impl Unsubscribe {
         frame
     }
 }
+
+impl PSubscribe {
+    /// Parse a `PSubscribe` instance from a received frame.
+    ///
+    /// The `PSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// PSUBSCRIBE pattern [pattern ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSubscribe> {
+        use ParseError::EndOfStream;
+
+        // At least one pattern is required.
+        let mut patterns = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PSubscribe { patterns })
+    }
+
+    /// Apply the `PSubscribe` command to the specified `Db` instance.
+    ///
+    /// Like `Subscribe::apply`, this enters the subscribed state, in which
+    /// further subscribe and unsubscribe commands are accepted.
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<SubscribeExit> {
+        subscribed(
+            Subscriptions::new(),
+            vec![],
+            self.patterns,
+            db,
+            dst,
+            shutdown,
+        )
+        .await
+    }
+}
+
+impl PUnsubscribe {
+    /// Parse a `PUnsubscribe` instance from a received frame.
+    ///
+    /// The `PUNSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// PUNSUBSCRIBE [pattern [pattern ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<PUnsubscribe, ParseError> {
+        use ParseError::EndOfStream;
+
+        // There may be no patterns listed, so start with an empty vec.
+        let mut patterns = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(PUnsubscribe { patterns })
+    }
+
+    /// Apply the `PUnsubscribe` command on a connection that is not subscribed
+    /// to any pattern, as `Unsubscribe::apply` does for channels.
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        if self.patterns.is_empty() {
+            dst.write_frame(&make_punsubscribe_frame(None, 0)).await?;
+        }
+
+        for pattern in self.patterns {
+            let response = make_punsubscribe_frame(Some(pattern), 0);
+            dst.write_frame(&response).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Subscriptions {
+    fn new() -> Subscriptions {
+        Subscriptions {
+            channels: StreamMap::new(),
+            patterns: StreamMap::new(),
+        }
+    }
+
+    /// Returns the total number of channels and patterns subscribed to, which
+    /// is reported in subscribe and unsubscribe replies.
+    fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+}
+
+impl fmt::Debug for Subscriptions {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        // The message streams cannot be formatted, only their names are.
+        fmt.debug_struct("Subscriptions")
+            .field("channels", &self.channels.keys().collect::<Vec<_>>())
+            .field("patterns", &self.patterns.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Exchange the contents of two databases, along with the expirations of
+/// their keys.
+///
+/// Connections keep the database they selected: after the swap, a connection
+/// which selected one of the databases sees the keys of the other. The swap
+/// happens atomically.
+#[derive(Debug)]
+pub struct SwapDb {
+    /// Index of the first database
+    index1: i64,
+
+    /// Index of the second database
+    index2: i64,
+}
+
+impl SwapDb {
+    /// Create a new `SwapDb` command which swaps databases `index1` and
+    /// `index2`.
+    pub fn new(index1: i64, index2: i64) -> SwapDb {
+        SwapDb { index1, index2 }
+    }
+
+    /// Get the index of the first database
+    pub fn index1(&self) -> i64 {
+        self.index1
+    }
+
+    /// Get the index of the second database
+    pub fn index2(&self) -> i64 {
+        self.index2
+    }
+
+    /// Parse a `SwapDb` instance from a received frame.
+    ///
+    /// The `SWAPDB` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SWAPDB index1 index2
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SwapDb> {
+        let index1 = parse.next_signed_int()?;
+        let index2 = parse.next_signed_int()?;
+
+        Ok(SwapDb { index1, index2 })
+    }
+
+    /// Apply the `SwapDb` command to `dbs`, the databases of the server.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dbs, dst))]
+    pub(crate) async fn apply(self, dbs: &[Db], dst: &mut Connection) -> crate::Result<()> {
+        let index = |index| {
+            usize::try_from(index)
+                .ok()
+                .filter(|&index| index < dbs.len())
+        };
+
+        let response = match (index(self.index1), index(self.index2)) {
+            (Some(index1), Some(index2)) => {
+                dbs[index1].swap(&dbs[index2]);
+                Frame::Simple("OK".to_string())
+            }
+            _ => Frame::Error("ERR DB index is out of range".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `SwapDb` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("swapdb".as_bytes()));
+        frame.push_int(self.index1);
+        frame.push_int(self.index2);
+        frame
+    }
+}
@@ -0,0 +1,86 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Mark the specified keys as recently used, without reading their values.
+///
+/// Touched keys are the last to be considered for eviction under the LRU
+/// policy. Returns the number of the specified keys that exist, so this can
+/// also be used as a multi-key `EXISTS`. A key mentioned multiple times is
+/// counted multiple times.
+#[derive(Debug)]
+pub struct Touch {
+    /// Name of the keys to touch
+    keys: Vec<Bytes>,
+}
+
+impl Touch {
+    /// Create a new `Touch` command which touches `keys`.
+    pub fn new(keys: Vec<Bytes>) -> Touch {
+        Touch { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse a `Touch` instance from a received frame.
+    ///
+    /// The `TOUCH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// TOUCH key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Touch> {
+        use ParseError::EndOfStream;
+
+        // At least one key must be provided.
+        let mut keys = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Touch { keys })
+    }
+
+    /// Apply the `Touch` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let count = self.keys.iter().filter(|key| db.touch(key)).count();
+
+        let response = Frame::Integer(count as i64);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Touch` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("touch".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        frame
+    }
+}
@@ -0,0 +1,65 @@
+use crate::Parse;
+
+/// Marks the start of a transaction block.
+///
+/// Subsequent commands are queued for atomic execution using `EXEC`. The
+/// command is handled by the connection handler, as the queued commands are
+/// per-connection state.
+#[derive(Debug, Default)]
+pub struct Multi {}
+
+/// Executes all previously queued commands in a transaction.
+///
+/// The reply is an array holding the reply of each queued command. If an error
+/// was detected while queuing, the transaction is discarded instead.
+#[derive(Debug, Default)]
+pub struct Exec {}
+
+/// Flushes all previously queued commands in a transaction.
+#[derive(Debug, Default)]
+pub struct Discard {}
+
+impl Multi {
+    /// Parse a `Multi` instance from a received frame.
+    ///
+    /// The `MULTI` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// MULTI
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Multi> {
+        Ok(Multi::default())
+    }
+}
+
+impl Exec {
+    /// Parse an `Exec` instance from a received frame.
+    ///
+    /// The `EXEC` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// EXEC
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Exec> {
+        Ok(Exec::default())
+    }
+}
+
+impl Discard {
+    /// Parse a `Discard` instance from a received frame.
+    ///
+    /// The `DISCARD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// DISCARD
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Discard> {
+        Ok(Discard::default())
+    }
+}
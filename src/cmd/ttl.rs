@@ -0,0 +1,155 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Returns the remaining time to live of a key, in seconds.
+///
+/// If the key does not exist, `-2` is returned. If the key exists but has no
+/// associated expiration, `-1` is returned.
+#[derive(Debug)]
+pub struct Ttl {
+    /// Name of the key to inspect
+    key: Bytes,
+}
+
+/// Returns the remaining time to live of a key, in milliseconds.
+///
+/// Works exactly like `TTL` except that the time is returned in milliseconds.
+#[derive(Debug)]
+pub struct Pttl {
+    /// Name of the key to inspect
+    key: Bytes,
+}
+
+impl Ttl {
+    /// Create a new `Ttl` command which inspects `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> Ttl {
+        Ttl {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `Ttl` instance from a received frame.
+    ///
+    /// The `TTL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// TTL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Ttl> {
+        let key = parse.next_bytes()?;
+
+        Ok(Ttl { key })
+    }
+
+    /// Apply the `Ttl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Redis rounds the remaining time to the closest second.
+        let response = ttl_response(db, &self.key, |ttl| {
+            i64::try_from((ttl.as_millis() + 500) / 1000).unwrap_or(i64::MAX)
+        });
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Ttl` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("ttl".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+impl Pttl {
+    /// Create a new `Pttl` command which inspects `key`.
+    pub fn new(key: impl AsRef<[u8]>) -> Pttl {
+        Pttl {
+            key: Bytes::copy_from_slice(key.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Parse a `Pttl` instance from a received frame.
+    ///
+    /// The `PTTL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// PTTL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Pttl> {
+        let key = parse.next_bytes()?;
+
+        Ok(Pttl { key })
+    }
+
+    /// Apply the `Pttl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = ttl_response(db, &self.key, |ttl| {
+            i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX)
+        });
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Pttl` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("pttl".as_bytes()));
+        frame.push_bulk(self.key);
+        frame
+    }
+}
+
+/// Build the reply for `TTL` and `PTTL`.
+///
+/// `-2` means the key does not exist and `-1` means the key has no expiration.
+/// Otherwise, `unit` converts the remaining duration into the reply value.
+fn ttl_response(db: &Db, key: &[u8], unit: impl Fn(std::time::Duration) -> i64) -> Frame {
+    match db.ttl(key) {
+        None => Frame::Integer(-2),
+        Some(None) => Frame::Integer(-1),
+        Some(Some(ttl)) => Frame::Integer(unit(ttl)),
+    }
+}
@@ -0,0 +1,98 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes the specified keys, freeing their values in the background.
+///
+/// Unlike `DEL`, the memory held by the values is reclaimed by a background
+/// task, so removing large lists or hashes does not delay the reply. A key is
+/// ignored if it does not exist. The number of keys that were removed is
+/// returned to the client.
+#[derive(Debug)]
+pub struct Unlink {
+    /// Name of the keys to remove
+    keys: Vec<Bytes>,
+}
+
+impl Unlink {
+    /// Create a new `Unlink` command which removes `keys`.
+    pub fn new(keys: Vec<Bytes>) -> Unlink {
+        Unlink { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[Bytes] {
+        &self.keys
+    }
+
+    /// Parse an `Unlink` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `UNLINK` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Unlink` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// UNLINK key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Unlink> {
+        use ParseError::EndOfStream;
+
+        // At least one key must be provided.
+        let mut keys = vec![parse.next_bytes()?];
+
+        // The remaining entries are all keys. Read until the frame is fully
+        // consumed.
+        loop {
+            match parse.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Unlink { keys })
+    }
+
+    /// Apply the `Unlink` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Count the keys that actually existed
+        let count = self.keys.iter().filter(|key| db.unlink(key)).count();
+
+        let response = Frame::Integer(count as i64);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Unlink` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("unlink".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(key);
+        }
+        frame
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{Connection, Frame, Parse, Shutdown};
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, instrument};
+
+/// Wait until the preceding writes are acknowledged by `numreplicas` replicas,
+/// or until `timeout` elapses.
+///
+/// `mini-redis` does not support replication, so this is a stub: no replica
+/// ever acknowledges the writes and the reply is always `0`. It exists so that
+/// clients issuing `WAIT` after their writes keep working. If replicas are
+/// requested the reply is sent once `timeout` has elapsed, a `0` timeout
+/// replying right away. Only the connection issuing the command waits.
+#[derive(Debug)]
+pub struct Wait {
+    /// Number of replicas to wait for
+    numreplicas: u64,
+
+    /// Longest time to wait for
+    timeout: Duration,
+}
+
+impl Wait {
+    /// Create a new `Wait` command which waits up to `timeout` for
+    /// `numreplicas` replicas to acknowledge the preceding writes.
+    pub fn new(numreplicas: u64, timeout: Duration) -> Wait {
+        Wait {
+            numreplicas,
+            timeout,
+        }
+    }
+
+    /// Get the number of replicas
+    pub fn numreplicas(&self) -> u64 {
+        self.numreplicas
+    }
+
+    /// Get the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Parse a `Wait` instance from a received frame.
+    ///
+    /// The `WAIT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries. The timeout is in
+    /// milliseconds.
+    ///
+    /// ```text
+    /// WAIT numreplicas timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Wait> {
+        let numreplicas = parse.next_int()?;
+        let timeout = Duration::from_millis(parse.next_int()?);
+
+        Ok(Wait {
+            numreplicas,
+            timeout,
+        })
+    }
+
+    /// Apply the `Wait` command.
+    ///
+    /// The response is written to `dst`, right away if no replica is
+    /// requested, once `timeout` has elapsed otherwise. If the server shuts
+    /// down in the meantime, no response is written.
+    #[instrument(skip(self, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        // There are no replicas to acknowledge anything, waiting for them
+        // always lasts the whole timeout.
+        if self.numreplicas > 0 {
+            tokio::select! {
+                _ = time::sleep(self.timeout) => {}
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+
+        let response = Frame::Integer(0);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Wait` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("wait".as_bytes()));
+        frame.push_int(self.numreplicas as i64);
+        frame.push_int(self.timeout.as_millis() as i64);
+        frame
+    }
+}
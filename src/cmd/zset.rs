@@ -0,0 +1,421 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Add the specified members with their scores to the sorted set stored at
+/// key.
+///
+/// If key does not exist, a new sorted set is created. The score of members
+/// already in the set is updated. The number of members that were added is
+/// returned.
+#[derive(Debug)]
+pub struct ZAdd {
+    /// Name of the sorted set
+    key: Bytes,
+
+    /// Score and member pairs to add
+    members: Vec<(f64, Bytes)>,
+}
+
+/// Returns the score of member in the sorted set stored at key.
+///
+/// `nil` is returned if the member or the key does not exist.
+#[derive(Debug)]
+pub struct ZScore {
+    /// Name of the sorted set
+    key: Bytes,
+
+    /// Member to look up
+    member: Bytes,
+}
+
+/// Returns the rank of member in the sorted set stored at key.
+///
+/// Members are ranked by score, then lexicographically, starting at `0` for
+/// the member with the lowest score. `nil` is returned if the member or the
+/// key does not exist.
+#[derive(Debug)]
+pub struct ZRank {
+    /// Name of the sorted set
+    key: Bytes,
+
+    /// Member to look up
+    member: Bytes,
+}
+
+/// Returns the members of the sorted set stored at key with a rank between
+/// start and stop, inclusive.
+///
+/// Negative indices count from the member with the highest score, `-1` being
+/// the last member. With `WITHSCORES`, every member is followed by its score.
+#[derive(Debug)]
+pub struct ZRange {
+    /// Name of the sorted set
+    key: Bytes,
+
+    /// Rank of the first member to return
+    start: i64,
+
+    /// Rank of the last member to return
+    stop: i64,
+
+    /// Whether scores are returned along with the members
+    with_scores: bool,
+}
+
+impl ZAdd {
+    /// Create a new `ZAdd` command which adds `members` to the sorted set at
+    /// `key`.
+    pub fn new(key: impl AsRef<[u8]>, members: Vec<(f64, Bytes)>) -> ZAdd {
+        ZAdd {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the score and member pairs
+    pub fn members(&self) -> &[(f64, Bytes)] {
+        &self.members
+    }
+
+    /// Parse a `ZAdd` instance from a received frame.
+    ///
+    /// The `ZADD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the key followed by one or more score
+    /// and member pairs.
+    ///
+    /// ```text
+    /// ZADD key score member [score member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZAdd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_bytes()?;
+
+        // At least one pair must be provided.
+        let mut members = vec![(parse_score(parse.next_string()?)?, parse.next_bytes()?)];
+
+        loop {
+            let score = match parse.next_string() {
+                Ok(score) => parse_score(score)?,
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            // A score must always be followed by its member.
+            members.push((score, parse.next_bytes()?));
+        }
+
+        Ok(ZAdd { key, members })
+    }
+
+    /// Apply the `ZAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zadd(&self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZAdd` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zadd".as_bytes()));
+        frame.push_bulk(self.key);
+        for (score, member) in self.members {
+            frame.push_bulk(score_bytes(score));
+            frame.push_bulk(member);
+        }
+        frame
+    }
+}
+
+impl ZScore {
+    /// Create a new `ZScore` command which looks up the score of `member` in
+    /// the sorted set at `key`.
+    pub fn new(key: impl AsRef<[u8]>, member: impl AsRef<[u8]>) -> ZScore {
+        ZScore {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            member: Bytes::copy_from_slice(member.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse a `ZScore` instance from a received frame.
+    ///
+    /// The `ZSCORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// ZSCORE key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScore> {
+        let key = parse.next_bytes()?;
+        let member = parse.next_bytes()?;
+
+        Ok(ZScore { key, member })
+    }
+
+    /// Apply the `ZScore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zscore(&self.key, &self.member) {
+            // A bulk string holding the score for RESP2 peers.
+            Ok(Some(score)) => Frame::Double(score),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZScore` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zscore".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.member);
+        frame
+    }
+}
+
+impl ZRank {
+    /// Create a new `ZRank` command which looks up the rank of `member` in the
+    /// sorted set at `key`.
+    pub fn new(key: impl AsRef<[u8]>, member: impl AsRef<[u8]>) -> ZRank {
+        ZRank {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            member: Bytes::copy_from_slice(member.as_ref()),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the member
+    pub fn member(&self) -> &Bytes {
+        &self.member
+    }
+
+    /// Parse a `ZRank` instance from a received frame.
+    ///
+    /// The `ZRANK` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// ZRANK key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRank> {
+        let key = parse.next_bytes()?;
+        let member = parse.next_bytes()?;
+
+        Ok(ZRank { key, member })
+    }
+
+    /// Apply the `ZRank` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrank(&self.key, &self.member) {
+            Ok(Some(rank)) => Frame::Integer(rank as i64),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRank` command to send to
+    /// the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrank".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_bulk(self.member);
+        frame
+    }
+}
+
+impl ZRange {
+    /// Create a new `ZRange` command which returns the members of the sorted
+    /// set at `key` ranked between `start` and `stop`, along with their scores
+    /// if `with_scores` is set.
+    pub fn new(key: impl AsRef<[u8]>, start: i64, stop: i64, with_scores: bool) -> ZRange {
+        ZRange {
+            key: Bytes::copy_from_slice(key.as_ref()),
+            start,
+            stop,
+            with_scores,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &Bytes {
+        &self.key
+    }
+
+    /// Get the start index
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the stop index
+    pub fn stop(&self) -> i64 {
+        self.stop
+    }
+
+    /// Returns `true` if scores are returned along with the members
+    pub fn with_scores(&self) -> bool {
+        self.with_scores
+    }
+
+    /// Parse a `ZRange` instance from a received frame.
+    ///
+    /// The `ZRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or five entries.
+    ///
+    /// ```text
+    /// ZRANGE key start stop [WITHSCORES]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRange> {
+        let key = parse.next_bytes()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        let with_scores = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "WITHSCORES" => true,
+            Ok(_) => return Err("`ZRANGE` only supports the WITHSCORES option".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ZRange {
+            key,
+            start,
+            stop,
+            with_scores,
+        })
+    }
+
+    /// Apply the `ZRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrange(&self.key, self.start, self.stop) {
+            Ok(members) => {
+                let mut response = Frame::array();
+                for (member, score) in members {
+                    response.push_bulk(member);
+                    if self.with_scores {
+                        response.push_bulk(score_bytes(score));
+                    }
+                }
+                response
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `ZRange` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("zrange".as_bytes()));
+        frame.push_bulk(self.key);
+        frame.push_int(self.start);
+        frame.push_int(self.stop);
+        if self.with_scores {
+            frame.push_bulk(Bytes::from("withscores".as_bytes()));
+        }
+        frame
+    }
+}
+
+/// Parse a score argument.
+///
+/// `inf` and `-inf` are accepted, NaN is not.
+fn parse_score(score: String) -> crate::Result<f64> {
+    match score.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok(score),
+        _ => Err("protocol error; invalid float".into()),
+    }
+}
+
+/// Format a score as it is replied.
+///
+/// `Display` for `f64` prints the shortest representation that parses back to
+/// the same value, and `inf` and `-inf` for infinities, as Redis does.
+fn score_bytes(score: f64) -> Bytes {
+    Bytes::from(score.to_string())
+}
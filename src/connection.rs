@@ -1,9 +1,13 @@
 use crate::frame::{self, Frame};
 
 use bytes::{Buf, BytesMut};
-use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 use tokio::net::TcpStream;
+use tokio::time;
 
 /// Send and receive `Frame` values from a remote peer.
 ///
@@ -16,32 +20,193 @@ use tokio::net::TcpStream;
 /// the `Connection` creates the frame and returns it to the caller.
 ///
 /// When sending frames, the frame is first encoded into the write buffer.
-/// The contents of the write buffer are then written to the socket.
+/// The contents of the write buffer are then written to the socket, once the
+/// buffer is full or `flush` is called. Frames are always written whole to
+/// the buffer, so a frame sent early because the buffer filled up is only
+/// split across writes to the socket, never corrupted.
 #[derive(Debug)]
 pub struct Connection {
-    // The `TcpStream`. It is decorated with a `BufWriter`, which provides write
-    // level buffering. The `BufWriter` implementation provided by Tokio is
-    // sufficient for our needs.
-    stream: BufWriter<TcpStream>,
+    // The `TcpStream`, or another byte stream such as the append-only file. It
+    // is decorated with a `BufWriter`, which provides write level buffering.
+    // The `BufWriter` implementation provided by Tokio is sufficient for our
+    // needs.
+    stream: BufWriter<Box<dyn Stream>>,
 
     // The buffer for reading frames.
     buffer: BytesMut,
+
+    // The protocol version negotiated with `HELLO`. Frames that only exist in
+    // RESP3 are downgraded when writing to a RESP2 peer.
+    protocol: u8,
+
+    // Frames written while a transaction is being executed. `None` when
+    // frames are written to the stream.
+    captured: Option<Vec<Frame>>,
+
+    // Largest bulk string or inline command accepted from the peer, in bytes.
+    // Reading a larger one fails instead of buffering it.
+    max_frame_size: usize,
+
+    // Largest number of entries accepted in an array or map from the peer.
+    max_array_len: usize,
+
+    // Longest a single read from, or write to, the stream may take. `None`
+    // waits indefinitely.
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+
+    // Set once reading or writing failed. The stream may then hold part of a
+    // frame, so the connection can no longer be used.
+    poisoned: bool,
+
+    // Set while frames written are dropped instead of being sent to the peer.
+    muted: bool,
 }
 
+/// Bytes frames can start with, identifying the type of the frame.
+const FRAME_TYPES: &[u8] = b"+-:$*>%,#";
+
+/// Bytes taken by an array entry besides the contents of its bulk string: the
+/// type byte, the length and two line endings.
+const ENTRY_OVERHEAD: usize = 32;
+
+/// Largest frame accepted by default, matching the limit Redis places on bulk
+/// strings.
+pub(crate) const DEFAULT_MAX_FRAME_SIZE: usize = 512 * 1024 * 1024;
+
+/// Size of the write buffer by default, matching the default of Tokio's
+/// `BufWriter`.
+pub(crate) const DEFAULT_WRITE_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Byte stream frames are read from and written to.
+pub(crate) trait Stream: AsyncRead + AsyncWrite + Send + Sync + Unpin + fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Sync + Unpin + fmt::Debug> Stream for T {}
+
 impl Connection {
     /// Create a new `Connection`, backed by `socket`. Read and write buffers
     /// are initialized.
     pub fn new(socket: TcpStream) -> Connection {
+        Connection::from_stream(socket)
+    }
+
+    /// Create a new `Connection`, backed by `socket`, buffering up to
+    /// `write_buffer_size` bytes of frames before writing them to the socket.
+    ///
+    /// A larger buffer lets many frames be sent with a single write, such as
+    /// the messages received by a subscriber. A `0` size writes each frame to
+    /// the socket as soon as it is encoded.
+    pub fn with_write_buffer_size(socket: TcpStream, write_buffer_size: usize) -> Connection {
+        Connection::from_stream_with_write_buffer_size(socket, write_buffer_size)
+    }
+
+    /// Create a new `Connection` backed by any byte stream.
+    pub(crate) fn from_stream(stream: impl Stream + 'static) -> Connection {
+        Connection::from_stream_with_write_buffer_size(stream, DEFAULT_WRITE_BUFFER_SIZE)
+    }
+
+    /// Create a new `Connection` backed by any byte stream, with a write
+    /// buffer of `write_buffer_size` bytes.
+    pub(crate) fn from_stream_with_write_buffer_size(
+        stream: impl Stream + 'static,
+        write_buffer_size: usize,
+    ) -> Connection {
         Connection {
-            stream: BufWriter::new(socket),
+            stream: BufWriter::with_capacity(write_buffer_size, Box::new(stream)),
             // Default to a 4KB read buffer. For the use case of mini redis,
             // this is fine. However, real applications will want to tune this
             // value to their specific use case. There is a high likelihood that
             // a larger read buffer will work better.
             buffer: BytesMut::with_capacity(4 * 1024),
+            // Peers speak RESP2 until they negotiate otherwise.
+            protocol: 2,
+            captured: None,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_array_len: usize::MAX,
+            read_timeout: None,
+            write_timeout: None,
+            poisoned: false,
+            muted: false,
         }
     }
 
+    /// Returns the protocol version used to encode frames, either `2` or `3`.
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    /// Set the protocol version used to encode frames.
+    ///
+    /// Only versions `2` and `3` are supported. Any other value is treated as
+    /// RESP2.
+    pub fn set_protocol(&mut self, version: u8) {
+        self.protocol = if version == 3 { 3 } else { 2 };
+    }
+
+    /// Returns the size of the largest frame accepted from the peer, in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Set the size of the largest frame accepted from the peer, in bytes.
+    ///
+    /// `read_frame` fails with a protocol error once a frame announces a bulk
+    /// string larger than this, or once an inline command that long has been
+    /// buffered without its line ending. A frame is also rejected once more
+    /// bytes have been buffered than `max_array_len` bulk strings of that size
+    /// could take. Defaults to 512MB.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    /// Returns the largest number of entries accepted in an array or map from
+    /// the peer.
+    pub fn max_array_len(&self) -> usize {
+        self.max_array_len
+    }
+
+    /// Set the largest number of entries accepted in an array or map from the
+    /// peer.
+    ///
+    /// `read_frame` fails with a protocol error once a frame announces more
+    /// entries than this, before they are received. Only the maximum frame
+    /// size applies by default.
+    pub fn set_max_array_len(&mut self, max_array_len: usize) {
+        self.max_array_len = max_array_len;
+    }
+
+    /// Returns how long a single read from the stream may take.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Set how long a single read from the stream may take, `None` waiting
+    /// indefinitely, the default.
+    ///
+    /// The timeout applies to each read rather than to whole frames: a frame
+    /// received slowly is still accepted as long as the peer keeps sending.
+    /// Once a read times out, `read_frame` fails with an error of kind
+    /// `io::ErrorKind::TimedOut` and the connection can no longer be used.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    /// Returns how long a single write to the stream may take.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Set how long a single write to the stream may take, `None` waiting
+    /// indefinitely, the default.
+    ///
+    /// As with `set_read_timeout`, the timeout applies to each write, and
+    /// flush, rather than to whole frames. Once a write times out, it fails
+    /// with an error of kind `io::ErrorKind::TimedOut` and the connection can
+    /// no longer be used.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+
     /// Read a single `Frame` value from the underlying stream.
     ///
     /// The function waits until it has retrieved enough data to parse a frame.
@@ -53,7 +218,32 @@ impl Connection {
     /// On success, the received frame is returned. If the `TcpStream`
     /// is closed in a way that doesn't break a frame in half, it returns
     /// `None`. Otherwise, an error is returned.
+    ///
+    /// A peer that closes, or only shuts down its writing half, while part of
+    /// a frame is buffered did not disconnect cleanly: an `io::Error` of kind
+    /// `io::ErrorKind::ConnectionReset` is returned, rather than `None`, so
+    /// that the two cases can be told apart. Frames received in full before
+    /// the end of the stream are still returned first.
     pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
+        let res = self.read_next_frame().await;
+        self.poisoned |= res.is_err();
+        res
+    }
+
+    /// Returns `true` if reading or writing a frame failed. The connection can
+    /// no longer be used.
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Mark the connection as no longer usable, for example because a reply
+    /// was not received in time and may still arrive.
+    pub(crate) fn poison(&mut self) {
+        self.poisoned = true;
+    }
+
+    /// Read a single `Frame` value, see `read_frame`.
+    async fn read_next_frame(&mut self) -> crate::Result<Option<Frame>> {
         loop {
             // Attempt to parse a frame from the buffered data. If enough data
             // has been buffered, the frame is returned.
@@ -61,12 +251,19 @@ impl Connection {
                 return Ok(Some(frame));
             }
 
+            // The buffered data is part of a single frame. Stop buffering once
+            // the frame is known to be too large.
+            if self.buffer.len() > self.max_buffered() {
+                return Err(frame::Error::from("protocol error; frame too large").into());
+            }
+
             // There is not enough buffered data to read a frame. Attempt to
             // read more data from the socket.
             //
             // On success, the number of bytes is returned. `0` indicates "end
             // of stream".
-            if 0 == self.stream.read_buf(&mut self.buffer).await? {
+            let read = self.stream.read_buf(&mut self.buffer);
+            if 0 == with_timeout(self.read_timeout, "read", read).await? {
                 // The remote closed the connection. For this to be a clean
                 // shutdown, there should be no data in the read buffer. If
                 // there is, this means that the peer closed the socket while
@@ -74,12 +271,32 @@ impl Connection {
                 if self.buffer.is_empty() {
                     return Ok(None);
                 } else {
-                    return Err("connection reset by peer".into());
+                    let err =
+                        io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by peer");
+                    return Err(err.into());
                 }
             }
         }
     }
 
+    /// Returns the number of bytes that may be buffered without completing a
+    /// frame.
+    ///
+    /// Frames announce the length of their bulk strings and arrays, which are
+    /// checked as soon as they are read, so a request of bulk strings within
+    /// both limits is never cut short however it is split across reads. An
+    /// inline command announces nothing, it is bounded by the maximum size of
+    /// a bulk string.
+    fn max_buffered(&self) -> usize {
+        match self.buffer.first() {
+            Some(first) if FRAME_TYPES.contains(first) => self
+                .max_frame_size
+                .saturating_add(ENTRY_OVERHEAD)
+                .saturating_mul(self.max_array_len),
+            _ => self.max_frame_size,
+        }
+    }
+
     /// Tries to parse a frame from the buffer. If the buffer contains enough
     /// data, the frame is returned and the data removed from the buffer. If not
     /// enough data has been buffered yet, `Ok(None)` is returned. If the
@@ -87,38 +304,26 @@ impl Connection {
     fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
         use frame::Error::Incomplete;
 
-        // Cursor is used to track the "current" location in the
-        // buffer. Cursor also implements `Buf` from the `bytes` crate
-        // which provides a number of helpful utilities for working
-        // with bytes.
-        let mut buf = Cursor::new(&self.buffer[..]);
-
-        // The first step is to check if enough data has been buffered to parse
-        // a single frame. This step is usually much faster than doing a full
-        // parse of the frame, and allows us to skip allocating data structures
-        // to hold the frame data unless we know the full frame has been
-        // received.
-        match Frame::check(&mut buf) {
-            Ok(_) => {
-                // The `check` function will have advanced the cursor until the
-                // end of the frame. Since the cursor had position set to zero
-                // before `Frame::check` was called, we obtain the length of the
-                // frame by checking the cursor position.
-                let len = buf.position() as usize;
-
-                // Reset the position to zero before passing the cursor to
-                // `Frame::parse`.
-                buf.set_position(0);
-
-                // Parse the frame from the buffer. This allocates the necessary
-                // structures to represent the frame and returns the frame
-                // value.
-                //
-                // If the encoded frame representation is invalid, an error is
-                // returned. This should terminate the **current** connection
-                // but should not impact any other connected client.
-                let frame = Frame::parse(&mut buf)?;
+        // Data that does not start with a frame type byte is an inline
+        // command, as typed into `telnet` or `nc`. Blank lines are skipped.
+        while let Some(&first) = self.buffer.first() {
+            if FRAME_TYPES.contains(&first) {
+                break;
+            }
+
+            match self.parse_inline()? {
+                Some(args) if args.is_empty() => continue,
+                Some(args) => return Ok(Some(Frame::Array(args))),
+                None => return Ok(None),
+            }
+        }
 
+        match Frame::from_bytes_with_limits(
+            &self.buffer[..],
+            self.max_frame_size,
+            self.max_array_len,
+        ) {
+            Ok((frame, len)) => {
                 // Discard the parsed data from the read buffer.
                 //
                 // When `advance` is called on the read buffer, all of the data
@@ -140,97 +345,131 @@ impl Connection {
             Err(Incomplete) => Ok(None),
             // An error was encountered while parsing the frame. The connection
             // is now in an invalid state. Returning `Err` from here will result
-            // in the connection being closed.
+            // in the connection being closed, but should not impact any other
+            // connected client.
             Err(e) => Err(e.into()),
         }
     }
 
+    /// Tries to parse an inline command from the buffer.
+    ///
+    /// An inline command is a single line of whitespace separated arguments,
+    /// terminated by `\n` or `\r\n`. The arguments are returned as bulk
+    /// frames, as if the command had been sent as an array. If the line has
+    /// not been fully received yet, `None` is returned. A line with more than
+    /// `max_array_len` arguments is an error, as for arrays.
+    fn parse_inline(&mut self) -> Result<Option<Vec<Frame>>, frame::Error> {
+        let end = match self.buffer.iter().position(|&b| b == b'\n') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let line = self.buffer.split_to(end + 1).freeze();
+
+        let args: Vec<_> = line[..end]
+            .split(|b| b.is_ascii_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| Frame::Bulk(line.slice_ref(arg)))
+            .collect();
+
+        frame::check_count(args.len() as u64, self.max_array_len)?;
+
+        Ok(Some(args))
+    }
+
     /// Write a single `Frame` value to the underlying stream.
     ///
-    /// The `Frame` value is written to the socket using the various `write_*`
-    /// functions provided by `AsyncWrite`. Calling these functions directly on
-    /// a `TcpStream` is **not** advised, as this will result in a large number of
-    /// syscalls. However, it is fine to call these functions on a *buffered*
-    /// write stream. The data will be written to the buffer. Once the buffer is
-    /// full, it is flushed to the underlying socket.
+    /// The `Frame` value is encoded with `Frame::encode`, using the protocol
+    /// version negotiated with the peer, and written to the *buffered* write
+    /// stream. Once the buffer is full, it is flushed to the underlying socket.
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        // Arrays are encoded by encoding each entry. All other frame types are
-        // considered literals. For now, mini-redis is not able to encode
-        // recursive frame structures. See below for more details.
-        match frame {
-            Frame::Array(val) => {
-                // Encode the frame type prefix. For an array, it is `*`.
-                self.stream.write_u8(b'*').await?;
-
-                // Encode the length of the array.
-                self.write_decimal(val.len() as u64).await?;
-
-                // Iterate and encode each entry in the array.
-                for entry in &**val {
-                    self.write_value(entry).await?;
-                }
-            }
-            // The frame type is a literal. Encode the value directly.
-            _ => self.write_value(frame).await?,
-        }
+        self.write_frame_buffered(frame).await?;
 
         // Ensure the encoded frame is written to the socket. The calls above
         // are to the buffered stream and writes. Calling `flush` writes the
         // remaining contents of the buffer to the socket.
-        self.stream.flush().await
+        self.flush().await
     }
 
-    /// Write a frame literal to the stream
-    async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Simple(val) => {
-                self.stream.write_u8(b'+').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Error(val) => {
-                self.stream.write_u8(b'-').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Integer(val) => {
-                self.stream.write_u8(b':').await?;
-                self.write_decimal(*val).await?;
-            }
-            Frame::Null => {
-                self.stream.write_all(b"$-1\r\n").await?;
-            }
-            Frame::Bulk(val) => {
-                let len = val.len();
+    /// Encode a single `Frame` value into the write buffer without flushing.
+    ///
+    /// This allows several frames to be sent with a single write to the
+    /// socket, for example when pipelining commands. The frames are only
+    /// guaranteed to reach the peer once `flush` is called. The buffer may
+    /// still be written out early if it fills up.
+    pub async fn write_frame_buffered(&mut self, frame: &Frame) -> io::Result<()> {
+        // While capturing, frames are collected instead of being encoded. See
+        // `start_capture`.
+        if let Some(captured) = &mut self.captured {
+            captured.push(frame.clone());
+            return Ok(());
+        }
 
-                self.stream.write_u8(b'$').await?;
-                self.write_decimal(len as u64).await?;
-                self.stream.write_all(val).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            // Encoding an `Array` from within a value cannot be done using a
-            // recursive strategy. In general, async fns do not support
-            // recursion. Mini-redis has not needed to encode nested arrays yet,
-            // so for now it is skipped.
-            Frame::Array(_val) => unreachable!(),
+        // The peer asked not to receive replies. See `set_muted`.
+        if self.muted {
+            return Ok(());
         }
 
-        Ok(())
+        // Frames are encoded in memory first, then written to the buffered
+        // stream in one go.
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf, self.protocol);
+
+        let write = self.stream.write_all(&buf);
+        let res = with_timeout(self.write_timeout, "write", write).await;
+        self.poisoned |= res.is_err();
+        res
     }
 
-    /// Write a decimal frame to the stream
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
-        use std::io::Write;
+    /// Write any buffered frames to the socket.
+    ///
+    /// Frames written with `write_frame_buffered` are only guaranteed to reach
+    /// the peer once this is called.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        let flush = self.stream.flush();
+        let res = with_timeout(self.write_timeout, "write", flush).await;
+        self.poisoned |= res.is_err();
+        res
+    }
 
-        // Convert the value to a string
-        let mut buf = [0u8; 20];
-        let mut buf = Cursor::new(&mut buf[..]);
-        write!(&mut buf, "{}", val)?;
+    /// Drop the frames written from now on instead of sending them to the
+    /// peer, if `muted` is set.
+    ///
+    /// This is used by `CLIENT REPLY` to silence the replies to commands.
+    pub(crate) fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
 
-        let pos = buf.position() as usize;
-        self.stream.write_all(&buf.get_ref()[..pos]).await?;
-        self.stream.write_all(b"\r\n").await?;
+    /// Start collecting written frames instead of sending them to the peer.
+    ///
+    /// This is used by `EXEC` to gather the replies of the queued commands
+    /// into a single array reply. The collected frames are returned by
+    /// `finish_capture`.
+    pub(crate) fn start_capture(&mut self) {
+        self.captured = Some(vec![]);
+    }
 
-        Ok(())
+    /// Stop collecting frames and return the frames written since
+    /// `start_capture` was called.
+    pub(crate) fn finish_capture(&mut self) -> Vec<Frame> {
+        self.captured.take().unwrap_or_default()
+    }
+}
+
+/// Wait for the I/O operation `op` for up to `timeout`, failing with an error
+/// of kind `io::ErrorKind::TimedOut` naming the operation once elapsed.
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    operation: &str,
+    op: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    match timeout {
+        Some(timeout) => match time::timeout(timeout, op).await {
+            Ok(res) => res,
+            Err(_) => {
+                let msg = format!("{} timed out", operation);
+                Err(io::Error::new(io::ErrorKind::TimedOut, msg))
+            }
+        },
+        None => op.await,
     }
 }
@@ -1,43 +1,321 @@
-use tokio::sync::{broadcast, Notify};
+use crate::cmd::{key_lossy, BitUnit, ExpireCondition, InsertPosition, ListEnd, PauseMode};
+use crate::glob;
+use crate::server::{Config, EventClass, KeyspaceEvents, MaxMemoryPolicy};
+use crate::zset::SortedSet;
+
+use tokio::sync::futures::Notified;
+use tokio::sync::{broadcast, mpsc, Notify, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::task;
 use tokio::time::{self, Duration, Instant};
 
-use bytes::Bytes;
-use std::collections::{BTreeSet, HashMap};
-use std::sync::{Arc, Mutex};
+use bytes::{Bytes, BytesMut};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::net::SocketAddr;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+use std::str;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
 use tracing::debug;
 
-/// A wrapper around a `Db` instance. This exists to allow orderly cleanup
-/// of the `Db` by signalling the background purge task to shut down when
-/// this struct is dropped.
+/// Maximum number of expired keys the background task purges from each shard
+/// before releasing the locks and yielding.
+const PURGE_BATCH_SIZE: usize = 100;
+
+/// Largest number of messages a pub/sub channel may buffer. Channels allocate
+/// their buffer up front, so this bounds the memory each one can take.
+pub(crate) const MAX_CHANNEL_CAPACITY: usize = 1 << 20;
+
+/// Number of low bits of a `SCAN` cursor holding the position of the next key
+/// in its shard. The bits above hold the index of the shard.
+const SCAN_POSITION_BITS: u32 = 48;
+
+/// Largest number of shards of a database, so that the index of any shard
+/// fits in a `SCAN` cursor.
+const MAX_SHARDS: usize = 1 << (64 - SCAN_POSITION_BITS);
+
+/// Names of the types of values, as reported by `TYPE`.
+const TYPE_NAMES: [&str; 5] = ["string", "list", "hash", "set", "zset"];
+
+/// Number of commands buffered for the slowest connection in `MONITOR` mode.
+/// Commands it could not keep up with are dropped.
+const MONITOR_CAPACITY: usize = 1024;
+
+/// Access frequency counter of new keys. It is not zero, so that new keys get
+/// a chance to be accessed before they are evicted.
+const LFU_INIT_VAL: u8 = 5;
+
+/// How hard it gets to increment the access frequency counter as it grows.
+/// With `10`, as in Redis, the counter saturates after about a million
+/// accesses.
+const LFU_LOG_FACTOR: f64 = 10.0;
+
+/// The access frequency counter is decremented once per period elapsed
+/// without the key being accessed.
+const LFU_DECAY_TIME: Duration = Duration::from_secs(60);
+
+/// A wrapper around the server's `Db` instances. This exists to allow orderly
+/// cleanup of the databases by signalling their background purge tasks to shut
+/// down when this struct is dropped.
 #[derive(Debug)]
 pub(crate) struct DbDropGuard {
-    /// The `Db` instance that will be shut down when this `DbHolder` struct
-    /// is dropped.
-    db: Db,
+    /// The `Db` instances that will be shut down when this `DbHolder` struct
+    /// is dropped, indexed by database number.
+    dbs: Vec<Db>,
 }
 
 /// Server state shared across all connections.
 ///
-/// `Db` contains a `HashMap` storing the key/value data and all
-/// `broadcast::Sender` values for active pub/sub channels.
+/// `Db` contains a `HashMap` storing the key/value data of one logical
+/// database. A server holds several of them, selected with `SELECT`. The
+/// `broadcast::Sender` values for active pub/sub channels are shared by all
+/// databases of a server.
 ///
 /// A `Db` instance is a handle to shared state. Cloning `Db` is shallow and
 /// only incurs an atomic ref count increment.
 ///
-/// When a `Db` value is created, a background task is spawned. This task is
-/// used to expire values after the requested duration has elapsed. The task
-/// runs until all instances of `Db` are dropped, at which point the task
-/// terminates.
+/// The keys are spread over several shards, each guarded by its own lock, so
+/// that commands on keys of different shards do not wait on each other.
+///
+/// When a `Db` value is created, two background tasks are spawned. The first
+/// is used to expire values after the requested duration has elapsed, the
+/// second drops the values removed by `UNLINK`. The tasks run until all
+/// instances of `Db` are dropped, at which point they terminate.
 #[derive(Debug, Clone)]
 pub(crate) struct Db {
     /// Handle to shared state. The background task will also have an
     /// `Arc<Shared>`.
     shared: Arc<Shared>,
+
+    /// Handle to state shared with the other databases of the server.
+    global: Arc<Global>,
+}
+
+/// State shared by all databases of a server.
+#[derive(Debug)]
+struct Global {
+    /// The pub/sub key-space. Redis uses a **separate** key space for key-value
+    /// and pub/sub. `mini-redis` handles this by using a separate `HashMap`.
+    /// Channels are not scoped to a database, a message published while one
+    /// database is selected reaches subscribers on every database.
+    pub_sub: Mutex<HashMap<String, broadcast::Sender<Bytes>>>,
+
+    /// Pattern subscriptions, keyed by glob-style pattern. Each message is sent
+    /// along with the name of the channel it was published to.
+    patterns: Mutex<HashMap<String, broadcast::Sender<(String, Bytes)>>>,
+
+    /// Number of messages each pub/sub channel buffers for its slowest
+    /// subscriber, unless configured otherwise for the channel.
+    channel_capacity: AtomicUsize,
+
+    /// Capacity of the channels configured with `PUBSUB CAPACITY`, overriding
+    /// `channel_capacity`.
+    channel_capacities: Mutex<HashMap<String, usize>>,
+
+    /// Serializes `EXEC` against all other commands.
+    ///
+    /// Regular commands hold a read guard while they are applied, `EXEC` holds
+    /// the write guard while applying its queued commands. This way no other
+    /// connection observes or modifies any database half way through a
+    /// transaction. A Tokio lock is used as the guard is held across `.await`
+    /// points while replies are written.
+    exec_lock: RwLock<()>,
+
+    /// Server statistics, reported by `INFO`.
+    stats: Stats,
+
+    /// Connections currently open, reported by `CLIENT LIST`.
+    clients: Mutex<HashMap<u64, ClientInfo>>,
+
+    /// Identifier given to the next connection to register.
+    next_client_id: AtomicU64,
+
+    /// Commands that took too long to apply, reported by `SLOWLOG`.
+    slowlog: Mutex<SlowLog>,
+
+    /// Commands processed by the server, formatted for the connections in
+    /// `MONITOR` mode, which each hold a receiver.
+    monitors: broadcast::Sender<String>,
+
+    /// Names of the commands clients may send, `None` if every command is
+    /// accepted.
+    allowed_commands: Mutex<Option<HashSet<String>>>,
+
+    /// Largest bulk string accepted from clients, in bytes. Connections read
+    /// it before each frame, so a new limit applies to the next request.
+    max_frame_size: AtomicUsize,
+
+    /// Instant until which connections are paused with `CLIENT PAUSE`, along
+    /// with the commands held, `None` unless paused.
+    pause: Mutex<Option<(Instant, PauseMode)>>,
+
+    /// Notifies the connections waiting for the pause to end that it was
+    /// replaced or ended early.
+    pause_changed: Notify,
+}
+
+/// Bounded log of the commands that took too long to apply.
+///
+/// Once `max_len` entries are logged, the oldest entry is dropped to make
+/// room for each new one.
+#[derive(Debug)]
+struct SlowLog {
+    /// Logged commands, oldest first.
+    entries: VecDeque<SlowLogEntry>,
+
+    /// Maximum number of entries kept.
+    max_len: usize,
+
+    /// Identifier given to the next entry. Identifiers are not reused when the
+    /// log is reset.
+    next_id: u64,
+}
+
+/// A command recorded in the slow log.
+#[derive(Debug, Clone)]
+pub(crate) struct SlowLogEntry {
+    /// Unique identifier of the entry.
+    id: u64,
+
+    /// When the command was logged.
+    logged_at: SystemTime,
+
+    /// How long the command took to apply.
+    duration: Duration,
+
+    /// Name and arguments of the command, possibly truncated.
+    args: Vec<Bytes>,
+
+    /// Address of the peer that sent the command, if any.
+    addr: Option<SocketAddr>,
+
+    /// Name of the connection that sent the command, if any.
+    name: Option<String>,
+}
+
+/// A connection registered with `Db::register_client`.
+#[derive(Debug, Clone)]
+pub(crate) struct ClientInfo {
+    /// Unique identifier of the connection.
+    id: u64,
+
+    /// Address of the peer.
+    addr: SocketAddr,
+
+    /// Name set with `CLIENT SETNAME`.
+    name: Option<String>,
+
+    /// When the connection was accepted.
+    connected_at: Instant,
+
+    /// Index of the database selected with `SELECT`.
+    db: usize,
+
+    /// Signals the connection handler to terminate, used by `CLIENT KILL`.
+    kill: broadcast::Sender<()>,
+}
+
+/// Registration of a connection with the server.
+///
+/// The connection is removed from the registry when the guard is dropped.
+/// This happens when its handler completes, including when it panics, so no
+/// entry outlives its connection.
+#[derive(Debug)]
+pub(crate) struct ClientGuard {
+    /// Identifier of the registered connection.
+    id: u64,
+
+    /// Address of the peer.
+    addr: SocketAddr,
+
+    /// State holding the registry.
+    global: Arc<Global>,
+}
+
+impl Global {
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel, counting pattern subscribers.
+    fn publish(&self, key: &str, value: Bytes) -> usize {
+        let pub_sub = self.pub_sub.lock().unwrap();
+
+        let num_subscribers = pub_sub
+            .get(key)
+            // On a successful message send on the broadcast channel, the number
+            // of subscribers is returned. An error indicates there are no
+            // receivers, in which case, `0` should be returned.
+            .map(|tx| tx.send(value.clone()).unwrap_or(0))
+            // If there is no entry for the channel key, then there are no
+            // subscribers. In this case, return `0`.
+            .unwrap_or(0);
+
+        drop(pub_sub);
+
+        // Every pattern has to be checked against the channel, the exact
+        // lookup above does not pay for this.
+        let patterns = self.patterns.lock().unwrap();
+
+        num_subscribers
+            + patterns
+                .iter()
+                .filter(|(pattern, _)| glob::matches(pattern.as_bytes(), key.as_bytes()))
+                .map(|(_, tx)| tx.send((key.to_string(), value.clone())).unwrap_or(0))
+                .sum::<usize>()
+    }
+
+    /// Returns the number of messages `channel` buffers once created.
+    fn capacity_of(&self, channel: &str) -> usize {
+        match self.channel_capacities.lock().unwrap().get(channel) {
+            Some(&capacity) => capacity,
+            None => self.channel_capacity.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters describing the activity of a server, reported by `INFO` and the
+/// metrics endpoint.
+///
+/// The counters are updated by the connection handlers without taking any
+/// lock, except for the per command counts.
+#[derive(Debug)]
+pub(crate) struct Stats {
+    /// When the server started.
+    started_at: Instant,
+
+    /// Number of connections accepted since the server started.
+    connections_received: AtomicU64,
+
+    /// Number of connections currently open.
+    connected_clients: AtomicU64,
+
+    /// Number of commands received since the server started.
+    commands_processed: AtomicU64,
+
+    /// Number of times each command was applied, keyed by command name.
+    commands: Mutex<HashMap<String, u64>>,
+
+    /// Number of keys removed because they expired.
+    expired_keys: AtomicU64,
+
+    /// Number of expired keys removed by the background task, as opposed to
+    /// the keys found expired when accessed. They are also counted in
+    /// `expired_keys`.
+    reaped_keys: AtomicU64,
 }
 
 #[derive(Debug)]
 struct Shared {
-    /// The shared state is guarded by a mutex. This is a `std::sync::Mutex` and
+    /// The keyspace is split into shards, and a key always belongs to the
+    /// shard picked by hashing its name. Commands on a single key only lock
+    /// that shard, commands on several keys lock each of their shards, in
+    /// index order so that they cannot deadlock, and commands on the whole
+    /// database lock every shard.
+    ///
+    /// Each shard is guarded by a mutex. This is a `std::sync::Mutex` and
     /// not a Tokio mutex. This is because there are no asynchronous operations
     /// being performed while holding the mutex. Additionally, the critical
     /// sections are very small.
@@ -49,294 +327,4637 @@ struct Shared {
     /// operations), then the entire operation, including waiting for the mutex,
     /// is considered a "blocking" operation and `tokio::task::spawn_blocking`
     /// should be used.
-    state: Mutex<State>,
+    shards: Box<[Mutex<State>]>,
+
+    /// Picks the shard of a key. Seeded randomly, so that clients cannot pile
+    /// their keys onto a single shard.
+    hasher: RandomState,
 
-    /// Notifies the background task handling entry expiration. The background
-    /// task waits on this to be notified, then checks for expired values or the
-    /// shutdown signal.
+    /// Notifies the background task handling entry expiration. A single task
+    /// purges every shard, it waits on this to be notified, then checks for
+    /// expired values or the shutdown signal.
     background_task: Notify,
+
+    /// True when the Db instance is shutting down. This happens when all `Db`
+    /// values drop. Setting this to `true` signals to the background task to
+    /// exit.
+    shutdown: AtomicBool,
+
+    /// False while the background task is paused with `DEBUG
+    /// SET-ACTIVE-EXPIRE 0`. Expired keys are then hidden from reads, but
+    /// only removed when written to or swept with `DEBUG EXPIRE-SWEEP`.
+    active_expire: AtomicBool,
+
+    /// Hands the values removed by `UNLINK` to the task that drops them, so
+    /// that freeing large values does not delay the command.
+    lazy_free: mpsc::UnboundedSender<Value>,
 }
 
+/// A shard of a database, holding the keys whose name hashes to it.
 #[derive(Debug)]
 struct State {
     /// The key-value data. We are not trying to do anything fancy so a
-    /// `std::collections::HashMap` works fine.
-    entries: HashMap<String, Entry>,
-
-    /// The pub/sub key-space. Redis uses a **separate** key space for key-value
-    /// and pub/sub. `mini-redis` handles this by using a separate `HashMap`.
-    pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
+    /// `std::collections::HashMap` works fine, alongside the order `SCAN`
+    /// visits it in.
+    entries: Entries,
 
     /// Tracks key TTLs.
     ///
     /// A `BTreeSet` is used to maintain expirations sorted by when they expire.
     /// This allows the background task to iterate this map to find the value
-    /// expiring next.
+    /// of the shard expiring next.
     ///
     /// While highly unlikely, it is possible for more than one expiration to be
     /// created for the same instant. Because of this, the `Instant` is
     /// insufficient for the key. A unique key (`String`) is used to
     /// break these ties.
-    expirations: BTreeSet<(Instant, String)>,
+    expirations: BTreeSet<(Instant, Bytes)>,
 
-    /// True when the Db instance is shutting down. This happens when all `Db`
-    /// values drop. Setting this to `true` signals to the background task to
-    /// exit.
-    shutdown: bool,
+    /// Memory accounting used to enforce `maxmemory`, shared by all shards of
+    /// the database so that keys are evicted in least recently used order
+    /// across shards. `None` when no limit is configured, in which case no
+    /// accounting is done at all, and the shards share nothing.
+    ///
+    /// The lock is only taken while holding the lock of a shard, never the
+    /// other way around.
+    memory: Option<Arc<Mutex<Memory>>>,
+
+    /// Keys written while the memory limit was exceeded. Other keys are
+    /// evicted to make room once the shard is unlocked, as they may belong to
+    /// other shards, but never these.
+    overflow: Vec<Bytes>,
+
+    /// What to do once the memory limit is exceeded. The same in every shard.
+    policy: MaxMemoryPolicy,
+
+    /// Publishes changes to the keyspace when keyspace notifications are
+    /// enabled. The same in every shard.
+    notifications: Notifications,
+
+    /// Connections blocked in `BLPOP` or `BRPOP`, by the keys they wait on.
+    /// Each connection registers a single `Notify` under all of its keys.
+    waiters: HashMap<Bytes, Vec<Arc<Notify>>>,
+
+    /// Number of keys holding each type of value, by type name, as reported
+    /// by `INFO`. Kept up to date as entries are written and removed, so that
+    /// reporting does not visit the keys.
+    types: HashMap<&'static str, u64>,
 }
 
-/// Entry in the key-value store
+/// The entries of a shard, by key.
+///
+/// Reading goes through the map directly, writing goes through `Entries` so
+/// that the keys are also kept sorted by `scan_position`. `SCAN` then resumes
+/// where the previous batch stopped without visiting the other keys.
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<Bytes, Entry>,
+
+    /// The keys of `map`, in the order `Db::scan` visits them.
+    scan_order: BTreeSet<(u64, Bytes)>,
+}
+
+/// Publishes keyspace notifications for the changes made to a database.
+#[derive(Debug)]
+struct Notifications {
+    /// Number of the database, as selected with `SELECT`.
+    index: usize,
+
+    /// Which changes to the keyspace are published.
+    events: KeyspaceEvents,
+
+    /// Notifications are published through the pub/sub channels shared with
+    /// the other databases.
+    global: Arc<Global>,
+}
+
+/// Tracks the approximate memory used by a database and the order in which
+/// keys were last accessed, in order to evict the least recently used keys
+/// once the limit is exceeded.
+#[derive(Debug)]
+struct Memory {
+    /// Maximum number of bytes the keys and values may use.
+    limit: usize,
+
+    /// Number of bytes currently used by the keys and values.
+    used: usize,
+
+    /// Size and last access of each key.
+    keys: HashMap<Bytes, Usage>,
+
+    /// Keys ordered by last access, least recently used first. The access
+    /// counter is unique, so it is sufficient to order the keys.
+    lru: BTreeSet<(u64, Bytes)>,
+
+    /// Incremented on every access.
+    clock: u64,
+}
+
+/// Exclusive access to the shard holding a key, see `Shared::lock`.
+///
+/// Once the shard is unlocked, keys are evicted if writes made through the
+/// guard exceeded the memory limit.
+struct ShardGuard<'a> {
+    shared: &'a Shared,
+
+    /// Always `Some`, until the guard is dropped.
+    state: Option<MutexGuard<'a, State>>,
+}
+
+/// Exclusive access to several shards, see `Shared::lock_keys` and
+/// `Shared::lock_all`.
+///
+/// Once the shards are unlocked, keys are evicted if writes made through the
+/// guard exceeded the memory limit.
+struct Shards<'a> {
+    shared: &'a Shared,
+
+    /// Indices of the locked shards, in increasing order.
+    indices: Vec<usize>,
+
+    /// The locked shards, in the same order as `indices`.
+    guards: Vec<MutexGuard<'a, State>>,
+}
+
+/// Memory used by a key and when it was last accessed.
 #[derive(Debug)]
+struct Usage {
+    /// Approximate size of the key and its value, in bytes.
+    size: usize,
+
+    /// Value of `Memory::clock` when the key was last accessed.
+    last_access: u64,
+}
+
+/// Entry in the key-value store
+#[derive(Debug, Clone)]
 struct Entry {
-    /// Stored data
-    data: Bytes,
+    /// Stored value
+    value: Value,
 
     /// Instant at which the entry expires and should be removed from the
     /// database.
     expires_at: Option<Instant>,
+
+    /// Instant at which the entry was last read or written, as reported by
+    /// `OBJECT IDLETIME`.
+    accessed_at: Instant,
+
+    /// Logarithmic access frequency counter, as reported by `OBJECT FREQ`,
+    /// only counted with the `allkeys-lfu` policy. See `Entry::frequency`.
+    frequency: u8,
+
+    /// Type of value the entry is counted as in `State::types`, `None` until
+    /// the entry is first written.
+    counted: Option<&'static str>,
+}
+
+/// Value stored at a key.
+///
+/// Redis keys can hold values of different types. Commands only operate on the
+/// type they were written for, the type of a key is reported by `TYPE`.
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    /// A binary safe string, as written by `SET`.
+    String(Bytes),
+
+    /// A list of binary safe strings, as written by `LPUSH` and `RPUSH`.
+    List(VecDeque<Bytes>),
+
+    /// A map from binary safe field names to binary safe strings, as written
+    /// by `HSET`.
+    Hash(HashMap<Bytes, Bytes>),
+
+    /// A set of unique binary safe strings, as written by `SADD`.
+    Set(HashSet<Bytes>),
+
+    /// A set of unique binary safe strings ordered by score, as written by
+    /// `ZADD`.
+    SortedSet(SortedSet),
+}
+
+/// Operation combining the source sets of `Db::set_store`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SetOp {
+    /// Members of every set
+    Inter,
+
+    /// Members of any set
+    Union,
+
+    /// Members of the first set that are in none of the others
+    Diff,
+}
+
+/// Condition under which `Db::set_if` writes a value.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SetCondition {
+    /// Always write the value
+    Always,
+
+    /// Only write the value if the key does not exist
+    IfAbsent,
+
+    /// Only write the value if the key already exists
+    IfPresent,
+}
+
+/// How `Db::set_and_get` sets the expiration of the key it writes.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SetExpiration {
+    /// Remove any expiration, the key is persistent
+    Clear,
+
+    /// Expire the key once the duration has elapsed
+    In(Duration),
+
+    /// Keep the expiration of the previous value, if any
+    Keep,
+}
+
+/// Outcome of `Db::rename`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenameResult {
+    /// The key was moved to its new name
+    Renamed,
+
+    /// The source key does not exist
+    NoSuchKey,
+
+    /// The destination key exists and the rename was conditional on it not
+    /// existing. Nothing was changed.
+    Exists,
+}
+
+/// Outcome of purging expired keys, telling the background task what to do
+/// next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Purge {
+    /// More keys have expired, purge again once other tasks had a chance to
+    /// run.
+    Again,
+
+    /// Sleep until this instant, when the next key expires.
+    Until(Instant),
+
+    /// No key expires, sleep until notified.
+    Idle,
+}
+
+/// Error returned when an operation cannot be applied to the value stored at a
+/// key.
+///
+/// These errors are expected at run time, for example when a client tries to
+/// increment a key holding text. They are reported back to the client as
+/// `Frame::Error` replies and do not terminate the connection.
+#[derive(Debug)]
+pub(crate) enum DbError {
+    /// The stored value cannot be interpreted as a 64 bit signed integer.
+    NotInteger,
+
+    /// Applying the operation would overflow a 64 bit signed integer.
+    Overflow,
+
+    /// The stored value cannot be interpreted as a floating point number.
+    NotFloat,
+
+    /// Applying the operation would produce an infinite or NaN float.
+    NotFinite,
+
+    /// The hash field cannot be interpreted as a 64 bit signed integer.
+    HashNotInteger,
+
+    /// The hash field cannot be interpreted as a floating point number.
+    HashNotFloat,
+
+    /// The key holds a value of a type the operation does not support.
+    ///
+    /// Every operation checks the type of the value after setting aside
+    /// expired keys, so a key that expired but was not purged yet is absent
+    /// rather than of the wrong type. All commands reply with this same error.
+    WrongType,
+
+    /// Evicting keys cannot free enough memory to store the value.
+    OutOfMemory,
+
+    /// The key does not exist.
+    NoSuchKey,
+
+    /// The index is out of the range of the list.
+    IndexOutOfRange,
+
+    /// The count of random entries requested is too large to be replied.
+    CountOutOfRange,
 }
 
 impl DbDropGuard {
-    /// Create a new `DbHolder`, wrapping a `Db` instance. When this is dropped
-    /// the `Db`'s purge task will be shut down.
-    pub(crate) fn new() -> DbDropGuard {
-        DbDropGuard { db: Db::new() }
+    /// Create a new `DbHolder`, wrapping `config.databases` `Db` instances, at
+    /// least one, that share their pub/sub channels. When this is dropped the
+    /// purge task of every `Db` will be shut down.
+    ///
+    /// If `config.maxmemory` is set, each database keeps the size of its keys
+    /// and values under that many bytes, as directed by
+    /// `config.maxmemory_policy`.
+    pub(crate) fn new(config: &Config) -> DbDropGuard {
+        let global = Arc::new(Global {
+            pub_sub: Mutex::new(HashMap::new()),
+            patterns: Mutex::new(HashMap::new()),
+            channel_capacity: AtomicUsize::new(
+                config.channel_capacity.clamp(1, MAX_CHANNEL_CAPACITY),
+            ),
+            channel_capacities: Mutex::new(HashMap::new()),
+            exec_lock: RwLock::new(()),
+            stats: Stats::new(),
+            clients: Mutex::new(HashMap::new()),
+            next_client_id: AtomicU64::new(1),
+            slowlog: Mutex::new(SlowLog {
+                entries: VecDeque::new(),
+                max_len: config.slowlog_max_len,
+                next_id: 0,
+            }),
+            monitors: broadcast::channel(MONITOR_CAPACITY).0,
+            allowed_commands: Mutex::new(
+                config
+                    .allowed_commands
+                    .as_ref()
+                    .map(|names| names.iter().map(|name| name.to_lowercase()).collect()),
+            ),
+            max_frame_size: AtomicUsize::new(config.max_frame_size),
+            pause: Mutex::new(None),
+            pause_changed: Notify::new(),
+        });
+
+        let dbs = (0..config.databases.max(1))
+            .map(|index| Db::new(global.clone(), index, config))
+            .collect();
+
+        DbDropGuard { dbs }
     }
 
-    /// Get the shared database. Internally, this is an
-    /// `Arc`, so a clone only increments the ref count.
-    pub(crate) fn db(&self) -> Db {
-        self.db.clone()
+    /// Get the shared databases, indexed by database number. Internally, each
+    /// is an `Arc`, so a clone only increments the ref count.
+    pub(crate) fn dbs(&self) -> Vec<Db> {
+        self.dbs.clone()
     }
 }
 
 impl Drop for DbDropGuard {
     fn drop(&mut self) {
-        // Signal the 'Db' instance to shut down the task that purges expired keys
-        self.db.shutdown_purge_task();
+        // Signal the 'Db' instances to shut down the tasks that purge expired
+        // keys
+        for db in &self.dbs {
+            db.shutdown_purge_task();
+        }
     }
 }
 
 impl Db {
-    /// Create a new, empty, `Db` instance. Allocates shared state and spawns a
-    /// background task to manage key expiration.
-    pub(crate) fn new() -> Db {
+    /// Create a new, empty, `Db` instance numbered `index`, split into
+    /// `config.shards` shards. Allocates shared state and spawns a background
+    /// task to manage key expiration.
+    fn new(global: Arc<Global>, index: usize, config: &Config) -> Db {
+        let (lazy_free, unlinked) = mpsc::unbounded_channel();
+
+        let memory = config
+            .maxmemory
+            .map(|limit| Arc::new(Mutex::new(Memory::new(limit))));
+
+        let shards = (0..config.shards.clamp(1, MAX_SHARDS))
+            .map(|_| {
+                Mutex::new(State {
+                    entries: Entries::default(),
+                    expirations: BTreeSet::new(),
+                    memory: memory.clone(),
+                    overflow: Vec::new(),
+                    policy: config.maxmemory_policy,
+                    notifications: Notifications {
+                        index,
+                        events: config.notify_keyspace_events,
+                        global: global.clone(),
+                    },
+                    waiters: HashMap::new(),
+                    types: HashMap::new(),
+                })
+            })
+            .collect();
+
         let shared = Arc::new(Shared {
-            state: Mutex::new(State {
-                entries: HashMap::new(),
-                pub_sub: HashMap::new(),
-                expirations: BTreeSet::new(),
-                shutdown: false,
-            }),
+            shards,
+            hasher: RandomState::new(),
             background_task: Notify::new(),
+            shutdown: AtomicBool::new(false),
+            active_expire: AtomicBool::new(true),
+            lazy_free,
         });
 
-        // Start the background task.
+        // Start the background tasks.
         tokio::spawn(purge_expired_tasks(shared.clone()));
+        tokio::task::spawn_blocking(move || drop_unlinked_values(unlinked));
+
+        Db { shared, global }
+    }
+
+    /// Wait until no transaction is executing and return a guard that keeps
+    /// transactions from starting while a regular command is applied.
+    pub(crate) async fn lock_command(&self) -> RwLockReadGuard<'_, ()> {
+        self.global.exec_lock.read().await
+    }
 
-        Db { shared }
+    /// Wait until no other command is being applied and return a guard giving
+    /// the holder exclusive access to all databases, for `EXEC`.
+    pub(crate) async fn lock_exec(&self) -> RwLockWriteGuard<'_, ()> {
+        self.global.exec_lock.write().await
     }
 
     /// Get the value associated with a key.
     ///
     /// Returns `None` if there is no value associated with the key. This may be
     /// due to never having assigned a value to the key or a previously assigned
-    /// value expired.
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
+    /// value expired. Returns `Err` if the key holds a value that is not a
+    /// string.
+    pub(crate) fn get(&self, key: &[u8]) -> Result<Option<Bytes>, DbError> {
         // Acquire the lock, get the entry and clone the value.
         //
         // Because data is stored using `Bytes`, a clone here is a shallow
         // clone. Data is not copied.
-        let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => Ok(Some(data.clone())),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(None),
+        }
     }
 
-    /// Set the value associated with a key along with an optional expiration
-    /// Duration.
+    /// Set `key` to `value` and return the value it held before.
     ///
-    /// If a value is already associated with the key, it is removed.
-    pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
-        let mut state = self.shared.state.lock().unwrap();
+    /// Reading and writing happen while holding the lock, so no other command
+    /// can observe or modify the key in between. Any expiration associated
+    /// with the key is discarded. Returns `Err` if the key holds a value that
+    /// is not a string, in which case it is left untouched.
+    pub(crate) fn getset(&self, key: Bytes, value: Bytes) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.shared.lock(&key);
+        state.remove_if_expired(&key);
 
-        // If this `set` becomes the key that expires **next**, the background
-        // task needs to be notified so it can update its state.
-        //
-        // Whether or not the task needs to be notified is computed during the
-        // `set` routine.
-        let mut notify = false;
-
-        let expires_at = expire.map(|duration| {
-            // `Instant` at which the key expires.
-            let when = Instant::now() + duration;
-
-            // Only notify the worker task if the newly inserted expiration is the
-            // **next** key to evict. In this case, the worker needs to be woken up
-            // to update its state.
-            notify = state
-                .next_expiration()
-                .map(|expiration| expiration > when)
-                .unwrap_or(true);
+        let prev = match state.entries.get(&key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => Some(data.clone()),
+            Some(_) => return Err(DbError::WrongType),
+            None => None,
+        };
 
-            when
-        });
+        state.reserve(key.len() + value.len())?;
 
-        // Insert the entry into the `HashMap`.
-        let prev = state.entries.insert(
+        // Removing the entry also drops its expiration.
+        state.remove_entry(&key);
+        state.entries.insert(
             key.clone(),
             Entry {
-                data: value,
-                expires_at,
+                value: Value::String(value),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
             },
         );
+        state.written(&key);
+        state.notify(EventClass::String, "set", &key);
 
-        // If there was a value previously associated with the key **and** it
-        // had an expiration time. The associated entry in the `expirations` map
-        // must also be removed. This avoids leaking data.
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                // clear expiration
-                state.expirations.remove(&(when, key.clone()));
-            }
-        }
+        Ok(prev)
+    }
 
-        // Track the expiration. If we insert before remove that will cause bug
-        // when current `(when, key)` equals prev `(when, key)`. Remove then insert
-        // can avoid this.
-        if let Some(when) = expires_at {
-            state.expirations.insert((when, key));
-        }
+    /// Remove `key` and return the value it held.
+    ///
+    /// Returns `None` if there is no value associated with the key. Returns
+    /// `Err` if the key holds a value that is not a string, in which case it is
+    /// not removed.
+    pub(crate) fn getdel(&self, key: &[u8]) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
 
-        // Release the mutex before notifying the background task. This helps
-        // reduce contention by avoiding the background task waking up only to
-        // be unable to acquire the mutex due to this function still holding it.
+        let value = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data.clone(),
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(None),
+        };
+
+        state.remove_entry(key);
+        state.notify(EventClass::Generic, "del", key);
+
+        Ok(Some(value))
+    }
+
+    /// Get the value associated with a key and, if `expire` is set, replace
+    /// its expiration: `Some(None)` removes it, `Some(Some(when))` removes the
+    /// key once `when` is reached.
+    ///
+    /// Returns `None` if there is no value associated with the key, in which
+    /// case nothing is changed. Returns `Err` if the key holds a value that is
+    /// not a string.
+    pub(crate) fn getex(
+        &self,
+        key: &[u8],
+        expire: Option<Option<Instant>>,
+    ) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let value = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data.clone(),
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(None),
+        };
+
+        let notify = match expire {
+            Some(Some(when)) => {
+                let notify = state.set_expiration(key, Some(when));
+                state.notify(EventClass::Generic, "expire", key);
+                notify
+            }
+            Some(None) => {
+                if state
+                    .entries
+                    .get(key)
+                    .and_then(|entry| entry.expires_at)
+                    .is_some()
+                {
+                    state.set_expiration(key, None);
+                    state.notify(EventClass::Generic, "persist", key);
+                }
+                false
+            }
+            None => false,
+        };
+
+        // Release the mutex before notifying the background task, as `set`
+        // does.
         drop(state);
 
         if notify {
-            // Finally, only notify the background task if it needs to update
-            // its state to reflect a new expiration.
             self.shared.background_task.notify_one();
         }
+
+        Ok(Some(value))
     }
 
-    /// Returns a `Receiver` for the requested channel.
+    /// Append `value` to the string stored at `key`.
     ///
-    /// The returned `Receiver` is used to receive values broadcast by `PUBLISH`
-    /// commands.
-    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
-        use std::collections::hash_map::Entry;
+    /// A missing key is created holding `value`. Any expiration is kept.
+    /// Returns the length of the string after the append.
+    pub(crate) fn append(&self, key: &[u8], value: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
 
-        // Acquire the mutex
-        let mut state = self.shared.state.lock().unwrap();
+        let current = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data.clone(),
+            Some(_) => return Err(DbError::WrongType),
+            None => Bytes::new(),
+        };
 
-        // If there is no entry for the requested channel, then create a new
-        // broadcast channel and associate it with the key. If one already
-        // exists, return an associated receiver.
-        match state.pub_sub.entry(key) {
-            Entry::Occupied(e) => e.get().subscribe(),
-            Entry::Vacant(e) => {
-                // No broadcast channel exists yet, so create one.
-                //
-                // The channel is created with a capacity of `1024` messages. A
-                // message is stored in the channel until **all** subscribers
-                // have seen it. This means that a slow subscriber could result
-                // in messages being held indefinitely.
-                //
-                // When the channel's capacity fills up, publishing will result
+        let len = current.len() + value.len();
+        state.reserve(key.len() + len)?;
+
+        // `Bytes` is immutable, so the string is copied into a new buffer.
+        let mut data = BytesMut::with_capacity(len);
+        data.extend_from_slice(&current);
+        data.extend_from_slice(value);
+        let data = Value::String(data.freeze());
+
+        match state.entries.get_mut(key) {
+            Some(entry) => entry.value = data,
+            None => {
+                state.entries.insert(
+                    Bytes::copy_from_slice(key),
+                    Entry {
+                        value: data,
+                        expires_at: None,
+                        accessed_at: Instant::now(),
+                        frequency: LFU_INIT_VAL,
+                        counted: None,
+                    },
+                );
+            }
+        }
+        state.written(key);
+        state.notify(EventClass::String, "append", key);
+
+        Ok(len)
+    }
+
+    /// Returns the length of the string stored at `key`, or `0` if the key
+    /// does not exist.
+    pub(crate) fn strlen(&self, key: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => Ok(data.len()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the bytes of the string stored at `key` between the offsets
+    /// `start` and `end`, both inclusive.
+    ///
+    /// Negative offsets count from the end of the string, `-1` being the last
+    /// byte. The range is clamped to the string, and an empty slice is returned
+    /// if it does not overlap it or the key does not exist. The slice shares
+    /// the stored data, nothing is copied.
+    pub(crate) fn getrange(&self, key: &[u8], start: i64, end: i64) -> Result<Bytes, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let data = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(Bytes::new()),
+        };
+
+        // As in Redis, both offsets are clamped to the string once negative
+        // ones are resolved, so a range ending before the string still holds
+        // its first byte.
+        let len = data.len() as i64;
+        let start = if start < 0 { start + len } else { start }.max(0);
+        let end = if end < 0 { end + len } else { end }.max(0).min(len - 1);
+
+        if start > end || data.is_empty() {
+            return Ok(Bytes::new());
+        }
+
+        Ok(data.slice(start as usize..=end as usize))
+    }
+
+    /// Overwrite the string stored at `key` with `value`, starting at byte
+    /// `offset`.
+    ///
+    /// The string is grown as needed, padding it with zero bytes if `offset` is
+    /// past its end. A missing key is treated as an empty string. Any
+    /// expiration is kept. Returns the length of the string after the write.
+    pub(crate) fn setrange(
+        &self,
+        key: &[u8],
+        offset: usize,
+        value: &[u8],
+    ) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let current = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data.clone(),
+            Some(_) => return Err(DbError::WrongType),
+            None => Bytes::new(),
+        };
+
+        // Writing nothing leaves the string untouched, and does not create the
+        // key if it is missing.
+        if value.is_empty() {
+            return Ok(current.len());
+        }
+
+        let len = current.len().max(offset + value.len());
+        state.reserve(key.len() + len)?;
+
+        // `Bytes` is immutable, so the string is copied into a new buffer.
+        let mut data = BytesMut::with_capacity(len);
+        data.extend_from_slice(&current);
+        data.resize(len, 0);
+        data[offset..offset + value.len()].copy_from_slice(value);
+        let data = Value::String(data.freeze());
+
+        match state.entries.get_mut(key) {
+            Some(entry) => entry.value = data,
+            None => {
+                state.entries.insert(
+                    Bytes::copy_from_slice(key),
+                    Entry {
+                        value: data,
+                        expires_at: None,
+                        accessed_at: Instant::now(),
+                        frequency: LFU_INIT_VAL,
+                        counted: None,
+                    },
+                );
+            }
+        }
+        state.written(key);
+        state.notify(EventClass::String, "setrange", key);
+
+        Ok(len)
+    }
+
+    /// Set the bit at `offset` in the string stored at `key` to `bit`.
+    ///
+    /// Bit `0` is the most significant bit of the first byte. The string is
+    /// padded with zero bytes if `offset` is past its end. A missing key is
+    /// treated as an empty string. Any expiration is kept. Returns the previous
+    /// value of the bit.
+    pub(crate) fn setbit(&self, key: &[u8], offset: u64, bit: bool) -> Result<bool, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let current = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data.clone(),
+            Some(_) => return Err(DbError::WrongType),
+            None => Bytes::new(),
+        };
+
+        let byte = (offset / 8) as usize;
+        let mask = 0x80 >> (offset % 8);
+
+        let len = current.len().max(byte + 1);
+        state.reserve(key.len() + len)?;
+
+        // `Bytes` is immutable, so the string is copied into a new buffer.
+        let mut data = BytesMut::with_capacity(len);
+        data.extend_from_slice(&current);
+        data.resize(len, 0);
+        let prev = data[byte] & mask != 0;
+        if bit {
+            data[byte] |= mask;
+        } else {
+            data[byte] &= !mask;
+        }
+        let data = Value::String(data.freeze());
+
+        match state.entries.get_mut(key) {
+            Some(entry) => entry.value = data,
+            None => {
+                state.entries.insert(
+                    Bytes::copy_from_slice(key),
+                    Entry {
+                        value: data,
+                        expires_at: None,
+                        accessed_at: Instant::now(),
+                        frequency: LFU_INIT_VAL,
+                        counted: None,
+                    },
+                );
+            }
+        }
+        state.written(key);
+        state.notify(EventClass::String, "setbit", key);
+
+        Ok(prev)
+    }
+
+    /// Returns the bit at `offset` in the string stored at `key`.
+    ///
+    /// Bits past the end of the string, or of a missing key, are unset.
+    pub(crate) fn getbit(&self, key: &[u8], offset: u64) -> Result<bool, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let data = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(false),
+        };
+
+        let bit = data
+            .get((offset / 8) as usize)
+            .is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0);
+
+        Ok(bit)
+    }
+
+    /// Count the bits set between `start` and `end`, inclusive, of the string
+    /// stored at `key`, offsets being in bytes or bits as set by `unit`.
+    ///
+    /// Offsets are clamped as in `getrange`. A missing key counts as `0`.
+    pub(crate) fn bitcount(
+        &self,
+        key: &[u8],
+        start: i64,
+        end: i64,
+        unit: BitUnit,
+    ) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let data = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(0),
+        };
+
+        let (start, end) = match bit_range(data.len(), start, end, unit) {
+            Some(range) => range,
+            None => return Ok(0),
+        };
+
+        let (first, last) = (start / 8, end / 8);
+        let count: u32 = data[first..=last]
+            .iter()
+            .map(|byte| byte.count_ones())
+            .sum();
+
+        // Leave out the bits of the first and last bytes outside the range.
+        let before = data[first] & !(0xff >> (start % 8));
+        let after = data[last] & (0xffu16 >> (end % 8 + 1)) as u8;
+
+        Ok((count - before.count_ones() - after.count_ones()) as usize)
+    }
+
+    /// Returns the offset of the first bit set to `bit` between `start` and
+    /// `end`, inclusive, of the string stored at `key`, offsets being in bytes
+    /// or bits as set by `unit`. The offset returned is in bits from the start
+    /// of the string.
+    ///
+    /// Returns `-1` if no bit in the range matches, with one exception,
+    /// matching Redis: when looking for a clear bit without an `end`, the
+    /// string is considered padded with clear bits, so the offset of the bit
+    /// right after the string is returned. A missing key is an empty string.
+    pub(crate) fn bitpos(
+        &self,
+        key: &[u8],
+        bit: bool,
+        start: i64,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> Result<i64, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let data = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(if bit { -1 } else { 0 }),
+        };
+
+        let (start, last) = match bit_range(data.len(), start, end.unwrap_or(-1), unit) {
+            Some(range) => range,
+            None => return Ok(-1),
+        };
+
+        // Bytes made only of the bits not looked for are skipped whole.
+        let skipped = if bit { 0x00 } else { 0xff };
+        let mut offset = start;
+        while offset <= last {
+            let byte = data[offset / 8];
+
+            if offset % 8 == 0 && offset + 7 <= last && byte == skipped {
+                offset += 8;
+                continue;
+            }
+
+            if (byte & (0x80 >> (offset % 8)) != 0) == bit {
+                return Ok(offset as i64);
+            }
+            offset += 1;
+        }
+
+        if !bit && end.is_none() {
+            return Ok(last as i64 + 1);
+        }
+
+        Ok(-1)
+    }
+
+    /// Returns the name of the type of the value stored at `key`.
+    ///
+    /// Returns `"none"` if the key does not exist.
+    pub(crate) fn value_type(&self, key: &[u8]) -> &'static str {
+        let state = self.shared.lock(key);
+
+        match state.live_entry(key) {
+            Some(entry) => entry.value.type_name(),
+            None => "none",
+        }
+    }
+
+    /// Describe the value stored at `key`: the name of its type, its
+    /// approximate size in bytes and whether the key has an expiration.
+    ///
+    /// Returns `None` if the key does not exist.
+    pub(crate) fn describe(&self, key: &[u8]) -> Option<(&'static str, usize, bool)> {
+        let state = self.shared.lock(key);
+
+        state.live_entry(key).map(|entry| {
+            (
+                entry.value.type_name(),
+                entry.value.size(),
+                entry.expires_at.is_some(),
+            )
+        })
+    }
+
+    /// Returns the name of the encoding of the value stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist. The key is not marked as
+    /// used.
+    pub(crate) fn encoding(&self, key: &[u8]) -> Option<&'static str> {
+        let state = self.shared.lock(key);
+        state.live_entry(key).map(|entry| entry.value.encoding())
+    }
+
+    /// Returns the time elapsed since `key` was last read or written.
+    ///
+    /// Returns `None` if the key does not exist. The key is not marked as
+    /// used.
+    pub(crate) fn idle_time(&self, key: &[u8]) -> Option<Duration> {
+        let state = self.shared.lock(key);
+        state
+            .live_entry(key)
+            .map(|entry| entry.accessed_at.elapsed())
+    }
+
+    /// Returns the access frequency counter of `key`, see `Entry::frequency`.
+    ///
+    /// Returns `None` if the key does not exist. The key is not marked as
+    /// used.
+    pub(crate) fn frequency(&self, key: &[u8]) -> Option<u8> {
+        let state = self.shared.lock(key);
+        state
+            .live_entry(key)
+            .map(|entry| entry.frequency(Instant::now()))
+    }
+
+    /// Returns `true` if a value is associated with `key`.
+    ///
+    /// Keys that have expired but have not been purged yet by the background
+    /// task are reported as missing.
+    pub(crate) fn exists(&self, key: &[u8]) -> bool {
+        let state = self.shared.lock(key);
+        state.live_entry(key).is_some()
+    }
+
+    /// Mark `key` as just used, as reading it would, without reading its
+    /// value.
+    ///
+    /// Returns `false` if the key does not exist.
+    pub(crate) fn touch(&self, key: &[u8]) -> bool {
+        let mut state = self.shared.lock(key);
+
+        if state.live_entry(key).is_none() {
+            return false;
+        }
+
+        state.record_access(key);
+        true
+    }
+
+    /// Returns a copy of every live key, along with its value and remaining
+    /// time to live.
+    ///
+    /// The copy is taken while holding the lock of every shard, so it
+    /// reflects the state of the database at a single point in time.
+    pub(crate) fn dump(&self) -> Vec<(Bytes, Value, Option<Duration>)> {
+        let shards = self.shared.lock_all();
+        let now = Instant::now();
+
+        shards
+            .iter()
+            .flat_map(|state| state.entries.iter())
+            .filter(|(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .map(|(key, entry)| {
+                let ttl = entry.expires_at.map(|when| when - now);
+                (key.clone(), entry.value.clone(), ttl)
+            })
+            .collect()
+    }
+
+    /// Returns a copy of every live key holding a string, along with its value
+    /// and remaining time to live.
+    ///
+    /// As with `dump`, the copy is taken while holding the lock of every
+    /// shard, so it is consistent, and later writes do not affect it. Every
+    /// value is copied, which is cheap for `Bytes` but still allocates an entry
+    /// per key, and no command can be applied to the database in the
+    /// meantime. Large keyspaces are better walked with `SCAN`.
+    pub(crate) fn snapshot(&self) -> Vec<(Bytes, Bytes, Option<Duration>)> {
+        let shards = self.shared.lock_all();
+        let now = Instant::now();
+
+        shards
+            .iter()
+            .flat_map(|state| state.entries.iter())
+            .filter(|(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .filter_map(|(key, entry)| match &entry.value {
+                Value::String(data) => {
+                    let ttl = entry.expires_at.map(|when| when - now);
+                    Some((key.clone(), data.clone(), ttl))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Store `value` at `key`, replacing any previous value, and expire it
+    /// after `expire` if set.
+    ///
+    /// Used to load keys from a snapshot. Unlike `set_if`, any type of value
+    /// can be stored, and keys are evicted as needed to stay within
+    /// `maxmemory`.
+    pub(crate) fn restore(&self, key: Bytes, value: Value, expire: Option<Duration>) {
+        let mut state = self.shared.lock(&key);
+
+        let expires_at = expire.map(|duration| Instant::now() + duration);
+
+        state.remove_entry(&key);
+        state.entries.insert(
+            key.clone(),
+            Entry {
+                value,
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let notify = state.set_expiration(&key, expires_at);
+        state.written(&key);
+
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+    }
+
+    /// Returns a copy of the value stored at `key`, along with its remaining
+    /// time to live.
+    ///
+    /// Returns `None` if the key does not exist.
+    pub(crate) fn dump_key(&self, key: &[u8]) -> Option<(Value, Option<Duration>)> {
+        let mut state = self.shared.lock(key);
+        let now = Instant::now();
+
+        let (value, ttl) = state.live_entry(key).map(|entry| {
+            let ttl = entry.expires_at.map(|when| when - now);
+            (entry.value.clone(), ttl)
+        })?;
+
+        state.record_access(key);
+        Some((value, ttl))
+    }
+
+    /// Store `value` at `key` and expire it after `expire` if set.
+    ///
+    /// Unlike `restore`, the memory limit is enforced like for any other
+    /// write, and if `key` already exists, it is overwritten only if `replace`
+    /// is set. Returns `false` if the key was kept.
+    pub(crate) fn restore_key(
+        &self,
+        key: &[u8],
+        value: Value,
+        expire: Option<Duration>,
+        replace: bool,
+    ) -> Result<bool, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        if !replace && state.entries.contains_key(key) {
+            return Ok(false);
+        }
+
+        state.reserve(key.len() + value.size())?;
+
+        let expires_at = expire.map(|duration| Instant::now() + duration);
+
+        state.remove_entry(key);
+        state.entries.insert(
+            Bytes::copy_from_slice(key),
+            Entry {
+                value,
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let notify = state.set_expiration(key, expires_at);
+        state.written(key);
+        state.wake_waiters(key);
+        state.notify(EventClass::Generic, "restore", key);
+
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the time to live of a key.
+    ///
+    /// The outer `Option` is `None` if the key does not exist. The inner
+    /// `Option` is `None` if the key exists but has no associated expiration.
+    pub(crate) fn ttl(&self, key: &[u8]) -> Option<Option<Duration>> {
+        let state = self.shared.lock(key);
+        let entry = state.live_entry(key)?;
+
+        Some(
+            entry
+                .expires_at
+                .map(|when| when.saturating_duration_since(Instant::now())),
+        )
+    }
+
+    /// Set the value associated with a key along with an optional expiration
+    /// Duration, if `condition` holds.
+    ///
+    /// If a value is already associated with the key, it is removed. Whether
+    /// the key exists is checked while holding the same lock used to insert
+    /// the value, so concurrent conditional sets of the same key never both
+    /// succeed. Returns `true` if the value was set.
+    ///
+    /// Returns `Err` if the value does not fit within `maxmemory`, even after
+    /// evicting every other key.
+    pub(crate) fn set_if(
+        &self,
+        key: Bytes,
+        value: Bytes,
+        expire: Option<Duration>,
+        condition: SetCondition,
+    ) -> Result<bool, DbError> {
+        let expire = match expire {
+            Some(duration) => SetExpiration::In(duration),
+            None => SetExpiration::Clear,
+        };
+
+        let (set, _) = self.set_and_get(key, value, expire, condition, false)?;
+        Ok(set)
+    }
+
+    /// Set the value associated with a key, as `set_if` does, and, if `get` is
+    /// set, return the value previously stored at the key.
+    ///
+    /// The previous value is returned whether or not `condition` holds. It is
+    /// read under the same lock used to insert the new value, and so is the
+    /// previous expiration when it is kept with `SetExpiration::Keep`. With
+    /// `get`, `Err` is returned and nothing is written if the previous value
+    /// is not a string.
+    pub(crate) fn set_and_get(
+        &self,
+        key: Bytes,
+        value: Bytes,
+        expire: SetExpiration,
+        condition: SetCondition,
+        get: bool,
+    ) -> Result<(bool, Option<Bytes>), DbError> {
+        let mut state = self.shared.lock(&key);
+
+        let (prev, prev_expires_at) = match state.live_entry(&key) {
+            Some(entry) => (Some(&entry.value), entry.expires_at),
+            None => (None, None),
+        };
+
+        let exists = prev.is_some();
+        let prev = match prev {
+            Some(Value::String(data)) if get => Some(data.clone()),
+            Some(_) if get => return Err(DbError::WrongType),
+            _ => None,
+        };
+
+        let allowed = match condition {
+            SetCondition::Always => true,
+            SetCondition::IfAbsent => !exists,
+            SetCondition::IfPresent => exists,
+        };
+
+        if !allowed {
+            return Ok((false, prev));
+        }
+
+        state.reserve(key.len() + value.len())?;
+
+        // `Instant` at which the key expires.
+        let expires_at = match expire {
+            SetExpiration::Clear => None,
+            // Commands reject expirations that cannot be represented. Should
+            // one get through, the key never expires rather than the lock
+            // being poisoned.
+            SetExpiration::In(duration) => Instant::now().checked_add(duration),
+            SetExpiration::Keep => prev_expires_at,
+        };
+
+        // If this `set` becomes the key that expires **next**, the background
+        // task needs to be notified so it can update its state.
+        //
+        // Only notify the worker task if the newly inserted expiration is the
+        // **next** key to evict. In this case, the worker needs to be woken up
+        // to update its state.
+        let notify = expires_at
+            .map(|when| {
+                state
+                    .next_expiration()
+                    .map(|expiration| expiration > when)
+                    .unwrap_or(true)
+            })
+            .unwrap_or(false);
+
+        // Insert the entry into the `HashMap`.
+        let replaced = state.entries.insert(
+            key.clone(),
+            Entry {
+                value: Value::String(value),
+                expires_at,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        // If there was a value previously associated with the key **and** it
+        // had an expiration time. The associated entry in the `expirations` map
+        // must also be removed. This avoids leaking data.
+        if let Some(replaced) = replaced {
+            state.uncount(replaced.counted);
+
+            if let Some(when) = replaced.expires_at {
+                // clear expiration
+                state.expirations.remove(&(when, key.clone()));
+            }
+        }
+
+        // Track the expiration. If we insert before remove that will cause bug
+        // when current `(when, key)` equals prev `(when, key)`. Remove then insert
+        // can avoid this.
+        if let Some(when) = expires_at {
+            state.expirations.insert((when, key.clone()));
+        }
+
+        state.written(&key);
+        state.notify(EventClass::String, "set", &key);
+
+        // Release the mutex before notifying the background task. This helps
+        // reduce contention by avoiding the background task waking up only to
+        // be unable to acquire the mutex due to this function still holding it.
+        drop(state);
+
+        if notify {
+            // Finally, only notify the background task if it needs to update
+            // its state to reflect a new expiration.
+            self.shared.background_task.notify_one();
+        }
+
+        Ok((true, prev))
+    }
+
+    /// Set each key of `pairs` to its value, if `condition` holds for every
+    /// key.
+    ///
+    /// Existing values are replaced and their expirations removed. Either all
+    /// keys are written or none is: the condition is checked for all keys while
+    /// holding the lock used to write them. Returns `true` if the values were
+    /// set.
+    ///
+    /// Returns `Err`, writing nothing, if a value does not fit within
+    /// `maxmemory`, even after evicting every other key.
+    pub(crate) fn mset(
+        &self,
+        pairs: Vec<(Bytes, Bytes)>,
+        condition: SetCondition,
+    ) -> Result<bool, DbError> {
+        let mut shards = self.shared.lock_keys(pairs.iter().map(|(key, _)| &key[..]));
+
+        let allowed = pairs.iter().all(|(key, _)| {
+            let exists = shards.state(key).live_entry(key).is_some();
+            match condition {
+                SetCondition::Always => true,
+                SetCondition::IfAbsent => !exists,
+                SetCondition::IfPresent => exists,
+            }
+        });
+
+        if !allowed {
+            return Ok(false);
+        }
+
+        for (key, value) in &pairs {
+            shards.state(key).reserve(key.len() + value.len())?;
+        }
+
+        for (key, value) in pairs {
+            let state = shards.state(&key);
+
+            // Dropping the previous entry also drops its expiration.
+            state.remove_entry(&key);
+            state.entries.insert(
+                key.clone(),
+                Entry {
+                    value: Value::String(value),
+                    expires_at: None,
+                    accessed_at: Instant::now(),
+                    frequency: LFU_INIT_VAL,
+                    counted: None,
+                },
+            );
+
+            state.written(&key);
+            state.notify(EventClass::String, "set", &key);
+        }
+
+        Ok(true)
+    }
+
+    /// Remove the value associated with a key.
+    ///
+    /// Returns `true` if a value was removed. Any expiration tracked for the key
+    /// is dropped as well so the background task never sees a stale entry.
+    pub(crate) fn remove(&self, key: &[u8]) -> bool {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let removed = state.remove_entry(key);
+        if removed {
+            state.notify(EventClass::Generic, "del", key);
+        }
+
+        removed
+    }
+
+    /// Remove the key, leaving the value to be dropped by a background task.
+    ///
+    /// The key is gone once this returns, but freeing the memory held by a
+    /// large list or hash happens off the request path. Returns `true` if the
+    /// key existed.
+    pub(crate) fn unlink(&self, key: &[u8]) -> bool {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let entry = match state.take_entry(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        state.notify(EventClass::Generic, "del", key);
+        drop(state);
+
+        // If the task is gone, the server is shutting down and the value is
+        // simply dropped here.
+        let _ = self.shared.lazy_free.send(entry.value);
+
+        true
+    }
+
+    /// Returns the names of the keys matching the glob-style `pattern`.
+    ///
+    /// Every key of the database is visited while holding the lock of every
+    /// shard, so this takes time proportional to the size of the database and
+    /// blocks other commands meanwhile. It is meant for debugging. Keys that
+    /// expired but have not been purged by the background task yet are
+    /// skipped.
+    pub(crate) fn keys_matching(&self, pattern: &[u8]) -> Vec<Bytes> {
+        let shards = self.shared.lock_all();
+        let now = Instant::now();
+
+        shards
+            .iter()
+            .flat_map(|state| state.entries.iter())
+            .filter(|(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .filter(|(key, _)| glob::matches(pattern, key))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Returns a batch of about `count` keys, starting at `cursor`, along with
+    /// the cursor to pass to get the next batch.
+    ///
+    /// Shards are visited in turn, and the keys of each shard in the order of
+    /// a hash of their name. The cursor holds the index of the shard and the
+    /// position in that order at which the next batch starts. A scan starts
+    /// with cursor `0` and is complete once `0` is returned as the next
+    /// cursor. As the position of a key does not depend on the other keys,
+    /// every key present for the entire scan is returned at least once, even
+    /// if keys are added or removed in between. Keys added or removed during
+    /// the scan may or may not be returned.
+    ///
+    /// A single shard is locked at a time, and only the keys of the batch are
+    /// visited, so that a scan does not hold up other commands.
+    ///
+    /// Only keys matching `pattern`, if given, are returned. They are filtered
+    /// after the batch is selected, so a batch may hold fewer than `count`
+    /// keys, or none, before the scan is complete.
+    pub(crate) fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> (u64, Vec<Bytes>) {
+        let count = count.max(1);
+        let mut shard = (cursor >> SCAN_POSITION_BITS) as usize;
+        let mut start = cursor & ((1 << SCAN_POSITION_BITS) - 1);
+        let mut batch = Vec::new();
+        let mut visited = 0;
+
+        while shard < self.shared.shards.len() && visited < count {
+            let state = self.shared.shards[shard].lock().unwrap();
+            let now = Instant::now();
+            let mut last = None;
+
+            // Keys sharing a position must be returned in the same batch, as
+            // the cursor cannot point between them. Expired keys count toward
+            // the batch, so that the work done is bounded by `count`.
+            for (position, key) in state.entries.scan_order.range((start, Bytes::new())..) {
+                if visited >= count && last != Some(*position) {
+                    // A key was visited before this one, so the cursor is
+                    // never `0`, which would end the scan.
+                    let next = (shard as u64) << SCAN_POSITION_BITS | position;
+                    return (next, filter_scanned(batch, pattern));
+                }
+                last = Some(*position);
+                visited += 1;
+
+                let live = state.entries[key]
+                    .expires_at
+                    .map(|when| when > now)
+                    .unwrap_or(true);
+                if live {
+                    batch.push(key.clone());
+                }
+            }
+
+            drop(state);
+            shard += 1;
+            start = 0;
+        }
+
+        let next = if shard < self.shared.shards.len() {
+            (shard as u64) << SCAN_POSITION_BITS
+        } else {
+            0
+        };
+
+        (next, filter_scanned(batch, pattern))
+    }
+
+    /// Returns the number of keys in the database.
+    ///
+    /// Keys that expired but have not been purged by the background task yet
+    /// are not counted.
+    pub(crate) fn len(&self) -> u64 {
+        let shards = self.shared.lock_all();
+        let now = Instant::now();
+
+        shards
+            .iter()
+            .flat_map(|state| state.entries.values())
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+            .count() as u64
+    }
+
+    /// Returns a key of the database picked uniformly at random, or `None` if
+    /// the database is empty.
+    ///
+    /// Keys that expired but have not been purged by the background task yet
+    /// are never returned. Picking a key visits the keys while holding the
+    /// lock of every shard.
+    pub(crate) fn random_key(&self) -> Option<Bytes> {
+        let shards = self.shared.lock_all();
+        let now = Instant::now();
+
+        let live = || {
+            shards
+                .iter()
+                .flat_map(|state| state.entries.iter())
+                .filter(move |(_, entry)| entry.expires_at.map(|when| when > now).unwrap_or(true))
+        };
+
+        let len = live().count();
+        if len == 0 {
+            return None;
+        }
+
+        let (key, _) = live().nth((random() % len as u64) as usize)?;
+        Some(key.clone())
+    }
+
+    /// Returns the number of expirations tracked by the database, including
+    /// those of keys that expired but have not been purged yet.
+    pub(crate) fn pending_expirations(&self) -> usize {
+        let shards = self.shared.lock_all();
+        shards.iter().map(|state| state.expirations.len()).sum()
+    }
+
+    /// Returns the number of keys holding each type of value, in the order
+    /// of `TYPE_NAMES`, and the number of keys with an expiration.
+    ///
+    /// Unlike `len`, this does not visit the keys: counters are maintained as
+    /// keys are written and removed. Keys that expired but have not been
+    /// purged yet are counted, as they are by Redis.
+    pub(crate) fn keyspace_counts(&self) -> ([(&'static str, u64); 5], u64) {
+        let shards = self.shared.lock_all();
+
+        let mut types = TYPE_NAMES.map(|type_name| (type_name, 0));
+        for (type_name, count) in types.iter_mut() {
+            *count = shards
+                .iter()
+                .filter_map(|state| state.types.get(type_name))
+                .sum();
+        }
+
+        let expires = shards
+            .iter()
+            .map(|state| state.expirations.len() as u64)
+            .sum();
+
+        (types, expires)
+    }
+
+    /// Returns the maximum number of bytes the keys and values may use, or
+    /// `None` if there is no limit.
+    pub(crate) fn maxmemory(&self) -> Option<usize> {
+        let state = self.shared.shards[0].lock().unwrap();
+        let memory = state.memory.as_ref()?;
+        let limit = memory.lock().unwrap().limit;
+        Some(limit)
+    }
+
+    /// Change the memory limit, or remove it if `limit` is `None`.
+    ///
+    /// Keys are evicted right away if they now use more than the limit. When a
+    /// limit is first set, existing keys are tracked as if they had all been
+    /// accessed at once, so the order in which they are evicted is arbitrary.
+    pub(crate) fn set_maxmemory(&self, limit: Option<usize>) {
+        let mut shards = self.shared.lock_all();
+
+        let limit = match limit {
+            Some(limit) => limit,
+            None => {
+                for state in shards.iter_mut() {
+                    state.memory = None;
+                }
+                return;
+            }
+        };
+
+        let current = shards.iter().next().and_then(|state| state.memory.clone());
+
+        let memory = match current {
+            Some(memory) => {
+                memory.lock().unwrap().limit = limit;
+                memory
+            }
+            None => {
+                let mut memory = Memory::new(limit);
+                for state in shards.iter() {
+                    for (key, entry) in state.entries.iter() {
+                        memory.update(key, key.len() + entry.value.size());
+                    }
+                }
+
+                let memory = Arc::new(Mutex::new(memory));
+                for state in shards.iter_mut() {
+                    state.memory = Some(memory.clone());
+                }
+                memory
+            }
+        };
+
+        drop(shards);
+        self.shared.evict(&memory, &[]);
+    }
+
+    /// Returns what the database does once the memory limit is exceeded.
+    pub(crate) fn maxmemory_policy(&self) -> MaxMemoryPolicy {
+        self.shared.shards[0].lock().unwrap().policy
+    }
+
+    /// Change what the database does once the memory limit is exceeded.
+    pub(crate) fn set_maxmemory_policy(&self, policy: MaxMemoryPolicy) {
+        let mut shards = self.shared.lock_all();
+        for state in shards.iter_mut() {
+            state.policy = policy;
+        }
+
+        let memory = shards.iter().next().and_then(|state| state.memory.clone());
+        drop(shards);
+
+        if let Some(memory) = memory {
+            self.shared.evict(&memory, &[]);
+        }
+    }
+
+    /// Returns which changes to the keyspace are published.
+    pub(crate) fn keyspace_events(&self) -> KeyspaceEvents {
+        self.shared.shards[0].lock().unwrap().notifications.events
+    }
+
+    /// Change which changes to the keyspace are published.
+    pub(crate) fn set_keyspace_events(&self, events: KeyspaceEvents) {
+        for state in self.shared.lock_all().iter_mut() {
+            state.notifications.events = events;
+        }
+    }
+
+    /// Returns the statistics of the server, shared by all databases.
+    pub(crate) fn stats(&self) -> &Stats {
+        &self.global.stats
+    }
+
+    /// Register a connection from `addr`, initially unnamed and on the first
+    /// database. It stays registered until the returned guard is dropped.
+    ///
+    /// A value is sent on the returned receiver when the connection is killed
+    /// with `Db::kill_clients`.
+    pub(crate) fn register_client(
+        &self,
+        addr: SocketAddr,
+    ) -> (ClientGuard, broadcast::Receiver<()>) {
+        let id = self.global.next_client_id.fetch_add(1, Ordering::Relaxed);
+        let (kill, killed) = broadcast::channel(1);
+
+        let info = ClientInfo {
+            id,
+            addr,
+            name: None,
+            connected_at: Instant::now(),
+            db: 0,
+            kill,
+        };
+        self.global.clients.lock().unwrap().insert(id, info);
+
+        let guard = ClientGuard {
+            id,
+            addr,
+            global: self.global.clone(),
+        };
+
+        (guard, killed)
+    }
+
+    /// Signal the handlers of the connections for which `filter` returns
+    /// `true` to terminate. Returns the number of connections signalled.
+    pub(crate) fn kill_clients(&self, filter: impl Fn(&ClientInfo) -> bool) -> usize {
+        let clients = self.global.clients.lock().unwrap();
+
+        let mut killed = 0;
+        for client in clients.values().filter(|client| filter(client)) {
+            // The handler holds the receiver until it is dropped, along with
+            // the registration, so the send cannot fail.
+            let _ = client.kill.send(());
+            killed += 1;
+        }
+
+        killed
+    }
+
+    /// Hold the commands selected by `mode` on every connection until `until`,
+    /// replacing the current pause, if any.
+    pub(crate) fn pause_clients(&self, until: Instant, mode: PauseMode) {
+        *self.global.pause.lock().unwrap() = Some((until, mode));
+        self.global.pause_changed.notify_waiters();
+    }
+
+    /// End the current pause, if any.
+    pub(crate) fn unpause_clients(&self) {
+        *self.global.pause.lock().unwrap() = None;
+        self.global.pause_changed.notify_waiters();
+    }
+
+    /// Returns a future completing once the pause is replaced or ended early.
+    /// Changes made after this returns are not missed, even before the future
+    /// is first polled.
+    pub(crate) fn pause_changed(&self) -> Notified<'_> {
+        self.global.pause_changed.notified()
+    }
+
+    /// Returns the instant until which a command must wait before being
+    /// applied, `None` if it can be applied right away. Only pauses of every
+    /// command hold it, unless it is a `write` command.
+    ///
+    /// The pause ends on its own once the instant is reached.
+    pub(crate) fn paused_until(&self, write: bool) -> Option<Instant> {
+        let mut pause = self.global.pause.lock().unwrap();
+
+        match *pause {
+            Some((until, _)) if until <= Instant::now() => {
+                *pause = None;
+                None
+            }
+            Some((until, PauseMode::All)) => Some(until),
+            Some((until, PauseMode::Write)) if write => Some(until),
+            _ => None,
+        }
+    }
+
+    /// Returns the connections currently open on the server, by order of
+    /// connection.
+    pub(crate) fn clients(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<_> = self
+            .global
+            .clients
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        clients.sort_by_key(|client| client.id);
+        clients
+    }
+
+    /// Record that the command `args` took `duration` to apply, on behalf of
+    /// the connection from `addr` named `name`.
+    pub(crate) fn slowlog_push(
+        &self,
+        args: Vec<Bytes>,
+        duration: Duration,
+        addr: Option<SocketAddr>,
+        name: Option<String>,
+    ) {
+        let mut slowlog = self.global.slowlog.lock().unwrap();
+
+        if slowlog.max_len == 0 {
+            return;
+        }
+
+        if slowlog.entries.len() == slowlog.max_len {
+            slowlog.entries.pop_front();
+        }
+
+        let id = slowlog.next_id;
+        slowlog.next_id += 1;
+
+        slowlog.entries.push_back(SlowLogEntry {
+            id,
+            logged_at: SystemTime::now(),
+            duration,
+            args,
+            addr,
+            name,
+        });
+    }
+
+    /// Returns up to `count` entries of the slow log, or all of them if
+    /// `count` is `None`, most recent first.
+    pub(crate) fn slowlog_get(&self, count: Option<usize>) -> Vec<SlowLogEntry> {
+        let slowlog = self.global.slowlog.lock().unwrap();
+
+        let count = count.unwrap_or(slowlog.entries.len());
+        slowlog.entries.iter().rev().take(count).cloned().collect()
+    }
+
+    /// Returns the number of entries in the slow log.
+    pub(crate) fn slowlog_len(&self) -> usize {
+        self.global.slowlog.lock().unwrap().entries.len()
+    }
+
+    /// Remove all entries from the slow log.
+    pub(crate) fn slowlog_reset(&self) {
+        self.global.slowlog.lock().unwrap().entries.clear();
+    }
+
+    /// Remove all keys.
+    ///
+    /// The expirations are cleared along with the entries. The background task
+    /// is notified so it stops waiting for an expiration that no longer
+    /// exists.
+    pub(crate) fn flush(&self) {
+        let mut shards = self.shared.lock_all();
+        for state in shards.iter_mut() {
+            state.entries.clear();
+            state.expirations.clear();
+            state.types.clear();
+        }
+
+        if let Some(memory) = shards.iter().next().and_then(|state| state.memory.as_ref()) {
+            memory.lock().unwrap().clear();
+        }
+
+        // Release the mutexes before notifying the background task, see
+        // `Db::set_if`.
+        drop(shards);
+        self.shared.background_task.notify_one();
+    }
+
+    /// Add `delta` to the integer stored at `key` and return the result.
+    ///
+    /// A missing key is treated as holding `0`. The read, the addition and the
+    /// write all happen while holding the lock, so concurrent increments are
+    /// never lost. Any expiration associated with the key is left untouched.
+    pub(crate) fn incr_by(&self, key: &[u8], delta: i64) -> Result<i64, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let entry = match state.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => {
+                // The key does not exist yet, the base value is `0`.
+                state.entries.insert(
+                    Bytes::copy_from_slice(key),
+                    Entry {
+                        value: Value::String(Bytes::from(delta.to_string())),
+                        expires_at: None,
+                        accessed_at: Instant::now(),
+                        frequency: LFU_INIT_VAL,
+                        counted: None,
+                    },
+                );
+                state.written(key);
+                state.notify(EventClass::String, "incrby", key);
+                return Ok(delta);
+            }
+        };
+
+        let data = match &mut entry.value {
+            Value::String(data) => data,
+            _ => return Err(DbError::WrongType),
+        };
+
+        let current = str::from_utf8(data)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .ok_or(DbError::NotInteger)?;
+
+        let value = current.checked_add(delta).ok_or(DbError::Overflow)?;
+        *data = Bytes::from(value.to_string());
+        state.written(key);
+        state.notify(EventClass::String, "incrby", key);
+
+        Ok(value)
+    }
+
+    /// Add `delta` to the floating point number stored at `key` and return the
+    /// result, formatted as it is stored.
+    ///
+    /// A missing key is treated as holding `0`. The new value is written
+    /// without an exponent or trailing zeros. As with `incr_by`, everything
+    /// happens while holding the lock and any expiration is left untouched.
+    pub(crate) fn incr_by_float(&self, key: &[u8], delta: f64) -> Result<Bytes, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let current = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => str::from_utf8(data)
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or(DbError::NotFloat)?,
+            Some(_) => return Err(DbError::WrongType),
+            None => 0.0,
+        };
+
+        let value = current + delta;
+        if !value.is_finite() {
+            return Err(DbError::NotFinite);
+        }
+
+        // `Display` for `f64` never uses an exponent, and prints the shortest
+        // representation that parses back to the same value.
+        let data = Bytes::from(value.to_string());
+
+        match state.entries.get_mut(key) {
+            Some(entry) => entry.value = Value::String(data.clone()),
+            None => {
+                state.entries.insert(
+                    Bytes::copy_from_slice(key),
+                    Entry {
+                        value: Value::String(data.clone()),
+                        expires_at: None,
+                        accessed_at: Instant::now(),
+                        frequency: LFU_INIT_VAL,
+                        counted: None,
+                    },
+                );
+            }
+        }
+        state.written(key);
+        state.notify(EventClass::String, "incrbyfloat", key);
+
+        Ok(data)
+    }
+
+    /// Set a timeout on `key`. Once `when` is reached, the key is removed.
+    ///
+    /// Any previous expiration is replaced, if `condition` is met. The
+    /// condition is checked under the same lock the expiration is set with.
+    /// Returns `false` if the key does not exist or the condition is not met.
+    pub(crate) fn expire(
+        &self,
+        key: &[u8],
+        when: Instant,
+        condition: Option<ExpireCondition>,
+    ) -> bool {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let current = match state.entries.get(key) {
+            Some(entry) => entry.expires_at,
+            None => return false,
+        };
+
+        // A key without expiration is treated as expiring after any instant.
+        let met = match condition {
+            None => true,
+            Some(ExpireCondition::IfNone) => current.is_none(),
+            Some(ExpireCondition::IfSome) => current.is_some(),
+            Some(ExpireCondition::IfGreater) => {
+                current.map(|current| when > current).unwrap_or(false)
+            }
+            Some(ExpireCondition::IfLess) => current.map(|current| when < current).unwrap_or(true),
+        };
+
+        if !met {
+            return false;
+        }
+
+        let notify = state.set_expiration(key, Some(when));
+        state.notify(EventClass::Generic, "expire", key);
+
+        // Release the mutex before notifying the background task, as `set`
+        // does.
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        true
+    }
+
+    /// Move the value stored at `src` to `dst`, along with its expiration.
+    ///
+    /// Any value stored at `dst` is overwritten, unless `nx` is set, in which
+    /// case nothing is changed if `dst` exists. The key keeps the exact
+    /// `Instant` it was due to expire at.
+    pub(crate) fn rename(&self, src: &[u8], dst: &[u8], nx: bool) -> RenameResult {
+        let mut shards = self.shared.lock_keys([src, dst]);
+        shards.state(src).remove_if_expired(src);
+        shards.state(dst).remove_if_expired(dst);
+
+        if !shards.state(src).entries.contains_key(src) {
+            return RenameResult::NoSuchKey;
+        }
+
+        if nx && shards.state(dst).entries.contains_key(dst) {
+            return RenameResult::Exists;
+        }
+
+        if src == dst {
+            return RenameResult::Renamed;
+        }
+
+        let entry = match shards.state(src).take_entry(src) {
+            Some(entry) => entry,
+            None => return RenameResult::NoSuchKey,
+        };
+        let expires_at = entry.expires_at;
+
+        let state = shards.state(dst);
+        state.remove_entry(dst);
+        state.entries.insert(
+            Bytes::copy_from_slice(dst),
+            Entry {
+                value: entry.value,
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        // The expiration instant is unchanged, so the background task does not
+        // need to be notified, even if `dst` belongs to another shard.
+        state.set_expiration(dst, expires_at);
+        state.written(dst);
+        state.wake_waiters(dst);
+        shards
+            .state(src)
+            .notify(EventClass::Generic, "rename_from", src);
+        shards
+            .state(dst)
+            .notify(EventClass::Generic, "rename_to", dst);
+
+        RenameResult::Renamed
+    }
+
+    /// Copy the value stored at `src` to `dst`, along with its expiration.
+    ///
+    /// The stored entry is cloned as a whole, so the copy holds a value of the
+    /// same type. If `dst` already exists, it is overwritten only if `replace`
+    /// is set. Returns `false` if nothing was copied, either because `src` does
+    /// not exist or because `dst` was kept.
+    pub(crate) fn copy(&self, src: &[u8], dst: &[u8], replace: bool) -> Result<bool, DbError> {
+        let mut shards = self.shared.lock_keys([src, dst]);
+        shards.state(src).remove_if_expired(src);
+        shards.state(dst).remove_if_expired(dst);
+
+        let entry = match shards.state(src).entries.get(src) {
+            Some(entry) => entry.clone(),
+            None => return Ok(false),
+        };
+
+        if !replace && shards.state(dst).entries.contains_key(dst) {
+            return Ok(false);
+        }
+
+        let size = dst.len() + shards.state(src).size_of(src);
+
+        let state = shards.state(dst);
+        state.reserve(size)?;
+        state.remove_entry(dst);
+        state.entries.insert(
+            Bytes::copy_from_slice(dst),
+            Entry {
+                value: entry.value,
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        // The copy expires at the same instant as `src`, so the background
+        // task does not need to be notified.
+        state.set_expiration(dst, entry.expires_at);
+        state.written(dst);
+        state.wake_waiters(dst);
+        state.notify(EventClass::Generic, "copy_to", dst);
+
+        Ok(true)
+    }
+
+    /// Move `key` to the `dst` database, along with its expiration.
+    ///
+    /// Nothing is moved if `key` does not exist or if `dst` already holds it.
+    /// The shard holding the key in both databases is locked for the whole
+    /// move, always in the same order, so that concurrent moves in opposite
+    /// directions cannot deadlock. Returns `true` if the key was moved.
+    pub(crate) fn move_to(&self, key: &[u8], dst: &Db) -> Result<bool, DbError> {
+        assert!(
+            !Arc::ptr_eq(&self.shared, &dst.shared),
+            "cannot move a key to the database holding it"
+        );
+
+        let (mut src_state, mut dst_state) = if Arc::as_ptr(&self.shared) < Arc::as_ptr(&dst.shared)
+        {
+            let src_state = self.shared.lock(key);
+            (src_state, dst.shared.lock(key))
+        } else {
+            let dst_state = dst.shared.lock(key);
+            (self.shared.lock(key), dst_state)
+        };
+
+        src_state.remove_if_expired(key);
+        dst_state.remove_if_expired(key);
+
+        if dst_state.entries.contains_key(key) {
+            return Ok(false);
+        }
+
+        let size = match src_state.entries.get(key) {
+            Some(entry) => key.len() + entry.value.size(),
+            None => return Ok(false),
+        };
+
+        dst_state.reserve(size)?;
+
+        let entry = match src_state.take_entry(key) {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        let expires_at = entry.expires_at;
+
+        src_state.notify(EventClass::Generic, "move_from", key);
+
+        dst_state.entries.insert(
+            Bytes::copy_from_slice(key),
+            Entry {
+                expires_at: None,
+                ..entry
+            },
+        );
+
+        let notify = dst_state.set_expiration(key, expires_at);
+        dst_state.written(key);
+        dst_state.wake_waiters(key);
+        dst_state.notify(EventClass::Generic, "move_to", key);
+
+        drop(src_state);
+        drop(dst_state);
+
+        if notify {
+            dst.shared.background_task.notify_one();
+        }
+
+        Ok(true)
+    }
+
+    /// Exchange the keys of this database with those of `other`, along with
+    /// their expirations.
+    ///
+    /// Every shard of both databases is locked for the duration of the swap,
+    /// those of the database with the lower address first, so that concurrent
+    /// swaps cannot deadlock. As each database hashes keys its own way, the
+    /// keys are redistributed among the shards of their new database.
+    /// Connections blocked on a key that now exists are woken up, it may hold
+    /// a list to pop from.
+    pub(crate) fn swap(&self, other: &Db) {
+        if Arc::ptr_eq(&self.shared, &other.shared) {
+            return;
+        }
+
+        let (mut shards, mut other_shards) =
+            if Arc::as_ptr(&self.shared) < Arc::as_ptr(&other.shared) {
+                let shards = self.shared.lock_all();
+                (shards, other.shared.lock_all())
+            } else {
+                let other_shards = other.shared.lock_all();
+                (self.shared.lock_all(), other_shards)
+            };
+
+        let entries = shards.take_entries();
+        let other_entries = other_shards.take_entries();
+
+        // The memory used by the keys, and the order in which they were
+        // accessed, go along with them. The limits stay with the databases.
+        let memory = shards.iter().next().and_then(|state| state.memory.clone());
+        let other_memory = other_shards
+            .iter()
+            .next()
+            .and_then(|state| state.memory.clone());
+        if let (Some(memory), Some(other_memory)) = (memory, other_memory) {
+            memory
+                .lock()
+                .unwrap()
+                .swap_usage(&mut other_memory.lock().unwrap());
+        }
+
+        shards.insert_entries(other_entries);
+        other_shards.insert_entries(entries);
+
+        // Release the mutexes before notifying the background tasks, see
+        // `Db::set_if`.
+        drop(shards);
+        drop(other_shards);
+        self.shared.background_task.notify_one();
+        other.shared.background_task.notify_one();
+    }
+
+    /// Remove the expiration from `key`, making it persistent.
+    ///
+    /// Returns `true` if the key existed and had an expiration.
+    pub(crate) fn persist(&self, key: &[u8]) -> bool {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        match state.entries.get(key) {
+            Some(entry) if entry.expires_at.is_some() => {
+                state.set_expiration(key, None);
+                state.notify(EventClass::Generic, "persist", key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Push `values` onto `end` of the list stored at `key`, one at a time.
+    ///
+    /// If the key does not exist, the list is created if `create` is set,
+    /// otherwise nothing is pushed and `0` is returned. Returns the length of
+    /// the list after the push.
+    pub(crate) fn push(
+        &self,
+        key: &[u8],
+        values: Vec<Bytes>,
+        end: ListEnd,
+        create: bool,
+    ) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::List(_)) => {}
+            None if create => {}
+            None => return Ok(0),
+            Some(_) => return Err(DbError::WrongType),
+        }
+
+        let added = values.iter().map(|value| value.len()).sum::<usize>();
+        state.reserve(key.len() + state.size_of(key) + added)?;
+
+        let entry = state.entries.get_or_insert(
+            key,
+            Entry {
+                value: Value::List(VecDeque::new()),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let list = match &mut entry.value {
+            Value::List(list) => list,
+            _ => return Err(DbError::WrongType),
+        };
+
+        for value in values {
+            match end {
+                ListEnd::Left => list.push_front(value),
+                ListEnd::Right => list.push_back(value),
+            }
+        }
+
+        let len = list.len();
+        state.written(key);
+        state.wake_waiters(key);
+
+        let event = match end {
+            ListEnd::Left => "lpush",
+            ListEnd::Right => "rpush",
+        };
+        state.notify(EventClass::List, event, key);
+
+        Ok(len)
+    }
+
+    /// Pop a value from `end` of the list stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist. The key is removed once the
+    /// last element has been popped, as Redis never stores empty lists.
+    pub(crate) fn pop(&self, key: &[u8], end: ListEnd) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.pop(key, end)
+    }
+
+    /// Pop a value from `end` of the first non-empty list among `keys`, or
+    /// register `waiter` to be notified once a value is pushed to any of them.
+    ///
+    /// Checking the lists and registering happen while holding the lock of
+    /// their shards, so a value pushed right after this returns `None` still
+    /// notifies `waiter`.
+    /// Waiters are unregistered from a key as they are notified, they must
+    /// call this again to keep waiting. Returns the key the value was popped
+    /// from along with the value.
+    pub(crate) fn pop_or_watch(
+        &self,
+        keys: &[Bytes],
+        end: ListEnd,
+        waiter: &Arc<Notify>,
+    ) -> Result<Option<(Bytes, Bytes)>, DbError> {
+        let mut shards = self.shared.lock_keys(keys.iter().map(|key| &key[..]));
+
+        for key in keys {
+            if let Some(value) = shards.state(key).pop(key, end)? {
+                return Ok(Some((key.clone(), value)));
+            }
+        }
+
+        for key in keys {
+            let waiters = shards.state(key).waiters.entry(key.clone()).or_default();
+            if !waiters.iter().any(|other| Arc::ptr_eq(other, waiter)) {
+                waiters.push(waiter.clone());
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Pop up to `count` values from `end` of the first non-empty list among
+    /// `keys`.
+    ///
+    /// Every key is locked while the lists are checked, so the values come
+    /// from the first list that is non-empty at a single point in time.
+    /// Returns the key the values were popped from along with the values, or
+    /// `None` if every list is empty.
+    pub(crate) fn mpop(
+        &self,
+        keys: &[Bytes],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<(Bytes, Vec<Bytes>)>, DbError> {
+        let mut shards = self.shared.lock_keys(keys.iter().map(|key| &key[..]));
+
+        for key in keys {
+            let values = shards.state(key).pop_many(key, end, count)?;
+            if !values.is_empty() {
+                return Ok(Some((key.clone(), values)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Unregister `waiter` from `keys`, once it no longer waits on them.
+    pub(crate) fn unwatch(&self, keys: &[Bytes], waiter: &Arc<Notify>) {
+        for key in keys {
+            let mut state = self.shared.lock(key);
+            if let Some(waiters) = state.waiters.get_mut(key) {
+                waiters.retain(|other| !Arc::ptr_eq(other, waiter));
+                if waiters.is_empty() {
+                    state.waiters.remove(key);
+                }
+            }
+        }
+    }
+
+    /// Returns the elements of the list stored at `key` between `start` and
+    /// `stop`, inclusive.
+    ///
+    /// Negative indices count from the end of the list, `-1` being the last
+    /// element. Out of range indices are clamped, so an empty `Vec` is returned
+    /// when the range does not overlap the list.
+    pub(crate) fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let list = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::List(list)) => list,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        let len = list.len() as i64;
+        let start = if start < 0 { start + len } else { start }.max(0);
+        let stop = if stop < 0 { stop + len } else { stop }.min(len - 1);
+
+        if start > stop {
+            return Ok(vec![]);
+        }
+
+        Ok(list
+            .range(start as usize..=stop as usize)
+            .cloned()
+            .collect())
+    }
+
+    /// Returns the indices of the elements of the list stored at `key` equal
+    /// to `element`, in the order they are found.
+    ///
+    /// The search starts from the head of the list if `rank` is positive, from
+    /// the tail if it is negative, and skips the first `|rank| - 1` matches.
+    /// At most `count` indices are returned, all of them if `count` is `0`.
+    /// `rank` must not be `0`.
+    pub(crate) fn lpos(
+        &self,
+        key: &[u8],
+        element: &[u8],
+        rank: i64,
+        count: usize,
+    ) -> Result<Vec<usize>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let list = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::List(list)) => list,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        let count = if count == 0 { usize::MAX } else { count };
+        let skip = usize::try_from(rank.unsigned_abs() - 1).unwrap_or(usize::MAX);
+
+        let matches = list
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| &value[..] == element)
+            .map(|(index, _)| index);
+
+        let indices = if rank > 0 {
+            matches.skip(skip).take(count).collect()
+        } else {
+            matches.rev().skip(skip).take(count).collect()
+        };
+
+        Ok(indices)
+    }
+
+    /// Returns the length of the list stored at `key`, `0` if the key does
+    /// not exist.
+    pub(crate) fn llen(&self, key: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::List(list)) => Ok(list.len()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(0),
+        }
+    }
+
+    /// Replace the element at `index` of the list stored at `key` with
+    /// `value`.
+    ///
+    /// Negative indices count from the end of the list, `-1` being the last
+    /// element. Unlike `lrange`, out of range indices are an error.
+    pub(crate) fn lset(&self, key: &[u8], index: i64, value: Bytes) -> Result<(), DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let len = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::List(list)) => list.len() as i64,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Err(DbError::NoSuchKey),
+        };
+
+        let index = if index < 0 { index + len } else { index };
+        if index < 0 || index >= len {
+            return Err(DbError::IndexOutOfRange);
+        }
+
+        state.reserve(key.len() + state.size_of(key) + value.len())?;
+
+        match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::List(list)) => list[index as usize] = value,
+            _ => return Err(DbError::WrongType),
+        }
+
+        state.written(key);
+        state.notify(EventClass::List, "lset", key);
+
+        Ok(())
+    }
+
+    /// Insert `value` at `position` relative to the first element of the list
+    /// stored at `key` equal to `pivot`.
+    ///
+    /// Returns the length of the list after the insertion, `Some(0)` if the
+    /// key does not exist, or `None` if `pivot` is not found. Nothing is
+    /// inserted in either case.
+    pub(crate) fn linsert(
+        &self,
+        key: &[u8],
+        position: InsertPosition,
+        pivot: &[u8],
+        value: Bytes,
+    ) -> Result<Option<usize>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let found = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::List(list)) => list.iter().position(|element| &element[..] == pivot),
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(Some(0)),
+        };
+
+        let index = match (found, position) {
+            (Some(index), InsertPosition::Before) => index,
+            (Some(index), InsertPosition::After) => index + 1,
+            (None, _) => return Ok(None),
+        };
+
+        state.reserve(key.len() + state.size_of(key) + value.len())?;
+
+        let len = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::List(list)) => {
+                list.insert(index, value);
+                list.len()
+            }
+            _ => return Err(DbError::WrongType),
+        };
+
+        state.written(key);
+        state.notify(EventClass::List, "linsert", key);
+
+        Ok(Some(len))
+    }
+
+    /// Set `fields` in the hash stored at `key`.
+    ///
+    /// The hash is created if the key does not exist and existing fields are
+    /// overwritten. Returns the number of fields that were added.
+    pub(crate) fn hset(&self, key: &[u8], fields: Vec<(Bytes, Bytes)>) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::Hash(_)) | None => {}
+            Some(_) => return Err(DbError::WrongType),
+        }
+
+        // Overwritten fields are counted twice, so this may overestimate.
+        let added = fields
+            .iter()
+            .map(|(field, value)| field.len() + value.len())
+            .sum::<usize>();
+        state.reserve(key.len() + state.size_of(key) + added)?;
+
+        let entry = state.entries.get_or_insert(
+            key,
+            Entry {
+                value: Value::Hash(HashMap::new()),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let hash = match &mut entry.value {
+            Value::Hash(hash) => hash,
+            _ => return Err(DbError::WrongType),
+        };
+
+        let mut added = 0;
+        for (field, value) in fields {
+            if hash.insert(field, value).is_none() {
+                added += 1;
+            }
+        }
+
+        state.written(key);
+        state.notify(EventClass::Hash, "hset", key);
+
+        Ok(added)
+    }
+
+    /// Add `delta` to the integer stored in `field` of the hash stored at
+    /// `key` and return the result.
+    ///
+    /// The hash and the field are created if they do not exist, a missing
+    /// field being treated as holding `0`. Everything happens while holding
+    /// the lock, so concurrent increments are never lost.
+    pub(crate) fn hincr_by(&self, key: &[u8], field: &[u8], delta: i64) -> Result<i64, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let current = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => match hash.get(field) {
+                Some(data) => str::from_utf8(data)
+                    .ok()
+                    .and_then(|s| s.parse::<i64>().ok())
+                    .ok_or(DbError::HashNotInteger)?,
+                None => 0,
+            },
+            Some(_) => return Err(DbError::WrongType),
+            None => 0,
+        };
+
+        let value = current.checked_add(delta).ok_or(DbError::Overflow)?;
+        let data = Bytes::from(value.to_string());
+
+        state.hash_insert(key, field, data)?;
+        state.notify(EventClass::Hash, "hincrby", key);
+
+        Ok(value)
+    }
+
+    /// Add `delta` to the floating point number stored in `field` of the hash
+    /// stored at `key` and return the result, formatted as it is stored.
+    ///
+    /// Behaves like `hincr_by` otherwise, the result being formatted as by
+    /// `incr_by_float`.
+    pub(crate) fn hincr_by_float(
+        &self,
+        key: &[u8],
+        field: &[u8],
+        delta: f64,
+    ) -> Result<Bytes, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let current = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => match hash.get(field) {
+                Some(data) => str::from_utf8(data)
+                    .ok()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .ok_or(DbError::HashNotFloat)?,
+                None => 0.0,
+            },
+            Some(_) => return Err(DbError::WrongType),
+            None => 0.0,
+        };
+
+        let value = current + delta;
+        if !value.is_finite() {
+            return Err(DbError::NotFinite);
+        }
+
+        let data = Bytes::from(value.to_string());
+
+        state.hash_insert(key, field, data.clone())?;
+        state.notify(EventClass::Hash, "hincrbyfloat", key);
+
+        Ok(data)
+    }
+
+    /// Returns the value of `field` in the hash stored at `key`.
+    ///
+    /// Returns `None` if either the key or the field does not exist.
+    pub(crate) fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash.get(field).cloned()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove `fields` from the hash stored at `key`.
+    ///
+    /// Returns the number of fields that were removed. The key is removed once
+    /// the hash holds no more fields.
+    pub(crate) fn hdel(&self, key: &[u8], fields: &[Bytes]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let hash = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::Hash(hash)) => hash,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(0),
+        };
+
+        let removed = fields
+            .iter()
+            .filter(|field| hash.remove(*field).is_some())
+            .count();
+
+        let emptied = hash.is_empty();
+
+        if emptied {
+            state.remove_entry(key);
+        } else {
+            state.written(key);
+        }
+
+        if removed > 0 {
+            state.notify(EventClass::Hash, "hdel", key);
+        }
+
+        if emptied {
+            state.notify(EventClass::Generic, "del", key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Returns all fields and values of the hash stored at `key`.
+    ///
+    /// Returns an empty `Vec` if the key does not exist.
+    pub(crate) fn hgetall(&self, key: &[u8]) -> Result<Vec<(Bytes, Bytes)>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns `count` fields of the hash stored at `key` picked at random,
+    /// along with their values.
+    ///
+    /// `count` is interpreted as by `srandmember`.
+    pub(crate) fn hrandfield(
+        &self,
+        key: &[u8],
+        count: i64,
+    ) -> Result<Vec<(Bytes, Bytes)>, DbError> {
+        check_sample_count(count)?;
+
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let fields = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => sample_distinct(hash.iter(), count)
+                .into_iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        drop(state);
+
+        sample_with_replacement(fields, count)
+    }
+
+    /// Returns `true` if `field` is in the hash stored at `key`.
+    pub(crate) fn hexists(&self, key: &[u8], field: &[u8]) -> Result<bool, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash.contains_key(field)),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the fields of the hash stored at `key`.
+    ///
+    /// Returns an empty `Vec` if the key does not exist.
+    pub(crate) fn hkeys(&self, key: &[u8]) -> Result<Vec<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash.keys().cloned().collect()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the values of the hash stored at `key`.
+    ///
+    /// Returns an empty `Vec` if the key does not exist.
+    pub(crate) fn hvals(&self, key: &[u8]) -> Result<Vec<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash.values().cloned().collect()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the number of fields of the hash stored at `key`, `0` if the
+    /// key does not exist.
+    pub(crate) fn hlen(&self, key: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash.len()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(0),
+        }
+    }
+
+    /// Add `members` to the set stored at `key`.
+    ///
+    /// The set is created if the key does not exist. Returns the number of
+    /// members that were added, members already in the set are not counted.
+    pub(crate) fn sadd(&self, key: &[u8], members: Vec<Bytes>) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::Set(_)) | None => {}
+            Some(_) => return Err(DbError::WrongType),
+        }
+
+        // Members already in the set are counted, so this may overestimate.
+        let added = members.iter().map(|member| member.len()).sum::<usize>();
+        state.reserve(key.len() + state.size_of(key) + added)?;
+
+        let entry = state.entries.get_or_insert(
+            key,
+            Entry {
+                value: Value::Set(HashSet::new()),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let set = match &mut entry.value {
+            Value::Set(set) => set,
+            _ => return Err(DbError::WrongType),
+        };
+
+        let mut added = 0;
+        for member in members {
+            if set.insert(member) {
+                added += 1;
+            }
+        }
+
+        state.written(key);
+        state.notify(EventClass::Set, "sadd", key);
+
+        Ok(added)
+    }
+
+    /// Remove `members` from the set stored at `key`.
+    ///
+    /// Returns the number of members that were removed. The key is removed
+    /// once the set holds no more members.
+    pub(crate) fn srem(&self, key: &[u8], members: &[Bytes]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        let set = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::Set(set)) => set,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(0),
+        };
+
+        let removed = members.iter().filter(|member| set.remove(*member)).count();
+
+        let emptied = set.is_empty();
+
+        if emptied {
+            state.remove_entry(key);
+        } else {
+            state.written(key);
+        }
+
+        if removed > 0 {
+            state.notify(EventClass::Set, "srem", key);
+        }
+
+        if emptied {
+            state.notify(EventClass::Generic, "del", key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Returns all members of the set stored at `key`.
+    ///
+    /// Returns an empty `Vec` if the key does not exist.
+    pub(crate) fn smembers(&self, key: &[u8]) -> Result<Vec<Bytes>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => Ok(set.iter().cloned().collect()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns `count` members of the set stored at `key` picked at random.
+    ///
+    /// A positive `count` picks distinct members, all of them if the set has
+    /// fewer. A negative `count` picks exactly `-count` members, the same
+    /// member possibly being picked several times. Nothing is returned if the
+    /// key does not exist.
+    ///
+    /// As in Redis, a count beyond half the range of `i64` is rejected. The
+    /// members picked several times are only picked once the key is unlocked,
+    /// so a large negative count does not hold up other connections.
+    pub(crate) fn srandmember(&self, key: &[u8], count: i64) -> Result<Vec<Bytes>, DbError> {
+        check_sample_count(count)?;
+
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let members = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => sample_distinct(set.iter(), count)
+                .into_iter()
+                .cloned()
+                .collect(),
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        drop(state);
+
+        sample_with_replacement(members, count)
+    }
+
+    /// Returns `true` if `member` is a member of the set stored at `key`.
+    pub(crate) fn sismember(&self, key: &[u8], member: &[u8]) -> Result<bool, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => Ok(set.contains(member)),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the number of members of the set stored at `key`.
+    pub(crate) fn scard(&self, key: &[u8]) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => Ok(set.len()),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(0),
+        }
+    }
+
+    /// Move `member` from the set stored at `src` to the set stored at `dst`.
+    ///
+    /// The `dst` set is created if the key does not exist, and `src` is
+    /// removed once it holds no more members. Both keys are locked for the
+    /// whole operation, so other connections observe the member in exactly
+    /// one of the sets. Returns `true` if the member was in `src`.
+    pub(crate) fn smove(&self, src: &[u8], dst: &[u8], member: Bytes) -> Result<bool, DbError> {
+        let mut shards = self.shared.lock_keys([src, dst]);
+
+        for key in [src, dst] {
+            let state = shards.state(key);
+            state.remove_if_expired(key);
+
+            match state.entries.get(key).map(|entry| &entry.value) {
+                Some(Value::Set(_)) | None => {}
+                Some(_) => return Err(DbError::WrongType),
+            }
+        }
+
+        let state = shards.state(src);
+        let found = matches!(
+            state.entries.get(src).map(|entry| &entry.value),
+            Some(Value::Set(set)) if set.contains(&member)
+        );
+
+        if !found {
+            return Ok(false);
+        }
+
+        // Nothing moves, but the member is in the set.
+        if src == dst {
+            return Ok(true);
+        }
+
+        // The destination is checked before the member is removed, so the
+        // member is never lost.
+        let state = shards.state(dst);
+        state.reserve(dst.len() + state.size_of(dst) + member.len())?;
+
+        let state = shards.state(src);
+        let mut emptied = false;
+        if let Some(Value::Set(set)) = state.entries.get_mut(src).map(|entry| &mut entry.value) {
+            set.remove(&member);
+            emptied = set.is_empty();
+        }
+
+        if emptied {
+            state.remove_entry(src);
+        } else {
+            state.written(src);
+        }
+
+        state.notify(EventClass::Set, "srem", src);
+        if emptied {
+            state.notify(EventClass::Generic, "del", src);
+        }
+
+        let state = shards.state(dst);
+        let entry = state.entries.get_or_insert(
+            dst,
+            Entry {
+                value: Value::Set(HashSet::new()),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        if let Value::Set(set) = &mut entry.value {
+            set.insert(member);
+        }
+
+        state.written(dst);
+        state.notify(EventClass::Set, "sadd", dst);
+
+        Ok(true)
+    }
+
+    /// Combine the sets stored at `keys` with `op` and store the result at
+    /// `dst`.
+    ///
+    /// Missing keys are treated as empty sets. Any value already stored at
+    /// `dst` is replaced, and `dst` is removed if the result is empty. Every
+    /// key is locked for the whole operation, so other connections never
+    /// observe a partial result. Returns the number of members of the result.
+    pub(crate) fn set_store(
+        &self,
+        op: SetOp,
+        dst: &[u8],
+        keys: &[Bytes],
+    ) -> Result<usize, DbError> {
+        let mut shards = self
+            .shared
+            .lock_keys(keys.iter().map(|key| &key[..]).chain([dst]));
+
+        let mut result: Option<HashSet<Bytes>> = None;
+
+        for key in keys {
+            let state = shards.state(key);
+            state.remove_if_expired(key);
+            state.record_access(key);
+
+            let set = match state.entries.get(&key[..]).map(|entry| &entry.value) {
+                Some(Value::Set(set)) => Some(set),
+                Some(_) => return Err(DbError::WrongType),
+                None => None,
+            };
+
+            result = Some(match (result, set) {
+                (None, set) => set.cloned().unwrap_or_default(),
+                (Some(mut members), Some(set)) => {
+                    match op {
+                        SetOp::Inter => members.retain(|member| set.contains(member)),
+                        SetOp::Union => members.extend(set.iter().cloned()),
+                        SetOp::Diff => members.retain(|member| !set.contains(member)),
+                    }
+                    members
+                }
+                (Some(_), None) if matches!(op, SetOp::Inter) => HashSet::new(),
+                (Some(members), None) => members,
+            });
+        }
+
+        let members = result.unwrap_or_default();
+        let len = members.len();
+
+        let state = shards.state(dst);
+        state.remove_if_expired(dst);
+
+        if members.is_empty() {
+            if state.remove_entry(dst) {
+                state.notify(EventClass::Generic, "del", dst);
+            }
+            return Ok(0);
+        }
+
+        let size = dst.len() + members.iter().map(|member| member.len()).sum::<usize>();
+        state.reserve(size)?;
+        state.remove_entry(dst);
+        state.entries.insert(
+            Bytes::copy_from_slice(dst),
+            Entry {
+                value: Value::Set(members),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let event = match op {
+            SetOp::Inter => "sinterstore",
+            SetOp::Union => "sunionstore",
+            SetOp::Diff => "sdiffstore",
+        };
+
+        state.written(dst);
+        state.notify(EventClass::Set, event, dst);
+
+        Ok(len)
+    }
+
+    /// Returns the number of members of the intersection of the sets stored at
+    /// `keys`, counting no further than `limit`.
+    ///
+    /// The intersection is not built: the members of the smallest set are
+    /// looked up in the others, until `limit` members are found in all of
+    /// them. A missing key makes the intersection empty. Every key is locked
+    /// for the whole operation.
+    pub(crate) fn sintercard(
+        &self,
+        keys: &[Bytes],
+        limit: Option<usize>,
+    ) -> Result<usize, DbError> {
+        let mut shards = self.shared.lock_keys(keys.iter().map(|key| &key[..]));
+
+        // Every key is checked, even once one is known to be missing.
+        let mut missing = false;
+        for key in keys {
+            let state = shards.state(key);
+            state.remove_if_expired(key);
+            state.record_access(key);
+
+            match state.entries.get(&key[..]).map(|entry| &entry.value) {
+                Some(Value::Set(_)) => {}
+                Some(_) => return Err(DbError::WrongType),
+                None => missing = true,
+            }
+        }
+
+        if missing {
+            return Ok(0);
+        }
+
+        let mut sets: Vec<&HashSet<Bytes>> = keys
+            .iter()
+            .filter_map(|key| match shards.state_ref(key).entries.get(&key[..]) {
+                Some(Entry {
+                    value: Value::Set(set),
+                    ..
+                }) => Some(set),
+                _ => None,
+            })
+            .collect();
+        sets.sort_by_key(|set| set.len());
+
+        let (smallest, others) = match sets.split_first() {
+            Some(split) => split,
+            None => return Ok(0),
+        };
+
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut count = 0;
+        for member in smallest.iter() {
+            if count == limit {
+                break;
+            }
+            if others.iter().all(|set| set.contains(member)) {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Add `members` with their scores to the sorted set stored at `key`.
+    ///
+    /// The sorted set is created if the key does not exist, and the score of
+    /// members already in the set is updated. Returns the number of members
+    /// that were added. Scores must not be NaN.
+    pub(crate) fn zadd(&self, key: &[u8], members: Vec<(f64, Bytes)>) -> Result<usize, DbError> {
+        let mut state = self.shared.lock(key);
+        state.remove_if_expired(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::SortedSet(_)) | None => {}
+            Some(_) => return Err(DbError::WrongType),
+        }
+
+        // Members already in the set are counted, so this may overestimate.
+        let added = members
+            .iter()
+            .map(|(_, member)| member.len() + std::mem::size_of::<f64>())
+            .sum::<usize>();
+        state.reserve(key.len() + state.size_of(key) + added)?;
+
+        let entry = state.entries.get_or_insert(
+            key,
+            Entry {
+                value: Value::SortedSet(SortedSet::new()),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        let zset = match &mut entry.value {
+            Value::SortedSet(zset) => zset,
+            _ => return Err(DbError::WrongType),
+        };
+
+        let mut added = 0;
+        for (score, member) in members {
+            if zset.insert(member, score) {
+                added += 1;
+            }
+        }
+
+        state.written(key);
+        state.notify(EventClass::SortedSet, "zadd", key);
+
+        Ok(added)
+    }
+
+    /// Returns the score of `member` in the sorted set stored at `key`.
+    ///
+    /// Returns `None` if either the key or the member does not exist.
+    pub(crate) fn zscore(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::SortedSet(zset)) => Ok(zset.score(member)),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the rank of `member` in the sorted set stored at `key`, `0`
+    /// being the member with the lowest score.
+    ///
+    /// Returns `None` if either the key or the member does not exist.
+    pub(crate) fn zrank(&self, key: &[u8], member: &[u8]) -> Result<Option<usize>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::SortedSet(zset)) => Ok(zset.rank(member)),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the members of the sorted set stored at `key` with a rank
+    /// between `start` and `stop`, inclusive, along with their scores.
+    ///
+    /// Indices are interpreted as by `lrange`: negative indices count from the
+    /// member with the highest score, and out of range indices are clamped.
+    pub(crate) fn zrange(
+        &self,
+        key: &[u8],
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(Bytes, f64)>, DbError> {
+        let mut state = self.shared.lock(key);
+        state.record_access(key);
+
+        let zset = match state.live_entry(key).map(|entry| &entry.value) {
+            Some(Value::SortedSet(zset)) => zset,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        let len = zset.len() as i64;
+        let start = if start < 0 { start + len } else { start }.max(0);
+        let stop = if stop < 0 { stop + len } else { stop }.min(len - 1);
+
+        if start > stop {
+            return Ok(vec![]);
+        }
+
+        Ok(zset
+            .iter()
+            .skip(start as usize)
+            .take((stop - start + 1) as usize)
+            .map(|(member, score)| (member.clone(), score))
+            .collect())
+    }
+
+    /// Returns a `Receiver` for the requested channel.
+    ///
+    /// The returned `Receiver` is used to receive values broadcast by `PUBLISH`
+    /// commands.
+    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
+        use std::collections::hash_map::Entry;
+
+        // Acquire the mutex
+        let mut pub_sub = self.global.pub_sub.lock().unwrap();
+
+        // If there is no entry for the requested channel, then create a new
+        // broadcast channel and associate it with the key. If one already
+        // exists, return an associated receiver.
+        match pub_sub.entry(key) {
+            // A channel whose subscribers all left is created again, so that a
+            // capacity configured meanwhile applies. Subscribers never lose
+            // their receiver to a change of capacity.
+            Entry::Occupied(e) if e.get().receiver_count() > 0 => e.get().subscribe(),
+            Entry::Occupied(mut e) => {
+                let (tx, rx) = broadcast::channel(self.global.capacity_of(e.key()));
+                e.insert(tx);
+                rx
+            }
+            Entry::Vacant(e) => {
+                // No broadcast channel exists yet, so create one.
+                //
+                // The channel is created with the configured capacity. A
+                // message is stored in the channel until **all** subscribers
+                // have seen it. This means that a slow subscriber could result
+                // in messages being held indefinitely.
+                //
+                // When the channel's capacity fills up, publishing will result
                 // in old messages being dropped. This prevents slow consumers
-                // from blocking the entire system.
-                let (tx, rx) = broadcast::channel(1024);
+                // from blocking the entire system. Subscribers are told how
+                // many messages they missed.
+                let (tx, rx) = broadcast::channel(self.global.capacity_of(e.key()));
                 e.insert(tx);
                 rx
             }
         }
     }
 
-    /// Publish a message to the channel. Returns the number of subscribers
-    /// listening on the channel.
-    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
-        let state = self.shared.state.lock().unwrap();
+    /// Returns a `Receiver` for the requested channel pattern.
+    ///
+    /// The returned `Receiver` receives the values broadcast by `PUBLISH`
+    /// commands to any channel matching the glob-style `pattern`, along with
+    /// the name of the channel.
+    pub(crate) fn psubscribe(&self, pattern: String) -> broadcast::Receiver<(String, Bytes)> {
+        let mut patterns = self.global.patterns.lock().unwrap();
+
+        match patterns.get(&pattern) {
+            Some(tx) if tx.receiver_count() > 0 => tx.subscribe(),
+            _ => {
+                // See `subscribe` for the choice of capacity. Patterns always
+                // use the default capacity.
+                let capacity = self.global.channel_capacity.load(Ordering::Relaxed);
+                let (tx, rx) = broadcast::channel(capacity);
+                patterns.insert(pattern, tx);
+                rx
+            }
+        }
+    }
+
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel, counting pattern subscribers.
+    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+        self.global.publish(key, value)
+    }
+
+    /// Returns a receiver of the commands processed by the server, as fed by
+    /// `feed_monitors`, for a connection entering `MONITOR` mode.
+    pub(crate) fn monitor(&self) -> broadcast::Receiver<String> {
+        self.global.monitors.subscribe()
+    }
+
+    /// Returns `true` if at least one connection is in `MONITOR` mode, so
+    /// that commands are only formatted when someone watches.
+    pub(crate) fn is_monitored(&self) -> bool {
+        self.global.monitors.receiver_count() > 0
+    }
+
+    /// Send a processed command to the connections in `MONITOR` mode.
+    pub(crate) fn feed_monitors(&self, line: String) {
+        // There is no one to send to once the last monitor leaves.
+        let _ = self.global.monitors.send(line);
+    }
+
+    /// Returns `true` if clients may send commands named `name`.
+    pub(crate) fn is_allowed(&self, name: &str) -> bool {
+        match &*self.global.allowed_commands.lock().unwrap() {
+            Some(names) => names.contains(name),
+            None => true,
+        }
+    }
+
+    /// Returns the names of the commands clients may send, sorted, or `None`
+    /// if every command is accepted.
+    pub(crate) fn allowed_commands(&self) -> Option<Vec<String>> {
+        let names = self.global.allowed_commands.lock().unwrap();
+        names.as_ref().map(|names| {
+            let mut names: Vec<_> = names.iter().cloned().collect();
+            names.sort();
+            names
+        })
+    }
+
+    /// Restrict the commands clients may send to those named in `names`, or
+    /// accept every command if `None`.
+    pub(crate) fn set_allowed_commands(&self, names: Option<HashSet<String>>) {
+        *self.global.allowed_commands.lock().unwrap() = names;
+    }
+
+    /// Returns the number of messages pub/sub channels buffer, unless
+    /// configured otherwise for the channel.
+    pub(crate) fn channel_capacity(&self) -> usize {
+        self.global.channel_capacity.load(Ordering::Relaxed)
+    }
+
+    /// Change the number of messages pub/sub channels buffer.
+    ///
+    /// Only channels created from now on are affected, existing subscribers
+    /// keep their receiver.
+    pub(crate) fn set_channel_capacity(&self, capacity: usize) {
+        let capacity = capacity.clamp(1, MAX_CHANNEL_CAPACITY);
+        self.global
+            .channel_capacity
+            .store(capacity, Ordering::Relaxed);
+    }
+
+    /// Returns the largest bulk string accepted from clients, in bytes.
+    pub(crate) fn max_frame_size(&self) -> usize {
+        self.global.max_frame_size.load(Ordering::Relaxed)
+    }
+
+    /// Change the largest bulk string accepted from clients.
+    ///
+    /// Frames already being received are checked against the previous limit.
+    pub(crate) fn set_max_frame_size(&self, max_frame_size: usize) {
+        self.global
+            .max_frame_size
+            .store(max_frame_size, Ordering::Relaxed);
+    }
+
+    /// Returns the number of messages `channel` buffers once created.
+    pub(crate) fn capacity_of(&self, channel: &str) -> usize {
+        self.global.capacity_of(channel)
+    }
+
+    /// Change the number of messages `channel` buffers, overriding the default
+    /// capacity.
+    ///
+    /// As with `set_channel_capacity`, the capacity applies once the channel
+    /// is created again, after its current subscribers all left.
+    pub(crate) fn set_capacity_of(&self, channel: String, capacity: usize) {
+        let capacity = capacity.clamp(1, MAX_CHANNEL_CAPACITY);
+        let mut capacities = self.global.channel_capacities.lock().unwrap();
+        capacities.insert(channel, capacity);
+    }
+
+    /// Returns the channels with at least one subscriber, sorted, keeping only
+    /// those matching the glob-style `pattern` if one is given.
+    ///
+    /// The broadcast channel of a channel whose subscribers all left is kept
+    /// around, it is not reported. Pattern subscriptions are not counted.
+    pub(crate) fn channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let pub_sub = self.global.pub_sub.lock().unwrap();
+
+        let mut channels: Vec<_> = pub_sub
+            .iter()
+            .filter(|(_, tx)| tx.receiver_count() > 0)
+            .filter(|(channel, _)| match pattern {
+                Some(pattern) => glob::matches(pattern.as_bytes(), channel.as_bytes()),
+                None => true,
+            })
+            .map(|(channel, _)| channel.clone())
+            .collect();
+
+        channels.sort();
+        channels
+    }
+
+    /// Returns the number of subscribers to `channel`, not counting pattern
+    /// subscribers.
+    pub(crate) fn subscribe_count(&self, channel: &str) -> usize {
+        let pub_sub = self.global.pub_sub.lock().unwrap();
+
+        pub_sub
+            .get(channel)
+            .map(|tx| tx.receiver_count())
+            .unwrap_or(0)
+    }
+
+    /// Pause or resume the purge of expired keys by the background task.
+    pub(crate) fn set_active_expire(&self, enabled: bool) {
+        self.shared.active_expire.store(enabled, Ordering::SeqCst);
+
+        // Once resumed, the task must purge the keys that expired meanwhile.
+        self.shared.background_task.notify_one();
+    }
+
+    /// Purge every expired key right away, whether or not the background task
+    /// is paused.
+    pub(crate) fn purge_expired(&self) {
+        while self.shared.purge_expired_keys() == Purge::Again {}
+    }
+
+    /// Signals the purge background task to shut down. This is called by the
+    /// `DbShutdown`s `Drop` implementation.
+    fn shutdown_purge_task(&self) {
+        // The background task must be signaled to shut down. This is done by
+        // setting `Shared::shutdown` to `true` and signalling the task.
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.background_task.notify_one();
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbError::NotInteger => "ERR value is not an integer or out of range".fmt(fmt),
+            DbError::Overflow => "ERR increment or decrement would overflow".fmt(fmt),
+            DbError::NotFloat => "ERR value is not a valid float".fmt(fmt),
+            DbError::NotFinite => "ERR increment would produce NaN or Infinity".fmt(fmt),
+            DbError::HashNotInteger => "ERR hash value is not an integer".fmt(fmt),
+            DbError::HashNotFloat => "ERR hash value is not a float".fmt(fmt),
+            DbError::WrongType => {
+                "WRONGTYPE Operation against a key holding the wrong kind of value".fmt(fmt)
+            }
+            DbError::OutOfMemory => {
+                "OOM command not allowed when used memory > 'maxmemory'".fmt(fmt)
+            }
+            DbError::NoSuchKey => "ERR no such key".fmt(fmt),
+            DbError::IndexOutOfRange => "ERR index out of range".fmt(fmt),
+            DbError::CountOutOfRange => "ERR value is out of range".fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl Shared {
+    /// Returns the index of the shard holding `key`.
+    fn shard(&self, key: &[u8]) -> usize {
+        if self.shards.len() == 1 {
+            return 0;
+        }
+
+        (self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+    }
+
+    /// Lock the shard holding `key`.
+    fn lock(&self, key: &[u8]) -> ShardGuard<'_> {
+        ShardGuard {
+            shared: self,
+            state: Some(self.shards[self.shard(key)].lock().unwrap()),
+        }
+    }
+
+    /// Lock the shards holding `keys`, each of them once.
+    ///
+    /// Shards are always locked by increasing index, so that commands on
+    /// several keys cannot deadlock each other.
+    fn lock_keys<'k>(&self, keys: impl IntoIterator<Item = &'k [u8]>) -> Shards<'_> {
+        let mut indices: Vec<usize> = keys.into_iter().map(|key| self.shard(key)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let guards = indices
+            .iter()
+            .map(|&index| self.shards[index].lock().unwrap())
+            .collect();
+
+        Shards {
+            shared: self,
+            indices,
+            guards,
+        }
+    }
+
+    /// Lock every shard, by increasing index.
+    fn lock_all(&self) -> Shards<'_> {
+        let guards = self
+            .shards
+            .iter()
+            .map(|shard| shard.lock().unwrap())
+            .collect();
+
+        Shards {
+            shared: self,
+            indices: (0..self.shards.len()).collect(),
+            guards,
+        }
+    }
+
+    /// Evict the least recently, or least frequently, used keys of every
+    /// shard, other than `keep`, until the memory limit is no longer exceeded.
+    /// Does nothing unless the policy allows evicting keys.
+    ///
+    /// The victim is picked, then the lock of its shard is taken to evict it,
+    /// so this must be called without holding the lock of any shard.
+    fn evict(&self, memory: &Arc<Mutex<Memory>>, keep: &[Bytes]) {
+        loop {
+            let policy = self.shards[0].lock().unwrap().policy;
+
+            let victim = {
+                let memory = memory.lock().unwrap();
+                if memory.used <= memory.limit {
+                    return;
+                }
+
+                match policy {
+                    MaxMemoryPolicy::AllKeysLru => {
+                        match memory.lru.iter().find(|(_, key)| !keep.contains(key)) {
+                            Some((_, victim)) => Some(victim.clone()),
+                            None => return,
+                        }
+                    }
+                    MaxMemoryPolicy::AllKeysLfu => None,
+                    MaxMemoryPolicy::NoEviction => return,
+                }
+            };
+
+            // Frequencies are kept with the entries, the shards are searched
+            // once the memory lock is released.
+            let victim = match victim.or_else(|| self.least_frequently_used(keep)) {
+                Some(victim) => victim,
+                None => return,
+            };
+
+            let mut state = self.shards[self.shard(&victim)].lock().unwrap();
+
+            // The limit may have been changed or removed in the meantime.
+            let current = match &state.memory {
+                Some(current) => Arc::ptr_eq(current, memory),
+                None => false,
+            };
+            if !current || state.policy != policy {
+                return;
+            }
+
+            // The victim may have been removed in the meantime, it is then no
+            // longer tracked, and the next one is picked.
+            if state.take_entry(&victim).is_some() {
+                debug!(key = %key_lossy(&victim), "evicting key");
+                state.notify(EventClass::Evicted, "evicted", &victim);
+            }
+        }
+    }
+
+    /// Returns the key with the lowest access frequency, other than `keep`,
+    /// breaking ties by picking the least recently used one.
+    ///
+    /// Every key is visited, taking the lock of one shard at a time, as the
+    /// frequencies decay as time passes and cannot be kept ordered.
+    fn least_frequently_used(&self, keep: &[Bytes]) -> Option<Bytes> {
+        let now = Instant::now();
+        let mut victim: Option<(u8, Instant, Bytes)> = None;
+
+        for shard in self.shards.iter() {
+            let state = shard.lock().unwrap();
+
+            for (key, entry) in state.entries.iter() {
+                if keep.contains(key) {
+                    continue;
+                }
+
+                let candidate = (entry.frequency(now), entry.accessed_at);
+                let better = match &victim {
+                    Some((frequency, accessed_at, _)) => candidate < (*frequency, *accessed_at),
+                    None => true,
+                };
+                if better {
+                    victim = Some((candidate.0, candidate.1, key.clone()));
+                }
+            }
+        }
+
+        victim.map(|(_, _, key)| key)
+    }
+
+    /// Purge up to `PURGE_BATCH_SIZE` expired keys from each shard and report
+    /// what the background task should do next.
+    ///
+    /// Purging is done in batches so that many keys expiring at once do not
+    /// hold a lock for long, the connections get a chance to run between
+    /// batches.
+    fn purge_expired_keys(&self) -> Purge {
+        if self.is_shutdown() {
+            // The database is shutting down. All handles to the shared state
+            // have dropped. The background task should exit.
+            return Purge::Idle;
+        }
+
+        // The task waits until the earliest of the next expirations of the
+        // shards.
+        let mut next = Purge::Idle;
+
+        for shard in self.shards.iter() {
+            next = match (next, shard.lock().unwrap().purge_expired_keys()) {
+                (Purge::Again, _) | (_, Purge::Again) => Purge::Again,
+                (Purge::Until(a), Purge::Until(b)) => Purge::Until(a.min(b)),
+                (Purge::Until(when), Purge::Idle) | (Purge::Idle, Purge::Until(when)) => {
+                    Purge::Until(when)
+                }
+                (Purge::Idle, Purge::Idle) => Purge::Idle,
+            };
+        }
+
+        next
+    }
+
+    /// Returns `true` if the database is shutting down
+    ///
+    /// The `shutdown` flag is set when all `Db` values have dropped, indicating
+    /// that the shared state can no longer be accessed.
+    fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+}
+
+impl Deref for ShardGuard<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        self.state.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for ShardGuard<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        self.state.as_mut().unwrap()
+    }
+}
+
+impl Drop for ShardGuard<'_> {
+    fn drop(&mut self) {
+        let overflow = self.state.as_mut().and_then(|state| state.take_overflow());
+
+        // Evicting locks other shards, this one must be released first.
+        self.state = None;
+
+        if let Some((memory, keep)) = overflow {
+            self.shared.evict(&memory, &keep);
+        }
+    }
+}
+
+impl<'a> Shards<'a> {
+    /// Returns the shard holding `key`, which must have been locked.
+    fn state(&mut self, key: &[u8]) -> &mut State {
+        let position = self.position(key);
+        &mut self.guards[position]
+    }
+
+    /// Returns the shard holding `key`, which must have been locked, for
+    /// reading. Unlike with `state`, several shards can be read at once.
+    fn state_ref(&self, key: &[u8]) -> &State {
+        &self.guards[self.position(key)]
+    }
+
+    /// Returns the position of the shard holding `key` in `guards`.
+    fn position(&self, key: &[u8]) -> usize {
+        let index = self.shared.shard(key);
+
+        self.indices
+            .binary_search(&index)
+            .expect("the shard of the key is not locked")
+    }
+
+    /// Returns the locked shards.
+    fn iter(&self) -> slice::Iter<'_, MutexGuard<'a, State>> {
+        self.guards.iter()
+    }
+
+    /// Returns the locked shards, mutably.
+    fn iter_mut(&mut self) -> slice::IterMut<'_, MutexGuard<'a, State>> {
+        self.guards.iter_mut()
+    }
+
+    /// Remove every entry of the locked shards, along with their expirations,
+    /// and return them. The memory accounting is left untouched.
+    fn take_entries(&mut self) -> Vec<(Bytes, Entry)> {
+        let mut entries = Vec::new();
+        for state in self.iter_mut() {
+            state.expirations.clear();
+            state.types.clear();
+            entries.extend(state.entries.drain());
+        }
+        entries
+    }
+
+    /// Insert `entries`, taken with `take_entries`, each in the shard it
+    /// belongs to, and wake up the connections blocked on their keys. The
+    /// entries are expected to be accounted for already.
+    fn insert_entries(&mut self, entries: Vec<(Bytes, Entry)>) {
+        for (key, entry) in entries {
+            let state = self.state(&key);
+            if let Some(when) = entry.expires_at {
+                state.expirations.insert((when, key.clone()));
+            }
+            if let Some(type_name) = entry.counted {
+                *state.types.entry(type_name).or_default() += 1;
+            }
+            state.entries.insert(key.clone(), entry);
+            state.wake_waiters(&key);
+        }
+    }
+}
+
+impl Drop for Shards<'_> {
+    fn drop(&mut self) {
+        let mut overflow: Option<(Arc<Mutex<Memory>>, Vec<Bytes>)> = None;
+
+        for state in self.iter_mut() {
+            if let Some((memory, keep)) = state.take_overflow() {
+                overflow
+                    .get_or_insert_with(|| (memory, Vec::new()))
+                    .1
+                    .extend(keep);
+            }
+        }
+
+        // Evicting locks shards one at a time, all of them must be released
+        // first.
+        self.guards.clear();
+
+        if let Some((memory, keep)) = overflow {
+            self.shared.evict(&memory, &keep);
+        }
+    }
+}
+
+impl Notifications {
+    /// Publish `event` on `key`, if events of `class` are enabled.
+    ///
+    /// Nothing is formatted or published when the class is disabled, so
+    /// disabled notifications cost a single check.
+    fn notify(&self, class: EventClass, event: &str, key: &[u8]) {
+        if !self.events.enabled(class) {
+            return;
+        }
+
+        if self.events.keyspace() {
+            // Channel names are strings, invalid UTF-8 in the key is
+            // replaced.
+            let channel = format!("__keyspace@{}__:{}", self.index, key_lossy(key));
+            self.global
+                .publish(&channel, Bytes::from(event.to_string()));
+        }
+
+        if self.events.keyevent() {
+            let channel = format!("__keyevent@{}__:{}", self.index, event);
+            self.global.publish(&channel, Bytes::copy_from_slice(key));
+        }
+    }
+}
+
+impl Entry {
+    /// Returns the access frequency counter, decayed by the time elapsed
+    /// since the last access.
+    ///
+    /// Like in Redis, the counter is logarithmic and approximate: it starts at
+    /// `LFU_INIT_VAL`, each access increments it with a probability that
+    /// shrinks as it grows, it saturates at 255, and it is decremented once
+    /// per `LFU_DECAY_TIME` without accesses, so that keys which used to be
+    /// hot are eventually evicted.
+    fn frequency(&self, now: Instant) -> u8 {
+        let idle = now.saturating_duration_since(self.accessed_at);
+        let periods = idle.as_secs() / LFU_DECAY_TIME.as_secs();
+        self.frequency
+            .saturating_sub(u8::try_from(periods).unwrap_or(u8::MAX))
+    }
+
+    /// Count an access, incrementing the counter with a probability of
+    /// `1 / ((counter - LFU_INIT_VAL) * LFU_LOG_FACTOR + 1)`.
+    fn count_access(&mut self) {
+        if self.frequency == u8::MAX {
+            return;
+        }
+
+        let base = f64::from(self.frequency.saturating_sub(LFU_INIT_VAL));
+        let probability = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+        if (random() as f64 / u64::MAX as f64) < probability {
+            self.frequency += 1;
+        }
+    }
+}
+
+impl Value {
+    /// Returns the name of the type, as reported by the `TYPE` command.
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+        }
+    }
+
+    /// Returns the name of the encoding of the value, as reported by the
+    /// `OBJECT ENCODING` command.
+    ///
+    /// As in Redis, strings holding an integer in its canonical form are
+    /// reported as `int`.
+    fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(data) => {
+                let is_int = str::from_utf8(data)
+                    .ok()
+                    .and_then(|s| s.parse::<i64>().ok())
+                    .map(|int| int.to_string().as_bytes() == data.as_ref())
+                    .unwrap_or(false);
+
+                if is_int {
+                    "int"
+                } else {
+                    "raw"
+                }
+            }
+            Value::List(_) => "quicklist",
+            Value::Hash(_) | Value::Set(_) => "hashtable",
+            Value::SortedSet(_) => "skiplist",
+        }
+    }
+
+    /// Returns the approximate number of bytes used by the value.
+    ///
+    /// Only the data is counted, not the overhead of the data structures
+    /// holding it.
+    fn size(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::List(list) => list.iter().map(|value| value.len()).sum(),
+            Value::Hash(hash) => hash
+                .iter()
+                .map(|(field, value)| field.len() + value.len())
+                .sum(),
+            Value::Set(set) => set.iter().map(|member| member.len()).sum(),
+            Value::SortedSet(zset) => zset
+                .iter()
+                .map(|(member, _)| member.len() + std::mem::size_of::<f64>())
+                .sum(),
+        }
+    }
+}
+
+impl Memory {
+    fn new(limit: usize) -> Memory {
+        Memory {
+            limit,
+            used: 0,
+            keys: HashMap::new(),
+            lru: BTreeSet::new(),
+            clock: 0,
+        }
+    }
+
+    /// Record `size` as the size of `key` and mark it as the most recently
+    /// used key.
+    fn update(&mut self, key: &[u8], size: usize) {
+        self.clock += 1;
+        let last_access = self.clock;
+
+        let prev = self
+            .keys
+            .insert(Bytes::copy_from_slice(key), Usage { size, last_access });
+
+        if let Some(prev) = prev {
+            self.used -= prev.size;
+            self.lru
+                .remove(&(prev.last_access, Bytes::copy_from_slice(key)));
+        }
+
+        self.used += size;
+        self.lru.insert((last_access, Bytes::copy_from_slice(key)));
+    }
+
+    /// Mark `key` as the most recently used key, if it is tracked.
+    fn access(&mut self, key: &[u8]) {
+        self.clock += 1;
+        let clock = self.clock;
+
+        if let Some(usage) = self.keys.get_mut(key) {
+            let prev = std::mem::replace(&mut usage.last_access, clock);
+            self.lru.remove(&(prev, Bytes::copy_from_slice(key)));
+            self.lru.insert((clock, Bytes::copy_from_slice(key)));
+        }
+    }
+
+    /// Stop tracking `key`, which has been removed.
+    fn forget(&mut self, key: &[u8]) {
+        if let Some(usage) = self.keys.remove(key) {
+            self.used -= usage.size;
+            self.lru
+                .remove(&(usage.last_access, Bytes::copy_from_slice(key)));
+        }
+    }
+
+    /// Stop tracking all keys.
+    fn clear(&mut self) {
+        self.used = 0;
+        self.keys.clear();
+        self.lru.clear();
+    }
+
+    /// Exchange the keys tracked with those tracked by `other`, keeping the
+    /// limits as they are.
+    fn swap_usage(&mut self, other: &mut Memory) {
+        std::mem::swap(&mut self.used, &mut other.used);
+        std::mem::swap(&mut self.keys, &mut other.keys);
+        std::mem::swap(&mut self.lru, &mut other.lru);
+        std::mem::swap(&mut self.clock, &mut other.clock);
+    }
+}
+
+impl Stats {
+    fn new() -> Stats {
+        Stats {
+            started_at: Instant::now(),
+            connections_received: AtomicU64::new(0),
+            connected_clients: AtomicU64::new(0),
+            commands_processed: AtomicU64::new(0),
+            commands: Mutex::new(HashMap::new()),
+            expired_keys: AtomicU64::new(0),
+            reaped_keys: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a connection being accepted.
+    pub(crate) fn connection_received(&self) {
+        self.connections_received.fetch_add(1, Ordering::Relaxed);
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a connection being closed.
+    pub(crate) fn connection_closed(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Record a command being received.
+    pub(crate) fn command_processed(&self) {
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record command `name` being applied.
+    pub(crate) fn command_applied(&self, name: &str) {
+        let mut commands = self.commands.lock().unwrap();
+
+        match commands.get_mut(name) {
+            Some(count) => *count += 1,
+            None => {
+                commands.insert(name.to_string(), 1);
+            }
+        }
+    }
+
+    /// Record a key being removed because it expired.
+    pub(crate) fn key_expired(&self) {
+        self.expired_keys.fetch_add(1, Ordering::Relaxed);
+    }
 
-        state
-            .pub_sub
-            .get(key)
-            // On a successful message send on the broadcast channel, the number
-            // of subscribers is returned. An error indicates there are no
-            // receivers, in which case, `0` should be returned.
-            .map(|tx| tx.send(value).unwrap_or(0))
-            // If there is no entry for the channel key, then there are no
-            // subscribers. In this case, return `0`.
-            .unwrap_or(0)
+    /// Record `count` expired keys being removed by the background task.
+    fn keys_reaped(&self, count: usize) {
+        self.reaped_keys.fetch_add(count as u64, Ordering::Relaxed);
     }
 
-    /// Signals the purge background task to shut down. This is called by the
-    /// `DbShutdown`s `Drop` implementation.
-    fn shutdown_purge_task(&self) {
-        // The background task must be signaled to shut down. This is done by
-        // setting `State::shutdown` to `true` and signalling the task.
-        let mut state = self.shared.state.lock().unwrap();
-        state.shutdown = true;
+    /// Returns the time elapsed since the server started.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
 
-        // Drop the lock before signalling the background task. This helps
-        // reduce lock contention by ensuring the background task doesn't
-        // wake up only to be unable to acquire the mutex.
-        drop(state);
-        self.shared.background_task.notify_one();
+    /// Returns the number of connections accepted since the server started.
+    pub(crate) fn connections_received(&self) -> u64 {
+        self.connections_received.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of connections currently open.
+    pub(crate) fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of commands received since the server started.
+    pub(crate) fn commands_processed(&self) -> u64 {
+        self.commands_processed.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many times each command was applied, sorted by command
+    /// name.
+    pub(crate) fn commands(&self) -> Vec<(String, u64)> {
+        let commands = self.commands.lock().unwrap();
+
+        let mut commands: Vec<_> = commands
+            .iter()
+            .map(|(name, &count)| (name.clone(), count))
+            .collect();
+        commands.sort();
+        commands
+    }
+
+    /// Returns the number of keys removed because they expired.
+    pub(crate) fn expired_keys(&self) -> u64 {
+        self.expired_keys.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of expired keys removed by the background task.
+    pub(crate) fn reaped_keys(&self) -> u64 {
+        self.reaped_keys.load(Ordering::Relaxed)
     }
 }
 
-impl Shared {
-    /// Purge all expired keys and return the `Instant` at which the **next**
-    /// key will expire. The background task will sleep until this instant.
-    fn purge_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
+impl SlowLogEntry {
+    /// Returns the unique identifier of the entry.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
 
-        if state.shutdown {
-            // The database is shutting down. All handles to the shared state
-            // have dropped. The background task should exit.
-            return None;
+    /// Returns when the command was logged.
+    pub(crate) fn logged_at(&self) -> SystemTime {
+        self.logged_at
+    }
+
+    /// Returns how long the command took to apply.
+    pub(crate) fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the name and arguments of the command.
+    pub(crate) fn args(&self) -> &[Bytes] {
+        &self.args
+    }
+
+    /// Returns the address of the peer that sent the command, if any.
+    pub(crate) fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// Returns the name of the connection that sent the command, if any.
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl ClientInfo {
+    /// Returns the identifier of the connection.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the address of the peer.
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the name of the connection, if it has one.
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the time elapsed since the connection was accepted.
+    pub(crate) fn age(&self) -> Duration {
+        self.connected_at.elapsed()
+    }
+
+    /// Returns the index of the selected database.
+    pub(crate) fn db(&self) -> usize {
+        self.db
+    }
+}
+
+impl ClientGuard {
+    /// Returns the address of the peer.
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Record the name of the connection, `None` if it has no name.
+    pub(crate) fn set_name(&self, name: Option<String>) {
+        self.update(|client| client.name = name);
+    }
+
+    /// Record the database selected by the connection.
+    pub(crate) fn set_db(&self, db: usize) {
+        self.update(|client| client.db = db);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ClientInfo)) {
+        if let Some(client) = self.global.clients.lock().unwrap().get_mut(&self.id) {
+            f(client);
+        }
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        // A poisoned lock must not prevent the entry from being removed.
+        let mut clients = match self.global.clients.lock() {
+            Ok(clients) => clients,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        clients.remove(&self.id);
+    }
+}
+
+impl Deref for Entries {
+    type Target = HashMap<Bytes, Entry>;
+
+    fn deref(&self) -> &HashMap<Bytes, Entry> {
+        &self.map
+    }
+}
+
+impl Entries {
+    fn get_mut(&mut self, key: &[u8]) -> Option<&mut Entry> {
+        self.map.get_mut(key)
+    }
+
+    /// Returns the entry at `key`, inserting `entry` first if there is none.
+    fn get_or_insert(&mut self, key: &[u8], entry: Entry) -> &mut Entry {
+        if !self.map.contains_key(key) {
+            self.insert(Bytes::copy_from_slice(key), entry);
+        }
+        self.map.get_mut(key).unwrap()
+    }
+
+    fn insert(&mut self, key: Bytes, entry: Entry) -> Option<Entry> {
+        let position = scan_position(&key);
+        let prev = self.map.insert(key.clone(), entry);
+        if prev.is_none() {
+            self.scan_order.insert((position, key));
+        }
+        prev
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<Entry> {
+        let (key, entry) = self.map.remove_entry(key)?;
+        self.scan_order.remove(&(scan_position(&key), key));
+        Some(entry)
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.scan_order.clear();
+    }
+
+    fn drain(&mut self) -> impl Iterator<Item = (Bytes, Entry)> + '_ {
+        self.scan_order.clear();
+        self.map.drain()
+    }
+}
+
+impl State {
+    /// Pop a value from `end` of the list stored at `key`.
+    ///
+    /// Returns `None` if the key does not exist. The key is removed once the
+    /// last element has been popped.
+    fn pop(&mut self, key: &[u8], end: ListEnd) -> Result<Option<Bytes>, DbError> {
+        Ok(self.pop_many(key, end, 1)?.pop())
+    }
+
+    /// Pop up to `count` values from `end` of the list stored at `key`, in
+    /// the order they are popped.
+    ///
+    /// Returns an empty `Vec` if the key does not exist. The key is removed
+    /// once the last element has been popped.
+    fn pop_many(&mut self, key: &[u8], end: ListEnd, count: usize) -> Result<Vec<Bytes>, DbError> {
+        self.remove_if_expired(key);
+
+        let list = match self.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::List(list)) => list,
+            Some(_) => return Err(DbError::WrongType),
+            None => return Ok(vec![]),
+        };
+
+        let count = count.min(list.len());
+        let values = match end {
+            ListEnd::Left => list.drain(..count).collect(),
+            ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+        };
+
+        let emptied = list.is_empty();
+
+        if emptied {
+            self.remove_entry(key);
+        } else {
+            self.written(key);
         }
 
-        // This is needed to make the borrow checker happy. In short, `lock()`
-        // returns a `MutexGuard` and not a `&mut State`. The borrow checker is
-        // not able to see "through" the mutex guard and determine that it is
-        // safe to access both `state.expirations` and `state.entries` mutably,
-        // so we get a "real" mutable reference to `State` outside of the loop.
-        let state = &mut *state;
+        let event = match end {
+            ListEnd::Left => "lpop",
+            ListEnd::Right => "rpop",
+        };
+        self.notify(EventClass::List, event, key);
+
+        if emptied {
+            self.notify(EventClass::Generic, "del", key);
+        }
+
+        Ok(values)
+    }
+
+    /// Notify the connections blocked on `key` that it may now hold a list to
+    /// pop from.
+    ///
+    /// Every waiter is notified, and unregistered. They then race to pop, the
+    /// lock making sure each value goes to a single one of them, and the others
+    /// register again.
+    fn wake_waiters(&mut self, key: &[u8]) {
+        for waiter in self.waiters.remove(key).into_iter().flatten() {
+            waiter.notify_one();
+        }
+    }
 
+    /// Purge up to `PURGE_BATCH_SIZE` expired keys from the shard.
+    fn purge_expired_keys(&mut self) -> Purge {
         // Find all keys scheduled to expire **before** now.
         let now = Instant::now();
+        let mut purged = 0;
 
-        while let Some(&(when, ref key)) = state.expirations.iter().next() {
+        while let Some(&(when, ref key)) = self.expirations.iter().next() {
             if when > now {
                 // Done purging, `when` is the instant at which the next key
-                // expires. The worker task will wait until this instant.
-                return Some(when);
+                // of the shard expires.
+                self.notifications.global.stats.keys_reaped(purged);
+                return Purge::Until(when);
             }
 
+            if purged == PURGE_BATCH_SIZE {
+                self.notifications.global.stats.keys_reaped(purged);
+                return Purge::Again;
+            }
+            purged += 1;
+
             // The key expired, remove it
-            state.entries.remove(key);
-            state.expirations.remove(&(when, key.clone()));
+            let key = key.clone();
+            self.take_entry(&key);
+            self.expired(&key);
         }
 
-        None
-    }
-
-    /// Returns `true` if the database is shutting down
-    ///
-    /// The `shutdown` flag is set when all `Db` values have dropped, indicating
-    /// that the shared state can no longer be accessed.
-    fn is_shutdown(&self) -> bool {
-        self.state.lock().unwrap().shutdown
+        self.notifications.global.stats.keys_reaped(purged);
+        Purge::Idle
     }
-}
 
-impl State {
     fn next_expiration(&self) -> Option<Instant> {
         self.expirations
             .iter()
             .next()
             .map(|expiration| expiration.0)
     }
+
+    /// Returns the entry stored at `key` if it has not expired.
+    ///
+    /// The background task purges expired keys asynchronously, so an entry may
+    /// still be present for a short while after its expiration instant. Such
+    /// entries are treated as missing.
+    fn live_entry(&self, key: &[u8]) -> Option<&Entry> {
+        let now = Instant::now();
+
+        self.entries
+            .get(key)
+            .filter(|entry| entry.expires_at.map(|when| when > now).unwrap_or(true))
+    }
+
+    /// Remove the entry stored at `key` if it has expired but has not been
+    /// purged by the background task yet.
+    ///
+    /// Mutating operations call this first so that they never act on a stale
+    /// value.
+    fn remove_if_expired(&mut self, key: &[u8]) {
+        if self.live_entry(key).is_none() && self.remove_entry(key) {
+            self.expired(key);
+        }
+    }
+
+    /// Record that `key` was removed because it expired.
+    fn expired(&self, key: &[u8]) {
+        self.notifications.global.stats.key_expired();
+        self.notify(EventClass::Expired, "expired", key);
+    }
+
+    /// Remove the entry stored at `key` along with its expiration.
+    ///
+    /// Returns `true` if an entry was removed.
+    fn remove_entry(&mut self, key: &[u8]) -> bool {
+        self.take_entry(key).is_some()
+    }
+
+    /// Remove the entry stored at `key` along with its expiration, and return
+    /// it.
+    fn take_entry(&mut self, key: &[u8]) -> Option<Entry> {
+        let mut prev = self.entries.remove(key)?;
+        self.uncount(prev.counted.take());
+
+        if let Some(when) = prev.expires_at {
+            self.expirations
+                .remove(&(when, Bytes::copy_from_slice(key)));
+        }
+        if let Some(memory) = &self.memory {
+            memory.lock().unwrap().forget(key);
+        }
+
+        Some(prev)
+    }
+
+    /// Returns the approximate number of bytes used by the value stored at
+    /// `key`.
+    ///
+    /// Returns `0` if the key does not exist or no memory limit is
+    /// configured.
+    fn size_of(&self, key: &[u8]) -> usize {
+        let memory = match &self.memory {
+            Some(memory) => memory.lock().unwrap(),
+            None => return 0,
+        };
+
+        memory
+            .keys
+            .get(key)
+            .map(|usage| usage.size - key.len())
+            .unwrap_or(0)
+    }
+
+    /// Store `data` in `field` of the hash stored at `key`, creating the hash
+    /// if needed, once the memory limit allows it.
+    ///
+    /// The caller has checked that `key` holds a hash or does not exist.
+    fn hash_insert(&mut self, key: &[u8], field: &[u8], data: Bytes) -> Result<(), DbError> {
+        self.reserve(key.len() + self.size_of(key) + field.len() + data.len())?;
+
+        let entry = self.entries.get_or_insert(
+            key,
+            Entry {
+                value: Value::Hash(HashMap::new()),
+                expires_at: None,
+                accessed_at: Instant::now(),
+                frequency: LFU_INIT_VAL,
+                counted: None,
+            },
+        );
+
+        if let Value::Hash(hash) = &mut entry.value {
+            hash.insert(Bytes::copy_from_slice(field), data);
+        }
+
+        self.written(key);
+
+        Ok(())
+    }
+
+    /// Check that a key using `size` bytes, including its name, may be
+    /// written within the memory limit.
+    ///
+    /// When evicting, other keys can always be evicted to make room, so this
+    /// only fails if the key on its own is larger than the limit. Otherwise,
+    /// writes are refused as long as the limit is exceeded.
+    fn reserve(&self, size: usize) -> Result<(), DbError> {
+        let memory = match &self.memory {
+            Some(memory) => memory.lock().unwrap(),
+            None => return Ok(()),
+        };
+
+        let full = self.policy == MaxMemoryPolicy::NoEviction && memory.used > memory.limit;
+
+        if size > memory.limit || full {
+            Err(DbError::OutOfMemory)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Publish `event` on `key`, if keyspace notifications of `class` are
+    /// enabled.
+    fn notify(&self, class: EventClass, event: &str, key: &[u8]) {
+        self.notifications.notify(class, event, key);
+    }
+
+    /// Mark `key` as the most recently used key, and count the access with
+    /// the `allkeys-lfu` policy.
+    fn record_access(&mut self, key: &[u8]) {
+        let lfu = self.policy == MaxMemoryPolicy::AllKeysLfu;
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            let now = Instant::now();
+            entry.frequency = entry.frequency(now);
+            if lfu {
+                entry.count_access();
+            }
+            entry.accessed_at = now;
+        }
+
+        if let Some(memory) = &self.memory {
+            memory.lock().unwrap().access(key);
+        }
+    }
+
+    /// Update the memory accounting after the value stored at `key` has been
+    /// written. If the limit is now exceeded and the policy allows it, the
+    /// least recently used keys are evicted once the shard is unlocked. `key`
+    /// itself is never evicted.
+    fn written(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            // The decay of the frequency is measured from the last access.
+            let now = Instant::now();
+            entry.frequency = entry.frequency(now);
+            entry.accessed_at = now;
+
+            // The entry is new, or its value was replaced by one of another
+            // type.
+            let type_name = entry.value.type_name();
+            if entry.counted != Some(type_name) {
+                let counted = entry.counted.replace(type_name);
+                *self.types.entry(type_name).or_default() += 1;
+                self.uncount(counted);
+            }
+        }
+
+        let memory = match &self.memory {
+            Some(memory) => memory,
+            None => return,
+        };
+
+        let size = match self.entries.get(key) {
+            Some(entry) => key.len() + entry.value.size(),
+            None => return,
+        };
+
+        let mut memory = memory.lock().unwrap();
+        memory.update(key, size);
+
+        if memory.used > memory.limit && self.policy != MaxMemoryPolicy::NoEviction {
+            self.overflow.push(Bytes::copy_from_slice(key));
+        }
+    }
+
+    /// Stop counting an entry that was counted as holding a `counted` value.
+    fn uncount(&mut self, counted: Option<&'static str>) {
+        if let Some(count) = counted.and_then(|type_name| self.types.get_mut(type_name)) {
+            *count -= 1;
+        }
+    }
+
+    /// Take the keys written while the memory limit was exceeded, along with
+    /// the accounting of the memory to free.
+    fn take_overflow(&mut self) -> Option<(Arc<Mutex<Memory>>, Vec<Bytes>)> {
+        if self.overflow.is_empty() {
+            return None;
+        }
+
+        let keep = std::mem::take(&mut self.overflow);
+        Some((self.memory.clone()?, keep))
+    }
+
+    /// Replace the expiration of the entry stored at `key` with `when`.
+    ///
+    /// The `expirations` set is kept in sync with the entry. Returns `true` if
+    /// the background task must be notified because `when` is now the next
+    /// expiration to fire. Does nothing if the key does not exist.
+    fn set_expiration(&mut self, key: &[u8], when: Option<Instant>) -> bool {
+        let notify = match when {
+            Some(when) => self
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true),
+            None => false,
+        };
+
+        let entry = match self.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        // Remove then insert, see `Db::set_if` for why the order matters.
+        if let Some(prev) = std::mem::replace(&mut entry.expires_at, when) {
+            self.expirations
+                .remove(&(prev, Bytes::copy_from_slice(key)));
+        }
+
+        if let Some(when) = when {
+            self.expirations.insert((when, Bytes::copy_from_slice(key)));
+        }
+
+        notify
+    }
+}
+
+/// Position of `key` in the order `Db::scan` visits the keys of its shard.
+///
+/// Positions fit in `SCAN_POSITION_BITS`, leaving room for the index of the
+/// shard in the cursor.
+fn scan_position(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish() >> (64 - SCAN_POSITION_BITS)
+}
+
+/// Keep the keys of a `Db::scan` batch matching `pattern`, if given.
+fn filter_scanned(keys: Vec<Bytes>, pattern: Option<&[u8]>) -> Vec<Bytes> {
+    match pattern {
+        Some(pattern) => keys
+            .into_iter()
+            .filter(|key| glob::matches(pattern, key))
+            .collect(),
+        None => keys,
+    }
+}
+
+/// Resolve the inclusive range between `start` and `end` of a string of `len`
+/// bytes, offsets being in bytes or bits as set by `unit`, to a range of bits.
+///
+/// Negative offsets count from the end of the string, and offsets are clamped
+/// to the string, as in `getrange`. Returns `None` if the range is empty.
+fn bit_range(len: usize, start: i64, end: i64, unit: BitUnit) -> Option<(usize, usize)> {
+    let len = match unit {
+        BitUnit::Byte => len as i64,
+        BitUnit::Bit => len as i64 * 8,
+    };
+
+    let start = if start < 0 { start + len } else { start }.max(0);
+    let end = if end < 0 { end + len } else { end }.min(len - 1);
+
+    if start > end {
+        return None;
+    }
+
+    let (start, end) = (start as usize, end as usize);
+    match unit {
+        BitUnit::Byte => Some((start * 8, end * 8 + 7)),
+        BitUnit::Bit => Some((start, end)),
+    }
+}
+
+/// Returns a random number.
+///
+/// Each `RandomState` is seeded differently, so hashing nothing with a new one
+/// is enough of a random source for picking keys.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Reject the counts of random entries Redis rejects, whose magnitude is more
+/// than half the range of `i64`.
+fn check_sample_count(count: i64) -> Result<(), DbError> {
+    if count.unsigned_abs() > i64::MAX as u64 / 2 {
+        return Err(DbError::CountOutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Pick `count` distinct `items` at random if `count` is positive, see
+/// `Db::srandmember`. Every item is returned for a negative `count`, to be
+/// picked from by `sample_with_replacement` once the key is unlocked.
+///
+/// Distinct items are picked by shuffling the first `count` of them into
+/// place, so each item has the same chance of being picked.
+fn sample_distinct<T: Copy>(items: impl Iterator<Item = T>, count: i64) -> Vec<T> {
+    let mut items: Vec<T> = items.collect();
+    let len = items.len();
+
+    if let Ok(count) = usize::try_from(count) {
+        let count = count.min(len);
+        for i in 0..count {
+            let j = i + (random() % (len - i) as u64) as usize;
+            items.swap(i, j);
+        }
+        items.truncate(count);
+    }
+
+    items
+}
+
+/// Pick `-count` of `items` at random for a negative `count`, possibly picking
+/// the same item several times. `items` are returned as is otherwise.
+///
+/// The entries are reserved up front, a count too large to be allocated is
+/// rejected rather than aborting the server.
+fn sample_with_replacement<T: Clone>(items: Vec<T>, count: i64) -> Result<Vec<T>, DbError> {
+    if count >= 0 || items.is_empty() {
+        return Ok(items);
+    }
+
+    let count = usize::try_from(count.unsigned_abs()).map_err(|_| DbError::CountOutOfRange)?;
+
+    let mut picked = Vec::new();
+    picked
+        .try_reserve_exact(count)
+        .map_err(|_| DbError::CountOutOfRange)?;
+
+    for _ in 0..count {
+        picked.push(items[(random() % items.len() as u64) as usize].clone());
+    }
+
+    Ok(picked)
 }
 
 /// Routine executed by the background task.
@@ -348,22 +4969,49 @@ async fn purge_expired_tasks(shared: Arc<Shared>) {
     while !shared.is_shutdown() {
         // Purge all keys that are expired. The function returns the instant at
         // which the **next** key will expire. The worker should wait until the
-        // instant has passed then purge again.
-        if let Some(when) = shared.purge_expired_keys() {
-            // Wait until the next key expires **or** until the background task
-            // is notified. If the task is notified, then it must reload its
-            // state as new keys have been set to expire early. This is done by
-            // looping.
-            tokio::select! {
-                _ = time::sleep_until(when) => {}
-                _ = shared.background_task.notified() => {}
-            }
+        // instant has passed then purge again. While paused, the task only
+        // waits to be resumed.
+        let next = if shared.active_expire.load(Ordering::SeqCst) {
+            shared.purge_expired_keys()
         } else {
-            // There are no keys expiring in the future. Wait until the task is
-            // notified.
-            shared.background_task.notified().await;
+            Purge::Idle
+        };
+
+        match next {
+            // More keys expired than are purged at once. Let other tasks use
+            // the database before purging the next batch.
+            Purge::Again => task::yield_now().await,
+            Purge::Until(when) => {
+                // Wait until the next key expires **or** until the background
+                // task is notified. If the task is notified, then it must
+                // reload its state as new keys have been set to expire early.
+                // This is done by looping.
+                tokio::select! {
+                    _ = time::sleep_until(when) => {}
+                    _ = shared.background_task.notified() => {}
+                }
+            }
+            Purge::Idle => {
+                // There are no keys expiring in the future. Wait until the task
+                // is notified.
+                shared.background_task.notified().await;
+            }
         }
     }
 
     debug!("Purge background task shut down")
 }
+
+/// Routine executed by the task dropping unlinked values.
+///
+/// Dropping a large value can take a while, so this runs on a blocking thread
+/// where it does not hold up the tasks serving connections. The channel closes
+/// once the `Shared` state holding the sender is dropped, which terminates the
+/// task.
+fn drop_unlinked_values(mut unlinked: mpsc::UnboundedReceiver<Value>) {
+    while let Some(value) = unlinked.blocking_recv() {
+        drop(value);
+    }
+
+    debug!("Lazy free background task shut down")
+}
@@ -1,8 +1,10 @@
 //! Provides a type representing a Redis protocol frame as well as utilities for
-//! parsing frames from a byte array.
+//! parsing frames from a byte array and encoding them into one.
 
-use bytes::{Buf, Bytes};
-use std::convert::TryInto;
+use crate::clients::ServerError;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::io::Cursor;
 use std::num::TryFromIntError;
@@ -13,10 +15,21 @@ use std::string::FromUtf8Error;
 pub enum Frame {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
     Null,
     Array(Vec<Frame>),
+    /// Out-of-band data sent by the server under RESP3, such as pub/sub
+    /// messages. Encoded as an array for RESP2 peers.
+    Push(Vec<Frame>),
+    /// RESP3 map of key/value pairs. Encoded as a flat array of alternating
+    /// keys and values for RESP2 peers.
+    Map(Vec<(Frame, Frame)>),
+    /// RESP3 floating point number. Encoded as a bulk string holding the
+    /// number for RESP2 peers.
+    Double(f64),
+    /// RESP3 boolean. Encoded as the integer `1` or `0` for RESP2 peers.
+    Boolean(bool),
 }
 
 #[derive(Debug)]
@@ -62,7 +75,7 @@ impl Frame {
     /// # Panics
     ///
     /// panics if `self` is not an array
-    pub(crate) fn push_int(&mut self, value: u64) {
+    pub(crate) fn push_int(&mut self, value: i64) {
         match self {
             Frame::Array(vec) => {
                 vec.push(Frame::Integer(value));
@@ -73,6 +86,30 @@ impl Frame {
 
     /// Checks if an entire message can be decoded from `src`
     pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
+        Frame::check_with_limit(src, usize::MAX)
+    }
+
+    /// Checks if an entire message can be decoded from `src`, rejecting
+    /// messages announcing a bulk string longer than `max_frame_size` bytes.
+    ///
+    /// Lengths are checked as soon as they are read, so an oversized message is
+    /// rejected before the rest of it has been received.
+    pub fn check_with_limit(src: &mut Cursor<&[u8]>, max_frame_size: usize) -> Result<(), Error> {
+        Frame::check_with_limits(src, max_frame_size, usize::MAX)
+    }
+
+    /// Checks if an entire message can be decoded from `src`, as
+    /// `check_with_limit` does, also rejecting arrays and maps with more than
+    /// `max_array_len` entries.
+    ///
+    /// Servers accept far fewer arguments than a frame of the maximum size
+    /// could hold, this lets them reject a request announcing too many of them
+    /// before buffering it.
+    pub fn check_with_limits(
+        src: &mut Cursor<&[u8]>,
+        max_frame_size: usize,
+        max_array_len: usize,
+    ) -> Result<(), Error> {
         match get_u8(src)? {
             b'+' => {
                 get_line(src)?;
@@ -83,7 +120,15 @@ impl Frame {
                 Ok(())
             }
             b':' => {
-                let _ = get_decimal(src)?;
+                let _ = get_signed_decimal(src)?;
+                Ok(())
+            }
+            b',' => {
+                let _ = get_double(src)?;
+                Ok(())
+            }
+            b'#' => {
+                let _ = get_boolean(src)?;
                 Ok(())
             }
             b'$' => {
@@ -94,15 +139,35 @@ impl Frame {
                     // Read the bulk string
                     let len: usize = get_decimal(src)?.try_into()?;
 
+                    // The length plus the trailing `\r\n` must not overflow
+                    // when no limit is set.
+                    let n = match len.checked_add(2) {
+                        Some(n) if len <= max_frame_size => n,
+                        _ => return Err("Protocol error: invalid bulk length".into()),
+                    };
+
                     // skip that number of bytes + 2 (\r\n).
-                    skip(src, len + 2)
+                    skip(src, n)
                 }
             }
-            b'*' => {
-                let len = get_decimal(src)?;
+            b'*' | b'>' => {
+                let len = check_count(get_decimal(src)?, max_array_len)?;
+
+                for _ in 0..len {
+                    Frame::check_with_limits(src, max_frame_size, max_array_len)?;
+                }
 
+                Ok(())
+            }
+            b'%' => {
+                let len = check_count(get_decimal(src)?, max_array_len)?;
+
+                // Each entry is a key followed by its value
+                let len = len
+                    .checked_mul(2)
+                    .ok_or("Protocol error: invalid multibulk length")?;
                 for _ in 0..len {
-                    Frame::check(src)?;
+                    Frame::check_with_limits(src, max_frame_size, max_array_len)?;
                 }
 
                 Ok(())
@@ -112,6 +177,9 @@ impl Frame {
     }
 
     /// The message has already been validated with `check`.
+    ///
+    /// Arrays and maps are not allocated more entries than `src` could hold,
+    /// whatever length they announce.
     pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, Error> {
         match get_u8(src)? {
             b'+' => {
@@ -133,8 +201,16 @@ impl Frame {
                 Ok(Frame::Error(string))
             }
             b':' => {
-                let len = get_decimal(src)?;
-                Ok(Frame::Integer(len))
+                let value = get_signed_decimal(src)?;
+                Ok(Frame::Integer(value))
+            }
+            b',' => {
+                let value = get_double(src)?;
+                Ok(Frame::Double(value))
+            }
+            b'#' => {
+                let value = get_boolean(src)?;
+                Ok(Frame::Boolean(value))
             }
             b'$' => {
                 if b'-' == peek_u8(src)? {
@@ -147,8 +223,10 @@ impl Frame {
                     Ok(Frame::Null)
                 } else {
                     // Read the bulk string
-                    let len = get_decimal(src)?.try_into()?;
-                    let n = len + 2;
+                    let len: usize = get_decimal(src)?.try_into()?;
+                    let n = len
+                        .checked_add(2)
+                        .ok_or("Protocol error: invalid bulk length")?;
 
                     if src.remaining() < n {
                         return Err(Error::Incomplete);
@@ -164,7 +242,7 @@ impl Frame {
             }
             b'*' => {
                 let len = get_decimal(src)?.try_into()?;
-                let mut out = Vec::with_capacity(len);
+                let mut out = Vec::with_capacity(capacity(len, src));
 
                 for _ in 0..len {
                     out.push(Frame::parse(src)?);
@@ -172,10 +250,207 @@ impl Frame {
 
                 Ok(Frame::Array(out))
             }
+            b'>' => {
+                let len = get_decimal(src)?.try_into()?;
+                let mut out = Vec::with_capacity(capacity(len, src));
+
+                for _ in 0..len {
+                    out.push(Frame::parse(src)?);
+                }
+
+                Ok(Frame::Push(out))
+            }
+            b'%' => {
+                let len = get_decimal(src)?.try_into()?;
+                let mut out = Vec::with_capacity(capacity(len, src));
+
+                for _ in 0..len {
+                    let key = Frame::parse(src)?;
+                    let value = Frame::parse(src)?;
+                    out.push((key, value));
+                }
+
+                Ok(Frame::Map(out))
+            }
             _ => unimplemented!(),
         }
     }
 
+    /// Parse a single frame from the start of `src`.
+    ///
+    /// On success, the frame is returned along with the number of bytes it was
+    /// encoded with. Any bytes following the frame are left alone. If `src`
+    /// only holds part of a frame, `Error::Incomplete` is returned, and more
+    /// data should be appended before trying again.
+    pub fn from_bytes(src: &[u8]) -> Result<(Frame, usize), Error> {
+        Frame::from_bytes_with_limit(src, usize::MAX)
+    }
+
+    /// Parse a single frame from the start of `src`, as `from_bytes` does,
+    /// rejecting frames that do not pass `check_with_limit`.
+    pub fn from_bytes_with_limit(
+        src: &[u8],
+        max_frame_size: usize,
+    ) -> Result<(Frame, usize), Error> {
+        Frame::from_bytes_with_limits(src, max_frame_size, usize::MAX)
+    }
+
+    /// Parse a single frame from the start of `src`, as `from_bytes` does,
+    /// rejecting frames that do not pass `check_with_limits`.
+    pub fn from_bytes_with_limits(
+        src: &[u8],
+        max_frame_size: usize,
+        max_array_len: usize,
+    ) -> Result<(Frame, usize), Error> {
+        let mut buf = Cursor::new(src);
+
+        // The first step is to check if enough data has been buffered to parse
+        // a single frame. This step is usually much faster than doing a full
+        // parse of the frame, and allows us to skip allocating data structures
+        // to hold the frame data unless we know the full frame has been
+        // received.
+        Frame::check_with_limits(&mut buf, max_frame_size, max_array_len)?;
+
+        // The `check` function will have advanced the cursor until the end of
+        // the frame. Since the cursor had position set to zero before
+        // `Frame::check` was called, we obtain the length of the frame by
+        // checking the cursor position.
+        let len = buf.position() as usize;
+
+        // Reset the position to zero before passing the cursor to
+        // `Frame::parse`.
+        buf.set_position(0);
+
+        // Parse the frame from the buffer. This allocates the necessary
+        // structures to represent the frame and returns the frame value.
+        let frame = Frame::parse(&mut buf)?;
+
+        Ok((frame, len))
+    }
+
+    /// Encode the frame using RESP3, returning the encoded bytes.
+    ///
+    /// Decoding the result with `from_bytes` yields an identical frame. Use
+    /// `encode` to target RESP2 peers.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut dst = BytesMut::new();
+        self.encode(&mut dst, 3);
+        dst.freeze()
+    }
+
+    /// Encode the frame at the end of `dst`.
+    ///
+    /// `protocol` is the version of the protocol spoken by the peer, `2` or
+    /// `3`. Frames that only exist in RESP3 are downgraded for RESP2 peers:
+    /// push frames are encoded as arrays, maps as flat arrays of alternating
+    /// keys and values, doubles as bulk strings and booleans as integers. Any
+    /// other value is treated as RESP2.
+    pub fn encode(&self, dst: &mut BytesMut, protocol: u8) {
+        match self {
+            Frame::Simple(val) => {
+                dst.put_u8(b'+');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Error(val) => {
+                dst.put_u8(b'-');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Integer(val) => {
+                dst.put_u8(b':');
+                put_decimal(dst, *val);
+            }
+            Frame::Null => {
+                dst.put_slice(b"$-1\r\n");
+            }
+            Frame::Double(val) => {
+                // Infinities are spelled the same in both versions, but RESP3
+                // expects `nan` in lowercase.
+                let val = if val.is_nan() {
+                    "nan".to_string()
+                } else {
+                    val.to_string()
+                };
+
+                if protocol == 3 {
+                    dst.put_u8(b',');
+                    dst.put_slice(val.as_bytes());
+                    dst.put_slice(b"\r\n");
+                } else {
+                    dst.put_u8(b'$');
+                    put_decimal(dst, val.len() as i64);
+                    dst.put_slice(val.as_bytes());
+                    dst.put_slice(b"\r\n");
+                }
+            }
+            Frame::Boolean(val) => {
+                if protocol == 3 {
+                    dst.put_slice(if *val { b"#t\r\n" } else { b"#f\r\n" });
+                } else {
+                    dst.put_u8(b':');
+                    put_decimal(dst, *val as i64);
+                }
+            }
+            Frame::Bulk(val) => {
+                dst.put_u8(b'$');
+                put_decimal(dst, val.len() as i64);
+                dst.put_slice(val);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Array(val) => {
+                // Encode the frame type prefix. For an array, it is `*`.
+                dst.put_u8(b'*');
+
+                // Encode the length of the array.
+                put_decimal(dst, val.len() as i64);
+
+                // Iterate and encode each entry in the array.
+                for entry in val {
+                    entry.encode(dst, protocol);
+                }
+            }
+            Frame::Push(val) => {
+                // RESP2 has no push type, the entries are sent as an array.
+                dst.put_u8(if protocol == 3 { b'>' } else { b'*' });
+                put_decimal(dst, val.len() as i64);
+
+                for entry in val {
+                    entry.encode(dst, protocol);
+                }
+            }
+            Frame::Map(val) => {
+                // RESP2 has no map type, keys and values are flattened into an
+                // array twice the size of the map.
+                if protocol == 3 {
+                    dst.put_u8(b'%');
+                    put_decimal(dst, val.len() as i64);
+                } else {
+                    dst.put_u8(b'*');
+                    put_decimal(dst, val.len() as i64 * 2);
+                }
+
+                for (key, value) in val {
+                    key.encode(dst, protocol);
+                    value.encode(dst, protocol);
+                }
+            }
+        }
+    }
+
+    /// Converts an error frame to `Err`, carrying the message sent by the
+    /// server as a [`ServerError`].
+    ///
+    /// Other frames are returned as is. Frames nested in arrays are not
+    /// inspected, the replies of a transaction for instance may hold errors
+    /// next to successful replies, each can be converted on its own.
+    pub fn into_result(self) -> crate::Result<Frame> {
+        match self {
+            Frame::Error(msg) => Err(ServerError::new(msg).into()),
+            frame => Ok(frame),
+        }
+    }
+
     /// Converts the frame to an "unexpected frame" error
     pub(crate) fn to_error(&self) -> crate::Error {
         format!("unexpected frame: {}", self).into()
@@ -205,7 +480,9 @@ impl fmt::Display for Frame {
                 Err(_) => write!(fmt, "{:?}", msg),
             },
             Frame::Null => "(nil)".fmt(fmt),
-            Frame::Array(parts) => {
+            Frame::Double(num) => num.fmt(fmt),
+            Frame::Boolean(val) => val.fmt(fmt),
+            Frame::Array(parts) | Frame::Push(parts) => {
                 for (i, part) in parts.iter().enumerate() {
                     if i > 0 {
                         // use space as the array element display separator
@@ -215,12 +492,48 @@ impl fmt::Display for Frame {
                     part.fmt(fmt)?;
                 }
 
+                Ok(())
+            }
+            Frame::Map(entries) => {
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, " ")?;
+                    }
+
+                    write!(fmt, "{}: {}", key, value)?;
+                }
+
                 Ok(())
             }
         }
     }
 }
 
+/// Reject array and map lengths above `max_len`, the maximum number of
+/// entries. The maximum size of bulk strings is a separate limit, so that
+/// lowering it does not refuse the commands needed to raise it again.
+///
+/// The error is worded as in Redis. Inline commands are checked the same way.
+pub(crate) fn check_count(len: u64, max_len: usize) -> Result<u64, Error> {
+    match usize::try_from(len) {
+        Ok(count) if count <= max_len => Ok(len),
+        _ => Err("Protocol error: invalid multibulk length".into()),
+    }
+}
+
+/// Returns the number of entries to allocate for an array or map announcing
+/// `len` entries. Every entry takes at least three bytes, so no more than a
+/// third of the bytes left in `src` can be entries.
+fn capacity(len: usize, src: &Cursor<&[u8]>) -> usize {
+    len.min(src.remaining() / 3)
+}
+
+/// Write a new-line terminated decimal
+fn put_decimal(dst: &mut BytesMut, val: i64) {
+    dst.put_slice(val.to_string().as_bytes());
+    dst.put_slice(b"\r\n");
+}
+
 fn peek_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
     if !src.has_remaining() {
         return Err(Error::Incomplete);
@@ -255,6 +568,36 @@ fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
     atoi::<u64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
 }
 
+/// Read a new-line terminated decimal that may be negative
+fn get_signed_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, Error> {
+    use atoi::atoi;
+
+    let line = get_line(src)?;
+
+    atoi::<i64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
+}
+
+/// Read a new-line terminated floating point number, as sent in RESP3 double
+/// frames
+fn get_double(src: &mut Cursor<&[u8]>) -> Result<f64, Error> {
+    let line = get_line(src)?;
+
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|line| line.parse::<f64>().ok())
+        .ok_or_else(|| "protocol error; invalid frame format".into())
+}
+
+/// Read a new-line terminated boolean, `t` or `f`, as sent in RESP3 boolean
+/// frames
+fn get_boolean(src: &mut Cursor<&[u8]>) -> Result<bool, Error> {
+    match get_line(src)? {
+        b"t" => Ok(true),
+        b"f" => Ok(false),
+        _ => Err("protocol error; invalid frame format".into()),
+    }
+}
+
 /// Find a line
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     // Scan the bytes directly
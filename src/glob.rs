@@ -0,0 +1,110 @@
+//! Glob-style pattern matching.
+//!
+//! Redis uses the same pattern syntax wherever a command accepts a pattern,
+//! for example for `PSUBSCRIBE`.
+
+/// Returns `true` if `string` matches the glob-style `pattern`.
+///
+/// The following syntax is supported:
+///
+/// * `?` matches any single byte.
+/// * `*` matches any sequence of bytes, including the empty one.
+/// * `[abc]` matches one of the listed bytes, `[a-z]` any byte in the range
+///   and `[^abc]` any byte that is not listed.
+/// * `\x` matches `x` literally.
+pub(crate) fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let mut p = 0;
+    let mut s = 0;
+
+    // Position of the last `*` seen and the position in `string` it currently
+    // matches up to. When the rest of the pattern fails to match, the `*` is
+    // extended by one byte and matching resumes from there.
+    let mut backtrack = None;
+
+    while s < string.len() {
+        if p < pattern.len() {
+            if pattern[p] == b'*' {
+                backtrack = Some((p, s));
+                p += 1;
+                continue;
+            }
+
+            if let Some(len) = match_one(&pattern[p..], string[s]) {
+                p += len;
+                s += 1;
+                continue;
+            }
+        }
+
+        match backtrack {
+            Some((star, pos)) => {
+                backtrack = Some((star, pos + 1));
+                p = star + 1;
+                s = pos + 1;
+            }
+            None => return false,
+        }
+    }
+
+    // The string is consumed. Any remaining stars match the empty string.
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+/// Match `byte` against the token at the start of `pattern`, which is not a
+/// `*`. Returns the length of the token if it matches.
+fn match_one(pattern: &[u8], byte: u8) -> Option<usize> {
+    match pattern[0] {
+        b'?' => Some(1),
+        b'[' => match_class(pattern, byte),
+        b'\\' if pattern.len() > 1 => (pattern[1] == byte).then_some(2),
+        c => (c == byte).then_some(1),
+    }
+}
+
+/// Match `byte` against the `[...]` class at the start of `pattern`. Returns
+/// the length of the class if it matches.
+///
+/// An unterminated class extends to the end of the pattern.
+fn match_class(pattern: &[u8], byte: u8) -> Option<usize> {
+    let mut i = 1;
+
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+
+    loop {
+        match pattern.get(i) {
+            None => break,
+            Some(&b']') => {
+                i += 1;
+                break;
+            }
+            Some(&b'\\') if i + 1 < pattern.len() => {
+                matched |= pattern[i + 1] == byte;
+                i += 2;
+            }
+            Some(&start)
+                if pattern.get(i + 1) == Some(&b'-')
+                    && pattern.get(i + 2).is_some_and(|&end| end != b']') =>
+            {
+                let end = pattern[i + 2];
+                let (low, high) = if start <= end {
+                    (start, end)
+                } else {
+                    (end, start)
+                };
+                matched |= low <= byte && byte <= high;
+                i += 3;
+            }
+            Some(&c) => {
+                matched |= c == byte;
+                i += 1;
+            }
+        }
+    }
+
+    (matched != negate).then_some(i)
+}
@@ -25,8 +25,10 @@
 //!   intermediate representation between a "command" and the byte
 //!   representation.
 
+mod aof;
+
 pub mod clients;
-pub use clients::{BlockingClient, BufferedClient, Client};
+pub use clients::{BlockingClient, BufferedClient, Client, ReconnectingClient};
 
 pub mod cmd;
 pub use cmd::Command;
@@ -41,14 +43,30 @@ mod db;
 use db::Db;
 use db::DbDropGuard;
 
+mod glob;
+
+mod metrics;
+
 mod parse;
 use parse::{Parse, ParseError};
 
+mod script;
+
 pub mod server;
 
+mod serialize;
+
+mod snapshot;
+
 mod shutdown;
 use shutdown::Shutdown;
 
+mod zset;
+
+/// The `rustls` crate used for TLS, re-exported to build the server and client
+/// TLS configurations with.
+pub use tokio_rustls::rustls;
+
 /// Default port that a redis server listens on.
 ///
 /// Used if no port is specified.
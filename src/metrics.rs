@@ -0,0 +1,192 @@
+//! Prometheus metrics endpoint.
+//!
+//! When `Config::metrics_addr` is set, the server listens on that address for
+//! HTTP requests and serves its statistics in the Prometheus text exposition
+//! format. Only `GET /metrics` is served; the rest of HTTP is not worth a
+//! dependency here, so requests are parsed by hand and every connection is
+//! closed after a single response.
+
+use crate::{Db, Shutdown};
+
+use std::fmt::{self, Write};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// Largest request accepted, in bytes. Prometheus sends a few headers only.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Serve metrics to the connections accepted by `listener` until the server
+/// shuts down.
+///
+/// `_shutdown_complete` is dropped once the endpoint stops accepting
+/// connections, letting the server know this task completed.
+pub(crate) async fn serve(
+    listener: TcpListener,
+    dbs: Vec<Db>,
+    mut shutdown: Shutdown,
+    _shutdown_complete: mpsc::Sender<()>,
+) {
+    loop {
+        let socket = tokio::select! {
+            res = listener.accept() => match res {
+                Ok((socket, _)) => socket,
+                Err(err) => {
+                    error!(cause = %err, "failed to accept metrics connection");
+                    continue;
+                }
+            },
+            _ = shutdown.recv() => return,
+        };
+
+        let dbs = dbs.clone();
+
+        // Scrapes are short lived, they are not waited for on shutdown.
+        tokio::spawn(async move {
+            if let Err(err) = respond(socket, &dbs).await {
+                debug!(cause = %err, "metrics connection error");
+            }
+        });
+    }
+}
+
+/// Read a single request from `socket` and write the response.
+async fn respond(mut socket: TcpStream, dbs: &[Db]) -> crate::Result<()> {
+    let mut request = Vec::new();
+
+    // Only the request line matters, but the headers are read as well so the
+    // peer does not see the connection reset while it is still writing.
+    while !request.ends_with(b"\r\n\r\n") {
+        if request.len() >= MAX_REQUEST_SIZE {
+            return Err("metrics request too large".into());
+        }
+
+        let mut buf = [0; 1024];
+        let n = socket.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let line = request.split(|&b| b == b'\r').next().unwrap_or_default();
+
+    let response = if line.starts_with(b"GET /metrics ") {
+        let mut body = String::new();
+        write_metrics(&mut body, dbs)?;
+
+        format!(
+            "HTTP/1.1 200 OK\r\n\
+             Content-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+
+    Ok(())
+}
+
+/// Write the metrics of the server holding `dbs` to `dst`.
+fn write_metrics(dst: &mut String, dbs: &[Db]) -> fmt::Result {
+    // Statistics are shared by all databases, and there is always at least
+    // one.
+    let stats = dbs[0].stats();
+
+    write_header(
+        dst,
+        "mini_redis_connections_received_total",
+        "counter",
+        "Number of connections accepted since the server started.",
+    )?;
+    writeln!(
+        dst,
+        "mini_redis_connections_received_total {}",
+        stats.connections_received()
+    )?;
+
+    write_header(
+        dst,
+        "mini_redis_connected_clients",
+        "gauge",
+        "Number of connections currently open.",
+    )?;
+    writeln!(
+        dst,
+        "mini_redis_connected_clients {}",
+        stats.connected_clients()
+    )?;
+
+    write_header(
+        dst,
+        "mini_redis_commands_total",
+        "counter",
+        "Number of times each command was applied.",
+    )?;
+    for (name, count) in stats.commands() {
+        writeln!(
+            dst,
+            "mini_redis_commands_total{{command=\"{}\"}} {}",
+            name, count
+        )?;
+    }
+
+    write_header(
+        dst,
+        "mini_redis_keys",
+        "gauge",
+        "Number of keys in each database.",
+    )?;
+    for (index, db) in dbs.iter().enumerate() {
+        writeln!(dst, "mini_redis_keys{{db=\"{}\"}} {}", index, db.len())?;
+    }
+
+    write_header(
+        dst,
+        "mini_redis_expired_keys_total",
+        "counter",
+        "Number of keys removed because they expired.",
+    )?;
+    writeln!(
+        dst,
+        "mini_redis_expired_keys_total {}",
+        stats.expired_keys()
+    )?;
+
+    write_header(
+        dst,
+        "mini_redis_reaped_keys_total",
+        "counter",
+        "Number of expired keys removed by the background task.",
+    )?;
+    writeln!(dst, "mini_redis_reaped_keys_total {}", stats.reaped_keys())?;
+
+    write_header(
+        dst,
+        "mini_redis_pending_expirations",
+        "gauge",
+        "Number of expirations tracked by each database.",
+    )?;
+    for (index, db) in dbs.iter().enumerate() {
+        writeln!(
+            dst,
+            "mini_redis_pending_expirations{{db=\"{}\"}} {}",
+            index,
+            db.pending_expirations()
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_header(dst: &mut String, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(dst, "# HELP {} {}", name, help)?;
+    writeln!(dst, "# TYPE {} {}", name, kind)
+}
@@ -1,6 +1,7 @@
 use crate::Frame;
 
 use bytes::Bytes;
+use std::convert::TryFrom;
 use std::{fmt, str, vec};
 
 /// Utility for parsing a command
@@ -52,24 +53,17 @@ impl Parse {
 
     /// Return the next entry as a string.
     ///
+    /// The entry is read as raw bytes, which must then be valid UTF-8. Use this
+    /// for keywords and names only; arguments that may hold arbitrary data
+    /// should be read with `next_bytes`.
+    ///
     /// If the next entry cannot be represented as a String, then an error is returned.
     pub(crate) fn next_string(&mut self) -> Result<String, ParseError> {
-        match self.next()? {
-            // Both `Simple` and `Bulk` representation may be strings. Strings
-            // are parsed to UTF-8.
-            //
-            // While errors are stored as strings, they are considered separate
-            // types.
-            Frame::Simple(s) => Ok(s),
-            Frame::Bulk(data) => str::from_utf8(&data[..])
-                .map(|s| s.to_string())
-                .map_err(|_| "protocol error; invalid string".into()),
-            frame => Err(format!(
-                "protocol error; expected simple frame or bulk frame, got {:?}",
-                frame
-            )
-            .into()),
-        }
+        let data = self.next_bytes()?;
+
+        str::from_utf8(&data[..])
+            .map(|s| s.to_string())
+            .map_err(|_| "protocol error; invalid string".into())
     }
 
     /// Return the next entry as raw bytes.
@@ -106,7 +100,7 @@ impl Parse {
 
         match self.next()? {
             // An integer frame type is already stored as an integer.
-            Frame::Integer(v) => Ok(v),
+            Frame::Integer(v) => u64::try_from(v).map_err(|_| MSG.into()),
             // Simple and bulk frames must be parsed as integers. If the parsing
             // fails, an error is returned.
             Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| MSG.into()),
@@ -115,6 +109,28 @@ impl Parse {
         }
     }
 
+    /// Return the next entry as a signed integer.
+    ///
+    /// Behaves like `next_int` but accepts negative values, as used for list
+    /// indices.
+    pub(crate) fn next_signed_int(&mut self) -> Result<i64, ParseError> {
+        use atoi::atoi;
+
+        const MSG: &str = "protocol error; invalid number";
+
+        match self.next()? {
+            Frame::Integer(v) => Ok(v),
+            Frame::Simple(data) => atoi::<i64>(data.as_bytes()).ok_or_else(|| MSG.into()),
+            Frame::Bulk(data) => atoi::<i64>(&data).ok_or_else(|| MSG.into()),
+            frame => Err(format!("protocol error; expected int frame but got {:?}", frame).into()),
+        }
+    }
+
+    /// Returns the number of entries left to parse.
+    pub(crate) fn remaining(&self) -> usize {
+        self.parts.len()
+    }
+
     /// Ensure there are no more entries in the array
     pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
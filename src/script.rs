@@ -0,0 +1,518 @@
+//! Scripts run by `EVAL`.
+//!
+//! Instead of Lua, scripts are written in a tiny expression language, enough
+//! to read a key and write it depending on its value without another client
+//! interleaving commands.
+//!
+//! A script is a list of expressions separated by `;`, evaluated in order.
+//! The value of the last one is the reply. An expression is one of:
+//!
+//! * `nil`, an integer such as `-3`, or a string quoted with `'` or `"`, in
+//!   which `\` escapes the next character.
+//! * `KEYS[n]` and `ARGV[n]`, the `n`th key name and argument given to
+//!   `EVAL`, starting at `1`.
+//! * An operation applied to expressions, such as `get(KEYS[1])`.
+//!
+//! The operations are:
+//!
+//! * `get(key)` returns the string stored at `key`, or `nil`.
+//! * `set(key, value)` sets `key` to `value`, discarding any time to live, and
+//!   returns `OK`.
+//! * `del(key)` removes `key`, and returns `1` if it existed, `0` otherwise.
+//! * `incr(key)` and `incrby(key, delta)` increment the integer stored at
+//!   `key` and return the new value.
+//! * `eq(a, b)` returns `1` if both values are equal, `0` otherwise. Integers
+//!   are compared as their decimal representation, `nil` only equals `nil`.
+//! * `if(condition, then[, else])` evaluates `then` only if `condition` is
+//!   neither `nil` nor `0`, `else`, or `nil` without it, otherwise.
+//!
+//! Any other operation is rejected when the script is parsed. Operation names
+//! are case insensitive.
+
+use crate::db::{Db, DbError, SetCondition};
+use crate::Frame;
+
+use bytes::Bytes;
+use std::cell::Cell;
+use std::fmt;
+
+/// How deeply operations can be nested in each other. This bounds the stack
+/// used to parse and evaluate a script.
+const MAX_DEPTH: usize = 64;
+
+/// A parsed script, ready to be run.
+#[derive(Debug)]
+pub(crate) struct Script {
+    /// Expressions evaluated in order, there is at least one
+    statements: Vec<Expr>,
+}
+
+/// Error returned when a script cannot be completed.
+///
+/// Writes applied by the script before the error are kept.
+#[derive(Debug)]
+pub(crate) enum Error {
+    /// The script used a value in a way it does not support, for instance an
+    /// argument that does not exist.
+    Runtime(String),
+
+    /// An operation failed, for instance because a key holds a value of the
+    /// wrong type.
+    Db(DbError),
+}
+
+#[derive(Debug)]
+enum Expr {
+    Nil,
+    Integer(i64),
+    String(Bytes),
+
+    /// `KEYS[n]`, with `n` starting at `1`
+    Key(usize),
+
+    /// `ARGV[n]`, with `n` starting at `1`
+    Arg(usize),
+
+    Call(Op, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Get,
+    Set,
+    Del,
+    Incr,
+    IncrBy,
+    Eq,
+    If,
+}
+
+/// The result of evaluating an expression.
+#[derive(Debug, PartialEq)]
+enum Value {
+    Nil,
+    Integer(i64),
+    String(Bytes),
+    Ok,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Integer(i64),
+    String(Bytes),
+    Open,
+    Close,
+    Comma,
+    Semicolon,
+    OpenBracket,
+    CloseBracket,
+}
+
+/// Parser state: the tokens of the script and the position of the next one.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+/// The keys and arguments a script is run with.
+struct Context<'a> {
+    db: &'a Db,
+    keys: &'a [Bytes],
+    args: &'a [Bytes],
+
+    /// Set once an operation wrote to `db`
+    wrote: Cell<bool>,
+}
+
+impl Script {
+    /// Parse the `source` of a script.
+    ///
+    /// Returns a description of the first error found, if any.
+    pub(crate) fn parse(source: &str) -> Result<Script, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+
+        let mut statements = vec![];
+
+        loop {
+            // Empty statements, including a trailing `;`, are allowed.
+            match parser.peek() {
+                None => break,
+                Some(Token::Semicolon) => {
+                    parser.pos += 1;
+                    continue;
+                }
+                Some(_) => statements.push(parser.expr(0)?),
+            }
+
+            match parser.next() {
+                None => break,
+                Some(Token::Semicolon) => {}
+                Some(token) => return Err(format!("unexpected {}", token)),
+            }
+        }
+
+        if statements.is_empty() {
+            return Err("script is empty".into());
+        }
+
+        Ok(Script { statements })
+    }
+
+    /// Run the script against `db` with the given `keys` and `args`, and
+    /// return the value of the last expression, along with whether the script
+    /// wrote to `db`, which it may have done even if it failed.
+    ///
+    /// The script runs to completion without yielding, the caller must keep
+    /// other connections from applying commands meanwhile for it to be atomic.
+    pub(crate) fn run(
+        &self,
+        db: &Db,
+        keys: &[Bytes],
+        args: &[Bytes],
+    ) -> (Result<Frame, Error>, bool) {
+        let cx = Context {
+            db,
+            keys,
+            args,
+            wrote: Cell::new(false),
+        };
+
+        let res = self
+            .statements
+            .iter()
+            .try_fold(Value::Nil, |_, statement| cx.eval(statement));
+
+        let res = res.map(|value| match value {
+            Value::Nil => Frame::Null,
+            Value::Integer(value) => Frame::Integer(value),
+            Value::String(value) => Frame::Bulk(value),
+            Value::Ok => Frame::Simple("OK".to_string()),
+        });
+
+        (res, cx.wrote.get())
+    }
+}
+
+impl Op {
+    fn from_name(name: &str) -> Option<Op> {
+        match &name.to_lowercase()[..] {
+            "get" => Some(Op::Get),
+            "set" => Some(Op::Set),
+            "del" => Some(Op::Del),
+            "incr" => Some(Op::Incr),
+            "incrby" => Some(Op::IncrBy),
+            "eq" => Some(Op::Eq),
+            "if" => Some(Op::If),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Op::Get => "get",
+            Op::Set => "set",
+            Op::Del => "del",
+            Op::Incr => "incr",
+            Op::IncrBy => "incrby",
+            Op::Eq => "eq",
+            Op::If => "if",
+        }
+    }
+
+    /// Returns the smallest and largest number of arguments of the operation.
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Op::Get | Op::Del | Op::Incr => (1, 1),
+            Op::Set | Op::IncrBy | Op::Eq => (2, 2),
+            Op::If => (2, 3),
+        }
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    /// Consume the next token, which must be `expected`.
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if *token == expected => Ok(()),
+            Some(token) => Err(format!("expected {}, found {}", expected, token)),
+            None => Err(format!(
+                "expected {}, found the end of the script",
+                expected
+            )),
+        }
+    }
+
+    /// Parse an expression nested in `depth` operations.
+    fn expr(&mut self, depth: usize) -> Result<Expr, String> {
+        if depth > MAX_DEPTH {
+            return Err("operations are nested too deeply".into());
+        }
+
+        let name = match self.next() {
+            Some(Token::Integer(value)) => return Ok(Expr::Integer(*value)),
+            Some(Token::String(value)) => return Ok(Expr::String(value.clone())),
+            Some(Token::Ident(name)) => name.clone(),
+            Some(token) => return Err(format!("unexpected {}", token)),
+            None => return Err("unexpected end of the script".into()),
+        };
+
+        match &name[..] {
+            "nil" => return Ok(Expr::Nil),
+            "KEYS" => return Ok(Expr::Key(self.index()?)),
+            "ARGV" => return Ok(Expr::Arg(self.index()?)),
+            _ => {}
+        }
+
+        let op = match Op::from_name(&name) {
+            Some(op) => op,
+            None => return Err(format!("unknown operation '{}'", name)),
+        };
+
+        self.expect(Token::Open)?;
+
+        let mut args = vec![];
+        if self.peek() != Some(&Token::Close) {
+            loop {
+                args.push(self.expr(depth + 1)?);
+
+                if self.peek() != Some(&Token::Comma) {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+
+        self.expect(Token::Close)?;
+
+        let (min, max) = op.arity();
+        if args.len() < min || args.len() > max {
+            return Err(format!(
+                "wrong number of arguments for '{}' operation",
+                op.name()
+            ));
+        }
+
+        Ok(Expr::Call(op, args))
+    }
+
+    /// Parse the `[n]` following `KEYS` or `ARGV`.
+    fn index(&mut self) -> Result<usize, String> {
+        self.expect(Token::OpenBracket)?;
+
+        let index = match self.next() {
+            Some(&Token::Integer(index)) if index >= 1 => index as usize,
+            _ => return Err("indices start at 1".into()),
+        };
+
+        self.expect(Token::CloseBracket)?;
+
+        Ok(index)
+    }
+}
+
+impl Context<'_> {
+    fn eval(&self, expr: &Expr) -> Result<Value, Error> {
+        let (op, args) = match expr {
+            Expr::Nil => return Ok(Value::Nil),
+            Expr::Integer(value) => return Ok(Value::Integer(*value)),
+            Expr::String(value) => return Ok(Value::String(value.clone())),
+            Expr::Key(index) => return lookup(self.keys, "KEYS", *index),
+            Expr::Arg(index) => return lookup(self.args, "ARGV", *index),
+            Expr::Call(op, args) => (*op, args),
+        };
+
+        let value = match op {
+            Op::Get => match self.db.get(&self.string(&args[0])?)? {
+                Some(value) => Value::String(value),
+                None => Value::Nil,
+            },
+            Op::Set => {
+                let key = self.string(&args[0])?;
+                let value = self.string(&args[1])?;
+                self.db.set_if(key, value, None, SetCondition::Always)?;
+                self.wrote.set(true);
+                Value::Ok
+            }
+            Op::Del => {
+                let removed = self.db.remove(&self.string(&args[0])?);
+                self.wrote.set(self.wrote.get() || removed);
+                Value::Integer(removed as i64)
+            }
+            Op::Incr => {
+                let value = self.db.incr_by(&self.string(&args[0])?, 1)?;
+                self.wrote.set(true);
+                Value::Integer(value)
+            }
+            Op::IncrBy => {
+                let key = self.string(&args[0])?;
+                let delta = self.integer(&args[1])?;
+                let value = self.db.incr_by(&key, delta)?;
+                self.wrote.set(true);
+                Value::Integer(value)
+            }
+            Op::Eq => {
+                let a = self.eval(&args[0])?;
+                let b = self.eval(&args[1])?;
+                Value::Integer((as_bytes(&a) == as_bytes(&b)) as i64)
+            }
+            Op::If => {
+                let condition = self.eval(&args[0])?;
+                if !matches!(condition, Value::Nil | Value::Integer(0)) {
+                    self.eval(&args[1])?
+                } else if let Some(otherwise) = args.get(2) {
+                    self.eval(otherwise)?
+                } else {
+                    Value::Nil
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Evaluate `expr` as a string, such as a key name.
+    fn string(&self, expr: &Expr) -> Result<Bytes, Error> {
+        match as_bytes(&self.eval(expr)?) {
+            Some(value) => Ok(value),
+            None => Err(Error::Runtime("expected a string, got nil".into())),
+        }
+    }
+
+    /// Evaluate `expr` as an integer, parsing strings.
+    fn integer(&self, expr: &Expr) -> Result<i64, Error> {
+        let value = match self.eval(expr)? {
+            Value::Integer(value) => Some(value),
+            Value::String(value) => std::str::from_utf8(&value)
+                .ok()
+                .and_then(|value| value.parse().ok()),
+            _ => None,
+        };
+
+        value.ok_or_else(|| Error::Runtime("value is not an integer".into()))
+    }
+}
+
+/// Returns the `index`th entry of `values`, starting at `1`.
+fn lookup(values: &[Bytes], name: &str, index: usize) -> Result<Value, Error> {
+    match values.get(index - 1) {
+        Some(value) => Ok(Value::String(value.clone())),
+        None => Err(Error::Runtime(format!(
+            "{}[{}] is out of range",
+            name, index
+        ))),
+    }
+}
+
+/// Returns the string representation of `value`, `None` for `nil`.
+fn as_bytes(value: &Value) -> Option<Bytes> {
+    match value {
+        Value::Nil => None,
+        Value::Integer(value) => Some(Bytes::from(value.to_string())),
+        Value::String(value) => Some(value.clone()),
+        Value::Ok => Some(Bytes::from_static(b"OK")),
+    }
+}
+
+/// Split the `source` of a script into tokens.
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '\'' | '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => value.push(escaped),
+                            None => return Err("unterminated string".into()),
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err("unterminated string".into()),
+                    }
+                }
+                Token::String(Bytes::from(value))
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+                match digits.parse() {
+                    Ok(value) => Token::Integer(value),
+                    Err(_) => return Err(format!("invalid integer '{}'", digits)),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    name.push(c);
+                    chars.next();
+                }
+                Token::Ident(name)
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(fmt, "'{}'", name),
+            Token::Integer(value) => write!(fmt, "'{}'", value),
+            Token::String(_) => "a string".fmt(fmt),
+            Token::Open => "'('".fmt(fmt),
+            Token::Close => "')'".fmt(fmt),
+            Token::Comma => "','".fmt(fmt),
+            Token::Semicolon => "';'".fmt(fmt),
+            Token::OpenBracket => "'['".fmt(fmt),
+            Token::CloseBracket => "']'".fmt(fmt),
+        }
+    }
+}
+
+impl From<DbError> for Error {
+    fn from(err: DbError) -> Error {
+        Error::Db(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Runtime(msg) => write!(fmt, "ERR Error running script: {}", msg),
+            Error::Db(err) => err.fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
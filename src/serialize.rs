@@ -0,0 +1,224 @@
+//! Serialization of individual values.
+//!
+//! `DUMP` serializes the value stored at a key, `RESTORE` recreates a key from
+//! such a blob. The encoding of values is shared with snapshots.
+//!
+//! # Format
+//!
+//! A blob starts with a version byte, followed by the type of the value, its
+//! time to live and the value itself:
+//!
+//! * Strings are prefixed by their length as a `u32`.
+//! * Lists and sets are prefixed by their number of elements as a `u32`,
+//!   followed by each element as a string.
+//! * Hashes are prefixed by their number of fields as a `u32`, followed by
+//!   each field name and value as strings.
+//! * Sorted sets are prefixed by their number of members as a `u32`, followed
+//!   by each member as a string and its score as an `f64`.
+//!
+//! The time to live is a `u8` flag, followed by the remaining time in
+//! milliseconds as a `u64` if the flag is set. All integers are big endian.
+//! The format only needs to round-trip within `mini-redis`; the version is
+//! bumped whenever it changes.
+
+use crate::db::Value;
+use crate::zset::SortedSet;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::time::Duration;
+
+const VERSION: u8 = 1;
+
+pub(crate) const TYPE_STRING: u8 = 0;
+pub(crate) const TYPE_LIST: u8 = 1;
+pub(crate) const TYPE_HASH: u8 = 2;
+pub(crate) const TYPE_SET: u8 = 3;
+pub(crate) const TYPE_SORTED_SET: u8 = 4;
+
+/// Serialize `value`, along with its remaining time to live.
+pub(crate) fn dump(value: &Value, ttl: Option<Duration>) -> crate::Result<Bytes> {
+    let mut buf = BytesMut::new();
+    buf.put_u8(VERSION);
+    buf.put_u8(value_type(value));
+
+    match ttl {
+        Some(ttl) => {
+            buf.put_u8(1);
+            buf.put_u64(u64::try_from(ttl.as_millis())?);
+        }
+        None => buf.put_u8(0),
+    }
+
+    put_value(&mut buf, value)?;
+
+    Ok(buf.freeze())
+}
+
+/// Deserialize a blob produced by `dump`, returning the value and its time to
+/// live.
+pub(crate) fn restore(mut src: Bytes) -> crate::Result<(Value, Option<Duration>)> {
+    let version = get_u8(&mut src)?;
+    if version != VERSION {
+        return Err(format!("unsupported serialization version {}", version).into());
+    }
+
+    let kind = get_u8(&mut src)?;
+
+    let ttl = match get_u8(&mut src)? {
+        0 => None,
+        1 => Some(Duration::from_millis(get_u64(&mut src)?)),
+        flag => return Err(format!("invalid time to live flag {}", flag).into()),
+    };
+
+    let value = get_value(&mut src, kind)?;
+
+    if src.has_remaining() {
+        return Err("trailing bytes after serialized value".into());
+    }
+
+    Ok((value, ttl))
+}
+
+/// Returns the tag identifying the type of `value`.
+pub(crate) fn value_type(value: &Value) -> u8 {
+    match value {
+        Value::String(_) => TYPE_STRING,
+        Value::List(_) => TYPE_LIST,
+        Value::Hash(_) => TYPE_HASH,
+        Value::Set(_) => TYPE_SET,
+        Value::SortedSet(_) => TYPE_SORTED_SET,
+    }
+}
+
+/// Encode `value`, without its type.
+pub(crate) fn put_value(dst: &mut BytesMut, value: &Value) -> crate::Result<()> {
+    match value {
+        Value::String(data) => put_bytes(dst, data)?,
+        Value::List(list) => {
+            dst.put_u32(u32::try_from(list.len())?);
+            for data in list {
+                put_bytes(dst, data)?;
+            }
+        }
+        Value::Hash(hash) => {
+            dst.put_u32(u32::try_from(hash.len())?);
+            for (field, data) in hash {
+                put_bytes(dst, field)?;
+                put_bytes(dst, data)?;
+            }
+        }
+        Value::Set(set) => {
+            dst.put_u32(u32::try_from(set.len())?);
+            for member in set {
+                put_bytes(dst, member)?;
+            }
+        }
+        Value::SortedSet(zset) => {
+            dst.put_u32(u32::try_from(zset.len())?);
+            for (member, score) in zset.iter() {
+                put_bytes(dst, member)?;
+                dst.put_f64(score);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode a value of type `kind`, as encoded by `put_value`.
+pub(crate) fn get_value(src: &mut Bytes, kind: u8) -> crate::Result<Value> {
+    let value = match kind {
+        TYPE_STRING => Value::String(get_bytes(src)?),
+        TYPE_LIST => {
+            let len = get_u32(src)?;
+
+            let mut list = VecDeque::new();
+            for _ in 0..len {
+                list.push_back(get_bytes(src)?);
+            }
+
+            Value::List(list)
+        }
+        TYPE_HASH => {
+            let len = get_u32(src)?;
+
+            let mut hash = HashMap::new();
+            for _ in 0..len {
+                let field = get_bytes(src)?;
+                hash.insert(field, get_bytes(src)?);
+            }
+
+            Value::Hash(hash)
+        }
+        TYPE_SET => {
+            let len = get_u32(src)?;
+
+            let mut set = HashSet::new();
+            for _ in 0..len {
+                set.insert(get_bytes(src)?);
+            }
+
+            Value::Set(set)
+        }
+        TYPE_SORTED_SET => {
+            let len = get_u32(src)?;
+
+            let mut zset = SortedSet::new();
+            for _ in 0..len {
+                let member = get_bytes(src)?;
+                let score = f64::from_bits(get_u64(src)?);
+                if score.is_nan() {
+                    return Err("invalid sorted set score".into());
+                }
+                zset.insert(member, score);
+            }
+
+            Value::SortedSet(zset)
+        }
+        kind => return Err(format!("invalid value type {}", kind).into()),
+    };
+
+    Ok(value)
+}
+
+pub(crate) fn put_bytes(dst: &mut BytesMut, data: &[u8]) -> crate::Result<()> {
+    dst.put_u32(u32::try_from(data.len())?);
+    dst.put_slice(data);
+    Ok(())
+}
+
+pub(crate) fn get_u8(src: &mut Bytes) -> crate::Result<u8> {
+    if !src.has_remaining() {
+        return Err("serialized data is truncated".into());
+    }
+
+    Ok(src.get_u8())
+}
+
+pub(crate) fn get_u32(src: &mut Bytes) -> crate::Result<u32> {
+    if src.remaining() < 4 {
+        return Err("serialized data is truncated".into());
+    }
+
+    Ok(src.get_u32())
+}
+
+pub(crate) fn get_u64(src: &mut Bytes) -> crate::Result<u64> {
+    if src.remaining() < 8 {
+        return Err("serialized data is truncated".into());
+    }
+
+    Ok(src.get_u64())
+}
+
+pub(crate) fn get_bytes(src: &mut Bytes) -> crate::Result<Bytes> {
+    let len = get_u32(src)? as usize;
+
+    if src.remaining() < len {
+        return Err("serialized data is truncated".into());
+    }
+
+    Ok(src.split_to(len))
+}
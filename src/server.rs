@@ -1,33 +1,320 @@
 //! Minimal Redis server implementation
 //!
 //! Provides an async `run` function that listens for inbound connections,
-//! spawning a task per connection.
+//! spawning a task per connection. [`Server`] runs it in a background task.
 
-use crate::{Command, Connection, Db, DbDropGuard, Shutdown};
+use crate::aof::Aof;
+use crate::cmd::{
+    resume_subscribed, Auth, ClientReply, ClientSetName, Eval, LPop, ListEnd, Monitor, RPop,
+    ReplyMode, Reset, Select, SubscribeExit, Subscriptions,
+};
+use crate::connection::{DEFAULT_MAX_FRAME_SIZE, DEFAULT_WRITE_BUFFER_SIZE};
+use crate::db::ClientGuard;
+use crate::{frame, metrics, snapshot, Command, Connection, Db, DbDropGuard, Frame, Shutdown};
 
+use bytes::Bytes;
+use socket2::{SockRef, TcpKeepalive};
+use std::collections::HashSet;
+use std::fmt;
 use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, instrument};
+use tokio::sync::{broadcast, mpsc, oneshot, Notify, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration, Instant};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, field, info, instrument, warn, Span};
+
+/// Server configuration, set at startup.
+///
+/// `Config::default()` matches the behavior of [`run`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Password clients must supply with `AUTH` before issuing other
+    /// commands. `None` disables authentication.
+    pub requirepass: Option<String>,
+
+    /// Number of logical databases clients can switch between with `SELECT`.
+    /// At least one database is always created.
+    pub databases: usize,
+
+    /// Number of shards the keys of each database are spread over. Each shard
+    /// has its own lock, so commands on keys of different shards can be
+    /// applied in parallel. Defaults to the number of CPUs, and is at most
+    /// 65536.
+    pub shards: usize,
+
+    /// Maximum number of bytes the keys and values of each database may use.
+    /// Once exceeded, the least recently used keys are evicted. `None` disables
+    /// the limit.
+    pub maxmemory: Option<usize>,
+
+    /// What to do once `maxmemory` is exceeded.
+    pub maxmemory_policy: MaxMemoryPolicy,
+
+    /// Path of the snapshot written by `SAVE`. If the file exists when the
+    /// server starts, the databases are loaded from it. `None` disables
+    /// persistence.
+    pub dbfilename: Option<PathBuf>,
+
+    /// Path of the append-only file. When set, every write command is
+    /// appended to the file, and the file is replayed on startup instead of
+    /// loading the snapshot. `None` disables the append-only file.
+    pub appendfilename: Option<PathBuf>,
+
+    /// How often the append-only file is synced to disk.
+    pub appendfsync: AppendFsync,
+
+    /// Number of messages each pub/sub channel buffers for its slowest
+    /// subscriber. Once full, the oldest messages are dropped, and subscribers
+    /// that did not receive them are notified.
+    ///
+    /// A larger capacity tolerates longer bursts of messages, or slower
+    /// subscribers, but the buffer of each channel is allocated when the
+    /// channel is created, and holds messages until every subscriber received
+    /// them. It can be changed at runtime with `CONFIG SET channel-capacity`,
+    /// and for a single channel with `PUBSUB CAPACITY`, which only affects
+    /// channels created from then on.
+    pub channel_capacity: usize,
+
+    /// Largest bulk string, or inline command, accepted from clients, in bytes.
+    /// Clients sending a larger one receive a protocol error and are
+    /// disconnected. A bulk string announcing more is rejected before it is
+    /// received. It can be changed at runtime with
+    /// `CONFIG SET proto-max-bulk-len`, down to 1mb.
+    pub max_frame_size: usize,
+
+    /// Largest number of arguments accepted in a request. Clients sending a
+    /// request announcing more receive a protocol error and are disconnected,
+    /// before the arguments are received.
+    pub max_array_len: usize,
+
+    /// Size of the buffer replies are encoded to before being written to the
+    /// socket, in bytes. Replies to commands are written as soon as they are
+    /// complete, messages received by subscribers are written in batches. `0`
+    /// writes every reply as soon as it is encoded.
+    pub write_buffer_size: usize,
+
+    /// Accept the `DEBUG` command, used to test clients. It is refused by
+    /// default.
+    pub enable_debug_command: bool,
+
+    /// Accept the `SHUTDOWN` command, letting any client stop the server. It
+    /// is refused by default.
+    pub enable_shutdown_command: bool,
+
+    /// Names of the commands clients may send, case insensitive, for instance
+    /// only read commands to lock a deployment down. Other commands are
+    /// refused with a `NOPERM` error, except `AUTH`, `HELLO` and `QUIT`, which
+    /// are always accepted. `None`, the default, accepts every command. It can
+    /// be changed at runtime with `CONFIG SET allowed-commands`, given a
+    /// comma-separated list of names, or `*` to accept every command.
+    pub allowed_commands: Option<HashSet<String>>,
+
+    /// Close connections that send no command for this long. Connections
+    /// waiting on subscriptions or blocking commands are not idle. `None`
+    /// keeps idle connections open.
+    pub timeout: Option<Duration>,
+
+    /// Set `TCP_NODELAY` on accepted connections, so that replies are sent as
+    /// soon as they are written instead of being delayed to be coalesced with
+    /// later ones. Enabled by default, as request/response latency matters
+    /// more than packet counts for Redis-like workloads.
+    pub tcp_nodelay: bool,
+
+    /// Send TCP keepalive probes once a connection has been idle for this
+    /// long, so that peers that went away without closing their connection
+    /// are detected. Unlike `timeout`, responsive peers are never
+    /// disconnected. `None` disables keepalive probes.
+    pub tcp_keepalive: Option<Duration>,
+
+    /// How long to wait on shutdown for the connections to finish the
+    /// commands they are applying. Once elapsed, the server returns without
+    /// waiting for the remaining connections. `None` waits for all of them.
+    pub shutdown_timeout: Option<Duration>,
+
+    /// Which changes to the keyspace are published to pub/sub subscribers.
+    pub notify_keyspace_events: KeyspaceEvents,
+
+    /// TLS configuration. When set, connections are encrypted with TLS, and
+    /// peers that do not perform a TLS handshake are refused. `None` accepts
+    /// plaintext connections.
+    pub tls: Option<Arc<ServerConfig>>,
+
+    /// Address of the metrics endpoint. When set, the server also listens on
+    /// this address and serves its statistics to `GET /metrics` requests, in
+    /// the Prometheus text format. `None` disables the endpoint.
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Commands taking at least this long to apply are recorded in the slow
+    /// log, reported by `SLOWLOG`. Time spent blocked, by `BLPOP` for example,
+    /// is not counted. `None` disables the slow log.
+    pub slowlog_log_slower_than: Option<Duration>,
+
+    /// Maximum number of commands kept in the slow log. Once reached, the
+    /// oldest entry is dropped for each new one.
+    pub slowlog_max_len: usize,
+}
+
+/// How often the append-only file is synced to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppendFsync {
+    /// Sync after every write command. Slow, but no acknowledged write is
+    /// lost.
+    Always,
+
+    /// Sync once per second. Up to a second of writes may be lost if the
+    /// server crashes.
+    #[default]
+    EverySec,
+}
+
+/// What a database does once its keys and values use more than `maxmemory`
+/// bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxMemoryPolicy {
+    /// Evict the least recently used keys to make room for new writes.
+    #[default]
+    AllKeysLru,
+
+    /// Evict the least frequently used keys to make room for new writes.
+    ///
+    /// Frequencies are approximate, logarithmic counters which decay while a
+    /// key is not accessed, as reported by `OBJECT FREQ`. Finding the victim
+    /// visits every key, which makes evicting slower than with `AllKeysLru`.
+    AllKeysLfu,
+
+    /// Keep every key, and reject write commands until memory is freed.
+    NoEviction,
+}
+
+/// Which changes to the keyspace are published to pub/sub subscribers, as
+/// set with `notify-keyspace-events` in Redis.
+///
+/// For every change, the name of the event is published to
+/// `__keyspace@<db>__:<key>` and the key to `__keyevent@<db>__:<event>`.
+/// Either kind of channel can be enabled independently, and nothing is
+/// published unless at least one is. The default publishes nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyspaceEvents {
+    /// Publish to `__keyspace@<db>__:<key>` channels
+    keyspace: bool,
+
+    /// Publish to `__keyevent@<db>__:<event>` channels
+    keyevent: bool,
+
+    /// Enabled classes of events, one bit per `EventClass`
+    classes: u16,
+}
+
+/// Handle to a server running in a background task.
+///
+/// The server listens on an ephemeral port of the loopback interface, which is
+/// convenient for tests and for embedding `mini-redis` in another program.
+/// Dropping the handle shuts the server down in the background; use
+/// [`Server::shutdown`] to wait for the shutdown to complete.
+#[derive(Debug)]
+pub struct Server {
+    /// Address the server listens on
+    addr: SocketAddr,
+
+    /// Dropped to signal the server to shut down
+    shutdown: oneshot::Sender<()>,
+
+    /// Task running the server
+    task: JoinHandle<()>,
+
+    /// Databases of the server, indexed by database number
+    dbs: Vec<Db>,
+}
+
+/// Classes of keyspace events, enabled separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EventClass {
+    /// Commands that apply to any type of value, such as `DEL` and `EXPIRE`
+    Generic,
+
+    /// String commands
+    String,
+
+    /// List commands
+    List,
+
+    /// Hash commands
+    Hash,
+
+    /// Set commands
+    Set,
+
+    /// Sorted set commands
+    SortedSet,
+
+    /// Keys removed because they expired
+    Expired,
+
+    /// Keys evicted to stay within `maxmemory`
+    Evicted,
+}
+
+/// Flag enabling each `EventClass` in a `notify-keyspace-events` setting,
+/// indexed by class.
+const EVENT_CLASS_FLAGS: &[u8] = b"g$lhszxe";
+
+/// Number of databases created by default, as in Redis.
+const DEFAULT_DATABASES: usize = 16;
+
+/// Number of messages pub/sub channels buffer by default.
+const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Number of arguments accepted in a request by default, as many as Redis
+/// accepts from clients that did not authenticate.
+const DEFAULT_MAX_ARRAY_LEN: usize = 1024 * 1024;
+
+/// Commands taking at least this long are recorded in the slow log by
+/// default, as in Redis.
+const DEFAULT_SLOWLOG_LOG_SLOWER_THAN: Duration = Duration::from_millis(10);
+
+/// Number of commands kept in the slow log by default, as in Redis.
+const DEFAULT_SLOWLOG_MAX_LEN: usize = 128;
+
+/// Number of arguments of a command recorded in the slow log, as in Redis.
+/// The arguments past the limit are summarized.
+const SLOWLOG_MAX_ARGC: usize = 32;
+
+/// Number of bytes of each argument recorded in the slow log, as in Redis.
+const SLOWLOG_MAX_ARG_LEN: usize = 128;
 
 /// Server listener state. Created in the `run` call. It includes a `run` method
 /// which performs the TCP listening and initialization of per-connection state.
 #[derive(Debug)]
 struct Listener {
-    /// Shared database handle.
+    /// Shared database handles.
     ///
-    /// Contains the key / value store as well as the broadcast channels for
+    /// Contains the key / value stores as well as the broadcast channels for
     /// pub/sub.
     ///
-    /// This holds a wrapper around an `Arc`. The internal `Db` can be
+    /// This holds a wrapper around `Arc`s. The internal `Db`s can be
     /// retrieved and passed into the per connection state (`Handler`).
     db_holder: DbDropGuard,
 
     /// TCP listener supplied by the `run` caller.
     listener: TcpListener,
 
+    /// Configuration supplied by the `run_with_config` caller, shared with
+    /// every connection handler.
+    config: Arc<Config>,
+
+    /// Append-only file, if enabled, shared with every connection handler.
+    aof: Option<Arc<Aof>>,
+
     /// Limit the max number of connections.
     ///
     /// A `Semaphore` is used to limit the max number of connections. Before
@@ -61,18 +348,27 @@ struct Listener {
     /// `shutdown_complete_rx.recv()` completing with `None`. At this point, it
     /// is safe to exit the server process.
     shutdown_complete_tx: mpsc::Sender<()>,
+
+    /// Handed to connection handlers, which send to it to stop the server on
+    /// `SHUTDOWN`. The server selects on the receiver along with the
+    /// `shutdown` future.
+    shutdown_request_tx: mpsc::Sender<()>,
 }
 
 /// Per-connection handler. Reads requests from `connection` and applies the
 /// commands to `db`.
 #[derive(Debug)]
 struct Handler {
-    /// Shared database handle.
+    /// Shared database handles, indexed by database number.
     ///
-    /// When a command is received from `connection`, it is applied with `db`.
-    /// The implementation of the command is in the `cmd` module. Each command
-    /// will need to interact with `db` in order to complete the work.
-    db: Db,
+    /// When a command is received from `connection`, it is applied with the
+    /// selected database. The implementation of the command is in the `cmd`
+    /// module. Each command will need to interact with the database in order
+    /// to complete the work.
+    dbs: Vec<Db>,
+
+    /// Index into `dbs` of the database selected with `SELECT`.
+    db_index: usize,
 
     /// The TCP connection decorated with the redis protocol encoder / decoder
     /// implemented using a buffered `TcpStream`.
@@ -93,10 +389,66 @@ struct Handler {
     /// which point the connection is terminated.
     shutdown: Shutdown,
 
+    /// Server configuration.
+    config: Arc<Config>,
+
+    /// Whether the peer may issue commands. Connections start authenticated
+    /// only if the server does not require a password.
+    authenticated: bool,
+
+    /// Append-only file write commands are logged to, if enabled.
+    aof: Option<Arc<Aof>>,
+
+    /// Name of the connection, set with `CLIENT SETNAME`.
+    ///
+    /// It is recorded in the span of `Handler::run`, so that the logs of the
+    /// connection can be told apart.
+    name: Option<String>,
+
+    /// Registration of the connection, listing it in `CLIENT LIST` until the
+    /// handler is dropped. `None` when replaying the append-only file.
+    client: Option<ClientGuard>,
+
+    /// Commands queued since `MULTI` was received.
+    ///
+    /// `None` when no transaction is open. Transactions are per-connection
+    /// state, so they live in the handler rather than in the shared `Db`.
+    transaction: Option<Transaction>,
+
+    /// Subscriptions of a RESP3 peer that sent another command while
+    /// subscribed.
+    ///
+    /// The command is applied like any other, then the subscribed state is
+    /// resumed with these subscriptions.
+    subscriptions: Option<Subscriptions>,
+
+    /// Frame received in the subscribed state, applied before reading the next
+    /// one.
+    pending: Option<Frame>,
+
+    /// Whether commands are replied, as set with `CLIENT REPLY`.
+    reply_mode: ReplyMode,
+
+    /// Requests the server to shut down, on `SHUTDOWN`.
+    shutdown_request: mpsc::Sender<()>,
+
     /// Not used directly. Instead, when `Handler` is dropped...?
     _shutdown_complete: mpsc::Sender<()>,
 }
 
+/// A transaction opened with `MULTI`.
+#[derive(Debug, Default)]
+struct Transaction {
+    /// Commands to apply on `EXEC`, in the order they were received. Write
+    /// commands are queued along with their frame when the append-only file
+    /// is enabled, in order to log them.
+    commands: Vec<(Command, Option<Frame>)>,
+
+    /// Set when a command could not be queued. `EXEC` then discards the
+    /// transaction instead of applying it.
+    failed: bool,
+}
+
 /// Maximum number of concurrent connections the redis server will accept.
 ///
 /// When this limit is reached, the server will stop accepting connections until
@@ -121,6 +473,42 @@ const MAX_CONNECTIONS: usize = 250;
 /// `tokio::signal::ctrl_c()` can be used as the `shutdown` argument. This will
 /// listen for a SIGINT signal.
 pub async fn run(listener: TcpListener, shutdown: impl Future) {
+    run_with_config(listener, shutdown, Config::default()).await
+}
+
+/// Apply the socket options of `config` to `socket`, a newly accepted
+/// connection: `TCP_NODELAY` as set by `tcp_nodelay`, and keepalive probes
+/// after `tcp_keepalive`.
+///
+/// The server does this for every connection it accepts, before reading from
+/// it.
+pub fn set_socket_options(socket: &TcpStream, config: &Config) -> io::Result<()> {
+    socket.set_nodelay(config.tcp_nodelay)?;
+
+    if let Some(idle) = config.tcp_keepalive {
+        let keepalive = TcpKeepalive::new().with_time(idle);
+        SockRef::from(socket).set_tcp_keepalive(&keepalive)?;
+    }
+
+    Ok(())
+}
+
+/// Run the mini-redis server with the given `config`.
+///
+/// Behaves like [`run`] otherwise.
+pub async fn run_with_config(listener: TcpListener, shutdown: impl Future, config: Config) {
+    let db_holder = DbDropGuard::new(&config);
+    serve(listener, shutdown, config, db_holder).await
+}
+
+/// Run the server with the databases held by `db_holder`, see
+/// [`run_with_config`].
+async fn serve(
+    listener: TcpListener,
+    shutdown: impl Future,
+    config: Config,
+    db_holder: DbDropGuard,
+) {
     // When the provided `shutdown` future completes, we must send a shutdown
     // message to all active connections. We use a broadcast channel for this
     // purpose. The call below ignores the receiver of the broadcast pair, and when
@@ -129,13 +517,73 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
 
+    // Connections may also ask the server to shut down, with `SHUTDOWN`.
+    let (shutdown_request_tx, mut shutdown_request_rx) = mpsc::channel(1);
+
+    let config = Arc::new(config);
+
+    // Restore the databases before accepting connections, so no client
+    // observes a partially loaded keyspace. The append-only file holds the
+    // most recent writes, so it takes precedence over the snapshot.
+    if let Some(path) = &config.appendfilename {
+        match replay_aof(db_holder.dbs(), config.clone(), path).await {
+            Ok(commands) => info!(path = %path.display(), commands, "replayed append-only file"),
+            Err(err) => {
+                warn!(path = %path.display(), cause = %err, "failed to replay append-only file")
+            }
+        }
+    } else if let Some(path) = &config.dbfilename {
+        match snapshot::load(&db_holder.dbs(), path).await {
+            Ok(keys) => info!(path = %path.display(), keys, "loaded snapshot"),
+            Err(err) => match err.downcast_ref::<io::Error>() {
+                Some(err) if err.kind() == io::ErrorKind::NotFound => {
+                    warn!(path = %path.display(), "snapshot not found, starting empty")
+                }
+                _ => {
+                    warn!(path = %path.display(), cause = %err, "failed to load snapshot, starting empty")
+                }
+            },
+        }
+    }
+
+    let aof = match &config.appendfilename {
+        Some(path) => match Aof::open(path, config.appendfsync).await {
+            Ok(aof) => Some(aof),
+            Err(err) => {
+                error!(path = %path.display(), cause = %err, "failed to open append-only file");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if let Some(addr) = config.metrics_addr {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                tokio::spawn(metrics::serve(
+                    listener,
+                    db_holder.dbs(),
+                    Shutdown::new(notify_shutdown.subscribe()),
+                    shutdown_complete_tx.clone(),
+                ));
+            }
+            Err(err) => {
+                error!(%addr, cause = %err, "failed to bind metrics endpoint");
+                return;
+            }
+        }
+    }
+
     // Initialize the listener state
     let mut server = Listener {
         listener,
-        db_holder: DbDropGuard::new(),
+        db_holder,
+        config,
+        aof,
         limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
         notify_shutdown,
         shutdown_complete_tx,
+        shutdown_request_tx,
     };
 
     // Concurrently run the server and listen for the `shutdown` signal. The
@@ -173,17 +621,27 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
             // The shutdown signal has been received.
             info!("shutting down");
         }
+        _ = shutdown_request_rx.recv() => {
+            info!("shutting down on request of a client");
+        }
     }
 
     // Extract the `shutdown_complete` receiver and transmitter
     // explicitly drop `shutdown_transmitter`. This is important, as the
     // `.await` below would otherwise never complete.
     let Listener {
+        listener,
+        db_holder,
+        config,
         shutdown_complete_tx,
         notify_shutdown,
+        aof,
         ..
     } = server;
 
+    // Stop accepting connections.
+    drop(listener);
+
     // When `notify_shutdown` is dropped, all tasks which have `subscribe`d will
     // receive the shutdown signal and can exit
     drop(notify_shutdown);
@@ -193,8 +651,435 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     // Wait for all active connections to finish processing. As the `Sender`
     // handle held by the listener has been dropped above, the only remaining
     // `Sender` instances are held by connection handler tasks. When those drop,
-    // the `mpsc` channel will close and `recv()` will return `None`.
-    let _ = shutdown_complete_rx.recv().await;
+    // the `mpsc` channel will close and `recv()` will return `None`. Past the
+    // shutdown timeout, if any, the remaining connections are left behind.
+    match config.shutdown_timeout {
+        Some(timeout) => {
+            if time::timeout(timeout, shutdown_complete_rx.recv())
+                .await
+                .is_err()
+            {
+                let connections = db_holder.dbs()[0].stats().connected_clients();
+                warn!(
+                    connections,
+                    "shutdown timed out, closing active connections"
+                );
+            }
+        }
+        None => {
+            let _ = shutdown_complete_rx.recv().await;
+        }
+    }
+
+    // No more commands can be logged, make sure the buffered ones reach the
+    // disk.
+    if let Some(aof) = aof {
+        if let Err(err) = aof.sync().await {
+            error!(cause = %err, "failed to sync append-only file");
+        }
+    }
+}
+
+impl Server {
+    /// Start a server with the default configuration.
+    pub async fn start() -> crate::Result<Server> {
+        Server::start_with_config(Config::default()).await
+    }
+
+    /// Start a server with the given `config`.
+    ///
+    /// Returns once the server is bound, it may still be loading its
+    /// databases from disk when the first connection arrives.
+    pub async fn start_with_config(config: Config) -> crate::Result<Server> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let db_holder = DbDropGuard::new(&config);
+        let dbs = db_holder.dbs();
+
+        // The receiver completes once the sender is sent to or dropped.
+        let (shutdown, stopped) = oneshot::channel();
+        let task = tokio::spawn(serve(listener, stopped, config, db_holder));
+
+        Ok(Server {
+            addr,
+            shutdown,
+            task,
+            dbs,
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns a copy of every key of database `db` holding a string, along
+    /// with its value and remaining time to live, in no particular order.
+    ///
+    /// The copy is taken at a single point in time, commands applied while it
+    /// is taken wait for it, and later commands do not affect it. Expired keys
+    /// are left out. Every key and value is copied, so for large keyspaces,
+    /// walking the keys with `SCAN` from a client is cheaper.
+    ///
+    /// # Panics
+    ///
+    /// panics if the server has no database `db`
+    pub fn snapshot(&self, db: usize) -> Vec<(Bytes, Bytes, Option<Duration>)> {
+        self.dbs[db].snapshot()
+    }
+
+    /// Shut the server down and wait for it to stop.
+    ///
+    /// Connections are closed gracefully, as when the `shutdown` future given
+    /// to [`run`] completes.
+    pub async fn shutdown(self) {
+        let Server { shutdown, task, .. } = self;
+        drop(shutdown);
+
+        if let Err(err) = task.await {
+            if err.is_panic() {
+                std::panic::resume_unwind(err.into_panic());
+            }
+        }
+    }
+}
+
+/// Rebuild the databases by applying every command logged in the append-only
+/// file at `path`. Returns the number of commands applied.
+///
+/// The commands are applied by a connection handler reading from the file, so
+/// they go through the same dispatch as commands received from peers. A
+/// missing file is not an error, there is nothing to replay.
+async fn replay_aof(dbs: Vec<Db>, config: Arc<Config>, path: &Path) -> crate::Result<usize> {
+    let file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    let mut log = Connection::from_stream(file);
+
+    // Replies are captured and discarded, so the handler's connection is never
+    // written to.
+    let (stream, _) = tokio::io::duplex(64);
+    let (notify_shutdown, _) = broadcast::channel(1);
+    let (shutdown_complete_tx, _) = mpsc::channel(1);
+    let (shutdown_request, _) = mpsc::channel(1);
+
+    let mut handler = Handler {
+        dbs,
+        db_index: 0,
+        connection: Connection::from_stream(stream),
+        shutdown: Shutdown::new(notify_shutdown.subscribe()),
+        config,
+        authenticated: true,
+        // Replayed commands must not be logged again.
+        aof: None,
+        name: None,
+        client: None,
+        transaction: None,
+        subscriptions: None,
+        pending: None,
+        reply_mode: ReplyMode::On,
+        shutdown_request,
+        _shutdown_complete: shutdown_complete_tx,
+    };
+
+    let mut commands = 0;
+
+    while let Some(frame) = log.read_frame().await? {
+        let cmd = Command::from_frame(frame)?;
+
+        handler.connection.start_capture();
+        handler.apply(cmd).await?;
+        handler.connection.finish_capture();
+
+        commands += 1;
+    }
+
+    Ok(commands)
+}
+
+/// Format the command received as `frame` for the connections in `MONITOR`
+/// mode: the current time, the database and address of the peer, then the
+/// quoted command name and arguments, as Redis does.
+///
+/// Passwords are not shown, see `is_secret`.
+fn monitor_line(db_index: usize, addr: Option<SocketAddr>, frame: &Frame) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let addr = addr.map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
+    let mut line = format!(
+        "{}.{:06} [{} {}]",
+        now.as_secs(),
+        now.subsec_micros(),
+        db_index,
+        addr
+    );
+
+    let frames = match frame {
+        Frame::Array(frames) => &frames[..],
+        frame => std::slice::from_ref(frame),
+    };
+
+    for (i, frame) in frames.iter().enumerate() {
+        line.push(' ');
+
+        if is_secret(frames, i) {
+            line.push_str("\"(redacted)\"");
+            continue;
+        }
+
+        let arg = match frame {
+            Frame::Bulk(data) => data.to_vec(),
+            Frame::Simple(data) => data.clone().into_bytes(),
+            frame => frame.to_string().into_bytes(),
+        };
+        quote(&mut line, &arg);
+    }
+
+    line
+}
+
+/// Returns whether the `i`th of the command's `frames` holds credentials, which
+/// MONITOR and the slow log show as `(redacted)`.
+///
+/// As in Redis, these are all the arguments of `AUTH`, and the username and
+/// password following `AUTH` in `HELLO`.
+fn is_secret(frames: &[Frame], i: usize) -> bool {
+    let is = |frame: &Frame, name: &str| frame.to_string().eq_ignore_ascii_case(name);
+
+    match frames.first() {
+        Some(name) if is(name, "auth") => i > 0,
+        Some(name) if is(name, "hello") => {
+            // HELLO [protover [AUTH username password] [SETNAME clientname]]
+            let mut j = 2;
+            while j < i {
+                if is(&frames[j], "auth") {
+                    if i <= j + 2 {
+                        return true;
+                    }
+                    j += 3;
+                } else {
+                    j += 2;
+                }
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Append `arg` to `line` in double quotes, escaping quotes, backslashes and
+/// non-printable bytes.
+fn quote(line: &mut String, arg: &[u8]) {
+    line.push('"');
+    for &byte in arg {
+        match byte {
+            b'"' => line.push_str("\\\""),
+            b'\\' => line.push_str("\\\\"),
+            b'\n' => line.push_str("\\n"),
+            b'\r' => line.push_str("\\r"),
+            b'\t' => line.push_str("\\t"),
+            b' '..=b'~' => line.push(byte as char),
+            byte => line.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    line.push('"');
+}
+
+/// Returns the name and arguments of the command received as `frame`, as
+/// recorded in the slow log.
+///
+/// As in Redis, long commands are truncated: only the first arguments are
+/// kept, followed by the number of arguments left out, and long arguments are
+/// cut short the same way. Passwords are not recorded, see `is_secret`.
+fn slowlog_args(frame: Frame) -> Vec<Bytes> {
+    let frames = match frame {
+        Frame::Array(frames) => frames,
+        frame => vec![frame],
+    };
+
+    let argc = frames.len();
+    let mut args = Vec::with_capacity(argc.min(SLOWLOG_MAX_ARGC));
+
+    for (i, frame) in frames.iter().enumerate() {
+        if args.len() == SLOWLOG_MAX_ARGC - 1 && argc > SLOWLOG_MAX_ARGC {
+            let more = argc - args.len();
+            args.push(Bytes::from(format!("... ({} more arguments)", more)));
+            break;
+        }
+
+        if is_secret(&frames, i) {
+            args.push(Bytes::from_static(b"(redacted)"));
+            continue;
+        }
+
+        let arg = match frame.clone() {
+            Frame::Bulk(data) => data,
+            Frame::Simple(data) => Bytes::from(data),
+            Frame::Integer(value) => Bytes::from(value.to_string()),
+            frame => Bytes::from(frame.to_string()),
+        };
+
+        if arg.len() > SLOWLOG_MAX_ARG_LEN {
+            let more = arg.len() - SLOWLOG_MAX_ARG_LEN;
+            let mut truncated = arg[..SLOWLOG_MAX_ARG_LEN].to_vec();
+            truncated.extend_from_slice(format!("... ({} more bytes)", more).as_bytes());
+            args.push(Bytes::from(truncated));
+        } else {
+            args.push(arg);
+        }
+    }
+
+    args
+}
+
+/// Completes once `timeout` has elapsed, never if it is `None`.
+async fn idle(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            requirepass: None,
+            databases: DEFAULT_DATABASES,
+            shards: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            maxmemory: None,
+            maxmemory_policy: MaxMemoryPolicy::default(),
+            dbfilename: None,
+            appendfilename: None,
+            appendfsync: AppendFsync::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_array_len: DEFAULT_MAX_ARRAY_LEN,
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            enable_debug_command: false,
+            enable_shutdown_command: false,
+            allowed_commands: None,
+            timeout: None,
+            tcp_nodelay: true,
+            tcp_keepalive: None,
+            shutdown_timeout: None,
+            notify_keyspace_events: KeyspaceEvents::default(),
+            tls: None,
+            metrics_addr: None,
+            slowlog_log_slower_than: Some(DEFAULT_SLOWLOG_LOG_SLOWER_THAN),
+            slowlog_max_len: DEFAULT_SLOWLOG_MAX_LEN,
+        }
+    }
+}
+
+impl FromStr for AppendFsync {
+    type Err = String;
+
+    /// Parse a policy named as in the Redis `appendfsync` setting.
+    fn from_str(s: &str) -> Result<AppendFsync, String> {
+        match &s.to_lowercase()[..] {
+            "always" => Ok(AppendFsync::Always),
+            "everysec" => Ok(AppendFsync::EverySec),
+            _ => Err(format!("invalid appendfsync policy '{}'", s)),
+        }
+    }
+}
+
+impl FromStr for MaxMemoryPolicy {
+    type Err = String;
+
+    /// Parse a policy named as in the Redis `maxmemory-policy` setting.
+    fn from_str(s: &str) -> Result<MaxMemoryPolicy, String> {
+        match &s.to_lowercase()[..] {
+            "allkeys-lru" => Ok(MaxMemoryPolicy::AllKeysLru),
+            "allkeys-lfu" => Ok(MaxMemoryPolicy::AllKeysLfu),
+            "noeviction" => Ok(MaxMemoryPolicy::NoEviction),
+            _ => Err(format!("invalid maxmemory policy '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for MaxMemoryPolicy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaxMemoryPolicy::AllKeysLru => "allkeys-lru".fmt(fmt),
+            MaxMemoryPolicy::AllKeysLfu => "allkeys-lfu".fmt(fmt),
+            MaxMemoryPolicy::NoEviction => "noeviction".fmt(fmt),
+        }
+    }
+}
+
+impl KeyspaceEvents {
+    /// Returns `true` if events of `class` are published.
+    pub(crate) fn enabled(&self, class: EventClass) -> bool {
+        (self.keyspace || self.keyevent) && self.classes & (1 << class as u16) != 0
+    }
+
+    /// Returns `true` if events are published to `__keyspace@<db>__:<key>`
+    /// channels.
+    pub(crate) fn keyspace(&self) -> bool {
+        self.keyspace
+    }
+
+    /// Returns `true` if events are published to `__keyevent@<db>__:<event>`
+    /// channels.
+    pub(crate) fn keyevent(&self) -> bool {
+        self.keyevent
+    }
+}
+
+impl FromStr for KeyspaceEvents {
+    type Err = String;
+
+    /// Parse flags as in the Redis `notify-keyspace-events` setting: `K` and
+    /// `E` enable the two kinds of channels, `g`, `$`, `l`, `h`, `s`, `z`, `x`
+    /// and `e` enable classes of events, and `A` is an alias for every class.
+    fn from_str(s: &str) -> Result<KeyspaceEvents, String> {
+        let mut events = KeyspaceEvents::default();
+
+        for flag in s.bytes() {
+            match flag {
+                b'K' => events.keyspace = true,
+                b'E' => events.keyevent = true,
+                b'A' => events.classes = (1 << EVENT_CLASS_FLAGS.len()) - 1,
+                flag => match EVENT_CLASS_FLAGS.iter().position(|&class| class == flag) {
+                    Some(class) => events.classes |= 1 << class,
+                    None => return Err(format!("invalid keyspace events '{}'", s)),
+                },
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+impl fmt::Display for KeyspaceEvents {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.classes == (1 << EVENT_CLASS_FLAGS.len()) - 1 {
+            "A".fmt(fmt)?;
+        } else {
+            for (class, &flag) in EVENT_CLASS_FLAGS.iter().enumerate() {
+                if self.classes & (1 << class) != 0 {
+                    (flag as char).fmt(fmt)?;
+                }
+            }
+        }
+
+        if self.keyspace {
+            "K".fmt(fmt)?;
+        }
+
+        if self.keyevent {
+            "E".fmt(fmt)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Listener {
@@ -237,31 +1122,111 @@ impl Listener {
             // error here is non-recoverable.
             let socket = self.accept().await?;
 
-            // Create the necessary per-connection handler state.
-            let mut handler = Handler {
-                // Get a handle to the shared database.
-                db: self.db_holder.db(),
-
-                // Initialize the connection state. This allocates read/write
-                // buffers to perform redis protocol frame parsing.
-                connection: Connection::new(socket),
+            // The connection is still usable without its options.
+            if let Err(err) = set_socket_options(&socket, &self.config) {
+                warn!(cause = %err, "failed to set socket options");
+            }
 
-                // Receive shutdown notifications.
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+            let tls = self.config.tls.clone().map(TlsAcceptor::from);
 
-                // Notifies the receiver half once all clones are
-                // dropped.
-                _shutdown_complete: self.shutdown_complete_tx.clone(),
-            };
+            // Get handles to the shared state needed by the handler. The
+            // handler itself is created by the connection's task.
+            let dbs = self.db_holder.dbs();
+            let mut shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+            let config = self.config.clone();
+            let aof = self.aof.clone();
+            let shutdown_complete = self.shutdown_complete_tx.clone();
+            let shutdown_request = self.shutdown_request_tx.clone();
 
             // Spawn a new task to process the connections. Tokio tasks are like
             // asynchronous green threads and are executed concurrently.
             tokio::spawn(async move {
-                // Process the connection. If an error is encountered, log it.
-                if let Err(err) = handler.run().await {
-                    error!(cause = ?err, "connection error");
-                }
-                // Move the permit into the task and drop it after completion.
+                let addr = match socket.peer_addr() {
+                    Ok(addr) => addr,
+                    Err(err) => {
+                        error!(cause = ?err, "failed to get peer address");
+                        return;
+                    }
+                };
+
+                // Initialize the connection state. This allocates read/write
+                // buffers to perform redis protocol frame parsing. The TLS
+                // handshake is performed here rather than by the listener, so
+                // a slow peer does not hold up accepting other connections.
+                let mut connection = match tls {
+                    Some(tls) => match tls.accept(socket).await {
+                        Ok(stream) => Connection::from_stream_with_write_buffer_size(
+                            stream,
+                            config.write_buffer_size,
+                        ),
+                        Err(err) => {
+                            error!(cause = ?err, "TLS handshake failed");
+                            return;
+                        }
+                    },
+                    None => Connection::with_write_buffer_size(socket, config.write_buffer_size),
+                };
+                connection.set_max_array_len(config.max_array_len);
+
+                // Statistics are shared by all databases, any of them can be
+                // used to update them.
+                let db = dbs[0].clone();
+                db.stats().connection_received();
+
+                // Killing the connection shuts its handler down.
+                let (client, killed) = db.register_client(addr);
+                shutdown.set_kill(killed);
+
+                // Create the necessary per-connection handler state.
+                let mut handler = Handler {
+                    dbs,
+
+                    // Connections start on the first database.
+                    db_index: 0,
+
+                    connection,
+
+                    // Receive shutdown notifications.
+                    shutdown,
+
+                    // Without a password, there is nothing to authenticate.
+                    authenticated: config.requirepass.is_none(),
+
+                    config,
+
+                    aof,
+
+                    // Connections start unnamed.
+                    name: None,
+
+                    // Register the connection, it is unregistered once the
+                    // handler is dropped.
+                    client: Some(client),
+
+                    // Connections start outside of a transaction, and
+                    // unsubscribed.
+                    transaction: None,
+                    subscriptions: None,
+                    pending: None,
+
+                    // Every command is replied until asked otherwise.
+                    reply_mode: ReplyMode::On,
+
+                    shutdown_request,
+
+                    // Notifies the receiver half once all clones are
+                    // dropped.
+                    _shutdown_complete: shutdown_complete,
+                };
+
+                // Process the connection. If an error is encountered, log it.
+                if let Err(err) = handler.run().await {
+                    error!(cause = ?err, "connection error");
+                }
+
+                db.stats().connection_closed();
+
+                // Move the permit into the task and drop it after completion.
                 // This returns the permit back to the semaphore.
                 drop(permit);
             });
@@ -314,20 +1279,63 @@ impl Handler {
     ///
     /// When the shutdown signal is received, the connection is processed until
     /// it reaches a safe state, at which point it is terminated.
-    #[instrument(skip(self))]
+    #[instrument(skip(self), fields(name = field::Empty))]
     async fn run(&mut self) -> crate::Result<()> {
         // As long as the shutdown signal has not been received, try to read a
         // new request frame.
         while !self.shutdown.is_shutdown() {
+            // The limit can be changed with `CONFIG SET proto-max-bulk-len`.
+            let max_frame_size = self.dbs[0].max_frame_size();
+            self.connection.set_max_frame_size(max_frame_size);
+
+            // Once the command a RESP3 peer sent while subscribed is applied,
+            // the connection goes back to the subscribed state.
+            if self.pending.is_none() {
+                if let Some(subscriptions) = self.subscriptions.take() {
+                    let db = &self.dbs[self.db_index];
+                    let exit = resume_subscribed(
+                        subscriptions,
+                        db,
+                        &mut self.connection,
+                        &mut self.shutdown,
+                    )
+                    .await?;
+                    if self.leave_subscribed(exit).await?.is_break() {
+                        return Ok(());
+                    }
+                }
+            }
+
             // While reading a request frame, also listen for the shutdown
-            // signal.
-            let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => res?,
+            // signal, and give up on the peer once it has been idle for too
+            // long.
+            let timeout = self.config.timeout;
+            let maybe_frame = match self.pending.take() {
+                Some(frame) => Some(frame),
+                None => tokio::select! {
+                res = self.connection.read_frame() => match res {
+                    Ok(maybe_frame) => maybe_frame,
+                    Err(err) => {
+                        // Tell peers sending invalid frames what went wrong
+                        // before closing the connection, as Redis does.
+                        if let Some(err) = err.downcast_ref::<frame::Error>() {
+                            let response = Frame::Error(format!("ERR {}", err));
+                            let _ = self.connection.write_frame(&response).await;
+                        }
+
+                        return Err(err);
+                    }
+                },
                 _ = self.shutdown.recv() => {
                     // If a shutdown signal is received, return from `run`.
                     // This will result in the task terminating.
                     return Ok(());
                 }
+                _ = idle(timeout) => {
+                    debug!("closing idle connection");
+                    return Ok(());
+                }
+                },
             };
 
             // If `None` is returned from `read_frame()` then the peer closed
@@ -335,13 +1343,78 @@ impl Handler {
             // terminated.
             let frame = match maybe_frame {
                 Some(frame) => frame,
-                None => return Ok(()),
+                None => {
+                    debug!("peer closed the connection");
+                    return Ok(());
+                }
             };
 
+            self.dbs[self.db_index].stats().command_processed();
+
+            // `CLIENT REPLY OFF` silences the replies to every command from
+            // then on, `CLIENT REPLY SKIP` to the next command only.
+            self.connection.set_muted(self.reply_mode != ReplyMode::On);
+            if self.reply_mode == ReplyMode::Skip {
+                self.reply_mode = ReplyMode::On;
+            }
+
+            // Until the peer authenticates, only `AUTH` and `PING` are
+            // accepted.
+            if !self.authenticated {
+                if self.apply_unauthenticated(frame).await?.is_break() {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            // Monitors see every command, as it is received. It is only
+            // formatted if there is one.
+            let db = &self.dbs[self.db_index];
+            if db.is_monitored() {
+                let addr = self.client.as_ref().map(ClientGuard::addr);
+                db.feed_monitors(monitor_line(self.db_index, addr, &frame));
+            }
+
+            // Inside a transaction, commands are queued instead of applied.
+            if self.transaction.is_some() {
+                if self.queue(frame).await?.is_break() {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            // Write commands are logged as they were received. The frame is
+            // consumed when parsing the command, so keep a copy.
+            let logged = self.aof.clone().map(|aof| (aof, frame.clone()));
+
+            // Likewise, the arguments are recorded if the command is slow.
+            let slowlog = self
+                .config
+                .slowlog_log_slower_than
+                .map(|threshold| (threshold, frame.clone()));
+
             // Convert the redis frame into a command struct. This returns an
-            // error if the frame is not a valid redis command or it is an
-            // unsupported command.
-            let cmd = Command::from_frame(frame)?;
+            // error if the frame is not a valid redis command, such as a
+            // command sent with the wrong number of arguments. The frame was
+            // read whole, so the peer is told and the connection stays usable.
+            let cmd = match Command::from_frame(frame) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    self.write_error(&format!("ERR {}", err)).await?;
+                    continue;
+                }
+            };
+
+            if let Some(err) = self.permission_error(&cmd) {
+                self.write_error(&err).await?;
+                continue;
+            }
+
+            // Commands held by `CLIENT PAUSE` wait for the pause to end.
+            // `CLIENT` commands are not, so that the pause can be ended early.
+            if cmd.get_name() != "client" && self.wait_unpaused(cmd.is_write()).await.is_break() {
+                return Ok(());
+            }
 
             // Logs the `cmd` object. The syntax here is a shorthand provided by
             // the `tracing` crate. It can be thought of as similar to:
@@ -354,6 +1427,21 @@ impl Handler {
             // as key-value pairs.
             debug!(?cmd);
 
+            self.record_command(&cmd);
+
+            // Blocking commands wait for other connections, they are not slow
+            // to apply.
+            let blocking = matches!(
+                cmd,
+                Command::Subscribe(_)
+                    | Command::PSubscribe(_)
+                    | Command::BLPop(_)
+                    | Command::BRPop(_)
+                    | Command::Wait(_)
+                    | Command::Monitor(_)
+            );
+            let started_at = Instant::now();
+
             // Perform the work needed to apply the command. This may mutate the
             // database state as a result.
             //
@@ -361,10 +1449,697 @@ impl Handler {
             // command to write response frames directly to the connection. In
             // the case of pub/sub, multiple frames may be send back to the
             // peer.
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown)
-                .await?;
+            match cmd {
+                Command::Multi(_) => {
+                    self.transaction = Some(Transaction::default());
+                    self.write_simple("OK").await?;
+                }
+                Command::Auth(cmd) => self.auth(cmd).await?,
+                Command::Reset(cmd) => self.reset(cmd).await?,
+                // The reply is flushed before the connection is closed.
+                Command::Quit(cmd) => {
+                    cmd.apply(&mut self.connection).await?;
+                    return Ok(());
+                }
+                // On success, the connection is closed without a reply.
+                Command::Shutdown(cmd) => {
+                    if self.shutdown_server(cmd).await?.is_break() {
+                        return Ok(());
+                    }
+                }
+                // Monitoring runs until the peer resets or closes the
+                // connection.
+                Command::Monitor(cmd) => {
+                    if self.monitor(cmd).await?.is_break() {
+                        return Ok(());
+                    }
+                }
+                Command::Exec(_) => self.write_error("ERR EXEC without MULTI").await?,
+                Command::Discard(_) => self.write_error("ERR DISCARD without MULTI").await?,
+                // Subscribing runs until the peer disconnects or resets the
+                // connection, it must not keep transactions from executing in
+                // the meantime.
+                Command::Subscribe(cmd) => {
+                    let db = &self.dbs[self.db_index];
+                    let exit = cmd
+                        .apply(db, &mut self.connection, &mut self.shutdown)
+                        .await?;
+                    if self.leave_subscribed(exit).await?.is_break() {
+                        return Ok(());
+                    }
+                }
+                Command::PSubscribe(cmd) => {
+                    let db = &self.dbs[self.db_index];
+                    let exit = cmd
+                        .apply(db, &mut self.connection, &mut self.shutdown)
+                        .await?;
+                    if self.leave_subscribed(exit).await?.is_break() {
+                        return Ok(());
+                    }
+                }
+                // Sleeping must not keep transactions from executing either.
+                cmd @ Command::DebugSleep(_) | cmd @ Command::Wait(_) => self.apply(cmd).await?,
+                // Neither must waiting for a list to pop from.
+                Command::BLPop(cmd) => {
+                    self.blocking_pop(cmd.keys(), cmd.timeout(), ListEnd::Left)
+                        .await?
+                }
+                Command::BRPop(cmd) => {
+                    self.blocking_pop(cmd.keys(), cmd.timeout(), ListEnd::Right)
+                        .await?
+                }
+                // Saving holds the databases exclusively, like a transaction,
+                // so the snapshot is consistent across databases.
+                cmd @ Command::Save(_) => {
+                    let db = self.dbs[self.db_index].clone();
+                    let _guard = db.lock_exec().await;
+                    self.apply(cmd).await?;
+                }
+                // So do scripts, so that they are atomic.
+                Command::Eval(cmd) => {
+                    let db = self.dbs[self.db_index].clone();
+                    let _guard = db.lock_exec().await;
+
+                    match logged {
+                        Some((aof, frame)) => self.eval_logged(&aof, cmd, frame).await?,
+                        None => cmd.apply(&db, &mut self.connection).await.map(drop)?,
+                    }
+                }
+                cmd => {
+                    let db = self.dbs[self.db_index].clone();
+                    let _guard = db.lock_command().await;
+
+                    match logged {
+                        Some((aof, frame)) if cmd.is_write() => {
+                            self.apply_logged(&aof, cmd, frame).await?
+                        }
+                        _ => self.apply(cmd).await?,
+                    }
+                }
+            }
+
+            if let Some((threshold, frame)) = slowlog {
+                let duration = started_at.elapsed();
+
+                if !blocking && duration >= threshold {
+                    self.dbs[self.db_index].slowlog_push(
+                        slowlog_args(frame),
+                        duration,
+                        self.client.as_ref().map(ClientGuard::addr),
+                        self.name.clone(),
+                    );
+                }
+            }
         }
 
         Ok(())
     }
+
+    /// Count `cmd` in the statistics of the server.
+    ///
+    /// Unknown commands are not counted, their names are chosen by the peer.
+    fn record_command(&self, cmd: &Command) {
+        if !matches!(cmd, Command::Unknown(_)) {
+            self.dbs[self.db_index]
+                .stats()
+                .command_applied(cmd.get_name());
+        }
+    }
+
+    /// Handle a frame received while a transaction is open.
+    ///
+    /// Commands are queued and acknowledged with `QUEUED`. Commands that fail
+    /// to parse are reported to the peer and mark the transaction as failed,
+    /// the connection stays usable so the peer can still `DISCARD`.
+    ///
+    /// Breaks if the connection must be closed.
+    async fn queue(&mut self, frame: Frame) -> crate::Result<ControlFlow<()>> {
+        let logged = self.aof.as_ref().map(|_| frame.clone());
+
+        let cmd = match Command::from_frame(frame) {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                self.fail_transaction();
+                self.write_error(&format!("ERR {}", err)).await?;
+                return Ok(ControlFlow::Continue(()));
+            }
+        };
+
+        if let Some(err) = self.permission_error(&cmd) {
+            self.fail_transaction();
+            self.write_error(&err).await?;
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        debug!(?cmd);
+
+        self.record_command(&cmd);
+
+        match cmd {
+            Command::Multi(_) => {
+                self.write_error("ERR MULTI calls can not be nested")
+                    .await?
+            }
+            Command::Exec(_) => {
+                // The transaction is held by pauses of write commands if any
+                // of its commands writes.
+                let write = self.transaction.as_ref().is_some_and(|transaction| {
+                    transaction.commands.iter().any(|(cmd, _)| cmd.is_write())
+                });
+                if self.wait_unpaused(write).await.is_break() {
+                    return Ok(ControlFlow::Break(()));
+                }
+
+                self.exec().await?
+            }
+            Command::Discard(_) => {
+                self.transaction = None;
+                self.write_simple("OK").await?;
+            }
+            // Resetting discards the transaction rather than being queued.
+            Command::Reset(cmd) => self.reset(cmd).await?,
+            // So does quitting.
+            Command::Quit(cmd) => {
+                cmd.apply(&mut self.connection).await?;
+                return Ok(ControlFlow::Break(()));
+            }
+            Command::Unknown(cmd) => {
+                self.fail_transaction();
+                cmd.apply(&mut self.connection).await?;
+            }
+            cmd @ Command::Subscribe(_)
+            | cmd @ Command::Unsubscribe(_)
+            | cmd @ Command::PSubscribe(_)
+            | cmd @ Command::PUnsubscribe(_)
+            | cmd @ Command::BLPop(_)
+            | cmd @ Command::BRPop(_)
+            | cmd @ Command::Wait(_)
+            | cmd @ Command::Auth(_)
+            | cmd @ Command::Monitor(_)
+            | cmd @ Command::ClientReply(_)
+            | cmd @ Command::Shutdown(_) => {
+                self.fail_transaction();
+                let msg = format!("ERR '{}' is not allowed in a transaction", cmd.get_name());
+                self.write_error(&msg).await?;
+            }
+            cmd => {
+                let logged = logged.filter(|_| cmd.is_write());
+
+                if let Some(transaction) = &mut self.transaction {
+                    transaction.commands.push((cmd, logged));
+                }
+                self.write_simple("QUEUED").await?;
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Wait for the pause started with `CLIENT PAUSE` to end, if it holds the
+    /// command about to be applied, `write` if it writes to the keyspace.
+    ///
+    /// The pause is checked again whenever it is replaced or ended early.
+    /// Breaks if the shutdown signal is received in the meantime.
+    async fn wait_unpaused(&mut self, write: bool) -> ControlFlow<()> {
+        let db = self.dbs[self.db_index].clone();
+
+        loop {
+            let changed = db.pause_changed();
+            let until = match db.paused_until(write) {
+                Some(until) => until,
+                None => return ControlFlow::Continue(()),
+            };
+
+            tokio::select! {
+                _ = time::sleep_until(until) => {}
+                _ = changed => {}
+                _ = self.shutdown.recv() => return ControlFlow::Break(()),
+            }
+        }
+    }
+
+    /// Apply all queued commands and reply with an array of their replies.
+    ///
+    /// The commands are applied while holding the database exclusively, so no
+    /// other connection can interleave commands with the transaction.
+    async fn exec(&mut self) -> crate::Result<()> {
+        let transaction = self.transaction.take().unwrap_or_default();
+
+        if transaction.failed {
+            return self
+                .write_error("EXECABORT Transaction discarded because of previous errors.")
+                .await;
+        }
+
+        // The lock is shared by all databases, so any of them can be used to
+        // acquire it. `SELECT` may switch databases while the guard is held.
+        let db = self.dbs[self.db_index].clone();
+        let guard = db.lock_exec().await;
+
+        // Each command writes its reply to the connection as usual. The replies
+        // are captured and sent together once all commands have been applied.
+        self.connection.start_capture();
+
+        // Queued write commands are logged whether or not they fail, replaying
+        // them fails the same way.
+        let aof = self.aof.clone();
+
+        for (cmd, logged) in transaction.commands {
+            let db_index = self.db_index;
+            self.apply(cmd).await?;
+
+            if let (Some(aof), Some(frame)) = (&aof, logged) {
+                aof.lock().await.append(db_index, &frame).await?;
+            }
+        }
+
+        let replies = self.connection.finish_capture();
+        drop(guard);
+
+        self.connection.write_frame(&Frame::Array(replies)).await?;
+
+        Ok(())
+    }
+
+    /// Apply a command to the selected database.
+    ///
+    /// Commands that need more than the selected database, such as `SELECT`,
+    /// `FLUSHALL`, `MOVE`, `SAVE`, `INFO` and `CONFIG`, are applied with the state held
+    /// by the handler.
+    async fn apply(&mut self, cmd: Command) -> crate::Result<()> {
+        match cmd {
+            Command::Select(cmd) => self.select(cmd).await,
+            Command::ClientSetName(cmd) => self.set_name(cmd).await,
+            Command::ClientReply(cmd) => self.client_reply(cmd).await,
+            Command::ClientGetName(cmd) => {
+                cmd.apply(self.name.as_deref(), &mut self.connection).await
+            }
+            Command::DebugObject(_)
+            | Command::DebugSleep(_)
+            | Command::DebugSetActiveExpire(_)
+            | Command::DebugExpireSweep(_)
+                if !self.config.enable_debug_command =>
+            {
+                self.write_error("ERR DEBUG command not allowed").await
+            }
+            Command::DebugSetActiveExpire(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            Command::DebugExpireSweep(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            Command::FlushAll(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            Command::Info(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            Command::Move(cmd) => {
+                cmd.apply(&self.dbs, self.db_index, &mut self.connection)
+                    .await
+            }
+            Command::SwapDb(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            // Unsubscribing while not subscribed is only confirmed.
+            Command::Unsubscribe(cmd) => cmd.apply(&mut self.connection).await,
+            Command::PUnsubscribe(cmd) => cmd.apply(&mut self.connection).await,
+            Command::ConfigGet(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            Command::ConfigSet(cmd) => cmd.apply(&self.dbs, &mut self.connection).await,
+            Command::Save(cmd) => {
+                let path = self.config.dbfilename.as_deref();
+                cmd.apply(&self.dbs, path, &mut self.connection).await
+            }
+            cmd => {
+                let db = &self.dbs[self.db_index];
+                cmd.apply(db, &mut self.connection, &mut self.shutdown)
+                    .await
+            }
+        }
+    }
+
+    /// Pop a value from `end` of the first non-empty list among `keys`, waiting
+    /// up to `timeout` for one to be pushed to if they are all empty.
+    ///
+    /// Each attempt to pop takes the command lock, and the append-only file
+    /// lock, for its own duration only, so that other connections can push
+    /// while this one waits. Popped values are logged as `LPOP` or `RPOP`, so
+    /// that replaying the file never blocks. A zero `timeout`, or one too
+    /// large to be represented as a deadline, waits indefinitely.
+    async fn blocking_pop(
+        &mut self,
+        keys: &[Bytes],
+        timeout: Duration,
+        end: ListEnd,
+    ) -> crate::Result<()> {
+        let db = self.dbs[self.db_index].clone();
+        let waiter = Arc::new(Notify::new());
+        let deadline = Instant::now()
+            .checked_add(timeout)
+            .filter(|_| !timeout.is_zero());
+
+        let response = loop {
+            let guard = db.lock_command().await;
+            let mut log = match &self.aof {
+                Some(aof) => Some(aof.lock().await),
+                None => None,
+            };
+
+            match db.pop_or_watch(keys, end, &waiter) {
+                Ok(Some((key, value))) => {
+                    if let Some(log) = &mut log {
+                        let frame = match end {
+                            ListEnd::Left => LPop::new(&key).into_frame(),
+                            ListEnd::Right => RPop::new(&key).into_frame(),
+                        };
+                        log.append(self.db_index, &frame).await?;
+                    }
+
+                    break Frame::Array(vec![Frame::Bulk(key), Frame::Bulk(value)]);
+                }
+                Ok(None) => {}
+                Err(err) => break Frame::Error(err.to_string()),
+            }
+
+            drop(log);
+            drop(guard);
+
+            let expired = async {
+                match deadline {
+                    Some(deadline) => time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = waiter.notified() => {}
+                _ = expired => break Frame::Null,
+                _ = self.shutdown.recv() => {
+                    db.unwatch(keys, &waiter);
+                    return Ok(());
+                }
+            }
+        };
+
+        db.unwatch(keys, &waiter);
+
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Apply a write command and append it to the append-only file.
+    ///
+    /// The file is locked while the command is applied, so that commands are
+    /// logged in the order they are applied. The reply is captured, so that
+    /// failed commands are not logged, and sent once the command is logged.
+    async fn apply_logged(&mut self, aof: &Aof, cmd: Command, frame: Frame) -> crate::Result<()> {
+        let mut log = aof.lock().await;
+        let db_index = self.db_index;
+
+        self.connection.start_capture();
+        let res = self.apply(cmd).await;
+        let replies = self.connection.finish_capture();
+        res?;
+
+        if !matches!(replies[..], [Frame::Error(_)]) {
+            log.append(db_index, &frame).await?;
+        }
+
+        drop(log);
+
+        for reply in &replies {
+            self.connection.write_frame_buffered(reply).await?;
+        }
+        self.connection.flush().await?;
+
+        Ok(())
+    }
+
+    /// Run a script and append it to the append-only file if it wrote.
+    ///
+    /// Unlike `apply_logged`, a script which fails is logged if it wrote
+    /// before failing, as its writes are kept. Replaying it writes the same,
+    /// then fails the same way.
+    async fn eval_logged(&mut self, aof: &Aof, cmd: Eval, frame: Frame) -> crate::Result<()> {
+        let mut log = aof.lock().await;
+        let db = self.dbs[self.db_index].clone();
+
+        self.connection.start_capture();
+        let res = cmd.apply(&db, &mut self.connection).await;
+        let replies = self.connection.finish_capture();
+
+        if res? {
+            log.append(self.db_index, &frame).await?;
+        }
+
+        drop(log);
+
+        for reply in &replies {
+            self.connection.write_frame_buffered(reply).await?;
+        }
+        self.connection.flush().await?;
+
+        Ok(())
+    }
+
+    /// Handle a frame received before the peer authenticated.
+    ///
+    /// Breaks if the connection must be closed.
+    async fn apply_unauthenticated(&mut self, frame: Frame) -> crate::Result<ControlFlow<()>> {
+        let cmd = match Command::from_frame(frame) {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                self.write_error(&format!("ERR {}", err)).await?;
+                return Ok(ControlFlow::Continue(()));
+            }
+        };
+
+        debug!(?cmd);
+
+        match cmd {
+            Command::Auth(cmd) => self.auth(cmd).await?,
+            Command::Reset(cmd) => self.reset(cmd).await?,
+            Command::Quit(cmd) => {
+                cmd.apply(&mut self.connection).await?;
+                return Ok(ControlFlow::Break(()));
+            }
+            cmd @ Command::Ping(_) => {
+                let db = &self.dbs[self.db_index];
+                cmd.apply(db, &mut self.connection, &mut self.shutdown)
+                    .await?
+            }
+            _ => self.write_error("NOAUTH Authentication required.").await?,
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Check the password supplied with `AUTH`.
+    async fn auth(&mut self, cmd: Auth) -> crate::Result<()> {
+        let requirepass = self.config.requirepass.as_deref();
+
+        if cmd.apply(requirepass, &mut self.connection).await? {
+            self.authenticated = true;
+        }
+
+        Ok(())
+    }
+
+    /// Switch to the database requested with `SELECT`.
+    async fn select(&mut self, cmd: Select) -> crate::Result<()> {
+        if let Some(index) = cmd.apply(self.dbs.len(), &mut self.connection).await? {
+            self.db_index = index;
+            if let Some(client) = &self.client {
+                client.set_db(index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Name the connection as requested with `CLIENT SETNAME`.
+    async fn set_name(&mut self, cmd: ClientSetName) -> crate::Result<()> {
+        if let Some(name) = cmd.apply(&mut self.connection).await? {
+            self.name = Some(name).filter(|name| !name.is_empty());
+            Span::current().record("name", self.name.as_deref().unwrap_or_default());
+            if let Some(client) = &self.client {
+                client.set_name(self.name.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turn the replies to the connection on or off, as requested with
+    /// `CLIENT REPLY`. Only turning them on is replied.
+    async fn client_reply(&mut self, cmd: ClientReply) -> crate::Result<()> {
+        self.reply_mode = cmd.mode();
+
+        if self.reply_mode == ReplyMode::On {
+            self.connection.set_muted(false);
+            self.write_simple("OK").await?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the connection to the state of a new connection, as requested
+    /// with `RESET`.
+    ///
+    /// Subscriptions are dropped by the subscribed state before this is
+    /// called.
+    async fn reset(&mut self, cmd: Reset) -> crate::Result<()> {
+        self.transaction = None;
+
+        self.db_index = 0;
+        self.name = None;
+        Span::current().record("name", "");
+        if let Some(client) = &self.client {
+            client.set_db(0);
+            client.set_name(None);
+        }
+
+        self.authenticated = self.config.requirepass.is_none();
+        self.connection.set_protocol(2);
+        self.reply_mode = ReplyMode::On;
+        self.connection.set_muted(false);
+
+        cmd.apply(&mut self.connection).await
+    }
+
+    /// Ask the server to shut down, once the snapshot requested with `SAVE`
+    /// has been written.
+    ///
+    /// Breaks if the server shuts down, the connection must then be closed.
+    /// The connection stays usable when the command is refused or the
+    /// snapshot cannot be written.
+    async fn shutdown_server(
+        &mut self,
+        cmd: crate::cmd::Shutdown,
+    ) -> crate::Result<ControlFlow<()>> {
+        if !self.config.enable_shutdown_command {
+            self.write_error("ERR SHUTDOWN command not allowed").await?;
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        if let (true, Some(path)) = (cmd.save(), &self.config.dbfilename) {
+            // As with `SAVE`, hold the databases exclusively so the snapshot
+            // is consistent across databases.
+            let db = self.dbs[self.db_index].clone();
+            let _guard = db.lock_exec().await;
+
+            if let Err(err) = snapshot::save(&self.dbs, path).await {
+                error!(cause = %err, "failed to save snapshot, not shutting down");
+                self.write_error("ERR Errors trying to SHUTDOWN. Check logs.")
+                    .await?;
+                return Ok(ControlFlow::Continue(()));
+            }
+        }
+
+        // The server is already shutting down if the channel is full.
+        let _ = self.shutdown_request.try_send(());
+
+        Ok(ControlFlow::Break(()))
+    }
+
+    /// Stream the commands processed by the server to the peer, as requested
+    /// with `MONITOR`, until it resets the connection.
+    ///
+    /// Breaks if the connection must be closed.
+    async fn monitor(&mut self, cmd: Monitor) -> crate::Result<ControlFlow<()>> {
+        // Receive the commands before confirming, so that none sent once the
+        // peer got the confirmation is missed.
+        let mut commands = self.dbs[self.db_index].monitor();
+        cmd.apply(&mut self.connection).await?;
+
+        loop {
+            let max_frame_size = self.dbs[0].max_frame_size();
+            self.connection.set_max_frame_size(max_frame_size);
+
+            tokio::select! {
+                res = commands.recv() => match res {
+                    Ok(line) => self.connection.write_frame(&Frame::Simple(line)).await?,
+                    // The commands the peer did not keep up with are dropped.
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return Ok(ControlFlow::Break(())),
+                },
+                res = self.connection.read_frame() => {
+                    let frame = match res? {
+                        Some(frame) => frame,
+                        None => return Ok(ControlFlow::Break(())),
+                    };
+
+                    match Command::from_frame(frame) {
+                        Ok(Command::Reset(cmd)) => {
+                            self.subscriptions = None;
+                            self.reset(cmd).await?;
+                            return Ok(ControlFlow::Continue(()));
+                        }
+                        Ok(Command::Quit(cmd)) => {
+                            cmd.apply(&mut self.connection).await?;
+                            return Ok(ControlFlow::Break(()));
+                        }
+                        Ok(cmd) => {
+                            let msg = format!(
+                                "ERR '{}' is not allowed in MONITOR mode, only RESET and QUIT are",
+                                cmd.get_name()
+                            );
+                            self.write_error(&msg).await?;
+                        }
+                        Err(err) => self.write_error(&format!("ERR {}", err)).await?,
+                    }
+                }
+                _ = self.shutdown.recv() => return Ok(ControlFlow::Break(())),
+            }
+        }
+    }
+
+    /// Handle the reason the connection left the subscribed state.
+    ///
+    /// Breaks if the connection must be closed.
+    async fn leave_subscribed(&mut self, exit: SubscribeExit) -> crate::Result<ControlFlow<()>> {
+        match exit {
+            SubscribeExit::Closed => return Ok(ControlFlow::Break(())),
+            SubscribeExit::Reset(cmd) => self.reset(cmd).await?,
+            SubscribeExit::Command(frame, subscriptions) => {
+                self.pending = Some(frame);
+                self.subscriptions = Some(subscriptions);
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Mark the open transaction as failed.
+    fn fail_transaction(&mut self) {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.failed = true;
+        }
+    }
+
+    async fn write_simple(&mut self, msg: &str) -> crate::Result<()> {
+        let response = Frame::Simple(msg.to_string());
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
+
+    /// Returns the error to reply to `cmd` if clients may not send it.
+    fn permission_error(&self, cmd: &Command) -> Option<String> {
+        // Connections can always authenticate and leave, and unknown commands
+        // are reported as such.
+        let exempt = matches!(
+            cmd,
+            Command::Auth(_) | Command::Hello(_) | Command::Quit(_) | Command::Unknown(_)
+        );
+
+        let name = cmd.get_name();
+        if exempt || self.dbs[self.db_index].is_allowed(name) {
+            return None;
+        }
+
+        Some(format!(
+            "NOPERM this user has no permissions to run the '{}' command",
+            name
+        ))
+    }
+
+    async fn write_error(&mut self, msg: &str) -> crate::Result<()> {
+        let response = Frame::Error(msg.to_string());
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+        Ok(())
+    }
 }
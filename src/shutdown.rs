@@ -9,6 +9,9 @@ use tokio::sync::broadcast;
 /// The `Shutdown` struct listens for the signal and tracks that the signal has
 /// been received. Callers may query for whether the shutdown signal has been
 /// received or not.
+///
+/// A connection may also be killed on its own, with `CLIENT KILL`. The
+/// connection handler then treats the kill signal as a shutdown signal.
 #[derive(Debug)]
 pub(crate) struct Shutdown {
     /// `true` if the shutdown signal has been received
@@ -16,6 +19,9 @@ pub(crate) struct Shutdown {
 
     /// The receive half of the channel used to listen for shutdown.
     notify: broadcast::Receiver<()>,
+
+    /// The receive half of the channel used to kill the connection, if any.
+    kill: Option<broadcast::Receiver<()>>,
 }
 
 impl Shutdown {
@@ -24,9 +30,15 @@ impl Shutdown {
         Shutdown {
             is_shutdown: false,
             notify,
+            kill: None,
         }
     }
 
+    /// Also shut down when a value is received from `kill`.
+    pub(crate) fn set_kill(&mut self, kill: broadcast::Receiver<()>) {
+        self.kill = Some(kill);
+    }
+
     /// Returns `true` if the shutdown signal has been received.
     pub(crate) fn is_shutdown(&self) -> bool {
         self.is_shutdown
@@ -40,8 +52,20 @@ impl Shutdown {
             return;
         }
 
-        // Cannot receive a "lag error" as only one value is ever sent.
-        let _ = self.notify.recv().await;
+        // Cannot receive a "lag error" as only one value is ever sent. A
+        // connection killed more than once may lag, but any result means the
+        // connection must terminate.
+        match &mut self.kill {
+            Some(kill) => {
+                tokio::select! {
+                    _ = self.notify.recv() => {}
+                    _ = kill.recv() => {}
+                }
+            }
+            None => {
+                let _ = self.notify.recv().await;
+            }
+        }
 
         // Remember that the signal has been received.
         self.is_shutdown = true;
@@ -0,0 +1,185 @@
+//! Snapshot persistence.
+//!
+//! A snapshot holds every key of every database, along with its value and
+//! expiration. It is written by `SAVE` and loaded when the server starts.
+//!
+//! # Format
+//!
+//! The file starts with the `MINIREDIS` magic string followed by a version
+//! byte. It is then a sequence of records, each starting with an opcode byte:
+//!
+//! * `SELECT` followed by a `u32` database index. Keys that follow belong to
+//!   that database.
+//! * A value type followed by the key, the expiration and the value, encoded
+//!   as described in the `serialize` module.
+//! * `EOF` ends the snapshot.
+//!
+//! Strings are prefixed by their length as a `u32`. The expiration is a `u8`
+//! flag, followed by the expiration time in milliseconds since the Unix epoch
+//! as a `u64` if the flag is set. Absolute times are stored so that keys
+//! expire while the server is stopped. All integers are big endian.
+
+use crate::db::{Db, Value};
+use crate::serialize::{
+    get_bytes, get_u32, get_u64, get_u8, get_value, put_bytes, put_value, value_type,
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::convert::TryFrom;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+const MAGIC: &[u8] = b"MINIREDIS";
+const VERSION: u8 = 1;
+
+const OP_SELECT: u8 = 0xfe;
+const OP_EOF: u8 = 0xff;
+
+/// A key read from a snapshot, not yet stored in its database.
+type Record = (usize, Bytes, Value, Option<Duration>);
+
+/// Write a snapshot of `dbs` to `path`.
+///
+/// The snapshot is written to a temporary file which then replaces `path`, so
+/// a failed save never leaves a truncated snapshot behind.
+pub(crate) async fn save(dbs: &[Db], path: &Path) -> crate::Result<()> {
+    let mut buf = BytesMut::new();
+    buf.put_slice(MAGIC);
+    buf.put_u8(VERSION);
+
+    let now = SystemTime::now();
+
+    for (index, db) in dbs.iter().enumerate() {
+        let entries = db.dump();
+
+        if entries.is_empty() {
+            continue;
+        }
+
+        buf.put_u8(OP_SELECT);
+        buf.put_u32(u32::try_from(index)?);
+
+        for (key, value, ttl) in entries {
+            put_entry(&mut buf, &key, &value, ttl.map(|ttl| now + ttl))?;
+        }
+    }
+
+    buf.put_u8(OP_EOF);
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    fs::write(&tmp, &buf).await?;
+    fs::rename(&tmp, path).await?;
+
+    Ok(())
+}
+
+/// Load the snapshot stored at `path` into `dbs`.
+///
+/// Keys that expired since the snapshot was written are skipped. The whole
+/// snapshot is read before any key is stored, so nothing is loaded if it is
+/// corrupt. Returns the number of keys loaded.
+pub(crate) async fn load(dbs: &[Db], path: &Path) -> crate::Result<usize> {
+    let mut src = Bytes::from(fs::read(path).await?);
+    let records = parse(&mut src, dbs.len())?;
+
+    let now = SystemTime::now();
+    let mut loaded = 0;
+
+    for (index, key, value, expires_at) in records {
+        let ttl = match expires_at {
+            Some(when) => match (UNIX_EPOCH + when).duration_since(now) {
+                Ok(ttl) if ttl > Duration::ZERO => Some(ttl),
+                // The key expired while the server was stopped.
+                _ => continue,
+            },
+            None => None,
+        };
+
+        dbs[index].restore(key, value, ttl);
+        loaded += 1;
+    }
+
+    Ok(loaded)
+}
+
+/// Parse every record of a snapshot. The expiration of each key is returned
+/// as the time elapsed since the Unix epoch.
+fn parse(src: &mut Bytes, databases: usize) -> crate::Result<Vec<Record>> {
+    if src.len() < MAGIC.len() || &src[..MAGIC.len()] != MAGIC {
+        return Err("not a snapshot file".into());
+    }
+    src.advance(MAGIC.len());
+
+    let version = get_u8(src)?;
+    if version != VERSION {
+        return Err(format!("unsupported snapshot version {}", version).into());
+    }
+
+    let mut records = vec![];
+    let mut index = 0;
+
+    loop {
+        let (key, value, expires_at) = match get_u8(src)? {
+            OP_EOF => break,
+            OP_SELECT => {
+                index = get_u32(src)? as usize;
+
+                if index >= databases {
+                    return Err(format!(
+                        "snapshot uses database {} but only {} are configured",
+                        index, databases
+                    )
+                    .into());
+                }
+
+                continue;
+            }
+            kind => {
+                let key = get_bytes(src)?;
+                let expires_at = get_expiration(src)?;
+                let value = get_value(src, kind)?;
+                (key, value, expires_at)
+            }
+        };
+
+        records.push((index, key, value, expires_at));
+    }
+
+    Ok(records)
+}
+
+/// Encode a single key.
+fn put_entry(
+    dst: &mut BytesMut,
+    key: &[u8],
+    value: &Value,
+    expires_at: Option<SystemTime>,
+) -> crate::Result<()> {
+    dst.put_u8(value_type(value));
+    put_bytes(dst, key)?;
+
+    match expires_at {
+        Some(when) => {
+            let millis = when.duration_since(UNIX_EPOCH)?.as_millis();
+            dst.put_u8(1);
+            dst.put_u64(u64::try_from(millis)?);
+        }
+        None => dst.put_u8(0),
+    }
+
+    put_value(dst, value)?;
+
+    Ok(())
+}
+
+/// Read an expiration, as the time elapsed since the Unix epoch.
+fn get_expiration(src: &mut Bytes) -> crate::Result<Option<Duration>> {
+    match get_u8(src)? {
+        0 => Ok(None),
+        1 => Ok(Some(Duration::from_millis(get_u64(src)?))),
+        flag => Err(format!("invalid snapshot expiration flag {}", flag).into()),
+    }
+}
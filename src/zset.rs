@@ -0,0 +1,97 @@
+//! Sorted sets, as written by `ZADD`.
+//!
+//! Every member of a sorted set is associated with a floating point score.
+//! Members are kept ordered by score, members with the same score being
+//! ordered lexicographically, as in Redis. Scores are never NaN.
+
+use bytes::Bytes;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+/// A set of unique binary safe strings ordered by score.
+///
+/// The members are indexed twice: by name, to look up the score of a member,
+/// and by score, to walk the members in order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SortedSet {
+    /// Score of each member
+    scores: HashMap<Bytes, f64>,
+
+    /// Members ordered by score, then lexicographically
+    ordered: BTreeSet<(Score, Bytes)>,
+}
+
+/// A score that is not NaN, and can therefore be totally ordered.
+///
+/// `0.0` and `-0.0` compare equal, as they do in Redis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl SortedSet {
+    /// Create an empty sorted set.
+    pub(crate) fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    /// Returns the number of members.
+    pub(crate) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Set the score of `member`, adding it if needed.
+    ///
+    /// Returns `true` if the member was added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `score` is NaN.
+    pub(crate) fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        assert!(!score.is_nan(), "sorted set scores cannot be NaN");
+
+        let added = match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                self.ordered.remove(&(Score(old), member.clone()));
+                false
+            }
+            None => true,
+        };
+
+        self.ordered.insert((Score(score), member));
+
+        added
+    }
+
+    /// Returns the score of `member`, if it is in the set.
+    pub(crate) fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Returns the position of `member` in the set, starting at `0` for the
+    /// member with the lowest score.
+    pub(crate) fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        let key = (Score(score), Bytes::copy_from_slice(member));
+
+        Some(self.ordered.range(..key).count())
+    }
+
+    /// Returns an iterator over the members and their scores, in order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        // Scores are never NaN, so they are always comparable.
+        self.0.partial_cmp(&other.0).unwrap()
+    }
+}
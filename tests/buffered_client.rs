@@ -23,6 +23,38 @@ async fn pool_key_value_get_set() {
     assert_eq!(b"world", &value[..])
 }
 
+/// Closing the buffer applies the commands queued before it, then rejects new
+/// ones.
+#[tokio::test]
+async fn close_drains_queued_commands() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let buffer = BufferedClient::with_capacity(client, 4);
+    let mut first = buffer.clone();
+    let mut second = buffer.clone();
+    let mut late = buffer.clone();
+
+    // `join!` polls the futures in order, so both commands are queued before
+    // the buffer is closed.
+    let (first, second, closed) = tokio::join!(
+        first.set("first", "1".into()),
+        second.set("second", "2".into()),
+        buffer.close(),
+    );
+    first.unwrap();
+    second.unwrap();
+    closed.unwrap();
+
+    assert!(late.set("late", "3".into()).await.is_err());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(
+        2,
+        client.exists(&["first", "second", "late"]).await.unwrap()
+    );
+}
+
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
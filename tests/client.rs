@@ -1,55 +1,3785 @@
-use mini_redis::{clients::Client, server};
+use bytes::Bytes;
+use mini_redis::clients::{
+    Client, Direction, LaggedError, Pool, ReconnectingClient, RetryPolicy, ServerError,
+    TimeoutError,
+};
+use mini_redis::cmd::{
+    BitUnit, ExpireCondition, GetExExpiration, InsertPosition, ListEnd, PauseMode,
+};
+use mini_redis::rustls::pki_types::PrivateKeyDer;
+use mini_redis::rustls::{RootCertStore, ServerConfig};
+use mini_redis::{server, Frame};
 use std::net::SocketAddr;
-use tokio::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+/// With a password configured, commands other than AUTH and PING are refused
+/// until the connection authenticates.
+#[tokio::test]
+async fn auth_required() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        requirepass: Some("secret".to_string()),
+        ..Default::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.get("hello").await.unwrap_err();
+    assert_eq!("NOAUTH Authentication required.", err.to_string());
+    assert_eq!(b"PONG", &client.ping(None).await.unwrap()[..]);
+
+    let err = client.auth("wrong").await.unwrap_err();
+    assert_eq!("ERR invalid password", err.to_string());
+    assert!(client.get("hello").await.is_err());
+
+    client.auth("secret").await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+}
+
+/// `RESET` returns the connection to database `0`, without a name, and
+/// requires authenticating again.
+#[tokio::test]
+async fn reset_connection() {
+    let config = server::Config {
+        requirepass: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.auth("secret").await.unwrap();
+
+    client.select(1).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.client_setname("pooled").await.unwrap();
+
+    client.reset().await.unwrap();
+
+    let err = client.get("hello").await.unwrap_err();
+    assert_eq!("NOAUTH Authentication required.", err.to_string());
+
+    client.auth("secret").await.unwrap();
+    assert_eq!(None, client.client_getname().await.unwrap());
+    assert_eq!(None, client.get("hello").await.unwrap());
+
+    client.select(1).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+}
+
+#[tokio::test]
+async fn quit_connection() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.quit().await.unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+}
+
+#[tokio::test]
+async fn maxmemory_evicts_least_recently_used() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        maxmemory: Some(50),
+        ..Default::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // Each key uses 12 bytes, so four of them fit.
+    for key in ["k1", "k2", "k3", "k4"] {
+        client.set(key, "0123456789".into()).await.unwrap();
+    }
+
+    // Touch `k1` so that `k2` becomes the least recently used key.
+    assert!(client.get("k1").await.unwrap().is_some());
+
+    client.set("k5", "0123456789".into()).await.unwrap();
+
+    assert!(client.get("k2").await.unwrap().is_none());
+    for key in ["k1", "k3", "k4", "k5"] {
+        assert!(client.get(key).await.unwrap().is_some());
+    }
+
+    // TOUCH refreshes `k1` and `k3` without reading them, so `k4` goes next.
+    // Missing keys are not counted.
+    assert_eq!(2, client.touch(&["k1", "k3", "k2"]).await.unwrap());
+    client.set("k6", "0123456789".into()).await.unwrap();
+    assert_eq!(0, client.exists(&["k4"]).await.unwrap());
+    assert_eq!(4, client.exists(&["k1", "k3", "k5", "k6"]).await.unwrap());
+
+    let err = client.set("big", vec![0; 100].into()).await.unwrap_err();
+    assert_eq!(
+        "OOM command not allowed when used memory > 'maxmemory'",
+        err.to_string()
+    );
+    assert!(client.get("big").await.unwrap().is_none());
+}
+
+/// With `allkeys-lfu`, the keys read most often survive eviction, whatever the
+/// order in which they were last used. OBJECT FREQ reports the counters, and
+/// is refused under other policies.
+#[tokio::test]
+async fn maxmemory_evicts_least_frequently_used() {
+    let addr = start_server().await.0;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("k1", "0123456789".into()).await.unwrap();
+    let err = client.object_freq("k1").await.unwrap_err();
+    assert!(err.to_string().starts_with("ERR An LFU maxmemory policy"));
+
+    let addr = start_server_with_config(server::Config {
+        shards: 4,
+        maxmemory: Some(50),
+        maxmemory_policy: server::MaxMemoryPolicy::AllKeysLfu,
+        ..Default::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // Each key uses 12 bytes, so four of them fit.
+    client.set("cold", "0123456789".into()).await.unwrap();
+    for key in ["k1", "k2", "k3"] {
+        client.set(key, "0123456789".into()).await.unwrap();
+        assert_eq!(5, client.object_freq(key).await.unwrap());
+    }
+
+    // New keys start at 5, and the first access always counts.
+    for key in ["k1", "k2", "k3"] {
+        for _ in 0..20 {
+            assert!(client.get(key).await.unwrap().is_some());
+        }
+        assert!(client.object_freq(key).await.unwrap() > 5);
+    }
+
+    // Writing `cold` makes it the most recently used key, but writes are not
+    // counted, so it remains the least frequently used one.
+    client.set("cold", "9876543210".into()).await.unwrap();
+    assert_eq!(5, client.object_freq("cold").await.unwrap());
+    client.set("k4", "0123456789".into()).await.unwrap();
+
+    assert_eq!(0, client.exists(&["cold"]).await.unwrap());
+    assert_eq!(4, client.exists(&["k1", "k2", "k3", "k4"]).await.unwrap());
+    assert!(client.object_freq("missing").await.is_err());
+}
+
+/// Commands spanning several keys, or the whole database, behave the same when
+/// the keys are spread over several shards.
+#[tokio::test]
+async fn sharded_keyspace() {
+    let addr = start_server_with_config(server::Config {
+        shards: 8,
+        maxmemory: Some(50),
+        ..Default::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let pairs = (0..4)
+        .map(|i| (format!("k{}", i), Bytes::from("0123456789")))
+        .collect();
+    client.mset(pairs).await.unwrap();
+    assert_eq!(4, client.dbsize().await.unwrap());
+
+    // Keys are evicted in least recently used order across shards.
+    assert!(client.get("k0").await.unwrap().is_some());
+    client.set("k4", "0123456789".into()).await.unwrap();
+    assert_eq!(0, client.exists(&["k1"]).await.unwrap());
+    assert_eq!(4, client.exists(&["k0", "k2", "k3", "k4"]).await.unwrap());
+
+    client.rename("k0", "renamed").await.unwrap();
+    assert!(client.copy("renamed", "k0", false).await.unwrap());
+    client.flushdb().await.unwrap();
+    client.config_set("maxmemory", "0").await.unwrap();
+
+    for i in 0..20 {
+        client.set(&format!("key{}", i), "v".into()).await.unwrap();
+    }
+
+    let mut keys = client.keys("key*").await.unwrap();
+    keys.sort();
+    let mut scanned = vec![];
+    let mut cursor = 0;
+    loop {
+        let (next, batch) = client.scan(cursor, None, Some(3)).await.unwrap();
+        scanned.extend(batch);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    scanned.sort();
+    assert_eq!(20, keys.len());
+    assert_eq!(keys, scanned);
+
+    // A push to any of the keys wakes a connection blocked on several of
+    // them.
+    let mut other = Client::connect(addr).await.unwrap();
+    let blocked = tokio::spawn(async move {
+        other
+            .blpop(&["list1", "list2", "list3"], Duration::from_secs(5))
+            .await
+            .unwrap()
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.rpush("list3", vec!["x".into()]).await.unwrap();
+    let (key, value) = blocked.await.unwrap().unwrap();
+    assert_eq!("list3", key);
+    assert_eq!(b"x", &value[..]);
+}
+
+/// CLIENT SETNAME names the connection, CLIENT GETNAME returns the name.
+#[tokio::test]
+async fn client_name() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.client_getname().await.unwrap());
+
+    client.client_setname("worker-1").await.unwrap();
+    assert_eq!(
+        Some("worker-1".to_string()),
+        client.client_getname().await.unwrap()
+    );
+
+    // Names are per connection.
+    assert_eq!(None, other.client_getname().await.unwrap());
+
+    let err = client.client_setname("has space").await.unwrap_err();
+    assert_eq!(
+        "ERR Client names cannot contain spaces, newlines or special characters.",
+        err.to_string()
+    );
+    assert!(client.client_setname("new\nline").await.is_err());
+    assert_eq!(
+        Some("worker-1".to_string()),
+        client.client_getname().await.unwrap()
+    );
+
+    // An empty name removes the name.
+    client.client_setname("").await.unwrap();
+    assert_eq!(None, client.client_getname().await.unwrap());
+}
+
+/// CLIENT LIST describes every open connection, and forgets closed ones.
+#[tokio::test]
+async fn client_list() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    other.client_setname("other").await.unwrap();
+    other.select(1).await.unwrap();
+
+    let list = client.client_list().await.unwrap();
+    let lines: Vec<_> = list.lines().collect();
+    assert_eq!(2, lines.len(), "{}", list);
+    assert!(lines[0].contains("name= "), "{}", lines[0]);
+    assert!(lines[0].ends_with(" db=0"), "{}", lines[0]);
+    assert!(lines[1].contains("name=other "), "{}", lines[1]);
+    assert!(lines[1].ends_with(" db=1"), "{}", lines[1]);
+    assert!(lines.iter().all(|line| line.contains(" addr=127.0.0.1:")));
+
+    drop(other);
+
+    // The handler of the closed connection unregisters it once it notices
+    // the peer is gone.
+    let mut list = client.client_list().await.unwrap();
+    while list.lines().count() != 1 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        list = client.client_list().await.unwrap();
+    }
+}
+
+/// CLIENT KILL closes the connections matching its filters.
+#[tokio::test]
+async fn client_kill() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut victim = Client::connect(addr).await.unwrap();
+    victim.client_setname("victim").await.unwrap();
+
+    // Find the identifier and address of the victim.
+    let list = client.client_list().await.unwrap();
+    let line = list
+        .lines()
+        .find(|line| line.contains("name=victim"))
+        .unwrap();
+    let field = |name: &str| {
+        line.split(' ')
+            .find_map(|field| field.strip_prefix(name))
+            .unwrap()
+            .to_string()
+    };
+    let id: u64 = field("id=").parse().unwrap();
+    let victim_addr = field("addr=");
+
+    // Both filters must match.
+    assert_eq!(
+        0,
+        client
+            .client_kill(Some(id), Some("1.2.3.4:5"))
+            .await
+            .unwrap()
+    );
+    assert_eq!(0, client.client_kill(Some(id + 1000), None).await.unwrap());
+
+    assert_eq!(
+        1,
+        client.client_kill(None, Some(&victim_addr)).await.unwrap()
+    );
+    assert!(victim.ping(None).await.is_err());
+
+    // A connection killing itself still receives the reply.
+    let list = client.client_list().await.unwrap();
+    let line = list
+        .lines()
+        .find(|line| !line.contains("name=victim"))
+        .unwrap();
+    let own_id: u64 = line
+        .split(' ')
+        .find_map(|field| field.strip_prefix("id="))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(1, client.client_kill(Some(own_id), None).await.unwrap());
+    assert!(client.ping(None).await.is_err());
+}
+
+/// CLIENT PAUSE holds writes, or every command, until the pause ends on its
+/// own or with CLIENT UNPAUSE.
+#[tokio::test]
+async fn client_pause() {
+    let (addr, _) = start_server().await;
+    let mut admin = Client::connect(addr).await.unwrap();
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    // Reads go on while writes are paused
+    admin
+        .client_pause(Duration::from_millis(300), PauseMode::Write)
+        .await
+        .unwrap();
+    let started_at = Instant::now();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+    assert!(started_at.elapsed() < Duration::from_millis(200));
+
+    client.set("hello", "paused".into()).await.unwrap();
+    assert!(started_at.elapsed() >= Duration::from_millis(250));
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"paused", &value[..]);
+
+    // Reads are held too with ALL, until unpaused
+    admin
+        .client_pause(Duration::from_secs(60), PauseMode::All)
+        .await
+        .unwrap();
+    let get = tokio::spawn(async move { client.get("hello").await.unwrap() });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!get.is_finished());
+
+    admin.client_unpause().await.unwrap();
+    let value = get.await.unwrap().unwrap();
+    assert_eq!(b"paused", &value[..]);
+}
+
+/// WAIT reports no replica, after waiting for the timeout if replicas were
+/// requested.
+#[tokio::test]
+async fn wait() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+
+    let start = Instant::now();
+    assert_eq!(0, client.wait(0, Duration::from_secs(10)).await.unwrap());
+    assert_eq!(0, client.wait(1, Duration::ZERO).await.unwrap());
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let start = Instant::now();
+    assert_eq!(0, client.wait(1, Duration::from_millis(100)).await.unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+/// CONFIG changes the memory limit and policy at runtime.
+/// COMMAND lists the supported commands and COMMAND COUNT gives their number.
+#[tokio::test]
+async fn command_table() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let commands = client.command().await.unwrap();
+    assert_eq!(commands.len() as u64, client.command_count().await.unwrap());
+    assert!(commands.contains(&("get".to_string(), 2)));
+    assert!(commands.contains(&("command".to_string(), -1)));
+
+    assert!(client.command_docs(&["get"]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn config_get_and_set() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let parameters = client.config_get("maxmemory*").await.unwrap();
+    assert_eq!(
+        vec![
+            ("maxmemory".to_string(), "0".to_string()),
+            ("maxmemory-policy".to_string(), "allkeys-lru".to_string()),
+        ],
+        parameters
+    );
+    assert!(client.config_get("missing").await.unwrap().is_empty());
+
+    for key in ["k1", "k2", "k3", "k4"] {
+        client.set(key, "0123456789".into()).await.unwrap();
+    }
+
+    // Each key uses 12 bytes, lowering the limit evicts keys right away.
+    client.config_set("maxmemory", "30").await.unwrap();
+    assert_eq!(2, client.dbsize().await.unwrap());
+
+    client.config_set("maxmemory", "1kb").await.unwrap();
+    let parameters = client.config_get("maxmemory").await.unwrap();
+    assert_eq!(
+        vec![("maxmemory".to_string(), "1024".to_string())],
+        parameters
+    );
+
+    // Without eviction, writes are refused once the limit is exceeded.
+    client
+        .config_set("maxmemory-policy", "noeviction")
+        .await
+        .unwrap();
+    client.config_set("maxmemory", "30").await.unwrap();
+    client.set("k5", "0123456789".into()).await.unwrap();
+    assert_eq!(3, client.dbsize().await.unwrap());
+    let err = client.set("k6", "0123456789".into()).await.unwrap_err();
+    assert_eq!(
+        "OOM command not allowed when used memory > 'maxmemory'",
+        err.to_string()
+    );
+
+    // Removing the limit accepts writes again.
+    client.config_set("maxmemory", "0").await.unwrap();
+    client.set("k6", "0123456789".into()).await.unwrap();
+
+    assert!(client.config_set("maxmemory", "10xb").await.is_err());
+    assert!(client
+        .config_set("maxmemory-policy", "random")
+        .await
+        .is_err());
+    assert!(client.config_set("missing", "1").await.is_err());
+}
+
+#[tokio::test]
+async fn save_and_load_snapshot() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-snapshot", std::process::id()));
+    let config = server::Config {
+        dbfilename: Some(path.clone()),
+        ..Default::default()
+    };
+
+    let addr = start_server_with_config(config.clone()).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    client
+        .set_expires("later", "bar".into(), Duration::from_secs(3600))
+        .await
+        .unwrap();
+    client
+        .set_expires("soon", "bar".into(), Duration::from_millis(100))
+        .await
+        .unwrap();
+    client.select(1).await.unwrap();
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client
+        .hset("hash", vec![("field".into(), "value".into())])
+        .await
+        .unwrap();
+    client.sadd("set", vec!["member".into()]).await.unwrap();
+    client.save().await.unwrap();
+
+    // `soon` expires before the snapshot is loaded again.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let addr = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+    assert!(client.ttl("later").await.unwrap() > 3500);
+    assert!(client.get("soon").await.unwrap().is_none());
+    assert_eq!(2, client.dbsize().await.unwrap());
+
+    client.select(1).await.unwrap();
+    let list = client.lrange("list", 0, -1).await.unwrap();
+    assert_eq!(vec!["a", "b"], list);
+    assert_eq!(
+        b"value",
+        &client.hget("hash", "field").await.unwrap().unwrap()[..]
+    );
+    assert_eq!(vec!["member"], client.smembers("set").await.unwrap());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// SHUTDOWN is refused unless enabled, and then stops the server, writing a
+/// snapshot first with SAVE.
+#[tokio::test]
+async fn shutdown_command() {
+    let addr = start_server_with_config(server::Config::default()).await;
+    let client = Client::connect(addr).await.unwrap();
+    assert!(client.shutdown(false).await.is_err());
+    let mut client = Client::connect(addr).await.unwrap();
+    client.ping(None).await.unwrap();
+
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-shutdown", std::process::id()));
+    let config = server::Config {
+        dbfilename: Some(path.clone()),
+        enable_shutdown_command: true,
+        ..Default::default()
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(server::run_with_config(
+        listener,
+        std::future::pending::<()>(),
+        config.clone(),
+    ));
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+    client.shutdown(true).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(other.ping(None).await.is_err());
+
+    let addr = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn corrupt_snapshot_starts_empty() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-corrupt", std::process::id()));
+    std::fs::write(&path, b"MINIREDIS\x01\xfe").unwrap();
+
+    let config = server::Config {
+        dbfilename: Some(path.clone()),
+        ..Default::default()
+    };
+    let addr = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.dbsize().await.unwrap());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn append_only_file_is_replayed() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-aof", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = server::Config {
+        appendfilename: Some(path.clone()),
+        appendfsync: server::AppendFsync::Always,
+        ..Default::default()
+    };
+
+    let addr = start_server_with_config(config.clone()).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    client.set("gone", "bar".into()).await.unwrap();
+    client.del(&["gone"]).await.unwrap();
+    client.incr("counter").await.unwrap();
+    client.incr("counter").await.unwrap();
+    // Fails, so it is not logged.
+    assert!(client.incr("hello").await.is_err());
+    // Fails after writing, so it is logged.
+    let script = "set(KEYS[1], 'x'); incr(KEYS[1])";
+    assert!(client.eval(script, &["script"], vec![]).await.is_err());
+    client.select(1).await.unwrap();
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    // Blocking pops are logged as the pop they performed, or not at all.
+    client
+        .rpush("queue", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client.blpop(&["queue"], Duration::ZERO).await.unwrap();
+    let timeout = Duration::from_millis(10);
+    assert!(client.blpop(&["empty"], timeout).await.unwrap().is_none());
+
+    let addr = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+    assert!(client.get("gone").await.unwrap().is_none());
+    assert_eq!(b"2", &client.get("counter").await.unwrap().unwrap()[..]);
+    assert_eq!(b"x", &client.get("script").await.unwrap().unwrap()[..]);
+    assert_eq!(3, client.dbsize().await.unwrap());
+
+    client.select(1).await.unwrap();
+    let list = client.lrange("list", 0, -1).await.unwrap();
+    assert_eq!(vec!["a", "b"], list);
+    let queue = client.lrange("queue", 0, -1).await.unwrap();
+    assert_eq!(vec!["b"], queue);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn save_without_snapshot_file() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.save().await.unwrap_err();
+    assert_eq!("ERR no snapshot file configured", err.to_string());
+}
+
+/// With a TLS configuration, the server accepts TLS connections and refuses
+/// plaintext ones.
+#[tokio::test]
+async fn tls_connection() {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(cert.signing_key.serialize_der().into());
+    let tls = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.cert.der().clone()], key)
+        .unwrap();
+
+    let config = server::Config {
+        tls: Some(Arc::new(tls)),
+        ..Default::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert.cert.der().clone()).unwrap();
+
+    let mut client = Client::connect_tls(addr, "localhost", roots.clone())
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+
+    // The certificate is not valid for another name.
+    assert!(Client::connect_tls(addr, "example.com", roots)
+        .await
+        .is_err());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert!(client.ping(None).await.is_err());
+}
+
+/// A `ReconnectingClient` reconnects once the server is back, and gives up
+/// when it stays down.
+#[tokio::test]
+async fn reconnecting_client() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(async move { server::run(listener, stopped).await });
+
+    let policy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(20),
+    };
+    let mut client = ReconnectingClient::connect_with_policy(addr, policy)
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    // The reply to a cancelled command is not mistaken for the reply to the
+    // next one.
+    tokio::select! {
+        biased;
+        _ = client.get("hello") => panic!("the reply arrived before the command is cancelled"),
+        _ = std::future::ready(()) => {}
+    }
+    client.set("hello", "again".into()).await.unwrap();
+    assert_eq!(b"again", &client.get("hello").await.unwrap().unwrap()[..]);
+
+    // Restart the server, the connection to the first one is closed.
+    stop.send(()).unwrap();
+    server.await.unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(async move { server::run(listener, stopped).await });
+
+    // The new server starts empty.
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert_eq!(0, client.publish("foo", "bar".into()).await.unwrap());
+
+    stop.send(()).unwrap();
+    server.await.unwrap();
+
+    assert!(client.get("hello").await.is_err());
+    assert!(client.set("hello", "world".into()).await.is_err());
+}
+
+/// On shutdown, the server stops waiting for connections stuck on a peer once
+/// the shutdown timeout elapses.
+#[tokio::test]
+async fn shutdown_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        shutdown_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server =
+        tokio::spawn(async move { server::run_with_config(listener, stopped, config).await });
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .set("big", vec![b'x'; 16 * 1024 * 1024].into())
+        .await
+        .unwrap();
+
+    // Request the value without ever reading it, so the handler blocks
+    // writing the reply.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nbig\r\n")
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    stop.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // No new connections are accepted.
+    assert!(Client::connect(addr).await.is_err());
+}
+
+/// The subscriber stream reports dropped messages and keeps going.
+#[tokio::test]
+async fn subscriber_stream_reports_lag() {
+    // A server that confirms the subscription, reports dropped messages, then
+    // publishes a message.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 64];
+        let _ = socket.read(&mut request).await.unwrap();
+        socket
+            .write_all(
+                b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n\
+                  *3\r\n$6\r\nlagged\r\n$5\r\nhello\r\n:3\r\n\
+                  *3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n",
+            )
+            .await
+            .unwrap();
+    });
+
+    let client = Client::connect(addr).await.unwrap();
+    let subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    let stream = subscriber.into_stream();
+    tokio::pin!(stream);
+
+    let err = stream.next().await.unwrap().unwrap_err();
+    let lagged = err.downcast_ref::<LaggedError>().unwrap();
+    assert_eq!("hello", lagged.channel());
+    assert_eq!(3, lagged.skipped());
+
+    let message = stream.next().await.unwrap().unwrap();
+    assert_eq!("hello", message.channel);
+    assert_eq!(b"world", &message.content[..]);
+
+    // The server closed the connection.
+    assert!(stream.next().await.is_none());
+}
+
+/// A subscriber that does not keep up with publishers is told how many
+/// messages it missed.
+#[tokio::test]
+async fn slow_subscriber_is_notified_of_lag() {
+    let addr = start_server_with_config(server::Config {
+        channel_capacity: 2,
+        ..Default::default()
+    })
+    .await;
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let subscriber = subscriber.subscribe(vec!["hello".into()]).await.unwrap();
+
+    // Publish more than the connection and the channel can buffer while the
+    // subscriber is not reading.
+    let mut publisher = Client::connect(addr).await.unwrap();
+    for _ in 0..400 {
+        let message = vec![b'x'; 64 * 1024];
+        publisher.publish("hello", message.into()).await.unwrap();
+    }
+
+    let stream = subscriber.into_stream();
+    tokio::pin!(stream);
+
+    let mut skipped = 0;
+    while let Some(message) = stream.next().await {
+        if let Err(err) = message {
+            let lagged = err.downcast_ref::<LaggedError>().unwrap();
+            assert_eq!("hello", lagged.channel());
+            skipped = lagged.skipped();
+            break;
+        }
+    }
+
+    assert!(skipped > 0);
+}
+
+/// A channel configured with a larger capacity buffers a burst of messages the
+/// default capacity would drop. Existing subscribers keep their channel when
+/// the capacity changes.
+#[tokio::test]
+async fn channel_capacity_absorbs_bursts() {
+    let addr = start_server_with_config(server::Config {
+        channel_capacity: 2,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(2, client.pubsub_capacity("burst", None).await.unwrap());
+    assert_eq!(
+        512,
+        client.pubsub_capacity("burst", Some(512)).await.unwrap()
+    );
+    let err = client.pubsub_capacity("burst", Some(0)).await.unwrap_err();
+    assert_eq!("ERR capacity is out of range", err.to_string());
+
+    client.config_set("channel-capacity", "4").await.unwrap();
+    let parameters = client.config_get("channel-capacity").await.unwrap();
+    assert_eq!(vec![("channel-capacity".into(), "4".into())], parameters);
+    assert_eq!(512, client.pubsub_capacity("burst", None).await.unwrap());
+    assert_eq!(4, client.pubsub_capacity("other", None).await.unwrap());
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let subscriber = subscriber.subscribe(vec!["burst".into()]).await.unwrap();
+
+    // Changing the capacity leaves the current subscriber's channel alone.
+    client.pubsub_capacity("burst", Some(1)).await.unwrap();
+
+    // Publish more than the connection can buffer while the subscriber is not
+    // reading, but less than the channel can.
+    let mut publisher = Client::connect(addr).await.unwrap();
+    for _ in 0..400 {
+        let message = vec![b'x'; 64 * 1024];
+        publisher.publish("burst", message.into()).await.unwrap();
+    }
+
+    let stream = subscriber.into_stream();
+    tokio::pin!(stream);
+
+    for _ in 0..400 {
+        let message = stream.next().await.unwrap().unwrap();
+        assert_eq!("burst", message.channel);
+    }
+}
+
+/// Many tasks sharing a pool never open more connections than the pool size.
+#[tokio::test]
+async fn pool_bounds_connections() {
+    let (addr, _) = start_server().await;
+
+    // Count the connections opened to the server by going through a proxy.
+    let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy_addr = proxy.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut inbound, _) = proxy.accept().await.unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut outbound = TcpStream::connect(addr).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+            });
+        }
+    });
+
+    let pool = Pool::new(proxy_addr, 4);
+
+    let tasks: Vec<_> = (0..50)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..20 {
+                    let mut client = pool.get().await.unwrap();
+                    client.incr("counter").await.unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut client = pool.get().await.unwrap();
+    assert_eq!(b"1000", &client.get("counter").await.unwrap().unwrap()[..]);
+
+    let opened = connections.load(Ordering::SeqCst);
+    assert!((1..=4).contains(&opened), "opened {} connections", opened);
+}
+
+/// Clients whose request was cancelled before the reply was read, or whose
+/// connection state changed, are not handed out again.
+#[tokio::test]
+async fn pool_discards_unusable_clients() {
+    let addr = start_server_with_config(server::Config {
+        enable_debug_command: true,
+        ..Default::default()
+    })
+    .await;
+    let pool = Pool::new(addr, 1);
+
+    let mut client = pool.get().await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let cancelled = client.debug_sleep(Duration::from_millis(100));
+    assert!(tokio::time::timeout(Duration::from_millis(10), cancelled)
+        .await
+        .is_err());
+    drop(client);
+
+    // The reply to the cancelled request is not mistaken for this one.
+    let mut client = pool.get().await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+
+    client.select(1).await.unwrap();
+    drop(client);
+    let mut client = pool.get().await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+
+    client.hello(Some(3)).await.unwrap();
+    drop(client);
+    let mut client = pool.get().await.unwrap();
+    let info = client.hello(None).await.unwrap();
+    let proto = info.iter().find(|(key, _)| key == "proto").unwrap();
+    assert_eq!("2", proto.1.to_string());
+}
 
 /// A PING PONG test without message provided.
 /// It should return "PONG".
 #[tokio::test]
-async fn ping_pong_without_message() {
+async fn ping_pong_without_message() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let pong = client.ping(None).await.unwrap();
+    assert_eq!(b"PONG", &pong[..]);
+}
+
+/// A PING PONG test with message provided.
+/// It should return the message.
+#[tokio::test]
+async fn ping_pong_with_message() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let pong = client.ping(Some("你好世界".into())).await.unwrap();
+    assert_eq!("你好世界".as_bytes(), &pong[..]);
+}
+
+/// ECHO returns binary messages byte for byte, including line breaks and null
+/// bytes.
+#[tokio::test]
+async fn echo_binary_message() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let msg = Bytes::from_static(b"hello\r\nworld\0");
+    assert_eq!(msg, client.echo(msg.clone()).await.unwrap());
+}
+
+/// Commands without a dedicated method can be sent as raw arguments, which
+/// are binary safe, and their reply is returned as is.
+#[tokio::test]
+async fn raw_command() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let reply = client
+        .raw_command(&[b"SET", b"hello", b"\xff\r\n\0"])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Simple(ref s) if s == "OK"));
+
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(&b"\xff\r\n\0"[..], &value[..]);
+
+    let reply = client.raw_command(&[b"strlen", b"hello"]).await.unwrap();
+    assert!(matches!(reply, Frame::Integer(4)));
+
+    let err = client.raw_command(&[b"nosuchcommand"]).await.unwrap_err();
+    assert_eq!("ERR unknown command 'nosuchcommand'", err.to_string());
+}
+
+/// HELLO reports the negotiated protocol and rejects unknown versions.
+#[tokio::test]
+async fn hello_negotiates_protocol() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let proto = |info: &[(String, Frame)]| {
+        info.iter()
+            .find(|(key, _)| key == "proto")
+            .map(|(_, value)| value.to_string())
+    };
+
+    // RESP2 is the default
+    let info = client.hello(None).await.unwrap();
+    assert_eq!(Some("2".to_string()), proto(&info));
+
+    let info = client.hello(Some(3)).await.unwrap();
+    assert_eq!(Some("3".to_string()), proto(&info));
+
+    let err = client.hello(Some(4)).await.unwrap_err();
+    assert_eq!("NOPROTO unsupported protocol version", err.to_string());
+
+    // Regular commands still work after switching
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+}
+
+/// Subscribers receive messages whether the connection uses RESP2 or RESP3.
+#[tokio::test]
+async fn receive_message_resp3() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.hello(Some(3)).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    tokio::spawn(async move {
+        let mut client = Client::connect(addr).await.unwrap();
+        client.publish("hello", "world".into()).await.unwrap()
+    });
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"world", &message.content[..])
+}
+
+/// `PUBSUB` reports the channels with subscribers and how many they have.
+#[tokio::test]
+async fn pubsub_introspection() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut first = client
+        .subscribe(vec!["one".into(), "two".into()])
+        .await
+        .unwrap();
+
+    let client = Client::connect(addr).await.unwrap();
+    let _second = client.subscribe(vec!["two".into()]).await.unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(
+        vec![
+            ("one".to_string(), 1),
+            ("two".to_string(), 2),
+            ("three".to_string(), 0)
+        ],
+        client
+            .pubsub_numsub(&["one", "two", "three"])
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        vec!["one", "two"],
+        client.pubsub_channels(None).await.unwrap()
+    );
+    assert_eq!(
+        vec!["two"],
+        client.pubsub_channels(Some("t*")).await.unwrap()
+    );
+
+    // Channels left by all their subscribers are no longer reported
+    first.unsubscribe(&["one".to_string()]).await.unwrap();
+    assert_eq!(vec!["two"], client.pubsub_channels(None).await.unwrap());
+    assert_eq!(
+        vec![("one".to_string(), 0)],
+        client.pubsub_numsub(&["one"]).await.unwrap()
+    );
+}
+
+/// Messages published in bursts are received whole, whatever the size of the
+/// write buffer of the server.
+#[tokio::test]
+async fn receive_message_burst() {
+    for write_buffer_size in [0, 7, 8 * 1024] {
+        let config = server::Config {
+            write_buffer_size,
+            ..Default::default()
+        };
+        let addr = start_server_with_config(config).await;
+
+        let client = Client::connect(addr).await.unwrap();
+        let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+        let mut publisher = Client::connect(addr).await.unwrap();
+        let mut pipeline = publisher.pipeline();
+        for i in 0..500 {
+            pipeline.publish("hello", format!("message {}", i).into());
+        }
+        pipeline.execute().await.unwrap();
+
+        for i in 0..500 {
+            let message = subscriber.next_message().await.unwrap().unwrap();
+            assert_eq!("hello", &message.channel);
+            assert_eq!(format!("message {}", i).as_bytes(), &message.content[..]);
+        }
+    }
+}
+
+/// A basic "hello world" style test. A server instance is started in a
+/// background task. A client instance is then established and set and get
+/// commands are sent to the server. The response is then evaluated
+#[tokio::test]
+async fn key_value_get_set() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+
+    let keys = vec![String::from("hello")];
+    let value = client.multiget(keys).await.unwrap();
+    assert_eq!(b"world", &value[0].as_ref().unwrap()[..]);
+    assert_eq!(1, value.len());
+
+    let keys = vec![
+        String::from("hello"),
+        String::from("hello"),
+        String::from("world"),
+    ];
+    let value = client.multiget(keys).await.unwrap();
+
+    assert_eq!(b"world", &value[0].as_ref().expect("")[..]);
+    assert_eq!(b"world", &value[1].as_ref().expect("")[..]);
+    assert!(value[2].is_none());
+    assert_eq!(3, value.len());
+
+    assert!(client.multiget(vec![]).await.unwrap().is_empty());
+}
+
+/// MGET returns `Some` for present keys and `None` for the others, in order.
+#[tokio::test]
+async fn mget() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("a", "1".into()).await.unwrap();
+    client.set("c", "3".into()).await.unwrap();
+    client.rpush("list", vec!["x".into()]).await.unwrap();
+
+    let values = client.mget(&["a", "b", "c", "list", "a"]).await.unwrap();
+    assert_eq!(
+        vec![
+            Some("1".into()),
+            None,
+            Some("3".into()),
+            None,
+            Some("1".into())
+        ],
+        values
+    );
+
+    assert!(client.mget(&[]).await.unwrap().is_empty());
+}
+
+/// MSET writes every pair, MSETNX writes all pairs or none.
+#[tokio::test]
+async fn mset_and_mset_nx() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("a", "old".into(), Duration::from_secs(3600))
+        .await
+        .unwrap();
+    let pairs = vec![("a".to_string(), "1".into()), ("b".to_string(), "2".into())];
+    client.mset(pairs).await.unwrap();
+    let values = client.multiget(vec!["a".into(), "b".into()]).await.unwrap();
+    assert_eq!(vec![Some("1".into()), Some("2".into())], values);
+    assert_eq!(-1, client.ttl("a").await.unwrap());
+
+    // `b` exists, so `c` is not written either.
+    let pairs = vec![("c".to_string(), "3".into()), ("b".to_string(), "4".into())];
+    assert!(!client.mset_nx(pairs).await.unwrap());
+    assert_eq!(None, client.get("c").await.unwrap());
+    assert_eq!(Some("2".into()), client.get("b").await.unwrap());
+
+    let pairs = vec![("c".to_string(), "3".into()), ("d".to_string(), "4".into())];
+    assert!(client.mset_nx(pairs).await.unwrap());
+    let values = client.multiget(vec!["c".into(), "d".into()]).await.unwrap();
+    assert_eq!(vec![Some("3".into()), Some("4".into())], values);
+}
+
+/// A pipeline sends several commands at once and returns the replies in
+/// order, including error replies.
+#[tokio::test]
+async fn pipeline_replies_in_order() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut pipeline = client.pipeline();
+    pipeline
+        .set("hello", "world".into())
+        .get("hello")
+        .incr("hello")
+        .incr("counter")
+        .get("missing");
+    assert_eq!(5, pipeline.len());
+
+    let replies = pipeline.execute().await.unwrap();
+    assert_eq!(5, replies.len());
+    assert_eq!("OK", replies[0].as_ref().unwrap().to_string());
+    assert_eq!("world", replies[1].as_ref().unwrap().to_string());
+    let err = replies[2].as_ref().unwrap_err();
+    assert_eq!(
+        "ERR value is not an integer or out of range",
+        err.to_string()
+    );
+    assert!(err.is::<ServerError>());
+    assert_eq!("1", replies[3].as_ref().unwrap().to_string());
+    assert!(matches!(replies[4], Ok(Frame::Null)));
+
+    // The pipeline is empty once executed and the client is still usable
+    assert!(pipeline.is_empty());
+    assert_eq!(2, client.incr("counter").await.unwrap());
+}
+
+/// DEBUG SLEEP only holds up the connection issuing it, DEBUG OBJECT describes
+/// a value. Both are refused unless enabled.
+#[tokio::test]
+async fn debug_commands() {
+    let addr = start_server_with_config(server::Config {
+        enable_debug_command: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut sleeper = Client::connect(addr).await.unwrap();
+    let sleeping = tokio::spawn(async move {
+        let start = Instant::now();
+        sleeper
+            .debug_sleep(Duration::from_millis(500))
+            .await
+            .unwrap();
+        start.elapsed()
+    });
+
+    // Other connections are served in the meantime.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let start = Instant::now();
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client
+        .lpush("list", vec!["a".into(), "bc".into()])
+        .await
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_millis(250));
+
+    assert!(sleeping.await.unwrap() >= Duration::from_millis(500));
+
+    let description = client.debug_object("hello").await.unwrap();
+    assert_eq!("type:string length:5 expires:true", description);
+    let description = client.debug_object("list").await.unwrap();
+    assert_eq!("type:list length:3 expires:false", description);
+    assert!(client.debug_object("missing").await.is_err());
+
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.debug_object("hello").await.is_err());
+    assert!(client.debug_sleep(Duration::ZERO).await.is_err());
+}
+
+/// Commands slower than the threshold are recorded in a bounded slow log,
+/// most recent first.
+#[tokio::test]
+async fn slowlog_records_slow_commands() {
+    let addr = start_server_with_config(server::Config {
+        enable_debug_command: true,
+        slowlog_log_slower_than: Some(Duration::from_millis(50)),
+        slowlog_max_len: 2,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.client_setname("slow").await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    client.debug_sleep(Duration::from_millis(60)).await.unwrap();
+    assert_eq!(1, client.slowlog_len().await.unwrap());
+
+    let entries = client.slowlog_get(None).await.unwrap();
+    assert_eq!(1, entries.len());
+    assert_eq!(0, entries[0].id);
+    assert_eq!(b"debug", &entries[0].args[0][..]);
+    assert_eq!(b"sleep", &entries[0].args[1][..]);
+    assert!(entries[0].duration >= Duration::from_millis(60));
+    assert_eq!("slow", entries[0].name);
+    assert!(!entries[0].addr.is_empty());
+
+    // Only the most recent entries are kept.
+    for _ in 0..2 {
+        client.debug_sleep(Duration::from_millis(60)).await.unwrap();
+    }
+    let entries = client.slowlog_get(Some(-1)).await.unwrap();
+    let ids: Vec<_> = entries.iter().map(|entry| entry.id).collect();
+    assert_eq!(vec![2, 1], ids);
+    assert_eq!(1, client.slowlog_get(Some(1)).await.unwrap().len());
+    assert!(client.slowlog_get(Some(-2)).await.is_err());
+
+    client.slowlog_reset().await.unwrap();
+    assert_eq!(0, client.slowlog_len().await.unwrap());
+}
+
+/// Long arguments and long commands are truncated in the slow log.
+#[tokio::test]
+async fn slowlog_truncates_arguments() {
+    let addr = start_server_with_config(server::Config {
+        slowlog_log_slower_than: Some(Duration::ZERO),
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", vec![b'a'; 200].into()).await.unwrap();
+    let keys: Vec<_> = (0..40).map(|i| format!("key{}", i)).collect();
+    let keys: Vec<_> = keys.iter().map(String::as_str).collect();
+    client.del(&keys).await.unwrap();
+
+    let entries = client.slowlog_get(Some(2)).await.unwrap();
+
+    let del = &entries[0].args;
+    assert_eq!(32, del.len());
+    assert_eq!(b"key29", &del[30][..]);
+    assert_eq!(b"... (10 more arguments)", &del[31][..]);
+
+    let set = &entries[1].args;
+    let mut expected = vec![b'a'; 128];
+    expected.extend_from_slice(b"... (72 more bytes)");
+    assert_eq!(expected, set[2]);
+}
+
+/// Passwords sent with AUTH are never recorded in the slow log.
+#[tokio::test]
+async fn slowlog_redacts_passwords() {
+    let addr = start_server_with_config(server::Config {
+        requirepass: Some("secret".to_string()),
+        slowlog_log_slower_than: Some(Duration::ZERO),
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.auth("secret").await.unwrap();
+
+    // Commands are only recorded once authenticated.
+    assert!(client.auth("wrong").await.is_err());
+    client.auth("secret").await.unwrap();
+
+    let entries = client.slowlog_get(Some(-1)).await.unwrap();
+    assert_eq!(2, entries.len());
+    for entry in entries {
+        assert_eq!(vec!["auth", "(redacted)"], entry.args);
+    }
+}
+
+/// OBJECT ENCODING reports strings holding an integer as `int`, OBJECT
+/// IDLETIME reports the time since the key was last used without resetting it.
+#[tokio::test]
+async fn object_encoding_and_idletime() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("int", "-1234".into()).await.unwrap();
+    client.set("padded", "01234".into()).await.unwrap();
+    client.set("raw", "hello".into()).await.unwrap();
+    assert_eq!("int", client.object_encoding("int").await.unwrap());
+    assert_eq!("raw", client.object_encoding("padded").await.unwrap());
+    assert_eq!("raw", client.object_encoding("raw").await.unwrap());
+    assert!(client.object_encoding("missing").await.is_err());
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(
+        Duration::from_secs(1),
+        client.object_idletime("int").await.unwrap()
+    );
+    assert_eq!(
+        Duration::from_secs(1),
+        client.object_idletime("int").await.unwrap()
+    );
+
+    client.get("int").await.unwrap();
+    assert_eq!(Duration::ZERO, client.object_idletime("int").await.unwrap());
+    assert!(client.object_idletime("missing").await.is_err());
+}
+
+/// A command fails with a `TimeoutError` when the server does not respond in
+/// time.
+#[tokio::test]
+async fn command_times_out() {
+    // A server that accepts connections but never responds
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        std::future::pending::<()>().await
+    });
+
+    let mut client = Client::connect(addr)
+        .await
+        .unwrap()
+        .with_timeout(Duration::from_millis(50));
+
+    let err = client.get("hello").await.unwrap_err();
+    let err = err.downcast_ref::<TimeoutError>().unwrap();
+    assert_eq!(Duration::from_millis(50), err.timeout());
+}
+
+/// The observer sees every frame sent and received, in order, including those
+/// of pipelines and error replies.
+#[tokio::test]
+async fn observer() {
+    let (addr, _) = start_server().await;
+
+    let frames = Arc::new(Mutex::new(vec![]));
+    let recorded = frames.clone();
+    let mut client = Client::connect(addr)
+        .await
+        .unwrap()
+        .with_observer(move |frame, direction| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((direction, frame.to_string()));
+        });
+
+    client.set("hello", "world".into()).await.unwrap();
+    client.get("hello").await.unwrap();
+    client.lpush("hello", vec!["a".into()]).await.unwrap_err();
+    client
+        .pipeline()
+        .get("hello")
+        .get("missing")
+        .execute()
+        .await
+        .unwrap();
+
+    let wrongtype =
+        "error: WRONGTYPE Operation against a key holding the wrong kind of value".to_string();
+    let expected = vec![
+        (Direction::Sent, "set hello world".to_string()),
+        (Direction::Received, "OK".to_string()),
+        (Direction::Sent, "get hello".to_string()),
+        (Direction::Received, "world".to_string()),
+        (Direction::Sent, "lpush hello a".to_string()),
+        (Direction::Received, wrongtype),
+        (Direction::Sent, "get hello".to_string()),
+        (Direction::Sent, "get missing".to_string()),
+        (Direction::Received, "world".to_string()),
+        (Direction::Received, "(nil)".to_string()),
+    ];
+    assert_eq!(expected, *frames.lock().unwrap());
+}
+
+/// INCR and DECR treat a missing key as `0` and reject values that are not
+/// integers without closing the connection.
+#[tokio::test]
+async fn incr_decr() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(1, client.incr("counter").await.unwrap());
+    assert_eq!(2, client.incr("counter").await.unwrap());
+    assert_eq!(1, client.decr("counter").await.unwrap());
+    assert_eq!(-1, client.decr("missing").await.unwrap());
+
+    let value = client.get("counter").await.unwrap().unwrap();
+    assert_eq!(b"1", &value[..]);
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client.incr("hello").await.unwrap_err();
+    assert_eq!(
+        "ERR value is not an integer or out of range",
+        err.to_string()
+    );
+    let err = err.downcast_ref::<ServerError>().unwrap();
+    assert_eq!("ERR", err.kind());
+    assert_eq!("ERR value is not an integer or out of range", err.message());
+
+    // The connection is still usable
+    assert_eq!(2, client.incr("counter").await.unwrap());
+}
+
+/// INCRBY, DECRBY and INCRBYFLOAT add arbitrary amounts.
+#[tokio::test]
+async fn incr_by() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(10, client.incr_by("counter", 10).await.unwrap());
+    assert_eq!(7, client.decr_by("counter", 3).await.unwrap());
+    assert_eq!(-5, client.decr_by("missing", 5).await.unwrap());
+
+    client
+        .set("max", i64::MAX.to_string().into())
+        .await
+        .unwrap();
+    assert!(client.incr_by("max", 1).await.is_err());
+    assert!(client.decr_by("counter", i64::MIN).await.is_err());
+
+    assert_eq!(10.5, client.incr_by_float("float", 10.5).await.unwrap());
+    assert_eq!(5.0, client.incr_by_float("float", -5.5).await.unwrap());
+    // Trailing zeros and exponents are not stored.
+    let value = client.get("float").await.unwrap().unwrap();
+    assert_eq!(b"5", &value[..]);
+    assert_eq!(1e20, client.incr_by_float("big", 1e20).await.unwrap());
+    let value = client.get("big").await.unwrap().unwrap();
+    assert_eq!(b"100000000000000000000", &value[..]);
+
+    // Integers are valid floats.
+    assert_eq!(7.25, client.incr_by_float("counter", 0.25).await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client.incr_by_float("hello", 1.0).await.unwrap_err();
+    assert_eq!("ERR value is not a valid float", err.to_string());
+    assert!(client.incr_by_float("big", f64::INFINITY).await.is_err());
+    assert!(client.incr_by("hello", 1).await.is_err());
+}
+
+/// DEL removes the given keys and reports how many existed.
+#[tokio::test]
+async fn del_multiple_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("a", "1".into()).await.unwrap();
+    client.set("b", "2".into()).await.unwrap();
+
+    assert_eq!(2, client.del(&["a", "b", "c"]).await.unwrap());
+    assert!(client.get("a").await.unwrap().is_none());
+    assert!(client.get("b").await.unwrap().is_none());
+    assert_eq!(0, client.del(&["a"]).await.unwrap());
+}
+
+/// UNLINK replies before the value it removed has been freed.
+#[tokio::test(flavor = "multi_thread")]
+async fn unlink_frees_in_background() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let values: Vec<Bytes> = (0..500_000).map(|i| i.to_string().into()).collect();
+    client.rpush("deleted", values.clone()).await.unwrap();
+    client.rpush("unlinked", values).await.unwrap();
+
+    // DEL frees the list before replying, UNLINK only removes the key.
+    let start = Instant::now();
+    assert_eq!(1, client.del(&["deleted"]).await.unwrap());
+    let deleted = start.elapsed();
+
+    let start = Instant::now();
+    assert_eq!(1, client.unlink(&["unlinked", "missing"]).await.unwrap());
+    let unlinked = start.elapsed();
+
+    assert!(
+        unlinked < deleted,
+        "UNLINK took {:?}, DEL took {:?}",
+        unlinked,
+        deleted
+    );
+    assert_eq!(0, client.exists(&["unlinked"]).await.unwrap());
+    assert_eq!(0, client.unlink(&["unlinked"]).await.unwrap());
+}
+
+/// RANDOMKEY returns any live key, or nil once the database is empty.
+#[tokio::test]
+async fn randomkey() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.randomkey().await.unwrap());
+
+    client
+        .set_expires("expired", "1".into(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(None, client.randomkey().await.unwrap());
+
+    for key in ["a", "b", "c"] {
+        client.set(key, "1".into()).await.unwrap();
+    }
+
+    let mut seen = Vec::new();
+    for _ in 0..100 {
+        let key = client.randomkey().await.unwrap().unwrap();
+        if !seen.contains(&key) {
+            seen.push(key);
+        }
+    }
+    seen.sort();
+    assert_eq!(vec!["a", "b", "c"], seen);
+}
+
+/// EXISTS counts existing keys, including duplicates, and ignores expired
+/// keys.
+#[tokio::test]
+async fn exists_counts_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("a", "1".into()).await.unwrap();
+    client
+        .set_expires("b", "2".into(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert_eq!(2, client.exists(&["a", "a", "b", "c"]).await.unwrap());
+}
+
+/// TYPE reports `string` for keys written by SET and `none` for missing keys.
+#[tokio::test]
+async fn value_type() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!("none", client.value_type("hello").await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!("string", client.value_type("hello").await.unwrap());
+}
+
+/// Lists are created on push, support pops from both ends and ranges with
+/// negative indices, and are removed once empty.
+#[tokio::test]
+async fn list_push_pop_range() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let len = client
+        .rpush("list", vec!["b".into(), "c".into()])
+        .await
+        .unwrap();
+    assert_eq!(2, len);
+    assert_eq!(3, client.lpush("list", vec!["a".into()]).await.unwrap());
+    assert_eq!("list", client.value_type("list").await.unwrap());
+
+    let all = client.lrange("list", 0, -1).await.unwrap();
+    assert_eq!(vec!["a", "b", "c"], all);
+    let tail = client.lrange("list", -2, 100).await.unwrap();
+    assert_eq!(vec!["b", "c"], tail);
+    assert!(client.lrange("list", 2, 1).await.unwrap().is_empty());
+
+    assert_eq!(Some("a".into()), client.lpop("list").await.unwrap());
+    assert_eq!(Some("c".into()), client.rpop("list").await.unwrap());
+    assert_eq!(Some("b".into()), client.rpop("list").await.unwrap());
+    assert_eq!(None, client.lpop("list").await.unwrap());
+    assert_eq!("none", client.value_type("list").await.unwrap());
+}
+
+/// LPOS finds matches from either end, skipping matches before the rank.
+#[tokio::test]
+async fn lpos() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let values = ["a", "b", "c", "b", "d", "b"];
+    let values = values.iter().map(|v| Bytes::from(*v)).collect();
+    client.rpush("list", values).await.unwrap();
+
+    assert_eq!(
+        Some(1),
+        client.lpos("list", "b".into(), None).await.unwrap()
+    );
+    assert_eq!(
+        Some(3),
+        client.lpos("list", "b".into(), Some(2)).await.unwrap()
+    );
+    assert_eq!(
+        Some(5),
+        client.lpos("list", "b".into(), Some(-1)).await.unwrap()
+    );
+    assert_eq!(
+        Some(1),
+        client.lpos("list", "b".into(), Some(-3)).await.unwrap()
+    );
+    assert_eq!(
+        None,
+        client.lpos("list", "b".into(), Some(4)).await.unwrap()
+    );
+    assert_eq!(
+        None,
+        client.lpos("list", "b".into(), Some(-4)).await.unwrap()
+    );
+    assert_eq!(None, client.lpos("list", "x".into(), None).await.unwrap());
+    assert_eq!(
+        None,
+        client.lpos("missing", "b".into(), None).await.unwrap()
+    );
+
+    let all = client
+        .lpos_count("list", "b".into(), None, 0)
+        .await
+        .unwrap();
+    assert_eq!(vec![1, 3, 5], all);
+    let two = client
+        .lpos_count("list", "b".into(), Some(-1), 2)
+        .await
+        .unwrap();
+    assert_eq!(vec![5, 3], two);
+    let none = client
+        .lpos_count("list", "x".into(), None, 0)
+        .await
+        .unwrap();
+    assert!(none.is_empty());
+
+    assert!(client.lpos("list", "b".into(), Some(0)).await.is_err());
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.lpos("hello", "b".into(), None).await.is_err());
+}
+
+/// LPUSHX and RPUSHX only push to lists that already exist, and never create
+/// the key.
+#[tokio::test]
+async fn list_push_existing() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.lpushx("queue", vec!["a".into()]).await.unwrap());
+    assert_eq!(0, client.rpushx("queue", vec!["a".into()]).await.unwrap());
+    assert_eq!(0, client.exists(&["queue"]).await.unwrap());
+
+    client.rpush("queue", vec!["b".into()]).await.unwrap();
+    let values = vec!["a".into(), "0".into()];
+    assert_eq!(3, client.lpushx("queue", values).await.unwrap());
+    let values = vec!["c".into(), "d".into()];
+    assert_eq!(5, client.rpushx("queue", values).await.unwrap());
+    let range = client.lrange("queue", 0, -1).await.unwrap();
+    assert_eq!(vec!["0", "a", "b", "c", "d"], range);
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.lpushx("hello", vec!["a".into()]).await.is_err());
+    assert!(client.rpushx("hello", vec!["a".into()]).await.is_err());
+}
+
+/// LLEN, LSET and LINSERT edit lists in place, with negative indices counting
+/// from the tail.
+#[tokio::test]
+async fn list_edit() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.llen("list").await.unwrap());
+    assert!(client.lset("list", 0, "x".into()).await.is_err());
+    assert_eq!(
+        Some(0),
+        client
+            .linsert("list", InsertPosition::Before, "a".into(), "x".into())
+            .await
+            .unwrap()
+    );
+    assert_eq!(0, client.llen("list").await.unwrap());
+
+    let values = ["a", "b", "c"];
+    let values = values.iter().map(|v| Bytes::from(*v)).collect();
+    client.rpush("list", values).await.unwrap();
+    assert_eq!(3, client.llen("list").await.unwrap());
+
+    client.lset("list", 0, "A".into()).await.unwrap();
+    client.lset("list", -1, "C".into()).await.unwrap();
+    assert!(client.lset("list", 3, "x".into()).await.is_err());
+    assert!(client.lset("list", -4, "x".into()).await.is_err());
+    let all = client.lrange("list", 0, -1).await.unwrap();
+    assert_eq!(vec!["A", "b", "C"], all);
+
+    assert_eq!(
+        Some(4),
+        client
+            .linsert("list", InsertPosition::Before, "A".into(), "x".into())
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        Some(5),
+        client
+            .linsert("list", InsertPosition::After, "C".into(), "y".into())
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        Some(6),
+        client
+            .linsert("list", InsertPosition::After, "b".into(), "b".into())
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        client
+            .linsert("list", InsertPosition::After, "z".into(), "x".into())
+            .await
+            .unwrap()
+    );
+    let all = client.lrange("list", 0, -1).await.unwrap();
+    assert_eq!(vec!["x", "A", "b", "b", "C", "y"], all);
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.llen("hello").await.is_err());
+    assert!(client.lset("hello", 0, "x".into()).await.is_err());
+}
+
+/// BLPOP and BRPOP pop right away from the first non-empty list, or wait for
+/// a value to be pushed.
+#[tokio::test]
+async fn blocking_pop() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .rpush("list", vec!["a".into(), "b".into(), "c".into()])
+        .await
+        .unwrap();
+    let timeout = Duration::from_secs(1);
+    let popped = client.blpop(&["empty", "list"], timeout).await.unwrap();
+    assert_eq!(Some(("list".to_string(), "a".into())), popped);
+    let popped = client.brpop(&["list"], timeout).await.unwrap();
+    assert_eq!(Some(("list".to_string(), "c".into())), popped);
+
+    // Nothing is pushed, the timeout elapses.
+    let start = Instant::now();
+    let timeout = Duration::from_millis(50);
+    assert!(client.blpop(&["empty"], timeout).await.unwrap().is_none());
+    assert!(start.elapsed() >= timeout);
+
+    // Two connections wait on the same list, each pushed value goes to a
+    // single one of them.
+    let mut waiters = Vec::new();
+    for _ in 0..2 {
+        let mut client = Client::connect(addr).await.unwrap();
+        waiters.push(tokio::spawn(async move {
+            client.blpop(&["queue"], Duration::ZERO).await.unwrap()
+        }));
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Waiting connections do not keep others from applying commands.
+    client.rpush("queue", vec!["x".into()]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let finished = waiters.iter().filter(|waiter| waiter.is_finished()).count();
+    assert_eq!(1, finished);
+
+    client.rpush("queue", vec!["y".into()]).await.unwrap();
+    let mut popped = Vec::new();
+    for waiter in waiters {
+        let (key, value) = waiter.await.unwrap().unwrap();
+        assert_eq!("queue", key);
+        popped.push(value);
+    }
+    popped.sort();
+    assert_eq!(vec!["x", "y"], popped);
+    assert_eq!(0, client.exists(&["queue"]).await.unwrap());
+
+    // A timeout too far in the future to be a deadline waits indefinitely.
+    let mut waiter = Client::connect(addr).await.unwrap();
+    let waiter = tokio::spawn(async move {
+        let timeout = Duration::from_secs_f64(1e19);
+        waiter.blpop(&["queue"], timeout).await.unwrap()
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiter.is_finished());
+    client.rpush("queue", vec!["z".into()]).await.unwrap();
+    let popped = waiter.await.unwrap();
+    assert_eq!(Some(("queue".to_string(), "z".into())), popped);
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.blpop(&["hello"], timeout).await.is_err());
+}
+
+/// List commands reject keys holding strings, and string commands reject keys
+/// holding lists.
+#[tokio::test]
+async fn list_wrong_type() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client.lpush("hello", vec!["a".into()]).await.unwrap_err();
+    assert_eq!(WRONGTYPE, err.to_string());
+
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    let err = client.get("list").await.unwrap_err();
+    assert_eq!(WRONGTYPE, err.to_string());
+}
+
+/// LMPOP pops from the first non-empty list, removing it once emptied.
+#[tokio::test]
+async fn list_multi_pop() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // Every list is empty
+    let popped = client
+        .lmpop(&["a", "b"], ListEnd::Left, None)
+        .await
+        .unwrap();
+    assert_eq!(None, popped);
+
+    client
+        .rpush("b", vec!["1".into(), "2".into(), "3".into(), "4".into()])
+        .await
+        .unwrap();
+    client.rpush("c", vec!["x".into()]).await.unwrap();
+
+    // A single element by default, from the first non-empty list
+    let popped = client
+        .lmpop(&["a", "b", "c"], ListEnd::Left, None)
+        .await
+        .unwrap();
+    assert_eq!(Some(("b".to_string(), vec![Bytes::from("1")])), popped);
+
+    let popped = client
+        .lmpop(&["a", "b", "c"], ListEnd::Right, Some(2))
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(("b".to_string(), vec![Bytes::from("4"), Bytes::from("3")])),
+        popped
+    );
+
+    // The count may exceed the length of the list
+    let popped = client
+        .lmpop(&["b", "c"], ListEnd::Left, Some(10))
+        .await
+        .unwrap();
+    assert_eq!(Some(("b".to_string(), vec![Bytes::from("2")])), popped);
+    assert_eq!("none", client.value_type("b").await.unwrap());
+
+    let popped = client
+        .lmpop(&["b", "c"], ListEnd::Right, Some(10))
+        .await
+        .unwrap();
+    assert_eq!(Some(("c".to_string(), vec![Bytes::from("x")])), popped);
+    assert_eq!("none", client.value_type("c").await.unwrap());
+
+    let err = client
+        .lmpop(&["b"], ListEnd::Left, Some(0))
+        .await
+        .unwrap_err();
+    assert_eq!("ERR count should be greater than 0", err.to_string());
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client
+        .lmpop(&["missing", "hello"], ListEnd::Left, None)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+/// Hash fields can be set, read, listed and removed. The key is removed with
+/// its last field.
+#[tokio::test]
+async fn hash_set_get_del() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let fields = vec![("a".to_string(), "1".into()), ("b".to_string(), "2".into())];
+    assert_eq!(2, client.hset("hash", fields).await.unwrap());
+
+    let fields = vec![("b".to_string(), "3".into()), ("c".to_string(), "4".into())];
+    assert_eq!(1, client.hset("hash", fields).await.unwrap());
+    assert_eq!("hash", client.value_type("hash").await.unwrap());
+
+    assert_eq!(Some("3".into()), client.hget("hash", "b").await.unwrap());
+    assert_eq!(None, client.hget("hash", "z").await.unwrap());
+
+    let mut all = client.hgetall("hash").await.unwrap();
+    all.sort();
+    assert_eq!(
+        vec![
+            ("a".to_string(), "1".into()),
+            ("b".to_string(), "3".into()),
+            ("c".to_string(), "4".into()),
+        ],
+        all
+    );
+
+    assert_eq!(2, client.hdel("hash", &["a", "b", "z"]).await.unwrap());
+    assert_eq!(1, client.hdel("hash", &["c"]).await.unwrap());
+    assert_eq!("none", client.value_type("hash").await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.hget("hello", "a").await.is_err());
+}
+
+/// The fields and values of a hash can be listed and counted. Missing keys
+/// behave as empty hashes.
+#[tokio::test]
+async fn hash_introspection() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(!client.hexists("hash", "a").await.unwrap());
+    assert!(client.hkeys("hash").await.unwrap().is_empty());
+    assert!(client.hvals("hash").await.unwrap().is_empty());
+    assert_eq!(0, client.hlen("hash").await.unwrap());
+
+    let fields = vec![("a".to_string(), "1".into()), ("b".to_string(), "2".into())];
+    client.hset("hash", fields).await.unwrap();
+
+    assert!(client.hexists("hash", "a").await.unwrap());
+    assert!(!client.hexists("hash", "z").await.unwrap());
+    let mut fields = client.hkeys("hash").await.unwrap();
+    fields.sort();
+    assert_eq!(vec!["a", "b"], fields);
+    let mut values = client.hvals("hash").await.unwrap();
+    values.sort();
+    assert_eq!(vec!["1", "2"], values);
+    assert_eq!(2, client.hlen("hash").await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.hexists("hello", "a").await.is_err());
+    assert!(client.hkeys("hello").await.is_err());
+    assert!(client.hvals("hello").await.is_err());
+    assert!(client.hlen("hello").await.is_err());
+}
+
+/// Hash fields can be used as counters, created on the first increment.
+#[tokio::test]
+async fn hash_incr_by() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // A fresh hash and field start at 0
+    assert_eq!(5, client.hincr_by("hash", "count", 5).await.unwrap());
+    assert_eq!("hash", client.value_type("hash").await.unwrap());
+    assert_eq!(2, client.hincr_by("hash", "count", -3).await.unwrap());
+    assert_eq!(
+        Some("2".into()),
+        client.hget("hash", "count").await.unwrap()
+    );
+
+    // A fresh field of an existing hash
+    assert_eq!(1.5, client.hincr_by_float("hash", "x", 1.5).await.unwrap());
+    assert_eq!(
+        -0.5,
+        client.hincr_by_float("hash", "x", -2.0).await.unwrap()
+    );
+    assert_eq!(Some("-0.5".into()), client.hget("hash", "x").await.unwrap());
+
+    // Integers are valid floats
+    assert_eq!(
+        2.25,
+        client.hincr_by_float("hash", "count", 0.25).await.unwrap()
+    );
+
+    let fields = vec![("name".to_string(), "alice".into())];
+    client.hset("hash", fields).await.unwrap();
+    let err = client.hincr_by("hash", "name", 1).await.unwrap_err();
+    assert_eq!("ERR hash value is not an integer", err.to_string());
+    let err = client.hincr_by("hash", "x", 1).await.unwrap_err();
+    assert_eq!("ERR hash value is not an integer", err.to_string());
+    let err = client
+        .hincr_by_float("hash", "name", 1.0)
+        .await
+        .unwrap_err();
+    assert_eq!("ERR hash value is not a float", err.to_string());
+
+    client
+        .hset(
+            "hash",
+            vec![("big".to_string(), i64::MAX.to_string().into())],
+        )
+        .await
+        .unwrap();
+    let err = client.hincr_by("hash", "big", 1).await.unwrap_err();
+    assert_eq!("ERR increment or decrement would overflow", err.to_string());
+    let big = client.hget("hash", "big").await.unwrap().unwrap();
+    assert_eq!(i64::MAX.to_string().as_bytes(), &big[..]);
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client.hincr_by("hello", "count", 1).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert!(client.hincr_by_float("hello", "count", 1.0).await.is_err());
+}
+
+/// Set members can be added, checked, listed, counted and removed. The key is
+/// removed with its last member.
+#[tokio::test]
+async fn set_add_rem_members() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let added = client
+        .sadd("set", vec!["a".into(), "b".into(), "a".into()])
+        .await
+        .unwrap();
+    assert_eq!(2, added);
+    let added = client
+        .sadd("set", vec!["b".into(), "c".into()])
+        .await
+        .unwrap();
+    assert_eq!(1, added);
+    assert_eq!("set", client.value_type("set").await.unwrap());
+    assert_eq!(3, client.scard("set").await.unwrap());
+
+    assert!(client.sismember("set", "a".into()).await.unwrap());
+    assert!(!client.sismember("set", "z".into()).await.unwrap());
+    assert!(!client.sismember("missing", "a".into()).await.unwrap());
+    assert_eq!(0, client.scard("missing").await.unwrap());
+
+    let mut members = client.smembers("set").await.unwrap();
+    members.sort();
+    assert_eq!(vec!["a", "b", "c"], members);
+
+    let removed = client
+        .srem("set", vec!["a".into(), "z".into()])
+        .await
+        .unwrap();
+    assert_eq!(1, removed);
+    let removed = client
+        .srem("set", vec!["b".into(), "c".into()])
+        .await
+        .unwrap();
+    assert_eq!(2, removed);
+    assert_eq!("none", client.value_type("set").await.unwrap());
+
+    // Sets expire like any other value.
+    client.sadd("set", vec!["a".into()]).await.unwrap();
+    client.expire("set", Duration::from_secs(1)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(client.smembers("set").await.unwrap().is_empty());
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client.sadd("hello", vec!["a".into()]).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert!(client.srem("hello", vec!["a".into()]).await.is_err());
+    assert!(client.smembers("hello").await.is_err());
+    assert!(client.sismember("hello", "a".into()).await.is_err());
+    assert!(client.scard("hello").await.is_err());
+}
+
+/// The store commands combine sets and store the result, removing the
+/// destination when the result is empty.
+#[tokio::test]
+async fn set_store_operations() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .sadd("a", vec!["1".into(), "2".into(), "3".into()])
+        .await
+        .unwrap();
+    client
+        .sadd("b", vec!["2".into(), "3".into(), "4".into()])
+        .await
+        .unwrap();
+    client.sadd("c", vec!["9".into()]).await.unwrap();
+
+    async fn members(client: &mut Client, key: &str) -> Vec<String> {
+        let mut members: Vec<_> = client
+            .smembers(key)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|member| String::from_utf8(member.to_vec()).unwrap())
+            .collect();
+        members.sort();
+        members
+    }
+
+    // Overlapping sets
+    assert_eq!(2, client.sinterstore("dst", &["a", "b"]).await.unwrap());
+    assert_eq!(vec!["2", "3"], members(&mut client, "dst").await);
+
+    assert_eq!(4, client.sunionstore("dst", &["a", "b"]).await.unwrap());
+    assert_eq!(vec!["1", "2", "3", "4"], members(&mut client, "dst").await);
+
+    assert_eq!(1, client.sdiffstore("dst", &["a", "b"]).await.unwrap());
+    assert_eq!(vec!["1"], members(&mut client, "dst").await);
+
+    // Disjoint sets
+    assert_eq!(4, client.sunionstore("dst", &["a", "c"]).await.unwrap());
+    assert_eq!(vec!["1", "2", "3", "9"], members(&mut client, "dst").await);
+
+    assert_eq!(3, client.sdiffstore("dst", &["a", "c"]).await.unwrap());
+    assert_eq!(vec!["1", "2", "3"], members(&mut client, "dst").await);
+
+    assert_eq!(0, client.sinterstore("dst", &["a", "c"]).await.unwrap());
+    assert_eq!("none", client.value_type("dst").await.unwrap());
+
+    // Missing keys are empty sets, and the destination may be a source
+    assert_eq!(
+        0,
+        client.sinterstore("dst", &["a", "missing"]).await.unwrap()
+    );
+    assert_eq!(3, client.sunionstore("a", &["a", "missing"]).await.unwrap());
+    assert_eq!(
+        0,
+        client.sdiffstore("dst", &["missing", "a"]).await.unwrap()
+    );
+    assert_eq!(1, client.sdiffstore("b", &["b", "a"]).await.unwrap());
+    assert_eq!(vec!["4"], members(&mut client, "b").await);
+
+    // The destination is replaced whatever it holds, sources must be sets
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(3, client.sunionstore("hello", &["a"]).await.unwrap());
+    assert_eq!("set", client.value_type("hello").await.unwrap());
+
+    client.set("string", "world".into()).await.unwrap();
+    let err = client
+        .sinterstore("dst", &["a", "string"])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert!(client.sunionstore("dst", &["string"]).await.is_err());
+    assert!(client.sdiffstore("dst", &["a", "string"]).await.is_err());
+}
+
+/// SINTERCARD counts the members of the intersection, stopping at LIMIT.
+#[tokio::test]
+async fn set_intercard() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let members = |range: std::ops::Range<u32>| range.map(|i| Bytes::from(i.to_string())).collect();
+    client.sadd("a", members(0..100)).await.unwrap();
+    client.sadd("b", members(50..150)).await.unwrap();
+    client.sadd("c", members(0..60)).await.unwrap();
+
+    assert_eq!(50, client.sintercard(&["a", "b"], None).await.unwrap());
+    assert_eq!(10, client.sintercard(&["a", "b", "c"], None).await.unwrap());
+    assert_eq!(
+        10,
+        client.sintercard(&["c", "b", "a"], Some(0)).await.unwrap()
+    );
+    assert_eq!(
+        4,
+        client.sintercard(&["a", "b", "c"], Some(4)).await.unwrap()
+    );
+    assert_eq!(
+        10,
+        client.sintercard(&["a", "b", "c"], Some(20)).await.unwrap()
+    );
+    assert_eq!(100, client.sintercard(&["a", "a"], None).await.unwrap());
+
+    // A missing set empties the intersection, but every key is type checked.
+    assert_eq!(0, client.sintercard(&["a", "missing"], None).await.unwrap());
+    client.set("string", "world".into()).await.unwrap();
+    let err = client
+        .sintercard(&["missing", "string"], None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+}
+
+#[tokio::test]
+async fn set_move() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .sadd("src", vec!["1".into(), "2".into()])
+        .await
+        .unwrap();
+
+    assert!(client.smove("src", "dst", "1".into()).await.unwrap());
+    assert!(!client.sismember("src", "1".into()).await.unwrap());
+    assert!(client.sismember("dst", "1".into()).await.unwrap());
+
+    // Members not in the source are not moved
+    assert!(!client.smove("src", "dst", "3".into()).await.unwrap());
+    assert!(!client.smove("missing", "dst", "1".into()).await.unwrap());
+    assert_eq!(1, client.scard("dst").await.unwrap());
+
+    // Moving within the same set leaves it unchanged
+    assert!(client.smove("src", "src", "2".into()).await.unwrap());
+    assert_eq!(1, client.scard("src").await.unwrap());
+
+    // The source is removed once empty
+    assert!(client.smove("src", "dst", "2".into()).await.unwrap());
+    assert_eq!("none", client.value_type("src").await.unwrap());
+    assert_eq!(2, client.scard("dst").await.unwrap());
+
+    // Both keys must hold sets
+    client.set("string", "world".into()).await.unwrap();
+    let err = client.smove("dst", "string", "1".into()).await.unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert!(client.smove("string", "dst", "1".into()).await.is_err());
+    assert!(client.sismember("dst", "1".into()).await.unwrap());
+}
+
+/// SRANDMEMBER and HRANDFIELD pick distinct elements for a positive count, at
+/// most all of them, and exactly as many as asked, with repeats, for a
+/// negative count.
+#[tokio::test]
+async fn random_members() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let all: Vec<Bytes> = vec!["a".into(), "b".into(), "c".into()];
+    client.sadd("set", all.clone()).await.unwrap();
+
+    let mut members = client.srandmember_count("set", 2).await.unwrap();
+    members.sort();
+    members.dedup();
+    assert_eq!(2, members.len());
+    assert!(members.iter().all(|member| all.contains(member)));
+
+    let mut members = client.srandmember_count("set", 10).await.unwrap();
+    members.sort();
+    assert_eq!(all, members);
+
+    let members = client.srandmember_count("set", -10).await.unwrap();
+    assert_eq!(10, members.len());
+    assert!(members.iter().all(|member| all.contains(member)));
+
+    // Every member is picked eventually.
+    let mut picked = vec![];
+    for _ in 0..100 {
+        picked.push(client.srandmember("set").await.unwrap().unwrap());
+    }
+    picked.sort();
+    picked.dedup();
+    assert_eq!(all, picked);
+
+    assert!(client.srandmember("missing").await.unwrap().is_none());
+    assert!(client
+        .srandmember_count("missing", 2)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(client.srandmember_count("set", 0).await.unwrap().is_empty());
+
+    client
+        .hset(
+            "hash",
+            vec![("f1".into(), "v1".into()), ("f2".into(), "v2".into())],
+        )
+        .await
+        .unwrap();
+
+    let field = client.hrandfield("hash").await.unwrap().unwrap();
+    assert!(field == "f1" || field == "f2");
+
+    let mut fields = client.hrandfield_count("hash", 5).await.unwrap();
+    fields.sort();
+    assert_eq!(vec!["f1", "f2"], fields);
+
+    let fields = client.hrandfield_with_values("hash", -6).await.unwrap();
+    assert_eq!(6, fields.len());
+    for (field, value) in fields {
+        assert_eq!(field.replace('f', "v").as_bytes(), &value[..]);
+    }
+
+    assert!(client.hrandfield("missing").await.unwrap().is_none());
+    assert!(client
+        .hrandfield_with_values("missing", 2)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Counts too large to be replied are refused, and the keys stay usable.
+    for count in [i64::MIN, -(i64::MAX / 2)] {
+        let err = client.srandmember_count("set", count).await.unwrap_err();
+        assert_eq!("ERR value is out of range", err.to_string());
+        let err = client.hrandfield_count("hash", count).await.unwrap_err();
+        assert_eq!("ERR value is out of range", err.to_string());
+    }
+    assert_eq!(3, client.scard("set").await.unwrap());
+    assert_eq!(2, client.hlen("hash").await.unwrap());
+}
+
+/// Sorted set members are ordered by score, ties broken lexicographically.
+#[tokio::test]
+async fn sorted_set_basics() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let added = client
+        .zadd(
+            "board",
+            vec![
+                (10.0, "carol".into()),
+                (5.5, "bob".into()),
+                (10.0, "alice".into()),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(3, added);
+    assert_eq!("zset", client.value_type("board").await.unwrap());
+
+    assert_eq!(
+        vec!["bob", "alice", "carol"],
+        client.zrange("board", 0, -1).await.unwrap()
+    );
+    assert_eq!(Some(10.0), client.zscore("board", "carol").await.unwrap());
+    assert_eq!(Some(5.5), client.zscore("board", "bob").await.unwrap());
+    assert_eq!(Some(0), client.zrank("board", "bob").await.unwrap());
+    assert_eq!(Some(1), client.zrank("board", "alice").await.unwrap());
+    assert_eq!(Some(2), client.zrank("board", "carol").await.unwrap());
+
+    // Updating a score moves the member without adding it
+    let added = client
+        .zadd(
+            "board",
+            vec![(1.0, "carol".into()), (f64::INFINITY, "dave".into())],
+        )
+        .await
+        .unwrap();
+    assert_eq!(1, added);
+    assert_eq!(
+        vec![
+            (Bytes::from("carol"), 1.0),
+            (Bytes::from("bob"), 5.5),
+            (Bytes::from("alice"), 10.0),
+            (Bytes::from("dave"), f64::INFINITY),
+        ],
+        client.zrange_with_scores("board", 0, -1).await.unwrap()
+    );
+
+    // Ranges are clamped, negative indices count from the highest score
+    assert_eq!(
+        vec!["alice", "dave"],
+        client.zrange("board", -2, 100).await.unwrap()
+    );
+    assert_eq!(vec!["bob"], client.zrange("board", 1, -3).await.unwrap());
+    assert!(client.zrange("board", 3, 1).await.unwrap().is_empty());
+    assert!(client.zrange("board", 10, 20).await.unwrap().is_empty());
+
+    // Missing keys and members
+    assert_eq!(None, client.zscore("board", "erin").await.unwrap());
+    assert_eq!(None, client.zrank("board", "erin").await.unwrap());
+    assert_eq!(None, client.zscore("missing", "bob").await.unwrap());
+    assert_eq!(None, client.zrank("missing", "bob").await.unwrap());
+    assert!(client.zrange("missing", 0, -1).await.unwrap().is_empty());
+
+    client.set("hello", "world".into()).await.unwrap();
+    let err = client
+        .zadd("hello", vec![(1.0, "a".into())])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+    assert!(client.zscore("hello", "a").await.is_err());
+    assert!(client.zrank("hello", "a").await.is_err());
+    assert!(client.zrange("hello", 0, -1).await.is_err());
+}
+
+/// Scores are read whether they are sent as doubles, under RESP3, or as bulk
+/// strings.
+#[tokio::test]
+async fn zscore_resp3() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.hello(Some(3)).await.unwrap();
+
+    let members = vec![(2.5, "a".into()), (f64::INFINITY, "b".into())];
+    client.zadd("z", members).await.unwrap();
+    assert_eq!(Some(2.5), client.zscore("z", "a").await.unwrap());
+    assert_eq!(Some(f64::INFINITY), client.zscore("z", "b").await.unwrap());
+    assert_eq!(None, client.zscore("z", "c").await.unwrap());
+}
+
+/// TTL and PTTL report the remaining time to live, `-1` for keys without an
+/// expiration and `-2` for missing keys.
+#[tokio::test]
+async fn ttl_and_pttl() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(-2, client.ttl("hello").await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(-1, client.pttl("hello").await.unwrap());
+
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(10, client.ttl("hello").await.unwrap());
+
+    let pttl = client.pttl("hello").await.unwrap();
+    assert!(pttl > 9_000 && pttl <= 10_000, "pttl = {}", pttl);
+}
+
+/// EXPIRE sets a timeout on an existing key and PERSIST removes it again.
+#[tokio::test]
+async fn expire_and_persist() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(!client
+        .expire("hello", Duration::from_secs(5))
+        .await
+        .unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(!client.persist("hello").await.unwrap());
+
+    assert!(client
+        .expire("hello", Duration::from_secs(5))
+        .await
+        .unwrap());
+    assert_eq!(5, client.ttl("hello").await.unwrap());
+
+    assert!(client.persist("hello").await.unwrap());
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+
+    // A short timeout removes the key
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(client
+        .expire("hello", Duration::from_secs(0))
+        .await
+        .unwrap());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(client.get("hello").await.unwrap().is_none());
+}
+
+/// EXPIREAT and PEXPIREAT expire keys at a wall clock time.
+#[tokio::test]
+async fn expire_at_timestamp() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let later = SystemTime::now() + Duration::from_secs(100);
+    assert!(!client.expire_at("hello", later).await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.expire_at("hello", later).await.unwrap());
+    let ttl = client.ttl("hello").await.unwrap();
+    assert!((98..=100).contains(&ttl), "{}", ttl);
+
+    assert!(client.pexpire_at("hello", later).await.unwrap());
+    let pttl = client.pttl("hello").await.unwrap();
+    assert!((98_000..=100_000).contains(&pttl), "{}", pttl);
+
+    // A time in the past removes the key right away
+    let earlier = SystemTime::now() - Duration::from_secs(100);
+    assert!(client.expire_at("hello", earlier).await.unwrap());
+    assert_eq!("none", client.value_type("hello").await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.pexpire_at("hello", earlier).await.unwrap());
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert!(!client.pexpire_at("hello", earlier).await.unwrap());
+}
+
+/// EXPIRE only replaces the timeout when its NX, XX, GT or LT condition is met.
+#[tokio::test]
+async fn expire_conditions() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let secs = Duration::from_secs;
+    client.set("hello", "world".into()).await.unwrap();
+
+    // Without a timeout, GT never applies and LT always does.
+    assert!(!client
+        .expire_if("hello", secs(10), ExpireCondition::IfSome)
+        .await
+        .unwrap());
+    assert!(!client
+        .expire_if("hello", secs(10), ExpireCondition::IfGreater)
+        .await
+        .unwrap());
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+    assert!(client
+        .expire_if("hello", secs(10), ExpireCondition::IfNone)
+        .await
+        .unwrap());
+    assert_eq!(10, client.ttl("hello").await.unwrap());
+
+    assert!(!client
+        .expire_if("hello", secs(20), ExpireCondition::IfNone)
+        .await
+        .unwrap());
+    assert!(!client
+        .expire_if("hello", secs(5), ExpireCondition::IfGreater)
+        .await
+        .unwrap());
+    assert!(client
+        .expire_if("hello", secs(20), ExpireCondition::IfGreater)
+        .await
+        .unwrap());
+    assert_eq!(20, client.ttl("hello").await.unwrap());
+
+    assert!(!client
+        .expire_if("hello", secs(30), ExpireCondition::IfLess)
+        .await
+        .unwrap());
+    assert!(client
+        .expire_if("hello", secs(15), ExpireCondition::IfLess)
+        .await
+        .unwrap());
+    assert!(client
+        .expire_if("hello", secs(30), ExpireCondition::IfSome)
+        .await
+        .unwrap());
+    assert_eq!(30, client.ttl("hello").await.unwrap());
+
+    client.set("persistent", "value".into()).await.unwrap();
+    assert!(client
+        .expire_if("persistent", secs(30), ExpireCondition::IfLess)
+        .await
+        .unwrap());
+
+    assert!(!client
+        .expire_if("missing", secs(30), ExpireCondition::IfLess)
+        .await
+        .unwrap());
+}
+
+/// Each database holds its own keys, and SELECT rejects indexes the server
+/// does not have.
+#[tokio::test]
+async fn select_isolates_databases() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+
+    client.select(1).await.unwrap();
+    assert!(client.get("hello").await.unwrap().is_none());
+    client.set("hello", "other".into()).await.unwrap();
+
+    client.select(0).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+
+    // Other connections start on database 0
+    let mut other = Client::connect(addr).await.unwrap();
+    let value = other.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+
+    let err = client.select(16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+    let err = client.select(-1).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+
+    // A failed SELECT keeps the current database
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+}
+
+/// MOVE transfers a key to another database unless it already holds it.
+#[tokio::test]
+async fn move_between_databases() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client.set("taken", "db0".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("taken", "db1".into()).await.unwrap();
+    client.select(0).await.unwrap();
+
+    assert!(client.move_key("hello", 1).await.unwrap());
+    assert_eq!(0, client.exists(&["hello"]).await.unwrap());
+    assert!(!client.move_key("hello", 1).await.unwrap());
+    assert!(!client.move_key("taken", 1).await.unwrap());
+    let value = client.get("taken").await.unwrap().unwrap();
+    assert_eq!(b"db0", &value[..]);
+
+    let err = client.move_key("taken", 0).await.unwrap_err();
+    assert_eq!(
+        "ERR source and destination objects are the same",
+        err.to_string()
+    );
+    let err = client.move_key("taken", 16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+
+    client.select(1).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+    let ttl = client.pttl("hello").await.unwrap();
+    assert!(ttl > 0 && ttl <= 60_000);
+    let value = client.get("taken").await.unwrap().unwrap();
+    assert_eq!(b"db1", &value[..]);
+
+    // Keys can be moved back, the databases are locked in the same order
+    // whichever way the key goes.
+    assert!(client.move_key("hello", 0).await.unwrap());
+}
+
+/// SWAPDB exchanges two databases, connections which selected one of them
+/// then see the keys of the other, with their TTLs.
+#[tokio::test]
+async fn swap_databases() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+    other.select(1).await.unwrap();
+
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    other.set("only", "db1".into()).await.unwrap();
+
+    client.swapdb(0, 1).await.unwrap();
+
+    assert_eq!(0, client.exists(&["hello", "list"]).await.unwrap());
+    let value = client.get("only").await.unwrap().unwrap();
+    assert_eq!(b"db1", &value[..]);
+
+    let value = other.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+    let ttl = other.pttl("hello").await.unwrap();
+    assert!(ttl > 0 && ttl <= 60_000);
+    assert_eq!(-1, other.pttl("list").await.unwrap());
+    assert!(other.get("only").await.unwrap().is_none());
+    assert_eq!(2, other.dbsize().await.unwrap());
+
+    // Swapping back, or with the same database, restores the keys
+    other.swapdb(1, 0).await.unwrap();
+    other.swapdb(0, 0).await.unwrap();
+    assert_eq!(2, client.dbsize().await.unwrap());
+    assert_eq!(1, other.dbsize().await.unwrap());
+
+    let err = client.swapdb(0, 16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+    let err = client.swapdb(-1, 0).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+}
+
+/// GETSET swaps the value and clears the TTL, GETDEL reads and removes the key.
+#[tokio::test]
+async fn getset_and_getdel() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.getset("hello", "1".into()).await.unwrap().is_none());
+
+    client
+        .set_expires("hello", "2".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    let prev = client.getset("hello", "3".into()).await.unwrap().unwrap();
+    assert_eq!(b"2", &prev[..]);
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+
+    let value = client.getdel("hello").await.unwrap().unwrap();
+    assert_eq!(b"3", &value[..]);
+    assert!(client.getdel("hello").await.unwrap().is_none());
+    assert_eq!(0, client.exists(&["hello"]).await.unwrap());
+
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.getset("list", "b".into()).await.is_err());
+    assert!(client.getdel("list").await.is_err());
+    assert_eq!("list", client.value_type("list").await.unwrap());
+}
+
+/// GETEX reads a value while leaving, replacing or removing its TTL.
+#[tokio::test]
+async fn getex() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.getex("hello", None).await.unwrap().is_none());
+
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.getex("hello", None).await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+
+    let expiration = GetExExpiration::In(Duration::from_secs(60));
+    let value = client.getex("hello", Some(expiration)).await.unwrap();
+    assert_eq!(b"world", &value.unwrap()[..]);
+    assert_eq!(60, client.ttl("hello").await.unwrap());
+
+    // Without options, the TTL is untouched.
+    client.getex("hello", None).await.unwrap().unwrap();
+    assert_eq!(60, client.ttl("hello").await.unwrap());
+
+    let expiration = GetExExpiration::At(SystemTime::now() + Duration::from_secs(120));
+    client
+        .getex("hello", Some(expiration))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(client.ttl("hello").await.unwrap() > 110);
+
+    let expiration = GetExExpiration::Persist;
+    client
+        .getex("hello", Some(expiration))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+
+    // A time in the past removes the key once it has been read.
+    let expiration = GetExExpiration::At(SystemTime::now() - Duration::from_secs(1));
+    let value = client.getex("hello", Some(expiration)).await.unwrap();
+    assert_eq!(b"world", &value.unwrap()[..]);
+    assert_eq!(0, client.exists(&["hello"]).await.unwrap());
+
+    let expiration = GetExExpiration::In(Duration::ZERO);
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.getex("hello", Some(expiration)).await.is_err());
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.getex("list", None).await.is_err());
+}
+
+/// KEYS returns the keys matching a glob-style pattern.
+#[tokio::test]
+async fn keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for key in ["user:1", "user:2", "session:a", "hat", "hit", "hot"] {
+        client.set(key, "value".into()).await.unwrap();
+    }
+    client
+        .set_expires("user:3", "value".into(), Duration::from_millis(1))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let mut keys = client.keys("*").await.unwrap();
+    keys.sort();
+    assert_eq!(
+        vec!["hat", "hit", "hot", "session:a", "user:1", "user:2"],
+        keys
+    );
+
+    let mut keys = client.keys("user:*").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["user:1", "user:2"], keys);
+
+    let mut keys = client.keys("h[ai]t").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["hat", "hit"], keys);
+
+    assert!(client.keys("missing*").await.unwrap().is_empty());
+}
+
+/// SCAN returns every key that is present for the whole iteration.
+#[tokio::test]
+async fn scan() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for i in 0..100 {
+        client
+            .set(&format!("key:{}", i), "value".into())
+            .await
+            .unwrap();
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut cursor = 0;
+    let mut calls = 0;
+
+    loop {
+        let (next, keys) = client.scan(cursor, None, Some(7)).await.unwrap();
+        assert!(keys.len() <= 7);
+        seen.extend(keys);
+        calls += 1;
+
+        // Keys removed or added in the middle of the scan do not keep the
+        // other keys from being returned.
+        if calls == 3 {
+            for i in 0..10 {
+                client.del(&[&format!("key:{}", i)]).await.unwrap();
+                client
+                    .set(&format!("new:{}", i), "value".into())
+                    .await
+                    .unwrap();
+            }
+        }
+
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+
+    for i in 10..100 {
+        assert!(
+            seen.contains(&format!("key:{}", i)),
+            "key:{} not returned",
+            i
+        );
+    }
+
+    // MATCH filters the keys of each batch.
+    let mut matched = vec![];
+    let mut cursor = 0;
+    loop {
+        let (next, keys) = client.scan(cursor, Some("key:5?"), None).await.unwrap();
+        matched.extend(keys);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    matched.sort();
+    let expected: Vec<String> = (50..60).map(|i| format!("key:{}", i)).collect();
+    assert_eq!(expected, matched);
+}
+
+/// `scan_iter` drives the SCAN cursor, yielding every key exactly once.
+#[tokio::test]
+async fn scan_iter() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for i in 0..500 {
+        client
+            .set(&format!("key:{}", i), "value".into())
+            .await
+            .unwrap();
+    }
+    client.set("other", "value".into()).await.unwrap();
+
+    let mut keys: Vec<Bytes> = client
+        .scan_iter(Some("key:*"), Some(13))
+        .collect::<Result<_, _>>()
+        .await
+        .unwrap();
+    keys.sort();
+
+    let mut expected: Vec<Bytes> = (0..500).map(|i| format!("key:{}", i).into()).collect();
+    expected.sort();
+    assert_eq!(expected, keys);
+
+    // The client is still usable once the stream completes
+    assert_eq!(501, client.dbsize().await.unwrap());
+}
+
+/// A SCAN batch is filled from as many shards as needed, resuming in the
+/// middle of a shard where the previous batch stopped.
+#[tokio::test]
+async fn scan_across_shards() {
+    let addr = start_server_with_config(server::Config {
+        shards: 64,
+        ..Default::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for i in 0..3 {
+        client
+            .set(&format!("key:{}", i), "value".into())
+            .await
+            .unwrap();
+    }
+    let (next, mut keys) = client.scan(0, None, Some(10)).await.unwrap();
+    assert_eq!(0, next);
+    keys.sort();
+    assert_eq!(vec!["key:0", "key:1", "key:2"], keys);
+
+    for i in 3..200 {
+        client
+            .set(&format!("key:{}", i), "value".into())
+            .await
+            .unwrap();
+    }
+    let mut scanned = vec![];
+    let mut cursor = 0;
+    loop {
+        let (next, keys) = client.scan(cursor, None, Some(5)).await.unwrap();
+        scanned.extend(keys);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    scanned.sort();
+    let mut expected: Vec<String> = (0..200).map(|i| format!("key:{}", i)).collect();
+    expected.sort();
+    assert_eq!(expected, scanned);
+}
+
+/// APPEND grows a string and STRLEN reports its length.
+#[tokio::test]
+async fn append_and_strlen() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(0, client.strlen("log").await.unwrap());
+    assert_eq!(5, client.append("log", "hello".into()).await.unwrap());
+    assert_eq!(11, client.append("log", " world".into()).await.unwrap());
+    assert_eq!(11, client.strlen("log").await.unwrap());
+    let value = client.get("log").await.unwrap().unwrap();
+    assert_eq!(b"hello world", &value[..]);
+
+    // The expiration is kept.
+    client
+        .set_expires("temp", "a".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(2, client.append("temp", "b".into()).await.unwrap());
+    assert!(client.ttl("temp").await.unwrap() > 0);
+
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.append("list", "b".into()).await.is_err());
+    assert!(client.strlen("list").await.is_err());
+}
+
+/// GETRANGE and SETRANGE read and patch parts of a string.
+#[tokio::test]
+async fn getrange_and_setrange() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("hello", "Hello World".into()).await.unwrap();
+    assert_eq!(b"Hello", &client.getrange("hello", 0, 4).await.unwrap()[..]);
+    assert_eq!(
+        b"World",
+        &client.getrange("hello", -5, -1).await.unwrap()[..]
+    );
+    assert_eq!(
+        b"World",
+        &client.getrange("hello", 6, 100).await.unwrap()[..]
+    );
+    assert!(client.getrange("hello", 20, 30).await.unwrap().is_empty());
+    assert!(client.getrange("hello", 3, 1).await.unwrap().is_empty());
+    assert!(client.getrange("missing", 0, -1).await.unwrap().is_empty());
+    // Offsets before the start of the string are clamped to its first byte.
+    assert_eq!(b"H", &client.getrange("hello", 0, -100).await.unwrap()[..]);
+    assert_eq!(
+        b"H",
+        &client.getrange("hello", -100, -100).await.unwrap()[..]
+    );
+    client.set("blank", "".into()).await.unwrap();
+    assert!(client.getrange("blank", 0, -100).await.unwrap().is_empty());
+
+    assert_eq!(
+        11,
+        client.setrange("hello", 6, "Redis".into()).await.unwrap()
+    );
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"Hello Redis", &value[..]);
+
+    // Writing past the end pads the string with zero bytes.
+    assert_eq!(5, client.setrange("padded", 3, "ab".into()).await.unwrap());
+    let value = client.get("padded").await.unwrap().unwrap();
+    assert_eq!(b"\0\0\0ab", &value[..]);
+
+    // Writing nothing does not create the key.
+    assert_eq!(0, client.setrange("empty", 5, "".into()).await.unwrap());
+    assert_eq!(0, client.exists(&["empty"]).await.unwrap());
+
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.getrange("list", 0, -1).await.is_err());
+    assert!(client.setrange("list", 0, "b".into()).await.is_err());
+}
+
+/// SETBIT, GETBIT and BITCOUNT operate on the bits of a string.
+#[tokio::test]
+async fn bits() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // Setting a bit past the end pads the string with zero bytes.
+    assert!(!client.setbit("bits", 1, true).await.unwrap());
+    assert!(!client.setbit("bits", 23, true).await.unwrap());
+    let value = client.get("bits").await.unwrap().unwrap();
+    assert_eq!(b"\x40\0\x01", &value[..]);
+
+    // The previous value of the bit is returned.
+    assert!(client.setbit("bits", 1, false).await.unwrap());
+    assert!(!client.getbit("bits", 1).await.unwrap());
+    assert!(client.getbit("bits", 23).await.unwrap());
+    assert!(!client.getbit("bits", 1000).await.unwrap());
+    assert!(!client.getbit("missing", 0).await.unwrap());
+
+    client.set("count", "foobar".into()).await.unwrap();
+    assert_eq!(26, client.bitcount("count", None).await.unwrap());
+    assert_eq!(4, client.bitcount("count", Some((0, 0))).await.unwrap());
+    assert_eq!(6, client.bitcount("count", Some((1, 1))).await.unwrap());
+    assert_eq!(7, client.bitcount("count", Some((-2, -1))).await.unwrap());
+    assert_eq!(0, client.bitcount("count", Some((3, 1))).await.unwrap());
+    assert_eq!(0, client.bitcount("missing", None).await.unwrap());
+
+    // Offsets past 4 gigabits are rejected rather than allocated.
+    assert!(client.setbit("huge", 1 << 32, true).await.is_err());
+    assert!(client.getbit("huge", 1 << 32).await.is_err());
+    assert_eq!(0, client.exists(&["huge"]).await.unwrap());
+
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.setbit("list", 0, true).await.is_err());
+    assert!(client.getbit("list", 0).await.is_err());
+    assert!(client.bitcount("list", None).await.is_err());
+}
+
+/// BITCOUNT and BITPOS take ranges in bytes or bits, and BITPOS follows the
+/// Redis conventions for bits missing from the range.
+#[tokio::test]
+async fn bit_ranges() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // Ranges in bits leave out the bits of the bytes they partly cover.
+    client.set("count", "foobar".into()).await.unwrap();
+    let count = client.bitcount_with_unit("count", 1, 1, BitUnit::Byte);
+    assert_eq!(6, count.await.unwrap());
+    let count = client.bitcount_with_unit("count", 5, 30, BitUnit::Bit);
+    assert_eq!(17, count.await.unwrap());
+    let count = client.bitcount_with_unit("count", 1, 6, BitUnit::Bit);
+    assert_eq!(4, count.await.unwrap());
+    let count = client.bitcount_with_unit("count", -8, -1, BitUnit::Bit);
+    assert_eq!(4, count.await.unwrap());
+    let count = client.bitcount_with_unit("count", 10, 9, BitUnit::Bit);
+    assert_eq!(0, count.await.unwrap());
+
+    client
+        .set("pos", Bytes::from_static(b"\x00\xff\xf0"))
+        .await
+        .unwrap();
+    let byte = BitUnit::Byte;
+    assert_eq!(8, client.bitpos("pos", true, None, byte).await.unwrap());
+    let pos = client.bitpos("pos", true, Some((2, None)), byte);
+    assert_eq!(16, pos.await.unwrap());
+    let pos = client.bitpos("pos", true, Some((2, Some(-1))), byte);
+    assert_eq!(16, pos.await.unwrap());
+    let pos = client.bitpos("pos", false, Some((1, None)), byte);
+    assert_eq!(20, pos.await.unwrap());
+    let pos = client.bitpos("pos", true, Some((7, Some(15))), BitUnit::Bit);
+    assert_eq!(8, pos.await.unwrap());
+    let pos = client.bitpos("pos", false, Some((9, Some(19))), BitUnit::Bit);
+    assert_eq!(-1, pos.await.unwrap());
+    let pos = client.bitpos("pos", false, Some((9, Some(20))), BitUnit::Bit);
+    assert_eq!(20, pos.await.unwrap());
+
+    // Looking for a set bit where there is none finds nothing.
+    client
+        .set("zeros", Bytes::from_static(b"\0\0\0"))
+        .await
+        .unwrap();
+    assert_eq!(-1, client.bitpos("zeros", true, None, byte).await.unwrap());
+    let pos = client.bitpos("zeros", true, Some((7, Some(-3))), BitUnit::Bit);
+    assert_eq!(-1, pos.await.unwrap());
+
+    // Without an end, the string is padded with clear bits.
+    client
+        .set("ones", Bytes::from_static(b"\xff\xff"))
+        .await
+        .unwrap();
+    assert_eq!(16, client.bitpos("ones", false, None, byte).await.unwrap());
+    let pos = client.bitpos("ones", false, Some((1, None)), byte);
+    assert_eq!(16, pos.await.unwrap());
+    let pos = client.bitpos("ones", false, Some((0, Some(-1))), byte);
+    assert_eq!(-1, pos.await.unwrap());
+    let pos = client.bitpos("ones", false, Some((5, Some(3))), byte);
+    assert_eq!(-1, pos.await.unwrap());
+
+    // A missing key is an empty string.
+    assert_eq!(
+        0,
+        client.bitpos("missing", false, None, byte).await.unwrap()
+    );
+    assert_eq!(
+        -1,
+        client.bitpos("missing", true, None, byte).await.unwrap()
+    );
+
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.bitpos("list", true, None, byte).await.is_err());
+    let count = client.bitcount_with_unit("list", 0, -1, BitUnit::Bit);
+    assert!(count.await.is_err());
+}
+
+/// COPY duplicates a key along with its type and expiration.
+#[tokio::test]
+async fn copy() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(!client.copy("missing", "dst", false).await.unwrap());
+    assert_eq!(0, client.exists(&["dst"]).await.unwrap());
+
+    client
+        .set_expires("string", "hello".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    assert!(client.copy("string", "copy", false).await.unwrap());
+    assert_eq!(b"hello", &client.get("copy").await.unwrap().unwrap()[..]);
+    let ttl = client.ttl("copy").await.unwrap();
+    assert!(ttl > 90 && ttl <= 100, "unexpected ttl {}", ttl);
+
+    // The copy is independent of the original.
+    client.append("copy", " world".into()).await.unwrap();
+    assert_eq!(b"hello", &client.get("string").await.unwrap().unwrap()[..]);
+
+    // An existing destination is only overwritten with REPLACE.
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    assert!(!client.copy("list", "copy", false).await.unwrap());
+    assert_eq!("string", client.value_type("copy").await.unwrap());
+    assert!(client.copy("list", "copy", true).await.unwrap());
+    assert_eq!("list", client.value_type("copy").await.unwrap());
+    assert_eq!(-1, client.ttl("copy").await.unwrap());
+    assert_eq!(
+        vec![Bytes::from("a"), Bytes::from("b")],
+        client.lrange("copy", 0, -1).await.unwrap()
+    );
+
+    assert!(client.copy("list", "list", true).await.is_err());
+}
+
+/// RENAME moves a key along with its expiration, RENAMENX does not overwrite.
+#[tokio::test]
+async fn rename() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.rename("missing", "other").await.is_err());
+    assert!(client.renamenx("missing", "other").await.is_err());
+
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client.set("other", "value".into()).await.unwrap();
+    let pttl = client.pttl("hello").await.unwrap();
+
+    client.rename("hello", "other").await.unwrap();
+    assert_eq!(0, client.exists(&["hello"]).await.unwrap());
+    let value = client.get("other").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+    let moved = client.pttl("other").await.unwrap();
+    assert!(moved > 0 && moved <= pttl);
+
+    client.set("taken", "value".into()).await.unwrap();
+    assert!(!client.renamenx("other", "taken").await.unwrap());
+    let value = client.get("taken").await.unwrap().unwrap();
+    assert_eq!(b"value", &value[..]);
+
+    assert!(client.renamenx("other", "free").await.unwrap());
+    assert_eq!(0, client.exists(&["other"]).await.unwrap());
+    assert!(client.pttl("free").await.unwrap() > 0);
+}
+
+/// DUMP serializes a value of any type, which RESTORE turns back into a key.
+#[tokio::test]
+async fn dump_and_restore() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("string", "hello".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client
+        .rpush("list", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client
+        .hset("hash", vec![("field".to_string(), "value".into())])
+        .await
+        .unwrap();
+    client.sadd("set", vec!["member".into()]).await.unwrap();
+    client
+        .zadd("zset", vec![(2.5, "b".into()), (1.0, "a".into())])
+        .await
+        .unwrap();
+
+    assert_eq!(None, client.dump("missing").await.unwrap());
+
+    for key in ["string", "list", "hash", "set", "zset"] {
+        let serialized = client.dump(key).await.unwrap().unwrap();
+
+        // Without REPLACE, existing keys are kept.
+        let err = client
+            .restore(key, Duration::ZERO, serialized.clone(), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("BUSYKEY"), "{}", err);
+
+        client
+            .restore(key, Duration::ZERO, serialized.clone(), true)
+            .await
+            .unwrap();
+
+        let copy = format!("{}-copy", key);
+        client
+            .restore(&copy, Duration::ZERO, serialized, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.value_type(key).await.unwrap(),
+            client.value_type(&copy).await.unwrap()
+        );
+    }
+
+    let value = client.get("string-copy").await.unwrap().unwrap();
+    assert_eq!(b"hello", &value[..]);
+    let ttl = client.pttl("string-copy").await.unwrap();
+    assert!(ttl > 0 && ttl <= 60_000);
+
+    let list = client.lrange("list-copy", 0, -1).await.unwrap();
+    assert_eq!(vec![Bytes::from("a"), Bytes::from("b")], list);
+    let value = client.hget("hash-copy", "field").await.unwrap().unwrap();
+    assert_eq!(b"value", &value[..]);
+    assert!(client.sismember("set-copy", "member".into()).await.unwrap());
+    let zset = client.zrange_with_scores("zset-copy", 0, -1).await.unwrap();
+    assert_eq!(vec![(Bytes::from("a"), 1.0), (Bytes::from("b"), 2.5)], zset);
+
+    // A non-zero TTL replaces the one recorded in the blob.
+    let serialized = client.dump("list").await.unwrap().unwrap();
+    client
+        .restore(
+            "expiring",
+            Duration::from_secs(10),
+            serialized.clone(),
+            false,
+        )
+        .await
+        .unwrap();
+    let ttl = client.pttl("expiring").await.unwrap();
+    assert!(ttl > 0 && ttl <= 10_000);
+    assert_eq!(-1, client.pttl("list-copy").await.unwrap());
+
+    // Truncated blobs are rejected.
+    let err = client
+        .restore(
+            "corrupt",
+            Duration::ZERO,
+            serialized.slice(..serialized.len() - 1),
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("DUMP payload"), "{}", err);
+    assert_eq!(0, client.exists(&["corrupt"]).await.unwrap());
+}
+
+/// INFO reports the number of keys of each type, kept up to date as keys are
+/// created, change type, and are removed.
+#[tokio::test]
+async fn info_keyspace_types() {
+    let addr = start_server_with_config(server::Config {
+        shards: 4,
+        ..Default::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("s1", "v".into()).await.unwrap();
+    client
+        .set_expires("s2", "v".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client
+        .rpush("l1", vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client.rpush("l2", vec!["a".into()]).await.unwrap();
+    client
+        .hset("h", vec![("f".to_string(), "v".into())])
+        .await
+        .unwrap();
+    client.sadd("set", vec!["a".into()]).await.unwrap();
+    client.zadd("z", vec![(1.0, "a".into())]).await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("other", "v".into()).await.unwrap();
+
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert_eq!(
+        "# Keyspace\r\n\
+         db0:keys=7,expires=1,string=2,list=2,hash=1,set=1,zset=1\r\n\
+         db1:keys=1,expires=0,string=1,list=0,hash=0,set=0,zset=0\r\n",
+        info
+    );
+
+    // Popping the last element removes the list, SET replaces one, renaming
+    // and copying keep the type, a store command stores a set.
+    client.select(0).await.unwrap();
+    client.rpop("l2").await.unwrap();
+    client.set("l1", "v".into()).await.unwrap();
+    client.rename("h", "h2").await.unwrap();
+    assert!(client.copy("z", "z2", false).await.unwrap());
+    client.sinterstore("s1", &["set"]).await.unwrap();
+    client.del(&["s2"]).await.unwrap();
+
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert_eq!(
+        "# Keyspace\r\n\
+         db0:keys=6,expires=0,string=1,list=0,hash=1,set=2,zset=2\r\n\
+         db1:keys=1,expires=0,string=1,list=0,hash=0,set=0,zset=0\r\n",
+        info
+    );
+
+    client.move_key("set", 1).await.unwrap();
+    client.flushdb().await.unwrap();
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert_eq!(
+        "# Keyspace\r\ndb1:keys=2,expires=0,string=1,list=0,hash=0,set=1,zset=0\r\n",
+        info
+    );
+}
+
+/// INFO reports server statistics, optionally filtered by section.
+#[tokio::test]
+async fn info() {
     let (addr, _) = start_server().await;
     let mut client = Client::connect(addr).await.unwrap();
 
-    let pong = client.ping(None).await.unwrap();
-    assert_eq!(b"PONG", &pong[..]);
+    client.set("hello", "world".into()).await.unwrap();
+    client
+        .set_expires("foo", "bar".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    let info = client.info(None).await.unwrap();
+    for header in &["# Server", "# Clients", "# Stats", "# Keyspace"] {
+        assert!(info.contains(header), "missing {} in {:?}", header, info);
+    }
+    assert!(info.contains("connected_clients:1\r\n"));
+    assert!(info.contains("total_connections_received:1\r\n"));
+    // SET, SET and INFO
+    assert!(info.contains("total_commands_processed:3\r\n"));
+    assert!(info.contains("db0:keys=2,expires=1,"));
+
+    let info = client.info(Some("KEYSPACE")).await.unwrap();
+    assert_eq!(
+        "# Keyspace\r\ndb0:keys=2,expires=1,string=2,list=0,hash=0,set=0,zset=0\r\n",
+        info
+    );
+
+    // Command counts are only reported on request.
+    let info = client.info(None).await.unwrap();
+    assert!(!info.contains("# Commandstats"));
+    let info = client.info(Some("commandstats")).await.unwrap();
+    assert!(info.contains("cmdstat_set:calls=2\r\n"), "{:?}", info);
+    assert!(info.contains("cmdstat_info:calls=4\r\n"), "{:?}", info);
+
+    assert_eq!("", client.info(Some("missing")).await.unwrap());
 }
 
-/// A PING PONG test with message provided.
-/// It should return the message.
+/// Keys expiring at the same instant are all purged by the background task,
+/// over several batches.
 #[tokio::test]
-async fn ping_pong_with_message() {
+async fn expired_keys_are_reaped() {
     let (addr, _) = start_server().await;
     let mut client = Client::connect(addr).await.unwrap();
 
-    let pong = client.ping(Some("你好世界".into())).await.unwrap();
-    assert_eq!("你好世界".as_bytes(), &pong[..]);
+    let mut pipeline = client.pipeline();
+    for i in 0..250 {
+        pipeline.set_expires(
+            &format!("key{}", i),
+            "value".into(),
+            Duration::from_millis(50),
+        );
+    }
+    pipeline.execute().await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(info.contains("expired_keys:250\r\n"), "{:?}", info);
+    assert!(info.contains("reaped_keys:250\r\n"), "{:?}", info);
+    assert_eq!(0, client.dbsize().await.unwrap());
 }
 
-/// A basic "hello world" style test. A server instance is started in a
-/// background task. A client instance is then established and set and get
-/// commands are sent to the server. The response is then evaluated
+/// With active expiration paused, expired keys are no longer visible but are
+/// only purged by an explicit sweep.
 #[tokio::test]
-async fn key_value_get_set() {
+async fn debug_active_expire() {
+    let addr = start_server_with_config(server::Config {
+        enable_debug_command: true,
+        ..Default::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.debug_set_active_expire(false).await.unwrap();
+    for key in ["one", "two"] {
+        client
+            .set_expires(key, "value".into(), Duration::from_millis(10))
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(info.contains("reaped_keys:0\r\n"), "{:?}", info);
+
+    // The keys are expired all the same.
+    assert_eq!(None, client.get("one").await.unwrap());
+    assert_eq!(0, client.dbsize().await.unwrap());
+
+    client.debug_expire_sweep().await.unwrap();
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(info.contains("reaped_keys:2\r\n"), "{:?}", info);
+
+    // Once resumed, the background task purges keys again.
+    client.debug_set_active_expire(true).await.unwrap();
+    client
+        .set_expires("three", "value".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(info.contains("reaped_keys:3\r\n"), "{:?}", info);
+}
+
+/// SETNX only sets keys that do not exist yet.
+#[tokio::test]
+async fn setnx() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert!(client.setnx("hello", "world".into()).await.unwrap());
+    assert!(!client.setnx("hello", "other".into()).await.unwrap());
+
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+}
+
+/// SETEX and PSETEX set a value along with a time to live, which must be
+/// positive.
+#[tokio::test]
+async fn setex_psetex() {
     let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set_ex("hello", 60, "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+    let ttl = client.ttl("hello").await.unwrap();
+    assert!(ttl > 0 && ttl <= 60);
+
+    client.pset_ex("other", 50, "value".into()).await.unwrap();
+    let pttl = client.pttl("other").await.unwrap();
+    assert!(pttl > 0 && pttl <= 50);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(client.get("other").await.unwrap().is_none());
+
+    for seconds in [0, -1] {
+        let err = client
+            .set_ex("hello", seconds, "new".into())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            "ERR invalid expire time in 'setex' command",
+            err.to_string()
+        );
+        let err = client
+            .pset_ex("hello", seconds, "new".into())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            "ERR invalid expire time in 'psetex' command",
+            err.to_string()
+        );
+    }
+    let err = client
+        .set_ex("hello", i64::MAX, "new".into())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR invalid expire time in 'setex' command",
+        err.to_string()
+    );
+
+    // Nothing was written
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+}
 
+/// SET KEEPTTL keeps the time to live of the previous value.
+#[tokio::test]
+async fn set_keep_ttl() {
+    let (addr, _) = start_server().await;
     let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client.set_keep_ttl("hello", "other".into()).await.unwrap();
+
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"other", &value[..]);
+    let ttl = client.ttl("hello").await.unwrap();
+    assert!(ttl > 0 && ttl <= 60);
+
+    // Without a previous expiration, the key stays persistent.
+    client.set_keep_ttl("fresh", "value".into()).await.unwrap();
+    assert_eq!(-1, client.ttl("fresh").await.unwrap());
+
+    // A plain SET still discards the time to live.
     client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+}
+
+/// SET GET replies with the previous value while setting the new one.
+#[tokio::test]
+async fn set_get() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
 
+    assert_eq!(None, client.set_get("hello", "world".into()).await.unwrap());
+    let prev = client.set_get("hello", "other".into()).await.unwrap();
+    assert_eq!(Some(Bytes::from("world")), prev);
     let value = client.get("hello").await.unwrap().unwrap();
-    assert_eq!(b"world", &value[..]);
+    assert_eq!(b"other", &value[..]);
 
-    let keys = vec![String::from("hello")]; 
-    let value = client.multiget(keys).await.unwrap();
-    assert_eq!(b"world", &value[0].as_ref().unwrap()[..]);
-    assert_eq!(1, value.len());
+    // Other types are left untouched.
+    client.lpush("list", vec!["a".into()]).await.unwrap();
+    assert!(client.set_get("list", "value".into()).await.is_err());
+    assert_eq!("list", client.value_type("list").await.unwrap());
+}
 
-    let keys = vec![String::from("hello"), String::from("hello"), String::from("world")]; 
-    let value = client.multiget(keys).await.unwrap();
+/// DBSIZE counts the keys of the selected database and skips expired keys.
+#[tokio::test]
+async fn dbsize_counts_live_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
 
-    assert_eq!(b"world", &value[0].as_ref().expect("")[..]);
-    assert_eq!(b"world", &value[1].as_ref().expect("")[..]);
-    assert!(value[2].is_none());
-    assert_eq!(3, value.len());
+    assert_eq!(0, client.dbsize().await.unwrap());
+
+    client.set("foo", "bar".into()).await.unwrap();
+    for key in &["hello", "world"] {
+        client
+            .set_expires(key, "value".into(), Duration::from_millis(100))
+            .await
+            .unwrap();
+    }
+    assert_eq!(3, client.dbsize().await.unwrap());
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(1, client.dbsize().await.unwrap());
+
+    client.select(1).await.unwrap();
+    assert_eq!(0, client.dbsize().await.unwrap());
+}
+
+/// FLUSHDB clears the selected database, FLUSHALL clears every database.
+#[tokio::test]
+async fn flushdb_and_flushall() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client
+        .set_expires("hello", "world".into(), Duration::from_millis(100))
+        .await
+        .unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("foo", "baz".into()).await.unwrap();
+
+    client.select(0).await.unwrap();
+    client.flushdb().await.unwrap();
+    assert_eq!(0, client.exists(&["hello", "foo"]).await.unwrap());
+
+    // Keys written after the flush are not affected by the cancelled
+    // expiration.
+    client.set("hello", "again".into()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(-1, client.ttl("hello").await.unwrap());
+
+    client.select(1).await.unwrap();
+    assert_eq!(1, client.exists(&["foo"]).await.unwrap());
+
+    client.flushall().await.unwrap();
+    assert_eq!(0, client.exists(&["foo"]).await.unwrap());
+    client.select(0).await.unwrap();
+    assert_eq!(0, client.exists(&["hello"]).await.unwrap());
+}
+
+/// Pub/sub channels are shared by all databases.
+#[tokio::test]
+async fn publish_across_databases() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    publisher.select(3).await.unwrap();
+    assert_eq!(1, publisher.publish("hello", "world".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"world", &message.content[..]);
 }
 
 /// similar to the "hello world" style test, But this time
@@ -71,6 +3801,122 @@ async fn receive_message_subscribed_channel() {
     assert_eq!(b"world", &message.content[..])
 }
 
+/// A subscriber can subscribe to more channels over the same connection.
+#[tokio::test]
+async fn subscribe_to_more_channels() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    subscriber.subscribe(&["world".into()]).await.unwrap();
+    assert_eq!(&["hello", "world"][..], subscriber.get_subscribed());
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(1, client.publish("world", "howdy?".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("world", &message.channel);
+    assert_eq!(b"howdy?", &message.content[..]);
+
+    subscriber.unsubscribe(&["world".into()]).await.unwrap();
+    assert_eq!(&["hello"][..], subscriber.get_subscribed());
+    assert_eq!(0, client.publish("world", "howdy?".into()).await.unwrap());
+}
+
+/// Messages received before the confirmation of a subscription are not lost.
+#[tokio::test]
+async fn message_before_subscribe_confirmation() {
+    // A server that confirms the first subscription, then publishes a message
+    // on it before confirming the second one.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 64];
+        let _ = socket.read(&mut request).await.unwrap();
+        socket
+            .write_all(b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n")
+            .await
+            .unwrap();
+        let _ = socket.read(&mut request).await.unwrap();
+        socket
+            .write_all(
+                b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nfirst\r\n\
+                  *3\r\n$9\r\nsubscribe\r\n$5\r\nworld\r\n:2\r\n\
+                  *3\r\n$7\r\nmessage\r\n$5\r\nworld\r\n$6\r\nsecond\r\n",
+            )
+            .await
+            .unwrap();
+    });
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    subscriber.subscribe(&["world".into()]).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"first", &message.content[..]);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("world", &message.channel);
+    assert_eq!(b"second", &message.content[..]);
+}
+
+/// Keyspace notifications publish each change to the key's channel and to the
+/// event's channel.
+#[tokio::test]
+async fn keyspace_notifications() {
+    let addr = start_server_with_config(server::Config {
+        notify_keyspace_events: "KEA".parse().unwrap(),
+        ..Default::default()
+    })
+    .await;
+
+    // Messages published to different channels may be received in any order,
+    // so each channel gets its own subscriber.
+    let client = Client::connect(addr).await.unwrap();
+    let channels = vec!["__keyspace@0__:hello".into()];
+    let mut subscriber = client.subscribe(channels).await.unwrap();
+
+    let client = Client::connect(addr).await.unwrap();
+    let channels = vec!["__keyevent@0__:del".into()];
+    let mut event_subscriber = client.subscribe(channels).await.unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.del(&["hello"]).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyspace@0__:hello", message.channel);
+    assert_eq!(b"set", &message.content[..]);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyspace@0__:hello", message.channel);
+    assert_eq!(b"del", &message.content[..]);
+
+    let message = event_subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyevent@0__:del", message.channel);
+    assert_eq!(b"hello", &message.content[..]);
+
+    // Notifications can be limited to some classes of events at runtime.
+    client
+        .config_set("notify-keyspace-events", "Kx")
+        .await
+        .unwrap();
+    let events = client.config_get("notify-keyspace-events").await.unwrap();
+    assert_eq!("xK", events[0].1);
+
+    client.set("hello", "world".into()).await.unwrap();
+    client
+        .set_expires("hello", "world".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyspace@0__:hello", message.channel);
+    assert_eq!(b"expired", &message.content[..]);
+}
+
 /// test that a client gets messages from multiple subscribed channels
 #[tokio::test]
 async fn receive_message_multiple_subscribed_channels() {
@@ -101,6 +3947,35 @@ async fn receive_message_multiple_subscribed_channels() {
     assert_eq!(b"howdy?", &message2.content[..])
 }
 
+/// PUBLISH reports how many subscribers receive the message.
+#[tokio::test]
+async fn publish_counts_subscribers() {
+    let (addr, _) = start_server().await;
+
+    let first = Client::connect(addr).await.unwrap();
+    let first = first.subscribe(vec!["hello".into()]).await.unwrap();
+    let second = Client::connect(addr).await.unwrap();
+    let mut second = second.subscribe(vec!["hello".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(2, publisher.publish("hello", "world".into()).await.unwrap());
+
+    // The server notices the closed connection asynchronously.
+    drop(first);
+    let mut count = 2;
+    for _ in 0..100 {
+        count = publisher.publish("hello", "world".into()).await.unwrap();
+        if count == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(1, count);
+
+    let message = second.next_message().await.unwrap().unwrap();
+    assert_eq!(b"world", &message.content[..]);
+}
+
 /// test that a client accurately removes its own subscribed chanel list
 /// when unsubscribing to all subscribed channels by submitting an empty vec
 #[tokio::test]
@@ -115,6 +3990,182 @@ async fn unsubscribes_from_channels() {
 
     subscriber.unsubscribe(&[]).await.unwrap();
     assert_eq!(subscriber.get_subscribed().len(), 0);
+
+    // Unsubscribing from nothing is confirmed as well, leaving the connection
+    // in sync for later subscriptions.
+    subscriber.unsubscribe(&[]).await.unwrap();
+    subscriber.subscribe(&["hello".into()]).await.unwrap();
+    assert_eq!(subscriber.get_subscribed(), &["hello".to_string()]);
+}
+
+/// EVAL runs a script reading and writing keys, and replies with the value of
+/// its last expression.
+#[tokio::test]
+async fn eval_script() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    const CHECK_AND_SET: &str = "if(eq(get(KEYS[1]), ARGV[1]), set(KEYS[1], ARGV[2]), 0)";
+
+    client.set("hello", "world".into()).await.unwrap();
+    let reply = client
+        .eval(
+            CHECK_AND_SET,
+            &["hello"],
+            vec!["world".into(), "there".into()],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Simple(ref s) if s == "OK"));
+    let reply = client
+        .eval(
+            CHECK_AND_SET,
+            &["hello"],
+            vec!["world".into(), "again".into()],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(0)));
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"there", &value[..]);
+
+    let reply = client
+        .eval(
+            "incr(KEYS[1]); incrby(KEYS[1], ARGV[1]);",
+            &["counter"],
+            vec!["10".into()],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(11)));
+    let reply = client.eval("get('missing')", &[], vec![]).await.unwrap();
+    assert!(matches!(reply, Frame::Null));
+    let reply = client
+        .eval("del(KEYS[1])", &["counter"], vec![])
+        .await
+        .unwrap();
+    assert!(matches!(reply, Frame::Integer(1)));
+
+    // Unknown operations are rejected before anything runs
+    let err = client
+        .eval("set(KEYS[1], 'x'); flushall()", &["hello"], vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR Error compiling script: unknown operation 'flushall'",
+        err.to_string()
+    );
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"there", &value[..]);
+
+    let err = client
+        .eval("get(KEYS[2])", &["hello"], vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "ERR Error running script: KEYS[2] is out of range",
+        err.to_string()
+    );
+
+    client.rpush("list", vec!["a".into()]).await.unwrap();
+    let err = client
+        .eval("incr(KEYS[1])", &["list"], vec![])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "WRONGTYPE Operation against a key holding the wrong kind of value",
+        err.to_string()
+    );
+}
+
+/// Scripts are atomic: no command of another connection is applied between
+/// the read and the write of a check-and-set script.
+#[tokio::test]
+async fn eval_is_atomic() {
+    let (addr, _) = start_server().await;
+
+    let tasks: Vec<_> = (0..20)
+        .map(|i| {
+            tokio::spawn(async move {
+                let mut client = Client::connect(addr).await.unwrap();
+                let script = "if(eq(get(KEYS[1]), nil), set(KEYS[1], ARGV[1]))";
+                let args = vec![Bytes::from(i.to_string())];
+                client.eval(script, &["owner"], args).await.unwrap()
+            })
+        })
+        .collect();
+
+    let mut set = 0;
+    for task in tasks {
+        if let Frame::Simple(_) = task.await.unwrap() {
+            set += 1;
+        }
+    }
+    assert_eq!(1, set);
+}
+
+/// A client in monitor mode sees the commands other connections send, with
+/// quoted arguments and passwords hidden, until it resets the connection.
+#[tokio::test]
+async fn monitor() {
+    let (addr, _) = start_server().await;
+    let mut monitor = Client::connect(addr)
+        .await
+        .unwrap()
+        .monitor()
+        .await
+        .unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("foo", "bar \"baz\"\n".into()).await.unwrap();
+    assert!(client.auth("secret").await.is_err());
+
+    let line = monitor.next_command().await.unwrap().unwrap();
+    assert!(line.ends_with(r#"] "select" "1""#), "{}", line);
+    assert!(line.contains(" [0 127.0.0.1:"), "{}", line);
+
+    let line = monitor.next_command().await.unwrap().unwrap();
+    let (time, command) = line.split_once(' ').unwrap();
+    assert!(time.parse::<f64>().is_ok(), "{}", line);
+    assert!(command.starts_with("[1 127.0.0.1:"), "{}", line);
+    assert!(
+        command.ends_with(r#"] "set" "foo" "bar \"baz\"\n""#),
+        "{}",
+        line
+    );
+
+    let line = monitor.next_command().await.unwrap().unwrap();
+    assert!(line.ends_with(r#"] "auth" "(redacted)""#), "{}", line);
+
+    let mut client = monitor.reset().await.unwrap();
+    assert_eq!("PONG", client.ping(None).await.unwrap());
+}
+
+/// A publisher sends messages without reading replies, and the client it
+/// turns back into reads the reply to its next command.
+#[tokio::test]
+async fn publisher() {
+    let (addr, _) = start_server().await;
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["events".into()]).await.unwrap();
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut publisher = client.publisher().await.unwrap();
+    for i in 0..1000 {
+        let message = format!("event {}", i);
+        publisher.publish("events", message.into()).await.unwrap();
+    }
+    publisher.flush().await.unwrap();
+
+    for i in 0..1000 {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(format!("event {}", i).as_bytes(), &message.content[..]);
+    }
+
+    let mut client = publisher.finish().await.unwrap();
+    assert_eq!(1, client.publish("events", "last".into()).await.unwrap());
+    assert_eq!("PONG", client.ping(None).await.unwrap());
 }
 
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
@@ -125,3 +4176,14 @@ async fn start_server() -> (SocketAddr, JoinHandle<()>) {
 
     (addr, handle)
 }
+
+async fn start_server_with_config(config: server::Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
+
+    addr
+}
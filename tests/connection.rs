@@ -0,0 +1,108 @@
+use bytes::Bytes;
+use mini_redis::{Connection, Frame};
+use std::io;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{self, Duration};
+
+/// A peer that stops sending in the middle of a frame makes reading time out.
+#[tokio::test]
+async fn read_timeout_mid_frame() {
+    let (mut peer, socket) = socket_pair().await;
+
+    let mut connection = Connection::new(socket);
+    connection.set_read_timeout(Some(Duration::from_millis(100)));
+    assert_eq!(Some(Duration::from_millis(100)), connection.read_timeout());
+
+    peer.write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhel")
+        .await
+        .unwrap();
+
+    let err = connection.read_frame().await.unwrap_err();
+    let err = err.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+}
+
+/// The read timeout applies to each read, a frame received slowly is accepted.
+#[tokio::test]
+async fn read_timeout_per_read() {
+    let (mut peer, socket) = socket_pair().await;
+
+    let mut connection = Connection::new(socket);
+    connection.set_read_timeout(Some(Duration::from_millis(200)));
+
+    tokio::spawn(async move {
+        for part in [&b"*2\r\n$3\r\nGET"[..], b"\r\n$5\r\nhel", b"lo\r\n"] {
+            peer.write_all(part).await.unwrap();
+            time::sleep(Duration::from_millis(100)).await;
+        }
+    });
+
+    match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Array(args) => {
+            assert_eq!(2, args.len());
+            assert_eq!(args[0], "GET");
+            assert_eq!(args[1], "hello");
+        }
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// A peer that stops reading makes writing time out once the socket buffers
+/// are full.
+#[tokio::test]
+async fn write_timeout() {
+    let (_peer, socket) = socket_pair().await;
+
+    let mut connection = Connection::new(socket);
+    connection.set_write_timeout(Some(Duration::from_millis(100)));
+    assert_eq!(Some(Duration::from_millis(100)), connection.write_timeout());
+
+    let frame = Frame::Bulk(Bytes::from(vec![b'x'; 64 * 1024 * 1024]));
+    let err = connection.write_frame(&frame).await.unwrap_err();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+}
+
+/// A peer shutting down its writing half between frames is a clean
+/// disconnect, frames received in full before are still read.
+#[tokio::test]
+async fn half_close_between_frames() {
+    let (mut peer, socket) = socket_pair().await;
+    let mut connection = Connection::new(socket);
+
+    peer.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    peer.shutdown().await.unwrap();
+
+    match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Array(args) => assert_eq!(args[0], "PING"),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+    assert!(connection.read_frame().await.unwrap().is_none());
+}
+
+/// A peer shutting down its writing half in the middle of a frame is not
+/// mistaken for a clean disconnect.
+#[tokio::test]
+async fn half_close_mid_frame() {
+    let (mut peer, socket) = socket_pair().await;
+    let mut connection = Connection::new(socket);
+
+    // The second element of the array is missing.
+    peer.write_all(b"*2\r\n$3\r\nGET\r\n").await.unwrap();
+    peer.shutdown().await.unwrap();
+
+    let err = connection.read_frame().await.unwrap_err();
+    let err = err.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::ConnectionReset, err.kind());
+    assert_eq!("connection reset by peer", err.to_string());
+}
+
+/// Returns both ends of a TCP connection.
+async fn socket_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (peer, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+
+    (peer.unwrap(), accepted.unwrap().0)
+}
@@ -0,0 +1,175 @@
+use bytes::BytesMut;
+use mini_redis::clients::ServerError;
+use mini_redis::frame::Error;
+use mini_redis::Frame;
+use std::io::Cursor;
+
+/// Encoded frames decode back to the same frame, and the number of bytes
+/// consumed is reported.
+#[test]
+fn encode_and_decode() {
+    let frame = Frame::Push(vec![
+        Frame::Simple("OK".into()),
+        Frame::Error("ERR oops".into()),
+        Frame::Integer(-42),
+        Frame::Bulk("hello".into()),
+        Frame::Null,
+        Frame::Array(vec![Frame::Integer(1), Frame::Bulk("".into())]),
+        Frame::Map(vec![(Frame::Bulk("key".into()), Frame::Integer(2))]),
+    ]);
+
+    let mut encoded = BytesMut::from(&frame.to_bytes()[..]);
+    let len = encoded.len();
+
+    // Bytes following the frame are not consumed.
+    encoded.extend_from_slice(b"+NEXT\r\n");
+
+    let (decoded, consumed) = Frame::from_bytes(&encoded).unwrap();
+    assert_eq!(len, consumed);
+    assert_eq!(frame.to_bytes(), decoded.to_bytes());
+}
+
+/// A partial frame is reported as incomplete rather than invalid.
+#[test]
+fn decode_incomplete() {
+    let encoded = Frame::Bulk("hello".into()).to_bytes();
+
+    for end in 0..encoded.len() {
+        match Frame::from_bytes(&encoded[..end]) {
+            Err(Error::Incomplete) => {}
+            res => panic!("unexpected result for {} bytes: {:?}", end, res),
+        }
+    }
+
+    assert!(matches!(Frame::from_bytes(b"?\r\n"), Err(Error::Other(_))));
+}
+
+/// Error frames convert to a `ServerError` carrying the exact message, other
+/// frames, including arrays holding errors, are returned as is.
+#[test]
+fn into_result() {
+    let err = Frame::Error("WRONGTYPE Operation against a key".into())
+        .into_result()
+        .unwrap_err();
+    assert_eq!("WRONGTYPE Operation against a key", err.to_string());
+    assert_eq!(
+        "WRONGTYPE",
+        err.downcast_ref::<ServerError>().unwrap().kind()
+    );
+
+    let frame = Frame::Array(vec![Frame::Integer(1), Frame::Error("ERR oops".into())]);
+    let entries = match frame.into_result().unwrap() {
+        Frame::Array(entries) => entries,
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+    assert!(matches!(
+        entries[0].clone().into_result(),
+        Ok(Frame::Integer(1))
+    ));
+    let err = entries[1].clone().into_result().unwrap_err();
+    assert_eq!("ERR oops", err.to_string());
+}
+
+/// RESP3-only frames are downgraded when encoding for RESP2 peers.
+#[test]
+fn encode_resp2() {
+    let frame = Frame::Map(vec![(Frame::Bulk("key".into()), Frame::Integer(2))]);
+
+    let mut resp2 = BytesMut::new();
+    frame.encode(&mut resp2, 2);
+    assert_eq!(&b"*2\r\n$3\r\nkey\r\n:2\r\n"[..], &resp2[..]);
+
+    let mut resp3 = BytesMut::new();
+    frame.encode(&mut resp3, 3);
+    assert_eq!(&b"%1\r\n$3\r\nkey\r\n:2\r\n"[..], &resp3[..]);
+}
+
+/// Doubles and booleans round-trip under RESP3, and are downgraded to bulk
+/// strings and integers for RESP2 peers.
+#[test]
+fn double_and_boolean() {
+    let cases: &[(Frame, &[u8], &[u8])] = &[
+        (Frame::Double(1.5), b",1.5\r\n", b"$3\r\n1.5\r\n"),
+        (Frame::Double(-3.0), b",-3\r\n", b"$2\r\n-3\r\n"),
+        (Frame::Double(f64::INFINITY), b",inf\r\n", b"$3\r\ninf\r\n"),
+        (
+            Frame::Double(f64::NEG_INFINITY),
+            b",-inf\r\n",
+            b"$4\r\n-inf\r\n",
+        ),
+        (Frame::Double(f64::NAN), b",nan\r\n", b"$3\r\nnan\r\n"),
+        (Frame::Boolean(true), b"#t\r\n", b":1\r\n"),
+        (Frame::Boolean(false), b"#f\r\n", b":0\r\n"),
+    ];
+
+    for (frame, resp3, resp2) in cases {
+        assert_eq!(*resp3, &frame.to_bytes()[..]);
+
+        let mut encoded = BytesMut::new();
+        frame.encode(&mut encoded, 2);
+        assert_eq!(*resp2, &encoded[..]);
+
+        let (decoded, consumed) = Frame::from_bytes(resp3).unwrap();
+        assert_eq!(resp3.len(), consumed);
+        assert_eq!(&decoded.to_bytes()[..], *resp3);
+    }
+
+    // Exponents and integers sent by other servers are accepted too.
+    assert!(matches!(
+        Frame::from_bytes(b",1e3\r\n"),
+        Ok((Frame::Double(value), 6)) if value == 1000.0
+    ));
+
+    for invalid in [&b",one\r\n"[..], b"#x\r\n", b"#true\r\n"] {
+        assert!(matches!(Frame::from_bytes(invalid), Err(Error::Other(_))));
+    }
+    assert!(matches!(Frame::from_bytes(b",1."), Err(Error::Incomplete)));
+}
+
+/// Arrays announcing more entries than allowed are rejected as soon as their
+/// header is read, and parsing an unchecked header does not allocate for the
+/// entries announced.
+#[test]
+fn multibulk_length() {
+    let encoded = b"*3\r\n:1\r\n:2\r\n:3\r\n";
+    assert!(Frame::from_bytes_with_limits(encoded, 1024, 3).is_ok());
+
+    // The maximum size of bulk strings does not limit the number of entries.
+    assert!(Frame::from_bytes_with_limits(encoded, 1, 3).is_ok());
+    assert!(Frame::from_bytes_with_limit(encoded, 1).is_ok());
+
+    for header in [&b"*4\r\n"[..], b"*99999999999999\r\n", b">4\r\n", b"%4\r\n"] {
+        match Frame::from_bytes_with_limits(header, 1024, 3) {
+            Err(Error::Other(err)) => {
+                assert_eq!("Protocol error: invalid multibulk length", err.to_string())
+            }
+            res => panic!("unexpected result for {:?}: {:?}", header, res),
+        }
+    }
+
+    let mut src = Cursor::new(&b"*99999999999999\r\n:1\r\n"[..]);
+    assert!(matches!(Frame::parse(&mut src), Err(Error::Incomplete)));
+}
+
+/// Lengths too large to be counted in bytes are rejected even when no limit is
+/// set, rather than overflowing.
+#[test]
+fn oversized_lengths_are_rejected() {
+    let cases = [
+        (
+            &b"%9223372036854775808\r\n"[..],
+            "Protocol error: invalid multibulk length",
+        ),
+        (
+            b"$18446744073709551615\r\n",
+            "Protocol error: invalid bulk length",
+        ),
+    ];
+
+    for (header, expected) in cases {
+        match Frame::from_bytes(header) {
+            Err(Error::Other(err)) => assert_eq!(expected, err.to_string()),
+            res => panic!("unexpected result for {:?}: {:?}", header, res),
+        }
+    }
+}
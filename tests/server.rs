@@ -1,6 +1,9 @@
-use mini_redis::server;
+use mini_redis::server::{self, Server};
+use mini_redis::{Connection, Frame};
 
+use bytes::Bytes;
 use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::time::{self, Duration};
@@ -56,6 +59,223 @@ async fn key_value_get_set() {
     assert_eq!(0, stream.read(&mut response).await.unwrap());
 }
 
+/// Keys are binary safe: a key which is not valid UTF-8 can be set, read back
+/// and listed.
+#[tokio::test]
+async fn key_value_binary_key() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Set a key made of invalid UTF-8
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$2\r\n\xff\xfe\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Get the key back
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$2\r\n\xff\xfe\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+
+    // The key is listed as it was written
+    stream
+        .write_all(b"*2\r\n$4\r\nKEYS\r\n$1\r\n*\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 12];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*1\r\n$2\r\n\xff\xfe\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$6\r\nEXISTS\r\n$2\r\n\xff\xfe\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+}
+
+/// Arguments holding data, such as hash fields, are binary safe as well.
+#[tokio::test]
+async fn binary_argument() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Set a field made of invalid UTF-8
+    stream
+        .write_all(b"*4\r\n$4\r\nHSET\r\n$4\r\nhash\r\n$2\r\n\xc3\x28\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$4\r\nHGET\r\n$4\r\nhash\r\n$2\r\n\xc3\x28\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$7\r\nHGETALL\r\n$4\r\nhash\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 23];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$2\r\n\xc3\x28\r\n$5\r\nworld\r\n", &response);
+}
+
+/// A `Server` handle runs the server in the background until it is shut down.
+#[tokio::test]
+async fn server_handle() {
+    let srv = Server::start().await.unwrap();
+    let addr = srv.addr();
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$4\r\nPING\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nhello\r\n", &response);
+
+    // Idle connections are closed on shutdown
+    srv.shutdown().await;
+    assert_eq!(0, stream.read(&mut response).await.unwrap());
+
+    // The listener is gone as well
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+/// A snapshot copies the live string keys of a database, and is not affected
+/// by the writes that follow.
+#[tokio::test]
+async fn server_snapshot() {
+    let srv = Server::start_with_config(server::Config {
+        enable_debug_command: true,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let mut connection = Connection::new(TcpStream::connect(srv.addr()).await.unwrap());
+
+    // Keep expired keys around, they are left out all the same.
+    command(&mut connection, "DEBUG SET-ACTIVE-EXPIRE 0").await;
+    command(&mut connection, "SET hello world").await;
+    command(&mut connection, "SET ttl value EX 60").await;
+    command(&mut connection, "SET expired value PX 1").await;
+    command(&mut connection, "RPUSH list a").await;
+    time::sleep(Duration::from_millis(20)).await;
+
+    let mut snapshot = srv.snapshot(0);
+    snapshot.sort();
+    assert_eq!(2, snapshot.len());
+    assert_eq!(
+        (Bytes::from("hello"), Bytes::from("world"), None),
+        snapshot[0]
+    );
+    let (key, value, ttl) = &snapshot[1];
+    assert_eq!((&b"ttl"[..], &b"value"[..]), (&key[..], &value[..]));
+    assert!(ttl.unwrap() <= Duration::from_secs(60));
+
+    command(&mut connection, "SET hello other").await;
+    command(&mut connection, "DEL ttl").await;
+    command(&mut connection, "SET new value").await;
+    assert_eq!(2, snapshot.len());
+    assert_eq!(&b"world"[..], &snapshot[0].1[..]);
+
+    let keys: Vec<_> = srv.snapshot(0).into_iter().map(|(key, ..)| key).collect();
+    assert_eq!(2, keys.len());
+    assert!(keys.contains(&Bytes::from("new")));
+    assert!(srv.snapshot(1).is_empty());
+
+    srv.shutdown().await;
+}
+
+/// `RESET` leaves the subscribed state and discards an open transaction.
+#[tokio::test]
+async fn reset_connection() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 34];
+    stream.read_exact(&mut response).await.unwrap();
+
+    stream.write_all(b"*1\r\n$5\r\nRESET\r\n").await.unwrap();
+
+    let mut response = [0; 8];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+RESET\r\n", &response);
+
+    // Regular commands are accepted again
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    // Queued commands are discarded
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+QUEUED\r\n", &response);
+
+    stream.write_all(b"*1\r\n$5\r\nRESET\r\n").await.unwrap();
+
+    let mut response = [0; 8];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+RESET\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
 /// Similar to the basic key-value test, however, this time timeouts will be
 /// tested. This test demonstrates how to test time related behavior.
 ///
@@ -119,6 +339,43 @@ async fn key_value_timeout() {
     assert_eq!(b"$-1\r\n", &response);
 }
 
+/// After `HELLO 3` the server replies with a RESP3 map and sends pub/sub
+/// replies as push frames.
+#[tokio::test]
+async fn hello_resp3_push() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")
+        .await
+        .unwrap();
+
+    let version = env!("CARGO_PKG_VERSION");
+    let expected = format!(
+        "%5\r\n$6\r\nserver\r\n$10\r\nmini-redis\r\n$7\r\nversion\r\n${}\r\n{}\r\n\
+         $5\r\nproto\r\n:3\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n",
+        version.len(),
+        version
+    );
+
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(expected.as_bytes(), &response[..]);
+
+    stream
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 34];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b">3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+}
+
 #[tokio::test]
 async fn pub_sub() {
     let addr = start_server().await;
@@ -329,6 +586,111 @@ async fn manage_subscription() {
         &b"*3\r\n$11\r\nunsubscribe\r\n$3\r\nfoo\r\n:0\r\n"[..],
         &response[..]
     );
+
+    // Unsubscribing again is confirmed without a channel
+    sub.write_all(b"*1\r\n$11\r\nunsubscribe\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 31];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n"[..],
+        &response[..]
+    );
+}
+
+/// Each channel of a `SUBSCRIBE` is confirmed with the running count of
+/// subscriptions, including the channels of earlier commands.
+#[tokio::test]
+async fn subscribe_counts_subscriptions() {
+    let addr = start_server().await;
+
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*3\r\n$9\r\nSUBSCRIBE\r\n$1\r\na\r\n$1\r\nb\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 60];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n\
+           *3\r\n$9\r\nsubscribe\r\n$1\r\nb\r\n:2\r\n"[..],
+        &response[..]
+    );
+
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 30];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:3\r\n"[..],
+        &response[..]
+    );
+}
+
+/// `UNSUBSCRIBE` without channels confirms every subscribed channel, in any
+/// order, with a decreasing count.
+#[tokio::test]
+async fn unsubscribe_from_all_channels() {
+    let addr = start_server().await;
+
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*4\r\n$9\r\nSUBSCRIBE\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 90];
+    sub.read_exact(&mut response).await.unwrap();
+
+    sub.write_all(b"*1\r\n$11\r\nUNSUBSCRIBE\r\n")
+        .await
+        .unwrap();
+
+    let mut channels = vec![];
+    for count in [b'2', b'1', b'0'] {
+        let mut response = [0; 33];
+        sub.read_exact(&mut response).await.unwrap();
+        assert_eq!(&b"*3\r\n$11\r\nunsubscribe\r\n$1\r\n"[..], &response[..26]);
+        assert_eq!(
+            &[b'\r', b'\n', b':', count, b'\r', b'\n'][..],
+            &response[27..]
+        );
+        channels.push(response[26]);
+    }
+
+    channels.sort_unstable();
+    assert_eq!(b"abc", &channels[..]);
+}
+
+/// Unsubscribing on a connection that is not subscribed is confirmed with a
+/// count of `0`, and the connection keeps serving commands.
+#[tokio::test]
+async fn unsubscribe_when_not_subscribed() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*1\r\n$11\r\nUNSUBSCRIBE\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n").await;
+
+    stream
+        .write_all(b"*2\r\n$12\r\nPUNSUBSCRIBE\r\n$6\r\nnews.*\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"*3\r\n$12\r\npunsubscribe\r\n$6\r\nnews.*\r\n:0\r\n",
+    )
+    .await;
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
 }
 
 // In this case we test that server Responds with an Error message if a client
@@ -381,27 +743,1478 @@ async fn send_error_get_set_after_subscribe() {
         .await
         .unwrap();
 
-    let mut response = [0; 28];
+    assert_reply(
+        &mut stream,
+        b"-ERR Can't execute 'set' in subscribe context\r\n",
+    )
+    .await;
 
-    stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-ERR unknown command \'set\'\r\n", &response);
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    assert_reply(
+        &mut stream,
+        b"-ERR Can't execute 'get' in subscribe context\r\n",
+    )
+    .await;
+
+    // Unknown commands are still reported as such
+    stream.write_all(b"*1\r\n$3\r\nFOO\r\n").await.unwrap();
+    assert_reply(&mut stream, b"-ERR unknown command 'foo'\r\n").await;
+
+    // PING is allowed, and replied with an array
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"*2\r\n$4\r\npong\r\n$0\r\n\r\n").await;
 
     stream
+        .write_all(b"*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"*2\r\n$4\r\npong\r\n$2\r\nhi\r\n").await;
+
+    // The refused SET was not applied
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    other
         .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
+    assert_reply(&mut other, b"$-1\r\n").await;
+}
 
-    let mut response = [0; 28];
+/// `QUIT` is confirmed and the connection closed, whatever state it is in.
+#[tokio::test]
+async fn quit_closes_connection() {
+    let addr = start_server().await;
 
-    stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-ERR unknown command \'get\'\r\n", &response);
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n$4\r\nQUIT\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    // The open transaction is discarded
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+QUEUED\r\n").await;
+    stream.write_all(b"*1\r\n$4\r\nQUIT\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    // Subscribed connections can quit too
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n",
+    )
+    .await;
+    stream.write_all(b"*1\r\n$4\r\nQUIT\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$-1\r\n").await;
 }
 
-async fn start_server() -> SocketAddr {
-    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-    let addr = listener.local_addr().unwrap();
+/// RESP3 peers may send any command while subscribed, and keep receiving
+/// messages.
+#[tokio::test]
+async fn resp3_commands_while_subscribed() {
+    let addr = start_server().await;
 
-    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")
+        .await
+        .unwrap();
+
+    let version = env!("CARGO_PKG_VERSION");
+    let expected = format!(
+        "%5\r\n$6\r\nserver\r\n$10\r\nmini-redis\r\n$7\r\nversion\r\n${}\r\n{}\r\n\
+         $5\r\nproto\r\n:3\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n",
+        version.len(),
+        version
+    );
+    assert_reply(&mut stream, expected.as_bytes()).await;
+
+    stream
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b">3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n",
+    )
+    .await;
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    // Messages are still delivered
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut publisher, b":1\r\n").await;
+    assert_reply(
+        &mut stream,
+        b">3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n",
+    )
+    .await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$5\r\nworld\r\n").await;
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
+
+    // The subscription is still there
+    stream
+        .write_all(b"*1\r\n$11\r\nUNSUBSCRIBE\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b">3\r\n$11\r\nunsubscribe\r\n$5\r\nhello\r\n:0\r\n",
+    )
+    .await;
+}
+
+/// PSUBSCRIBE delivers messages published to every channel matching a glob
+/// pattern, along with the pattern and the channel name.
+#[tokio::test]
+async fn psubscribe_patterns() {
+    let addr = start_server().await;
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+    subscriber
+        .write_all(b"*3\r\n$10\r\nPSUBSCRIBE\r\n$6\r\nnews.*\r\n$8\r\nh[ae]ll?\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut subscriber,
+        b"*3\r\n$10\r\npsubscribe\r\n$6\r\nnews.*\r\n:1\r\n",
+    )
+    .await;
+    assert_reply(
+        &mut subscriber,
+        b"*3\r\n$10\r\npsubscribe\r\n$8\r\nh[ae]ll?\r\n:2\r\n",
+    )
+    .await;
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$11\r\nnews.sports\r\n$2\r\nhi\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut publisher, b":1\r\n").await;
+    assert_reply(
+        &mut subscriber,
+        b"*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$11\r\nnews.sports\r\n$2\r\nhi\r\n",
+    )
+    .await;
+
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhallo\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut publisher, b":1\r\n").await;
+    assert_reply(
+        &mut subscriber,
+        b"*4\r\n$8\r\npmessage\r\n$8\r\nh[ae]ll?\r\n$5\r\nhallo\r\n$5\r\nworld\r\n",
+    )
+    .await;
+
+    // No pattern matches
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhillo\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut publisher, b":0\r\n").await;
+
+    subscriber
+        .write_all(b"*2\r\n$12\r\nPUNSUBSCRIBE\r\n$6\r\nnews.*\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut subscriber,
+        b"*3\r\n$12\r\npunsubscribe\r\n$6\r\nnews.*\r\n:1\r\n",
+    )
+    .await;
+
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$11\r\nnews.sports\r\n$2\r\nhi\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut publisher, b":0\r\n").await;
+}
+
+/// SET with NX only writes missing keys, SET with XX only writes existing
+/// keys. A failed condition replies with null and leaves the key untouched.
+#[tokio::test]
+async fn set_nx_xx() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*4\r\n$3\r\nSET\r\n$5\r\nhello\r\n$1\r\n1\r\n$2\r\nXX\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$-1\r\n").await;
+
+    stream
+        .write_all(b"*4\r\n$3\r\nSET\r\n$5\r\nhello\r\n$1\r\n1\r\n$2\r\nNX\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*4\r\n$3\r\nSET\r\n$5\r\nhello\r\n$1\r\n2\r\n$2\r\nnx\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$-1\r\n").await;
+
+    stream
+        .write_all(
+            b"*6\r\n$3\r\nSET\r\n$5\r\nhello\r\n$1\r\n3\r\n$2\r\nXX\r\n$2\r\nEX\r\n$2\r\n10\r\n",
+        )
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$1\r\n3\r\n").await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b":10\r\n").await;
+}
+
+/// Commands sent after MULTI are queued and applied together by EXEC, which
+/// replies with an array holding each reply.
+#[tokio::test]
+async fn multi_exec() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+QUEUED\r\n").await;
+
+    stream
+        .write_all(b"*2\r\n$4\r\nINCR\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+QUEUED\r\n").await;
+
+    stream
+        .write_all(b"*4\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+QUEUED\r\n").await;
+
+    stream
+        .write_all(b"*4\r\n$6\r\nLRANGE\r\n$4\r\nlist\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+QUEUED\r\n").await;
+
+    // Nothing has been applied yet
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    other
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut other, b"$-1\r\n").await;
+
+    stream.write_all(b"*1\r\n$4\r\nEXEC\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"*4\r\n+OK\r\n:2\r\n:2\r\n*2\r\n$1\r\na\r\n$1\r\nb\r\n",
+    )
+    .await;
+
+    // The transaction is closed
+    stream.write_all(b"*1\r\n$4\r\nEXEC\r\n").await.unwrap();
+    assert_reply(&mut stream, b"-ERR EXEC without MULTI\r\n").await;
+}
+
+/// A command that fails to queue aborts EXEC, and DISCARD leaves the
+/// connection usable.
+#[tokio::test]
+async fn multi_queue_error() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    // GET without a key cannot be parsed
+    stream.write_all(b"*1\r\n$3\r\nGET\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR wrong number of arguments for 'get' command\r\n",
+    )
+    .await;
+
+    stream.write_all(b"*1\r\n$4\r\nEXEC\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-EXECABORT Transaction discarded because of previous errors.\r\n",
+    )
+    .await;
+
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream.write_all(b"*1\r\n$3\r\nFOO\r\n").await.unwrap();
+    assert_reply(&mut stream, b"-ERR unknown command 'foo'\r\n").await;
+
+    stream.write_all(b"*1\r\n$7\r\nDISCARD\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
+}
+
+/// Blocking pops cannot be queued in a transaction, as they would block EXEC.
+#[tokio::test]
+async fn multi_rejects_blocking_pop() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"MULTI\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream.write_all(b"BLPOP list 0\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR 'blpop' is not allowed in a transaction\r\n",
+    )
+    .await;
+
+    stream.write_all(b"EXEC\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-EXECABORT Transaction discarded because of previous errors.\r\n",
+    )
+    .await;
+}
+
+/// Commands may be sent inline, as a line of space separated arguments, the
+/// way they are typed into `telnet` or `nc`.
+#[tokio::test]
+async fn inline_commands() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"PING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
+
+    // Lines may end with a bare newline and hold extra whitespace.
+    stream.write_all(b"SET  foo   bar\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    // Blank lines are skipped, and inline commands can be mixed with arrays.
+    stream
+        .write_all(b"\r\n*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$3\r\nbar\r\n").await;
+
+    // A command split across writes is parsed once the line is complete.
+    stream.write_all(b"GET ").await.unwrap();
+    time::sleep(Duration::from_millis(10)).await;
+    stream.write_all(b"foo\r\n").await.unwrap();
+    assert_reply(&mut stream, b"$3\r\nbar\r\n").await;
+}
+
+/// A bulk string header announcing more than the maximum frame size is
+/// rejected before the string is received, and the connection is closed.
+#[tokio::test]
+async fn oversized_bulk_is_rejected() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$1000000000\r\n")
+        .await
+        .unwrap();
+
+    assert_reply(&mut stream, b"-ERR Protocol error: invalid bulk length\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+}
+
+/// The maximum frame size can be lowered, and applies to bulk strings and to
+/// frames that do not announce their length.
+#[tokio::test]
+async fn max_frame_size_is_configurable() {
+    let addr = start_server_with_config(server::Config {
+        max_frame_size: 1024,
+        ..Default::default()
+    })
+    .await;
+
+    // Frames under the limit are accepted.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$2048\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"-ERR Protocol error: invalid bulk length\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    // Only the bulk strings are limited, however the request is split across
+    // reads.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$1000\r\n")
+        .await
+        .unwrap();
+    stream.write_all(&[b'x'; 500]).await.unwrap();
+    time::sleep(Duration::from_millis(10)).await;
+    stream.write_all(&[b'x'; 500]).await.unwrap();
+    stream.write_all(b"\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    // Arrays may hold more entries than the maximum size of a bulk string.
+    let mut request = b"*2049\r\n$3\r\nDEL\r\n".to_vec();
+    for _ in 0..2048 {
+        request.extend_from_slice(b"$1\r\na\r\n");
+    }
+    stream.write_all(&request).await.unwrap();
+    assert_reply(&mut stream, b":0\r\n").await;
+
+    // An inline command is only known to be complete once its line ends.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(&[b'a'; 2048]).await.unwrap();
+    assert_reply(&mut stream, b"-ERR protocol error; frame too large\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+}
+
+/// A request announcing more arguments than the maximum is rejected before the
+/// arguments are received, and the connection is closed.
+#[tokio::test]
+async fn oversized_multibulk_is_rejected() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*99999999999999\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR Protocol error: invalid multibulk length\r\n",
+    )
+    .await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    // The maximum can be lowered.
+    let addr = start_server_with_config(server::Config {
+        max_array_len: 3,
+        ..Default::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream.write_all(b"*4\r\n$4\r\nMSET\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR Protocol error: invalid multibulk length\r\n",
+    )
+    .await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    // Inline commands are limited too.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"SET hello world\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream.write_all(b"MSET a 1 b 2\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR Protocol error: invalid multibulk length\r\n",
+    )
+    .await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+}
+
+/// `proto-max-bulk-len` reports the maximum size of bulk strings, and can be
+/// changed to 1mb or more.
+#[tokio::test]
+async fn proto_max_bulk_len_config() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let frame = command(&mut connection, "CONFIG GET proto-max-bulk-len").await;
+    assert_eq!(frame.to_string(), "proto-max-bulk-len 536870912");
+
+    let frame = command(&mut connection, "CONFIG SET proto-max-bulk-len 1mb").await;
+    assert_eq!(frame, "OK");
+    let frame = command(&mut connection, "CONFIG GET proto-max-bulk-len").await;
+    assert_eq!(frame.to_string(), "proto-max-bulk-len 1048576");
+
+    // As in Redis, the limit cannot be lowered under 1mb.
+    for value in ["0", "1kb", "1048575"] {
+        let cmd = format!("CONFIG SET proto-max-bulk-len {}", value);
+        match command(&mut connection, &cmd).await {
+            Frame::Error(msg) => assert!(msg.starts_with("ERR Invalid argument"), "{}", msg),
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    }
+}
+
+/// A new `proto-max-bulk-len` applies to connections already open, from the
+/// request after the one they were waiting for. Closing a connection for an
+/// oversized bulk string leaves the others open.
+#[tokio::test]
+async fn proto_max_bulk_len_applies_at_runtime() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
+
+    let frame = command(&mut connection, "CONFIG SET proto-max-bulk-len 1mb").await;
+    assert_eq!(frame, "OK");
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nbig\r\n$2000000\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"-ERR Protocol error: invalid bulk length\r\n").await;
+    assert_eq!(0, stream.read(&mut [0; 1]).await.unwrap());
+
+    assert_eq!(command(&mut connection, "SET small 1").await, "OK");
+}
+
+/// Subscribed connections check their commands against the current
+/// `proto-max-bulk-len` too.
+#[tokio::test]
+async fn proto_max_bulk_len_applies_while_subscribed() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut sub, b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n").await;
+
+    let frame = command(&mut connection, "CONFIG SET proto-max-bulk-len 1mb").await;
+    assert_eq!(frame, "OK");
+
+    sub.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut sub, b"*2\r\n$4\r\npong\r\n$0\r\n\r\n").await;
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$2000000\r\n")
+        .await
+        .unwrap();
+    assert_eq!(0, sub.read(&mut [0; 1]).await.unwrap());
+}
+
+/// A value too large for `maxmemory` is refused with an error, and the
+/// connection that sent it is left open.
+#[tokio::test]
+async fn oversized_value_is_out_of_memory() {
+    let addr = start_server_with_config(server::Config {
+        maxmemory: Some(100),
+        ..Default::default()
+    })
+    .await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nbig\r\n$200\r\n")
+        .await
+        .unwrap();
+    stream.write_all(&[b'x'; 200]).await.unwrap();
+    stream.write_all(b"\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-OOM command not allowed when used memory > 'maxmemory'\r\n",
+    )
+    .await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nbig\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$-1\r\n").await;
+}
+
+/// Connections sending no command within the timeout are closed, unless they
+/// are subscribed to channels.
+#[tokio::test]
+async fn idle_connections_are_closed() {
+    let addr = start_server_with_config(server::Config {
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    })
+    .await;
+
+    let mut idle = TcpStream::connect(addr).await.unwrap();
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+    subscriber
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut subscriber,
+        b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n",
+    )
+    .await;
+
+    let closed = time::timeout(Duration::from_secs(5), idle.read(&mut [0; 1])).await;
+    assert_eq!(0, closed.unwrap().unwrap());
+
+    // The subscriber was idle as long, and still receives messages.
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut publisher, b":1\r\n").await;
+    assert_reply(
+        &mut subscriber,
+        b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n",
+    )
+    .await;
+}
+
+/// MGET takes the keys without their number, as in Redis.
+#[tokio::test]
+async fn mget() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*4\r\n$4\r\nMGET\r\n$5\r\nhello\r\n$3\r\nfoo\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"*3\r\n$5\r\nworld\r\n$-1\r\n$5\r\nworld\r\n").await;
+}
+
+/// Unknown COMMAND subcommands are reported as unknown commands.
+#[tokio::test]
+async fn command_unknown_subcommand() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$7\r\nCOMMAND\r\n$4\r\nINFO\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"-ERR unknown command 'command info'\r\n").await;
+
+    // The connection is still usable
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+PONG\r\n").await;
+}
+
+/// EXPIRE reports conflicting conditions without changing the key.
+#[tokio::test]
+async fn expire_conflicting_conditions() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*5\r\n$6\r\nEXPIRE\r\n$5\r\nhello\r\n$2\r\n10\r\n$2\r\nNX\r\n$2\r\nGT\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR NX, XX, GT and LT options at the same time are not compatible\r\n",
+    )
+    .await;
+
+    // Repeating a condition is allowed.
+    stream
+        .write_all(b"*5\r\n$6\r\nEXPIRE\r\n$5\r\nhello\r\n$2\r\n10\r\n$2\r\nNX\r\n$2\r\nnx\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b":1\r\n").await;
+}
+
+/// SET KEEPTTL and GET can be combined, along with NX and XX.
+#[tokio::test]
+async fn set_keepttl_get() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nEX\r\n$2\r\n60\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(
+            b"*5\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nother\r\n$7\r\nKEEPTTL\r\n$3\r\nGET\r\n",
+        )
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$5\r\nworld\r\n").await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b":60\r\n").await;
+
+    // When NX does not hold, the previous value is still returned.
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nthird\r\n$2\r\nNX\r\n$3\r\nGET\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$5\r\nother\r\n").await;
+}
+
+/// The metrics endpoint serves the counters of the server in the Prometheus
+/// text format.
+#[tokio::test]
+async fn metrics_endpoint() {
+    // Reserve a free port for the endpoint.
+    let metrics_addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let addr = start_server_with_config(server::Config {
+        metrics_addr: Some(metrics_addr),
+        ..Default::default()
+    })
+    .await;
+
+    // The endpoint is listening once the server replies.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    let mut http = TcpStream::connect(metrics_addr).await.unwrap();
+    http.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    http.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("\nmini_redis_connected_clients 1\n"));
+    assert!(response.contains("\nmini_redis_commands_total{command=\"set\"} 1\n"));
+    assert!(response.contains("\nmini_redis_keys{db=\"0\"} 1\n"));
+    assert!(response.contains("\nmini_redis_expired_keys_total 0\n"));
+    assert!(response.contains("\nmini_redis_pending_expirations{db=\"0\"} 0\n"));
+
+    let mut http = TcpStream::connect(metrics_addr).await.unwrap();
+    http.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    http.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+/// Commands sent with too few or too many arguments are refused, and the
+/// connection stays usable.
+#[tokio::test]
+async fn wrong_number_of_arguments() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nGET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR wrong number of arguments for 'get' command\r\n",
+    )
+    .await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nSET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR wrong number of arguments for 'set' command\r\n",
+    )
+    .await;
+
+    stream.write_all(b"*1\r\n$3\r\nDEL\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR wrong number of arguments for 'del' command\r\n",
+    )
+    .await;
+
+    // The name of the command is reported as sent, in lower case
+    stream.write_all(b"*1\r\n$6\r\nLRange\r\n").await.unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR wrong number of arguments for 'lrange' command\r\n",
+    )
+    .await;
+
+    // Variadic commands accept any number of arguments above the minimum
+    stream
+        .write_all(b"*3\r\n$3\r\nDEL\r\n$1\r\na\r\n$1\r\nb\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b":0\r\n").await;
+}
+
+/// EXPIREAT accepts any timestamp: negative ones are in the past, and ones too
+/// far in the future are refused.
+#[tokio::test]
+async fn expireat_out_of_range() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+
+    stream
+        .write_all(b"*3\r\n$8\r\nEXPIREAT\r\n$5\r\nhello\r\n$19\r\n9223372036854775807\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR invalid expire time in 'expireat' command\r\n",
+    )
+    .await;
+
+    stream
+        .write_all(b"*3\r\n$8\r\nEXPIREAT\r\n$5\r\nhello\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b":1\r\n").await;
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$-1\r\n").await;
+}
+
+/// EXPIRE refuses timeouts too far in the future, with or without a
+/// condition, and leaves the key usable.
+#[tokio::test]
+async fn expire_out_of_range() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(command(&mut connection, "SET hello world").await, "OK");
+
+    for cmd in [
+        "EXPIRE hello 18446744073709551615",
+        "EXPIRE hello 18446744073709551615 NX",
+        "EXPIRE hello 18446744073709551615 GT",
+    ] {
+        match command(&mut connection, cmd).await {
+            Frame::Error(msg) => assert_eq!("ERR invalid expire time in 'expire' command", msg),
+            frame => panic!("unexpected frame for {}: {:?}", cmd, frame),
+        }
+    }
+
+    assert_eq!(command(&mut connection, "GET hello").await, "world");
+    assert!(matches!(
+        command(&mut connection, "TTL hello").await,
+        Frame::Integer(-1)
+    ));
+}
+
+/// SET refuses expirations that are not positive or too far in the future,
+/// without writing the value.
+#[tokio::test]
+async fn set_expire_out_of_range() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for cmd in [
+        "SET hello world EX 0",
+        "SET hello world PX 0",
+        "SET hello world EX -1",
+        "SET hello world EX 9223372036854775807",
+    ] {
+        match command(&mut connection, cmd).await {
+            Frame::Error(msg) => assert_eq!("ERR invalid expire time in 'set' command", msg),
+            frame => panic!("unexpected frame for {}: {:?}", cmd, frame),
+        }
+    }
+
+    // The connection is still usable, and nothing was written.
+    assert!(matches!(
+        command(&mut connection, "GET hello").await,
+        Frame::Null
+    ));
+    assert_eq!(
+        command(&mut connection, "SET hello world EX 10").await,
+        "OK"
+    );
+}
+
+/// GETEX refuses expirations too far in the future, whether given as a time to
+/// live or as a timestamp, and leaves the key as it was.
+#[tokio::test]
+async fn getex_out_of_range() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(command(&mut connection, "SET hello world").await, "OK");
+
+    for cmd in [
+        "GETEX hello EX 18446744073709551615",
+        "GETEX hello EXAT 18446744073709551615",
+    ] {
+        match command(&mut connection, cmd).await {
+            Frame::Error(msg) => assert_eq!("ERR invalid expire time in 'getex' command", msg),
+            frame => panic!("unexpected frame for {}: {:?}", cmd, frame),
+        }
+    }
+
+    assert_eq!(command(&mut connection, "GETEX hello").await, "world");
+    assert!(matches!(
+        command(&mut connection, "TTL hello").await,
+        Frame::Integer(-1)
+    ));
+}
+
+/// OBJECT ENCODING replies with a bulk string, as Redis does.
+#[tokio::test]
+async fn object_encoding_reply() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"SET hello 12\r\n").await.unwrap();
+    assert_reply(&mut stream, b"+OK\r\n").await;
+    stream
+        .write_all(b"OBJECT ENCODING hello\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$3\r\nint\r\n").await;
+}
+
+/// PTTL saturates when the remaining time to live does not fit in a reply.
+#[tokio::test]
+async fn ttl_out_of_range() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(command(&mut connection, "SET hello world").await, "OK");
+    let frame = command(&mut connection, "GETEX hello PXAT 18446744073709551615").await;
+    assert_eq!(frame, "world");
+
+    assert!(matches!(
+        command(&mut connection, "PTTL hello").await,
+        Frame::Integer(i64::MAX)
+    ));
+    match command(&mut connection, "TTL hello").await {
+        Frame::Integer(ttl) => assert!(ttl > 18_000_000_000_000_000, "{}", ttl),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// Commands operating on a type of value, by type, along with the command
+/// creating a key of that type. `{}` stands for the key.
+const TYPED_COMMANDS: &[(&str, &str, &[&str])] = &[
+    (
+        "string",
+        "SET {} v",
+        &[
+            "GET {}",
+            "GETSET {} v",
+            "GETDEL {}",
+            "GETEX {} PERSIST",
+            "SET {} v GET",
+            "APPEND {} v",
+            "STRLEN {}",
+            "GETRANGE {} 0 1",
+            "SETRANGE {} 0 v",
+            "INCR {}",
+            "INCRBY {} 1",
+            "INCRBYFLOAT {} 1.5",
+            "DECR {}",
+            "DECRBY {} 1",
+            "GETBIT {} 0",
+            "SETBIT {} 0 1",
+            "BITCOUNT {}",
+            "BITPOS {} 1",
+        ],
+    ),
+    (
+        "list",
+        "RPUSH {} a",
+        &[
+            "LPUSH {} a",
+            "RPUSH {} a",
+            "LPUSHX {} a",
+            "RPUSHX {} a",
+            "LPOP {}",
+            "RPOP {}",
+            "LRANGE {} 0 -1",
+            "LLEN {}",
+            "LSET {} 0 a",
+            "LINSERT {} BEFORE a b",
+            "LPOS {} a",
+            "LMPOP 1 {} LEFT",
+            "BLPOP {} 0.01",
+            "BRPOP {} 0.01",
+        ],
+    ),
+    (
+        "hash",
+        "HSET {} f v",
+        &[
+            "HSET {} f v",
+            "HGET {} f",
+            "HDEL {} f",
+            "HGETALL {}",
+            "HINCRBY {} f 1",
+            "HINCRBYFLOAT {} f 1.5",
+            "HEXISTS {} f",
+            "HKEYS {}",
+            "HVALS {}",
+            "HLEN {}",
+            "HRANDFIELD {}",
+            "HRANDFIELD {} -2 WITHVALUES",
+        ],
+    ),
+    (
+        "set",
+        "SADD {} a",
+        &[
+            "SADD {} a",
+            "SREM {} a",
+            "SMEMBERS {}",
+            "SISMEMBER {} a",
+            "SCARD {}",
+            "SMOVE {} {}:dst a",
+            "SINTERSTORE {}:dst {}",
+            "SINTERCARD 1 {}",
+            "SUNIONSTORE {}:dst {}",
+            "SDIFFSTORE {}:dst {}",
+            "SRANDMEMBER {}",
+            "SRANDMEMBER {} 2",
+        ],
+    ),
+    (
+        "zset",
+        "ZADD {} 1 a",
+        &["ZADD {} 1 a", "ZSCORE {} a", "ZRANK {} a", "ZRANGE {} 0 -1"],
+    ),
+];
+
+/// Every command operating on a type of value rejects keys holding another
+/// type with the same error, and leaves them untouched.
+#[tokio::test]
+async fn wrong_type_matrix() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for (kind, create, _) in TYPED_COMMANDS {
+        command(&mut connection, &create.replace("{}", kind)).await;
+    }
+
+    for (kind, _, _) in TYPED_COMMANDS {
+        for (other, _, commands) in TYPED_COMMANDS {
+            if kind == other {
+                continue;
+            }
+
+            for cmd in *commands {
+                let cmd = cmd.replace("{}", kind);
+                match command(&mut connection, &cmd).await {
+                    Frame::Error(err) => assert_eq!(
+                        "WRONGTYPE Operation against a key holding the wrong kind of value", err,
+                        "{} on a {}",
+                        cmd, kind
+                    ),
+                    frame => panic!("{} on a {} replied {:?}", cmd, kind, frame),
+                }
+            }
+        }
+
+        let reply = command(&mut connection, &format!("TYPE {}", kind)).await;
+        assert_eq!(kind.to_string(), reply.to_string());
+    }
+}
+
+/// A key that expired is absent for every command, whatever the type of its
+/// value, even before the key is purged.
+#[tokio::test]
+async fn wrong_type_expired_key() {
+    let addr = start_server_with_config(server::Config {
+        enable_debug_command: true,
+        ..Default::default()
+    })
+    .await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // Keep expired keys around.
+    command(&mut connection, "DEBUG SET-ACTIVE-EXPIRE 0").await;
+
+    let expire_at =
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_millis(50);
+    let mut checks = vec![];
+
+    for (kind, create, _) in TYPED_COMMANDS {
+        for (other, _, commands) in TYPED_COMMANDS {
+            if kind == other {
+                continue;
+            }
+
+            for cmd in *commands {
+                let key = format!("{}:{}", kind, checks.len());
+                command(&mut connection, &create.replace("{}", &key)).await;
+                let pexpireat = format!("PEXPIREAT {} {}", key, expire_at.as_millis());
+                assert_eq!("1", command(&mut connection, &pexpireat).await.to_string());
+                checks.push((kind, cmd.replace("{}", &key)));
+            }
+        }
+    }
+
+    time::sleep(Duration::from_millis(100)).await;
+
+    for (kind, cmd) in checks {
+        // Commands may still fail on a missing key, as LSET does.
+        if let Frame::Error(err) = command(&mut connection, &cmd).await {
+            assert!(
+                !err.starts_with("WRONGTYPE"),
+                "{} on an expired {} replied {}",
+                cmd,
+                kind,
+                err
+            );
+        }
+    }
+}
+
+/// SINTERCARD rejects a number of keys that does not match its arguments.
+#[tokio::test]
+async fn sintercard_arguments() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for (cmd, err) in [
+        ("SINTERCARD 0 a", "ERR numkeys should be greater than 0"),
+        (
+            "SINTERCARD 3 a b",
+            "ERR Number of keys can't be greater than number of args",
+        ),
+        (
+            "SINTERCARD 1 a COUNT 1",
+            "ERR `SINTERCARD` only supports the LIMIT option",
+        ),
+    ] {
+        match command(&mut connection, cmd).await {
+            Frame::Error(msg) => assert_eq!(err, msg),
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    }
+}
+
+/// BITPOS only looks for bits of `0` or `1`, and both bit commands only take
+/// the BYTE and BIT units.
+#[tokio::test]
+async fn bit_arguments() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for (cmd, err) in [
+        ("BITPOS a 2", "ERR The bit argument must be 1 or 0."),
+        (
+            "BITPOS a 1 0 -1 WORD",
+            "ERR `BITPOS` only supports the BYTE and BIT units",
+        ),
+        (
+            "BITCOUNT a 0 -1 WORD",
+            "ERR `BITCOUNT` only supports the BYTE and BIT units",
+        ),
+    ] {
+        match command(&mut connection, cmd).await {
+            Frame::Error(msg) => assert_eq!(err, msg),
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    }
+}
+
+/// Commands outside the configured allowlist are refused, inside or outside
+/// transactions, until the allowlist is changed with CONFIG SET.
+#[tokio::test]
+async fn allowed_commands() {
+    let names = ["GET", "config", "multi", "exec"];
+    let addr = start_server_with_config(server::Config {
+        allowed_commands: Some(names.iter().map(|name| name.to_string()).collect()),
+        ..server::Config::default()
+    })
+    .await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let noperm = "NOPERM this user has no permissions to run the 'set' command";
+    match command(&mut connection, "SET a 1").await {
+        Frame::Error(msg) => assert_eq!(noperm, msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+    assert!(matches!(
+        command(&mut connection, "GET a").await,
+        Frame::Null
+    ));
+
+    // The transaction is aborted, as for other errors while queueing.
+    assert_eq!(command(&mut connection, "MULTI").await, "OK");
+    match command(&mut connection, "SET a 1").await {
+        Frame::Error(msg) => assert_eq!(noperm, msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+    match command(&mut connection, "EXEC").await {
+        Frame::Error(msg) => assert!(msg.starts_with("EXECABORT"), "{}", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    let frame = command(&mut connection, "CONFIG GET allowed-commands").await;
+    assert_eq!(frame.to_string(), "allowed-commands config,exec,get,multi");
+
+    // Unknown commands are reported as such rather than refused.
+    match command(&mut connection, "FOO").await {
+        Frame::Error(msg) => assert!(msg.starts_with("ERR unknown command"), "{}", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    match command(&mut connection, "CONFIG SET allowed-commands get,foo").await {
+        Frame::Error(msg) => assert!(msg.starts_with("ERR Invalid argument"), "{}", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    let frame = command(&mut connection, "CONFIG SET allowed-commands *").await;
+    assert_eq!(frame, "OK");
+    assert_eq!(command(&mut connection, "SET a 1").await, "OK");
+
+    let frame = command(&mut connection, "CONFIG SET allowed-commands ping").await;
+    assert_eq!(frame, "OK");
+    assert_eq!(command(&mut connection, "PING").await, "PONG");
+    match command(&mut connection, "GET a").await {
+        Frame::Error(msg) => assert!(msg.starts_with("NOPERM"), "{}", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// CLIENT REPLY OFF and SKIP drop the replies to commands, errors included,
+/// until CLIENT REPLY ON is replied.
+#[tokio::test]
+async fn client_reply() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"CLIENT REPLY OFF\r\nSET a 1\r\nFOO\r\nCLIENT REPLY ON\r\nGET a\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+OK\r\n$1\r\n1\r\n").await;
+
+    stream
+        .write_all(b"CLIENT REPLY SKIP\r\nINCR a\r\nGET a\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"$1\r\n2\r\n").await;
+
+    // RESET turns replies back on.
+    stream
+        .write_all(b"CLIENT REPLY OFF\r\nINCR a\r\nRESET\r\nGET a\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b"+RESET\r\n$1\r\n3\r\n").await;
+
+    stream
+        .write_all(b"CLIENT REPLY MAYBE\r\nMULTI\r\nCLIENT REPLY OFF\r\n")
+        .await
+        .unwrap();
+    assert_reply(
+        &mut stream,
+        b"-ERR `CLIENT REPLY` only supports the ON, OFF and SKIP modes\r\n+OK\r\n\
+          -ERR 'client' is not allowed in a transaction\r\n",
+    )
+    .await;
+}
+
+/// ZSCORE replies a double to RESP3 peers and a bulk string to RESP2 peers.
+#[tokio::test]
+async fn zscore_double() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"ZADD z 1.5 a\r\nZSCORE z a\r\n")
+        .await
+        .unwrap();
+    assert_reply(&mut stream, b":1\r\n$3\r\n1.5\r\n").await;
+
+    let mut connection = Connection::new(stream);
+    assert!(matches!(
+        command(&mut connection, "HELLO 3").await,
+        Frame::Map(_)
+    ));
+    assert!(matches!(
+        command(&mut connection, "ZSCORE z a").await,
+        Frame::Double(score) if score == 1.5
+    ));
+}
+
+/// Accepted sockets get `TCP_NODELAY` by default, and keepalive probes once
+/// configured.
+#[tokio::test]
+async fn socket_options() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let configs = [
+        (server::Config::default(), true, false),
+        (
+            server::Config {
+                tcp_nodelay: false,
+                tcp_keepalive: Some(Duration::from_secs(60)),
+                ..Default::default()
+            },
+            false,
+            true,
+        ),
+    ];
+
+    for (config, nodelay, keepalive) in configs {
+        let (_peer, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        let (socket, _) = accepted.unwrap();
+        server::set_socket_options(&socket, &config).unwrap();
+
+        assert_eq!(nodelay, socket.nodelay().unwrap());
+        assert_eq!(
+            keepalive,
+            socket2::SockRef::from(&socket).keepalive().unwrap()
+        );
+    }
+
+    // The server applies them to the connections it accepts.
+    let addr = start_server_with_config(server::Config {
+        tcp_keepalive: Some(Duration::from_secs(60)),
+        ..Default::default()
+    })
+    .await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    match command(&mut connection, "PING").await {
+        Frame::Simple(pong) => assert_eq!("PONG", pong),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// Send `cmd`, a line of space separated arguments, and return the reply.
+async fn command(connection: &mut Connection, cmd: &str) -> Frame {
+    let frame = Frame::Array(
+        cmd.split(' ')
+            .map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes())))
+            .collect(),
+    );
+
+    connection.write_frame(&frame).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap()
+}
+
+/// Read a reply of the same length as `expected` and compare them.
+async fn assert_reply(stream: &mut TcpStream, expected: &[u8]) {
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        std::str::from_utf8(expected).unwrap(),
+        std::str::from_utf8(&response).unwrap()
+    );
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
+
+async fn start_server_with_config(config: server::Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
 
     addr
 }